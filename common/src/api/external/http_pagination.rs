@@ -35,12 +35,18 @@
 //! "name" in ascending order, "id" in ascending order, or either of those (plus
 //! name in descending order) without any new boilerplate for that type.
 //!
+//! Resources that have labels are paginated in the same ways, except that the
+//! scan parameters also accept a `label_selector` that limits the scan to
+//! resources having the given labels.  Since it's part of the scan parameters,
+//! the selector is preserved in the page token along with the sort mode.
+//!
 //! There may be resources that can't be paginated using one of the above three
 //! ways, and we can define new ways to paginate them.  As you will notice below,
 //! there's a fair bit of boilerplate for each way of paginating (rather than for
 //! each resource paginated that way).  Where possible, we should share code.
 
 use crate::api::external::DataPageParams;
+use crate::api::external::LabelSelector;
 use crate::api::external::Name;
 use crate::api::external::ObjectIdentity;
 use crate::api::external::PaginationOrder;
@@ -145,10 +151,11 @@ pub fn marker_for_id<S, T: ObjectIdentity>(_: &S, t: &T) -> Uuid {
 /// Marker function that extracts the "name" or "id" from an object, depending
 /// on the scan in use
 ///
-/// This is intended for use with [`ScanByNameOrId::results_page`] with objects
-/// that impl [`ObjectIdentity`].
-pub fn marker_for_name_or_id<T: ObjectIdentity>(
-    scan: &ScanByNameOrId,
+/// This is intended for use with [`ScanByNameOrId::results_page`] (or
+/// [`ScanByNameOrIdWithLabels::results_page`]) with objects that impl
+/// [`ObjectIdentity`].
+pub fn marker_for_name_or_id<S: NameOrIdScanParams, T: ObjectIdentity>(
+    scan: &S,
     item: &T,
 ) -> NameOrIdMarker {
     let identity = item.identity();
@@ -350,55 +357,83 @@ pub enum PagField {
     Name,
 }
 
-pub fn pagination_field_for_scan_params(p: &ScanByNameOrId) -> PagField {
-    match p.sort_by {
+pub fn pagination_field_for_scan_params<S: NameOrIdScanParams>(
+    p: &S,
+) -> PagField {
+    match p.sort_by() {
         NameOrIdSortMode::NameAscending => PagField::Name,
         NameOrIdSortMode::NameDescending => PagField::Name,
         NameOrIdSortMode::IdAscending => PagField::Id,
     }
 }
 
+/// Describes the scan modes that sort by name or id
+///
+/// This allows the functions here that deal with scanning by name or id to be
+/// shared by [`ScanByNameOrId`] and [`ScanByNameOrIdWithLabels`].
+pub trait NameOrIdScanParams: ScanParams<MarkerValue = NameOrIdMarker> {
+    fn sort_by(&self) -> NameOrIdSortMode;
+}
+
+impl NameOrIdScanParams for ScanByNameOrId {
+    fn sort_by(&self) -> NameOrIdSortMode {
+        self.sort_by
+    }
+}
+
+/// Implementation of [`ScanParams::direction`] for [`NameOrIdScanParams`]
+fn nameid_direction<S: NameOrIdScanParams>(scan: &S) -> PaginationOrder {
+    match scan.sort_by() {
+        NameOrIdSortMode::NameAscending => PaginationOrder::Ascending,
+        NameOrIdSortMode::NameDescending => PaginationOrder::Descending,
+        NameOrIdSortMode::IdAscending => PaginationOrder::Ascending,
+    }
+}
+
+/// Implementation of [`ScanParams::from_query`] for [`NameOrIdScanParams`]
+fn nameid_from_query<S: NameOrIdScanParams>(
+    p: &PaginationParams<S, PageSelector<S, NameOrIdMarker>>,
+) -> Result<&S, HttpError> {
+    match &p.page {
+        WhichPage::First(scan_mode) => Ok(scan_mode),
+
+        WhichPage::Next(PageSelector {
+            scan,
+            last_seen: NameOrIdMarker::Name(_),
+        }) => match scan.sort_by() {
+            NameOrIdSortMode::NameAscending => Ok(scan),
+            NameOrIdSortMode::NameDescending => Ok(scan),
+            NameOrIdSortMode::IdAscending => Err(()),
+        },
+
+        WhichPage::Next(PageSelector {
+            scan,
+            last_seen: NameOrIdMarker::Id(_),
+        }) => match scan.sort_by() {
+            NameOrIdSortMode::NameAscending => Err(()),
+            NameOrIdSortMode::NameDescending => Err(()),
+            NameOrIdSortMode::IdAscending => Ok(scan),
+        },
+    }
+    .map_err(|_| bad_token_error())
+}
+
 impl ScanParams for ScanByNameOrId {
     type MarkerValue = NameOrIdMarker;
 
     fn direction(&self) -> PaginationOrder {
-        match self.sort_by {
-            NameOrIdSortMode::NameAscending => PaginationOrder::Ascending,
-            NameOrIdSortMode::NameDescending => PaginationOrder::Descending,
-            NameOrIdSortMode::IdAscending => PaginationOrder::Ascending,
-        }
+        nameid_direction(self)
     }
 
     fn from_query(
         p: &PaginationParams<Self, PageSelector<Self, Self::MarkerValue>>,
     ) -> Result<&Self, HttpError> {
-        match &p.page {
-            WhichPage::First(scan_mode) => Ok(scan_mode),
-
-            WhichPage::Next(PageSelectorByNameOrId {
-                scan,
-                last_seen: NameOrIdMarker::Name(_),
-            }) => match scan.sort_by {
-                NameOrIdSortMode::NameAscending => Ok(scan),
-                NameOrIdSortMode::NameDescending => Ok(scan),
-                NameOrIdSortMode::IdAscending => Err(()),
-            },
-
-            WhichPage::Next(PageSelectorByNameOrId {
-                scan,
-                last_seen: NameOrIdMarker::Id(_),
-            }) => match scan.sort_by {
-                NameOrIdSortMode::NameAscending => Err(()),
-                NameOrIdSortMode::NameDescending => Err(()),
-                NameOrIdSortMode::IdAscending => Ok(scan),
-            },
-        }
-        .map_err(|_| bad_token_error())
+        nameid_from_query(p)
     }
 }
 
 /// Serves the same purpose as [`data_page_params_for`] for the specific case of
-/// `ScanByNameOrId` when scanning by `name`
+/// `ScanByNameOrId` (or `ScanByNameOrIdWithLabels`) when scanning by `name`
 ///
 /// Why do we need a separate function here?  Because `data_page_params_for` only
 /// knows how to return the (statically-defined) marker value from the page
@@ -408,20 +443,21 @@ impl ScanParams for ScanByNameOrId {
 /// that from this function and its partner, [`data_page_params_nameid_id`].
 /// These functions are where we look at the enum variant and extract the
 /// specific marker value out.
-pub fn data_page_params_nameid_name<'a, C>(
+pub fn data_page_params_nameid_name<'a, S, C>(
     rqctx: &'a Arc<RequestContext<C>>,
-    pag_params: &'a PaginatedByNameOrId,
+    pag_params: &'a PaginationParams<S, PageSelector<S, NameOrIdMarker>>,
 ) -> Result<DataPageParams<'a, Name>, HttpError>
 where
+    S: NameOrIdScanParams,
     C: dropshot::ServerContext,
 {
     let limit = rqctx.page_limit(pag_params)?;
     data_page_params_nameid_name_limit(limit, pag_params)
 }
 
-fn data_page_params_nameid_name_limit(
+fn data_page_params_nameid_name_limit<S: NameOrIdScanParams>(
     limit: NonZeroU32,
-    pag_params: &PaginatedByNameOrId,
+    pag_params: &PaginationParams<S, PageSelector<S, NameOrIdMarker>>,
 ) -> Result<DataPageParams<Name>, HttpError> {
     let data_page = data_page_params_with_limit(limit, pag_params)?;
     let direction = data_page.direction;
@@ -437,20 +473,21 @@ fn data_page_params_nameid_name_limit(
 }
 
 /// See [`data_page_params_nameid_name`].
-pub fn data_page_params_nameid_id<'a, C>(
+pub fn data_page_params_nameid_id<'a, S, C>(
     rqctx: &'a Arc<RequestContext<C>>,
-    pag_params: &'a PaginatedByNameOrId,
+    pag_params: &'a PaginationParams<S, PageSelector<S, NameOrIdMarker>>,
 ) -> Result<DataPageParams<'a, Uuid>, HttpError>
 where
+    S: NameOrIdScanParams,
     C: dropshot::ServerContext,
 {
     let limit = rqctx.page_limit(pag_params)?;
     data_page_params_nameid_id_limit(limit, pag_params)
 }
 
-fn data_page_params_nameid_id_limit(
+fn data_page_params_nameid_id_limit<S: NameOrIdScanParams>(
     limit: NonZeroU32,
-    pag_params: &PaginatedByNameOrId,
+    pag_params: &PaginationParams<S, PageSelector<S, NameOrIdMarker>>,
 ) -> Result<DataPageParams<Uuid>, HttpError> {
     let data_page = data_page_params_with_limit(limit, pag_params)?;
     let direction = data_page.direction;
@@ -465,6 +502,91 @@ fn data_page_params_nameid_id_limit(
     Ok(DataPageParams { limit, direction, marker })
 }
 
+// Pagination by name only, filtered by labels
+
+/// Query parameters for pagination by name only, for resources with labels
+pub type PaginatedByNameWithLabels =
+    PaginationParams<ScanByNameWithLabels, PageSelectorByNameWithLabels>;
+/// Page selector for pagination by name only, for resources with labels
+pub type PageSelectorByNameWithLabels =
+    PageSelector<ScanByNameWithLabels, Name>;
+/// Scan parameters for resources that support scanning by name only and
+/// filtering by labels
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+pub struct ScanByNameWithLabels {
+    #[serde(default = "default_name_sort_mode")]
+    sort_by: NameSortMode,
+    /// only list resources that have all of these labels
+    label_selector: Option<LabelSelector>,
+}
+
+impl ScanByNameWithLabels {
+    pub fn label_selector(&self) -> Option<&LabelSelector> {
+        self.label_selector.as_ref()
+    }
+}
+
+impl ScanParams for ScanByNameWithLabels {
+    type MarkerValue = Name;
+    fn direction(&self) -> PaginationOrder {
+        PaginationOrder::Ascending
+    }
+    fn from_query(
+        p: &PaginationParams<Self, PageSelector<Self, Self::MarkerValue>>,
+    ) -> Result<&Self, HttpError> {
+        Ok(match p.page {
+            WhichPage::First(ref scan_params) => scan_params,
+            WhichPage::Next(PageSelector { ref scan, .. }) => scan,
+        })
+    }
+}
+
+// Pagination by name or id, filtered by labels
+
+/// Query parameters for pagination by name or id, for resources with labels
+pub type PaginatedByNameOrIdWithLabels = PaginationParams<
+    ScanByNameOrIdWithLabels,
+    PageSelectorByNameOrIdWithLabels,
+>;
+/// Page selector for pagination by name or id, for resources with labels
+pub type PageSelectorByNameOrIdWithLabels =
+    PageSelector<ScanByNameOrIdWithLabels, NameOrIdMarker>;
+/// Scan parameters for resources that support scanning by name or id and
+/// filtering by labels
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+pub struct ScanByNameOrIdWithLabels {
+    #[serde(default = "default_nameid_sort_mode")]
+    sort_by: NameOrIdSortMode,
+    /// only list resources that have all of these labels
+    label_selector: Option<LabelSelector>,
+}
+
+impl ScanByNameOrIdWithLabels {
+    pub fn label_selector(&self) -> Option<&LabelSelector> {
+        self.label_selector.as_ref()
+    }
+}
+
+impl NameOrIdScanParams for ScanByNameOrIdWithLabels {
+    fn sort_by(&self) -> NameOrIdSortMode {
+        self.sort_by
+    }
+}
+
+impl ScanParams for ScanByNameOrIdWithLabels {
+    type MarkerValue = NameOrIdMarker;
+
+    fn direction(&self) -> PaginationOrder {
+        nameid_direction(self)
+    }
+
+    fn from_query(
+        p: &PaginationParams<Self, PageSelector<Self, Self::MarkerValue>>,
+    ) -> Result<&Self, HttpError> {
+        nameid_from_query(p)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::data_page_params_nameid_id_limit;
@@ -488,9 +610,12 @@ mod test {
     use super::PaginatedById;
     use super::PaginatedByName;
     use super::PaginatedByNameOrId;
+    use super::PaginatedByNameWithLabels;
     use super::ScanById;
     use super::ScanByName;
     use super::ScanByNameOrId;
+    use super::ScanByNameOrIdWithLabels;
+    use super::ScanByNameWithLabels;
     use super::ScanParams;
     use crate::api::external::IdentityMetadata;
     use crate::api::external::ObjectIdentity;
@@ -887,4 +1012,93 @@ mod test {
         assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
         assert_eq!(error.external_message, "invalid page token");
     }
+
+    #[test]
+    fn test_scan_by_name_with_labels() {
+        // Start with the common battery of tests.  The label selector must
+        // survive the round trip through the page token.
+        let scan = ScanByNameWithLabels {
+            sort_by: NameSortMode::NameAscending,
+            label_selector: Some("env=prod,team=storage".parse().unwrap()),
+        };
+
+        let list = list_of_things();
+        let (p0, p1) = test_scan_param_common(
+            &list,
+            &scan,
+            "sort_by=name_ascending&label_selector=env%3Dprod%2Cteam%3Dstorage",
+            &"thing0".parse().unwrap(),
+            &"thing19".parse().unwrap(),
+            &ScanByNameWithLabels {
+                sort_by: NameSortMode::NameAscending,
+                label_selector: None,
+            },
+            &marker_for_name,
+        );
+        assert_eq!(scan.direction(), PaginationOrder::Ascending);
+        assert_eq!(
+            ScanByNameWithLabels::from_query(&p1)
+                .unwrap()
+                .label_selector()
+                .unwrap()
+                .to_string(),
+            "env=prod,team=storage"
+        );
+
+        // Verify data pages based on the query params.
+        let limit = NonZeroU32::new(123).unwrap();
+        let data_page = data_page_params_with_limit(limit, &p0).unwrap();
+        assert_eq!(data_page.marker, None);
+        let data_page = data_page_params_with_limit(limit, &p1).unwrap();
+        assert_eq!(data_page.marker.unwrap().as_str(), "thing19");
+
+        // Test from_query(): error case.
+        let error = serde_urlencoded::from_str::<PaginatedByNameWithLabels>(
+            "label_selector=env",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "label selector term must have the form \"key=value\": \"env\""
+        );
+    }
+
+    #[test]
+    fn test_scan_by_nameid_with_labels() {
+        // Start with the common battery of tests.
+        let scan = ScanByNameOrIdWithLabels {
+            sort_by: NameOrIdSortMode::IdAscending,
+            label_selector: Some("env=prod".parse().unwrap()),
+        };
+        assert_eq!(pagination_field_for_scan_params(&scan), PagField::Id);
+        assert_eq!(scan.direction(), PaginationOrder::Ascending);
+
+        let list = list_of_things();
+        let thing0_marker = NameOrIdMarker::Id(list[0].identity.id);
+        let thinglast_id = list[list.len() - 1].identity.id;
+        let thinglast_marker = NameOrIdMarker::Id(thinglast_id);
+        let (p0, p1) = test_scan_param_common(
+            &list,
+            &scan,
+            "sort_by=id_ascending&label_selector=env%3Dprod",
+            &thing0_marker,
+            &thinglast_marker,
+            &ScanByNameOrIdWithLabels {
+                sort_by: NameOrIdSortMode::NameAscending,
+                label_selector: None,
+            },
+            &marker_for_name_or_id,
+        );
+
+        // Verify data pages based on the query params.
+        let limit = NonZeroU32::new(123).unwrap();
+        let data_page = data_page_params_nameid_id_limit(limit, &p0).unwrap();
+        assert_eq!(data_page.marker, None);
+        let data_page = data_page_params_nameid_id_limit(limit, &p1).unwrap();
+        assert_eq!(data_page.marker, Some(&thinglast_id));
+
+        let error = data_page_params_nameid_name_limit(limit, &p1).unwrap_err();
+        assert_eq!(error.status_code, StatusCode::BAD_REQUEST);
+        assert_eq!(error.external_message, "invalid page token");
+    }
 }
@@ -26,6 +26,7 @@ use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -531,8 +532,11 @@ pub enum ResourceType {
     Silo,
     SiloUser,
     SiloGroup,
+    ServiceAccount,
+    ServiceAccountKey,
     IdentityProvider,
     SamlIdentityProvider,
+    OidcIdentityProvider,
     SshKey,
     ConsoleSession,
     DeviceAuthRequest,
@@ -607,6 +611,225 @@ pub struct IdentityMetadataUpdateParams {
     pub description: Option<String>,
 }
 
+// LABELS
+
+/// Maximum number of labels that may be attached to one resource
+pub const MAX_LABELS_PER_RESOURCE: usize = 32;
+
+/// Key/value labels attached to a resource
+///
+/// Labels don't mean anything to the system.  They let users tag resources
+/// (e.g., with an owner or an environment) and then list only the resources
+/// having particular labels (see [`LabelSelector`]).
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "BTreeMap<String, String>")]
+pub struct Labels(BTreeMap<String, String>);
+
+impl TryFrom<BTreeMap<String, String>> for Labels {
+    type Error = String;
+    fn try_from(labels: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        if labels.len() > MAX_LABELS_PER_RESOURCE {
+            return Err(format!(
+                "a resource may have at most {} labels",
+                MAX_LABELS_PER_RESOURCE
+            ));
+        }
+        for (key, value) in &labels {
+            if key.is_empty() {
+                return Err(String::from(
+                    "label key requires at least one character",
+                ));
+            }
+            check_label_part("key", key)?;
+            check_label_part("value", value)?;
+        }
+        Ok(Labels(labels))
+    }
+}
+
+/// Checks one label key or value.  `what` describes which one it is, for
+/// error messages.
+fn check_label_part(what: &str, part: &str) -> Result<(), String> {
+    if part.len() > 63 {
+        return Err(format!(
+            "label {} may contain at most 63 characters: \"{}\"",
+            what, part
+        ));
+    }
+    if let Some(c) =
+        part.chars().find(|c| !c.is_ascii_alphanumeric() && !"-_.".contains(*c))
+    {
+        return Err(format!(
+            "label {} contains invalid character: \"{}\" (allowed \
+             characters are ASCII letters, digits, \"-\", \"_\", and \".\")",
+            what, c
+        ));
+    }
+    let starts_ok =
+        part.chars().next().map_or(true, |c| c.is_ascii_alphanumeric());
+    let ends_ok =
+        part.chars().last().map_or(true, |c| c.is_ascii_alphanumeric());
+    if !starts_ok || !ends_ok {
+        return Err(format!(
+            "label {} must begin and end with an ASCII letter or digit: \"{}\"",
+            what, part
+        ));
+    }
+    Ok(())
+}
+
+impl Labels {
+    /// Returns the value of the label `key`, if the label is present
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Custom JsonSchema implementation to encode the constraints on Labels
+impl JsonSchema for Labels {
+    fn schema_name() -> String {
+        "Labels".to_string()
+    }
+    fn json_schema(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        let value_schema = schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            string: Some(Box::new(schemars::schema::StringValidation {
+                max_length: Some(63),
+                min_length: None,
+                pattern: Some(
+                    r#"^([a-zA-Z0-9]([-_.a-zA-Z0-9]*[a-zA-Z0-9])?)?$"#
+                        .to_string(),
+                ),
+            })),
+            ..Default::default()
+        };
+        schemars::schema::SchemaObject {
+            metadata: Some(Box::new(schemars::schema::Metadata {
+                title: Some(
+                    "Key/value labels attached to a resource".to_string(),
+                ),
+                description: Some(
+                    "Label keys and values may contain at most 63 characters, \
+                     which must be ASCII letters, digits, '-', '_', or '.', \
+                     and must begin and end with a letter or digit.  Keys \
+                     may not be empty."
+                        .to_string(),
+                ),
+                ..Default::default()
+            })),
+            instance_type: Some(schemars::schema::InstanceType::Object.into()),
+            object: Some(Box::new(schemars::schema::ObjectValidation {
+                max_properties: Some(MAX_LABELS_PER_RESOURCE as u32),
+                additional_properties: Some(Box::new(value_schema.into())),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Selects the resources that have all of a set of labels
+///
+/// A selector is written as a comma-separated list of `key=value` terms, such
+/// as `env=prod,team=storage`.  A resource matches if, for every term, it has
+/// a label with that key and value.
+#[derive(Clone, Debug, DeserializeFromStr, Eq, PartialEq, SerializeDisplay)]
+pub struct LabelSelector(Labels);
+
+impl FromStr for LabelSelector {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut labels = BTreeMap::new();
+        for term in value.split(',') {
+            let (key, value) = term.split_once('=').ok_or_else(|| {
+                format!(
+                    "label selector term must have the form \"key=value\": \
+                     \"{}\"",
+                    term
+                )
+            })?;
+            if labels.insert(key.to_string(), value.to_string()).is_some() {
+                return Err(format!(
+                    "label selector has more than one term for key \"{}\"",
+                    key
+                ));
+            }
+        }
+        Labels::try_from(labels).map(LabelSelector)
+    }
+}
+
+impl Display for LabelSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        let terms: Vec<String> = self
+            .0
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+impl LabelSelector {
+    /// Returns the labels that a resource must have to match this selector
+    pub fn labels(&self) -> &Labels {
+        &self.0
+    }
+
+    /// Returns whether a resource with the given labels matches this selector
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.0.iter().all(|(key, value)| labels.get(key) == Some(value))
+    }
+}
+
+/// Custom JsonSchema implementation to encode the format of a LabelSelector
+impl JsonSchema for LabelSelector {
+    fn schema_name() -> String {
+        "LabelSelector".to_string()
+    }
+    fn json_schema(
+        _: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            metadata: Some(Box::new(schemars::schema::Metadata {
+                title: Some(
+                    "Selects the resources that have all of a set of labels"
+                        .to_string(),
+                ),
+                description: Some(
+                    "A comma-separated list of \"key=value\" terms, such as \
+                     \"env=prod,team=storage\".  A resource matches if, for \
+                     every term, it has a label with that key and value."
+                        .to_string(),
+                ),
+                ..Default::default()
+            })),
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            string: Some(Box::new(schemars::schema::StringValidation {
+                max_length: None,
+                min_length: None,
+                pattern: Some(
+                    r#"^[^,=]+=[^,=]*(,[^,=]+=[^,=]*)*$"#.to_string(),
+                ),
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 // Specific API resources
 
 // INSTANCES
@@ -774,6 +997,8 @@ pub struct Instance {
     pub memory: ByteCount,
     /// RFC1035-compliant hostname for the Instance.
     pub hostname: String, // TODO-cleanup different type?
+    /// labels attached to this Instance
+    pub labels: Labels,
 
     #[serde(flatten)]
     pub runtime: InstanceRuntimeState,
@@ -793,6 +1018,7 @@ pub struct Disk {
     pub block_size: ByteCount,
     pub state: DiskState,
     pub device_path: String,
+    pub labels: Labels,
 }
 
 /// State of a Disk (primarily: attached or not)
@@ -811,6 +1037,8 @@ pub struct Disk {
 pub enum DiskState {
     /// Disk is being initialized
     Creating,
+    /// Disk is ready to receive blocks being imported into it
+    Importing,
     /// Disk is ready but detached from any Instance
     Detached,
     /// Disk is being attached to the given Instance
@@ -839,6 +1067,7 @@ impl TryFrom<(&str, Option<Uuid>)> for DiskState {
     ) -> Result<Self, Self::Error> {
         match (s, maybe_id) {
             ("creating", None) => Ok(DiskState::Creating),
+            ("importing", None) => Ok(DiskState::Importing),
             ("detached", None) => Ok(DiskState::Detached),
             ("destroyed", None) => Ok(DiskState::Destroyed),
             ("faulted", None) => Ok(DiskState::Faulted),
@@ -858,6 +1087,7 @@ impl DiskState {
     pub fn label(&self) -> &'static str {
         match self {
             DiskState::Creating => "creating",
+            DiskState::Importing => "importing",
             DiskState::Detached => "detached",
             DiskState::Attaching(_) => "attaching",
             DiskState::Attached(_) => "attached",
@@ -882,6 +1112,7 @@ impl DiskState {
             DiskState::Detaching(id) => Some(id),
 
             DiskState::Creating => None,
+            DiskState::Importing => None,
             DiskState::Detached => None,
             DiskState::Destroyed => None,
             DiskState::Faulted => None,
@@ -1948,15 +2179,17 @@ mod test {
     use super::RouteTarget;
     use super::VpcFirewallRuleHostFilter;
     use super::VpcFirewallRuleTarget;
+    use super::MAX_LABELS_PER_RESOURCE;
     use super::{
-        ByteCount, Digest, L4Port, L4PortRange, Name, RoleName,
-        VpcFirewallRuleAction, VpcFirewallRuleDirection, VpcFirewallRuleFilter,
-        VpcFirewallRulePriority, VpcFirewallRuleProtocol,
-        VpcFirewallRuleStatus, VpcFirewallRuleUpdate,
+        ByteCount, Digest, L4Port, L4PortRange, LabelSelector, Labels, Name,
+        RoleName, VpcFirewallRuleAction, VpcFirewallRuleDirection,
+        VpcFirewallRuleFilter, VpcFirewallRulePriority,
+        VpcFirewallRuleProtocol, VpcFirewallRuleStatus, VpcFirewallRuleUpdate,
         VpcFirewallRuleUpdateParams,
     };
     use crate::api::external::Error;
     use crate::api::external::ResourceType;
+    use std::collections::BTreeMap;
     use std::convert::TryFrom;
     use std::str::FromStr;
 
@@ -2108,6 +2341,103 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_labels_parse() {
+        // Error cases
+        let error_cases: Vec<(&str, &str, &str)> = vec![
+            ("", "prod", "label key requires at least one character"),
+            (
+                "env",
+                "pr od",
+                "label value contains invalid character: \" \" (allowed \
+                 characters are ASCII letters, digits, \"-\", \"_\", and \
+                 \".\")",
+            ),
+            (
+                "-env",
+                "prod",
+                "label key must begin and end with an ASCII letter or \
+                 digit: \"-env\"",
+            ),
+            (
+                "env",
+                "prod.",
+                "label value must begin and end with an ASCII letter or \
+                 digit: \"prod.\"",
+            ),
+        ];
+        for (key, value, expected_message) in error_cases {
+            eprintln!("check label {:?}={:?} (expecting error)", key, value);
+            let labels = BTreeMap::from([(key.to_string(), value.to_string())]);
+            assert_eq!(Labels::try_from(labels).unwrap_err(), expected_message);
+        }
+
+        let long = "a".repeat(64);
+        let labels = BTreeMap::from([(long.clone(), String::new())]);
+        assert_eq!(
+            Labels::try_from(labels).unwrap_err(),
+            format!(
+                "label key may contain at most 63 characters: \"{}\"",
+                long
+            )
+        );
+
+        let too_many: BTreeMap<String, String> = (0..=MAX_LABELS_PER_RESOURCE)
+            .map(|i| (format!("key{}", i), String::new()))
+            .collect();
+        assert_eq!(
+            Labels::try_from(too_many).unwrap_err(),
+            format!(
+                "a resource may have at most {} labels",
+                MAX_LABELS_PER_RESOURCE
+            )
+        );
+
+        // Success cases
+        let labels: Labels = serde_json::from_str(
+            r#"{"env": "prod", "cost-center": "A_1.2", "empty": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(labels.get("env"), Some("prod"));
+        assert_eq!(labels.get("cost-center"), Some("A_1.2"));
+        assert_eq!(labels.get("empty"), Some(""));
+        assert_eq!(labels.get("team"), None);
+    }
+
+    #[test]
+    fn test_label_selector() {
+        let labels: Labels =
+            serde_json::from_str(r#"{"env": "prod", "team": "storage"}"#)
+                .unwrap();
+
+        let selector: LabelSelector = "env=prod".parse().unwrap();
+        assert!(selector.matches(&labels));
+        let selector: LabelSelector = "team=storage,env=prod".parse().unwrap();
+        assert!(selector.matches(&labels));
+        assert_eq!(selector.to_string(), "env=prod,team=storage");
+        let selector: LabelSelector = "env=dev".parse().unwrap();
+        assert!(!selector.matches(&labels));
+        let selector: LabelSelector = "env=prod,owner=me".parse().unwrap();
+        assert!(!selector.matches(&labels));
+
+        assert_eq!(
+            "env".parse::<LabelSelector>().unwrap_err(),
+            "label selector term must have the form \"key=value\": \"env\""
+        );
+        assert_eq!(
+            "env=prod,".parse::<LabelSelector>().unwrap_err(),
+            "label selector term must have the form \"key=value\": \"\""
+        );
+        assert_eq!(
+            "env=prod,env=dev".parse::<LabelSelector>().unwrap_err(),
+            "label selector has more than one term for key \"env\""
+        );
+        assert_eq!(
+            "=prod".parse::<LabelSelector>().unwrap_err(),
+            "label key requires at least one character"
+        );
+    }
+
     #[test]
     fn test_bytecount() {
         // Smallest supported value: all constructors
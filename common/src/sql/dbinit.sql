@@ -307,7 +307,8 @@ CREATE INDEX on omicron.public.volume (
 
 CREATE TYPE omicron.public.authentication_mode AS ENUM (
   'local',
-  'saml'
+  'saml',
+  'oidc'
 );
 
 CREATE TYPE omicron.public.user_provision_type AS ENUM (
@@ -357,6 +358,26 @@ CREATE UNIQUE INDEX ON omicron.public.silo_user (
 ) WHERE
     time_deleted IS NULL;
 
+/*
+ * Password hashes for users of the "local" identity provider
+ *
+ * Users without a row here can't log in with a password.  The hash is a PHC
+ * string (which records its own algorithm and parameters), so hashes created
+ * with older parameters remain valid after those change.
+ */
+CREATE TABLE omicron.public.silo_user_password_hash (
+    silo_user_id UUID NOT NULL PRIMARY KEY,
+    hash STRING(4096) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+
+    /*
+     * Consecutive failed login attempts, and the time until which logins are
+     * refused after too many of them
+     */
+    failed_attempts INT8 NOT NULL DEFAULT 0,
+    time_locked_until TIMESTAMPTZ
+);
+
 /*
  * Silo groups
  */
@@ -393,12 +414,69 @@ CREATE INDEX ON omicron.public.silo_group_membership (
     silo_group_id
 );
 
+/*
+ * Service accounts
+ *
+ * A service account is a principal within a Silo that's used by automation
+ * rather than by a person.  It authenticates with API keys rather than through
+ * an identity provider.
+ */
+CREATE TABLE omicron.public.service_account (
+    /* Identity metadata */
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    silo_id UUID NOT NULL,
+
+    /*
+     * If set, the Project to which the service account's access is limited
+     * (and the Organization containing it)
+     */
+    organization_id UUID,
+    project_id UUID
+);
+
+CREATE UNIQUE INDEX ON omicron.public.service_account (
+    silo_id,
+    name
+) WHERE
+    time_deleted IS NULL;
+
+/*
+ * API keys for service accounts
+ *
+ * A service account may have several keys at once, so that keys can be
+ * rotated without interrupting its use.
+ */
+CREATE TABLE omicron.public.service_account_key (
+    /* Identifies the key in the API without revealing the key itself */
+    id UUID PRIMARY KEY,
+    token STRING(40) NOT NULL,
+    service_account_id UUID NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX ON omicron.public.service_account_key (
+    token
+);
+
+CREATE INDEX ON omicron.public.service_account_key (
+    service_account_id
+) WHERE
+    time_deleted IS NULL;
+
 /*
  * Silo identity provider list
  */
 
 CREATE TYPE omicron.public.provider_type AS ENUM (
-  'saml'
+  'saml',
+  'oidc'
 );
 
 CREATE TABLE omicron.public.identity_provider (
@@ -451,7 +529,17 @@ CREATE TABLE omicron.public.saml_identity_provider (
     public_cert TEXT,
     private_key TEXT,
 
-    group_attribute_name TEXT
+    group_attribute_name TEXT,
+
+    /*
+     * If the metadata document was supplied by URL, it is periodically
+     * re-fetched from there.  These columns record the URL and the outcome of
+     * the most recent refresh attempts.
+     */
+    idp_metadata_url TEXT,
+    time_metadata_refresh_attempted TIMESTAMPTZ,
+    time_metadata_refreshed TIMESTAMPTZ,
+    metadata_refresh_error TEXT
 );
 
 CREATE INDEX ON omicron.public.saml_identity_provider (
@@ -460,6 +548,36 @@ CREATE INDEX ON omicron.public.saml_identity_provider (
 ) WHERE
     time_deleted IS NULL;
 
+/*
+ * Silo OpenID Connect identity provider
+ */
+CREATE TABLE omicron.public.oidc_identity_provider (
+    /* Identity metadata */
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    silo_id UUID NOT NULL,
+
+    issuer TEXT NOT NULL,
+    discovery_document_string TEXT NOT NULL,
+
+    client_id TEXT NOT NULL,
+    client_secret TEXT,
+    redirect_uri TEXT NOT NULL,
+
+    group_claim_name TEXT
+);
+
+CREATE INDEX ON omicron.public.oidc_identity_provider (
+    id,
+    silo_id
+) WHERE
+    time_deleted IS NULL;
+
 /*
  * Users' public SSH keys, per RFD 44
  */
@@ -504,6 +622,8 @@ CREATE TABLE omicron.public.organization (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     /* child resource generation number, per RFD 192 */
     rcgen INT NOT NULL
@@ -528,6 +648,8 @@ CREATE TABLE omicron.public.project (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     /* Which organization this project belongs to */
     organization_id UUID NOT NULL /* foreign key into "Organization" table */
@@ -571,6 +693,8 @@ CREATE TABLE omicron.public.instance (
     /* Indicates that the object has been deleted */
     /* This is redundant for Instances, but we keep it here for consistency. */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     /* Every Instance is in exactly one Project at a time. */
     project_id UUID NOT NULL,
@@ -660,6 +784,8 @@ CREATE TABLE omicron.public.disk (
     /* Indicates that the object has been deleted */
     /* This is redundant for Disks, but we keep it here for consistency. */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     /* child resource generation number, per RFD 192 */
     rcgen INT NOT NULL,
@@ -712,6 +838,8 @@ CREATE TABLE omicron.public.image (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     project_id UUID NOT NULL,
     volume_id UUID NOT NULL,
@@ -738,6 +866,8 @@ CREATE TABLE omicron.public.global_image (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     volume_id UUID NOT NULL,
 
@@ -770,6 +900,8 @@ CREATE TABLE omicron.public.snapshot (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
 
     /* Every Snapshot is in exactly one Project at a time. */
     project_id UUID NOT NULL,
@@ -843,6 +975,8 @@ CREATE TABLE omicron.public.vpc (
     time_modified TIMESTAMPTZ NOT NULL,
     /* Indicates that the object has been deleted */
     time_deleted TIMESTAMPTZ,
+    /* User-defined key/value labels */
+    labels JSONB NOT NULL DEFAULT '{}',
     project_id UUID NOT NULL,
     system_router_id UUID NOT NULL,
     dns_name STRING(63) NOT NULL,
@@ -1408,16 +1542,26 @@ CREATE TABLE omicron.public.saga_node_event (
  */
 CREATE TABLE omicron.public.console_session (
     token STRING(40) PRIMARY KEY,
+    /* Identifies the session to its user, without revealing the token */
+    id UUID NOT NULL,
     time_created TIMESTAMPTZ NOT NULL,
     time_last_used TIMESTAMPTZ NOT NULL,
     silo_user_id UUID NOT NULL
 );
 
--- to be used for cleaning up old tokens
+CREATE UNIQUE INDEX ON omicron.public.console_session (
+    id
+);
+
+-- used for cleaning up expired sessions
 CREATE INDEX ON omicron.public.console_session (
     time_created
 );
 
+CREATE INDEX ON omicron.public.console_session (
+    time_last_used
+);
+
 -- This index is used to remove sessions for a user that's being deleted.
 CREATE INDEX ON omicron.public.console_session (
     silo_user_id
@@ -1506,6 +1650,15 @@ INSERT INTO omicron.public.user_builtin (
  * OAuth 2.0 Device Authorization Grant (RFC 8628)
  */
 
+-- Actions that a scoped access token may be used for
+CREATE TYPE omicron.public.token_scope_action AS ENUM (
+    'read',
+    'list_children',
+    'modify',
+    'create_child',
+    'operate'
+);
+
 -- Device authorization requests. These records are short-lived,
 -- and removed as soon as a token is granted. This allows us to
 -- use the `user_code` as primary key, despite it not having very
@@ -1516,19 +1669,38 @@ CREATE TABLE omicron.public.device_auth_request (
     client_id UUID NOT NULL,
     device_code STRING(40) NOT NULL,
     time_created TIMESTAMPTZ NOT NULL,
-    time_expires TIMESTAMPTZ NOT NULL
+    time_expires TIMESTAMPTZ NOT NULL,
+    /* Scope requested by the client, if any (see "device_access_token") */
+    scope_actions omicron.public.token_scope_action[],
+    scope_organization_id UUID,
+    scope_project_id UUID
 );
 
 -- Access tokens granted in response to successful device authorization flows.
--- TODO-security: expire tokens.
 CREATE TABLE omicron.public.device_access_token (
     token STRING(40) PRIMARY KEY,
+    /* Identifies the token in the API without revealing the token itself */
+    id UUID NOT NULL,
     client_id UUID NOT NULL,
     device_code STRING(40) NOT NULL,
     silo_user_id UUID NOT NULL,
     time_requested TIMESTAMPTZ NOT NULL,
     time_created TIMESTAMPTZ NOT NULL,
-    time_expires TIMESTAMPTZ
+    time_expires TIMESTAMPTZ,
+    time_revoked TIMESTAMPTZ,
+    /*
+     * A token with a scope can only be used for the listed actions and, if
+     * given, only within the given Organization or Project.  If the scope
+     * names a Project, its Organization is recorded too.  A NULL
+     * "scope_actions" means the token is not scoped at all.
+     */
+    scope_actions omicron.public.token_scope_action[],
+    scope_organization_id UUID,
+    scope_project_id UUID
+);
+
+CREATE UNIQUE INDEX ON omicron.public.device_access_token (
+    id
 );
 
 -- This UNIQUE constraint is critical for ensuring that at most
@@ -1537,7 +1709,8 @@ CREATE UNIQUE INDEX ON omicron.public.device_access_token (
     client_id, device_code
 );
 
--- This index is used to remove tokens for a user that's being deleted.
+-- This index is used to list and revoke a user's tokens, and to remove
+-- tokens for a user that's being deleted.
 CREATE INDEX ON omicron.public.device_access_token (
     silo_user_id
 );
@@ -1605,7 +1778,8 @@ CREATE TABLE omicron.public.role_builtin (
 CREATE TYPE omicron.public.identity_type AS ENUM (
   'user_builtin',
   'silo_user',
-  'silo_group'
+  'silo_group',
+  'service_account'
 );
 
 CREATE TABLE omicron.public.role_assignment (
@@ -1653,6 +1827,20 @@ CREATE TABLE omicron.public.role_assignment (
 
 /*******************************************************************/
 
+/*
+ * The generation of each DNS zone whose contents Nexus maintains.  Nexus bumps
+ * the generation in the same transaction that reads the data the zone is built
+ * from, so that DNS servers can tell which of two updates is newer, even if
+ * they come from different Nexus instances.
+ */
+CREATE TABLE omicron.public.dns_zone_generation (
+    zone STRING(253) PRIMARY KEY,
+    generation INT8 NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL
+);
+
+/*******************************************************************/
+
 /*
  * Metadata for the schema itself.  This version number isn't great, as there's
  * nothing to ensure it gets bumped when it should be, but it's a start.
@@ -7,12 +7,18 @@ use futures::stream::{self, StreamExt, TryStreamExt};
 use omicron_common::address::{
     Ipv6Subnet, ReservedRackSubnet, AZ_PREFIX, DNS_PORT, DNS_SERVER_PORT,
 };
+use rand::Rng;
 use slog::{info, Logger};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::net::{IpAddr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::sync::Mutex;
+use std::time::Instant;
 use trust_dns_resolver::config::{
     NameServerConfig, Protocol, ResolverConfig, ResolverOpts,
 };
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::rr::{Name, RData};
 use trust_dns_resolver::TokioAsyncResolver;
 
 pub type DnsError = crate::Error<crate::types::Error>;
@@ -65,7 +71,7 @@ impl Updater {
         Self::new_from_addrs(addrs, log)
     }
 
-    fn new_from_addrs(addrs: Vec<SocketAddr>, log: Logger) -> Self {
+    pub fn new_from_addrs(addrs: Vec<SocketAddr>, log: Logger) -> Self {
         let clients = addrs
             .into_iter()
             .map(|addr| {
@@ -161,6 +167,27 @@ impl Updater {
 
         Ok(())
     }
+
+    /// Replaces the contents of the zone in all DNS servers.
+    ///
+    /// Returns an error if replacing the zone fails on any server, including
+    /// because the server is already at a newer generation. Servers which were
+    /// updated stay updated, so the caller should retry with the same, or a
+    /// newer, generation.
+    pub async fn dns_zone_replace<'a>(
+        &'a self,
+        body: &'a crate::types::DnsZoneUpdate,
+    ) -> Result<(), DnsError> {
+        stream::iter(&self.clients)
+            .map(Ok::<_, DnsError>)
+            .try_for_each_concurrent(None, |client| async move {
+                client.dns_zone_replace(body).await?;
+                Ok(())
+            })
+            .await?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -172,10 +199,39 @@ pub enum ResolveError {
     NotFound(crate::names::SRV),
 }
 
+/// Errors from [`Resolver::try_backends`].
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError<E> {
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+
+    #[error("All {} backends for {srv} failed", .errors.len())]
+    AllBackendsFailed {
+        srv: crate::names::SRV,
+        /// The error from each backend, in the order they were tried.
+        errors: Vec<(SocketAddrV6, E)>,
+    },
+}
+
+/// A backend of a service, from one of the service's SRV records and the AAAA
+/// record of its target.
+#[derive(Clone, Debug)]
+struct Backend {
+    priority: u16,
+    weight: u16,
+    address: SocketAddrV6,
+}
+
+struct CachedBackends {
+    backends: Vec<Backend>,
+    valid_until: Instant,
+}
+
 /// A wrapper around a DNS resolver, providing a way to conveniently
-/// look up IP addresses of services based on their SRV keys.
+/// look up the addresses of services based on their SRV keys.
 pub struct Resolver {
     inner: Box<TokioAsyncResolver>,
+    cache: Mutex<HashMap<crate::names::SRV, CachedBackends>>,
 }
 
 impl Resolver {
@@ -191,18 +247,22 @@ impl Resolver {
     ) -> Result<Self, ResolveError> {
         let mut rc = ResolverConfig::new();
         for socket_addr in dns_addrs.into_iter() {
-            rc.add_name_server(NameServerConfig {
-                socket_addr,
-                protocol: Protocol::Udp,
-                tls_dns_name: None,
-                trust_nx_responses: false,
-                bind_addr: None,
-            });
+            // Responses that don't fit in a UDP datagram are truncated by the
+            // server, and the resolver retries them over TCP.
+            for protocol in [Protocol::Udp, Protocol::Tcp] {
+                rc.add_name_server(NameServerConfig {
+                    socket_addr,
+                    protocol,
+                    tls_dns_name: None,
+                    trust_nx_responses: false,
+                    bind_addr: None,
+                });
+            }
         }
         let inner =
             Box::new(TokioAsyncResolver::tokio(rc, ResolverOpts::default())?);
 
-        Ok(Self { inner })
+        Ok(Self { inner, cache: Mutex::new(HashMap::new()) })
     }
 
     /// Convenience wrapper for [`Resolver::new`] which determines the subnet
@@ -215,34 +275,188 @@ impl Resolver {
 
     /// Looks up a single [`Ipv6Addr`] based on the SRV name.
     /// Returns an error if the record does not exist.
-    // TODO: There are lots of ways this API can expand: Caching,
-    // actually respecting TTL, looking up ports, etc.
-    //
-    // For now, however, it serves as a very simple "get everyone using DNS"
-    // API that can be improved upon later.
     pub async fn lookup_ipv6(
         &self,
         srv: crate::names::SRV,
     ) -> Result<Ipv6Addr, ResolveError> {
-        let response = self.inner.ipv6_lookup(&srv.to_string()).await?;
-        let address = response
-            .iter()
-            .next()
-            .ok_or_else(|| ResolveError::NotFound(srv))?;
-        Ok(*address)
+        Ok(*self.lookup_socket_v6(srv).await?.ip())
     }
 
     pub async fn lookup_ip(
         &self,
         srv: crate::names::SRV,
     ) -> Result<IpAddr, ResolveError> {
-        let response = self.inner.lookup_ip(&srv.to_string()).await?;
-        let address = response
-            .iter()
-            .next()
-            .ok_or_else(|| ResolveError::NotFound(srv))?;
-        Ok(address)
+        Ok(IpAddr::V6(self.lookup_ipv6(srv).await?))
+    }
+
+    /// Looks up a single [`SocketAddrV6`] based on the SRV name, chosen
+    /// according to the priority and weight of the SRV records.
+    /// Returns an error if the record does not exist.
+    pub async fn lookup_socket_v6(
+        &self,
+        srv: crate::names::SRV,
+    ) -> Result<SocketAddrV6, ResolveError> {
+        let mut addresses = self.lookup_all_socket_v6(srv).await?;
+        // `lookup_all_socket_v6` never returns an empty list.
+        Ok(addresses.swap_remove(0))
+    }
+
+    /// Looks up every [`SocketAddrV6`] based on the SRV name, in the order
+    /// clients should try them (RFC 2782): by increasing priority, and in a
+    /// random order weighted by the SRV records' weights within each priority.
+    /// Returns an error if there are no addresses.
+    ///
+    /// The addresses are cached for as long as the TTLs of the DNS records
+    /// they came from allow.
+    pub async fn lookup_all_socket_v6(
+        &self,
+        srv: crate::names::SRV,
+    ) -> Result<Vec<SocketAddrV6>, ResolveError> {
+        let backends = self.lookup_backends(&srv).await?;
+        if backends.is_empty() {
+            return Err(ResolveError::NotFound(srv));
+        }
+        Ok(order_backends(&backends, &mut rand::thread_rng()))
+    }
+
+    /// Forgets any cached addresses for the SRV name, so that the next lookup
+    /// goes to the DNS servers.
+    pub fn invalidate(&self, srv: &crate::names::SRV) {
+        self.cache.lock().unwrap().remove(srv);
+    }
+
+    /// Calls `f` with the address of each backend of `service`, in the order
+    /// given by [`Resolver::lookup_all_socket_v6`], until one call succeeds.
+    ///
+    /// If every backend fails, the cached addresses of the service are
+    /// forgotten, so that later calls see any changes to its DNS records.
+    pub async fn try_backends<T, E, F, Fut>(
+        &self,
+        service: crate::names::ServiceName,
+        mut f: F,
+    ) -> Result<T, BackendError<E>>
+    where
+        F: FnMut(SocketAddrV6) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let srv = crate::names::SRV::Service(service);
+        let addresses = self.lookup_all_socket_v6(srv.clone()).await?;
+        let mut errors = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            match f(address).await {
+                Ok(value) => return Ok(value),
+                Err(e) => errors.push((address, e)),
+            }
+        }
+        self.invalidate(&srv);
+        Err(BackendError::AllBackendsFailed { srv, errors })
+    }
+
+    async fn lookup_backends(
+        &self,
+        srv: &crate::names::SRV,
+    ) -> Result<Vec<Backend>, ResolveError> {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(srv)
+            .filter(|cached| cached.valid_until > Instant::now())
+            .map(|cached| cached.backends.clone());
+        if let Some(backends) = cached {
+            return Ok(backends);
+        }
+
+        let response = self.inner.srv_lookup(&srv.to_string()).await?;
+        let mut valid_until = response.as_lookup().valid_until();
+
+        // The DNS server includes the AAAA records for the SRV targets in the
+        // additional section, though the resolver may not keep all of them.
+        // Look up any targets it didn't.
+        let mut target_addrs: HashMap<Name, Vec<Ipv6Addr>> = HashMap::new();
+        for record in response.as_lookup().record_iter() {
+            if let Some(RData::AAAA(address)) = record.data() {
+                target_addrs
+                    .entry(record.name().clone())
+                    .or_default()
+                    .push(*address);
+            }
+        }
+        let mut backends = Vec::new();
+        for record in response.iter() {
+            let target = record.target();
+            if !target_addrs.contains_key(target) {
+                let addresses =
+                    match self.inner.ipv6_lookup(target.clone()).await {
+                        Ok(lookup) => {
+                            valid_until = valid_until.min(lookup.valid_until());
+                            lookup.iter().copied().collect()
+                        }
+                        // The target may have been removed since the SRV record
+                        // was written.
+                        Err(e)
+                            if matches!(
+                                e.kind(),
+                                ResolveErrorKind::NoRecordsFound { .. }
+                            ) =>
+                        {
+                            Vec::new()
+                        }
+                        Err(e) => return Err(e.into()),
+                    };
+                target_addrs.insert(target.clone(), addresses);
+            }
+            backends.extend(target_addrs[target].iter().map(|address| {
+                Backend {
+                    priority: record.priority(),
+                    weight: record.weight(),
+                    address: SocketAddrV6::new(*address, record.port(), 0, 0),
+                }
+            }));
+        }
+
+        self.cache.lock().unwrap().insert(
+            srv.clone(),
+            CachedBackends { backends: backends.clone(), valid_until },
+        );
+        Ok(backends)
+    }
+}
+
+/// Orders backends as RFC 2782 asks clients to try them.
+///
+/// Backends are tried in order of increasing priority. Among backends with the
+/// same priority, each next backend is picked at random from the remaining
+/// ones, with a probability proportional to its weight. Backends with zero
+/// weight have a very small chance of being picked before the others.
+fn order_backends<R: Rng>(
+    backends: &[Backend],
+    rng: &mut R,
+) -> Vec<SocketAddrV6> {
+    let mut by_priority: BTreeMap<u16, Vec<&Backend>> = BTreeMap::new();
+    for backend in backends {
+        by_priority.entry(backend.priority).or_default().push(backend);
     }
+
+    let mut ordered = Vec::with_capacity(backends.len());
+    for (_, mut group) in by_priority {
+        group.sort_by_key(|backend| backend.weight != 0);
+        while !group.is_empty() {
+            let total: u32 =
+                group.iter().map(|backend| u32::from(backend.weight)).sum();
+            let choice = rng.gen_range(0..=total);
+            let mut running_sum = 0;
+            let index = group
+                .iter()
+                .position(|backend| {
+                    running_sum += u32::from(backend.weight);
+                    running_sum >= choice
+                })
+                .expect("running sum should reach the total weight");
+            ordered.push(group.remove(index).address);
+        }
+    }
+    ordered
 }
 
 #[cfg(test)]
@@ -250,6 +464,8 @@ mod test {
     use super::*;
     use crate::names::{BackendName, ServiceName, AAAA, SRV};
     use omicron_test_utils::dev::test_setup_log;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     use std::str::FromStr;
     use std::sync::Arc;
     use tempfile::TempDir;
@@ -288,7 +504,7 @@ mod test {
                 },
                 dropshot: dropshot::ConfigDropshot {
                     bind_address: "[::1]:0".parse().unwrap(),
-                    request_body_max_bytes: 1024,
+                    request_body_max_bytes: 16384,
                     ..Default::default()
                 },
                 data: internal_dns::dns_data::Config {
@@ -540,6 +756,246 @@ mod test {
         logctx.cleanup_successful();
     }
 
+    // Services with many backends have SRV records that don't fit in a UDP
+    // response, which the resolver must retry over TCP.
+    #[tokio::test]
+    async fn lookup_many_records() {
+        let logctx = test_setup_log("lookup_many_records");
+        let dns_server = DnsServer::create(&logctx.log).await;
+
+        let mut address_getter = LocalAddressGetter::default();
+        address_getter.add_dns_server(
+            dns_server.dns_server_address(),
+            dns_server.dropshot_server_address(),
+        );
+
+        let resolver = Resolver::new(&address_getter)
+            .expect("Error creating localhost resolver");
+        let updater = Updater::new(&address_getter, logctx.log.clone());
+
+        let srv_crdb = SRV::Service(ServiceName::Cockroach);
+        let addrs = (0..32)
+            .map(|i| {
+                (
+                    AAAA::Zone(Uuid::new_v4()),
+                    SocketAddrV6::new(
+                        Ipv6Addr::new(0xff, 0, 0, 0, 0, 0, 0, i),
+                        32345,
+                        0,
+                        0,
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+        let records = HashMap::from([(srv_crdb.clone(), addrs.clone())]);
+        updater.insert_dns_records(&records).await.unwrap();
+
+        let ip = resolver
+            .lookup_ipv6(srv_crdb)
+            .await
+            .expect("Should have been able to look up IP address");
+        assert!(addrs.iter().any(|(_, addr)| addr.ip() == &ip));
+
+        logctx.cleanup_successful();
+    }
+
+    #[tokio::test]
+    async fn replace_zone() {
+        let logctx = test_setup_log("replace_zone");
+        let dns_server = DnsServer::create(&logctx.log).await;
+
+        let mut address_getter = LocalAddressGetter::default();
+        address_getter.add_dns_server(
+            dns_server.dns_server_address(),
+            dns_server.dropshot_server_address(),
+        );
+
+        let resolver = Resolver::new(&address_getter)
+            .expect("Error creating localhost resolver");
+        let updater = Updater::new(&address_getter, logctx.log.clone());
+
+        let srv_crdb = SRV::Service(ServiceName::Cockroach);
+        let aaaa = AAAA::Zone(Uuid::new_v4());
+        let address = Ipv6Addr::from_str("ff::01").unwrap();
+        let update = crate::types::DnsZoneUpdate {
+            generation: 1,
+            records: vec![
+                DnsKv {
+                    key: DnsRecordKey { name: aaaa.to_string() },
+                    records: vec![DnsRecord::Aaaa(address)],
+                },
+                DnsKv {
+                    key: DnsRecordKey { name: srv_crdb.to_string() },
+                    records: vec![DnsRecord::Srv(Srv {
+                        prio: 0,
+                        weight: 0,
+                        port: 12345,
+                        target: aaaa.to_string(),
+                    })],
+                },
+            ],
+        };
+        updater.dns_zone_replace(&update).await.unwrap();
+        let ip = resolver
+            .lookup_ipv6(srv_crdb.clone())
+            .await
+            .expect("Should have been able to look up IP address");
+        assert_eq!(ip, address);
+
+        // Replacing the zone with an older generation fails, and doesn't
+        // remove the records.
+        let stale_update =
+            crate::types::DnsZoneUpdate { generation: 0, records: vec![] };
+        updater
+            .dns_zone_replace(&stale_update)
+            .await
+            .expect_err("Should not have replaced zone at older generation");
+        let ip = resolver
+            .lookup_ipv6(srv_crdb)
+            .await
+            .expect("Should have been able to look up IP address");
+        assert_eq!(ip, address);
+
+        logctx.cleanup_successful();
+    }
+
+    // Lookups of sockets use the ports of the SRV records, and backends can be
+    // tried in turn until one works.
+    #[tokio::test]
+    async fn lookup_sockets_and_try_backends() {
+        let logctx = test_setup_log("lookup_sockets_and_try_backends");
+        let dns_server = DnsServer::create(&logctx.log).await;
+
+        let mut address_getter = LocalAddressGetter::default();
+        address_getter.add_dns_server(
+            dns_server.dns_server_address(),
+            dns_server.dropshot_server_address(),
+        );
+
+        let resolver = Resolver::new(&address_getter)
+            .expect("Error creating localhost resolver");
+        let updater = Updater::new(&address_getter, logctx.log.clone());
+
+        let srv_crdb = SRV::Service(ServiceName::Cockroach);
+        let cockroach_addrs = (1..=3)
+            .map(|i| {
+                SocketAddrV6::new(
+                    Ipv6Addr::new(0xff, 0, 0, 0, 0, 0, 0, i),
+                    1110 + i,
+                    0,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let records = HashMap::from([(
+            srv_crdb.clone(),
+            cockroach_addrs
+                .iter()
+                .map(|addr| (AAAA::Zone(Uuid::new_v4()), *addr))
+                .collect(),
+        )]);
+        updater.insert_dns_records(&records).await.unwrap();
+
+        let addr = resolver
+            .lookup_socket_v6(srv_crdb.clone())
+            .await
+            .expect("Should have been able to look up socket address");
+        assert!(cockroach_addrs.contains(&addr));
+
+        let mut addrs = resolver
+            .lookup_all_socket_v6(srv_crdb.clone())
+            .await
+            .expect("Should have been able to look up socket addresses");
+        addrs.sort();
+        assert_eq!(addrs, cockroach_addrs);
+
+        // Only the last backend works, but we should find it.
+        let working = cockroach_addrs[2];
+        let addr = resolver
+            .try_backends(ServiceName::Cockroach, |addr| async move {
+                if addr == working {
+                    Ok(addr)
+                } else {
+                    Err("backend is down")
+                }
+            })
+            .await
+            .expect("Should have found a working backend");
+        assert_eq!(addr, working);
+
+        // If no backend works, we should see every failure.
+        let err = resolver
+            .try_backends(ServiceName::Cockroach, |_| async {
+                Err::<(), _>("backend is down")
+            })
+            .await
+            .expect_err("Should not have found a working backend");
+        match err {
+            BackendError::AllBackendsFailed { srv, errors } => {
+                assert_eq!(srv, srv_crdb);
+                assert_eq!(errors.len(), cockroach_addrs.len());
+            }
+            _ => panic!("Unexpected error: {err}"),
+        }
+
+        logctx.cleanup_successful();
+    }
+
+    fn backend(priority: u16, weight: u16, last_octet: u16) -> Backend {
+        Backend {
+            priority,
+            weight,
+            address: SocketAddrV6::new(
+                Ipv6Addr::new(0xff, 0, 0, 0, 0, 0, 0, last_octet),
+                12345,
+                0,
+                0,
+            ),
+        }
+    }
+
+    #[test]
+    fn order_backends_by_priority() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let backends = [
+            backend(2, 10, 1),
+            backend(0, 10, 2),
+            backend(1, 10, 3),
+            backend(0, 10, 4),
+        ];
+        for _ in 0..100 {
+            let ordered = order_backends(&backends, &mut rng);
+            assert_eq!(ordered.len(), backends.len());
+            let mut first = ordered[..2].to_vec();
+            first.sort();
+            assert_eq!(first, vec![backends[1].address, backends[3].address]);
+            assert_eq!(ordered[2], backends[2].address);
+            assert_eq!(ordered[3], backends[0].address);
+        }
+    }
+
+    #[test]
+    fn order_backends_by_weight() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let backends = [backend(0, 1, 1), backend(0, 99, 2), backend(0, 0, 3)];
+        let ntrials = 1000;
+        let mut nfirst = [0; 3];
+        for _ in 0..ntrials {
+            let ordered = order_backends(&backends, &mut rng);
+            assert_eq!(ordered.len(), backends.len());
+            let index = backends
+                .iter()
+                .position(|backend| backend.address == ordered[0])
+                .unwrap();
+            nfirst[index] += 1;
+        }
+
+        // The heavy backend should usually come first, and the others rarely.
+        assert!(nfirst[1] > ntrials * 9 / 10, "{:?}", nfirst);
+        assert!(nfirst[0] < ntrials / 10, "{:?}", nfirst);
+        assert!(nfirst[2] < ntrials / 10, "{:?}", nfirst);
+    }
+
     #[tokio::test]
     async fn update_record() {
         let logctx = test_setup_log("update_record");
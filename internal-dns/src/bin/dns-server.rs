@@ -13,7 +13,7 @@
 use anyhow::anyhow;
 use anyhow::Context;
 use clap::Parser;
-use std::net::{SocketAddr, SocketAddrV6};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -23,10 +23,13 @@ struct Args {
     config_file: PathBuf,
 
     #[clap(long, action)]
-    server_address: SocketAddrV6,
+    server_address: SocketAddr,
 
+    /// The address to answer DNS queries on. Servers for the control plane's
+    /// internal zone listen on the underlay network, while servers for an
+    /// external zone may listen on any IPv4 or IPv6 address.
     #[clap(long, action)]
-    dns_address: SocketAddrV6,
+    dns_address: SocketAddr,
 
     #[clap(long, action)]
     dns_zone: String,
@@ -44,7 +47,7 @@ async fn main() -> Result<(), anyhow::Error> {
         toml::from_str(&config_file_contents)
             .with_context(|| format!("parse config file {:?}", config_file))?;
 
-    config.dropshot.bind_address = SocketAddr::V6(args.server_address);
+    config.dropshot.bind_address = args.server_address;
     eprintln!("{:?}", config);
 
     let log = config
@@ -9,7 +9,7 @@ use internal_dns_client::{
     Client,
 };
 use slog::{Drain, Logger};
-use std::net::Ipv6Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 #[derive(Debug, Parser)]
 #[clap(name = "dnsadm", about = "Administer DNS records")]
@@ -27,11 +27,20 @@ struct Opt {
 #[derive(Debug, Subcommand)]
 enum SubCommand {
     ListRecords,
+    AddA(AddACommand),
     AddAAAA(AddAAAACommand),
     AddSRV(AddSRVCommand),
     DeleteRecord(DeleteRecordCommand),
 }
 
+#[derive(Debug, Args)]
+struct AddACommand {
+    #[clap(action)]
+    name: String,
+    #[clap(action)]
+    addr: Ipv4Addr,
+}
+
 #[derive(Debug, Args)]
 struct AddAAAACommand {
     #[clap(action)]
@@ -79,6 +88,14 @@ async fn main() -> Result<()> {
             let records = client.dns_records_list().await?;
             println!("{:#?}", records);
         }
+        SubCommand::AddA(cmd) => {
+            client
+                .dns_records_create(&vec![DnsKv {
+                    key: DnsRecordKey { name: cmd.name },
+                    records: vec![DnsRecord::A(cmd.addr)],
+                }])
+                .await?;
+        }
         SubCommand::AddAAAA(cmd) => {
             client
                 .dns_records_create(&vec![DnsKv {
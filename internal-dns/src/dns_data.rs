@@ -7,8 +7,11 @@
 use anyhow::Context;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
 use slog::{error, info, o, trace};
-use std::net::Ipv6Addr;
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
 
 /// Configuration related to data model
@@ -24,6 +27,13 @@ pub struct Config {
 /// default maximum number of messages to buffer
 const NMAX_MESSAGES_DEFAULT: usize = 16;
 
+/// Name of the sled tree holding data about the zone as a whole, such as its
+/// generation. The records themselves live in the default tree, keyed by name.
+const META_TREE: &str = "meta";
+
+/// Key of the zone's generation number in the meta tree
+const KEY_GENERATION: &[u8] = b"generation";
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -46,6 +56,7 @@ pub struct SRV {
 #[derive(Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(tag = "type", content = "data")]
 pub enum DnsRecord {
+    A(Ipv4Addr),
     AAAA(Ipv6Addr),
     SRV(SRV),
 }
@@ -53,6 +64,14 @@ pub enum DnsRecord {
 pub struct DnsRecordKey {
     name: String,
 }
+
+impl DnsRecordKey {
+    /// The key under which this name's records are stored. DNS names are
+    /// compared without regard to case, so names are stored in lowercase.
+    fn db_key(&self) -> String {
+        self.name.to_ascii_lowercase()
+    }
+}
 #[derive(Debug)]
 pub struct DnsResponse<T> {
     tx: tokio::sync::oneshot::Sender<T>,
@@ -65,6 +84,61 @@ pub struct DnsKV {
     records: Vec<DnsRecord>,
 }
 
+/// The complete contents of the zone at a particular generation
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DnsZoneUpdate {
+    /// generation number of these contents, which must be no older than the
+    /// zone's current generation
+    pub generation: u64,
+    /// every record in the zone; names not listed here are removed
+    pub records: Vec<DnsKV>,
+}
+
+/// The generation of the zone's current contents
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DnsZoneGeneration {
+    pub generation: u64,
+}
+
+/// Errors replacing the contents of the zone
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error(
+        "zone is at generation {current}, which is newer than the requested \
+        generation {requested}"
+    )]
+    StaleGeneration { current: u64, requested: u64 },
+
+    #[error(
+        "zone is already at generation {generation} with different contents"
+    )]
+    ConflictingContents { generation: u64 },
+
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+/// Returns the generation of the zone stored in `db`.
+///
+/// A new zone is at generation 0. Replacing the zone moves it to the requested
+/// generation, and every update to individual records bumps it by one, so the
+/// generation changes whenever the contents do.
+pub fn zone_generation(db: &sled::Db) -> Result<u64, anyhow::Error> {
+    let meta = db.open_tree(META_TREE).context("open meta tree")?;
+    read_generation(meta.get(KEY_GENERATION).context("get generation")?)
+}
+
+fn read_generation(bits: Option<sled::IVec>) -> Result<u64, anyhow::Error> {
+    match bits {
+        None => Ok(0),
+        Some(bits) => {
+            let bytes = <[u8; 8]>::try_from(bits.as_ref())
+                .context("generation is not a u64")?;
+            Ok(u64::from_be_bytes(bytes))
+        }
+    }
+}
+
 // XXX some refactors to help
 // - each variant should have its own struct containing the data.  This way we
 //   can pass it to functions as a bundle without them having to consume the
@@ -79,6 +153,8 @@ pub enum DnsCmd {
     Get(Option<DnsRecordKey>, DnsResponse<Vec<DnsKV>>),
     Set(Vec<DnsKV>, DnsResponse<()>),
     Delete(Vec<DnsRecordKey>, DnsResponse<()>),
+    ReplaceZone(DnsZoneUpdate, DnsResponse<Result<(), UpdateError>>),
+    GetGeneration(DnsResponse<Result<u64, anyhow::Error>>),
 }
 
 /// Data model client
@@ -145,6 +221,39 @@ impl Client {
             .context("send message")?;
         rx.await.context("recv response")
     }
+
+    /// Atomically replace every record in the zone, moving it to the update's
+    /// generation.
+    ///
+    /// Fails if the zone is already at a newer generation, or at the same
+    /// generation with different contents. Replacing the zone with exactly the
+    /// contents it already has at that generation is allowed, so that retrying
+    /// an update is harmless.
+    pub async fn replace_zone(
+        &self,
+        update: DnsZoneUpdate,
+    ) -> Result<(), UpdateError> {
+        slog::trace!(
+            &self.log,
+            "replace_zone";
+            "generation" => update.generation,
+            "records" => ?update.records,
+        );
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.sender
+            .try_send(DnsCmd::ReplaceZone(update, DnsResponse { tx }))
+            .context("send message")?;
+        rx.await.context("recv response")?
+    }
+
+    pub async fn get_generation(&self) -> Result<u64, anyhow::Error> {
+        slog::trace!(&self.log, "get_generation");
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.sender
+            .try_send(DnsCmd::GetGeneration(DnsResponse { tx }))
+            .context("send message")?;
+        rx.await.context("recv response")?
+    }
 }
 
 /// Runs the body of the data model server event loop
@@ -171,6 +280,12 @@ async fn data_server(mut server: Server) {
             DnsCmd::Delete(records, response) => {
                 server.cmd_delete_records(records, response).await;
             }
+            DnsCmd::ReplaceZone(update, response) => {
+                server.cmd_replace_zone(update, response).await;
+            }
+            DnsCmd::GetGeneration(response) => {
+                server.cmd_get_generation(response).await;
+            }
         }
     }
 }
@@ -191,7 +306,7 @@ impl Server {
         // If a key is provided search just for that key. Otherwise return all
         // the db entries.
         if let Some(key) = key {
-            let bits = match self.db.get(key.name.as_bytes()) {
+            let bits = match self.db.get(key.db_key()) {
                 Ok(Some(bits)) => bits,
                 _ => {
                     match response.tx.send(Vec::new()) {
@@ -291,33 +406,17 @@ impl Server {
         records: Vec<DnsKV>,
         response: DnsResponse<()>,
     ) {
-        for kv in records {
-            let bits = match serde_json::to_string(&kv.records) {
-                Ok(bits) => bits,
-                Err(e) => {
-                    error!(self.log, "serialize record: {}", e);
-                    match response.tx.send(()) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            error!(self.log, "response tx: {:?}", e);
-                        }
-                    }
-                    return;
-                }
-            };
-            match self.db.insert(kv.key.name.as_bytes(), bits.as_bytes()) {
-                Ok(_) => {}
-                Err(e) => {
-                    error!(self.log, "db insert: {}", e);
-                    match response.tx.send(()) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            error!(self.log, "response tx: {:?}", e);
-                        }
-                    }
-                    return;
-                }
-            }
+        let result = records
+            .iter()
+            .map(|kv| {
+                let bits = serde_json::to_vec(&kv.records)
+                    .context("serialize records")?;
+                Ok((kv.key.db_key(), bits))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()
+            .and_then(|inserts| self.update_records(inserts, Vec::new()));
+        if let Err(e) = result {
+            error!(self.log, "set records: {:#}", e);
         }
         match response.tx.send(()) {
             Ok(_) => {}
@@ -332,22 +431,138 @@ impl Server {
         records: Vec<DnsRecordKey>,
         response: DnsResponse<()>,
     ) {
-        for k in records {
-            match self.db.remove(k.name.as_bytes()) {
-                Ok(_) => {}
-                Err(e) => {
-                    error!(self.log, "db delete: {}", e);
-                    match response.tx.send(()) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            error!(self.log, "response tx: {:?}", e);
-                        }
-                    }
-                    return;
+        let removes = records.iter().map(|k| k.db_key()).collect();
+        if let Err(e) = self.update_records(Vec::new(), removes) {
+            error!(self.log, "delete records: {:#}", e);
+        }
+        match response.tx.send(()) {
+            Ok(_) => {}
+            Err(e) => {
+                error!(self.log, "response tx: {:?}", e);
+            }
+        }
+    }
+
+    /// Insert and remove individual names, bumping the zone's generation in
+    /// the same transaction so that the change shows up in its SOA serial.
+    fn update_records(
+        &self,
+        inserts: Vec<(String, Vec<u8>)>,
+        removes: Vec<String>,
+    ) -> Result<(), anyhow::Error> {
+        let meta = self.db.open_tree(META_TREE).context("open meta tree")?;
+
+        let mut batch = sled::Batch::default();
+        for key in removes {
+            batch.remove(key.as_bytes());
+        }
+        for (key, bits) in inserts {
+            batch.insert(key.as_bytes(), bits);
+        }
+
+        let generation = (&**self.db, &meta)
+            .transaction(|(records, meta)| {
+                let current = read_generation(meta.get(KEY_GENERATION)?)
+                    .map_err(ConflictableTransactionError::Abort)?;
+                let generation = current + 1;
+                records.apply_batch(&batch)?;
+                meta.insert(KEY_GENERATION, &generation.to_be_bytes())?;
+                Ok(generation)
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => {
+                    anyhow::Error::new(e).context("db")
                 }
+            })?;
+        trace!(self.log, "updated records"; "generation" => generation);
+        Ok(())
+    }
+
+    async fn cmd_replace_zone(
+        &self,
+        update: DnsZoneUpdate,
+        response: DnsResponse<Result<(), UpdateError>>,
+    ) {
+        let result = self.replace_zone(update);
+        if let Err(e) = &result {
+            error!(self.log, "replace zone: {:#}", e);
+        }
+        match response.tx.send(result) {
+            Ok(_) => {}
+            Err(e) => {
+                error!(self.log, "response tx: {:?}", e);
             }
         }
-        match response.tx.send(()) {
+    }
+
+    fn replace_zone(&self, update: DnsZoneUpdate) -> Result<(), UpdateError> {
+        let meta = self.db.open_tree(META_TREE).context("open meta tree")?;
+
+        // Build the new contents of the zone up front, removing every name
+        // that exists now and inserting the new ones. Commands are handled one
+        // at a time, so the zone can't change before we apply this.
+        let mut batch = sled::Batch::default();
+        let mut current_contents = BTreeMap::new();
+        for entry in self.db.iter() {
+            let (key, bits) = entry.context("db iteration")?;
+            batch.remove(key.clone());
+            current_contents.insert(key.to_vec(), bits.to_vec());
+        }
+        let mut contents = BTreeMap::new();
+        for kv in &update.records {
+            let bits =
+                serde_json::to_vec(&kv.records).context("serialize records")?;
+            batch.insert(kv.key.db_key().as_bytes(), bits.clone());
+            contents.insert(kv.key.db_key().into_bytes(), bits);
+        }
+        // Servers at the same generation must have the same contents, so the
+        // current generation may only be replayed with identical records.
+        let unchanged = contents == current_contents;
+
+        let requested = update.generation;
+        (&**self.db, &meta)
+            .transaction(|(records, meta)| {
+                let current = read_generation(meta.get(KEY_GENERATION)?)
+                    .map_err(|e| {
+                        ConflictableTransactionError::Abort(e.into())
+                    })?;
+                if requested < current {
+                    return Err(ConflictableTransactionError::Abort(
+                        UpdateError::StaleGeneration { current, requested },
+                    ));
+                }
+                if requested == current && !unchanged {
+                    return Err(ConflictableTransactionError::Abort(
+                        UpdateError::ConflictingContents {
+                            generation: current,
+                        },
+                    ));
+                }
+                records.apply_batch(&batch)?;
+                meta.insert(KEY_GENERATION, &requested.to_be_bytes())?;
+                Ok(())
+            })
+            .map_err(|e| match e {
+                TransactionError::Abort(e) => e,
+                TransactionError::Storage(e) => {
+                    UpdateError::Internal(anyhow::Error::new(e).context("db"))
+                }
+            })?;
+        info!(
+            self.log,
+            "replaced zone";
+            "generation" => requested,
+            "names" => update.records.len(),
+        );
+        Ok(())
+    }
+
+    async fn cmd_get_generation(
+        &self,
+        response: DnsResponse<Result<u64, anyhow::Error>>,
+    ) {
+        match response.tx.send(zone_generation(&self.db)) {
             Ok(_) => {}
             Err(e) => {
                 error!(self.log, "response tx: {:?}", e);
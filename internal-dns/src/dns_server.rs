@@ -2,27 +2,32 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::collections::BTreeSet;
 use std::io::Result;
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use crate::dns_data::DnsRecord;
+use crate::dns_data::{zone_generation, DnsRecord};
+use anyhow::Context;
 use pretty_hex::*;
 use serde::Deserialize;
-use slog::{error, Logger};
-use tokio::net::UdpSocket;
-use trust_dns_client::rr::LowerName;
+use slog::{debug, error, o, trace, Logger};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::time::{timeout, Duration};
+use trust_dns_proto::error::ProtoError;
 use trust_dns_proto::op::header::Header;
+use trust_dns_proto::op::message::Message;
+use trust_dns_proto::op::op_code::OpCode;
+use trust_dns_proto::op::query::Query;
 use trust_dns_proto::op::response_code::ResponseCode;
-use trust_dns_proto::rr::rdata::SRV;
+use trust_dns_proto::op::Edns;
+use trust_dns_proto::rr::dns_class::DNSClass;
+use trust_dns_proto::rr::rdata::{SOA, SRV};
 use trust_dns_proto::rr::record_data::RData;
 use trust_dns_proto::rr::record_type::RecordType;
 use trust_dns_proto::rr::{Name, Record};
-use trust_dns_proto::serialize::binary::{
-    BinDecodable, BinDecoder, BinEncoder,
-};
-use trust_dns_server::authority::{MessageRequest, MessageResponseBuilder};
 
 /// Configuration related to the DNS server
 #[derive(Deserialize, Debug, Clone)]
@@ -34,227 +39,450 @@ pub struct Config {
     pub zone: String,
 }
 
+/// The largest DNS message, limited by the two-byte length prefix used over
+/// TCP.
+const MAX_MESSAGE_SIZE: usize = u16::MAX as usize;
+
+/// The largest UDP response we send to clients that don't use EDNS to
+/// advertise their own limit (RFC 1035 section 4.2.1).
+const MAX_UDP_PAYLOAD_NO_EDNS: u16 = 512;
+
+/// The UDP payload size we advertise to clients that use EDNS.
+const MAX_UDP_PAYLOAD_EDNS: u16 = 4096;
+
+/// How long we keep an idle TCP connection open, waiting for another request.
+/// RFC 7766 recommends that servers default to a few seconds.
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct Server {
     pub address: SocketAddr,
     pub handle: tokio::task::JoinHandle<Result<()>>,
+    pub tcp_handle: tokio::task::JoinHandle<Result<()>>,
 }
 
 impl Drop for Server {
     fn drop(&mut self) {
-        self.handle.abort()
+        self.handle.abort();
+        self.tcp_handle.abort();
     }
 }
 
+/// Run the DNS server, answering requests over both UDP and TCP on the
+/// configured address.
 pub async fn run(
     log: Logger,
     db: Arc<sled::Db>,
     config: Config,
 ) -> Result<Server> {
+    let zone = Name::from_str(&config.zone).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid zone '{}': {}", config.zone, e),
+        )
+    })?;
     let socket = Arc::new(UdpSocket::bind(config.bind_address).await?);
     let address = socket.local_addr()?;
 
-    let handle = tokio::task::spawn(async move {
-        loop {
-            let mut buf = vec![0u8; 16384];
-            let (n, src) = socket.recv_from(&mut buf).await?;
-            buf.resize(n, 0);
+    // Bind the TCP listener to the UDP socket's address, so that both use the
+    // same port even if the OS picked it.
+    let listener = TcpListener::bind(address).await?;
+
+    let handle = {
+        let log = log.clone();
+        let db = db.clone();
+        let zone = zone.clone();
+        tokio::task::spawn(async move {
+            loop {
+                let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
+                let (n, src) = socket.recv_from(&mut buf).await?;
+                buf.truncate(n);
+
+                let socket = socket.clone();
+                let log = log.new(o!("src" => src.to_string()));
+                let db = db.clone();
+                let zone = zone.clone();
 
-            let socket = socket.clone();
-            let log = log.clone();
+                tokio::spawn(async move {
+                    handle_req(log, db, socket, src, buf, zone).await
+                });
+            }
+        })
+    };
+
+    let tcp_handle = tokio::task::spawn(async move {
+        loop {
+            let (stream, src) = listener.accept().await?;
+            let log = log.new(o!("src" => src.to_string(), "tcp" => true));
             let db = db.clone();
-            let zone = config.zone.clone();
+            let zone = zone.clone();
 
             tokio::spawn(async move {
-                handle_req(log, db, socket, src, buf, zone).await
+                if let Err(e) = handle_tcp_conn(&log, db, stream, zone).await {
+                    debug!(log, "closing TCP connection"; "error" => ?e);
+                }
             });
         }
     });
 
-    Ok(Server { address, handle })
+    Ok(Server { address, handle, tcp_handle })
 }
 
-async fn respond_nxdomain(
-    log: &Logger,
+async fn handle_req(
+    log: Logger,
+    db: Arc<sled::Db>,
     socket: Arc<UdpSocket>,
     src: SocketAddr,
-    rb: MessageResponseBuilder<'_>,
-    header: Header,
-    mr: &MessageRequest,
+    buf: Vec<u8>,
+    zone: Name,
 ) {
-    let mresp = rb.error_msg(&header, ResponseCode::NXDomain);
-    let mut resp_data = Vec::new();
-    let mut enc = BinEncoder::new(&mut resp_data);
-    match mresp.destructive_emit(&mut enc) {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "NXDOMAIN destructive emit: {}", e);
-            nack(&log, &mr, &socket, &header, &src).await;
-            return;
-        }
-    }
-    match socket.send_to(&resp_data, &src).await {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "NXDOMAIN send: {}", e);
-        }
+    let (request, response) = match handle_message(&log, &db, &zone, &buf) {
+        Some(exchange) => exchange,
+        None => return,
+    };
+    let resp_data =
+        match encode_response(&log, response, request.max_payload().into()) {
+            Ok(resp_data) => resp_data,
+            Err(e) => {
+                error!(log, "failed to encode response"; "error" => ?e);
+                return;
+            }
+        };
+    trace!(log, "sending response"; "bytes" => ?resp_data.hex_dump());
+    if let Err(e) = socket.send_to(&resp_data, &src).await {
+        error!(log, "failed to send response"; "error" => ?e);
     }
 }
 
-async fn handle_req<'a, 'b, 'c>(
-    log: Logger,
+/// Answer the requests sent over a TCP connection, until the client closes it
+/// or stays idle for too long.
+///
+/// Each message is preceded by its length as a two-byte, big-endian integer
+/// (RFC 1035 section 4.2.2). Requests are answered in the order received.
+async fn handle_tcp_conn(
+    log: &Logger,
     db: Arc<sled::Db>,
-    socket: Arc<UdpSocket>,
-    src: SocketAddr,
-    buf: Vec<u8>,
-    zone: String,
-) {
-    println!("{:?}", buf.hex_dump());
+    mut stream: TcpStream,
+    zone: Name,
+) -> Result<()> {
+    loop {
+        let len = match timeout(TCP_IDLE_TIMEOUT, stream.read_u16()).await {
+            Ok(Ok(len)) => usize::from(len),
+            // The client closed the connection between requests.
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                trace!(log, "closing idle TCP connection");
+                return Ok(());
+            }
+        };
+        let mut buf = vec![0u8; len];
+        match timeout(TCP_IDLE_TIMEOUT, stream.read_exact(&mut buf)).await {
+            Ok(result) => result?,
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    e,
+                ))
+            }
+        };
 
-    let mut dec = BinDecoder::new(&buf);
-    let mr = match MessageRequest::read(&mut dec) {
-        Ok(mr) => mr,
+        let response = match handle_message(log, &db, &zone, &buf) {
+            Some((_, response)) => response,
+            // We can't answer a malformed message, and can't trust the framing
+            // of anything that follows it.
+            None => return Ok(()),
+        };
+        let resp_data = encode_response(log, response, MAX_MESSAGE_SIZE)
+            .map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+            })?;
+        trace!(log, "sending response"; "bytes" => ?resp_data.hex_dump());
+        let len = u16::try_from(resp_data.len())
+            .expect("encoded response should fit in a DNS message");
+        stream.write_u16(len).await?;
+        stream.write_all(&resp_data).await?;
+    }
+}
+
+/// Parse a request and build its response, returning both.
+///
+/// Returns `None` if the request can't be parsed.
+fn handle_message(
+    log: &Logger,
+    db: &sled::Db,
+    zone: &Name,
+    buf: &[u8],
+) -> Option<(Message, Message)> {
+    trace!(log, "received request"; "bytes" => ?buf.hex_dump());
+
+    let request = match Message::from_vec(buf) {
+        Ok(request) => request,
         Err(e) => {
-            error!(log, "read message: {}", e);
-            return;
+            error!(log, "failed to parse request"; "error" => ?e);
+            return None;
         }
     };
+    debug!(
+        log,
+        "received request";
+        "id" => request.id(),
+        "op_code" => ?request.op_code(),
+        "queries" => ?request.queries(),
+    );
 
-    println!("{:#?}", mr);
-
-    let header = Header::response_from_request(mr.header());
-    let zone = LowerName::from(Name::from_str(&zone).unwrap());
+    let mut response = build_response(log, db, zone, &request);
+    if request.extensions().is_some() {
+        let mut edns = Edns::new();
+        edns.set_max_payload(MAX_UDP_PAYLOAD_EDNS);
+        response.set_edns(edns);
+    }
+    debug!(
+        log,
+        "sending response";
+        "id" => response.id(),
+        "response_code" => %response.response_code(),
+        "answers" => response.answers().len(),
+        "additionals" => response.additionals().len(),
+    );
+    Some((request, response))
+}
 
-    // Ensure the query is for this zone, otherwise bail with servfail. This
-    // will cause resolvers to look to other DNS servers for this query.
-    let name = mr.query().name();
-    if !zone.zone_of(name) {
-        nack(&log, &mr, &socket, &header, &src).await;
-        return;
+/// Encode a response, truncating it if needed to fit in `max_size` bytes.
+///
+/// The additional section only holds glue the client could look up itself,
+/// so it's dropped first, without setting the TC bit (RFC 2181 section 9).
+/// If the response is still too big, the answers are dropped as well and the
+/// TC bit is set, telling the client to retry over TCP.
+fn encode_response(
+    log: &Logger,
+    mut response: Message,
+    max_size: usize,
+) -> std::result::Result<Vec<u8>, ProtoError> {
+    let max_size = max_size.max(MAX_UDP_PAYLOAD_NO_EDNS.into());
+    let resp_data = response.to_vec()?;
+    if resp_data.len() <= max_size {
+        return Ok(resp_data);
     }
 
-    let name = mr.query().original().name().clone();
-    let key = name.to_string();
-    let key = key.trim_end_matches('.');
+    response.take_additionals();
+    let resp_data = response.to_vec()?;
+    if resp_data.len() <= max_size {
+        debug!(
+            log,
+            "dropped additional records from response";
+            "max_size" => max_size,
+        );
+        return Ok(resp_data);
+    }
 
-    let rb = MessageResponseBuilder::from_message_request(&mr);
+    response.take_answers();
+    response.take_name_servers();
+    response.set_truncated(true);
+    debug!(log, "truncated response"; "max_size" => max_size);
+    response.to_vec()
+}
 
-    let bits = match db.get(key.as_bytes()) {
-        Ok(Some(bits)) => bits,
+/// Build the response to a DNS request.
+///
+/// Each question is answered with the records of its name that match the
+/// requested type, or all of them for `ANY`. A question for a name that exists
+/// but has no records of the requested type gets an empty answer (NODATA).
+/// The A and AAAA records for the targets of any SRV answers are included in
+/// the additional section, so that clients need not look them up separately.
+/// Names are matched without regard to case (RFC 4343).
+///
+/// The zone's apex always exists, and holds its SOA record. Responses without
+/// any answers carry the SOA record in the authority section, as RFC 2308 asks
+/// of negative responses.
+///
+/// Messages that aren't standard queries are rejected with NOTIMP, and messages
+/// without any questions with FORMERR. If any question is outside our zone, or
+/// we fail to look up its records, the whole request fails with SERVFAIL, which
+/// causes resolvers to look to other DNS servers. NXDOMAIN is only returned if
+/// none of the questions' names exist.
+fn build_response(
+    log: &Logger,
+    db: &sled::Db,
+    zone: &Name,
+    request: &Message,
+) -> Message {
+    let mut header = Header::response_from_request(request.header());
+    header.set_authoritative(true);
+    let mut response = Message::new();
+    response.set_header(header);
+    response.add_queries(request.queries().iter().cloned());
 
-        // If no record is found bail with NXDOMAIN.
-        Ok(None) => {
-            respond_nxdomain(&log, socket, src, rb, header, &mr).await;
-            return;
-        }
+    if request.op_code() != OpCode::Query {
+        response.set_response_code(ResponseCode::NotImp);
+        return response;
+    }
+    if request.queries().is_empty() {
+        response.set_response_code(ResponseCode::FormErr);
+        return response;
+    }
 
-        // If we encountered an error bail with SERVFAIL.
+    let soa = match soa_record(db, zone) {
+        Ok(soa) => soa,
         Err(e) => {
-            error!(log, "db get: {}", e);
-            nack(&log, &mr, &socket, &header, &src).await;
-            return;
+            error!(log, "failed to build SOA record"; "error" => ?e);
+            return servfail(request);
         }
     };
 
-    let records: Vec<crate::dns_data::DnsRecord> =
-        match serde_json::from_slice(bits.as_ref()) {
-            Ok(r) => r,
+    let mut any_name_exists = false;
+    let mut glue_targets = BTreeSet::new();
+    for query in request.queries() {
+        if !zone.zone_of(query.name()) {
+            debug!(log, "query outside of zone"; "name" => %query.name());
+            return servfail(request);
+        }
+        let is_apex = query.name() == zone;
+        let mut answers = match answer_query(db, query) {
+            Ok(Some(answers)) => answers,
+            Ok(None) if is_apex => Vec::new(),
+            Ok(None) => continue,
             Err(e) => {
-                error!(log, "deserialize record: {}", e);
-                nack(&log, &mr, &socket, &header, &src).await;
-                return;
+                error!(
+                    log,
+                    "failed to look up records";
+                    "name" => %query.name(),
+                    "error" => ?e,
+                );
+                return servfail(request);
             }
         };
+        any_name_exists = true;
+        if is_apex
+            && matches!(query.query_type(), RecordType::SOA | RecordType::ANY)
+            && matches!(query.query_class(), DNSClass::IN | DNSClass::ANY)
+        {
+            answers.insert(0, soa.clone());
+        }
+        for answer in answers {
+            if let Some(RData::SRV(srv)) = answer.data() {
+                glue_targets.insert(srv.target().clone());
+            }
+            response.add_answer(answer);
+        }
+    }
+    if response.answers().is_empty() {
+        response.add_name_server(soa);
+    }
+    if !any_name_exists {
+        response.set_response_code(ResponseCode::NXDomain);
+        return response;
+    }
+
+    // Glue records are a convenience for the client, so failing to find them
+    // isn't fatal.
+    for target in glue_targets {
+        if !zone.zone_of(&target) {
+            continue;
+        }
+        for record_type in [RecordType::A, RecordType::AAAA] {
+            let query = Query::query(target.clone(), record_type);
+            match answer_query(db, &query) {
+                Ok(Some(glue)) => {
+                    response.add_additionals(glue);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!(
+                        log,
+                        "failed to look up SRV target";
+                        "name" => %query.name(),
+                        "error" => ?e,
+                    );
+                }
+            }
+        }
+    }
+    response
+}
+
+/// Build the zone's SOA record, whose serial number is the generation of the
+/// zone's contents.
+fn soa_record(db: &sled::Db, zone: &Name) -> anyhow::Result<Record> {
+    let generation = zone_generation(db)?;
+    // Serial numbers are compared using sequence space arithmetic (RFC 1982),
+    // so it's fine for them to wrap around.
+    let serial = generation as u32;
+
+    // The refresh, retry and expire timers only matter to secondary servers,
+    // which we don't have. The minimum is the TTL of negative responses, which
+    // we don't want cached any more than our records, whose TTL is zero.
+    let mname = Name::from_ascii("ns")?.append_domain(zone)?;
+    let rname = Name::from_ascii("hostmaster")?.append_domain(zone)?;
+    let soa = SOA::new(mname, rname, serial, 3600, 600, 18000, 0);
 
+    let mut record = Record::new();
+    record
+        .set_name(zone.clone())
+        .set_rr_type(RecordType::SOA)
+        .set_data(Some(RData::SOA(soa)));
+    Ok(record)
+}
+
+fn servfail(request: &Message) -> Message {
+    let mut response = Message::new();
+    response
+        .set_header(Header::response_from_request(request.header()))
+        .set_response_code(ResponseCode::ServFail)
+        .add_queries(request.queries().iter().cloned());
+    response
+}
+
+/// Return the records answering a single question, or `None` if the name in
+/// the question doesn't exist.
+fn answer_query(
+    db: &sled::Db,
+    query: &Query,
+) -> anyhow::Result<Option<Vec<Record>>> {
+    let name = query.name();
+    let key = name.to_lowercase().to_string();
+    let key = key.trim_end_matches('.');
+
+    let bits = match db.get(key.as_bytes()).context("db get")? {
+        Some(bits) => bits,
+        None => return Ok(None),
+    };
+    let records: Vec<DnsRecord> =
+        serde_json::from_slice(bits.as_ref()).context("deserialize record")?;
     if records.is_empty() {
-        error!(log, "No records found for {}", key);
-        respond_nxdomain(&log, socket, src, rb, header, &mr).await;
-        return;
+        return Ok(None);
     }
 
-    let mut response_records: Vec<Record> = vec![];
+    if !matches!(query.query_class(), DNSClass::IN | DNSClass::ANY) {
+        return Ok(Some(vec![]));
+    }
+    let mut answers = Vec::new();
     for record in &records {
-        let resp = match record {
-            DnsRecord::AAAA(addr) => {
-                let mut aaaa = Record::new();
-                aaaa.set_name(name.clone())
-                    .set_rr_type(RecordType::AAAA)
-                    .set_data(Some(RData::AAAA(*addr)));
-                aaaa
-            }
+        let rdata = match record {
+            DnsRecord::A(addr) => RData::A(*addr),
+            DnsRecord::AAAA(addr) => RData::AAAA(*addr),
             DnsRecord::SRV(crate::dns_data::SRV {
                 prio,
                 weight,
                 port,
                 target,
             }) => {
-                let mut srv = Record::new();
-                let tgt = match Name::from_str(&target) {
-                    Ok(tgt) => tgt,
-                    Err(e) => {
-                        error!(log, "srv target: '{}' {}", target, e);
-                        nack(&log, &mr, &socket, &header, &src).await;
-                        return;
-                    }
-                };
-                srv.set_name(name.clone())
-                    .set_rr_type(RecordType::SRV)
-                    .set_data(Some(RData::SRV(SRV::new(
-                        *prio, *weight, *port, tgt,
-                    ))));
-                srv
+                let target = Name::from_str(target)
+                    .with_context(|| format!("srv target: '{}'", target))?;
+                RData::SRV(SRV::new(*prio, *weight, *port, target))
             }
         };
-        response_records.push(resp);
-    }
-
-    let mresp = rb.build(
-        header,
-        response_records.iter().collect::<Vec<&Record>>(),
-        vec![],
-        vec![],
-        vec![],
-    );
-
-    let mut resp_data = Vec::new();
-    let mut enc = BinEncoder::new(&mut resp_data);
-    match mresp.destructive_emit(&mut enc) {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "destructive emit: {}", e);
-            nack(&log, &mr, &socket, &header, &src).await;
-            return;
-        }
-    }
-    match socket.send_to(&resp_data, &src).await {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "send: {}", e);
-        }
-    }
-}
-
-async fn nack(
-    log: &Logger,
-    mr: &MessageRequest,
-    socket: &UdpSocket,
-    header: &Header,
-    src: &SocketAddr,
-) {
-    let rb = MessageResponseBuilder::from_message_request(mr);
-    let mresp = rb.error_msg(header, ResponseCode::ServFail);
-    let mut resp_data = Vec::new();
-    let mut enc = BinEncoder::new(&mut resp_data);
-    match mresp.destructive_emit(&mut enc) {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "destructive emit: {}", e);
-            return;
-        }
-    }
-    match socket.send_to(&resp_data, &src).await {
-        Ok(_) => {}
-        Err(e) => {
-            error!(log, "destructive emit: {}", e);
+        let query_type = query.query_type();
+        if query_type == RecordType::ANY || query_type == rdata.to_record_type()
+        {
+            let mut answer = Record::new();
+            answer
+                .set_name(name.clone())
+                .set_rr_type(rdata.to_record_type())
+                .set_data(Some(rdata));
+            answers.push(answer);
         }
     }
+    Ok(Some(answers))
 }
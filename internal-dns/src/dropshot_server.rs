@@ -4,7 +4,9 @@
 
 //! Dropshot server for configuring DNS namespace
 
-use crate::dns_data::{self, DnsKV, DnsRecordKey};
+use crate::dns_data::{
+    self, DnsKV, DnsRecordKey, DnsZoneGeneration, DnsZoneUpdate, UpdateError,
+};
 use dropshot::endpoint;
 use std::sync::Arc;
 
@@ -24,6 +26,8 @@ pub fn api() -> dropshot::ApiDescription<Arc<Context>> {
     api.register(dns_records_list).expect("register dns_records_list");
     api.register(dns_records_create).expect("register dns_records_create");
     api.register(dns_records_delete).expect("register dns_records_delete");
+    api.register(dns_zone_replace).expect("register dns_zone_replace");
+    api.register(dns_zone_generation).expect("register dns_zone_generation");
     api
 }
 
@@ -71,3 +75,50 @@ async fn dns_records_delete(
     })?;
     Ok(dropshot::HttpResponseDeleted())
 }
+
+// Requests with a generation older than the zone's current one, or with the
+// current generation but different records, are rejected with 409 Conflict.
+#[endpoint(
+    method = PUT,
+    path = "/zone",
+)]
+async fn dns_zone_replace(
+    rqctx: Arc<dropshot::RequestContext<Arc<Context>>>,
+    rq: dropshot::TypedBody<DnsZoneUpdate>,
+) -> Result<dropshot::HttpResponseUpdatedNoContent, dropshot::HttpError> {
+    let apictx = rqctx.context();
+    apictx.client.replace_zone(rq.into_inner()).await.map_err(|e| match e {
+        UpdateError::StaleGeneration { .. } => {
+            dropshot::HttpError::for_client_error(
+                Some(String::from("StaleGeneration")),
+                http::StatusCode::CONFLICT,
+                e.to_string(),
+            )
+        }
+        UpdateError::ConflictingContents { .. } => {
+            dropshot::HttpError::for_client_error(
+                Some(String::from("ConflictingContents")),
+                http::StatusCode::CONFLICT,
+                e.to_string(),
+            )
+        }
+        UpdateError::Internal(e) => {
+            dropshot::HttpError::for_internal_error(format!("{:#}", e))
+        }
+    })?;
+    Ok(dropshot::HttpResponseUpdatedNoContent())
+}
+
+#[endpoint(
+    method = GET,
+    path = "/zone/generation",
+)]
+async fn dns_zone_generation(
+    rqctx: Arc<dropshot::RequestContext<Arc<Context>>>,
+) -> Result<dropshot::HttpResponseOk<DnsZoneGeneration>, dropshot::HttpError> {
+    let apictx = rqctx.context();
+    let generation = apictx.client.get_generation().await.map_err(|e| {
+        dropshot::HttpError::for_internal_error(format!("{:#}", e))
+    })?;
+    Ok(dropshot::HttpResponseOk(DnsZoneGeneration { generation }))
+}
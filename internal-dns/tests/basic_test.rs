@@ -2,16 +2,21 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::net::Ipv6Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::Result;
 use dropshot::test_util::LogContext;
 use internal_dns_client::{
-    types::{DnsKv, DnsRecord, DnsRecordKey, Srv},
+    types::{DnsKv, DnsRecord, DnsRecordKey, DnsZoneUpdate, Srv},
     Client,
 };
 use omicron_test_utils::dev::test_setup_log;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use trust_dns_proto::op::{Edns, Message, MessageType, OpCode, Query};
+use trust_dns_proto::rr::{Name, RData, RecordType};
 use trust_dns_resolver::error::ResolveErrorKind;
 use trust_dns_resolver::TokioAsyncResolver;
 use trust_dns_resolver::{
@@ -288,6 +293,516 @@ pub async fn servfail() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test]
+pub async fn answers_match_query_type() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("answers_match_query_type", "oxide.internal".into())
+            .await?;
+    let client = &test_ctx.client;
+    let address = test_ctx.dns_server.address;
+
+    // Store both an AAAA and a SRV record under the same name.
+    let name = "mixed.oxide.internal";
+    let addr = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x1);
+    let srv = Srv { prio: 1, weight: 2, port: 3, target: "outpost47".into() };
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: name.into() },
+            records: vec![DnsRecord::Aaaa(addr), DnsRecord::Srv(srv)],
+        }])
+        .await?;
+
+    // Each query should only be answered with records of the requested type.
+    for query_type in [RecordType::AAAA, RecordType::SRV] {
+        let response = raw_query(address, &[(name, query_type)]).await?;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.authoritative());
+        assert_eq!(response.answers().len(), 1);
+        assert_eq!(response.answers()[0].record_type(), query_type);
+    }
+
+    // ANY should return everything stored for the name.
+    let response = raw_query(address, &[(name, RecordType::ANY)]).await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    let mut types = response
+        .answers()
+        .iter()
+        .map(|record| record.record_type())
+        .collect::<Vec<_>>();
+    types.sort();
+    assert_eq!(types, vec![RecordType::AAAA, RecordType::SRV]);
+
+    // Asking for a type the name doesn't have is NODATA, not NXDOMAIN.
+    for query_type in [RecordType::A, RecordType::TXT] {
+        let response = raw_query(address, &[(name, query_type)]).await?;
+        assert_eq!(response.response_code(), ResponseCode::NoError);
+        assert!(response.answers().is_empty());
+    }
+
+    // The resolver should only see the AAAA record when asking for addresses.
+    let response = test_ctx.resolver.ipv6_lookup(format!("{}.", name)).await?;
+    assert_eq!(response.iter().collect::<Vec<_>>(), vec![&addr]);
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn a_records() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("a_records", "oxide.example".into()).await?;
+    let client = &test_ctx.client;
+    let resolver = &test_ctx.resolver;
+    let address = test_ctx.dns_server.address;
+
+    // Names are stored in lowercase, whatever case they were given in.
+    let addr_v4 = Ipv4Addr::new(192, 0, 2, 1);
+    let addr_v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x1);
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: "Eng.sys.oxide.example".into() },
+            records: vec![DnsRecord::A(addr_v4), DnsRecord::Aaaa(addr_v6)],
+        }])
+        .await?;
+    let records = client.dns_records_list().await?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].key.name, "eng.sys.oxide.example");
+
+    // Queries are answered without regard to the case of the name.
+    let response = resolver.ipv4_lookup("ENG.sys.oxide.example.").await?;
+    assert_eq!(response.iter().collect::<Vec<_>>(), vec![&addr_v4]);
+    let response = resolver.ipv6_lookup("eng.Sys.oxide.example.").await?;
+    assert_eq!(response.iter().collect::<Vec<_>>(), vec![&addr_v6]);
+    let response =
+        raw_query(address, &[("eng.SYS.oxide.example", RecordType::A)]).await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 1);
+    assert_eq!(response.answers()[0].data(), Some(&RData::A(addr_v4)));
+
+    // SRV answers carry both kinds of address for their targets.
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: "_https._tcp.oxide.example".into() },
+            records: vec![DnsRecord::Srv(Srv {
+                prio: 0,
+                weight: 0,
+                port: 443,
+                target: "eng.sys.oxide.example".into(),
+            })],
+        }])
+        .await?;
+    let response =
+        raw_query(address, &[("_https._tcp.oxide.example", RecordType::SRV)])
+            .await?;
+    let mut glue = response
+        .additionals()
+        .iter()
+        .map(|record| record.record_type())
+        .collect::<Vec<_>>();
+    glue.sort();
+    assert_eq!(glue, vec![RecordType::A, RecordType::AAAA]);
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn srv_includes_aaaa_glue() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("srv_includes_aaaa_glue", "oxide.internal".into())
+            .await?;
+    let client = &test_ctx.client;
+
+    let target = "db.oxide.internal";
+    let addr1 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x1);
+    let addr2 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x2);
+    let service = "_db._tcp.oxide.internal";
+    let srv = Srv { prio: 0, weight: 0, port: 5432, target: target.into() };
+    client
+        .dns_records_create(&vec![
+            DnsKv {
+                key: DnsRecordKey { name: target.into() },
+                records: vec![DnsRecord::Aaaa(addr1), DnsRecord::Aaaa(addr2)],
+            },
+            DnsKv {
+                key: DnsRecordKey { name: service.into() },
+                records: vec![DnsRecord::Srv(srv)],
+            },
+        ])
+        .await?;
+
+    // The answer should only contain the SRV record, with the addresses of
+    // its target in the additional section.
+    let response =
+        raw_query(test_ctx.dns_server.address, &[(service, RecordType::SRV)])
+            .await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 1);
+    assert_eq!(response.answers()[0].record_type(), RecordType::SRV);
+    let glue = response
+        .additionals()
+        .iter()
+        .map(|record| {
+            assert_eq!(record.name().to_string(), format!("{}.", target));
+            match record.data() {
+                Some(RData::AAAA(addr)) => *addr,
+                other => panic!("expected AAAA glue, found {:?}", other),
+            }
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(glue, vec![addr1, addr2]);
+
+    // The resolver should use the glue to find the service's addresses.
+    let response = test_ctx.resolver.srv_lookup(service).await?;
+    assert_eq!(
+        response.ip_iter().collect::<Vec<_>>(),
+        vec![IpAddr::V6(addr1), IpAddr::V6(addr2)]
+    );
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn multiple_questions() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("multiple_questions", "oxide.internal".into())
+            .await?;
+    let client = &test_ctx.client;
+    let address = test_ctx.dns_server.address;
+
+    let name = "devron.oxide.internal";
+    let addr = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x1);
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: name.into() },
+            records: vec![DnsRecord::Aaaa(addr)],
+        }])
+        .await?;
+
+    // Each question is answered, and the message as a whole succeeds as long
+    // as one of the names exists.
+    let response = raw_query(
+        address,
+        &[
+            (name, RecordType::AAAA),
+            ("unicorn.oxide.internal", RecordType::AAAA),
+        ],
+    )
+    .await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.queries().len(), 2);
+    assert_eq!(response.answers().len(), 1);
+
+    let response = raw_query(
+        address,
+        &[
+            ("unicorn.oxide.internal", RecordType::AAAA),
+            ("pegasus.oxide.internal", RecordType::AAAA),
+        ],
+    )
+    .await?;
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+
+    // Any question outside the zone fails the whole message.
+    let response = raw_query(
+        address,
+        &[(name, RecordType::AAAA), ("oxide.computer", RecordType::AAAA)],
+    )
+    .await?;
+    assert_eq!(response.response_code(), ResponseCode::ServFail);
+    assert!(response.answers().is_empty());
+
+    // A message without any questions is malformed.
+    let response = raw_query(address, &[]).await?;
+    assert_eq!(response.response_code(), ResponseCode::FormErr);
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn zone_replace() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("zone_replace", "oxide.internal".into()).await?;
+    let client = &test_ctx.client;
+    let resolver = &test_ctx.resolver;
+    let address = test_ctx.dns_server.address;
+
+    // A new zone starts at generation 0.
+    assert_eq!(client.dns_zone_generation().await?.generation, 0);
+
+    let zone_update =
+        |generation: u64, name: &str, addr: Ipv6Addr| DnsZoneUpdate {
+            generation,
+            records: vec![DnsKv {
+                key: DnsRecordKey { name: name.into() },
+                records: vec![DnsRecord::Aaaa(addr)],
+            }],
+        };
+    let addr1 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x1);
+    let addr2 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x2);
+
+    client
+        .dns_zone_replace(&zone_update(2, "devron.oxide.internal", addr1))
+        .await?;
+    assert_eq!(client.dns_zone_generation().await?.generation, 2);
+    let response = resolver.lookup_ip("devron.oxide.internal.").await?;
+    assert_eq!(response.iter().collect::<Vec<_>>(), vec![IpAddr::V6(addr1)]);
+
+    // Older generations are rejected, and leave the zone alone.
+    let err = client
+        .dns_zone_replace(&zone_update(1, "hromi.oxide.internal", addr2))
+        .await
+        .expect_err("replacing the zone with an older generation");
+    match err {
+        internal_dns_client::Error::ErrorResponse(rv) => {
+            assert_eq!(rv.status().as_u16(), 409);
+        }
+        _ => panic!("unexpected error: {:?}", err),
+    }
+    assert_eq!(client.dns_zone_generation().await?.generation, 2);
+    lookup_ip_expect_nxdomain(resolver, "hromi.oxide.internal").await;
+
+    // Newer generations replace every record in the zone. Replaying the same
+    // generation is allowed.
+    for _ in 0..2 {
+        client
+            .dns_zone_replace(&zone_update(3, "hromi.oxide.internal", addr2))
+            .await?;
+    }
+    assert_eq!(client.dns_zone_generation().await?.generation, 3);
+    let records = client.dns_records_list().await?;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].key.name, "hromi.oxide.internal");
+    lookup_ip_expect_nxdomain(resolver, "devron.oxide.internal").await;
+
+    // The same generation with different records is rejected too, so that
+    // servers at the same generation never have different contents.
+    let err = client
+        .dns_zone_replace(&zone_update(3, "devron.oxide.internal", addr1))
+        .await
+        .expect_err("replacing the zone with different contents");
+    match err {
+        internal_dns_client::Error::ErrorResponse(rv) => {
+            assert_eq!(rv.status().as_u16(), 409);
+            assert_eq!(rv.error_code.as_deref(), Some("ConflictingContents"));
+        }
+        _ => panic!("unexpected error: {:?}", err),
+    }
+    lookup_ip_expect_nxdomain(resolver, "devron.oxide.internal").await;
+
+    // Updates to individual records bump the generation.
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: "devron.oxide.internal".into() },
+            records: vec![DnsRecord::Aaaa(addr1)],
+        }])
+        .await?;
+    assert_eq!(client.dns_zone_generation().await?.generation, 4);
+    client
+        .dns_records_delete(&vec![DnsRecordKey {
+            name: "devron.oxide.internal".into(),
+        }])
+        .await?;
+    assert_eq!(client.dns_zone_generation().await?.generation, 5);
+
+    // The generation is the serial number of the zone's SOA record, which is
+    // also included in negative responses.
+    let response =
+        raw_query(address, &[("oxide.internal", RecordType::SOA)]).await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 1);
+    match response.answers()[0].data() {
+        Some(RData::SOA(soa)) => assert_eq!(soa.serial(), 5),
+        other => panic!("expected SOA record, found {:?}", other),
+    }
+    let response =
+        raw_query(address, &[("devron.oxide.internal", RecordType::AAAA)])
+            .await?;
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+    assert_eq!(response.name_servers().len(), 1);
+    assert_eq!(response.name_servers()[0].record_type(), RecordType::SOA);
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn large_response_uses_tcp() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("large_response_uses_tcp", "oxide.internal".into())
+            .await?;
+    let client = &test_ctx.client;
+    let address = test_ctx.dns_server.address;
+
+    // Create a service with enough backends that its SRV records won't fit in
+    // a 512-byte UDP response.
+    let service = "_crucible._tcp.oxide.internal";
+    let nbackends = 64;
+    let mut srvs = Vec::new();
+    for i in 0..nbackends {
+        let target = format!("crucible-{}.oxide.internal", i);
+        client
+            .dns_records_create(&vec![DnsKv {
+                key: DnsRecordKey { name: target.clone() },
+                records: vec![DnsRecord::Aaaa(Ipv6Addr::new(
+                    0xfd, 0, 0, 0, 0, 0, 0, i,
+                ))],
+            }])
+            .await?;
+        srvs.push(DnsRecord::Srv(Srv {
+            prio: 0,
+            weight: 0,
+            port: 32345,
+            target,
+        }));
+    }
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: service.into() },
+            records: srvs,
+        }])
+        .await?;
+
+    // Over UDP, the response is truncated to an empty answer.
+    let response = raw_query(address, &[(service, RecordType::SRV)]).await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(response.truncated());
+    assert!(response.answers().is_empty());
+    assert!(response.additionals().is_empty());
+
+    // Over TCP, the whole response fits, glue and all.
+    let response =
+        raw_tcp_query(address, &[(service, RecordType::SRV)]).await?;
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert!(!response.truncated());
+    assert_eq!(response.answers().len(), usize::from(nbackends));
+    assert_eq!(response.additionals().len(), usize::from(nbackends));
+
+    // The resolver should retry over TCP when it sees the TC bit.
+    let response = test_ctx.resolver.srv_lookup(service).await?;
+    assert_eq!(response.iter().count(), usize::from(nbackends));
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn edns_payload_size() -> Result<(), anyhow::Error> {
+    let test_ctx =
+        init_client_server("edns_payload_size", "oxide.internal".into())
+            .await?;
+    let client = &test_ctx.client;
+    let address = test_ctx.dns_server.address;
+
+    // Each of these SRV records takes around 30 bytes on the wire, so together
+    // they need more than 512 bytes but less than 4096.
+    let service = "_crucible._tcp.oxide.internal";
+    let nbackends = 32;
+    let srvs = (0..nbackends)
+        .map(|i| {
+            DnsRecord::Srv(Srv {
+                prio: 0,
+                weight: 0,
+                port: 32345,
+                target: format!("crucible-{}.oxide.internal", i),
+            })
+        })
+        .collect();
+    client
+        .dns_records_create(&vec![DnsKv {
+            key: DnsRecordKey { name: service.into() },
+            records: srvs,
+        }])
+        .await?;
+
+    let response = raw_query(address, &[(service, RecordType::SRV)]).await?;
+    assert!(response.truncated());
+    assert!(response.extensions().is_none());
+
+    // Clients advertising a larger payload size with EDNS get the full
+    // response, along with our own payload size.
+    let mut request = query_message(&[(service, RecordType::SRV)])?;
+    let mut edns = Edns::new();
+    edns.set_max_payload(4096);
+    request.set_edns(edns);
+    let response = send_udp(address, &request).await?;
+    assert!(!response.truncated());
+    assert_eq!(response.answers().len(), nbackends);
+    assert_eq!(
+        response.extensions().as_ref().map(|edns| edns.max_payload()),
+        Some(4096)
+    );
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
+/// Build a standard query with the given questions.
+fn query_message(
+    questions: &[(&str, RecordType)],
+) -> Result<Message, anyhow::Error> {
+    let mut request = Message::new();
+    request.set_id(4747).set_op_code(OpCode::Query);
+    for (name, query_type) in questions {
+        request.add_query(Query::query(Name::from_str(name)?, *query_type));
+    }
+    Ok(request)
+}
+
+/// Send a message with the given questions directly to the DNS server, and
+/// return its response.
+async fn raw_query(
+    address: SocketAddr,
+    questions: &[(&str, RecordType)],
+) -> Result<Message, anyhow::Error> {
+    send_udp(address, &query_message(questions)?).await
+}
+
+async fn send_udp(
+    address: SocketAddr,
+    request: &Message,
+) -> Result<Message, anyhow::Error> {
+    let socket = UdpSocket::bind("[::1]:0").await?;
+    socket.send_to(&request.to_vec()?, address).await?;
+    let mut buf = vec![0u8; 4096];
+    let n = tokio::time::timeout(
+        tokio::time::Duration::from_secs(5),
+        socket.recv(&mut buf),
+    )
+    .await??;
+    let response = Message::from_vec(&buf[..n])?;
+    assert_eq!(response.id(), request.id());
+    assert_eq!(response.message_type(), MessageType::Response);
+    Ok(response)
+}
+
+/// Like [`raw_query`], but over TCP.
+async fn raw_tcp_query(
+    address: SocketAddr,
+    questions: &[(&str, RecordType)],
+) -> Result<Message, anyhow::Error> {
+    let request = query_message(questions)?;
+    let request_data = request.to_vec()?;
+
+    let mut stream = TcpStream::connect(address).await?;
+    stream.write_u16(u16::try_from(request_data.len())?).await?;
+    stream.write_all(&request_data).await?;
+    let response_data =
+        tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+            let len = stream.read_u16().await?;
+            let mut buf = vec![0u8; usize::from(len)];
+            stream.read_exact(&mut buf).await?;
+            Ok::<_, std::io::Error>(buf)
+        })
+        .await??;
+    let response = Message::from_vec(&response_data)?;
+    assert_eq!(response.id(), request.id());
+    assert_eq!(response.message_type(), MessageType::Response);
+    Ok(response)
+}
+
 struct TestContext {
     client: Client,
     resolver: TokioAsyncResolver,
@@ -332,13 +847,15 @@ async fn init_client_server(
     };
 
     let mut rc = ResolverConfig::new();
-    rc.add_name_server(NameServerConfig {
-        socket_addr: dns_server.address,
-        protocol: Protocol::Udp,
-        tls_dns_name: None,
-        trust_nx_responses: false,
-        bind_addr: None,
-    });
+    for protocol in [Protocol::Udp, Protocol::Tcp] {
+        rc.add_name_server(NameServerConfig {
+            socket_addr: dns_server.address,
+            protocol,
+            tls_dns_name: None,
+            trust_nx_responses: false,
+            bind_addr: None,
+        });
+    }
 
     let resolver =
         TokioAsyncResolver::tokio(rc, ResolverOpts::default()).unwrap();
@@ -379,7 +896,7 @@ fn test_config(
         },
         dropshot: dropshot::ConfigDropshot {
             bind_address: format!("[::1]:0").parse().unwrap(),
-            request_body_max_bytes: 1024,
+            request_body_max_bytes: 16384,
             ..Default::default()
         },
         data: internal_dns::dns_data::Config {
@@ -25,6 +25,7 @@ impl From<types::DiskState> for omicron_common::api::external::DiskState {
     fn from(s: types::DiskState) -> Self {
         match s {
             types::DiskState::Creating => Self::Creating,
+            types::DiskState::Importing => Self::Importing,
             types::DiskState::Detached => Self::Detached,
             types::DiskState::Attaching(u) => Self::Attaching(u),
             types::DiskState::Attached(u) => Self::Attached(u),
@@ -127,6 +128,7 @@ impl From<omicron_common::api::external::DiskState> for types::DiskState {
         use omicron_common::api::external::DiskState;
         match s {
             DiskState::Creating => Self::Creating,
+            DiskState::Importing => Self::Importing,
             DiskState::Detached => Self::Detached,
             DiskState::Attaching(u) => Self::Attaching(u),
             DiskState::Attached(u) => Self::Attached(u),
@@ -171,6 +173,23 @@ impl From<&omicron_common::api::internal::nexus::ProducerEndpoint>
     }
 }
 
+impl TryFrom<&types::ProducerEndpoint>
+    for omicron_common::api::internal::nexus::ProducerEndpoint
+{
+    type Error = String;
+
+    fn try_from(s: &types::ProducerEndpoint) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: s.id,
+            address: s.address.parse().map_err(|e| {
+                format!("invalid producer address \"{}\": {}", s.address, e)
+            })?,
+            base_route: s.base_route.clone(),
+            interval: s.interval.clone().into(),
+        })
+    }
+}
+
 impl From<omicron_common::api::internal::nexus::UpdateArtifactKind>
     for types::UpdateArtifactKind
 {
@@ -190,3 +209,9 @@ impl From<std::time::Duration> for types::Duration {
         Self { secs: s.as_secs(), nanos: s.subsec_nanos() }
     }
 }
+
+impl From<types::Duration> for std::time::Duration {
+    fn from(s: types::Duration) -> Self {
+        Self::new(s.secs, s.nanos)
+    }
+}
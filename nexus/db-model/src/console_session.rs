@@ -4,6 +4,7 @@
 
 use crate::schema::console_session;
 use chrono::{DateTime, Utc};
+use nexus_types::external_api::views;
 use uuid::Uuid;
 
 // TODO: `struct SessionToken(String)` for session token
@@ -12,6 +13,7 @@ use uuid::Uuid;
 #[diesel(table_name = console_session)]
 pub struct ConsoleSession {
     pub token: String,
+    pub id: Uuid,
     pub time_created: DateTime<Utc>,
     pub time_last_used: DateTime<Utc>,
     pub silo_user_id: Uuid,
@@ -20,10 +22,26 @@ pub struct ConsoleSession {
 impl ConsoleSession {
     pub fn new(token: String, silo_user_id: Uuid) -> Self {
         let now = Utc::now();
-        Self { token, silo_user_id, time_last_used: now, time_created: now }
+        Self {
+            token,
+            id: Uuid::new_v4(),
+            silo_user_id,
+            time_last_used: now,
+            time_created: now,
+        }
     }
 
     pub fn id(&self) -> String {
         self.token.clone()
     }
 }
+
+impl From<ConsoleSession> for views::ConsoleSession {
+    fn from(session: ConsoleSession) -> Self {
+        Self {
+            id: session.id,
+            time_created: session.time_created,
+            time_last_used: session.time_last_used,
+        }
+    }
+}
@@ -7,16 +7,80 @@
 //! [device_auth.rs](nexus/src/app/device_auth.rs) for an overview of how these are
 //! used.
 
+use super::impl_enum_type;
 use crate::schema::{device_access_token, device_auth_request};
 
 use chrono::{DateTime, Duration, Utc};
-use nexus_types::external_api::views;
+use nexus_types::external_api::{shared, views};
 use rand::{distributions::Slice, rngs::StdRng, Rng, RngCore, SeedableRng};
 use uuid::Uuid;
 
 /// Default timeout in seconds for client to authenticate for a token request.
 const CLIENT_AUTHENTICATION_TIMEOUT: i64 = 300;
 
+impl_enum_type!(
+    #[derive(SqlType, Debug, QueryId)]
+    #[diesel(postgres_type(name = "token_scope_action"))]
+    pub struct TokenScopeActionEnum;
+
+    #[derive(Clone, Copy, Debug, AsExpression, FromSqlRow, PartialEq)]
+    #[diesel(sql_type = TokenScopeActionEnum)]
+    pub enum TokenScopeAction;
+
+    // Enum values
+    Read => b"read"
+    ListChildren => b"list_children"
+    Modify => b"modify"
+    CreateChild => b"create_child"
+    Operate => b"operate"
+);
+
+impl From<shared::TokenScopeAction> for TokenScopeAction {
+    fn from(action: shared::TokenScopeAction) -> Self {
+        match action {
+            shared::TokenScopeAction::Read => TokenScopeAction::Read,
+            shared::TokenScopeAction::ListChildren => {
+                TokenScopeAction::ListChildren
+            }
+            shared::TokenScopeAction::Modify => TokenScopeAction::Modify,
+            shared::TokenScopeAction::CreateChild => {
+                TokenScopeAction::CreateChild
+            }
+            shared::TokenScopeAction::Operate => TokenScopeAction::Operate,
+        }
+    }
+}
+
+impl From<TokenScopeAction> for shared::TokenScopeAction {
+    fn from(action: TokenScopeAction) -> Self {
+        match action {
+            TokenScopeAction::Read => shared::TokenScopeAction::Read,
+            TokenScopeAction::ListChildren => {
+                shared::TokenScopeAction::ListChildren
+            }
+            TokenScopeAction::Modify => shared::TokenScopeAction::Modify,
+            TokenScopeAction::CreateChild => {
+                shared::TokenScopeAction::CreateChild
+            }
+            TokenScopeAction::Operate => shared::TokenScopeAction::Operate,
+        }
+    }
+}
+
+/// Assembles a scope from the columns that store it (which are the same for
+/// requests and tokens)
+fn scope_from_columns(
+    actions: &Option<Vec<TokenScopeAction>>,
+    organization_id: Option<Uuid>,
+    project_id: Option<Uuid>,
+) -> Option<shared::TokenScope> {
+    actions.as_ref().map(|actions| shared::TokenScope {
+        actions: actions.iter().map(|a| (*a).into()).collect(),
+        organization_id,
+        project_id,
+    })
+}
+
 /// Initial record of an OAuth 2.0 Device Authorization Grant.
 /// Does *not* include a token; that is only granted after the
 /// `user_code` has been verified and login has succeeded.
@@ -29,6 +93,9 @@ pub struct DeviceAuthRequest {
     pub user_code: String,
     pub time_created: DateTime<Utc>,
     pub time_expires: DateTime<Utc>,
+    pub scope_actions: Option<Vec<TokenScopeAction>>,
+    pub scope_organization_id: Option<Uuid>,
+    pub scope_project_id: Option<Uuid>,
 }
 
 impl DeviceAuthRequest {
@@ -62,7 +129,7 @@ const TOKEN_LENGTH: usize = 20;
 /// Generate a random token/device code.
 // TODO: this should be merged with session::generate_session_token,
 // and probably also the key generation in the disk creation saga.
-fn generate_token() -> String {
+pub(crate) fn generate_token() -> String {
     let mut bytes: [u8; TOKEN_LENGTH] = [0; TOKEN_LENGTH];
     let mut rng = StdRng::from_entropy();
     rng.fill_bytes(&mut bytes);
@@ -100,7 +167,7 @@ fn generate_user_code() -> String {
 }
 
 impl DeviceAuthRequest {
-    pub fn new(client_id: Uuid) -> Self {
+    pub fn new(client_id: Uuid, scope: Option<shared::TokenScope>) -> Self {
         let now = Utc::now();
         Self {
             client_id,
@@ -109,12 +176,28 @@ impl DeviceAuthRequest {
             time_created: now,
             time_expires: now
                 + Duration::seconds(CLIENT_AUTHENTICATION_TIMEOUT),
+            scope_actions: scope.as_ref().map(|scope| {
+                scope.actions.iter().map(|a| (*a).into()).collect()
+            }),
+            scope_organization_id: scope
+                .as_ref()
+                .and_then(|scope| scope.organization_id),
+            scope_project_id: scope.and_then(|scope| scope.project_id),
         }
     }
 
     pub fn id(&self) -> String {
         self.user_code.clone()
     }
+
+    /// Returns the scope requested by the client, if any
+    pub fn scope(&self) -> Option<shared::TokenScope> {
+        scope_from_columns(
+            &self.scope_actions,
+            self.scope_organization_id,
+            self.scope_project_id,
+        )
+    }
 }
 
 /// An access token granted in response to a successful device authorization flow.
@@ -123,12 +206,17 @@ impl DeviceAuthRequest {
 #[diesel(table_name = device_access_token)]
 pub struct DeviceAccessToken {
     pub token: String,
+    pub id: Uuid,
     pub client_id: Uuid,
     pub device_code: String,
     pub silo_user_id: Uuid,
     pub time_requested: DateTime<Utc>,
     pub time_created: DateTime<Utc>,
     pub time_expires: Option<DateTime<Utc>>,
+    pub time_revoked: Option<DateTime<Utc>>,
+    pub scope_actions: Option<Vec<TokenScopeAction>>,
+    pub scope_organization_id: Option<Uuid>,
+    pub scope_project_id: Option<Uuid>,
 }
 
 impl DeviceAccessToken {
@@ -142,12 +230,17 @@ impl DeviceAccessToken {
         assert!(time_requested <= now);
         Self {
             token: generate_token(),
+            id: Uuid::new_v4(),
             client_id,
             device_code,
             silo_user_id,
             time_requested,
             time_created: now,
             time_expires: None,
+            time_revoked: None,
+            scope_actions: None,
+            scope_organization_id: None,
+            scope_project_id: None,
         }
     }
 
@@ -159,6 +252,40 @@ impl DeviceAccessToken {
         self.time_expires = Some(time);
         self
     }
+
+    /// Limits the token to `scope`
+    ///
+    /// If the scope names a Project, it must also name that Project's
+    /// Organization.
+    pub fn scoped(mut self, scope: shared::TokenScope) -> Self {
+        assert!(scope.project_id.is_none() || scope.organization_id.is_some());
+        self.scope_actions = Some(
+            scope.actions.into_iter().map(TokenScopeAction::from).collect(),
+        );
+        self.scope_organization_id = scope.organization_id;
+        self.scope_project_id = scope.project_id;
+        self
+    }
+
+    /// Returns the limits on what this token may be used for, if any
+    pub fn scope(&self) -> Option<shared::TokenScope> {
+        scope_from_columns(
+            &self.scope_actions,
+            self.scope_organization_id,
+            self.scope_project_id,
+        )
+    }
+}
+
+impl From<DeviceAccessToken> for views::DeviceAccessToken {
+    fn from(access_token: DeviceAccessToken) -> Self {
+        Self {
+            id: access_token.id,
+            time_created: access_token.time_created,
+            time_expires: access_token.time_expires,
+            scope: access_token.scope(),
+        }
+    }
 }
 
 impl From<DeviceAccessToken> for views::DeviceAccessTokenGrant {
@@ -30,6 +30,9 @@ pub struct Disk {
     #[diesel(embed)]
     identity: DiskIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     /// child resource generation number, per RFD 192
     rcgen: Generation,
 
@@ -86,6 +89,7 @@ impl Disk {
 
         Ok(Self {
             identity,
+            labels: params.labels.into(),
             rcgen: external::Generation::new().into(),
             project_id,
             volume_id,
@@ -123,6 +127,7 @@ impl Into<external::Disk> for Disk {
             block_size: self.block_size.into(),
             state: self.state().into(),
             device_path,
+            labels: self.labels.0,
         }
     }
 }
@@ -183,6 +188,15 @@ impl DiskRuntimeState {
         }
     }
 
+    pub fn importing(self) -> Self {
+        Self {
+            disk_state: external::DiskState::Importing.label().to_string(),
+            attach_instance_id: None,
+            gen: self.gen.next().into(),
+            time_updated: Utc::now(),
+        }
+    }
+
     pub fn state(&self) -> DiskState {
         // TODO: If we could store disk state in-line, we could avoid the
         // unwrap. Would prefer to parse it as such.
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{BlockSize, ByteCount, Digest};
+use super::{BlockSize, ByteCount, Digest, Labels};
 use crate::schema::global_image;
 use db_macros::Resource;
 use nexus_types::external_api::views;
@@ -25,6 +25,9 @@ pub struct GlobalImage {
     #[diesel(embed)]
     pub identity: GlobalImageIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub volume_id: Uuid,
     pub url: Option<String>,
     pub distribution: String,
@@ -41,6 +44,7 @@ impl From<GlobalImage> for views::GlobalImage {
     fn from(image: GlobalImage) -> Self {
         Self {
             identity: image.identity(),
+            labels: image.labels.0,
             url: image.url,
             distribution: image.distribution,
             version: image.version,
@@ -3,7 +3,10 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::impl_enum_type;
-use crate::schema::{identity_provider, saml_identity_provider};
+use crate::schema::{
+    identity_provider, oidc_identity_provider, saml_identity_provider,
+};
+use chrono::{DateTime, Utc};
 use db_macros::Resource;
 use nexus_types::identity::Resource;
 
@@ -22,12 +25,14 @@ impl_enum_type!(
 
     // Enum values
     Saml => b"saml"
+    Oidc => b"oidc"
 );
 
 impl From<IdentityProviderType> for views::IdentityProviderType {
     fn from(idp_type: IdentityProviderType) -> Self {
         match idp_type {
             IdentityProviderType::Saml => views::IdentityProviderType::Saml,
+            IdentityProviderType::Oidc => views::IdentityProviderType::Oidc,
         }
     }
 }
@@ -85,6 +90,19 @@ pub struct SamlIdentityProvider {
     /// if set, attributes with this name will be considered to denote a user's
     /// group membership, where the values will be the group names.
     pub group_attribute_name: Option<String>,
+
+    /// if set, the idp descriptor was fetched from this url and is
+    /// periodically refreshed from it
+    pub idp_metadata_url: Option<String>,
+
+    /// time of the most recent attempt to refresh the idp descriptor
+    pub time_metadata_refresh_attempted: Option<DateTime<Utc>>,
+
+    /// time at which the idp descriptor was last successfully fetched
+    pub time_metadata_refreshed: Option<DateTime<Utc>>,
+
+    /// error from the most recent refresh attempt, if it failed
+    pub metadata_refresh_error: Option<String>,
 }
 
 impl From<SamlIdentityProvider> for views::SamlIdentityProvider {
@@ -97,6 +115,56 @@ impl From<SamlIdentityProvider> for views::SamlIdentityProvider {
             slo_url: saml_idp.slo_url,
             technical_contact_email: saml_idp.technical_contact_email,
             public_cert: saml_idp.public_cert,
+            metadata_refresh: saml_idp.idp_metadata_url.map(|url| {
+                views::SamlMetadataRefreshStatus {
+                    url,
+                    time_last_attempt: saml_idp.time_metadata_refresh_attempted,
+                    time_last_success: saml_idp.time_metadata_refreshed,
+                    last_error: saml_idp.metadata_refresh_error,
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Queryable, Insertable, Clone, Debug, Selectable, Resource)]
+#[diesel(table_name = oidc_identity_provider)]
+pub struct OidcIdentityProvider {
+    #[diesel(embed)]
+    pub identity: OidcIdentityProviderIdentity,
+
+    pub silo_id: Uuid,
+
+    /// the provider's issuer identifier
+    pub issuer: String,
+
+    /// provider's discovery document, as fetched when the provider was
+    /// created
+    pub discovery_document_string: String,
+
+    /// client id registered with the provider for this silo
+    pub client_id: String,
+
+    /// client secret registered with the provider, if the client is
+    /// confidential
+    pub client_secret: Option<String>,
+
+    /// endpoint where the provider should send the user back after login
+    pub redirect_uri: String,
+
+    /// if set, ID token claims with this name will be considered to denote a
+    /// user's group membership, where the values will be the group names.
+    pub group_claim_name: Option<String>,
+}
+
+impl From<OidcIdentityProvider> for views::OidcIdentityProvider {
+    fn from(oidc_idp: OidcIdentityProvider) -> Self {
+        Self {
+            identity: oidc_idp.identity(),
+            issuer: oidc_idp.issuer,
+            client_id: oidc_idp.client_id,
+            redirect_uri: oidc_idp.redirect_uri,
+            group_claim_name: oidc_idp.group_claim_name,
         }
     }
 }
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{BlockSize, ByteCount, Digest};
+use super::{BlockSize, ByteCount, Digest, Labels};
 use crate::schema::image;
 use db_macros::Resource;
 use nexus_types::external_api::views;
@@ -26,6 +26,9 @@ pub struct Image {
     #[diesel(embed)]
     pub identity: ImageIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub project_id: Uuid,
     pub volume_id: Uuid,
     pub url: Option<String>,
@@ -42,6 +45,7 @@ impl From<Image> for views::Image {
     fn from(image: Image) -> Self {
         Self {
             identity: image.identity(),
+            labels: image.labels.0,
             project_id: image.project_id,
             url: image.url,
             version: image.version,
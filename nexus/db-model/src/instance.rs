@@ -2,7 +2,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{ByteCount, Disk, Generation, InstanceCpuCount, InstanceState};
+use super::{
+    ByteCount, Disk, Generation, InstanceCpuCount, InstanceState, Labels,
+};
 use crate::collection::DatastoreAttachTargetConfig;
 use crate::schema::{disk, instance};
 use chrono::{DateTime, Utc};
@@ -22,6 +24,9 @@ pub struct Instance {
     #[diesel(embed)]
     identity: InstanceIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     /// id for the project containing this Instance
     pub project_id: Uuid,
 
@@ -44,6 +49,7 @@ impl Instance {
             InstanceIdentity::new(instance_id, params.identity.clone());
         Self {
             identity,
+            labels: params.labels.clone().into(),
             project_id,
             user_data: params.user_data.clone(),
             runtime_state: runtime,
@@ -64,6 +70,7 @@ impl Into<external::Instance> for Instance {
             ncpus: self.runtime().ncpus.into(),
             memory: self.runtime().memory.into(),
             hostname: self.runtime().hostname.clone(),
+            labels: self.labels.0,
             runtime: self.runtime().clone().into(),
         }
     }
@@ -0,0 +1,53 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use diesel::backend::RawValue;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, ToSql};
+use diesel::sql_types;
+use omicron_common::api::external;
+use serde::{Deserialize, Serialize};
+
+/// User-defined labels on a resource, stored as a JSON object
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(sql_type = sql_types::Jsonb)]
+#[repr(transparent)]
+pub struct Labels(pub external::Labels);
+
+NewtypeFrom! { () pub struct Labels(external::Labels); }
+NewtypeDeref! { () pub struct Labels(external::Labels); }
+
+impl ToSql<sql_types::Jsonb, Pg> for Labels {
+    fn to_sql<'a>(
+        &'a self,
+        out: &mut serialize::Output<'a, '_, Pg>,
+    ) -> serialize::Result {
+        let value = serde_json::to_value(&self.0)?;
+        <serde_json::Value as ToSql<sql_types::Jsonb, Pg>>::to_sql(
+            &value,
+            &mut out.reborrow(),
+        )
+    }
+}
+
+impl FromSql<sql_types::Jsonb, Pg> for Labels {
+    fn from_sql(bytes: RawValue<Pg>) -> deserialize::Result<Self> {
+        let value =
+            <serde_json::Value as FromSql<sql_types::Jsonb, Pg>>::from_sql(
+                bytes,
+            )?;
+        Ok(Labels(serde_json::from_value(value)?))
+    }
+}
@@ -32,6 +32,7 @@ mod ipv4net;
 mod ipv6;
 mod ipv6net;
 mod l4_port_range;
+mod labels;
 mod macaddr;
 mod name;
 mod network_interface;
@@ -51,6 +52,7 @@ mod role_builtin;
 pub mod saga_types;
 pub mod schema;
 mod service;
+mod service_account;
 mod service_kind;
 mod silo;
 mod silo_group;
@@ -103,6 +105,7 @@ pub use ipv4net::*;
 pub use ipv6::*;
 pub use ipv6net::*;
 pub use l4_port_range::*;
+pub use labels::*;
 pub use name::*;
 pub use network_interface::*;
 pub use organization::*;
@@ -115,6 +118,7 @@ pub use region_snapshot::*;
 pub use role_assignment::*;
 pub use role_builtin::*;
 pub use service::*;
+pub use service_account::*;
 pub use service_kind::*;
 pub use silo::*;
 pub use silo_group::*;
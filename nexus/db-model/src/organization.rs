@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{Generation, Name, Project};
+use super::{Generation, Labels, Name, Project};
 use crate::collection::DatastoreCollectionConfig;
 use crate::schema::{organization, project};
 use chrono::{DateTime, Utc};
@@ -19,6 +19,9 @@ pub struct Organization {
     #[diesel(embed)]
     identity: OrganizationIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub silo_id: Uuid,
 
     /// child resource generation number, per RFD 192
@@ -31,6 +34,7 @@ impl Organization {
         let id = Uuid::new_v4();
         Self {
             identity: OrganizationIdentity::new(id, params.identity),
+            labels: params.labels.into(),
             silo_id,
             rcgen: Generation::new(),
         }
@@ -39,7 +43,7 @@ impl Organization {
 
 impl From<Organization> for views::Organization {
     fn from(org: Organization) -> Self {
-        Self { identity: org.identity() }
+        Self { identity: org.identity(), labels: org.labels.0 }
     }
 }
 
@@ -56,6 +60,7 @@ impl DatastoreCollectionConfig<Project> for Organization {
 pub struct OrganizationUpdate {
     pub name: Option<Name>,
     pub description: Option<String>,
+    pub labels: Option<Labels>,
     pub time_modified: DateTime<Utc>,
 }
 
@@ -64,6 +69,7 @@ impl From<params::OrganizationUpdate> for OrganizationUpdate {
         Self {
             name: params.identity.name.map(|n| n.into()),
             description: params.identity.description,
+            labels: params.labels.map(Labels),
             time_modified: Utc::now(),
         }
     }
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::Name;
+use super::{Labels, Name};
 use crate::schema::project;
 use chrono::{DateTime, Utc};
 use db_macros::Resource;
@@ -18,6 +18,9 @@ pub struct Project {
     #[diesel(embed)]
     identity: ProjectIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub organization_id: Uuid,
 }
 
@@ -26,6 +29,7 @@ impl Project {
     pub fn new(organization_id: Uuid, params: params::ProjectCreate) -> Self {
         Self {
             identity: ProjectIdentity::new(Uuid::new_v4(), params.identity),
+            labels: params.labels.into(),
             organization_id,
         }
     }
@@ -35,6 +39,7 @@ impl From<Project> for views::Project {
     fn from(project: Project) -> Self {
         Self {
             identity: project.identity(),
+            labels: project.labels.0,
             organization_id: project.organization_id,
         }
     }
@@ -46,6 +51,7 @@ impl From<Project> for views::Project {
 pub struct ProjectUpdate {
     pub name: Option<Name>,
     pub description: Option<String>,
+    pub labels: Option<Labels>,
     pub time_modified: DateTime<Utc>,
 }
 
@@ -54,6 +60,7 @@ impl From<params::ProjectUpdate> for ProjectUpdate {
         Self {
             name: params.identity.name.map(Name),
             description: params.identity.description,
+            labels: params.labels.map(Labels),
             time_modified: Utc::now(),
         }
     }
@@ -14,6 +14,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         rcgen -> Int8,
         project_id -> Uuid,
         volume_id -> Uuid,
@@ -36,6 +37,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         project_id -> Uuid,
         volume_id -> Uuid,
         url -> Nullable<Text>,
@@ -54,6 +56,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         volume_id -> Uuid,
         url -> Nullable<Text>,
         distribution -> Text,
@@ -72,6 +75,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
 
         project_id -> Uuid,
         disk_id -> Uuid,
@@ -94,6 +98,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         project_id -> Uuid,
         user_data -> Binary,
         state -> crate::InstanceStateEnum,
@@ -217,6 +222,16 @@ table! {
     }
 }
 
+table! {
+    silo_user_password_hash (silo_user_id) {
+        silo_user_id -> Uuid,
+        hash -> Text,
+        time_created -> Timestamptz,
+        failed_attempts -> Int8,
+        time_locked_until -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     silo_group (id) {
         id -> Uuid,
@@ -274,6 +289,56 @@ table! {
         public_cert -> Nullable<Text>,
         private_key -> Nullable<Text>,
         group_attribute_name -> Nullable<Text>,
+
+        idp_metadata_url -> Nullable<Text>,
+        time_metadata_refresh_attempted -> Nullable<Timestamptz>,
+        time_metadata_refreshed -> Nullable<Timestamptz>,
+        metadata_refresh_error -> Nullable<Text>,
+    }
+}
+
+table! {
+    oidc_identity_provider (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+
+        silo_id -> Uuid,
+
+        issuer -> Text,
+        discovery_document_string -> Text,
+
+        client_id -> Text,
+        client_secret -> Nullable<Text>,
+        redirect_uri -> Text,
+        group_claim_name -> Nullable<Text>,
+    }
+}
+
+table! {
+    service_account (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+        silo_id -> Uuid,
+        organization_id -> Nullable<Uuid>,
+        project_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    service_account_key (id) {
+        id -> Uuid,
+        token -> Text,
+        service_account_id -> Uuid,
+        time_created -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
     }
 }
 
@@ -299,6 +364,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         rcgen -> Int8,
     }
 }
@@ -321,6 +387,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         organization_id -> Uuid,
     }
 }
@@ -363,6 +430,7 @@ table! {
 table! {
     console_session (token) {
         token -> Text,
+        id -> Uuid,
         time_created -> Timestamptz,
         time_last_used -> Timestamptz,
         silo_user_id -> Uuid,
@@ -480,6 +548,7 @@ table! {
         time_created -> Timestamptz,
         time_modified -> Timestamptz,
         time_deleted -> Nullable<Timestamptz>,
+        labels -> Jsonb,
         project_id -> Uuid,
         system_router_id -> Uuid,
         vni -> Int4,
@@ -574,18 +643,26 @@ table! {
         device_code -> Text,
         time_created -> Timestamptz,
         time_expires -> Timestamptz,
+        scope_actions -> Nullable<Array<crate::TokenScopeActionEnum>>,
+        scope_organization_id -> Nullable<Uuid>,
+        scope_project_id -> Nullable<Uuid>,
     }
 }
 
 table! {
     device_access_token (token) {
         token -> Text,
+        id -> Uuid,
         client_id -> Uuid,
         device_code -> Text,
         silo_user_id -> Uuid,
         time_requested -> Timestamptz,
         time_created -> Timestamptz,
         time_expires -> Nullable<Timestamptz>,
+        time_revoked -> Nullable<Timestamptz>,
+        scope_actions -> Nullable<Array<crate::TokenScopeActionEnum>>,
+        scope_organization_id -> Nullable<Uuid>,
+        scope_project_id -> Nullable<Uuid>,
     }
 }
 
@@ -626,12 +703,21 @@ table! {
     }
 }
 
+table! {
+    dns_zone_generation (zone) {
+        zone -> Text,
+        generation -> Int8,
+        time_modified -> Timestamptz,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(ip_pool_range, ip_pool);
 joinable!(ip_pool_range -> ip_pool (ip_pool_id));
 
 allow_tables_to_appear_in_same_query!(
     dataset,
     disk,
+    external_ip,
     instance,
     metric_producer,
     network_interface,
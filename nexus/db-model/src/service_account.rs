@@ -0,0 +1,97 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::device_auth::generate_token;
+use crate::schema::{service_account, service_account_key};
+use chrono::{DateTime, Utc};
+use db_macros::Resource;
+use nexus_types::external_api::params;
+use nexus_types::external_api::views;
+use nexus_types::identity::Resource;
+use uuid::Uuid;
+
+/// Describes a service account within the database.
+#[derive(Clone, Debug, Insertable, Queryable, Resource, Selectable)]
+#[diesel(table_name = service_account)]
+pub struct ServiceAccount {
+    #[diesel(embed)]
+    identity: ServiceAccountIdentity,
+
+    pub silo_id: Uuid,
+
+    /// The Organization containing `project_id`, if set
+    pub organization_id: Option<Uuid>,
+    /// The Project to which this service account's access is limited, if any
+    pub project_id: Option<Uuid>,
+}
+
+impl ServiceAccount {
+    /// Creates a new service account.  `project`, if given, is the
+    /// Organization and Project to which its access is limited.
+    pub fn new(
+        silo_id: Uuid,
+        project: Option<(Uuid, Uuid)>,
+        params: params::ServiceAccountCreate,
+    ) -> Self {
+        Self {
+            identity: ServiceAccountIdentity::new(
+                Uuid::new_v4(),
+                params.identity,
+            ),
+            silo_id,
+            organization_id: project
+                .map(|(organization_id, _)| organization_id),
+            project_id: project.map(|(_, project_id)| project_id),
+        }
+    }
+}
+
+impl From<ServiceAccount> for views::ServiceAccount {
+    fn from(service_account: ServiceAccount) -> Self {
+        Self {
+            identity: service_account.identity(),
+            project_id: service_account.project_id,
+        }
+    }
+}
+
+/// An API key with which a service account authenticates.
+// TODO-security: wrap token in an opaque struct to avoid accidental leaks.
+#[derive(Clone, Debug, Insertable, Queryable, Selectable)]
+#[diesel(table_name = service_account_key)]
+pub struct ServiceAccountKey {
+    pub id: Uuid,
+    pub token: String,
+    pub service_account_id: Uuid,
+    pub time_created: DateTime<Utc>,
+    pub time_deleted: Option<DateTime<Utc>>,
+}
+
+impl ServiceAccountKey {
+    pub fn new(service_account_id: Uuid) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            token: generate_token(),
+            service_account_id,
+            time_created: Utc::now(),
+            time_deleted: None,
+        }
+    }
+}
+
+impl From<ServiceAccountKey> for views::ServiceAccountKey {
+    fn from(key: ServiceAccountKey) -> Self {
+        Self { id: key.id, time_created: key.time_created }
+    }
+}
+
+impl From<ServiceAccountKey> for views::ServiceAccountKeyCreated {
+    fn from(key: ServiceAccountKey) -> Self {
+        Self {
+            id: key.id,
+            time_created: key.time_created,
+            key: format!("oxide-key-{}", key.token),
+        }
+    }
+}
@@ -26,6 +26,7 @@ impl_enum_type!(
     // Enum values
     Local => b"local"
     Saml => b"saml"
+    Oidc => b"oidc"
 );
 
 impl From<shared::AuthenticationMode> for AuthenticationMode {
@@ -33,6 +34,7 @@ impl From<shared::AuthenticationMode> for AuthenticationMode {
         match params {
             shared::AuthenticationMode::Local => AuthenticationMode::Local,
             shared::AuthenticationMode::Saml => AuthenticationMode::Saml,
+            shared::AuthenticationMode::Oidc => AuthenticationMode::Oidc,
         }
     }
 }
@@ -42,6 +44,7 @@ impl From<AuthenticationMode> for shared::AuthenticationMode {
         match model {
             AuthenticationMode::Local => Self::Local,
             AuthenticationMode::Saml => Self::Saml,
+            AuthenticationMode::Oidc => Self::Oidc,
         }
     }
 }
@@ -127,6 +130,10 @@ impl TryFrom<Silo> for views::Silo {
                 Some(SiloIdentityMode::SamlJit)
             }
             (AuthenticationMode::Saml, UserProvisionType::ApiOnly) => None,
+            (AuthenticationMode::Oidc, UserProvisionType::Jit) => {
+                Some(SiloIdentityMode::OidcJit)
+            }
+            (AuthenticationMode::Oidc, UserProvisionType::ApiOnly) => None,
             (AuthenticationMode::Local, UserProvisionType::ApiOnly) => {
                 Some(SiloIdentityMode::LocalOnly)
             }
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use crate::schema::silo_user;
+use crate::schema::{silo_user, silo_user_password_hash};
 use db_macros::Asset;
 use nexus_types::external_api::views;
 use nexus_types::identity::Asset;
@@ -33,6 +33,33 @@ impl SiloUser {
     }
 }
 
+/// Describes the password hash of a silo user within the database.
+// This deliberately doesn't implement `Debug`, to keep the hash out of logs.
+#[derive(Queryable, Insertable, Selectable)]
+#[diesel(table_name = silo_user_password_hash)]
+pub struct SiloUserPasswordHash {
+    pub silo_user_id: Uuid,
+    /// The hash, as a PHC string
+    pub hash: String,
+    pub time_created: chrono::DateTime<chrono::Utc>,
+    /// The number of consecutive failed login attempts
+    pub failed_attempts: i64,
+    /// If set, logins are refused until this time
+    pub time_locked_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl SiloUserPasswordHash {
+    pub fn new(silo_user_id: Uuid, hash: String) -> Self {
+        Self {
+            silo_user_id,
+            hash,
+            time_created: chrono::Utc::now(),
+            failed_attempts: 0,
+            time_locked_until: None,
+        }
+    }
+}
+
 impl From<SiloUser> for views::User {
     fn from(user: SiloUser) -> Self {
         Self {
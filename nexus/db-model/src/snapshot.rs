@@ -6,6 +6,7 @@ use super::{impl_enum_type, ByteCount};
 use crate::schema::snapshot;
 use crate::BlockSize;
 use crate::Generation;
+use crate::Labels;
 use db_macros::Resource;
 use nexus_types::external_api::views;
 use nexus_types::identity::Resource;
@@ -42,6 +43,9 @@ pub struct Snapshot {
     #[diesel(embed)]
     pub identity: SnapshotIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub project_id: Uuid,
     // which disk is this a snapshot of
     pub disk_id: Uuid,
@@ -62,6 +66,7 @@ impl From<Snapshot> for views::Snapshot {
     fn from(snapshot: Snapshot) -> Self {
         Self {
             identity: snapshot.identity(),
+            labels: snapshot.labels.0,
             project_id: snapshot.project_id,
             disk_id: snapshot.disk_id,
             state: snapshot.state.into(),
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{Generation, Ipv6Net, Name, VpcFirewallRule, VpcSubnet};
+use super::{Generation, Ipv6Net, Labels, Name, VpcFirewallRule, VpcSubnet};
 use crate::collection::DatastoreCollectionConfig;
 use crate::schema::{vpc, vpc_firewall_rule, vpc_subnet};
 use crate::Vni;
@@ -22,6 +22,9 @@ pub struct Vpc {
     #[diesel(embed)]
     identity: VpcIdentity,
 
+    /// user-defined labels
+    pub labels: Labels,
+
     pub project_id: Uuid,
     pub system_router_id: Uuid,
     pub vni: Vni,
@@ -40,6 +43,7 @@ impl From<Vpc> for views::Vpc {
     fn from(vpc: Vpc) -> Self {
         Self {
             identity: vpc.identity(),
+            labels: vpc.labels.0,
             project_id: vpc.project_id,
             system_router_id: vpc.system_router_id,
             ipv6_prefix: *vpc.ipv6_prefix,
@@ -55,6 +59,7 @@ impl From<Vpc> for views::Vpc {
 #[derive(Clone, Debug)]
 pub struct IncompleteVpc {
     pub identity: VpcIdentity,
+    pub labels: Labels,
     pub project_id: Uuid,
     pub system_router_id: Uuid,
     pub vni: Vni,
@@ -90,6 +95,7 @@ impl IncompleteVpc {
         );
         Ok(Self {
             identity,
+            labels: params.labels.into(),
             project_id,
             system_router_id,
             vni: Vni(external::Vni::random()),
@@ -120,6 +126,7 @@ impl DatastoreCollectionConfig<VpcSubnet> for Vpc {
 pub struct VpcUpdate {
     pub name: Option<Name>,
     pub description: Option<String>,
+    pub labels: Option<Labels>,
     pub time_modified: DateTime<Utc>,
     pub dns_name: Option<Name>,
 }
@@ -129,6 +136,7 @@ impl From<params::VpcUpdate> for VpcUpdate {
         Self {
             name: params.identity.name.map(Name),
             description: params.identity.description,
+            labels: params.labels.map(Labels),
             time_modified: Utc::now(),
             dns_name: params.dns_name.map(Name),
         }
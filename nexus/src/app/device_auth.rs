@@ -50,8 +50,11 @@ use crate::context::OpContext;
 use crate::db::lookup::LookupPath;
 use crate::db::model::{DeviceAccessToken, DeviceAuthRequest};
 use crate::external_api::device_auth::DeviceAccessTokenResponse;
+use crate::external_api::shared;
 
-use omicron_common::api::external::{CreateResult, Error};
+use omicron_common::api::external::{
+    CreateResult, DataPageParams, DeleteResult, Error, ListResultVec,
+};
 
 use chrono::Utc;
 use uuid::Uuid;
@@ -59,16 +62,20 @@ use uuid::Uuid;
 impl super::Nexus {
     /// Start a device authorization grant flow.
     /// Corresponds to steps 1 & 2 in the flow description above.
+    ///
+    /// If the client supplies a `scope`, the token eventually granted will be
+    /// limited to it.
     pub async fn device_auth_request_create(
         &self,
         opctx: &OpContext,
         client_id: Uuid,
+        scope: Option<shared::TokenScope>,
     ) -> CreateResult<DeviceAuthRequest> {
         // TODO-correctness: the `user_code` generated for a new request
         // is used as a primary key, but may potentially collide with an
         // existing outstanding request. So we should retry some (small)
         // number of times if inserting the new request fails.
-        let auth_request = DeviceAuthRequest::new(client_id);
+        let auth_request = DeviceAuthRequest::new(client_id, scope);
         self.db_datastore.device_auth_request_create(opctx, auth_request).await
     }
 
@@ -82,6 +89,12 @@ impl super::Nexus {
         user_code: String,
         silo_user_id: Uuid,
     ) -> CreateResult<DeviceAccessToken> {
+        // A scoped token must not be able to mint itself a new token that's
+        // free of those limits.
+        if opctx.authn.scope().is_some() {
+            return Err(Error::Forbidden);
+        }
+
         let (.., authz_request, db_request) =
             LookupPath::new(opctx, &self.db_datastore)
                 .device_auth_request(&user_code)
@@ -94,6 +107,30 @@ impl super::Nexus {
             .await?;
         assert_eq!(authz_user.id(), silo_user_id);
 
+        // If the client asked for a scope limited to some resource, the user
+        // must be able to see that resource.  We also record the Organization
+        // of a Project so that the scope can be checked without looking it up
+        // again.
+        let scope = match db_request.scope() {
+            None => None,
+            Some(mut scope) => {
+                if let Some(project_id) = scope.project_id {
+                    let (.., authz_org, _) =
+                        LookupPath::new(opctx, &self.db_datastore)
+                            .project_id(project_id)
+                            .lookup_for(authz::Action::Read)
+                            .await?;
+                    scope.organization_id = Some(authz_org.id());
+                } else if let Some(organization_id) = scope.organization_id {
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .organization_id(organization_id)
+                        .lookup_for(authz::Action::Read)
+                        .await?;
+                }
+                Some(scope)
+            }
+        };
+
         // Create an access token record.
         let token = DeviceAccessToken::new(
             db_request.client_id,
@@ -101,6 +138,10 @@ impl super::Nexus {
             db_request.time_created,
             silo_user_id,
         );
+        let token = match scope {
+            None => token,
+            Some(scope) => token.scoped(scope),
+        };
 
         if db_request.time_expires < Utc::now() {
             // Store the expired token anyway so that the client
@@ -118,7 +159,8 @@ impl super::Nexus {
                 message: "device authorization request expired".to_string(),
             })
         } else {
-            // TODO-security: set an expiration time for the valid token.
+            let time_expires = token.time_created + self.device_token_ttl;
+            let token = token.expires(time_expires);
             self.db_datastore
                 .device_access_token_create(
                     opctx,
@@ -150,13 +192,14 @@ impl super::Nexus {
         }
     }
 
-    /// Look up the actor for which a token was granted.
+    /// Look up the actor for which a token was granted, along with the limits
+    /// on what the token may be used for.
     /// Corresponds to a request *after* completing the flow above.
     pub async fn device_access_token_actor(
         &self,
         opctx: &OpContext,
         token: String,
-    ) -> Result<Actor, Reason> {
+    ) -> Result<(Actor, Option<shared::TokenScope>), Reason> {
         let (.., db_access_token) = LookupPath::new(opctx, &self.db_datastore)
             .device_access_token(&token)
             .fetch()
@@ -180,7 +223,51 @@ impl super::Nexus {
                 e => Reason::UnknownError { source: e },
             })?;
         let silo_id = db_silo_user.silo_id;
+        let actor = Actor::SiloUser { silo_user_id, silo_id };
+
+        if db_access_token.time_revoked.is_some() {
+            return Err(Reason::RevokedCredentials { actor });
+        }
+        if let Some(time_expires) = db_access_token.time_expires {
+            if time_expires <= Utc::now() {
+                return Err(Reason::ExpiredCredentials { actor });
+            }
+        }
 
-        Ok(Actor::SiloUser { silo_user_id, silo_id })
+        Ok((actor, db_access_token.scope()))
+    }
+
+    /// List the unexpired, unrevoked access tokens granted to a user.
+    pub async fn device_access_tokens_list(
+        &self,
+        opctx: &OpContext,
+        silo_user_id: Uuid,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<DeviceAccessToken> {
+        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_user_id(silo_user_id)
+            .lookup_for(authz::Action::ListChildren)
+            .await?;
+        assert_eq!(authz_user.id(), silo_user_id);
+        self.db_datastore
+            .device_access_tokens_list(opctx, &authz_user, pagparams)
+            .await
+    }
+
+    /// Revoke one of the access tokens granted to a user.
+    pub async fn device_access_token_revoke(
+        &self,
+        opctx: &OpContext,
+        silo_user_id: Uuid,
+        token_id: Uuid,
+    ) -> DeleteResult {
+        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_user_id(silo_user_id)
+            .lookup_for(authz::Action::Modify)
+            .await?;
+        assert_eq!(authz_user.id(), silo_user_id);
+        self.db_datastore
+            .device_access_token_revoke(opctx, &authz_user, token_id)
+            .await
     }
 }
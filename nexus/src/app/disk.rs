@@ -12,15 +12,21 @@ use crate::db;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Name;
 use crate::external_api::params;
+use crate::external_api::shared;
+use anyhow::Context;
+use omicron_common::api::external;
 use omicron_common::api::external::ByteCount;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::UpdateResult;
 use omicron_common::api::internal::nexus::DiskRuntimeState;
+use sled_agent_client::types::VolumeConstructionRequest;
 use sled_agent_client::Client as SledAgentClient;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -42,7 +48,8 @@ impl super::Nexus {
             .await?;
 
         match &params.disk_source {
-            params::DiskSource::Blank { block_size } => {
+            params::DiskSource::Blank { block_size }
+            | params::DiskSource::ImportingBlocks { block_size } => {
                 // Reject disks where the block size doesn't evenly divide the
                 // total size
                 if (params.size.to_bytes() % block_size.0 as u64) != 0 {
@@ -231,6 +238,7 @@ impl super::Nexus {
         organization_name: &Name,
         project_name: &Name,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Disk> {
         let (.., authz_project) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
@@ -238,7 +246,12 @@ impl super::Nexus {
             .lookup_for(authz::Action::ListChildren)
             .await?;
         self.db_datastore
-            .project_list_disks(opctx, &authz_project, pagparams)
+            .project_list_disks(
+                opctx,
+                &authz_project,
+                pagparams,
+                label_selector,
+            )
             .await
     }
 
@@ -270,6 +283,398 @@ impl super::Nexus {
         Ok(db_disk)
     }
 
+    /// Grows a disk and/or replaces its labels, as given in `params`
+    ///
+    /// A disk attached to a running instance is grown in place.
+    pub async fn disk_update(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+        params: &params::DiskUpdate,
+    ) -> UpdateResult<db::model::Disk> {
+        let (.., authz_disk, db_disk) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .disk_name(disk_name)
+                .fetch_for(authz::Action::Modify)
+                .await?;
+
+        let mut db_disk = db_disk;
+        if let Some(new_size) = params.size {
+            db_disk =
+                self.disk_resize(opctx, &authz_disk, db_disk, new_size).await?;
+        }
+
+        if let Some(labels) = &params.labels {
+            db_disk = self
+                .db_datastore
+                .disk_update_labels(opctx, &authz_disk, labels.clone().into())
+                .await?;
+        }
+
+        Ok(db_disk)
+    }
+
+    /// Grows a disk to `new_size`
+    async fn disk_resize(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        authz_disk: &authz::Disk,
+        db_disk: db::model::Disk,
+        new_size: ByteCount,
+    ) -> UpdateResult<db::model::Disk> {
+        let old_size = db_disk.size.0;
+        if new_size.to_bytes() == old_size.to_bytes() {
+            return Ok(db_disk);
+        }
+
+        // Reject requests to shrink the disk, since that would destroy
+        // whatever the guest has stored at the end of it
+        if new_size.to_bytes() < old_size.to_bytes() {
+            return Err(Error::InvalidValue {
+                label: String::from("size"),
+                message: format!(
+                    "disks can only grow: total size must be at least {}",
+                    old_size
+                ),
+            });
+        }
+
+        // Reject sizes that the block size doesn't evenly divide
+        if (new_size.to_bytes() % db_disk.block_size.to_bytes() as u64) != 0 {
+            return Err(Error::InvalidValue {
+                label: String::from("size"),
+                message: String::from(
+                    "total size must be a multiple of the disk's block size",
+                ),
+            });
+        }
+
+        // Reject sizes that MIN_DISK_SIZE_BYTES doesn't evenly divide
+        if (new_size.to_bytes() % params::MIN_DISK_SIZE_BYTES as u64) != 0 {
+            return Err(Error::InvalidValue {
+                label: String::from("size"),
+                message: format!(
+                    "total size must be a multiple of {}",
+                    ByteCount::from(params::MIN_DISK_SIZE_BYTES)
+                ),
+            });
+        }
+
+        // Regions are allocated in whole extents, so the disk's regions may
+        // already have room for some or all of the new size.  Only allocate
+        // what's left.
+        let regions =
+            self.db_datastore.get_allocated_regions(db_disk.volume_id).await?;
+        let capacity = regions
+            .iter()
+            .map(|(_, region)| {
+                region.block_size().to_bytes()
+                    * region.blocks_per_extent() as u64
+                    * region.extent_count() as u64
+            })
+            .sum::<u64>()
+            / db::datastore::REGION_REDUNDANCY_THRESHOLD as u64;
+        let extension_size = if new_size.to_bytes() > capacity {
+            Some(
+                ByteCount::try_from(new_size.to_bytes() - capacity)
+                    .map_err(|e| Error::internal_error(&e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        let saga_params = sagas::disk_resize::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            disk_id: authz_disk.id(),
+            block_size: db_disk.block_size,
+            old_size,
+            new_size,
+            extension_size,
+        };
+        self.execute_saga::<sagas::disk_resize::SagaDiskResize>(saga_params)
+            .await?;
+        self.db_datastore.disk_refetch(opctx, authz_disk).await
+    }
+
+    /// Write blocks into a disk that is being imported
+    pub async fn disk_import_blocks_bulk_write(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> UpdateResult<()> {
+        let (.., authz_disk, db_disk) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .disk_name(disk_name)
+                .fetch_for(authz::Action::Modify)
+                .await?;
+
+        let disk_state = db_disk.state().state().clone();
+        if disk_state != external::DiskState::Importing {
+            return Err(Error::invalid_request(&format!(
+                "cannot import blocks into a disk in state \"{}\"",
+                disk_state
+            )));
+        }
+
+        let block_size = db_disk.block_size.to_bytes() as u64;
+        let len = data.len() as u64;
+        if len == 0 || len % block_size != 0 || offset % block_size != 0 {
+            return Err(Error::invalid_request(&format!(
+                "writes must be a whole number of blocks ({} bytes each), \
+                starting on a block boundary",
+                block_size
+            )));
+        }
+        if len > u64::from(params::MAX_DISK_BULK_WRITE_BYTES) {
+            return Err(Error::invalid_request(&format!(
+                "writes can be at most {} bytes",
+                params::MAX_DISK_BULK_WRITE_BYTES
+            )));
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= db_disk.size.to_bytes() => (),
+            _ => {
+                return Err(Error::invalid_request(
+                    "write extends past the end of the disk",
+                ));
+            }
+        }
+
+        let (volume_construction_request, sled_agent_client) = self
+            .disk_bulk_io_target(authz_disk.id(), db_disk.volume_id)
+            .await?;
+        sled_agent_client
+            .disk_bulk_write(
+                &authz_disk.id(),
+                &sled_agent_client::types::DiskBulkWriteBody {
+                    volume_construction_request,
+                    offset,
+                    base64_encoded_data: base64::encode(data),
+                },
+            )
+            .await
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    /// Finish importing a disk, making it available to instances
+    pub async fn disk_finalize_import(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+    ) -> UpdateResult<()> {
+        let (.., authz_disk, db_disk) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .disk_name(disk_name)
+                .fetch_for(authz::Action::Modify)
+                .await?;
+
+        let disk_state = db_disk.state().state().clone();
+        if disk_state != external::DiskState::Importing {
+            return Err(Error::invalid_request(&format!(
+                "cannot finalize the import of a disk in state \"{}\"",
+                disk_state
+            )));
+        }
+
+        // The generation check makes this a no-op if another request
+        // finalized the import first.
+        self.db_datastore
+            .disk_update_runtime(
+                opctx,
+                &authz_disk,
+                &db_disk.runtime().detach(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Read the contents of a detached disk
+    ///
+    /// Returns the disk's size and a body that streams its contents.  The
+    /// contents are read in the background as the body is consumed; if a read
+    /// fails, the body is aborted.
+    pub async fn disk_export(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+    ) -> Result<(ByteCount, hyper::Body), Error> {
+        let (.., authz_disk, db_disk) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .disk_name(disk_name)
+                .fetch_for(authz::Action::Read)
+                .await?;
+
+        // Only detached disks can be exported, so that nothing writes to the
+        // disk while it's being read.
+        let disk_state = db_disk.state().state().clone();
+        if disk_state != external::DiskState::Detached {
+            return Err(Error::invalid_request(&format!(
+                "cannot export a disk in state \"{}\"",
+                disk_state
+            )));
+        }
+
+        let disk_id = authz_disk.id();
+        let size = db_disk.size.0;
+        let (volume_construction_request, sled_agent_client) =
+            self.disk_bulk_io_target(disk_id, db_disk.volume_id).await?;
+        let chunk_size = move |offset: u64| {
+            std::cmp::min(
+                size.to_bytes() - offset,
+                u64::from(params::MAX_DISK_BULK_WRITE_BYTES),
+            )
+        };
+
+        // Read the first chunk before responding, so that a sled that can't
+        // read the disk at all produces an error response rather than an
+        // aborted body.
+        let first_chunk = disk_bulk_read(
+            &sled_agent_client,
+            disk_id,
+            &volume_construction_request,
+            0,
+            chunk_size(0),
+        )
+        .await?;
+
+        let log = self.log.new(o!("disk_id" => disk_id.to_string()));
+        let (mut sender, body) = hyper::Body::channel();
+        tokio::spawn(async move {
+            if sender.send_data(first_chunk.into()).await.is_err() {
+                // The client went away.
+                return;
+            }
+
+            let mut offset = chunk_size(0);
+            while offset < size.to_bytes() {
+                let data = match disk_bulk_read(
+                    &sled_agent_client,
+                    disk_id,
+                    &volume_construction_request,
+                    offset,
+                    chunk_size(offset),
+                )
+                .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        warn!(log, "disk export failed";
+                            "offset" => offset,
+                            "error" => error.to_string());
+                        sender.abort();
+                        return;
+                    }
+                };
+
+                if sender.send_data(data.into()).await.is_err() {
+                    // The client went away.
+                    return;
+                }
+                offset += chunk_size(offset);
+            }
+        });
+
+        Ok((size, body))
+    }
+
+    /// Returns the volume backing a disk that isn't attached to an instance,
+    /// along with a client for a sled that can do I/O to it on our behalf
+    async fn disk_bulk_io_target(
+        &self,
+        disk_id: Uuid,
+        volume_id: Uuid,
+    ) -> Result<(VolumeConstructionRequest, Arc<SledAgentClient>), Error> {
+        let volume = self.db_datastore.volume_get(volume_id).await?;
+        let volume_construction_request = serde_json::from_str(volume.data())
+            .map_err(|e| {
+            Error::internal_error(&format!(
+                "failed to deserialize disk {} volume data: {}",
+                disk_id, e,
+            ))
+        })?;
+
+        // The disk isn't attached to an instance, so any sled can reach its
+        // regions.
+        let sled_id = self
+            .random_sled_id()
+            .await?
+            .ok_or_else(|| Error::unavail("no sleds available"))?;
+        let sled_agent_client = self.sled_client(&sled_id).await?;
+        Ok((volume_construction_request, sled_agent_client))
+    }
+
+    pub async fn disk_fetch_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+    ) -> LookupResult<shared::Policy<authz::DiskRole>> {
+        let (.., authz_disk) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .disk_name(disk_name)
+            .lookup_for(authz::Action::ReadPolicy)
+            .await?;
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_fetch_visible(opctx, &authz_disk)
+            .await?
+            .into_iter()
+            .map(|r| r.try_into().context("parsing database role assignment"))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| Error::internal_error(&format!("{:#}", error)))?;
+        Ok(shared::Policy { role_assignments })
+    }
+
+    pub async fn disk_update_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        disk_name: &Name,
+        policy: &shared::Policy<authz::DiskRole>,
+    ) -> UpdateResult<shared::Policy<authz::DiskRole>> {
+        let (.., authz_disk) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .disk_name(disk_name)
+            .lookup_for(authz::Action::ModifyPolicy)
+            .await?;
+
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_replace_visible(
+                opctx,
+                &authz_disk,
+                &policy.role_assignments,
+            )
+            .await?
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(shared::Policy { role_assignments })
+    }
+
     /// Modifies the runtime state of the Disk as requested.  This generally
     /// means attaching or detaching the disk.
     // TODO(https://github.com/oxidecomputer/omicron/issues/811):
@@ -442,6 +847,7 @@ impl super::Nexus {
         organization_name: &Name,
         project_name: &Name,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Snapshot> {
         let (.., authz_project) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
@@ -450,7 +856,12 @@ impl super::Nexus {
             .await?;
 
         self.db_datastore
-            .project_list_snapshots(opctx, &authz_project, pagparams)
+            .project_list_snapshots(
+                opctx,
+                &authz_project,
+                pagparams,
+                label_selector,
+            )
             .await
     }
 
@@ -520,3 +931,32 @@ impl super::Nexus {
         Ok(())
     }
 }
+
+/// Reads `size` bytes at `offset` from the disk backed by
+/// `volume_construction_request`, by way of the given sled
+async fn disk_bulk_read(
+    sled_agent_client: &SledAgentClient,
+    disk_id: Uuid,
+    volume_construction_request: &VolumeConstructionRequest,
+    offset: u64,
+    size: u64,
+) -> Result<Vec<u8>, Error> {
+    let response = sled_agent_client
+        .disk_bulk_read(
+            &disk_id,
+            &sled_agent_client::types::DiskBulkReadBody {
+                volume_construction_request: volume_construction_request
+                    .clone(),
+                offset,
+                size,
+            },
+        )
+        .await
+        .map_err(Error::from)?;
+    base64::decode(&response.into_inner().base64_encoded_data).map_err(|e| {
+        Error::internal_error(&format!(
+            "failed to decode data read from disk {}: {}",
+            disk_id, e
+        ))
+    })
+}
@@ -0,0 +1,222 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Names of silos and instances in the external DNS zone
+
+use crate::authn;
+use crate::authz;
+use crate::config::ExternalDnsConfig;
+use crate::context::OpContext;
+use crate::db;
+use crate::db::datastore::ExternalDnsZoneData;
+use anyhow::Context;
+use internal_dns_client::multiclient::Updater;
+use internal_dns_client::types::{
+    DnsKv, DnsRecord, DnsRecordKey, DnsZoneUpdate,
+};
+use slog::Logger;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+
+/// The longest DNS name, not counting the trailing dot (RFC 1035 section
+/// 2.3.4).
+const MAX_NAME_LEN: usize = 253;
+
+/// The longest label within a DNS name.
+const MAX_LABEL_LEN: usize = 63;
+
+/// How often to republish the external DNS zone, in case an update failed
+const EXTERNAL_DNS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Everything Nexus needs to keep the external DNS zone up to date
+pub(super) struct ExternalDns {
+    config: ExternalDnsConfig,
+    updater: Updater,
+    /// Operational context used to read all silos and instances, and bump the
+    /// zone's generation
+    opctx: OpContext,
+}
+
+impl ExternalDns {
+    pub(super) fn new(
+        log: &Logger,
+        authz: Arc<authz::Authz>,
+        datastore: Arc<db::DataStore>,
+        config: &ExternalDnsConfig,
+    ) -> ExternalDns {
+        let log = log.new(o!("component" => "ExternalDns"));
+        let updater =
+            Updater::new_from_addrs(config.servers.clone(), log.clone());
+        let opctx = OpContext::for_background(
+            log,
+            authz,
+            authn::Context::internal_api(),
+            datastore,
+        );
+        ExternalDns { config: config.clone(), updater, opctx }
+    }
+
+    async fn update(
+        &self,
+        datastore: &db::DataStore,
+    ) -> Result<(), anyhow::Error> {
+        let data = datastore
+            .external_dns_zone_data(
+                &self.opctx,
+                &self.config.zone,
+                self.config.instance_records,
+            )
+            .await
+            .context("reading external DNS zone data")?;
+        let update = DnsZoneUpdate {
+            generation: data.generation,
+            records: zone_records(&self.opctx.log, &self.config, &data),
+        };
+        match self.updater.dns_zone_replace(&update).await {
+            Ok(()) => {}
+            // A server that's already seen a newer generation got it from a
+            // concurrent update, which read data at least as new as ours.
+            Err(internal_dns_client::Error::ErrorResponse(rv))
+                if rv.status().as_u16() == 409
+                    && rv.error_code.as_deref() == Some("StaleGeneration") =>
+            {
+                debug!(
+                    self.opctx.log,
+                    "external DNS zone already updated past our generation";
+                    "generation" => data.generation,
+                );
+            }
+            Err(error) => {
+                return Err(error).context("replacing external DNS zone")
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build the records of the external DNS zone from `data`.
+///
+/// Each silo is named `<silo>.sys.<zone>`, and resolves to the addresses of
+/// the external API.  Each inbound external IP of an instance is published
+/// under `<hostname>.<project>.<organization>.<silo>.<zone>`; instances whose
+/// hostname isn't a valid DNS label, or whose name would be too long, are left
+/// out.
+fn zone_records(
+    log: &Logger,
+    config: &ExternalDnsConfig,
+    data: &ExternalDnsZoneData,
+) -> Vec<DnsKv> {
+    let zone = config.zone.trim_end_matches('.').to_ascii_lowercase();
+    let mut names: BTreeMap<String, Vec<DnsRecord>> = BTreeMap::new();
+
+    for silo_name in &data.silo_names {
+        let name = format!("{}.sys.{}", silo_name, zone);
+        names.insert(
+            name,
+            config.api_addresses.iter().copied().map(address_record).collect(),
+        );
+    }
+
+    for instance in &data.instances {
+        if !is_valid_label(&instance.hostname) {
+            debug!(
+                log,
+                "not publishing instance with invalid DNS label as hostname";
+                "hostname" => &instance.hostname,
+            );
+            continue;
+        }
+        let name = format!(
+            "{}.{}.{}.{}.{}",
+            instance.hostname.to_ascii_lowercase(),
+            instance.project_name,
+            instance.organization_name,
+            instance.silo_name,
+            zone,
+        );
+        if name.len() > MAX_NAME_LEN {
+            debug!(
+                log,
+                "not publishing instance whose DNS name is too long";
+                "name" => &name,
+            );
+            continue;
+        }
+        names.entry(name).or_default().push(address_record(instance.ip));
+    }
+
+    names
+        .into_iter()
+        .map(|(name, records)| DnsKv { key: DnsRecordKey { name }, records })
+        .collect()
+}
+
+fn address_record(ip: IpAddr) -> DnsRecord {
+    match ip {
+        IpAddr::V4(ip) => DnsRecord::A(ip),
+        IpAddr::V6(ip) => DnsRecord::Aaaa(ip),
+    }
+}
+
+/// Returns whether `label` may be used as a label of a host name (RFC 1123
+/// section 2.1).
+pub(super) fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LEN
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+impl super::Nexus {
+    /// Replace the contents of the external DNS zone with the names of the
+    /// current silos and instances, if external DNS is configured.
+    ///
+    /// This should be called after anything changes those names.  By then,
+    /// the change itself has happened, so failures are logged rather than
+    /// returned; [`external_dns_refresh_task`] retries them.
+    pub(crate) async fn external_dns_update(&self) {
+        let external_dns = match &self.external_dns {
+            Some(external_dns) => external_dns,
+            None => return,
+        };
+        if let Err(error) = external_dns.update(&self.db_datastore).await {
+            warn!(self.log, "failed to update external DNS zone";
+                "error" => format!("{:#}", error));
+        }
+    }
+}
+
+/// Periodically republishes the external DNS zone, for as long as `nexus`
+/// exists
+///
+/// A failed update after a change to silos or instances would otherwise leave
+/// the zone stale until the next change.  The first update also publishes the
+/// names that existed before Nexus started.
+pub(super) async fn external_dns_refresh_task(nexus: Weak<super::Nexus>) {
+    match nexus.upgrade() {
+        Some(nexus) => {
+            // Nothing in the database can be read until it's been populated.
+            if nexus.external_dns.is_none()
+                || nexus.wait_for_populate().await.is_err()
+            {
+                return;
+            }
+        }
+        None => return,
+    }
+
+    let mut interval = tokio::time::interval(EXTERNAL_DNS_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let nexus = match nexus.upgrade() {
+            Some(nexus) => nexus,
+            None => return,
+        };
+        nexus.external_dns_update().await;
+    }
+}
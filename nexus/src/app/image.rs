@@ -17,6 +17,7 @@ use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
@@ -49,6 +50,7 @@ impl super::Nexus {
         organization_name: &Name,
         project_name: &Name,
         _pagparams: &DataPageParams<'_, Name>,
+        _label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Image> {
         let _ = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
@@ -208,6 +210,7 @@ impl super::Nexus {
                         global_image_id,
                         params.identity.clone(),
                     ),
+                    labels: params.labels.clone().into(),
                     volume_id: volume.id(),
                     url: Some(url.clone()),
                     distribution: params.distribution.name.to_string(),
@@ -266,6 +269,7 @@ impl super::Nexus {
                         global_image_id,
                         params.identity.clone(),
                     ),
+                    labels: params.labels.clone().into(),
                     volume_id: volume.id(),
                     url: None,
                     distribution: "alpine".parse().map_err(|_| {
@@ -288,8 +292,11 @@ impl super::Nexus {
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::GlobalImage> {
-        self.db_datastore.global_image_list_images(opctx, pagparams).await
+        self.db_datastore
+            .global_image_list_images(opctx, pagparams, label_selector)
+            .await
     }
 
     pub async fn global_image_fetch(
@@ -18,6 +18,8 @@ use crate::db::identity::Resource;
 use crate::db::lookup::LookupPath;
 use crate::db::queries::network_interface;
 use crate::external_api::params;
+use crate::external_api::shared;
+use anyhow::Context;
 use nexus_db_model::IpKind;
 use nexus_db_model::Name;
 use omicron_common::api::external::ByteCount;
@@ -27,6 +29,7 @@ use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::InstanceState;
 use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::UpdateResult;
@@ -95,31 +98,7 @@ impl super::Nexus {
             }
         }
 
-        // Reject instances where the memory is not at least
-        // MIN_MEMORY_SIZE_BYTES
-        if params.memory.to_bytes() < params::MIN_MEMORY_SIZE_BYTES as u64 {
-            return Err(Error::InvalidValue {
-                label: String::from("size"),
-                message: format!(
-                    "memory must be at least {}",
-                    ByteCount::from(params::MIN_MEMORY_SIZE_BYTES)
-                ),
-            });
-        }
-
-        // Reject instances where the memory is not divisible by
-        // MIN_MEMORY_SIZE_BYTES
-        if (params.memory.to_bytes() % params::MIN_MEMORY_SIZE_BYTES as u64)
-            != 0
-        {
-            return Err(Error::InvalidValue {
-                label: String::from("size"),
-                message: format!(
-                    "memory must be divisible by {}",
-                    ByteCount::from(params::MIN_MEMORY_SIZE_BYTES)
-                ),
-            });
-        }
+        check_instance_memory(params.memory)?;
 
         let saga_params = sagas::instance_create::Params {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
@@ -140,6 +119,11 @@ impl super::Nexus {
             .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
             .internal_context("looking up output from instance create saga")?;
 
+        // Publish the names of the instance's external IPs.
+        if !params.external_ips.is_empty() {
+            self.external_dns_update().await;
+        }
+
         // TODO-correctness TODO-robustness TODO-design It's not quite correct
         // to take this instance id and look it up again.  It's possible that
         // it's been modified or even deleted since the saga executed.  In that
@@ -188,6 +172,7 @@ impl super::Nexus {
         organization_name: &Name,
         project_name: &Name,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Instance> {
         let (.., authz_project) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
@@ -195,7 +180,12 @@ impl super::Nexus {
             .lookup_for(authz::Action::ListChildren)
             .await?;
         self.db_datastore
-            .project_list_instances(opctx, &authz_project, pagparams)
+            .project_list_instances(
+                opctx,
+                &authz_project,
+                pagparams,
+                label_selector,
+            )
             .await
     }
 
@@ -227,6 +217,120 @@ impl super::Nexus {
         Ok(db_instance)
     }
 
+    pub async fn instance_fetch_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        instance_name: &Name,
+    ) -> LookupResult<shared::Policy<authz::InstanceRole>> {
+        let (.., authz_instance) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .instance_name(instance_name)
+            .lookup_for(authz::Action::ReadPolicy)
+            .await?;
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_fetch_visible(opctx, &authz_instance)
+            .await?
+            .into_iter()
+            .map(|r| r.try_into().context("parsing database role assignment"))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| Error::internal_error(&format!("{:#}", error)))?;
+        Ok(shared::Policy { role_assignments })
+    }
+
+    pub async fn instance_update_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        instance_name: &Name,
+        policy: &shared::Policy<authz::InstanceRole>,
+    ) -> UpdateResult<shared::Policy<authz::InstanceRole>> {
+        let (.., authz_instance) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .instance_name(instance_name)
+            .lookup_for(authz::Action::ModifyPolicy)
+            .await?;
+
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_replace_visible(
+                opctx,
+                &authz_instance,
+                &policy.role_assignments,
+            )
+            .await?
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(shared::Policy { role_assignments })
+    }
+
+    /// Reconfigure a stopped instance and/or replace its labels
+    ///
+    /// The new configuration is sent to the sled agent the next time the
+    /// instance starts.  Labels can be replaced regardless of the instance's
+    /// state.
+    pub async fn instance_update(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        instance_name: &Name,
+        params: &params::InstanceUpdate,
+    ) -> UpdateResult<db::model::Instance> {
+        let (.., authz_instance, db_instance) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .instance_name(instance_name)
+                .fetch_for(authz::Action::Modify)
+                .await?;
+
+        if let Some(memory) = params.memory {
+            check_instance_memory(memory)?;
+        }
+        if let Some(hostname) = &params.hostname {
+            check_instance_hostname(hostname)?;
+        }
+
+        let labels = params.labels.clone().map(db::model::Labels::from);
+        let db_instance = if params.ncpus.is_some()
+            || params.memory.is_some()
+            || params.hostname.is_some()
+        {
+            // The labels are replaced in the same update as the hardware, so
+            // that the request is applied either entirely or not at all.
+            self.db_datastore
+                .instance_update(
+                    opctx,
+                    &authz_instance,
+                    &db_instance,
+                    params.ncpus,
+                    params.memory,
+                    params.hostname.clone(),
+                    labels,
+                )
+                .await?
+        } else if let Some(labels) = labels {
+            self.db_datastore
+                .instance_update_labels(opctx, &authz_instance, labels)
+                .await?
+        } else {
+            db_instance
+        };
+
+        if params.hostname.is_some() {
+            self.external_dns_update().await;
+        }
+
+        Ok(db_instance)
+    }
+
     // This operation may only occur on stopped instances, which implies that
     // the attached disks do not have any running "upstairs" process running
     // within the sled.
@@ -258,6 +362,7 @@ impl super::Nexus {
         self.db_datastore
             .deallocate_external_ip_by_instance_id(opctx, authz_instance.id())
             .await?;
+        self.external_dns_update().await;
         Ok(())
     }
 
@@ -480,7 +585,7 @@ impl super::Nexus {
         db_instance: &db::model::Instance,
         requested: InstanceRuntimeStateRequested,
     ) -> Result<(), Error> {
-        opctx.authorize(authz::Action::Modify, authz_instance).await?;
+        opctx.authorize(authz::Action::Operate, authz_instance).await?;
 
         self.check_runtime_change_allowed(
             &db_instance.runtime().clone().into(),
@@ -585,31 +690,40 @@ impl super::Nexus {
 
         // Gather the SSH public keys of the actor make the request so
         // that they may be injected into the new image via cloud-init.
+        // Service accounts don't have SSH keys.
         // TODO-security: this should be replaced with a lookup based on
         // on `SiloUser` role assignments once those are in place.
         let actor = opctx.authn.actor_required().internal_context(
             "loading current user's ssh keys for new Instance",
         )?;
-        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
-            .silo_user_id(actor.actor_id())
-            .lookup_for(authz::Action::ListChildren)
-            .await?;
-        let public_keys = self
-            .db_datastore
-            .ssh_keys_list(
-                opctx,
-                &authz_user,
-                &DataPageParams {
-                    marker: None,
-                    direction: dropshot::PaginationOrder::Ascending,
-                    limit: std::num::NonZeroU32::new(MAX_KEYS_PER_INSTANCE)
-                        .unwrap(),
-                },
-            )
-            .await?
-            .into_iter()
-            .map(|ssh_key| ssh_key.public_key)
-            .collect::<Vec<String>>();
+        let public_keys = match actor {
+            authn::Actor::SiloUser { silo_user_id, .. } => {
+                let (.., authz_user) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .silo_user_id(*silo_user_id)
+                        .lookup_for(authz::Action::ListChildren)
+                        .await?;
+                self.db_datastore
+                    .ssh_keys_list(
+                        opctx,
+                        &authz_user,
+                        &DataPageParams {
+                            marker: None,
+                            direction: dropshot::PaginationOrder::Ascending,
+                            limit: std::num::NonZeroU32::new(
+                                MAX_KEYS_PER_INSTANCE,
+                            )
+                            .unwrap(),
+                        },
+                    )
+                    .await?
+                    .into_iter()
+                    .map(|ssh_key| ssh_key.public_key)
+                    .collect::<Vec<String>>()
+            }
+            authn::Actor::UserBuiltin { .. }
+            | authn::Actor::ServiceAccount { .. } => vec![],
+        };
 
         // Ask the sled agent to begin the state change.  Then update the
         // database to reflect the new intermediate state.  If this update is
@@ -1106,13 +1220,13 @@ impl super::Nexus {
         instance_name: &Name,
         params: &params::InstanceSerialConsoleRequest,
     ) -> Result<params::InstanceSerialConsoleData, Error> {
-        let db_instance = self
-            .instance_fetch(
-                opctx,
-                organization_name,
-                project_name,
-                instance_name,
-            )
+        // The serial console is as sensitive as the ability to reboot the
+        // Instance, so it requires the same permission.
+        let (.., db_instance) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .instance_name(instance_name)
+            .fetch_for(authz::Action::Operate)
             .await?;
 
         let sa = self.instance_sled(&db_instance).await?;
@@ -1133,3 +1247,48 @@ impl super::Nexus {
         })
     }
 }
+
+/// Reject instance memory sizes that aren't a positive multiple of
+/// MIN_MEMORY_SIZE_BYTES
+fn check_instance_memory(memory: ByteCount) -> Result<(), Error> {
+    // Reject instances where the memory is not at least
+    // MIN_MEMORY_SIZE_BYTES
+    if memory.to_bytes() < params::MIN_MEMORY_SIZE_BYTES as u64 {
+        return Err(Error::InvalidValue {
+            label: String::from("size"),
+            message: format!(
+                "memory must be at least {}",
+                ByteCount::from(params::MIN_MEMORY_SIZE_BYTES)
+            ),
+        });
+    }
+
+    // Reject instances where the memory is not divisible by
+    // MIN_MEMORY_SIZE_BYTES
+    if (memory.to_bytes() % params::MIN_MEMORY_SIZE_BYTES as u64) != 0 {
+        return Err(Error::InvalidValue {
+            label: String::from("size"),
+            message: format!(
+                "memory must be divisible by {}",
+                ByteCount::from(params::MIN_MEMORY_SIZE_BYTES)
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Reject hostnames that can't name the instance in the external DNS zone
+fn check_instance_hostname(hostname: &str) -> Result<(), Error> {
+    if !super::external_dns::is_valid_label(hostname) {
+        return Err(Error::InvalidValue {
+            label: String::from("hostname"),
+            message: String::from(
+                "hostname must be a DNS label: at most 63 letters, digits \
+                and hyphens, not starting or ending with a hyphen",
+            ),
+        });
+    }
+
+    Ok(())
+}
@@ -24,6 +24,7 @@ use uuid::Uuid;
 // by resource.
 mod device_auth;
 mod disk;
+mod external_dns;
 mod external_ip;
 mod iam;
 mod image;
@@ -34,6 +35,7 @@ mod oximeter;
 mod project;
 mod rack;
 mod saga;
+mod service_account;
 mod session;
 mod silo;
 mod sled;
@@ -99,6 +101,22 @@ pub struct Nexus {
     /// Operational context used for external request authentication
     opctx_external_authn: OpContext,
 
+    /// Keeps the external DNS zone up to date, if one is configured
+    external_dns: Option<external_dns::ExternalDns>,
+
+    /// Limits the rate of password logins for local users
+    login_rate_limiter: authn::passwords::LoginRateLimiter,
+
+    /// How long an access token granted through a device authorization flow
+    /// remains valid
+    device_token_ttl: chrono::Duration,
+
+    /// How long a console session may go unused before it expires
+    session_idle_timeout: chrono::Duration,
+
+    /// How long a console session may last, however often it's used
+    session_absolute_timeout: chrono::Duration,
+
     /// Max issue delay for samael crate - used only for testing
     // the samael crate has an extra check (beyond the check against the SAML
     // response NotOnOrAfter) that fails if the issue instant was too long ago.
@@ -194,6 +212,28 @@ impl Nexus {
                 authn::Context::external_authn(),
                 Arc::clone(&db_datastore),
             ),
+            external_dns: config.pkg.external_dns.as_ref().map(|config| {
+                external_dns::ExternalDns::new(
+                    &log,
+                    Arc::clone(&authz),
+                    Arc::clone(&db_datastore),
+                    config,
+                )
+            }),
+            login_rate_limiter: authn::passwords::LoginRateLimiter::new(
+                authn::passwords::LOGIN_RATE_MAX_ATTEMPTS,
+                authn::passwords::LOGIN_RATE_WINDOW,
+                authn::passwords::LOGIN_RATE_MAX_USERNAMES,
+            ),
+            device_token_ttl: chrono::Duration::minutes(
+                config.pkg.authn.device_token_ttl_minutes.into(),
+            ),
+            session_idle_timeout: chrono::Duration::minutes(
+                config.pkg.console.session_idle_timeout_minutes.into(),
+            ),
+            session_absolute_timeout: chrono::Duration::minutes(
+                config.pkg.console.session_absolute_timeout_minutes.into(),
+            ),
             samael_max_issue_delay: std::sync::Mutex::new(None),
         };
 
@@ -220,6 +260,13 @@ impl Nexus {
         );
 
         *nexus.recovery_task.lock().unwrap() = Some(recovery_task);
+
+        tokio::spawn(external_dns::external_dns_refresh_task(Arc::downgrade(
+            &nexus,
+        )));
+        tokio::spawn(session::session_cleanup_task(Arc::downgrade(&nexus)));
+        tokio::spawn(silo::saml_metadata_refresh_task(Arc::downgrade(&nexus)));
+
         nexus
     }
 
@@ -250,6 +297,17 @@ impl Nexus {
         &self.opctx_external_authn
     }
 
+    /// Returns an [`OpContext`] used for refreshing the metadata of SAML
+    /// identity providers.
+    pub fn opctx_for_saml_metadata_refresh(&self) -> OpContext {
+        OpContext::for_background(
+            self.log.new(o!("component" => "SamlMetadataRefresh")),
+            Arc::clone(&self.authz),
+            authn::Context::internal_saml_metadata_refresh(),
+            Arc::clone(&self.db_datastore),
+        )
+    }
+
     /// Returns an [`OpContext`] used for balancing services.
     pub fn opctx_for_service_balancer(&self) -> OpContext {
         OpContext::for_background(
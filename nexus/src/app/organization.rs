@@ -4,21 +4,28 @@
 
 //! Organizations, and roles contained within
 
+use crate::app::sagas;
+use crate::authn;
 use crate::authz;
 use crate::context::OpContext;
 use crate::db;
+use crate::db::identity::Resource;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Name;
 use crate::external_api::params;
 use crate::external_api::shared;
 use anyhow::Context;
+use omicron_common::api::external;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
-use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
 use omicron_common::api::external::UpdateResult;
+use std::num::NonZeroU32;
+use std::sync::Arc;
 use uuid::Uuid;
 
 impl super::Nexus {
@@ -58,29 +65,88 @@ impl super::Nexus {
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Organization> {
-        self.db_datastore.organizations_list_by_name(opctx, pagparams).await
+        self.db_datastore
+            .organizations_list_by_name(opctx, pagparams, label_selector)
+            .await
     }
 
     pub async fn organizations_list_by_id(
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Uuid>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Organization> {
-        self.db_datastore.organizations_list_by_id(opctx, pagparams).await
+        self.db_datastore
+            .organizations_list_by_id(opctx, pagparams, label_selector)
+            .await
     }
 
+    /// Deletes an Organization.  With `cascade`, every Project in the
+    /// Organization (and everything in them) is deleted first, by a saga.
+    ///
+    /// A cascading delete only starts the saga, and returns it.  The
+    /// Organization is gone once the saga has finished.
     pub async fn organization_delete(
-        &self,
+        self: &Arc<Self>,
         opctx: &OpContext,
         organization_name: &Name,
-    ) -> DeleteResult {
+        params: &params::CascadeDelete,
+    ) -> Result<Option<external::Saga>, Error> {
         let (.., authz_org, db_org) =
             LookupPath::new(opctx, &self.db_datastore)
                 .organization_name(organization_name)
                 .fetch()
                 .await?;
-        self.db_datastore.organization_delete(opctx, &authz_org, &db_org).await
+        if !params.cascade {
+            self.db_datastore
+                .organization_delete(opctx, &authz_org, &db_org)
+                .await?;
+            return Ok(None);
+        }
+
+        // Check this up front, rather than finding out that the Organization
+        // can't be deleted after having deleted all of its Projects.
+        opctx.authorize(authz::Action::Delete, &authz_org).await?;
+
+        let mut project_ids: Vec<Uuid> = Vec::new();
+        loop {
+            let pagparams = DataPageParams {
+                marker: project_ids.last(),
+                direction: dropshot::PaginationOrder::Ascending,
+                limit: NonZeroU32::new(100).unwrap(),
+            };
+            let projects = self
+                .db_datastore
+                .projects_list_by_id(opctx, &authz_org, &pagparams, None)
+                .await?;
+            if projects.is_empty() {
+                break;
+            }
+            project_ids.extend(projects.iter().map(|project| project.id()));
+        }
+
+        let mut projects = Vec::with_capacity(project_ids.len());
+        for project_id in project_ids {
+            let authz_project = authz::Project::new(
+                authz_org.clone(),
+                project_id,
+                LookupType::ById(project_id),
+            );
+            projects.push(self.project_to_delete(opctx, &authz_project).await?);
+        }
+
+        let saga_params = sagas::project_delete::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            organization_id: authz_org.id(),
+            projects,
+            delete_organization: true,
+        };
+        let saga = self
+            .start_saga::<sagas::project_delete::SagaProjectDelete>(saga_params)
+            .await?;
+        Ok(Some(saga))
     }
 
     pub async fn organization_update(
@@ -94,13 +160,22 @@ impl super::Nexus {
                 .organization_name(organization_name)
                 .lookup_for(authz::Action::Modify)
                 .await?;
-        self.db_datastore
+        let db_organization = self
+            .db_datastore
             .organization_update(
                 opctx,
                 &authz_organization,
                 new_params.clone().into(),
             )
-            .await
+            .await?;
+
+        // The names of the instances in the external DNS zone include the
+        // name of their Organization.
+        if new_params.identity.name.is_some() {
+            self.external_dns_update().await;
+        }
+
+        Ok(db_organization)
     }
 
     // Role assignments
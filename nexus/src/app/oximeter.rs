@@ -8,6 +8,8 @@ use crate::authz;
 use crate::context::OpContext;
 use crate::db;
 use crate::db::identity::Asset;
+use crate::db::lookup::LookupPath;
+use crate::external_api::params;
 use crate::external_api::params::ResourceMetrics;
 use crate::internal_api::params::OximeterInfo;
 use dropshot::PaginationParams;
@@ -15,20 +17,22 @@ use internal_dns_client::{
     multiclient::{ResolveError, Resolver},
     names::{ServiceName, SRV},
 };
-use omicron_common::address::CLICKHOUSE_PORT;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::ListResultVec;
-use omicron_common::api::external::PaginationOrder;
+use omicron_common::api::external::ResourceType;
 use omicron_common::api::internal::nexus;
 use omicron_common::backoff;
+use oximeter::types::{Datum, Field, FieldValue};
 use oximeter_client::Client as OximeterClient;
 use oximeter_db::query::Timestamp;
 use oximeter_db::Measurement;
+use oximeter_db::Timeseries;
 use oximeter_db::TimeseriesSchema;
 use oximeter_db::TimeseriesSchemaPaginationParams;
 use oximeter_producer::register;
 use slog::Logger;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::net::SocketAddr;
 use std::num::NonZeroU32;
@@ -64,11 +68,10 @@ impl LazyTimeseriesClient {
     pub async fn get(&self) -> Result<oximeter_db::Client, ResolveError> {
         let address = match &self.source {
             ClientSource::FromIp { address } => *address,
-            ClientSource::FromDns { resolver } => SocketAddr::new(
+            ClientSource::FromDns { resolver } => SocketAddr::V6(
                 resolver
-                    .lookup_ip(SRV::Service(ServiceName::Clickhouse))
+                    .lookup_socket_v6(SRV::Service(ServiceName::Clickhouse))
                     .await?,
-                CLICKHOUSE_PORT,
             ),
         };
 
@@ -94,51 +97,9 @@ impl super::Nexus {
             "address" => oximeter_info.address,
         );
 
-        // Regardless, notify the collector of any assigned metric producers. This should be empty
-        // if this Oximeter collector is registering for the first time, but may not be if the
-        // service is re-registering after failure.
-        let pagparams = DataPageParams {
-            marker: None,
-            direction: PaginationOrder::Ascending,
-            limit: std::num::NonZeroU32::new(100).unwrap(),
-        };
-        let producers = self
-            .db_datastore
-            .producers_list_by_oximeter_id(
-                oximeter_info.collector_id,
-                &pagparams,
-            )
-            .await?;
-        if !producers.is_empty() {
-            debug!(
-                self.log,
-                "registered oximeter collector that is already assigned producers, re-assigning them to the collector";
-                "n_producers" => producers.len(),
-                "collector_id" => ?oximeter_info.collector_id,
-            );
-            let client = self.build_oximeter_client(
-                &oximeter_info.collector_id,
-                oximeter_info.address,
-            );
-            for producer in producers.into_iter() {
-                let producer_info = oximeter_client::types::ProducerEndpoint {
-                    id: producer.id(),
-                    address: SocketAddr::new(
-                        producer.ip.ip(),
-                        producer.port.try_into().unwrap(),
-                    )
-                    .to_string(),
-                    base_route: producer.base_route,
-                    interval: oximeter_client::types::Duration::from(
-                        Duration::from_secs_f64(producer.interval),
-                    ),
-                };
-                client
-                    .producers_post(&producer_info)
-                    .await
-                    .map_err(Error::from)?;
-            }
-        }
+        // Note that the collector is not notified of any producers it has already been assigned
+        // here. It's expected to request those itself, through `oximeter_list_producers`, which
+        // may be the case if the collector is re-registering after a restart.
         Ok(())
     }
 
@@ -150,6 +111,29 @@ impl super::Nexus {
         self.db_datastore.oximeter_list(page_params).await
     }
 
+    /// List the metric producers assigned to an oximeter collector.
+    pub async fn oximeter_list_producers(
+        &self,
+        collector_id: Uuid,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<nexus::ProducerEndpoint> {
+        Ok(self
+            .db_datastore
+            .producers_list_by_oximeter_id(collector_id, pagparams)
+            .await?
+            .into_iter()
+            .map(|producer| nexus::ProducerEndpoint {
+                id: producer.id(),
+                address: SocketAddr::new(
+                    producer.ip.ip(),
+                    producer.port.try_into().unwrap(),
+                ),
+                base_route: producer.base_route,
+                interval: Duration::from_secs_f64(producer.interval),
+            })
+            .collect())
+    }
+
     /// Register as a metric producer with the oximeter metric collection server.
     pub async fn register_as_producer(&self, address: SocketAddr) {
         let producer_endpoint = nexus::ProducerEndpoint {
@@ -202,6 +186,43 @@ impl super::Nexus {
         Ok(())
     }
 
+    /// Unregister a metric producer, so that it is no longer collected from.
+    ///
+    /// This asks the oximeter collector to which the producer is assigned to
+    /// stop collecting from it, and then removes the record of the producer's
+    /// assignment.  The record is only removed once the collector has let go
+    /// of the producer, so a failure here can simply be retried.
+    pub async fn unassign_producer(&self, id: Uuid) -> Result<(), Error> {
+        let oximeter_id = self
+            .db_datastore
+            .producer_endpoint_oximeter_id(&id)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found_by_id(ResourceType::MetricProducer, &id)
+            })?;
+        let info = self.db_datastore.oximeter_fetch(oximeter_id).await?;
+        let address =
+            SocketAddr::from((info.ip.ip(), info.port.try_into().unwrap()));
+        let client = self.build_oximeter_client(&info.id, address);
+        match client.producer_delete(&id).await {
+            Ok(_) => {}
+            // The collector may have already stopped collecting from the
+            // producer on its own, for example if the producer has been
+            // unreachable for some time.
+            Err(oximeter_client::Error::ErrorResponse(rv))
+                if rv.status() == http::StatusCode::NOT_FOUND => {}
+            Err(e) => return Err(Error::from(e)),
+        }
+        self.db_datastore.producer_endpoint_delete(&id).await?;
+        info!(
+            self.log,
+            "unassigned producer from collector";
+            "producer_id" => ?id,
+            "collector_id" => ?oximeter_id,
+        );
+        Ok(())
+    }
+
     /// List existing timeseries schema.
     pub async fn timeseries_schema_list(
         &self,
@@ -324,6 +345,131 @@ impl super::Nexus {
         .unwrap())
     }
 
+    /// Run a query against any timeseries, returning the matching timeseries
+    /// that the caller is allowed to read.
+    ///
+    /// Timeseries whose targets identify API resources, through fields such
+    /// as `project_id` or `instance_id`, are visible only to callers that can
+    /// read every one of those resources. All other timeseries describe the
+    /// control plane itself, and require read access to the fleet.
+    /// Timeseries the caller may not read are silently omitted, just as they
+    /// would be from any other listing.
+    pub async fn timeseries_query(
+        &self,
+        opctx: &OpContext,
+        query: &params::TimeseriesQuery,
+    ) -> Result<Vec<Timeseries>, Error> {
+        opctx.authn.actor_required()?;
+        if query.start_time >= query.end_time {
+            return Err(Error::invalid_request(
+                "start_time must be earlier than end_time",
+            ));
+        }
+
+        let criteria: Vec<&str> =
+            query.criteria.iter().map(String::as_str).collect();
+        let client = self.timeseries_client.get().await.map_err(|e| {
+            Error::internal_error(&format!(
+                "Cannot access timeseries DB: {}",
+                e
+            ))
+        })?;
+        let mut matches = match client
+            .select_timeseries_matching(
+                &query.timeseries_name,
+                &criteria,
+                Some(Timestamp::Inclusive(query.start_time)),
+                Some(Timestamp::Exclusive(query.end_time)),
+                NonZeroU32::new(MAX_TIMESERIES_QUERY_MEASUREMENTS),
+            )
+            .await
+        {
+            Ok(matches) => matches,
+            // As with `select_timeseries`, a valid name that isn't in the
+            // database yet just has no data.
+            Err(oximeter_db::Error::TimeseriesNotFound(_)) => {
+                return Ok(vec![])
+            }
+            Err(e) => return Err(map_oximeter_err(e)),
+        };
+
+        // Drop the timeseries the caller may not read before selecting any
+        // measurements, so that those timeseries neither count against the
+        // limit on measurements nor cost anything to fetch.  Many timeseries
+        // usually share the same resources (e.g., all the instances of one
+        // project), so each authz decision is made once.
+        let mut decisions = BTreeMap::new();
+        for target in matches.targets() {
+            for scope in timeseries_scopes(target) {
+                if !decisions.contains_key(&scope) {
+                    let visible = self.scope_visible(opctx, scope).await?;
+                    decisions.insert(scope, visible);
+                }
+            }
+        }
+        matches.retain_targets(|target| {
+            timeseries_scopes(target).iter().all(|scope| decisions[scope])
+        });
+        let visible = client
+            .select_measurements(&matches)
+            .await
+            .map_err(map_oximeter_err)?;
+
+        match &query.aggregation {
+            None => Ok(visible),
+            Some(aggregation) => visible
+                .into_iter()
+                .map(|timeseries| {
+                    aggregate_timeseries(
+                        timeseries,
+                        query.start_time,
+                        aggregation,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    // Return whether the caller may read the resource identified by `scope`.
+    async fn scope_visible(
+        &self,
+        opctx: &OpContext,
+        scope: TimeseriesScope,
+    ) -> Result<bool, Error> {
+        let lookup = LookupPath::new(opctx, &self.db_datastore);
+        let action = authz::Action::Read;
+        let result = match scope {
+            TimeseriesScope::Fleet => {
+                opctx.authorize(action, &authz::FLEET).await
+            }
+            TimeseriesScope::Silo(id) => {
+                lookup.silo_id(id).lookup_for(action).await.map(|_| ())
+            }
+            TimeseriesScope::Organization(id) => {
+                lookup.organization_id(id).lookup_for(action).await.map(|_| ())
+            }
+            TimeseriesScope::Project(id) => {
+                lookup.project_id(id).lookup_for(action).await.map(|_| ())
+            }
+            TimeseriesScope::Instance(id) => {
+                lookup.instance_id(id).lookup_for(action).await.map(|_| ())
+            }
+            TimeseriesScope::Disk(id) => {
+                lookup.disk_id(id).lookup_for(action).await.map(|_| ())
+            }
+            TimeseriesScope::Vpc(id) => {
+                lookup.vpc_id(id).lookup_for(action).await.map(|_| ())
+            }
+        };
+        match result {
+            Ok(()) => Ok(true),
+            Err(Error::ObjectNotFound { .. }) | Err(Error::Forbidden) => {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     // Internal helper to build an Oximeter client from its ID and address (common data between
     // model type and the API type).
     fn build_oximeter_client(
@@ -367,6 +513,122 @@ fn map_oximeter_err(error: oximeter_db::Error) -> Error {
         oximeter_db::Error::DatabaseUnavailable(_) => {
             Error::ServiceUnavailable { internal_message: error.to_string() }
         }
+        oximeter_db::Error::InvalidTimeseriesName
+        | oximeter_db::Error::NoSuchField { .. }
+        | oximeter_db::Error::IncorrectFieldType { .. }
+        | oximeter_db::Error::UnknownFieldComparison
+        | oximeter_db::Error::InvalidFieldSelectorString { .. }
+        | oximeter_db::Error::InvalidFieldValue { .. }
+        | oximeter_db::Error::InvalidFieldCmp { .. }
+        | oximeter_db::Error::InvalidSelectionOp { .. }
+        | oximeter_db::Error::QuerySyntax { .. }
+        | oximeter_db::Error::InvalidQuery(_) => {
+            Error::invalid_request(&error.to_string())
+        }
         _ => Error::InternalError { internal_message: error.to_string() },
     }
 }
+
+/// The maximum number of measurements returned by a timeseries query, across
+/// all matching timeseries.
+const MAX_TIMESERIES_QUERY_MEASUREMENTS: u32 = 10_000;
+
+/// An API resource that a timeseries describes, identified by one of the
+/// fields of the timeseries' target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum TimeseriesScope {
+    /// The timeseries describes the control plane, not any API resource.
+    Fleet,
+    Silo(Uuid),
+    Organization(Uuid),
+    Project(Uuid),
+    Instance(Uuid),
+    Disk(Uuid),
+    Vpc(Uuid),
+}
+
+impl TimeseriesScope {
+    fn from_field(field: &Field) -> Option<Self> {
+        let id = match field.value {
+            FieldValue::Uuid(id) => id,
+            _ => return None,
+        };
+        match field.name.as_str() {
+            "silo_id" => Some(TimeseriesScope::Silo(id)),
+            "organization_id" => Some(TimeseriesScope::Organization(id)),
+            "project_id" => Some(TimeseriesScope::Project(id)),
+            "instance_id" => Some(TimeseriesScope::Instance(id)),
+            // The Crucible upstairs is identified by the disk it backs.
+            "disk_id" | "upstairs_uuid" => Some(TimeseriesScope::Disk(id)),
+            "vpc_id" => Some(TimeseriesScope::Vpc(id)),
+            _ => None,
+        }
+    }
+}
+
+// Return the resources that a caller must be able to read in order to read the
+// timeseries with the given target.
+fn timeseries_scopes(target: &oximeter_db::Target) -> Vec<TimeseriesScope> {
+    let mut scopes: Vec<TimeseriesScope> =
+        target.fields.iter().filter_map(TimeseriesScope::from_field).collect();
+    if scopes.is_empty() {
+        scopes.push(TimeseriesScope::Fleet);
+    }
+    scopes
+}
+
+// Combine the measurements of `timeseries` into one measurement per interval
+// of the aggregation, with intervals starting at `start_time`.
+fn aggregate_timeseries(
+    mut timeseries: Timeseries,
+    start_time: chrono::DateTime<chrono::Utc>,
+    aggregation: &params::TimeseriesAggregation,
+) -> Result<Timeseries, Error> {
+    let interval_ms = i64::from(aggregation.interval_seconds.get()) * 1000;
+    let mut buckets: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+    for measurement in &timeseries.measurements {
+        let value = match measurement.datum() {
+            Datum::I64(x) => *x as f64,
+            Datum::F64(x) => *x,
+            Datum::CumulativeI64(x) => x.value() as f64,
+            Datum::CumulativeF64(x) => x.value(),
+            datum => {
+                return Err(Error::invalid_request(&format!(
+                    "cannot aggregate timeseries '{}' with datum type {}",
+                    timeseries.timeseries_name,
+                    datum.datum_type(),
+                )));
+            }
+        };
+        let offset = measurement.timestamp() - start_time;
+        let index = offset.num_milliseconds() / interval_ms;
+        buckets.entry(index).or_default().push(value);
+    }
+
+    timeseries.measurements = buckets
+        .into_iter()
+        .map(|(index, values)| {
+            let timestamp = start_time
+                + chrono::Duration::milliseconds(index * interval_ms);
+            let datum = match aggregation.method {
+                params::TimeseriesAggregationMethod::Count => {
+                    Datum::I64(values.len() as i64)
+                }
+                params::TimeseriesAggregationMethod::Sum => {
+                    Datum::F64(values.iter().sum())
+                }
+                params::TimeseriesAggregationMethod::Mean => {
+                    Datum::F64(values.iter().sum::<f64>() / values.len() as f64)
+                }
+                params::TimeseriesAggregationMethod::Min => Datum::F64(
+                    values.iter().copied().fold(f64::INFINITY, f64::min),
+                ),
+                params::TimeseriesAggregationMethod::Max => Datum::F64(
+                    values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                ),
+            };
+            Measurement::with_timestamp(timestamp, datum)
+        })
+        .collect();
+    Ok(timeseries)
+}
@@ -4,23 +4,30 @@
 
 //! Project APIs, contained within organizations
 
+use crate::app::sagas;
+use crate::authn;
 use crate::authz;
 use crate::context::OpContext;
 use crate::db;
+use crate::db::identity::Resource;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Name;
 use crate::external_api::params;
 use crate::external_api::shared;
 use anyhow::Context;
 use nexus_defaults as defaults;
+use omicron_common::api::external;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
-use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
 use omicron_common::api::external::UpdateResult;
+use std::num::NonZeroU32;
+use std::sync::Arc;
 use uuid::Uuid;
 
 impl super::Nexus {
@@ -67,6 +74,7 @@ impl super::Nexus {
                     // handle the logic around name and dns_name by making
                     // dns_name optional
                     dns_name: "default".parse().unwrap(),
+                    labels: Default::default(),
                 },
             )
             .await?;
@@ -105,13 +113,14 @@ impl super::Nexus {
         opctx: &OpContext,
         organization_name: &Name,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Project> {
         let (.., authz_org) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
             .lookup_for(authz::Action::ListChildren)
             .await?;
         self.db_datastore
-            .projects_list_by_name(opctx, &authz_org, pagparams)
+            .projects_list_by_name(opctx, &authz_org, pagparams, label_selector)
             .await
     }
 
@@ -120,13 +129,14 @@ impl super::Nexus {
         opctx: &OpContext,
         organization_name: &Name,
         pagparams: &DataPageParams<'_, Uuid>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Project> {
         let (.., authz_org) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
             .lookup_for(authz::Action::ListChildren)
             .await?;
         self.db_datastore
-            .projects_list_by_id(opctx, &authz_org, pagparams)
+            .projects_list_by_id(opctx, &authz_org, pagparams, label_selector)
             .await
     }
 
@@ -142,23 +152,129 @@ impl super::Nexus {
             .project_name(project_name)
             .lookup_for(authz::Action::Modify)
             .await?;
-        self.db_datastore
+        let db_project = self
+            .db_datastore
             .project_update(opctx, &authz_project, new_params.clone().into())
-            .await
+            .await?;
+
+        // The names of the project's instances in the external DNS zone
+        // include the name of the project.
+        if new_params.identity.name.is_some() {
+            self.external_dns_update().await;
+        }
+
+        Ok(db_project)
     }
 
-    pub async fn project_delete(
+    pub async fn project_move(
         &self,
         opctx: &OpContext,
         organization_name: &Name,
         project_name: &Name,
-    ) -> DeleteResult {
-        let (.., authz_project) = LookupPath::new(opctx, &self.db_datastore)
-            .organization_name(organization_name)
-            .project_name(project_name)
-            .lookup_for(authz::Action::Delete)
+        params: &params::ProjectMove,
+    ) -> UpdateResult<db::model::Project> {
+        let (.., authz_src_org, authz_project, db_project) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .fetch_for(authz::Action::Modify)
+                .await?;
+        let (.., authz_dst_org) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(&params.organization.clone().into())
+            .lookup_for(authz::Action::CreateChild)
+            .await?;
+        let db_project = self
+            .db_datastore
+            .project_move(
+                opctx,
+                &authz_src_org,
+                &authz_project,
+                &db_project,
+                &authz_dst_org,
+            )
+            .await?;
+
+        // The names of the project's instances in the external DNS zone
+        // include the name of its Organization.
+        self.external_dns_update().await;
+
+        Ok(db_project)
+    }
+
+    /// Deletes a Project.  With `cascade`, everything in the Project is
+    /// deleted first, by a saga.
+    ///
+    /// A cascading delete only starts the saga, and returns it.  The Project
+    /// is gone once the saga has finished.
+    pub async fn project_delete(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        params: &params::CascadeDelete,
+    ) -> Result<Option<external::Saga>, Error> {
+        let (.., authz_org, authz_project) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .organization_name(organization_name)
+                .project_name(project_name)
+                .lookup_for(authz::Action::Delete)
+                .await?;
+        if !params.cascade {
+            self.db_datastore.project_delete(opctx, &authz_project).await?;
+            return Ok(None);
+        }
+
+        let project = self.project_to_delete(opctx, &authz_project).await?;
+        let saga_params = sagas::project_delete::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            organization_id: authz_org.id(),
+            projects: vec![project],
+            delete_organization: false,
+        };
+        let saga = self
+            .start_saga::<sagas::project_delete::SagaProjectDelete>(saga_params)
             .await?;
-        self.db_datastore.project_delete(opctx, &authz_project).await
+        Ok(Some(saga))
+    }
+
+    /// Lists the disks that a cascading delete of `authz_project` has to
+    /// delete, after checking that the caller may delete them
+    pub(crate) async fn project_to_delete(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+    ) -> Result<sagas::project_delete::ProjectToDelete, Error> {
+        let mut disk_ids: Vec<Uuid> = Vec::new();
+        let mut marker: Option<Name> = None;
+        loop {
+            let pagparams = DataPageParams {
+                marker: marker.as_ref(),
+                direction: dropshot::PaginationOrder::Ascending,
+                limit: NonZeroU32::new(100).unwrap(),
+            };
+            let disks = self
+                .db_datastore
+                .project_list_disks(opctx, authz_project, &pagparams, None)
+                .await?;
+            match disks.last() {
+                Some(disk) => marker = Some(Name(disk.name().clone())),
+                None => break,
+            }
+            for disk in disks {
+                let authz_disk = authz::Disk::new(
+                    authz_project.clone(),
+                    disk.id(),
+                    LookupType::ById(disk.id()),
+                );
+                opctx.authorize(authz::Action::Delete, &authz_disk).await?;
+                disk_ids.push(disk.id());
+            }
+        }
+
+        Ok(sagas::project_delete::ProjectToDelete {
+            project_id: authz_project.id(),
+            disk_ids,
+        })
     }
 
     // Role assignments
@@ -11,6 +11,7 @@ use crate::authz;
 use crate::context::OpContext;
 use crate::saga_interface::SagaContext;
 use anyhow::Context;
+use futures::future::BoxFuture;
 use futures::StreamExt;
 use omicron_common::api::external;
 use omicron_common::api::external::DataPageParams;
@@ -24,6 +25,7 @@ use steno::DagBuilder;
 use steno::SagaDag;
 use steno::SagaId;
 use steno::SagaName;
+use steno::SagaResult;
 use steno::SagaResultOk;
 use uuid::Uuid;
 
@@ -71,6 +73,49 @@ impl super::Nexus {
         self: &Arc<Self>,
         params: N::Params,
     ) -> Result<SagaResultOk, Error> {
+        let (_, future) = self.create_and_start_saga::<N>(params).await?;
+        saga_result_to_result(future.await)
+    }
+
+    /// Given a saga type and parameters, create a new saga and start it,
+    /// without waiting for it to finish.
+    ///
+    /// Returns the saga as it is right after starting.  Its progress can be
+    /// followed with [`Self::saga_get`].  Its outcome is only logged here.
+    pub(crate) async fn start_saga<N: NexusSaga>(
+        self: &Arc<Self>,
+        params: N::Params,
+    ) -> Result<external::Saga, Error> {
+        let (saga_id, future) = self.create_and_start_saga::<N>(params).await?;
+        let log = self.log.new(o!(
+            "saga_name" => N::NAME,
+            "saga_id" => saga_id.to_string()
+        ));
+        tokio::spawn(async move {
+            match saga_result_to_result(future.await) {
+                Ok(_) => info!(log, "saga finished"),
+                Err(error) => {
+                    error!(log, "saga failed"; "error" => #%error)
+                }
+            }
+        });
+
+        self.sec_client
+            .saga_get(saga_id)
+            .await
+            .map(external::Saga::from)
+            .map_err(|_: ()| {
+                Error::internal_error(&format!(
+                    "saga {} not found right after starting it",
+                    saga_id
+                ))
+            })
+    }
+
+    async fn create_and_start_saga<N: NexusSaga>(
+        self: &Arc<Self>,
+        params: N::Params,
+    ) -> Result<(SagaId, BoxFuture<'static, SagaResult>), Error> {
         let saga = {
             let builder = DagBuilder::new(SagaName::new(N::NAME));
             let dag = N::make_saga_dag(&params, builder)?;
@@ -113,16 +158,19 @@ impl super::Nexus {
             .context("starting saga")
             .map_err(|error| Error::internal_error(&format!("{:#}", error)))?;
 
-        let result = future.await;
-        result.kind.map_err(|saga_error| {
-            saga_error
-                .error_source
-                .convert::<Error>()
-                .unwrap_or_else(|e| Error::internal_error(&e.to_string()))
-                .internal_context(format!(
-                    "saga error at node {:?}",
-                    saga_error.error_node_name
-                ))
-        })
+        Ok((saga_id, future))
     }
 }
+
+fn saga_result_to_result(result: SagaResult) -> Result<SagaResultOk, Error> {
+    result.kind.map_err(|saga_error| {
+        saga_error
+            .error_source
+            .convert::<Error>()
+            .unwrap_or_else(|e| Error::internal_error(&e.to_string()))
+            .internal_context(format!(
+                "saga error at node {:?}",
+                saga_error.error_node_name
+            ))
+    })
+}
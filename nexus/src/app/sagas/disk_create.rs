@@ -142,7 +142,8 @@ async fn sdc_create_disk_record(
         .create_params
         .disk_source
     {
-        params::DiskSource::Blank { block_size } => {
+        params::DiskSource::Blank { block_size }
+        | params::DiskSource::ImportingBlocks { block_size } => {
             db::model::BlockSize::try_from(*block_size).map_err(|e| {
                 ActionError::action_failed(Error::internal_error(
                     &e.to_string(),
@@ -267,7 +268,8 @@ async fn sdc_regions_ensure(
 
     let mut read_only_parent: Option<Box<VolumeConstructionRequest>> =
         match &params.create_params.disk_source {
-            params::DiskSource::Blank { block_size: _ } => None,
+            params::DiskSource::Blank { block_size: _ }
+            | params::DiskSource::ImportingBlocks { block_size: _ } => None,
             params::DiskSource::Snapshot { snapshot_id } => {
                 debug!(log, "grabbing snapshot {}", snapshot_id);
 
@@ -487,12 +489,17 @@ async fn sdc_finalize_disk_record(
     // Action::Modify on Disks within the Project.  So this shouldn't break in
     // practice.  However, that's brittle.  It would be better if this were
     // better guaranteed.
+    //
+    // Disks being imported wait in the "importing" state until the import is
+    // finalized.
+    let runtime = match params.create_params.disk_source {
+        params::DiskSource::ImportingBlocks { .. } => {
+            disk_created.runtime().importing()
+        }
+        _ => disk_created.runtime().detach(),
+    };
     datastore
-        .disk_update_runtime(
-            &opctx,
-            &authz_disk,
-            &disk_created.runtime().detach(),
-        )
+        .disk_update_runtime(&opctx, &authz_disk, &runtime)
         .await
         .map_err(ActionError::action_failed)?;
 
@@ -0,0 +1,436 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Growing a disk
+//!
+//! Crucible regions can't be resized, so a disk grows by appending a new
+//! sub-volume (backed by newly-allocated regions) to its volume.  If the disk's
+//! existing regions already have room for the new size (because regions are
+//! allocated in whole extents), only the disk record changes.
+//!
+//! If the disk is attached to a running instance, the instance's sled agent is
+//! given the grown volume, so that the guest sees the new size right away.
+
+use super::{
+    common_storage::delete_crucible_regions,
+    common_storage::ensure_all_datasets_and_regions, ActionRegistry,
+    NexusActionContext, NexusSaga, SagaInitError, ACTION_GENERATE_ID,
+};
+use crate::app::sagas::NexusAction;
+use crate::context::OpContext;
+use crate::db::identity::{Asset, Resource};
+use crate::db::lookup::LookupPath;
+use crate::{authn, authz, db};
+use lazy_static::lazy_static;
+use omicron_common::api::external::ByteCount;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::InstanceState;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde::Deserialize;
+use serde::Serialize;
+use sled_agent_client::types::{
+    CrucibleOpts, InstanceDiskVolumeBody, VolumeConstructionRequest,
+};
+use std::sync::Arc;
+use steno::ActionError;
+use steno::ActionFunc;
+use steno::Node;
+use uuid::Uuid;
+
+// disk resize saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub disk_id: Uuid,
+    pub block_size: db::model::BlockSize,
+    pub old_size: ByteCount,
+    pub new_size: ByteCount,
+    /// how much space must be allocated beyond what the disk's regions already
+    /// hold, if any
+    pub extension_size: Option<ByteCount>,
+}
+
+// disk resize saga: actions
+
+lazy_static! {
+    static ref REGIONS_ALLOC: NexusAction = ActionFunc::new_action(
+        "disk-resize.regions-alloc",
+        sdr_alloc_regions,
+        sdr_alloc_regions_undo,
+    );
+    static ref REGIONS_ENSURE: NexusAction = ActionFunc::new_action(
+        "disk-resize.regions-ensure",
+        sdr_regions_ensure,
+        sdr_regions_ensure_undo,
+    );
+    static ref RESIZE_DISK_RECORD: NexusAction = ActionFunc::new_action(
+        "disk-resize.resize-disk-record",
+        sdr_resize_disk_record,
+        sdr_resize_disk_record_undo,
+    );
+    static ref NOTIFY_SLED_AGENT: NexusAction = ActionFunc::new_action(
+        "disk-resize.notify-sled-agent",
+        sdr_notify_sled_agent,
+        sdr_notify_sled_agent_undo,
+    );
+}
+
+// disk resize saga: definition
+
+#[derive(Debug)]
+pub struct SagaDiskResize;
+impl NexusSaga for SagaDiskResize {
+    const NAME: &'static str = "disk-resize";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        registry.register(Arc::clone(&*REGIONS_ALLOC));
+        registry.register(Arc::clone(&*REGIONS_ENSURE));
+        registry.register(Arc::clone(&*RESIZE_DISK_RECORD));
+        registry.register(Arc::clone(&*NOTIFY_SLED_AGENT));
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        // The new regions are allocated under their own id until they become
+        // part of the disk's volume.  This id is also the id of the sub-volume
+        // that they make up.
+        builder.append(Node::action(
+            "extension_id",
+            "GenerateExtensionId",
+            ACTION_GENERATE_ID.as_ref(),
+        ));
+
+        if params.extension_size.is_some() {
+            builder.append(Node::action(
+                "datasets_and_regions",
+                "RegionsAlloc",
+                REGIONS_ALLOC.as_ref(),
+            ));
+
+            builder.append(Node::action(
+                "extension",
+                "RegionsEnsure",
+                REGIONS_ENSURE.as_ref(),
+            ));
+        }
+
+        builder.append(Node::action(
+            "resized_disk",
+            "ResizeDiskRecord",
+            RESIZE_DISK_RECORD.as_ref(),
+        ));
+
+        builder.append(Node::action(
+            "no_result",
+            "NotifySledAgent",
+            NOTIFY_SLED_AGENT.as_ref(),
+        ));
+
+        Ok(builder.build()?)
+    }
+}
+
+// disk resize saga: action implementations
+
+async fn sdr_alloc_regions(
+    sagactx: NexusActionContext,
+) -> Result<Vec<(db::model::Dataset, db::model::Region)>, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let extension_id = sagactx.lookup::<Uuid>("extension_id")?;
+    let extension_size = params.extension_size.ok_or_else(|| {
+        ActionError::action_failed(Error::internal_error(
+            "allocating regions for a resize that needs none",
+        ))
+    })?;
+
+    osagactx
+        .datastore()
+        .region_allocate_with_block_size(
+            extension_id,
+            params.block_size,
+            extension_size,
+        )
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn sdr_alloc_regions_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let datasets_and_regions = sagactx
+        .lookup::<Vec<(db::model::Dataset, db::model::Region)>>(
+            "datasets_and_regions",
+        )?;
+    osagactx
+        .datastore()
+        .regions_hard_delete(
+            datasets_and_regions
+                .into_iter()
+                .map(|(_, region)| region.id())
+                .collect(),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Call out to Crucible agents to create the new regions, returning the
+/// sub-volume that they make up.
+async fn sdr_regions_ensure(
+    sagactx: NexusActionContext,
+) -> Result<VolumeConstructionRequest, ActionError> {
+    let log = sagactx.user_data().log();
+    let extension_id = sagactx.lookup::<Uuid>("extension_id")?;
+
+    let datasets_and_regions = ensure_all_datasets_and_regions(
+        &log,
+        sagactx.lookup::<Vec<(db::model::Dataset, db::model::Region)>>(
+            "datasets_and_regions",
+        )?,
+    )
+    .await?;
+
+    let block_size = datasets_and_regions[0].1.block_size;
+
+    let mut rng = StdRng::from_entropy();
+    Ok(VolumeConstructionRequest::Region {
+        block_size,
+        // gen of 0 is here, these regions were just allocated.
+        gen: 0,
+        opts: CrucibleOpts {
+            id: extension_id,
+            target: datasets_and_regions
+                .iter()
+                .map(|(dataset, region)| {
+                    dataset.address_with_port(region.port_number).to_string()
+                })
+                .collect(),
+
+            lossy: false,
+            flush_timeout: None,
+
+            // all downstairs will expect encrypted blocks
+            key: Some(base64::encode({
+                let mut random_bytes: [u8; 32] = [0; 32];
+                rng.fill_bytes(&mut random_bytes);
+                random_bytes
+            })),
+
+            cert_pem: None,
+            key_pem: None,
+            root_cert_pem: None,
+
+            control: None,
+
+            read_only: false,
+        },
+    })
+}
+
+async fn sdr_regions_ensure_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let datasets_and_regions = sagactx
+        .lookup::<Vec<(db::model::Dataset, db::model::Region)>>(
+            "datasets_and_regions",
+        )?;
+    delete_crucible_regions(datasets_and_regions).await?;
+    Ok(())
+}
+
+async fn sdr_resize_disk_record(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let datastore = osagactx.datastore();
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+
+    let extension_id = sagactx.lookup::<Uuid>("extension_id")?;
+    let extension = if params.extension_size.is_some() {
+        Some(sagactx.lookup::<VolumeConstructionRequest>("extension")?)
+    } else {
+        None
+    };
+
+    let (.., authz_disk) = LookupPath::new(&opctx, &datastore)
+        .disk_id(params.disk_id)
+        .lookup_for(authz::Action::Modify)
+        .await
+        .map_err(ActionError::action_failed)?;
+    datastore
+        .disk_resize(
+            &opctx,
+            &authz_disk,
+            params.old_size,
+            params.new_size,
+            extension_id,
+            extension,
+        )
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn sdr_resize_disk_record_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let datastore = osagactx.datastore();
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+
+    let extension_id = sagactx.lookup::<Uuid>("extension_id")?;
+    let extension_region_ids = if params.extension_size.is_some() {
+        sagactx
+            .lookup::<Vec<(db::model::Dataset, db::model::Region)>>(
+                "datasets_and_regions",
+            )?
+            .into_iter()
+            .map(|(_, region)| region.id())
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let (.., authz_disk) = LookupPath::new(&opctx, &datastore)
+        .disk_id(params.disk_id)
+        .lookup_for(authz::Action::Modify)
+        .await?;
+    datastore
+        .disk_resize_undo(
+            &opctx,
+            &authz_disk,
+            params.old_size,
+            params.new_size,
+            extension_id,
+            extension_region_ids,
+        )
+        .await?;
+    Ok(())
+}
+
+/// If the disk is attached to an instance that's on a sled, give that sled's
+/// agent the disk's grown volume.  Otherwise, the instance will get it the
+/// next time it starts.
+async fn sdr_notify_sled_agent(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    notify_sled_agent(&sagactx, &params, |volume| volume)
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn sdr_notify_sled_agent_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let params = sagactx.saga_params::<Params>()?;
+    let extension_id = sagactx.lookup::<Uuid>("extension_id")?;
+
+    // The disk's volume record hasn't been put back yet, so give the sled
+    // agent the volume as it was before the extension was appended.
+    notify_sled_agent(&sagactx, &params, |volume| match volume {
+        VolumeConstructionRequest::Volume {
+            id,
+            block_size,
+            sub_volumes,
+            read_only_parent,
+        } => VolumeConstructionRequest::Volume {
+            id,
+            block_size,
+            sub_volumes: sub_volumes
+                .into_iter()
+                .filter(|sub_volume| match sub_volume {
+                    VolumeConstructionRequest::Region { opts, .. } => {
+                        opts.id != extension_id
+                    }
+                    _ => true,
+                })
+                .collect(),
+            read_only_parent,
+        },
+        volume => volume,
+    })
+    .await?;
+    Ok(())
+}
+
+/// If the disk is attached to an instance that's on a sled, send that sled's
+/// agent the disk's volume, as transformed by `volume_for_instance`
+async fn notify_sled_agent(
+    sagactx: &NexusActionContext,
+    params: &Params,
+    volume_for_instance: impl FnOnce(
+        VolumeConstructionRequest,
+    ) -> VolumeConstructionRequest,
+) -> Result<(), Error> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let datastore = osagactx.datastore();
+    let opctx = OpContext::for_saga_action(sagactx, &params.serialized_authn);
+
+    let (.., disk) = LookupPath::new(&opctx, &datastore)
+        .disk_id(params.disk_id)
+        .fetch()
+        .await?;
+    let instance_id = match disk.runtime().attach_instance_id {
+        Some(instance_id) => instance_id,
+        None => {
+            info!(log, "disk {} not attached to an instance", disk.id());
+            return Ok(());
+        }
+    };
+
+    let (.., instance) = LookupPath::new(&opctx, &datastore)
+        .instance_id(instance_id)
+        .fetch()
+        .await?;
+    match instance.runtime().state.state() {
+        InstanceState::Starting
+        | InstanceState::Running
+        | InstanceState::Stopping
+        | InstanceState::Rebooting
+        | InstanceState::Migrating => (),
+        state => {
+            info!(
+                log,
+                "instance {} is {}; not notifying sled agent of resized disk {}",
+                instance_id,
+                state,
+                disk.id()
+            );
+            return Ok(());
+        }
+    }
+
+    let volume = datastore.volume_get(disk.volume_id).await?;
+    let volume_construction_request = serde_json::from_str(volume.data())
+        .map_err(|e| {
+            Error::internal_error(&format!(
+                "failed to deserialize volume data: {}",
+                e,
+            ))
+        })?;
+
+    let sled_agent_client = osagactx.nexus().instance_sled(&instance).await?;
+    sled_agent_client
+        .instance_disk_volume_put(
+            &instance.id(),
+            &disk.id(),
+            &InstanceDiskVolumeBody {
+                volume_construction_request: volume_for_instance(
+                    volume_construction_request,
+                ),
+            },
+        )
+        .await
+        .map_err(Error::from)?;
+
+    Ok(())
+}
@@ -21,8 +21,10 @@ use uuid::Uuid;
 
 pub mod disk_create;
 pub mod disk_delete;
+pub mod disk_resize;
 pub mod instance_create;
 pub mod instance_migrate;
+pub mod project_delete;
 pub mod snapshot_create;
 pub mod volume_delete;
 pub mod volume_remove_rop;
@@ -92,12 +94,16 @@ fn make_action_registry() -> ActionRegistry {
 
     <disk_create::SagaDiskCreate as NexusSaga>::register_actions(&mut registry);
     <disk_delete::SagaDiskDelete as NexusSaga>::register_actions(&mut registry);
+    <disk_resize::SagaDiskResize as NexusSaga>::register_actions(&mut registry);
     <instance_create::SagaInstanceCreate as NexusSaga>::register_actions(
         &mut registry,
     );
     <instance_migrate::SagaInstanceMigrate as NexusSaga>::register_actions(
         &mut registry,
     );
+    <project_delete::SagaProjectDelete as NexusSaga>::register_actions(
+        &mut registry,
+    );
     <snapshot_create::SagaSnapshotCreate as NexusSaga>::register_actions(
         &mut registry,
     );
@@ -0,0 +1,743 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Cascading deletion of Projects (and optionally their Organization)
+//!
+//! Each Project is torn down by a sequence of subsagas, which remove the
+//! Project's children in dependency order: instances are stopped and deleted
+//! (releasing their network interfaces, external IPs and disk attachments)
+//! and snapshots are deleted, then each of the Project's disks is deleted by a
+//! disk delete subsaga, and finally VPCs (with their subnets, routers and
+//! firewall rules) are deleted, followed by the Project itself.  When an
+//! Organization is being deleted, it goes last, once all of its Projects are
+//! gone.
+//!
+//! The disk delete subsagas are part of the DAG, so the disks have to be
+//! listed when the saga is created (see [`ProjectToDelete`]).  A disk created
+//! in the Project after that makes the cascade fail before deleting the
+//! Project record.
+//!
+//! Every other action lists whatever children still exist when it runs and
+//! deletes those, so re-running an action after a Nexus restart (via saga
+//! recovery) picks up where the previous attempt left off.  There's nothing
+//! sensible to undo once something has been deleted, so undo actions are
+//! no-ops: a failed cascade leaves behind whatever it didn't get to, and can be
+//! retried.
+//!
+//! The DELETE request that starts this saga returns it right away, without
+//! waiting for it.  Its progress can be followed under `/system/sagas`, and
+//! each action logs how far it has got.
+//!
+//! TODO-completeness: Project images are not implemented yet, so there are
+//! none to delete here.
+
+use super::{ActionRegistry, NexusActionContext, NexusSaga, SagaInitError};
+use crate::app::sagas::disk_delete;
+use crate::app::sagas::NexusAction;
+use crate::context::OpContext;
+use crate::db::identity::Resource;
+use crate::db::lookup::LookupPath;
+use crate::db::model::Name;
+use crate::{authn, authz, db};
+use lazy_static::lazy_static;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::InstanceState;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupType;
+use omicron_common::backoff::{self, BackoffError};
+use serde::Deserialize;
+use serde::Serialize;
+use sled_agent_client::types::InstanceRuntimeStateRequested;
+use sled_agent_client::types::InstanceStateRequested;
+use slog::info;
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use steno::new_action_noop_undo;
+use steno::ActionError;
+use steno::DagBuilder;
+use steno::Node;
+use steno::SagaName;
+use uuid::Uuid;
+
+/// Number of children fetched per database query while listing them
+const LIST_BATCH_SIZE: u32 = 100;
+
+/// How long to wait for a Project's instances to stop before giving up
+const INSTANCE_STOP_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// project delete saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub organization_id: Uuid,
+    pub projects: Vec<ProjectToDelete>,
+    /// whether to delete the Organization after all of `projects`
+    pub delete_organization: bool,
+}
+
+/// A Project to delete, along with the disks in it
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProjectToDelete {
+    pub project_id: Uuid,
+    /// the disks to delete with disk delete subsagas, which the caller must
+    /// be authorized to delete
+    pub disk_ids: Vec<Uuid>,
+}
+
+// The subsagas that delete each Project's instances, snapshots and VPCs, and
+// the Project itself, take parameters identifying the Project.
+#[derive(Debug, Deserialize, Serialize)]
+struct ProjectParams {
+    serialized_authn: authn::saga::Serialized,
+    project_id: Uuid,
+}
+
+// project delete saga: actions
+
+lazy_static! {
+    static ref STOP_INSTANCES: NexusAction = new_action_noop_undo(
+        "project-delete.stop-instances",
+        spd_stop_instances
+    );
+    static ref WAIT_INSTANCES_STOPPED: NexusAction = new_action_noop_undo(
+        "project-delete.wait-instances-stopped",
+        spd_wait_instances_stopped
+    );
+    static ref DELETE_INSTANCES: NexusAction = new_action_noop_undo(
+        "project-delete.delete-instances",
+        spd_delete_instances
+    );
+    static ref DELETE_SNAPSHOTS: NexusAction = new_action_noop_undo(
+        "project-delete.delete-snapshots",
+        spd_delete_snapshots
+    );
+    static ref DELETE_VPCS: NexusAction =
+        new_action_noop_undo("project-delete.delete-vpcs", spd_delete_vpcs);
+    static ref DELETE_PROJECT_RECORD: NexusAction = new_action_noop_undo(
+        "project-delete.delete-project-record",
+        spd_delete_project_record
+    );
+    static ref DELETE_ORGANIZATION_RECORD: NexusAction = new_action_noop_undo(
+        "project-delete.delete-organization-record",
+        spd_delete_organization_record
+    );
+}
+
+// project delete saga: definition
+
+#[derive(Debug)]
+pub struct SagaProjectDelete;
+impl NexusSaga for SagaProjectDelete {
+    const NAME: &'static str = "project-delete";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        registry.register(Arc::clone(&*STOP_INSTANCES));
+        registry.register(Arc::clone(&*WAIT_INSTANCES_STOPPED));
+        registry.register(Arc::clone(&*DELETE_INSTANCES));
+        registry.register(Arc::clone(&*DELETE_SNAPSHOTS));
+        registry.register(Arc::clone(&*DELETE_VPCS));
+        registry.register(Arc::clone(&*DELETE_PROJECT_RECORD));
+        registry.register(Arc::clone(&*DELETE_ORGANIZATION_RECORD));
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        // Projects are deleted one after another, rather than in parallel, so
+        // that the saga's progress through the list is easy to follow.
+        for (i, project) in params.projects.iter().enumerate() {
+            let project_params = ProjectParams {
+                serialized_authn: params.serialized_authn.clone(),
+                project_id: project.project_id,
+            };
+            let params_node_name = format!("project_params{}", i);
+            builder.append(Node::constant(
+                &params_node_name,
+                serde_json::to_value(&project_params).map_err(|e| {
+                    SagaInitError::SerializeError(params_node_name.clone(), e)
+                })?,
+            ));
+
+            // First get rid of the instances (which may have the disks
+            // attached) and the snapshots.
+            let subsaga_name =
+                SagaName::new(&format!("project-delete-project{i}-instances"));
+            let mut subsaga_builder = DagBuilder::new(subsaga_name);
+            subsaga_builder.append(Node::action(
+                "instances_stopping",
+                "StopInstances",
+                STOP_INSTANCES.as_ref(),
+            ));
+            subsaga_builder.append(Node::action(
+                "instances_stopped",
+                "WaitInstancesStopped",
+                WAIT_INSTANCES_STOPPED.as_ref(),
+            ));
+            subsaga_builder.append(Node::action(
+                "instances_deleted",
+                "DeleteInstances",
+                DELETE_INSTANCES.as_ref(),
+            ));
+            subsaga_builder.append(Node::action(
+                "snapshots_deleted",
+                "DeleteSnapshots",
+                DELETE_SNAPSHOTS.as_ref(),
+            ));
+            builder.append(Node::subsaga(
+                format!("project{}_instances", i).as_str(),
+                subsaga_builder.build()?,
+                &params_node_name,
+            ));
+
+            // Then the disks, each with its own disk delete subsaga.
+            for (j, disk_id) in project.disk_ids.iter().enumerate() {
+                let disk_params = disk_delete::Params { disk_id: *disk_id };
+                let disk_params_node_name =
+                    format!("project{}_disk_params{}", i, j);
+                builder.append(Node::constant(
+                    &disk_params_node_name,
+                    serde_json::to_value(&disk_params).map_err(|e| {
+                        SagaInitError::SerializeError(
+                            disk_params_node_name.clone(),
+                            e,
+                        )
+                    })?,
+                ));
+
+                let subsaga_name = SagaName::new(&format!(
+                    "project-delete-project{i}-disk{j}"
+                ));
+                let subsaga = disk_delete::SagaDiskDelete::make_saga_dag(
+                    &disk_params,
+                    DagBuilder::new(subsaga_name),
+                )?;
+                builder.append(Node::subsaga(
+                    format!("project{}_disk{}", i, j).as_str(),
+                    subsaga,
+                    disk_params_node_name,
+                ));
+            }
+
+            // Finally the VPCs and the Project itself.
+            let subsaga_name =
+                SagaName::new(&format!("project-delete-project{i}"));
+            let mut subsaga_builder = DagBuilder::new(subsaga_name);
+            subsaga_builder.append(Node::action(
+                "vpcs_deleted",
+                "DeleteVpcs",
+                DELETE_VPCS.as_ref(),
+            ));
+            subsaga_builder.append(Node::action(
+                "project_deleted",
+                "DeleteProjectRecord",
+                DELETE_PROJECT_RECORD.as_ref(),
+            ));
+            builder.append(Node::subsaga(
+                format!("project{}", i).as_str(),
+                subsaga_builder.build()?,
+                params_node_name,
+            ));
+        }
+
+        if params.delete_organization {
+            builder.append(Node::action(
+                "organization_deleted",
+                "DeleteOrganizationRecord",
+                DELETE_ORGANIZATION_RECORD.as_ref(),
+            ));
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+// project delete saga: action implementations
+
+/// Returns the Project being deleted by the current subsaga, or `None` if it
+/// has already been deleted
+async fn lookup_project(
+    sagactx: &NexusActionContext,
+    opctx: &OpContext,
+    project_id: Uuid,
+) -> Result<Option<authz::Project>, ActionError> {
+    let datastore = sagactx.user_data().datastore();
+    match LookupPath::new(opctx, datastore)
+        .project_id(project_id)
+        .lookup_for(authz::Action::Delete)
+        .await
+    {
+        Ok((.., authz_project)) => Ok(Some(authz_project)),
+        Err(Error::ObjectNotFound { .. }) => Ok(None),
+        Err(e) => Err(ActionError::action_failed(e)),
+    }
+}
+
+/// Returns the parameters for fetching the page of children after `marker`
+fn page_after(marker: Option<&Name>) -> DataPageParams<'_, Name> {
+    DataPageParams {
+        marker,
+        direction: dropshot::PaginationOrder::Ascending,
+        limit: NonZeroU32::new(LIST_BATCH_SIZE).unwrap(),
+    }
+}
+
+/// Collects every page of a paginated, name-ordered list of resources
+async fn list_all<T, F, Fut>(list_page: F) -> Result<Vec<T>, Error>
+where
+    T: Resource,
+    F: Fn(Option<Name>) -> Fut,
+    Fut: Future<Output = ListResultVec<T>>,
+{
+    let mut all: Vec<T> = Vec::new();
+    loop {
+        let marker = all.last().map(|item| Name(item.name().clone()));
+        let batch = list_page(marker).await?;
+        let done = batch.len() < LIST_BATCH_SIZE as usize;
+        all.extend(batch);
+        if done {
+            return Ok(all);
+        }
+    }
+}
+
+async fn list_instances(
+    datastore: &db::DataStore,
+    opctx: &OpContext,
+    authz_project: &authz::Project,
+) -> Result<Vec<db::model::Instance>, Error> {
+    list_all(|marker| async move {
+        datastore
+            .project_list_instances(
+                opctx,
+                authz_project,
+                &page_after(marker.as_ref()),
+                None,
+            )
+            .await
+    })
+    .await
+}
+
+async fn spd_stop_instances(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    let instances =
+        list_instances(osagactx.datastore(), &opctx, &authz_project)
+            .await
+            .map_err(ActionError::action_failed)?;
+    let total = instances.len();
+    for (i, db_instance) in instances.into_iter().enumerate() {
+        match db_instance.runtime().state.state() {
+            InstanceState::Stopped
+            | InstanceState::Failed
+            | InstanceState::Destroyed => continue,
+            InstanceState::Migrating | InstanceState::Repairing => {
+                return Err(ActionError::action_failed(
+                    Error::InvalidRequest {
+                        message: format!(
+                            "cannot stop instance \"{}\" while it is {}",
+                            db_instance.name(),
+                            db_instance.runtime().state.state(),
+                        ),
+                    },
+                ));
+            }
+            _ => (),
+        }
+
+        info!(
+            log,
+            "project {}: stopping instance {} ({} of {})",
+            params.project_id,
+            db_instance.id(),
+            i + 1,
+            total
+        );
+        let authz_instance = authz::Instance::new(
+            authz_project.clone(),
+            db_instance.id(),
+            LookupType::ById(db_instance.id()),
+        );
+        let requested = InstanceRuntimeStateRequested {
+            run_state: InstanceStateRequested::Stopped,
+            migration_params: None,
+        };
+        osagactx
+            .nexus()
+            .instance_set_runtime(
+                &opctx,
+                &authz_instance,
+                &db_instance,
+                requested,
+            )
+            .await
+            .map_err(ActionError::action_failed)?;
+    }
+    Ok(())
+}
+
+async fn spd_wait_instances_stopped(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let datastore = osagactx.datastore();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    let check_stopped = || async {
+        let instances = list_instances(datastore, &opctx, &authz_project)
+            .await
+            .map_err(BackoffError::Permanent)?;
+        let running = instances
+            .iter()
+            .filter(|instance| {
+                !matches!(
+                    instance.runtime().state.state(),
+                    InstanceState::Stopped | InstanceState::Failed
+                )
+            })
+            .count();
+        if running == 0 {
+            Ok(())
+        } else {
+            Err(BackoffError::transient(Error::unavail(&format!(
+                "{} instance(s) in project {} have not yet stopped",
+                running, params.project_id
+            ))))
+        }
+    };
+    let log_not_stopped = |error: Error, delay| {
+        info!(log, "{}; checking again in {:?}", error, delay);
+    };
+
+    let mut policy =
+        backoff::internal_service_policy_with_max(Duration::from_secs(5));
+    policy.max_elapsed_time = Some(INSTANCE_STOP_TIMEOUT);
+    backoff::retry_notify(policy, check_stopped, log_not_stopped)
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn spd_delete_instances(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let datastore = osagactx.datastore();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    let instances = list_instances(datastore, &opctx, &authz_project)
+        .await
+        .map_err(ActionError::action_failed)?;
+    let total = instances.len();
+    for (i, db_instance) in instances.into_iter().enumerate() {
+        info!(
+            log,
+            "project {}: deleting instance {} ({} of {})",
+            params.project_id,
+            db_instance.id(),
+            i + 1,
+            total
+        );
+        let authz_instance = authz::Instance::new(
+            authz_project.clone(),
+            db_instance.id(),
+            LookupType::ById(db_instance.id()),
+        );
+
+        // Unlike project_destroy_instance(), release the instance's external
+        // IPs and network interfaces before deleting the instance record: if
+        // we're interrupted part way through, the instance is still listed
+        // when this action is re-run, so nothing gets left behind.
+        datastore
+            .deallocate_external_ip_by_instance_id(&opctx, authz_instance.id())
+            .await
+            .map_err(ActionError::action_failed)?;
+        datastore
+            .instance_delete_all_network_interfaces(&opctx, &authz_instance)
+            .await
+            .map_err(ActionError::action_failed)?;
+        datastore
+            .project_delete_instance(&opctx, &authz_instance)
+            .await
+            .map_err(ActionError::action_failed)?;
+    }
+
+    osagactx.nexus().external_dns_update().await;
+    Ok(())
+}
+
+async fn spd_delete_snapshots(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let datastore = osagactx.datastore();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    let snapshots = list_all(|marker| {
+        let (opctx, authz_project) = (&opctx, &authz_project);
+        async move {
+            datastore
+                .project_list_snapshots(
+                    opctx,
+                    authz_project,
+                    &page_after(marker.as_ref()),
+                    None,
+                )
+                .await
+        }
+    })
+    .await
+    .map_err(ActionError::action_failed)?;
+    let total = snapshots.len();
+    for (i, db_snapshot) in snapshots.into_iter().enumerate() {
+        info!(
+            log,
+            "project {}: deleting snapshot {} ({} of {})",
+            params.project_id,
+            db_snapshot.id(),
+            i + 1,
+            total
+        );
+        let authz_snapshot = authz::Snapshot::new(
+            authz_project.clone(),
+            db_snapshot.id(),
+            LookupType::ById(db_snapshot.id()),
+        );
+        datastore
+            .project_delete_snapshot(&opctx, &authz_snapshot, &db_snapshot)
+            .await
+            .map_err(ActionError::action_failed)?;
+
+        // TODO-robustness As with project_delete_snapshot(), if we're
+        // interrupted between deleting the snapshot record and kicking off
+        // these volume deletions, the volumes are left behind.
+        osagactx
+            .nexus()
+            .volume_delete(db_snapshot.volume_id)
+            .await
+            .map_err(ActionError::action_failed)?;
+        if let Some(volume_id) = db_snapshot.destination_volume_id {
+            osagactx
+                .nexus()
+                .volume_delete(volume_id)
+                .await
+                .map_err(ActionError::action_failed)?;
+        }
+    }
+    Ok(())
+}
+
+async fn spd_delete_vpcs(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let datastore = osagactx.datastore();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    let vpcs = list_all(|marker| {
+        let (opctx, authz_project) = (&opctx, &authz_project);
+        async move {
+            datastore
+                .project_list_vpcs(
+                    opctx,
+                    authz_project,
+                    &page_after(marker.as_ref()),
+                    None,
+                )
+                .await
+        }
+    })
+    .await
+    .map_err(ActionError::action_failed)?;
+    let total = vpcs.len();
+    for (i, db_vpc) in vpcs.into_iter().enumerate() {
+        info!(
+            log,
+            "project {}: deleting vpc {} ({} of {})",
+            params.project_id,
+            db_vpc.id(),
+            i + 1,
+            total
+        );
+        let authz_vpc = authz::Vpc::new(
+            authz_project.clone(),
+            db_vpc.id(),
+            LookupType::ById(db_vpc.id()),
+        );
+
+        // Subnets go first, since the VPC can't be deleted while it has any.
+        // (They can't be deleted while they have network interfaces, but
+        // those went away with the instances.)
+        let subnets = list_all(|marker| {
+            let (opctx, authz_vpc) = (&opctx, &authz_vpc);
+            async move {
+                datastore
+                    .vpc_list_subnets(
+                        opctx,
+                        authz_vpc,
+                        &page_after(marker.as_ref()),
+                    )
+                    .await
+            }
+        })
+        .await
+        .map_err(ActionError::action_failed)?;
+        for db_subnet in subnets {
+            let authz_subnet = authz::VpcSubnet::new(
+                authz_vpc.clone(),
+                db_subnet.id(),
+                LookupType::ById(db_subnet.id()),
+            );
+            datastore
+                .vpc_delete_subnet(&opctx, &db_subnet, &authz_subnet)
+                .await
+                .map_err(ActionError::action_failed)?;
+        }
+
+        // project_delete_vpc() deletes the VPC record before its system router
+        // and firewall rules.  We do the reverse so that everything attached
+        // to the VPC is still found if this action is re-run after being
+        // interrupted.  The VPC has no subnets left, so there's nothing for
+        // the system router to route in the meantime.
+        let routers = list_all(|marker| {
+            let (opctx, authz_vpc) = (&opctx, &authz_vpc);
+            async move {
+                datastore
+                    .vpc_list_routers(
+                        opctx,
+                        authz_vpc,
+                        &page_after(marker.as_ref()),
+                    )
+                    .await
+            }
+        })
+        .await
+        .map_err(ActionError::action_failed)?;
+        for db_router in routers {
+            let authz_router = authz::VpcRouter::new(
+                authz_vpc.clone(),
+                db_router.id(),
+                LookupType::ById(db_router.id()),
+            );
+            datastore
+                .vpc_delete_router(&opctx, &authz_router)
+                .await
+                .map_err(ActionError::action_failed)?;
+        }
+        datastore
+            .vpc_delete_all_firewall_rules(&opctx, &authz_vpc)
+            .await
+            .map_err(ActionError::action_failed)?;
+        datastore
+            .project_delete_vpc(&opctx, &db_vpc, &authz_vpc)
+            .await
+            .map_err(ActionError::action_failed)?;
+    }
+    Ok(())
+}
+
+async fn spd_delete_project_record(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<ProjectParams>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+    let authz_project =
+        match lookup_project(&sagactx, &opctx, params.project_id).await? {
+            Some(authz_project) => authz_project,
+            None => return Ok(()),
+        };
+
+    // The disks were listed when the saga was created.  Don't leave behind
+    // any that showed up since.
+    let datastore = osagactx.datastore();
+    let disks = datastore
+        .project_list_disks(&opctx, &authz_project, &page_after(None), None)
+        .await
+        .map_err(ActionError::action_failed)?;
+    if !disks.is_empty() {
+        return Err(ActionError::action_failed(Error::InvalidRequest {
+            message: format!(
+                "project {} has disks that were created while it was being \
+                deleted",
+                params.project_id
+            ),
+        }));
+    }
+
+    info!(osagactx.log(), "deleting project {}", params.project_id);
+    datastore
+        .project_delete(&opctx, &authz_project)
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn spd_delete_organization_record(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let datastore = osagactx.datastore();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = OpContext::for_saga_action(&sagactx, &params.serialized_authn);
+
+    let (.., authz_org, db_org) = match LookupPath::new(&opctx, datastore)
+        .organization_id(params.organization_id)
+        .fetch_for(authz::Action::Delete)
+        .await
+    {
+        Ok(result) => result,
+        // Deleted by a previous attempt at this action.
+        Err(Error::ObjectNotFound { .. }) => return Ok(()),
+        Err(e) => return Err(ActionError::action_failed(e)),
+    };
+
+    info!(osagactx.log(), "deleting organization {}", params.organization_id);
+    datastore
+        .organization_delete(&opctx, &authz_org, &db_org)
+        .await
+        .map_err(ActionError::action_failed)
+}
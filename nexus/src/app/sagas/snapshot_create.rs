@@ -454,6 +454,7 @@ async fn ssc_create_snapshot_record(
             snapshot_id,
             params.create_params.identity.clone(),
         ),
+        labels: params.create_params.labels.clone().into(),
 
         project_id: params.project_id,
         disk_id: disk.id(),
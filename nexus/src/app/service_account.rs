@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Service accounts and their API keys
+
+use crate::authn::{Actor, ProjectScope, Reason};
+use crate::authz;
+use crate::context::OpContext;
+use crate::db;
+use crate::db::lookup::LookupPath;
+use crate::db::model::Name;
+use crate::external_api::params;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use uuid::Uuid;
+
+impl super::Nexus {
+    // Service accounts
+
+    /// Create a service account in the current Silo, optionally limited to
+    /// one of its Projects.
+    pub async fn service_account_create(
+        &self,
+        opctx: &OpContext,
+        params: params::ServiceAccountCreate,
+    ) -> CreateResult<db::model::ServiceAccount> {
+        let authz_silo = opctx
+            .authn
+            .silo_required()
+            .internal_context("creating a service account")?;
+        let project = match params.project_id {
+            None => None,
+            Some(project_id) => {
+                let (project_silo, authz_org, authz_project) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .project_id(project_id)
+                        .lookup_for(authz::Action::Read)
+                        .await?;
+                if project_silo.id() != authz_silo.id() {
+                    return Err(Error::invalid_request(
+                        "a service account can only be limited to a project \
+                        in its own silo",
+                    ));
+                }
+                Some((authz_org.id(), authz_project.id()))
+            }
+        };
+        let service_account =
+            db::model::ServiceAccount::new(authz_silo.id(), project, params);
+        self.db_datastore
+            .service_account_create(opctx, &authz_silo, service_account)
+            .await
+    }
+
+    pub async fn service_accounts_list(
+        &self,
+        opctx: &OpContext,
+        pagparams: &DataPageParams<'_, Name>,
+    ) -> ListResultVec<db::model::ServiceAccount> {
+        let authz_silo = opctx
+            .authn
+            .silo_required()
+            .internal_context("listing current silo's service accounts")?;
+        self.db_datastore
+            .service_accounts_list(opctx, &authz_silo, pagparams)
+            .await
+    }
+
+    pub async fn service_account_fetch(
+        &self,
+        opctx: &OpContext,
+        service_account_name: &Name,
+    ) -> LookupResult<db::model::ServiceAccount> {
+        let (.., db_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_name(service_account_name)
+                .fetch()
+                .await?;
+        Ok(db_service_account)
+    }
+
+    pub async fn service_account_delete(
+        &self,
+        opctx: &OpContext,
+        service_account_name: &Name,
+    ) -> DeleteResult {
+        let (.., authz_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_name(service_account_name)
+                .lookup_for(authz::Action::Delete)
+                .await?;
+        self.db_datastore
+            .service_account_delete(opctx, &authz_service_account)
+            .await
+    }
+
+    // API keys
+
+    pub async fn service_account_key_create(
+        &self,
+        opctx: &OpContext,
+        service_account_name: &Name,
+    ) -> CreateResult<db::model::ServiceAccountKey> {
+        let (.., authz_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_name(service_account_name)
+                .lookup_for(authz::Action::CreateChild)
+                .await?;
+        let key = db::model::ServiceAccountKey::new(authz_service_account.id());
+        self.db_datastore
+            .service_account_key_create(opctx, &authz_service_account, key)
+            .await
+    }
+
+    pub async fn service_account_keys_list(
+        &self,
+        opctx: &OpContext,
+        service_account_name: &Name,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<db::model::ServiceAccountKey> {
+        let (.., authz_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_name(service_account_name)
+                .lookup_for(authz::Action::ListChildren)
+                .await?;
+        self.db_datastore
+            .service_account_keys_list(opctx, &authz_service_account, pagparams)
+            .await
+    }
+
+    pub async fn service_account_key_delete(
+        &self,
+        opctx: &OpContext,
+        service_account_name: &Name,
+        key_id: Uuid,
+    ) -> DeleteResult {
+        let (.., authz_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_name(service_account_name)
+                .lookup_for(authz::Action::Modify)
+                .await?;
+        self.db_datastore
+            .service_account_key_delete(opctx, &authz_service_account, key_id)
+            .await
+    }
+
+    /// Look up the service account that owns an API key, for authenticating
+    /// a request made with it.
+    pub async fn service_account_key_actor(
+        &self,
+        opctx: &OpContext,
+        key: String,
+    ) -> Result<Actor, Reason> {
+        let db_key = self
+            .db_datastore
+            .service_account_key_fetch(opctx, key)
+            .await
+            .map_err(|e| match e {
+                Error::ObjectNotFound { .. } => Reason::UnknownActor {
+                    actor: "from service account key".to_string(),
+                },
+                e => Reason::UnknownError { source: e },
+            })?;
+
+        let service_account_id = db_key.service_account_id;
+        let (.., db_service_account) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .service_account_id(service_account_id)
+                .fetch()
+                .await
+                .map_err(|e| match e {
+                    Error::ObjectNotFound { .. } => Reason::UnknownActor {
+                        actor: service_account_id.to_string(),
+                    },
+                    e => Reason::UnknownError { source: e },
+                })?;
+        let project_scope = db_service_account
+            .organization_id
+            .zip(db_service_account.project_id)
+            .map(|(organization_id, project_id)| ProjectScope {
+                organization_id,
+                project_id,
+            });
+
+        Ok(Actor::ServiceAccount {
+            service_account_id,
+            silo_id: db_service_account.silo_id,
+            project_scope,
+        })
+    }
+}
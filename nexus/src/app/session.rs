@@ -10,16 +10,25 @@ use crate::authz;
 use crate::context::OpContext;
 use crate::db;
 use crate::db::lookup::LookupPath;
+use chrono::Utc;
 use hex;
 use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::UpdateResult;
 use rand::{rngs::StdRng, RngCore, SeedableRng};
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// How often expired console sessions are deleted
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
 fn generate_session_token() -> String {
     // TODO: "If getrandom is unable to provide secure entropy this method will panic."
     // Should we explicitly handle that?
@@ -134,6 +143,76 @@ impl super::Nexus {
         self.db_datastore.session_hard_delete(opctx, &authz_session).await
     }
 
+    /// List a user's unexpired console sessions.
+    pub async fn sessions_list(
+        &self,
+        opctx: &OpContext,
+        silo_user_id: Uuid,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<db::model::ConsoleSession> {
+        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_user_id(silo_user_id)
+            .lookup_for(authz::Action::ListChildren)
+            .await?;
+        let now = Utc::now();
+        self.db_datastore
+            .sessions_list(
+                opctx,
+                &authz_user,
+                now - self.session_idle_timeout,
+                now - self.session_absolute_timeout,
+                pagparams,
+            )
+            .await
+    }
+
+    /// Revoke one of a user's console sessions.
+    pub async fn session_revoke(
+        &self,
+        opctx: &OpContext,
+        silo_user_id: Uuid,
+        session_id: Uuid,
+    ) -> DeleteResult {
+        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_user_id(silo_user_id)
+            .lookup_for(authz::Action::Modify)
+            .await?;
+        self.db_datastore.session_revoke(opctx, &authz_user, session_id).await
+    }
+
+    /// Revoke all of a user's console sessions except the one with token
+    /// `current_token`, if given.
+    pub async fn sessions_revoke_others(
+        &self,
+        opctx: &OpContext,
+        silo_user_id: Uuid,
+        current_token: Option<&str>,
+    ) -> DeleteResult {
+        let (.., authz_user) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_user_id(silo_user_id)
+            .lookup_for(authz::Action::Modify)
+            .await?;
+        self.db_datastore
+            .sessions_revoke_all(opctx, &authz_user, current_token)
+            .await
+    }
+
+    /// Delete all console sessions that have expired, returning how many
+    /// were deleted.
+    pub async fn sessions_delete_expired(
+        &self,
+        opctx: &OpContext,
+    ) -> Result<usize, Error> {
+        let now = Utc::now();
+        self.db_datastore
+            .sessions_delete_expired(
+                opctx,
+                now - self.session_idle_timeout,
+                now - self.session_absolute_timeout,
+            )
+            .await
+    }
+
     pub async fn lookup_silo_for_authn(
         &self,
         opctx: &OpContext,
@@ -162,3 +241,47 @@ impl super::Nexus {
         Ok(db_silo_user.silo_id)
     }
 }
+
+/// Periodically deletes expired console sessions, for as long as `nexus`
+/// exists
+///
+/// Expired sessions can't be used, but they'd otherwise stay in the database
+/// forever if their users never came back.
+pub(super) async fn session_cleanup_task(nexus: Weak<super::Nexus>) {
+    let (log, opctx) = match nexus.upgrade() {
+        Some(nexus) => {
+            // Nothing in the database can be read until it's been populated.
+            if nexus.wait_for_populate().await.is_err() {
+                return;
+            }
+            let log = nexus.log.new(o!("component" => "SessionCleanup"));
+            let opctx = OpContext::for_background(
+                log.clone(),
+                Arc::clone(&nexus.authz),
+                authn::Context::external_authn(),
+                Arc::clone(&nexus.db_datastore),
+            );
+            (log, opctx)
+        }
+        None => return,
+    };
+
+    let mut interval = tokio::time::interval(SESSION_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let nexus = match nexus.upgrade() {
+            Some(nexus) => nexus,
+            None => return,
+        };
+        match nexus.sessions_delete_expired(&opctx).await {
+            Ok(0) => (),
+            Ok(ndeleted) => {
+                info!(log, "deleted expired sessions"; "count" => ndeleted)
+            }
+            Err(error) => {
+                warn!(log, "failed to delete expired sessions";
+                    "error" => ?error)
+            }
+        }
+    }
+}
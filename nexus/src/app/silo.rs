@@ -15,6 +15,7 @@ use crate::external_api::params;
 use crate::external_api::shared;
 use crate::{authn, authz};
 use anyhow::Context;
+use chrono::Utc;
 use nexus_db_model::UserProvisionType;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
@@ -24,8 +25,64 @@ use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::UpdateResult;
 use omicron_common::api::external::{CreateResult, ResourceType};
 use std::str::FromStr;
+use std::sync::Weak;
+use std::time::Duration;
 use uuid::Uuid;
 
+/// Hash the password described by `password` for storage, if there is one
+async fn password_hash_for(
+    silo_user_id: Uuid,
+    password: &params::UserPassword,
+) -> Result<Option<db::model::SiloUserPasswordHash>, Error> {
+    match password {
+        params::UserPassword::Password(password) => {
+            let hash = authn::passwords::hash(password).await?;
+            Ok(Some(db::model::SiloUserPasswordHash::new(silo_user_id, hash)))
+        }
+        params::UserPassword::InvalidPassword => Ok(None),
+    }
+}
+
+/// How often URL-sourced SAML identity provider metadata is re-fetched
+const SAML_METADATA_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How many SAML identity providers are fetched from the database at a time
+/// when refreshing their metadata
+const SAML_METADATA_REFRESH_BATCH_SIZE: u32 = 100;
+
+/// Fetch a SAML IdP metadata document from `url`
+async fn saml_metadata_fetch(url: &str) -> Result<String, Error> {
+    let dur = Duration::from_secs(5);
+    let client = reqwest::ClientBuilder::new()
+        .connect_timeout(dur)
+        .timeout(dur)
+        .build()
+        .map_err(|e| {
+            Error::internal_error(&format!(
+                "failed to build reqwest client: {}",
+                e
+            ))
+        })?;
+
+    let response =
+        client.get(url).send().await.map_err(|e| Error::InvalidValue {
+            label: String::from("url"),
+            message: format!("error querying url: {}", e),
+        })?;
+
+    if !response.status().is_success() {
+        return Err(Error::InvalidValue {
+            label: String::from("url"),
+            message: format!("querying url returned: {}", response.status()),
+        });
+    }
+
+    response.text().await.map_err(|e| Error::InvalidValue {
+        label: String::from("url"),
+        message: format!("error getting text from url: {}", e),
+    })
+}
+
 impl super::Nexus {
     // Silos
 
@@ -39,9 +96,12 @@ impl super::Nexus {
         // create arbitrary groups in the Silo, but we allow them to create
         // this one in this case.
         let external_authn_opctx = self.opctx_external_authn();
-        self.datastore()
+        let silo = self
+            .datastore()
             .silo_create(&opctx, &external_authn_opctx, new_silo_params)
-            .await
+            .await?;
+        self.external_dns_update().await;
+        Ok(silo)
     }
 
     pub async fn silos_list_by_name(
@@ -94,7 +154,9 @@ impl super::Nexus {
                 .silo_name(name)
                 .fetch_for(authz::Action::Delete)
                 .await?;
-        self.db_datastore.silo_delete(opctx, &authz_silo, &db_silo).await
+        self.db_datastore.silo_delete(opctx, &authz_silo, &db_silo).await?;
+        self.external_dns_update().await;
+        Ok(())
     }
 
     // Role assignments
@@ -205,6 +267,54 @@ impl super::Nexus {
         Ok(db_silo_user)
     }
 
+    /// Revoke all of the access tokens granted to a user in a Silo
+    pub async fn silo_user_tokens_revoke(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_user_id: Uuid,
+    ) -> DeleteResult {
+        let (authz_silo,) = LookupPath::new(opctx, self.datastore())
+            .silo_name(silo_name)
+            .lookup_for(authz::Action::Read)
+            .await?;
+        let (authz_silo_user, _) = self
+            .silo_user_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_user_id,
+                authz::Action::Modify,
+            )
+            .await?;
+        self.db_datastore
+            .device_access_tokens_revoke_all(opctx, &authz_silo_user)
+            .await
+    }
+
+    /// Revoke all of the console sessions of a user in a Silo
+    pub async fn silo_user_sessions_revoke(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_user_id: Uuid,
+    ) -> DeleteResult {
+        let (authz_silo,) = LookupPath::new(opctx, self.datastore())
+            .silo_name(silo_name)
+            .lookup_for(authz::Action::Read)
+            .await?;
+        let (authz_silo_user, _) = self
+            .silo_user_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_user_id,
+                authz::Action::Modify,
+            )
+            .await?;
+        self.db_datastore
+            .sessions_revoke_all(opctx, &authz_silo_user, None)
+            .await
+    }
+
     // The "local" identity provider (available only in `LocalOnly` Silos)
 
     /// Helper function for looking up a LocalOnly Silo by name
@@ -245,13 +355,23 @@ impl super::Nexus {
         opctx
             .authorize(authz::Action::CreateChild, &authz_silo_user_list)
             .await?;
+        let silo_user_id = Uuid::new_v4();
+        let db_password_hash =
+            password_hash_for(silo_user_id, &new_user_params.password).await?;
         let silo_user = db::model::SiloUser::new(
             authz_silo.id(),
-            Uuid::new_v4(),
+            silo_user_id,
             new_user_params.external_id.as_ref().to_owned(),
         );
-        let (_, db_silo_user) =
-            self.datastore().silo_user_create(&authz_silo, silo_user).await?;
+        let (_, db_silo_user) = self
+            .datastore()
+            .silo_user_create_with_password(
+                opctx,
+                &authz_silo,
+                silo_user,
+                db_password_hash,
+            )
+            .await?;
         Ok(db_silo_user)
     }
 
@@ -275,6 +395,267 @@ impl super::Nexus {
         self.db_datastore.silo_user_delete(opctx, &authz_silo_user).await
     }
 
+    /// Set or invalidate the password of a user in a Silo's local identity
+    /// provider
+    ///
+    /// This also unlocks a user who was locked out after too many failed
+    /// logins.
+    pub async fn local_idp_user_set_password(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_user_id: Uuid,
+        password: &params::UserPassword,
+    ) -> UpdateResult<()> {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let (authz_silo_user, _) = self
+            .silo_user_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_user_id,
+                authz::Action::Modify,
+            )
+            .await?;
+        let db_password_hash =
+            password_hash_for(authz_silo_user.id(), password).await?;
+        self.datastore()
+            .silo_user_password_hash_set(
+                opctx,
+                authz_silo_user,
+                db_password_hash,
+            )
+            .await
+    }
+
+    /// Helper function for looking up a group in a LocalOnly Silo
+    ///
+    /// Like [`Nexus::silo_user_lookup_by_id`], this validates that the group
+    /// is in the expected Silo.
+    async fn local_idp_group_lookup_by_id(
+        &self,
+        opctx: &OpContext,
+        authz_silo: &authz::Silo,
+        silo_group_id: Uuid,
+        action: authz::Action,
+    ) -> LookupResult<authz::SiloGroup> {
+        let (_, authz_silo_group, db_silo_group) =
+            LookupPath::new(opctx, self.datastore())
+                .silo_group_id(silo_group_id)
+                .fetch_for(action)
+                .await?;
+        if db_silo_group.silo_id != authz_silo.id() {
+            return Err(authz_silo_group.not_found());
+        }
+        Ok(authz_silo_group)
+    }
+
+    /// Create a group in a Silo's local identity provider
+    pub async fn local_idp_create_group(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        new_group_params: params::GroupCreate,
+    ) -> CreateResult<db::model::SiloGroup> {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let silo_group = db::model::SiloGroup::new(
+            Uuid::new_v4(),
+            authz_silo.id(),
+            new_group_params.display_name.to_string(),
+        );
+        let authz_silo_group_list = authz::SiloGroupList::new(authz_silo);
+        self.db_datastore
+            .silo_group_create(opctx, &authz_silo_group_list, silo_group)
+            .await
+    }
+
+    /// Delete a group in a Silo's local identity provider
+    ///
+    /// The group must have no members.
+    pub async fn local_idp_delete_group(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_group_id: Uuid,
+    ) -> DeleteResult {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let authz_silo_group = self
+            .local_idp_group_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_group_id,
+                authz::Action::Delete,
+            )
+            .await?;
+        self.db_datastore.silo_group_delete(opctx, &authz_silo_group).await
+    }
+
+    /// Add a user to a group in a Silo's local identity provider
+    pub async fn local_idp_group_add_member(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_group_id: Uuid,
+        silo_user_id: Uuid,
+    ) -> UpdateResult<()> {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let authz_silo_group = self
+            .local_idp_group_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_group_id,
+                authz::Action::Modify,
+            )
+            .await?;
+        let (authz_silo_user, _) = self
+            .silo_user_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_user_id,
+                authz::Action::Read,
+            )
+            .await?;
+        self.db_datastore
+            .silo_group_membership_add(
+                opctx,
+                &authz_silo_group,
+                &authz_silo_user,
+            )
+            .await
+    }
+
+    /// Remove a user from a group in a Silo's local identity provider
+    pub async fn local_idp_group_remove_member(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        silo_group_id: Uuid,
+        silo_user_id: Uuid,
+    ) -> DeleteResult {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let authz_silo_group = self
+            .local_idp_group_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_group_id,
+                authz::Action::Modify,
+            )
+            .await?;
+        let (authz_silo_user, _) = self
+            .silo_user_lookup_by_id(
+                opctx,
+                &authz_silo,
+                silo_user_id,
+                authz::Action::Read,
+            )
+            .await?;
+        self.db_datastore
+            .silo_group_membership_remove(
+                opctx,
+                &authz_silo_group,
+                &authz_silo_user,
+            )
+            .await
+    }
+
+    /// Returns whether another attempt to log in as `username` in the given
+    /// Silo is allowed right now, counting this one
+    pub fn login_local_allowed(
+        &self,
+        silo_name: &Name,
+        username: &str,
+    ) -> bool {
+        self.login_rate_limiter.attempt(silo_name.as_str(), username)
+    }
+
+    /// Authenticate a user of a Silo's local identity provider with their
+    /// password
+    ///
+    /// Returns `None` if the credentials aren't valid, including when the
+    /// user doesn't exist, has no password, or is locked out after too many
+    /// failed attempts.  These cases are deliberately indistinguishable to
+    /// the caller.
+    pub async fn login_local(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        credentials: &params::UsernamePasswordCredentials,
+    ) -> LookupResult<Option<db::model::SiloUser>> {
+        let (authz_silo, _) =
+            self.local_idp_fetch_silo(opctx, silo_name).await?;
+        let datastore = self.datastore();
+        let password = &credentials.password;
+
+        let (authz_silo_user, db_silo_user) = match datastore
+            .silo_user_fetch_by_external_id(
+                opctx,
+                &authz_silo,
+                credentials.username.as_ref(),
+            )
+            .await?
+        {
+            Some(user) => user,
+            None => {
+                authn::passwords::verify(password, None).await?;
+                return Ok(None);
+            }
+        };
+        let db_password_hash = match datastore
+            .silo_user_password_hash_fetch(opctx, &authz_silo_user)
+            .await?
+        {
+            Some(db_password_hash) => db_password_hash,
+            None => {
+                authn::passwords::verify(password, None).await?;
+                return Ok(None);
+            }
+        };
+
+        if let Some(time_locked_until) = db_password_hash.time_locked_until {
+            if time_locked_until > Utc::now() {
+                debug!(opctx.log, "refusing login for locked out user";
+                    "silo_user_id" => %authz_silo_user.id());
+                authn::passwords::verify(password, None).await?;
+                return Ok(None);
+            }
+        }
+
+        if !authn::passwords::verify(password, Some(db_password_hash.hash))
+            .await?
+        {
+            let lockout_duration =
+                chrono::Duration::from_std(authn::passwords::LOCKOUT_DURATION)
+                    .unwrap();
+            let locked_until = Utc::now() + lockout_duration;
+            let locked = datastore
+                .silo_user_password_login_failed(
+                    opctx,
+                    &authz_silo_user,
+                    authn::passwords::MAX_FAILED_LOGINS,
+                    locked_until,
+                )
+                .await?;
+            if locked {
+                warn!(opctx.log,
+                    "locking out user after too many failed logins";
+                    "silo_user_id" => %authz_silo_user.id(),
+                    "locked_until" => %locked_until);
+            }
+            return Ok(None);
+        }
+
+        if db_password_hash.failed_attempts != 0 {
+            datastore
+                .silo_user_password_login_succeeded(opctx, &authz_silo_user)
+                .await?;
+        }
+        Ok(Some(db_silo_user))
+    }
+
     /// Based on an authenticated subject, fetch or create a silo user
     pub async fn silo_user_from_authenticated_subject(
         &self,
@@ -526,47 +907,19 @@ impl super::Nexus {
             )));
         }
 
-        let idp_metadata_document_string = match &params.idp_metadata_source {
+        let (idp_metadata_url, idp_metadata_document_string) = match &params
+            .idp_metadata_source
+        {
             params::IdpMetadataSource::Url { url } => {
                 // Download the SAML IdP descriptor, and write it into the DB.
                 // This is so that it can be deserialized later.
                 //
-                // Importantly, do this only once and store it. It would
-                // introduce attack surface to download it each time it was
-                // required.
-                let dur = std::time::Duration::from_secs(5);
-                let client = reqwest::ClientBuilder::new()
-                    .connect_timeout(dur)
-                    .timeout(dur)
-                    .build()
-                    .map_err(|e| {
-                        Error::internal_error(&format!(
-                            "failed to build reqwest client: {}",
-                            e
-                        ))
-                    })?;
-
-                let response = client.get(url).send().await.map_err(|e| {
-                    Error::InvalidValue {
-                        label: String::from("url"),
-                        message: format!("error querying url: {}", e),
-                    }
-                })?;
-
-                if !response.status().is_success() {
-                    return Err(Error::InvalidValue {
-                        label: String::from("url"),
-                        message: format!(
-                            "querying url returned: {}",
-                            response.status()
-                        ),
-                    });
-                }
-
-                response.text().await.map_err(|e| Error::InvalidValue {
-                    label: String::from("url"),
-                    message: format!("error getting text from url: {}", e),
-                })?
+                // Importantly, store it rather than downloading it each time
+                // it's required: that would introduce attack surface on every
+                // login.  It's refreshed from the same URL periodically (see
+                // `saml_metadata_refresh_task()`), but only replaced if the
+                // new document is valid.
+                (Some(url.clone()), saml_metadata_fetch(url).await?)
             }
 
             params::IdpMetadataSource::Base64EncodedXml { data } => {
@@ -578,9 +931,11 @@ impl super::Nexus {
                             e
                         ),
                     })?;
-                String::from_utf8_lossy(&bytes).into_owned()
+                (None, String::from_utf8_lossy(&bytes).into_owned())
             }
         };
+        let time_metadata_refreshed =
+            idp_metadata_url.as_ref().map(|_| Utc::now());
 
         let provider = db::model::SamlIdentityProvider {
             identity: db::model::SamlIdentityProviderIdentity::new(
@@ -606,6 +961,11 @@ impl super::Nexus {
                 .map(|x| x.private_key.clone()),
 
             group_attribute_name: params.group_attribute_name.clone(),
+
+            idp_metadata_url,
+            time_metadata_refresh_attempted: time_metadata_refreshed,
+            time_metadata_refreshed,
+            metadata_refresh_error: None,
         };
 
         let _authn_provider: authn::silos::SamlIdentityProvider =
@@ -634,4 +994,273 @@ impl super::Nexus {
                 .await?;
         Ok(saml_identity_provider)
     }
+
+    /// Re-fetch the metadata of each SAML identity provider whose metadata
+    /// was supplied by URL, replacing the stored metadata if the new document
+    /// is valid
+    ///
+    /// Returns the number of providers whose metadata was refreshed.  Failures
+    /// for individual providers are recorded with the provider (and reported
+    /// in its view) rather than returned.
+    pub async fn saml_identity_providers_refresh_metadata(
+        &self,
+        opctx: &OpContext,
+    ) -> Result<usize, Error> {
+        let mut nrefreshed = 0;
+        let mut marker = None;
+        loop {
+            let batch = self
+                .db_datastore
+                .saml_identity_provider_list_url_sourced(
+                    opctx,
+                    &DataPageParams {
+                        marker: marker.as_ref(),
+                        direction: dropshot::PaginationOrder::Ascending,
+                        limit: std::num::NonZeroU32::new(
+                            SAML_METADATA_REFRESH_BATCH_SIZE,
+                        )
+                        .unwrap(),
+                    },
+                )
+                .await?;
+            marker = match batch.last() {
+                Some(last) => Some(last.id()),
+                None => break,
+            };
+
+            for provider in batch {
+                match self
+                    .saml_identity_provider_refresh_metadata(opctx, provider)
+                    .await
+                {
+                    Ok(true) => nrefreshed += 1,
+                    Ok(false) => (),
+                    Err(error) => {
+                        warn!(opctx.log, "failed to refresh SAML metadata";
+                            "error" => ?error)
+                    }
+                }
+            }
+        }
+
+        Ok(nrefreshed)
+    }
+
+    /// Re-fetch the metadata of one URL-sourced SAML identity provider and
+    /// record the outcome, returning whether the metadata was replaced
+    async fn saml_identity_provider_refresh_metadata(
+        &self,
+        opctx: &OpContext,
+        provider: db::model::SamlIdentityProvider,
+    ) -> Result<bool, Error> {
+        let (.., authz_idp, provider) =
+            LookupPath::new(opctx, &self.db_datastore)
+                .saml_identity_provider_id(provider.id())
+                .fetch_for(authz::Action::Modify)
+                .await?;
+        let url = match &provider.idp_metadata_url {
+            Some(url) => url.clone(),
+            None => return Ok(false),
+        };
+
+        // Validate the new document the same way it was validated when the
+        // provider was created.  On any failure, the old document is kept.
+        let result = match saml_metadata_fetch(&url).await {
+            Ok(idp_metadata_document_string) => {
+                let candidate = db::model::SamlIdentityProvider {
+                    idp_metadata_document_string,
+                    ..provider
+                };
+                let document = candidate.idp_metadata_document_string.clone();
+                authn::silos::SamlIdentityProvider::try_from(candidate)
+                    .map(|_| document)
+                    .map_err(|e| format!("invalid metadata: {:#}", e))
+            }
+            Err(error) => Err(error.to_string()),
+        };
+
+        let refreshed = result.is_ok();
+        if let Err(message) = &result {
+            info!(opctx.log, "SAML metadata refresh failed";
+                "saml_identity_provider_id" => %authz_idp.id(),
+                "error" => message);
+        }
+        self.db_datastore
+            .saml_identity_provider_metadata_refreshed(
+                opctx, &authz_idp, result,
+            )
+            .await?;
+        Ok(refreshed)
+    }
+
+    pub async fn oidc_identity_provider_create(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        params: params::OidcIdentityProviderCreate,
+    ) -> CreateResult<db::model::OidcIdentityProvider> {
+        let (authz_silo, db_silo) = LookupPath::new(opctx, &self.db_datastore)
+            .silo_name(silo_name)
+            .fetch()
+            .await?;
+        let authz_idp_list = authz::SiloIdentityProviderList::new(authz_silo);
+
+        if db_silo.user_provision_type != UserProvisionType::Jit {
+            return Err(Error::invalid_request(
+                "cannot create identity providers in this kind of Silo",
+            ));
+        }
+
+        // As with SAML, check this now to protect the code that fetches the
+        // discovery document from an external source.
+        opctx.authorize(authz::Action::CreateChild, &authz_idp_list).await?;
+
+        // The authentication mode is immutable so it's safe to check this here
+        // and bail out.
+        if db_silo.authentication_mode
+            != nexus_db_model::AuthenticationMode::Oidc
+        {
+            return Err(Error::invalid_request(&format!(
+                "cannot create OIDC identity provider for this Silo type \
+                (expected authentication mode {:?}, found {:?})",
+                nexus_db_model::AuthenticationMode::Oidc,
+                &db_silo.authentication_mode,
+            )));
+        }
+
+        // Download the provider's discovery document, and write it into the
+        // DB.  As with SAML metadata, this is done only once: the endpoints it
+        // names are trusted from then on.
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            params.issuer.trim_end_matches('/')
+        );
+        let dur = std::time::Duration::from_secs(5);
+        let client = reqwest::ClientBuilder::new()
+            .connect_timeout(dur)
+            .timeout(dur)
+            .build()
+            .map_err(|e| {
+                Error::internal_error(&format!(
+                    "failed to build reqwest client: {}",
+                    e
+                ))
+            })?;
+
+        let response =
+            client.get(&discovery_url).send().await.map_err(|e| {
+                Error::InvalidValue {
+                    label: String::from("issuer"),
+                    message: format!(
+                        "error querying discovery document: {}",
+                        e
+                    ),
+                }
+            })?;
+
+        if !response.status().is_success() {
+            return Err(Error::InvalidValue {
+                label: String::from("issuer"),
+                message: format!(
+                    "querying discovery document returned: {}",
+                    response.status()
+                ),
+            });
+        }
+
+        let discovery_document_string =
+            response.text().await.map_err(|e| Error::InvalidValue {
+                label: String::from("issuer"),
+                message: format!(
+                    "error getting text of discovery document: {}",
+                    e
+                ),
+            })?;
+
+        let provider = db::model::OidcIdentityProvider {
+            identity: db::model::OidcIdentityProviderIdentity::new(
+                Uuid::new_v4(),
+                params.identity,
+            ),
+            silo_id: db_silo.id(),
+
+            issuer: params.issuer,
+            discovery_document_string,
+
+            client_id: params.client_id,
+            client_secret: params.client_secret,
+            redirect_uri: params.redirect_uri,
+
+            group_claim_name: params.group_claim_name,
+        };
+
+        let _authn_provider: authn::silos::OidcIdentityProvider =
+            provider.clone().try_into().map_err(|e: anyhow::Error|
+                // As with SAML, failing to convert from the model to the authn
+                // type here means something about this request doesn't work.
+                Error::invalid_request(&format!("{:#}", e)))?;
+
+        self.db_datastore
+            .oidc_identity_provider_create(opctx, &authz_idp_list, provider)
+            .await
+    }
+
+    pub async fn oidc_identity_provider_fetch(
+        &self,
+        opctx: &OpContext,
+        silo_name: &Name,
+        provider_name: &Name,
+    ) -> LookupResult<db::model::OidcIdentityProvider> {
+        let (.., oidc_identity_provider) =
+            LookupPath::new(opctx, &self.datastore())
+                .silo_name(silo_name)
+                .oidc_identity_provider_name(provider_name)
+                .fetch()
+                .await?;
+        Ok(oidc_identity_provider)
+    }
+}
+
+/// Periodically refreshes URL-sourced SAML identity provider metadata, for as
+/// long as `nexus` exists
+///
+/// Identity providers rotate their signing certificates.  Without this, every
+/// login to the Silo would fail after a rotation until the provider was
+/// recreated.
+pub(super) async fn saml_metadata_refresh_task(nexus: Weak<super::Nexus>) {
+    let (log, opctx) = match nexus.upgrade() {
+        Some(nexus) => {
+            // Nothing in the database can be read until it's been populated.
+            if nexus.wait_for_populate().await.is_err() {
+                return;
+            }
+            let opctx = nexus.opctx_for_saml_metadata_refresh();
+            (opctx.log.clone(), opctx)
+        }
+        None => return,
+    };
+
+    // Metadata is fetched when each provider is created, so there's no need
+    // to refresh it as soon as we start.
+    let mut interval = tokio::time::interval_at(
+        tokio::time::Instant::now() + SAML_METADATA_REFRESH_INTERVAL,
+        SAML_METADATA_REFRESH_INTERVAL,
+    );
+    loop {
+        interval.tick().await;
+        let nexus = match nexus.upgrade() {
+            Some(nexus) => nexus,
+            None => return,
+        };
+        match nexus.saml_identity_providers_refresh_metadata(&opctx).await {
+            Ok(0) => (),
+            Ok(nrefreshed) => {
+                info!(log, "refreshed SAML metadata"; "count" => nrefreshed)
+            }
+            Err(error) => {
+                warn!(log, "failed to refresh SAML metadata";
+                    "error" => ?error)
+            }
+        }
+    }
 }
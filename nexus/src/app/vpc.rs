@@ -14,6 +14,8 @@ use crate::db::model::Name;
 use crate::db::model::VpcRouterKind;
 use crate::db::queries::vpc_subnet::SubnetError;
 use crate::external_api::params;
+use crate::external_api::shared;
+use anyhow::Context;
 use nexus_defaults as defaults;
 use omicron_common::api::external;
 use omicron_common::api::external::CreateResult;
@@ -21,6 +23,7 @@ use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
@@ -198,6 +201,7 @@ impl super::Nexus {
         organization_name: &Name,
         project_name: &Name,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<db::model::Vpc> {
         let (.., authz_project) = LookupPath::new(opctx, &self.db_datastore)
             .organization_name(organization_name)
@@ -205,7 +209,12 @@ impl super::Nexus {
             .lookup_for(authz::Action::ListChildren)
             .await?;
         self.db_datastore
-            .project_list_vpcs(&opctx, &authz_project, pagparams)
+            .project_list_vpcs(
+                &opctx,
+                &authz_project,
+                pagparams,
+                label_selector,
+            )
             .await
     }
 
@@ -237,6 +246,59 @@ impl super::Nexus {
         Ok(db_vpc)
     }
 
+    pub async fn vpc_fetch_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        vpc_name: &Name,
+    ) -> LookupResult<shared::Policy<authz::VpcRole>> {
+        let (.., authz_vpc) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .vpc_name(vpc_name)
+            .lookup_for(authz::Action::ReadPolicy)
+            .await?;
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_fetch_visible(opctx, &authz_vpc)
+            .await?
+            .into_iter()
+            .map(|r| r.try_into().context("parsing database role assignment"))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| Error::internal_error(&format!("{:#}", error)))?;
+        Ok(shared::Policy { role_assignments })
+    }
+
+    pub async fn vpc_update_policy(
+        &self,
+        opctx: &OpContext,
+        organization_name: &Name,
+        project_name: &Name,
+        vpc_name: &Name,
+        policy: &shared::Policy<authz::VpcRole>,
+    ) -> UpdateResult<shared::Policy<authz::VpcRole>> {
+        let (.., authz_vpc) = LookupPath::new(opctx, &self.db_datastore)
+            .organization_name(organization_name)
+            .project_name(project_name)
+            .vpc_name(vpc_name)
+            .lookup_for(authz::Action::ModifyPolicy)
+            .await?;
+
+        let role_assignments = self
+            .db_datastore
+            .role_assignment_replace_visible(
+                opctx,
+                &authz_vpc,
+                &policy.role_assignments,
+            )
+            .await?
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(shared::Policy { role_assignments })
+    }
+
     pub async fn project_update_vpc(
         &self,
         opctx: &OpContext,
@@ -39,9 +39,6 @@ pub trait SessionStore {
         token: String,
     ) -> Option<Self::SessionModel>;
 
-    /// Mark session expired
-    async fn session_expire(&self, token: String) -> Option<()>;
-
     /// Maximum time session can remain idle before expiring
     fn session_idle_timeout(&self) -> Duration;
 
@@ -113,13 +110,10 @@ where
         };
 
         // if the session has gone unused for longer than idle_timeout, it is
-        // expired
+        // expired.  Expired sessions are left in place here; they're deleted
+        // periodically in the background.
         let now = Utc::now();
         if session.time_last_used() + ctx.session_idle_timeout() < now {
-            let expired_session = ctx.session_expire(token.clone()).await;
-            if expired_session.is_none() {
-                debug!(log, "failed to expire session")
-            }
             return SchemeResult::Failed(Reason::BadCredentials {
                 actor,
                 source: anyhow!(
@@ -136,10 +130,6 @@ where
         // existed longer than absolute_timeout, it is expired and we can no
         // longer extend the session
         if session.time_created() + ctx.session_absolute_timeout() < now {
-            let expired_session = ctx.session_expire(token.clone()).await;
-            if expired_session.is_none() {
-                debug!(log, "failed to expire session")
-            }
             return SchemeResult::Failed(Reason::BadCredentials {
                 actor,
                 source: anyhow!(
@@ -162,7 +152,7 @@ where
             debug!(log, "failed to extend session")
         }
 
-        SchemeResult::Authenticated(Details { actor })
+        SchemeResult::Authenticated(Details { actor, scope: None })
     }
 }
 
@@ -241,12 +231,6 @@ mod test {
             (*sessions).insert(token, new_session)
         }
 
-        async fn session_expire(&self, token: String) -> Option<()> {
-            let mut sessions = self.sessions.lock().unwrap();
-            (*sessions).remove(&token);
-            Some(())
-        }
-
         fn session_idle_timeout(&self) -> Duration {
             Duration::hours(1)
         }
@@ -308,8 +292,12 @@ mod test {
             })
         ));
 
-        // key should be removed from sessions dict, i.e., session deleted
-        assert!(!context.sessions.lock().unwrap().contains_key("abc"))
+        // the expired session isn't deleted here, but it isn't extended either
+        let sessions = context.sessions.lock().unwrap();
+        assert!(
+            sessions.get("abc").unwrap().time_last_used
+                < Utc::now() - Duration::hours(1)
+        )
     }
 
     #[tokio::test]
@@ -334,9 +322,9 @@ mod test {
             })
         ));
 
-        // key should be removed from sessions dict, i.e., session deleted
+        // the expired session isn't deleted here, but it isn't extended either
         let sessions = context.sessions.lock().unwrap();
-        assert!(!sessions.contains_key("abc"))
+        assert!(sessions.get("abc").unwrap().time_last_used < Utc::now())
     }
 
     #[tokio::test]
@@ -356,7 +344,7 @@ mod test {
         let result = authn_with_cookie(&context, Some("session=abc")).await;
         assert!(matches!(
             result,
-            SchemeResult::Authenticated(Details { actor: _ })
+            SchemeResult::Authenticated(Details { actor: _, scope: None })
         ));
 
         // valid cookie should have updated time_last_used
@@ -25,6 +25,7 @@
 //! authentication, but they'd all produce the same [`Context`] struct.
 
 pub mod external;
+pub mod passwords;
 pub mod saga;
 pub mod silos;
 
@@ -35,12 +36,14 @@ pub use crate::db::fixed_data::user_builtin::USER_EXTERNAL_AUTHN;
 pub use crate::db::fixed_data::user_builtin::USER_INTERNAL_API;
 pub use crate::db::fixed_data::user_builtin::USER_INTERNAL_READ;
 pub use crate::db::fixed_data::user_builtin::USER_SAGA_RECOVERY;
+pub use crate::db::fixed_data::user_builtin::USER_SAML_METADATA_REFRESH;
 pub use crate::db::fixed_data::user_builtin::USER_SERVICE_BALANCER;
 use crate::db::model::ConsoleSession;
 
 use crate::authz;
 use crate::db;
 use crate::db::identity::Asset;
+use crate::external_api::shared;
 use omicron_common::api::external::LookupType;
 use serde::Deserialize;
 use serde::Serialize;
@@ -77,7 +80,7 @@ impl Context {
         &self,
     ) -> Result<&Actor, omicron_common::api::external::Error> {
         match &self.kind {
-            Kind::Authenticated(Details { actor }) => Ok(actor),
+            Kind::Authenticated(Details { actor, .. }) => Ok(actor),
             Kind::Unauthenticated => {
                 Err(omicron_common::api::external::Error::Unauthenticated {
                     internal_message: "Actor required".to_string(),
@@ -123,7 +126,8 @@ impl Context {
         &self,
     ) -> Result<Option<authz::Silo>, omicron_common::api::external::Error> {
         self.actor_required().map(|actor| match actor {
-            Actor::SiloUser { silo_id, .. } => Some(authz::Silo::new(
+            Actor::SiloUser { silo_id, .. }
+            | Actor::ServiceAccount { silo_id, .. } => Some(authz::Silo::new(
                 authz::FLEET,
                 *silo_id,
                 LookupType::ById(*silo_id),
@@ -132,6 +136,17 @@ impl Context {
         })
     }
 
+    /// Returns the limits on what the credential used to authenticate may be
+    /// used for, if any
+    ///
+    /// These apply in addition to whatever roles the actor has been granted.
+    pub fn scope(&self) -> Option<&shared::TokenScope> {
+        match &self.kind {
+            Kind::Authenticated(Details { scope, .. }) => scope.as_ref(),
+            Kind::Unauthenticated => None,
+        }
+    }
+
     /// Returns the list of schemes tried, in order
     ///
     /// This should generally *not* be exposed to clients.
@@ -171,6 +186,12 @@ impl Context {
         Context::context_for_builtin_user(USER_DB_INIT.id)
     }
 
+    /// Returns an authenticated context for refreshing the metadata of SAML
+    /// identity providers
+    pub fn internal_saml_metadata_refresh() -> Context {
+        Context::context_for_builtin_user(USER_SAML_METADATA_REFRESH.id)
+    }
+
     /// Returns an authenticated context for Nexus-driven service balancing.
     pub fn internal_service_balancer() -> Context {
         Context::context_for_builtin_user(USER_SERVICE_BALANCER.id)
@@ -180,6 +201,7 @@ impl Context {
         Context {
             kind: Kind::Authenticated(Details {
                 actor: Actor::UserBuiltin { user_builtin_id },
+                scope: None,
             }),
             schemes_tried: Vec::new(),
         }
@@ -195,6 +217,7 @@ impl Context {
                     silo_user_id: USER_TEST_PRIVILEGED.id(),
                     silo_id: USER_TEST_PRIVILEGED.silo_id,
                 },
+                scope: None,
             }),
             schemes_tried: Vec::new(),
         }
@@ -216,6 +239,24 @@ impl Context {
         Context {
             kind: Kind::Authenticated(Details {
                 actor: Actor::SiloUser { silo_user_id, silo_id },
+                scope: None,
+            }),
+            schemes_tried: Vec::new(),
+        }
+    }
+
+    /// Returns an authenticated context for the specific Silo user using a
+    /// credential limited to `scope`
+    #[cfg(test)]
+    pub fn for_test_user_with_scope(
+        silo_user_id: Uuid,
+        silo_id: Uuid,
+        scope: shared::TokenScope,
+    ) -> Context {
+        Context {
+            kind: Kind::Authenticated(Details {
+                actor: Actor::SiloUser { silo_user_id, silo_id },
+                scope: Some(scope),
             }),
             schemes_tried: Vec::new(),
         }
@@ -229,6 +270,7 @@ mod test {
     use super::USER_INTERNAL_API;
     use super::USER_INTERNAL_READ;
     use super::USER_SAGA_RECOVERY;
+    use super::USER_SAML_METADATA_REFRESH;
     use super::USER_SERVICE_BALANCER;
     use super::USER_TEST_PRIVILEGED;
     use super::USER_TEST_UNPRIVILEGED;
@@ -272,6 +314,10 @@ mod test {
         let actor = authn.actor().unwrap();
         assert_eq!(actor.actor_id(), USER_SAGA_RECOVERY.id);
 
+        let authn = Context::internal_saml_metadata_refresh();
+        let actor = authn.actor().unwrap();
+        assert_eq!(actor.actor_id(), USER_SAML_METADATA_REFRESH.id);
+
         let authn = Context::internal_api();
         let actor = authn.actor().unwrap();
         assert_eq!(actor.actor_id(), USER_INTERNAL_API.id);
@@ -296,13 +342,32 @@ enum Kind {
 pub struct Details {
     /// the actor performing the request
     actor: Actor,
+    /// limits on what the credential used to authenticate may be used for
+    scope: Option<shared::TokenScope>,
 }
 
 /// Who is performing an operation
 #[derive(Clone, Copy, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Actor {
-    UserBuiltin { user_builtin_id: Uuid },
-    SiloUser { silo_user_id: Uuid, silo_id: Uuid },
+    UserBuiltin {
+        user_builtin_id: Uuid,
+    },
+    SiloUser {
+        silo_user_id: Uuid,
+        silo_id: Uuid,
+    },
+    ServiceAccount {
+        service_account_id: Uuid,
+        silo_id: Uuid,
+        project_scope: Option<ProjectScope>,
+    },
+}
+
+/// The Project (and its Organization) that a service account is limited to
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ProjectScope {
+    pub organization_id: Uuid,
+    pub project_id: Uuid,
 }
 
 impl Actor {
@@ -310,6 +375,9 @@ impl Actor {
         match self {
             Actor::UserBuiltin { user_builtin_id, .. } => *user_builtin_id,
             Actor::SiloUser { silo_user_id, .. } => *silo_user_id,
+            Actor::ServiceAccount { service_account_id, .. } => {
+                *service_account_id
+            }
         }
     }
 
@@ -317,6 +385,7 @@ impl Actor {
         match self {
             Actor::UserBuiltin { .. } => None,
             Actor::SiloUser { silo_id, .. } => Some(*silo_id),
+            Actor::ServiceAccount { silo_id, .. } => Some(*silo_id),
         }
     }
 
@@ -324,6 +393,16 @@ impl Actor {
         match self {
             Actor::UserBuiltin { .. } => None,
             Actor::SiloUser { silo_user_id, .. } => Some(*silo_user_id),
+            Actor::ServiceAccount { .. } => None,
+        }
+    }
+
+    /// Returns the Project this actor is limited to, if any
+    pub fn project_scope(&self) -> Option<ProjectScope> {
+        match self {
+            Actor::UserBuiltin { .. } => None,
+            Actor::SiloUser { .. } => None,
+            Actor::ServiceAccount { project_scope, .. } => *project_scope,
         }
     }
 }
@@ -333,6 +412,9 @@ impl From<&Actor> for db::model::IdentityType {
         match actor {
             Actor::UserBuiltin { .. } => db::model::IdentityType::UserBuiltin,
             Actor::SiloUser { .. } => db::model::IdentityType::SiloUser,
+            Actor::ServiceAccount { .. } => {
+                db::model::IdentityType::ServiceAccount
+            }
         }
     }
 }
@@ -356,6 +438,16 @@ impl std::fmt::Debug for Actor {
                 .field("silo_user_id", &silo_user_id)
                 .field("silo_id", &silo_id)
                 .finish_non_exhaustive(),
+            Actor::ServiceAccount {
+                service_account_id,
+                silo_id,
+                project_scope,
+            } => f
+                .debug_struct("Actor::ServiceAccount")
+                .field("service_account_id", &service_account_id)
+                .field("silo_id", &silo_id)
+                .field("project_scope", &project_scope)
+                .finish_non_exhaustive(),
         }
     }
 }
@@ -419,6 +511,14 @@ pub enum Reason {
         source: anyhow::Error,
     },
 
+    /// The credentials were valid once, but they have expired
+    #[error("expired credentials for actor {actor:?}")]
+    ExpiredCredentials { actor: Actor },
+
+    /// The credentials were valid once, but they have been revoked
+    #[error("revoked credentials for actor {actor:?}")]
+    RevokedCredentials { actor: Actor },
+
     /// Operational error while attempting to authenticate
     #[error("unexpected error during authentication: {source:#}")]
     UnknownError {
@@ -448,11 +548,15 @@ impl From<Error> for dropshot::HttpError {
             // this on all requests, since different creds can always change the
             // behavior.
             e @ Reason::UnknownActor { .. }
-            | e @ Reason::BadCredentials { .. } => dropshot::HttpError::from(
-                omicron_common::api::external::Error::Unauthenticated {
-                    internal_message: format!("{:#}", e),
-                },
-            ),
+            | e @ Reason::BadCredentials { .. }
+            | e @ Reason::ExpiredCredentials { .. }
+            | e @ Reason::RevokedCredentials { .. } => {
+                dropshot::HttpError::from(
+                    omicron_common::api::external::Error::Unauthenticated {
+                        internal_message: format!("{:#}", e),
+                    },
+                )
+            }
             Reason::UnknownError { source } => source.into(),
         }
     }
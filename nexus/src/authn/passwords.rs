@@ -0,0 +1,264 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Password hashing and login rate limiting for local users
+
+use argon2::password_hash::SaltString;
+use argon2::Algorithm;
+use argon2::Argon2;
+use argon2::Params;
+use argon2::PasswordHash;
+use argon2::PasswordHasher;
+use argon2::PasswordVerifier;
+use argon2::Version;
+use lazy_static::lazy_static;
+use nexus_types::external_api::params::Password;
+use omicron_common::api::external::Error;
+use rand::rngs::OsRng;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+// Parameters for new hashes.  These follow the OWASP recommendation for
+// argon2id: 19 MiB of memory, 2 iterations, 1 degree of parallelism.  Each
+// hash records the parameters it was made with, so these can be raised
+// without invalidating existing passwords.
+const ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+
+lazy_static! {
+    /// Hash verified when there's no actual hash to check a password against
+    ///
+    /// Verifying against this whenever a user doesn't exist or has no password
+    /// keeps the time a login takes from revealing which users exist.
+    static ref DUMMY_HASH: String =
+        hash_blocking(b"not anyone's password").unwrap();
+}
+
+fn argon2() -> Argon2<'static> {
+    let params = Params::new(
+        ARGON2_MEMORY_KIB,
+        ARGON2_ITERATIONS,
+        ARGON2_PARALLELISM,
+        None,
+    )
+    .expect("invalid argon2 parameters");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+fn hash_blocking(password: &[u8]) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password, &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|error| {
+            Error::internal_error(&format!("hashing password: {}", error))
+        })
+}
+
+fn verify_blocking(password: &[u8], hash: &str) -> Result<bool, Error> {
+    let hash = PasswordHash::new(hash).map_err(|error| {
+        Error::internal_error(&format!("parsing password hash: {}", error))
+    })?;
+    Ok(argon2().verify_password(password, &hash).is_ok())
+}
+
+/// Hash `password` with argon2id, returning the hash as a PHC string
+///
+/// Hashing is deliberately expensive, so it's done on a thread where blocking
+/// is allowed.
+pub async fn hash(password: &Password) -> Result<String, Error> {
+    let password = password.as_ref().as_bytes().to_vec();
+    tokio::task::spawn_blocking(move || hash_blocking(&password))
+        .await
+        .map_err(|error| {
+            Error::internal_error(&format!("hashing password: {}", error))
+        })?
+}
+
+/// Returns whether `password` matches `hash`, a PHC string made by [`hash`]
+///
+/// If `hash` is `None` (because there's no user, or no password for them),
+/// this still does the work of checking a hash, then returns `false`.
+pub async fn verify(
+    password: &Password,
+    hash: Option<String>,
+) -> Result<bool, Error> {
+    let password = password.as_ref().as_bytes().to_vec();
+    tokio::task::spawn_blocking(move || match hash {
+        Some(hash) => verify_blocking(&password, &hash),
+        None => verify_blocking(&password, &DUMMY_HASH).map(|_| false),
+    })
+    .await
+    .map_err(|error| {
+        Error::internal_error(&format!("verifying password: {}", error))
+    })?
+}
+
+/// Number of consecutive failed logins after which a user is locked out
+pub const MAX_FAILED_LOGINS: i64 = 5;
+
+/// How long a user is locked out after too many failed logins
+pub const LOCKOUT_DURATION: Duration = Duration::from_secs(15 * 60);
+
+/// Number of login attempts allowed for one username within
+/// [`LOGIN_RATE_WINDOW`]
+pub const LOGIN_RATE_MAX_ATTEMPTS: usize = 10;
+
+/// Period over which login attempts are counted for rate limiting
+pub const LOGIN_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Most usernames whose recent login attempts are remembered at once
+pub const LOGIN_RATE_MAX_USERNAMES: usize = 10_000;
+
+/// Limits the rate of login attempts for each username in each silo
+///
+/// This protects against guessing passwords faster than the lockout after
+/// repeated failures would otherwise allow, and against spending all of
+/// Nexus's time hashing.  It's kept in memory, so each Nexus instance keeps
+/// its own count.
+///
+/// Usernames come from clients, so the number remembered is capped: once
+/// it's reached, the username tried least recently is forgotten to make room.
+/// That user's attempts start being counted again from zero, but they're
+/// still subject to the lockout after repeated failures.
+pub struct LoginRateLimiter {
+    max_attempts: usize,
+    window: Duration,
+    max_usernames: usize,
+    /// times of recent attempts, for each (silo name, username)
+    attempts: Mutex<BTreeMap<(String, String), VecDeque<Instant>>>,
+}
+
+impl LoginRateLimiter {
+    /// Allow up to `max_attempts` logins for any one username within any
+    /// period of `window`, remembering at most `max_usernames` usernames
+    pub fn new(
+        max_attempts: usize,
+        window: Duration,
+        max_usernames: usize,
+    ) -> LoginRateLimiter {
+        LoginRateLimiter {
+            max_attempts,
+            window,
+            max_usernames,
+            attempts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record an attempt to log in as `username` in the given silo, returning
+    /// whether it's allowed
+    ///
+    /// Attempts that aren't allowed aren't recorded, so a client that keeps
+    /// trying regains access once it slows down.
+    pub fn attempt(&self, silo_name: &str, username: &str) -> bool {
+        self.attempt_at(silo_name, username, Instant::now())
+    }
+
+    fn attempt_at(
+        &self,
+        silo_name: &str,
+        username: &str,
+        now: Instant,
+    ) -> bool {
+        let mut attempts = self.attempts.lock().unwrap();
+
+        // Forget attempts that have left the window, so that the map only
+        // holds usernames that have been tried recently.
+        attempts.retain(|_, times| {
+            while let Some(time) = times.front() {
+                if now.duration_since(*time) < self.window {
+                    break;
+                }
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let key = (silo_name.to_string(), username.to_string());
+        if !attempts.contains_key(&key) && attempts.len() >= self.max_usernames
+        {
+            let oldest = attempts
+                .iter()
+                .min_by_key(|(_, times)| times.back().copied())
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                attempts.remove(&oldest);
+            }
+        }
+
+        let times = attempts.entry(key).or_default();
+        if times.len() >= self.max_attempts {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify() {
+        let password: Password = "correct horse".parse().unwrap();
+        let other: Password = "battery staple".parse().unwrap();
+        let hash = hash(&password).await.unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify(&password, Some(hash.clone())).await.unwrap());
+        assert!(!verify(&other, Some(hash.clone())).await.unwrap());
+
+        // Each hash is salted differently.
+        assert_ne!(hash, super::hash(&password).await.unwrap());
+
+        // Nothing matches a missing hash.
+        assert!(!verify(&password, None).await.unwrap());
+
+        // A malformed hash is an error, rather than a mismatch.
+        assert!(verify(&password, Some(String::from("bogus"))).await.is_err());
+    }
+
+    #[test]
+    fn test_login_rate_limiter() {
+        let limiter = LoginRateLimiter::new(2, Duration::from_secs(60), 100);
+        let start = Instant::now();
+        let later = start + Duration::from_secs(30);
+        let much_later = start + Duration::from_secs(61);
+
+        assert!(limiter.attempt_at("silo", "alice", start));
+        assert!(limiter.attempt_at("silo", "alice", later));
+        assert!(!limiter.attempt_at("silo", "alice", later));
+
+        // Other usernames, and the same username in other silos, are counted
+        // separately.
+        assert!(limiter.attempt_at("silo", "bob", later));
+        assert!(limiter.attempt_at("other-silo", "alice", later));
+
+        // Once the first attempt leaves the window, there's room for one more.
+        assert!(limiter.attempt_at("silo", "alice", much_later));
+        assert!(!limiter.attempt_at("silo", "alice", much_later));
+    }
+
+    #[test]
+    fn test_login_rate_limiter_max_usernames() {
+        let limiter = LoginRateLimiter::new(1, Duration::from_secs(60), 2);
+        let start = Instant::now();
+        let later = start + Duration::from_secs(1);
+
+        assert!(limiter.attempt_at("silo", "alice", start));
+        assert!(limiter.attempt_at("silo", "bob", later));
+        assert!(!limiter.attempt_at("silo", "alice", later));
+
+        // A third username makes room for itself by forgetting alice, who was
+        // tried least recently.
+        assert!(limiter.attempt_at("silo", "carol", later));
+        assert_eq!(limiter.attempts.lock().unwrap().len(), 2);
+        assert!(!limiter.attempt_at("silo", "bob", later));
+        assert!(limiter.attempt_at("silo", "alice", later));
+    }
+}
@@ -10,7 +10,9 @@ use crate::db::lookup::LookupPath;
 use crate::db::{model, DataStore};
 use omicron_common::api::external::LookupResult;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 use samael::metadata::ContactPerson;
 use samael::metadata::ContactType;
 use samael::metadata::EntityDescriptor;
@@ -66,6 +68,7 @@ impl TryFrom<model::SamlIdentityProvider> for SamlIdentityProvider {
 
 pub enum IdentityProviderType {
     Saml(SamlIdentityProvider),
+    Oidc(OidcIdentityProvider),
 }
 
 impl IdentityProviderType {
@@ -115,6 +118,32 @@ impl IdentityProviderType {
 
                 Ok((authz_silo, db_silo, saml_identity_provider))
             }
+
+            model::IdentityProviderType::Oidc => {
+                let (.., oidc_identity_provider) =
+                    LookupPath::new(opctx, datastore)
+                        .silo_name(silo_name)
+                        .oidc_identity_provider_name(provider_name)
+                        .fetch()
+                        .await?;
+
+                // As with SAML, the provider was validated before it went
+                // into the DB, so failing to convert it is a server error.
+                let oidc_identity_provider = IdentityProviderType::Oidc(
+                    oidc_identity_provider.try_into().map_err(
+                        |e: anyhow::Error| {
+                            omicron_common::api::external::Error::internal_error(
+                                &format!(
+                                    "oidc_identity_provider.try_into() failed! {:#}",
+                                    e
+                                ),
+                            )
+                        },
+                    )?,
+                );
+
+                Ok((authz_silo, db_silo, oidc_identity_provider))
+            }
         }
     }
 }
@@ -444,3 +473,415 @@ pub struct AuthenticatedSubject {
     pub external_id: String,
     pub groups: Vec<String>,
 }
+
+/// Signature algorithms accepted for ID tokens
+///
+/// These all use RSA keys, which is what providers publish by default.
+const OIDC_ID_TOKEN_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+];
+
+/// The parts of an OpenID Provider's discovery document (OpenID Connect
+/// Discovery 1.0, section 3) that are used to log users in
+#[derive(Debug, Deserialize)]
+pub struct OidcProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Option<Vec<String>>,
+}
+
+pub struct OidcIdentityProvider {
+    pub metadata: OidcProviderMetadata,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub group_claim_name: Option<String>,
+}
+
+impl TryFrom<model::OidcIdentityProvider> for OidcIdentityProvider {
+    type Error = anyhow::Error;
+    fn try_from(
+        model: model::OidcIdentityProvider,
+    ) -> Result<Self, Self::Error> {
+        let metadata: OidcProviderMetadata =
+            serde_json::from_str(&model.discovery_document_string)
+                .context("parsing discovery document")?;
+
+        // The issuer in the discovery document must be exactly the one it was
+        // fetched for (OpenID Connect Discovery 1.0, section 4.3).
+        if metadata.issuer != model.issuer {
+            bail!(
+                "discovery document issuer {} does not match configured \
+                issuer {}",
+                metadata.issuer,
+                model.issuer
+            );
+        }
+
+        // Logins always use PKCE with the S256 method.  Providers that don't
+        // advertise which methods they support are given the benefit of the
+        // doubt.
+        if let Some(methods) = &metadata.code_challenge_methods_supported {
+            if !methods.iter().any(|m| m == "S256") {
+                bail!("provider does not support PKCE with S256");
+            }
+        }
+
+        let provider = OidcIdentityProvider {
+            metadata,
+            client_id: model.client_id,
+            client_secret: model.client_secret,
+            redirect_uri: model.redirect_uri,
+            group_claim_name: model.group_claim_name,
+        };
+
+        // check that there is a valid authorization url
+        let _authorization_url =
+            provider.authorization_url(&OidcLoginState::new(None))?;
+
+        Ok(provider)
+    }
+}
+
+/// Secrets of an OpenID Connect login in progress
+///
+/// These are generated when the user is sent to their provider and kept in a
+/// cookie on their browser until the provider sends them back, which ties the
+/// response to the browser that started the login.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcLoginState {
+    /// returned by the provider along with the authorization code
+    pub state: String,
+    /// included by the provider in the ID token
+    pub nonce: String,
+    /// PKCE code verifier (RFC 7636), sent when redeeming the code
+    pub code_verifier: String,
+    /// where to send the user once they've logged in
+    pub referer: Option<String>,
+}
+
+impl OidcLoginState {
+    pub fn new(referer: Option<String>) -> Self {
+        OidcLoginState {
+            state: random_url_safe_string(),
+            nonce: random_url_safe_string(),
+            code_verifier: random_url_safe_string(),
+            referer,
+        }
+    }
+
+    /// Encode this state so that it can be used as a cookie value
+    pub fn to_encoded(&self) -> Result<String> {
+        Ok(base64::encode_config(
+            serde_json::to_string(&self).context("encoding login state")?,
+            base64::URL_SAFE_NO_PAD,
+        ))
+    }
+
+    pub fn from_encoded(encoded: &str) -> Result<Self> {
+        let bytes = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .context("base64 decoding login state")?;
+        serde_json::from_slice(&bytes).context("json from login state")
+    }
+}
+
+/// 32 random bytes, encoded with the URL-safe base64 alphabet
+///
+/// The encoding is 43 characters, which is also within the length allowed for
+/// a PKCE code verifier.
+fn random_url_safe_string() -> String {
+    let mut rng = StdRng::from_entropy();
+    let mut random_bytes: [u8; 32] = [0; 32];
+    rng.fill_bytes(&mut random_bytes);
+    base64::encode_config(random_bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Returns the PKCE code challenge for `code_verifier` using the S256 method
+/// (RFC 7636, section 4.2)
+pub fn pkce_code_challenge(code_verifier: &str) -> String {
+    let digest =
+        ring::digest::digest(&ring::digest::SHA256, code_verifier.as_bytes());
+    base64::encode_config(digest.as_ref(), base64::URL_SAFE_NO_PAD)
+}
+
+#[derive(Deserialize)]
+struct OidcTokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct OidcJwkSet {
+    keys: Vec<OidcJwk>,
+}
+
+/// A key from a provider's JWK set (RFC 7517)
+///
+/// Only the fields of RSA public keys are read.
+#[derive(Deserialize)]
+struct OidcJwk {
+    kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    key_use: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OidcIdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    #[serde(flatten)]
+    other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl OidcIdentityProvider {
+    /// Returns the URL of the provider's authorization endpoint to which the
+    /// user should be sent to log in, using the secrets in `login_state`
+    pub fn authorization_url(
+        &self,
+        login_state: &OidcLoginState,
+    ) -> Result<String> {
+        let mut url =
+            reqwest::Url::parse(&self.metadata.authorization_endpoint)
+                .context("parsing authorization endpoint")?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("scope", "openid profile email")
+            .append_pair("state", &login_state.state)
+            .append_pair("nonce", &login_state.nonce)
+            .append_pair(
+                "code_challenge",
+                &pkce_code_challenge(&login_state.code_verifier),
+            )
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.to_string())
+    }
+
+    /// Redeem the authorization `code` the provider sent to the callback, and
+    /// return the subject of the ID token that comes back
+    ///
+    /// The ID token's signature is checked against the provider's published
+    /// keys, along with its issuer, audience, expiry and nonce.
+    pub async fn authenticated_subject(
+        &self,
+        code: &str,
+        login_state: &OidcLoginState,
+    ) -> Result<AuthenticatedSubject, HttpError> {
+        let dur = std::time::Duration::from_secs(5);
+        let client = reqwest::ClientBuilder::new()
+            .connect_timeout(dur)
+            .timeout(dur)
+            .build()
+            .map_err(|e| {
+                HttpError::for_internal_error(format!(
+                    "failed to build reqwest client: {}",
+                    e
+                ))
+            })?;
+
+        // Redeem the authorization code for an ID token.
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", login_state.code_verifier.as_str()),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret.as_str()));
+        }
+
+        let response = client
+            .post(&self.metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| {
+                HttpError::for_unavail(
+                    None,
+                    format!("error querying token endpoint! {}", e),
+                )
+            })?;
+
+        if !response.status().is_success() {
+            return Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "token endpoint rejected authorization code: {}",
+                    response.status()
+                ),
+            ));
+        }
+
+        let token_response: OidcTokenResponse =
+            response.json().await.map_err(|e| {
+                HttpError::for_unavail(
+                    None,
+                    format!("error reading token endpoint response! {}", e),
+                )
+            })?;
+        let id_token = token_response.id_token;
+
+        // Find the key that signed the ID token.  Providers rotate their keys,
+        // so these are fetched for each login rather than stored.
+        let header = jsonwebtoken::decode_header(&id_token).map_err(|e| {
+            HttpError::for_bad_request(
+                None,
+                format!("could not parse ID token header! {}", e),
+            )
+        })?;
+
+        if !OIDC_ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(HttpError::for_bad_request(
+                None,
+                format!("unsupported ID token algorithm {:?}", header.alg),
+            ));
+        }
+
+        let jwk_set: OidcJwkSet = client
+            .get(&self.metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| {
+                HttpError::for_unavail(
+                    None,
+                    format!("error querying jwks uri! {}", e),
+                )
+            })?
+            .json()
+            .await
+            .map_err(|e| {
+                HttpError::for_unavail(
+                    None,
+                    format!("error reading jwks! {}", e),
+                )
+            })?;
+
+        let jwk = jwk_set
+            .keys
+            .iter()
+            .filter(|jwk| jwk.kty == "RSA")
+            .filter(|jwk| jwk.key_use.as_deref().unwrap_or("sig") == "sig")
+            .find(|jwk| header.kid.is_none() || jwk.kid == header.kid)
+            .ok_or_else(|| {
+                HttpError::for_bad_request(
+                    None,
+                    format!("no key found for ID token kid {:?}", header.kid),
+                )
+            })?;
+
+        let decoding_key = match (&jwk.n, &jwk.e) {
+            (Some(n), Some(e)) => DecodingKey::from_rsa_components(n, e)
+                .map_err(|e| {
+                    HttpError::for_unavail(
+                        None,
+                        format!("bad RSA key in jwks! {}", e),
+                    )
+                })?,
+            _ => {
+                return Err(HttpError::for_unavail(
+                    None,
+                    "RSA key in jwks is missing its components".into(),
+                ))
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[&self.metadata.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = jsonwebtoken::decode::<OidcIdTokenClaims>(
+            &id_token,
+            &decoding_key,
+            &validation,
+        )
+        .map_err(|e| {
+            HttpError::for_bad_request(
+                None,
+                format!("ID token not valid! {}", e),
+            )
+        })?
+        .claims;
+
+        // The nonce ties the ID token to this login, which keeps an ID token
+        // issued for some other login from being replayed here.
+        if claims.nonce.as_deref() != Some(login_state.nonce.as_str()) {
+            return Err(HttpError::for_bad_request(
+                None,
+                "ID token nonce does not match login".into(),
+            ));
+        }
+
+        let mut groups = Vec::new();
+        if let Some(group_claim_name) = &self.group_claim_name {
+            let values = match claims.other.get(group_claim_name) {
+                Some(serde_json::Value::String(group)) => {
+                    vec![group.as_str()]
+                }
+                Some(serde_json::Value::Array(values)) => {
+                    values.iter().filter_map(|value| value.as_str()).collect()
+                }
+                _ => vec![],
+            };
+
+            for group in values {
+                // Trim whitespace, and skip empty groups
+                let group = group.trim();
+                if !group.is_empty() {
+                    groups.push(group.to_string());
+                }
+            }
+        }
+
+        Ok(AuthenticatedSubject { external_id: claims.sub, groups })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pkce_code_challenge() {
+        // The challenge is the unpadded base64url encoding of the SHA-256
+        // digest of the verifier.
+        assert_eq!(
+            pkce_code_challenge("dBjftJeZ4CVP-mJ92K9qBG4dDC7fnw7w2zPgLQNrTwk"),
+            "lEYfQPa98-gub1cK4E1i_hFfS1H0XZ4v78f4hoNiiRQ",
+        );
+    }
+
+    #[test]
+    fn test_oidc_login_state_round_trip() {
+        let login_state =
+            OidcLoginState::new(Some(String::from("https://example.com/a")));
+        assert_eq!(login_state.code_verifier.len(), 43);
+        assert_ne!(login_state.state, login_state.nonce);
+
+        let encoded = login_state.to_encoded().unwrap();
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let decoded = OidcLoginState::from_encoded(&encoded).unwrap();
+        assert_eq!(decoded.state, login_state.state);
+        assert_eq!(decoded.nonce, login_state.nonce);
+        assert_eq!(decoded.code_verifier, login_state.code_verifier);
+        assert_eq!(decoded.referer, login_state.referer);
+
+        assert!(OidcLoginState::from_encoded("not state").is_err());
+    }
+}
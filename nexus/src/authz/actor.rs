@@ -4,9 +4,11 @@
 
 //! Oso integration for Actor types
 
+use super::oso_generic::Perm;
 use super::roles::RoleSet;
 use crate::authn;
 use crate::authz::SiloUser;
+use crate::external_api::shared;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
 use uuid::Uuid;
@@ -16,13 +18,15 @@ use uuid::Uuid;
 #[derive(Clone, Debug)]
 pub struct AnyActor {
     actor: Option<authn::Actor>,
+    scope: Option<shared::TokenScope>,
     roles: RoleSet,
 }
 
 impl AnyActor {
     pub fn new(authn: &authn::Context, roles: RoleSet) -> Self {
         let actor = authn.actor().cloned();
-        AnyActor { actor, roles }
+        let scope = authn.scope().cloned();
+        AnyActor { actor, scope, roles }
     }
 }
 
@@ -36,6 +40,8 @@ impl oso::PolarClass for AnyActor {
                 a.actor.map(|actor| AuthenticatedActor {
                     actor_id: actor.actor_id(),
                     silo_id: actor.silo_id(),
+                    project_scope: actor.project_scope(),
+                    token_scope: a.scope.clone(),
                     roles: a.roles.clone(),
                 })
             })
@@ -47,6 +53,8 @@ impl oso::PolarClass for AnyActor {
 pub struct AuthenticatedActor {
     actor_id: Uuid,
     silo_id: Option<Uuid>,
+    project_scope: Option<authn::ProjectScope>,
+    token_scope: Option<shared::TokenScope>,
     roles: RoleSet,
 }
 
@@ -78,6 +86,8 @@ impl oso::PolarClass for AuthenticatedActor {
                 AuthenticatedActor {
                     actor_id: authn::USER_DB_INIT.id,
                     silo_id: None,
+                    project_scope: None,
+                    token_scope: None,
                     roles: RoleSet::new(),
                 },
                 "USER_DB_INIT",
@@ -86,6 +96,8 @@ impl oso::PolarClass for AuthenticatedActor {
                 AuthenticatedActor {
                     actor_id: authn::USER_INTERNAL_API.id,
                     silo_id: None,
+                    project_scope: None,
+                    token_scope: None,
                     roles: RoleSet::new(),
                 },
                 "USER_INTERNAL_API",
@@ -99,9 +111,93 @@ impl oso::PolarClass for AuthenticatedActor {
                     )
                 })
             })
+            .add_attribute_getter("project_scope", |a: &AuthenticatedActor| {
+                // The scope is only ever set for actors in a Silo.
+                a.project_scope.zip(a.silo_id).map(|(scope, silo_id)| {
+                    let silo = super::Silo::new(
+                        super::FLEET,
+                        silo_id,
+                        LookupType::ById(silo_id),
+                    );
+                    let organization = super::Organization::new(
+                        silo,
+                        scope.organization_id,
+                        LookupType::ById(scope.organization_id),
+                    );
+                    super::Project::new(
+                        organization,
+                        scope.project_id,
+                        LookupType::ById(scope.project_id),
+                    )
+                })
+            })
+            .add_attribute_getter("token_scope", |a: &AuthenticatedActor| {
+                // Scoped tokens are only ever granted to actors in a Silo.
+                a.token_scope
+                    .clone()
+                    .zip(a.silo_id)
+                    .map(|(scope, silo_id)| TokenScope::new(&scope, silo_id))
+            })
             .add_method(
                 "equals_silo_user",
                 |a: &AuthenticatedActor, u: SiloUser| a.actor_id == u.id(),
             )
     }
 }
+
+/// Represents the limits on an access token ([`shared::TokenScope`]) for Polar
+#[derive(Clone, Debug)]
+pub struct TokenScope {
+    permissions: Vec<String>,
+    organization: Option<super::Organization>,
+    project: Option<super::Project>,
+}
+
+impl TokenScope {
+    fn new(scope: &shared::TokenScope, silo_id: Uuid) -> TokenScope {
+        let silo =
+            super::Silo::new(super::FLEET, silo_id, LookupType::ById(silo_id));
+        let organization = scope.organization_id.map(|organization_id| {
+            super::Organization::new(
+                silo,
+                organization_id,
+                LookupType::ById(organization_id),
+            )
+        });
+        // A scope that names a Project always names its Organization, too.
+        let project = scope.project_id.zip(organization.clone()).map(
+            |(project_id, organization)| {
+                super::Project::new(
+                    organization,
+                    project_id,
+                    LookupType::ById(project_id),
+                )
+            },
+        );
+        // If that somehow isn't the case, deny everything rather than treat
+        // the token as unlimited.
+        let permissions = if scope.project_id.is_some() && project.is_none() {
+            Vec::new()
+        } else {
+            scope
+                .actions
+                .iter()
+                .map(|action| Perm::from(action).to_string())
+                .collect()
+        };
+        TokenScope { permissions, organization, project }
+    }
+}
+
+impl oso::PolarClass for TokenScope {
+    fn get_polar_class_builder() -> oso::ClassBuilder<Self> {
+        oso::Class::builder()
+            .add_attribute_getter("permissions", |s: &TokenScope| {
+                s.permissions.clone()
+            })
+            .add_attribute_getter("organization", |s: &TokenScope| {
+                s.organization.clone()
+            })
+            .add_attribute_getter("project", |s: &TokenScope| s.project.clone())
+    }
+}
@@ -616,6 +616,63 @@ impl AuthorizedResource for SiloUserList {
     }
 }
 
+/// Synthetic resource describing the list of Groups in a Silo
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SiloGroupList(Silo);
+
+impl SiloGroupList {
+    pub fn new(silo: Silo) -> SiloGroupList {
+        SiloGroupList(silo)
+    }
+
+    pub fn silo(&self) -> &Silo {
+        &self.0
+    }
+}
+
+impl oso::PolarClass for SiloGroupList {
+    fn get_polar_class_builder() -> oso::ClassBuilder<Self> {
+        oso::Class::builder()
+            .with_equality_check()
+            .add_attribute_getter("silo", |list: &SiloGroupList| list.0.clone())
+    }
+}
+
+impl AuthorizedResource for SiloGroupList {
+    fn load_roles<'a, 'b, 'c, 'd, 'e, 'f>(
+        &'a self,
+        opctx: &'b OpContext,
+        datastore: &'c DataStore,
+        authn: &'d authn::Context,
+        roleset: &'e mut RoleSet,
+    ) -> futures::future::BoxFuture<'f, Result<(), Error>>
+    where
+        'a: 'f,
+        'b: 'f,
+        'c: 'f,
+        'd: 'f,
+        'e: 'f,
+    {
+        // There are no roles on this resource, but we still need to load the
+        // Silo-related roles.
+        self.silo().load_roles(opctx, datastore, authn, roleset)
+    }
+
+    fn on_unauthorized(
+        &self,
+        _: &Authz,
+        error: Error,
+        _: AnyActor,
+        _: Action,
+    ) -> Error {
+        error
+    }
+
+    fn polar_class(&self) -> oso::Class {
+        Self::get_polar_class()
+    }
+}
+
 // Main resource hierarchy: Organizations, Projects, and their resources
 
 authz_resource! {
@@ -735,8 +792,51 @@ authz_resource! {
     name = "Disk",
     parent = "Project",
     primary_key = Uuid,
-    roles_allowed = false,
-    polar_snippet = InProject,
+    roles_allowed = true,
+    polar_snippet = Custom,
+}
+
+impl ApiResourceWithRolesType for Disk {
+    type AllowedRoles = DiskRole;
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Display,
+    Eq,
+    FromStr,
+    PartialEq,
+    Serialize,
+    JsonSchema,
+)]
+#[cfg_attr(test, derive(EnumIter))]
+#[display(style = "kebab-case")]
+#[serde(rename_all = "snake_case")]
+pub enum DiskRole {
+    Admin,
+    Viewer,
+}
+
+impl db::model::DatabaseString for DiskRole {
+    type Error = anyhow::Error;
+
+    fn to_database_string(&self) -> &str {
+        match self {
+            DiskRole::Admin => "admin",
+            DiskRole::Viewer => "viewer",
+        }
+    }
+
+    fn from_database_string(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "admin" => Ok(DiskRole::Admin),
+            "viewer" => Ok(DiskRole::Viewer),
+            _ => Err(anyhow!("unsupported Disk role from database: {:?}", s)),
+        }
+    }
 }
 
 authz_resource! {
@@ -759,8 +859,57 @@ authz_resource! {
     name = "Instance",
     parent = "Project",
     primary_key = Uuid,
-    roles_allowed = false,
-    polar_snippet = InProject,
+    roles_allowed = true,
+    polar_snippet = Custom,
+}
+
+impl ApiResourceWithRolesType for Instance {
+    type AllowedRoles = InstanceRole;
+}
+
+/// Roles that may be assigned on an individual Instance
+///
+/// Operators can start, stop, and reboot the Instance and use its serial
+/// console, but cannot otherwise modify or delete it.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Display,
+    Eq,
+    FromStr,
+    PartialEq,
+    Serialize,
+    JsonSchema,
+)]
+#[cfg_attr(test, derive(EnumIter))]
+#[display(style = "kebab-case")]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceRole {
+    Operator,
+    Viewer,
+}
+
+impl db::model::DatabaseString for InstanceRole {
+    type Error = anyhow::Error;
+
+    fn to_database_string(&self) -> &str {
+        match self {
+            InstanceRole::Operator => "operator",
+            InstanceRole::Viewer => "viewer",
+        }
+    }
+
+    fn from_database_string(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "operator" => Ok(InstanceRole::Operator),
+            "viewer" => Ok(InstanceRole::Viewer),
+            _ => {
+                Err(anyhow!("unsupported Instance role from database: {:?}", s))
+            }
+        }
+    }
 }
 
 authz_resource! {
@@ -775,8 +924,51 @@ authz_resource! {
     name = "Vpc",
     parent = "Project",
     primary_key = Uuid,
-    roles_allowed = false,
-    polar_snippet = InProject,
+    roles_allowed = true,
+    polar_snippet = Custom,
+}
+
+impl ApiResourceWithRolesType for Vpc {
+    type AllowedRoles = VpcRole;
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Display,
+    Eq,
+    FromStr,
+    PartialEq,
+    Serialize,
+    JsonSchema,
+)]
+#[cfg_attr(test, derive(EnumIter))]
+#[display(style = "kebab-case")]
+#[serde(rename_all = "snake_case")]
+pub enum VpcRole {
+    Admin,
+    Viewer,
+}
+
+impl db::model::DatabaseString for VpcRole {
+    type Error = anyhow::Error;
+
+    fn to_database_string(&self) -> &str {
+        match self {
+            VpcRole::Admin => "admin",
+            VpcRole::Viewer => "viewer",
+        }
+    }
+
+    fn from_database_string(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "admin" => Ok(VpcRole::Admin),
+            "viewer" => Ok(VpcRole::Viewer),
+            _ => Err(anyhow!("unsupported Vpc role from database: {:?}", s)),
+        }
+    }
 }
 
 authz_resource! {
@@ -939,6 +1131,22 @@ authz_resource! {
     polar_snippet = Custom,
 }
 
+authz_resource! {
+    name = "OidcIdentityProvider",
+    parent = "Silo",
+    primary_key = Uuid,
+    roles_allowed = false,
+    polar_snippet = Custom,
+}
+
+authz_resource! {
+    name = "ServiceAccount",
+    parent = "Silo",
+    primary_key = Uuid,
+    roles_allowed = false,
+    polar_snippet = Custom,
+}
+
 authz_resource! {
     name = "SshKey",
     parent = "SiloUser",
@@ -981,10 +1189,13 @@ authz_resource! {
 
 #[cfg(test)]
 mod test {
+    use super::DiskRole;
     use super::FleetRole;
+    use super::InstanceRole;
     use super::OrganizationRole;
     use super::ProjectRole;
     use super::SiloRole;
+    use super::VpcRole;
     use crate::db::test_database_string_impl;
 
     #[test]
@@ -1001,5 +1212,14 @@ mod test {
         test_database_string_impl::<ProjectRole, _>(
             "tests/output/authz-roles-project.txt",
         );
+        test_database_string_impl::<DiskRole, _>(
+            "tests/output/authz-roles-disk.txt",
+        );
+        test_database_string_impl::<InstanceRole, _>(
+            "tests/output/authz-roles-instance.txt",
+        );
+        test_database_string_impl::<VpcRole, _>(
+            "tests/output/authz-roles-vpc.txt",
+        );
     }
 }
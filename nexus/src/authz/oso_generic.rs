@@ -6,6 +6,7 @@
 
 use super::actor::AnyActor;
 use super::actor::AuthenticatedActor;
+use super::actor::TokenScope;
 use super::api_resources::*;
 use super::context::AuthorizedResource;
 use super::roles::RoleSet;
@@ -13,6 +14,7 @@ use super::Authz;
 use crate::authn;
 use crate::context::OpContext;
 use crate::db::DataStore;
+use crate::external_api::shared;
 use anyhow::ensure;
 use anyhow::Context;
 use futures::future::BoxFuture;
@@ -103,6 +105,7 @@ pub fn make_omicron_oso(log: &slog::Logger) -> Result<OsoInit, anyhow::Error> {
         Action::get_polar_class(),
         AnyActor::get_polar_class(),
         AuthenticatedActor::get_polar_class(),
+        TokenScope::get_polar_class(),
         Database::get_polar_class(),
         Fleet::get_polar_class(),
         IpPoolList::get_polar_class(),
@@ -111,6 +114,7 @@ pub fn make_omicron_oso(log: &slog::Logger) -> Result<OsoInit, anyhow::Error> {
         DeviceAuthRequestList::get_polar_class(),
         SiloIdentityProviderList::get_polar_class(),
         SiloUserList::get_polar_class(),
+        SiloGroupList::get_polar_class(),
     ];
     for c in classes {
         oso_builder = oso_builder.register_class(c)?;
@@ -139,8 +143,10 @@ pub fn make_omicron_oso(log: &slog::Logger) -> Result<OsoInit, anyhow::Error> {
         Silo::init(),
         SiloUser::init(),
         SiloGroup::init(),
+        ServiceAccount::init(),
         IdentityProvider::init(),
         SamlIdentityProvider::init(),
+        OidcIdentityProvider::init(),
         Sled::init(),
         UpdateAvailableArtifact::init(),
         UserBuiltin::init(),
@@ -169,6 +175,7 @@ pub enum Action {
     ModifyPolicy,
     CreateChild,
     Delete,
+    Operate, // only used for [`Instance`]
 }
 
 impl oso::PolarClass for Action {
@@ -197,6 +204,7 @@ pub enum Perm {
     Modify,
     ListChildren,
     CreateChild,
+    Operate, // Only for [`Instance`]
 }
 
 impl From<&Action> for Perm {
@@ -210,6 +218,19 @@ impl From<&Action> for Perm {
             Action::Delete => Perm::Modify,
             Action::ListChildren => Perm::ListChildren,
             Action::CreateChild => Perm::CreateChild,
+            Action::Operate => Perm::Operate,
+        }
+    }
+}
+
+impl From<&shared::TokenScopeAction> for Perm {
+    fn from(a: &shared::TokenScopeAction) -> Self {
+        match a {
+            shared::TokenScopeAction::Read => Perm::Read,
+            shared::TokenScopeAction::ListChildren => Perm::ListChildren,
+            shared::TokenScopeAction::Modify => Perm::Modify,
+            shared::TokenScopeAction::CreateChild => Perm::CreateChild,
+            shared::TokenScopeAction::Operate => Perm::Operate,
         }
     }
 }
@@ -224,6 +245,7 @@ impl fmt::Display for Perm {
             Perm::Modify => "modify",
             Perm::ListChildren => "list_children",
             Perm::CreateChild => "create_child",
+            Perm::Operate => "operate",
         })
     }
 }
@@ -31,6 +31,7 @@ use omicron_test_utils::dev;
 use resource_builder::DynAuthorizedResource;
 use resource_builder::ResourceBuilder;
 use resource_builder::ResourceSet;
+use std::collections::BTreeMap;
 use std::io::Cursor;
 use std::io::Write;
 use std::sync::Arc;
@@ -166,6 +167,502 @@ async fn test_iam_roles_behavior() {
     logctx.cleanup_successful();
 }
 
+/// Verifies that an access token's scope only ever narrows what its user may do
+///
+/// We create a user that administers one Organization and can view a Project
+/// in another one.  Then, for several different token scopes, we attempt every
+/// action on resources inside and outside those scopes.  A scoped token must
+/// never be able to do something that its user can't do without it, nor any
+/// action or resource outside of its scope.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scoped_token_cannot_escalate() {
+    let logctx = dev::test_setup_log("test_scoped_token_cannot_escalate");
+    let mut db = test_setup_database(&logctx.log).await;
+    let (opctx, datastore) = db::datastore::datastore_test(&logctx, &db).await;
+
+    let silo_id = Uuid::new_v4();
+    let silo =
+        authz::Silo::new(authz::FLEET, silo_id, LookupType::ById(silo_id));
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &silo,
+            &[shared::RoleAssignment {
+                identity_type: shared::IdentityType::SiloUser,
+                identity_id: USER_TEST_PRIVILEGED.id(),
+                role_name: SiloRole::Admin,
+            }],
+        )
+        .await
+        .unwrap();
+
+    let org1 = authz::Organization::new(
+        silo.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1")),
+    );
+    let org2 = authz::Organization::new(
+        silo.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org2")),
+    );
+    let proj1 = authz::Project::new(
+        org1.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1-proj1")),
+    );
+    let proj2 = authz::Project::new(
+        org1.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1-proj2")),
+    );
+    let proj3 = authz::Project::new(
+        org2.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org2-proj3")),
+    );
+    let instance1 = authz::Instance::new(
+        proj1.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1-proj1-instance1")),
+    );
+    let instance2 = authz::Instance::new(
+        proj2.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1-proj2-instance2")),
+    );
+    let instance3 = authz::Instance::new(
+        proj3.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org2-proj3-instance3")),
+    );
+
+    // The user under test administers "org1" and can only view "org2-proj3".
+    let user_id = Uuid::new_v4();
+    datastore
+        .silo_user_create(
+            &silo,
+            db::model::SiloUser::new(silo_id, user_id, String::from("user")),
+        )
+        .await
+        .unwrap();
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &org1,
+            &[shared::RoleAssignment {
+                identity_type: shared::IdentityType::SiloUser,
+                identity_id: user_id,
+                role_name: authz::OrganizationRole::Admin,
+            }],
+        )
+        .await
+        .unwrap();
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &proj3,
+            &[shared::RoleAssignment {
+                identity_type: shared::IdentityType::SiloUser,
+                identity_id: user_id,
+                role_name: authz::ProjectRole::Viewer,
+            }],
+        )
+        .await
+        .unwrap();
+
+    // For each resource, record which Organization and Project (if any) it's
+    // in so that we can tell whether it's within a token's scope.
+    type ScopedResource = (Arc<dyn DynAuthorizedResource>, Uuid, Option<Uuid>);
+    let resources: Vec<ScopedResource> = vec![
+        (Arc::new(org1.clone()), org1.id(), None),
+        (Arc::new(org2.clone()), org2.id(), None),
+        (Arc::new(proj1.clone()), org1.id(), Some(proj1.id())),
+        (Arc::new(proj2.clone()), org1.id(), Some(proj2.id())),
+        (Arc::new(proj3.clone()), org2.id(), Some(proj3.id())),
+        (Arc::new(instance1.clone()), org1.id(), Some(proj1.id())),
+        (Arc::new(instance2.clone()), org1.id(), Some(proj2.id())),
+        (Arc::new(instance3.clone()), org2.id(), Some(proj3.id())),
+    ];
+
+    let all_actions = vec![
+        shared::TokenScopeAction::Read,
+        shared::TokenScopeAction::ListChildren,
+        shared::TokenScopeAction::Modify,
+        shared::TokenScopeAction::CreateChild,
+        shared::TokenScopeAction::Operate,
+    ];
+    let scopes = vec![
+        shared::TokenScope {
+            actions: vec![shared::TokenScopeAction::Read],
+            organization_id: None,
+            project_id: None,
+        },
+        shared::TokenScope {
+            actions: all_actions.clone(),
+            organization_id: Some(org1.id()),
+            project_id: Some(proj1.id()),
+        },
+        shared::TokenScope {
+            actions: all_actions.clone(),
+            organization_id: Some(org2.id()),
+            project_id: Some(proj3.id()),
+        },
+        shared::TokenScope {
+            actions: vec![
+                shared::TokenScopeAction::Read,
+                shared::TokenScopeAction::ListChildren,
+            ],
+            organization_id: Some(org1.id()),
+            project_id: None,
+        },
+    ];
+
+    let authz = Arc::new(authz::Authz::new(&logctx.log));
+    let unscoped_opctx = OpContext::for_background(
+        logctx.log.new(o!()),
+        Arc::clone(&authz),
+        authn::Context::for_test_user(user_id, silo_id),
+        Arc::clone(&datastore),
+    );
+    for scope in &scopes {
+        let scoped_opctx = OpContext::for_background(
+            logctx.log.new(o!()),
+            Arc::clone(&authz),
+            authn::Context::for_test_user_with_scope(
+                user_id,
+                silo_id,
+                scope.clone(),
+            ),
+            Arc::clone(&datastore),
+        );
+        for (resource, organization_id, project_id) in &resources {
+            let in_scope = match (scope.organization_id, scope.project_id) {
+                (None, _) => true,
+                (Some(o), None) => o == *organization_id,
+                (_, Some(p)) => Some(p) == *project_id,
+            };
+            for action in authz::Action::iter() {
+                let scoped =
+                    resource.do_authorize(&scoped_opctx, action).await.is_ok();
+                if !scoped {
+                    continue;
+                }
+                let unscoped = resource
+                    .do_authorize(&unscoped_opctx, action)
+                    .await
+                    .is_ok();
+                assert!(
+                    unscoped,
+                    "token with scope {:?} exceeded its user's roles \
+                    ({:?} on {})",
+                    scope,
+                    action,
+                    resource.resource_name()
+                );
+                assert!(
+                    in_scope,
+                    "token with scope {:?} used outside its resource \
+                    ({:?} on {})",
+                    scope,
+                    action,
+                    resource.resource_name()
+                );
+                let perm = authz::oso_generic::Perm::from(&action);
+                assert!(
+                    scope
+                        .actions
+                        .iter()
+                        .any(|a| { authz::oso_generic::Perm::from(a) == perm }),
+                    "token with scope {:?} used for an action outside its \
+                    scope ({:?} on {})",
+                    scope,
+                    action,
+                    resource.resource_name()
+                );
+            }
+        }
+    }
+
+    // Spot-check that the scopes still allow what they should.
+    let make_opctx = |scope: &shared::TokenScope| {
+        OpContext::for_background(
+            logctx.log.new(o!()),
+            Arc::clone(&authz),
+            authn::Context::for_test_user_with_scope(
+                user_id,
+                silo_id,
+                scope.clone(),
+            ),
+            Arc::clone(&datastore),
+        )
+    };
+    let read_only = make_opctx(&scopes[0]);
+    let proj1_only = make_opctx(&scopes[1]);
+    let proj3_only = make_opctx(&scopes[2]);
+    let org1_read = make_opctx(&scopes[3]);
+
+    read_only.authorize(authz::Action::Read, &instance3).await.unwrap();
+    read_only
+        .authorize(authz::Action::Modify, &instance1)
+        .await
+        .expect_err("read-only token modified an instance");
+
+    proj1_only.authorize(authz::Action::Operate, &instance1).await.unwrap();
+    proj1_only.authorize(authz::Action::Delete, &instance1).await.unwrap();
+    proj1_only
+        .authorize(authz::Action::Operate, &instance2)
+        .await
+        .expect_err("project-scoped token used outside its project");
+    proj1_only
+        .authorize(authz::Action::Modify, &org1)
+        .await
+        .expect_err("project-scoped token used on its organization");
+
+    // The token's scope grants nothing that the user's roles don't.
+    proj3_only.authorize(authz::Action::Read, &instance3).await.unwrap();
+    proj3_only
+        .authorize(authz::Action::Modify, &instance3)
+        .await
+        .expect_err("scoped token exceeded its user's roles");
+
+    org1_read.authorize(authz::Action::Read, &instance2).await.unwrap();
+    org1_read.authorize(authz::Action::ListChildren, &proj2).await.unwrap();
+    org1_read
+        .authorize(authz::Action::CreateChild, &proj2)
+        .await
+        .expect_err("read-only token created a child");
+    org1_read
+        .authorize(authz::Action::Read, &instance3)
+        .await
+        .expect_err("organization-scoped token used outside its organization");
+
+    db.cleanup().await.unwrap();
+    logctx.cleanup_successful();
+}
+
+/// Verifies who may move a Project between Organizations and that roles are
+/// inherited from the new Organization afterwards
+///
+/// Moving a Project requires "modify" on the Project and on its current
+/// Organization, and "create_child" on the destination Organization (see
+/// `DataStore::project_move()`).  Since a
+/// Project's roles are inherited through its `authz::Project`, which is built
+/// from the Project's current parent, a moved Project gets its inherited roles
+/// from its new Organization with nothing else to update.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_project_move() {
+    let logctx = dev::test_setup_log("test_project_move");
+    let mut db = test_setup_database(&logctx.log).await;
+    let (opctx, datastore) = db::datastore::datastore_test(&logctx, &db).await;
+
+    let silo_id = Uuid::new_v4();
+    let silo =
+        authz::Silo::new(authz::FLEET, silo_id, LookupType::ById(silo_id));
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &silo,
+            &[shared::RoleAssignment {
+                identity_type: shared::IdentityType::SiloUser,
+                identity_id: USER_TEST_PRIVILEGED.id(),
+                role_name: SiloRole::Admin,
+            }],
+        )
+        .await
+        .unwrap();
+
+    let org1 = authz::Organization::new(
+        silo.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org1")),
+    );
+    let org2 = authz::Organization::new(
+        silo.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org2")),
+    );
+    let project_id = Uuid::new_v4();
+    let project = authz::Project::new(
+        org1.clone(),
+        project_id,
+        LookupType::ByName(String::from("org1-proj")),
+    );
+
+    // Create the users under test.
+    let authz = Arc::new(authz::Authz::new(&logctx.log));
+    let mut users = BTreeMap::new();
+    for username in [
+        "org1-collaborator",
+        "org2-collaborator",
+        "both-collaborator",
+        "project-admin",
+        "project-admin-org2-collaborator",
+        "org1-admin-org2-collaborator",
+    ] {
+        let user_id = Uuid::new_v4();
+        datastore
+            .silo_user_create(
+                &silo,
+                db::model::SiloUser::new(
+                    silo_id,
+                    user_id,
+                    String::from(username),
+                ),
+            )
+            .await
+            .unwrap();
+        let user_opctx = OpContext::for_background(
+            logctx.log.new(o!("username" => username)),
+            Arc::clone(&authz),
+            authn::Context::for_test_user(user_id, silo_id),
+            Arc::clone(&datastore),
+        );
+        users.insert(username, (user_id, user_opctx));
+    }
+    fn assignment<R>(user_id: Uuid, role_name: R) -> shared::RoleAssignment<R> {
+        shared::RoleAssignment {
+            identity_type: shared::IdentityType::SiloUser,
+            identity_id: user_id,
+            role_name,
+        }
+    }
+
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &org1,
+            &[
+                assignment(
+                    users["org1-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+                assignment(
+                    users["both-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+                assignment(
+                    users["org1-admin-org2-collaborator"].0,
+                    authz::OrganizationRole::Admin,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &org2,
+            &[
+                assignment(
+                    users["org2-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+                assignment(
+                    users["both-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+                assignment(
+                    users["project-admin-org2-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+                assignment(
+                    users["org1-admin-org2-collaborator"].0,
+                    authz::OrganizationRole::Collaborator,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+    datastore
+        .role_assignment_replace_visible(
+            &opctx,
+            &project,
+            &[
+                assignment(users["project-admin"].0, authz::ProjectRole::Admin),
+                assignment(
+                    users["project-admin-org2-collaborator"].0,
+                    authz::ProjectRole::Admin,
+                ),
+            ],
+        )
+        .await
+        .unwrap();
+
+    // Only users that can modify both the Project and its Organization, and
+    // create Projects in the destination Organization, may move the Project
+    // there.  In particular, a Project's admin can't take it away from its
+    // Organization's admins.
+    for (username, expected) in [
+        ("org1-collaborator", false),
+        ("org2-collaborator", false),
+        ("both-collaborator", false),
+        ("project-admin", false),
+        ("project-admin-org2-collaborator", false),
+        ("org1-admin-org2-collaborator", true),
+    ] {
+        let user_opctx = &users[username].1;
+        let can_move = user_opctx
+            .authorize(authz::Action::Modify, &project)
+            .await
+            .is_ok()
+            && user_opctx.authorize(authz::Action::Modify, &org1).await.is_ok()
+            && user_opctx
+                .authorize(authz::Action::CreateChild, &org2)
+                .await
+                .is_ok();
+        assert_eq!(
+            can_move, expected,
+            "user {:?}: unexpected result for moving the project",
+            username
+        );
+    }
+
+    // Once moved, the Project (and everything in it) inherits roles from its
+    // new Organization and no longer from its old one.  Roles assigned on the
+    // Project itself move with it.
+    let moved_project = authz::Project::new(
+        org2.clone(),
+        project_id,
+        LookupType::ByName(String::from("org2-proj")),
+    );
+    let moved_instance = authz::Instance::new(
+        moved_project.clone(),
+        Uuid::new_v4(),
+        LookupType::ByName(String::from("org2-proj-instance")),
+    );
+    for (username, can_read, can_modify) in [
+        ("org1-collaborator", false, false),
+        ("org2-collaborator", true, true),
+        ("both-collaborator", true, true),
+        ("project-admin", true, true),
+    ] {
+        let user_opctx = &users[username].1;
+        for (action, expected) in [
+            (authz::Action::Read, can_read),
+            (authz::Action::Modify, can_modify),
+        ] {
+            let allowed =
+                user_opctx.authorize(action, &moved_project).await.is_ok();
+            assert_eq!(
+                allowed, expected,
+                "user {:?}: unexpected result for {:?} on moved project",
+                username, action
+            );
+            let allowed =
+                user_opctx.authorize(action, &moved_instance).await.is_ok();
+            assert_eq!(
+                allowed, expected,
+                "user {:?}: unexpected result for {:?} on moved instance",
+                username, action
+            );
+        }
+    }
+
+    db.cleanup().await.unwrap();
+    logctx.cleanup_successful();
+}
+
 /// Now that we've set up the resource hierarchy and users with associated
 /// roles, exhaustively attempt to authorize every action for every resource by
 /// every user and write a human-readable summary to `out`
@@ -217,7 +714,7 @@ async fn authorize_one_resource(
         let mut out = Cursor::new(&mut buffer);
         write!(out, "resource: {}\n\n", resource.resource_name())?;
 
-        write!(out, "  {:31}", "USER")?;
+        write!(out, "  {:37}", "USER")?;
         for action in authz::Action::iter() {
             write!(out, " {:>2}", action_abbreviation(action))?;
         }
@@ -225,7 +722,7 @@ async fn authorize_one_resource(
 
         for ctx_tuple in user_contexts.iter() {
             let (ref username, ref opctx) = **ctx_tuple;
-            write!(out, "  {:31}", &username)?;
+            write!(out, "  {:37}", &username)?;
             for action in authz::Action::iter() {
                 let result = resource.do_authorize(opctx, action).await;
                 trace!(
@@ -269,6 +766,7 @@ fn action_abbreviation(action: authz::Action) -> &'static str {
         authz::Action::ModifyPolicy => "MP",
         authz::Action::CreateChild => "CC",
         authz::Action::Delete => "D",
+        authz::Action::Operate => "OP",
     }
 }
 
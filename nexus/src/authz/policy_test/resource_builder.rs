@@ -280,3 +280,20 @@ impl DynAuthorizedResource for authz::SiloUserList {
         format!("{}: user list", self.silo().resource_name())
     }
 }
+
+impl DynAuthorizedResource for authz::SiloGroupList {
+    fn do_authorize<'a, 'b>(
+        &'a self,
+        opctx: &'b OpContext,
+        action: authz::Action,
+    ) -> BoxFuture<'a, Result<(), Error>>
+    where
+        'b: 'a,
+    {
+        opctx.authorize(action, self).boxed()
+    }
+
+    fn resource_name(&self) -> String {
+        format!("{}: group list", self.silo().resource_name())
+    }
+}
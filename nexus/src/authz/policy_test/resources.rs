@@ -111,6 +111,7 @@ async fn make_silo(
 
     builder.new_resource(authz::SiloIdentityProviderList::new(silo.clone()));
     builder.new_resource(authz::SiloUserList::new(silo.clone()));
+    builder.new_resource(authz::SiloGroupList::new(silo.clone()));
 
     let norganizations = if first_branch { 2 } else { 1 };
     for i in 0..norganizations {
@@ -186,18 +187,30 @@ async fn make_project(
     );
 
     let disk_name = format!("{}-disk1", project_name);
-    builder.new_resource(authz::Disk::new(
+    let disk = authz::Disk::new(
         project.clone(),
         Uuid::new_v4(),
         LookupType::ByName(disk_name.clone()),
-    ));
-    builder.new_resource(instance.clone());
+    );
+
+    // Disks, Instances, and VPCs support roles of their own.
+    if first_branch {
+        builder.new_resource_with_users(disk).await;
+        builder.new_resource_with_users(instance.clone()).await;
+    } else {
+        builder.new_resource(disk);
+        builder.new_resource(instance.clone());
+    }
     builder.new_resource(authz::NetworkInterface::new(
         instance,
         Uuid::new_v4(),
         LookupType::ByName(format!("{}-nic1", instance_name)),
     ));
-    builder.new_resource(vpc1.clone());
+    if first_branch {
+        builder.new_resource_with_users(vpc1.clone()).await;
+    } else {
+        builder.new_resource(vpc1.clone());
+    }
     // Test a resource nested two levels below Project
     builder.new_resource(authz::VpcSubnet::new(
         vpc1,
@@ -237,6 +250,7 @@ pub fn exempted_authz_classes() -> BTreeSet<String> {
         authz::Action::get_polar_class(),
         authz::actor::AnyActor::get_polar_class(),
         authz::actor::AuthenticatedActor::get_polar_class(),
+        authz::actor::TokenScope::get_polar_class(),
         // Resources whose behavior should be identical to an existing type
         // and we don't want to do the test twice for performance reasons:
         // none yet.
@@ -256,8 +270,10 @@ pub fn exempted_authz_classes() -> BTreeSet<String> {
         authz::SshKey::get_polar_class(),
         authz::SiloUser::get_polar_class(),
         authz::SiloGroup::get_polar_class(),
+        authz::ServiceAccount::get_polar_class(),
         authz::IdentityProvider::get_polar_class(),
         authz::SamlIdentityProvider::get_polar_class(),
+        authz::OidcIdentityProvider::get_polar_class(),
         authz::UpdateAvailableArtifact::get_polar_class(),
         authz::UserBuiltin::get_polar_class(),
         authz::GlobalImage::get_polar_class(),
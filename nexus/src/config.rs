@@ -14,6 +14,7 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_with::DeserializeFromStr;
 use serde_with::SerializeDisplay;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
@@ -24,6 +25,9 @@ use std::path::{Path, PathBuf};
 pub struct AuthnConfig {
     /// allowed authentication schemes for external HTTP server
     pub schemes_external: Vec<SchemeName>,
+    /// how long an access token granted through a device authorization flow
+    /// remains valid
+    pub device_token_ttl_minutes: u32,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -45,6 +49,21 @@ pub struct UpdatesConfig {
     pub default_base_url: String,
 }
 
+/// Configuration for the external DNS zone, under which Nexus publishes the
+/// names of silos and, optionally, instances.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ExternalDnsConfig {
+    /// DNS name the operator has delegated to the external DNS servers
+    pub zone: String,
+    /// Addresses of the external DNS servers' HTTP APIs, through which Nexus
+    /// replaces the contents of the zone
+    pub servers: Vec<SocketAddr>,
+    /// Addresses of the external API, which each silo's name resolves to
+    pub api_addresses: Vec<IpAddr>,
+    /// Whether to also publish names for instances with external IPs
+    pub instance_records: bool,
+}
+
 /// Optional configuration for the timeseries database.
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct TimeseriesDbConfig {
@@ -141,6 +160,10 @@ pub struct PackageConfig {
     /// unconfigured.
     #[serde(default)]
     pub updates: Option<UpdatesConfig>,
+    /// External DNS configuration. Nexus doesn't publish any external names
+    /// when this is unconfigured.
+    #[serde(default)]
+    pub external_dns: Option<ExternalDnsConfig>,
     /// Tunable configuration for testing and experimentation
     #[serde(default)]
     pub tunables: Tunables,
@@ -212,8 +235,8 @@ impl std::fmt::Display for SchemeName {
 mod test {
     use super::Tunables;
     use super::{
-        AuthnConfig, Config, ConsoleConfig, LoadError, PackageConfig,
-        SchemeName, TimeseriesDbConfig, UpdatesConfig,
+        AuthnConfig, Config, ConsoleConfig, ExternalDnsConfig, LoadError,
+        PackageConfig, SchemeName, TimeseriesDbConfig, UpdatesConfig,
     };
     use dropshot::ConfigDropshot;
     use dropshot::ConfigLogging;
@@ -321,6 +344,7 @@ mod test {
             session_absolute_timeout_minutes = 480
             [authn]
             schemes_external = []
+            device_token_ttl_minutes = 43200
             [log]
             mode = "file"
             level = "debug"
@@ -331,6 +355,11 @@ mod test {
             [updates]
             trusted_root = "/path/to/root.json"
             default_base_url = "http://example.invalid/"
+            [external_dns]
+            zone = "oxide.example"
+            servers = [ "10.1.2.3:5353" ]
+            api_addresses = [ "10.1.2.3" ]
+            instance_records = true
             [tunables]
             max_vpc_ipv4_subnet_prefix = 27
             [deployment]
@@ -380,7 +409,10 @@ mod test {
                         session_idle_timeout_minutes: 60,
                         session_absolute_timeout_minutes: 480
                     },
-                    authn: AuthnConfig { schemes_external: Vec::new() },
+                    authn: AuthnConfig {
+                        schemes_external: Vec::new(),
+                        device_token_ttl_minutes: 43200,
+                    },
                     log: ConfigLogging::File {
                         level: ConfigLoggingLevel::Debug,
                         if_exists: ConfigLoggingIfExists::Fail,
@@ -393,6 +425,12 @@ mod test {
                         trusted_root: PathBuf::from("/path/to/root.json"),
                         default_base_url: "http://example.invalid/".into(),
                     }),
+                    external_dns: Some(ExternalDnsConfig {
+                        zone: "oxide.example".into(),
+                        servers: vec!["10.1.2.3:5353".parse().unwrap()],
+                        api_addresses: vec!["10.1.2.3".parse().unwrap()],
+                        instance_records: true,
+                    }),
                     tunables: Tunables { max_vpc_ipv4_subnet_prefix: 27 },
                 },
             }
@@ -408,6 +446,7 @@ mod test {
             session_absolute_timeout_minutes = 480
             [authn]
             schemes_external = [ "spoof", "session_cookie" ]
+            device_token_ttl_minutes = 43200
             [log]
             mode = "file"
             level = "debug"
@@ -450,6 +489,7 @@ mod test {
             session_absolute_timeout_minutes = 480
             [authn]
             schemes_external = ["trust-me"]
+            device_token_ttl_minutes = 43200
             [log]
             mode = "file"
             level = "debug"
@@ -501,6 +541,7 @@ mod test {
             session_absolute_timeout_minutes = 480
             [authn]
             schemes_external = []
+            device_token_ttl_minutes = 43200
             [log]
             mode = "file"
             level = "debug"
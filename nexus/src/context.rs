@@ -12,6 +12,7 @@ use crate::authn::external::session_cookie::{Session, SessionStore};
 use crate::authn::ConsoleSessionWithSiloId;
 use crate::authz::AuthorizedResource;
 use crate::db::DataStore;
+use crate::external_api::shared;
 use crate::saga_interface::SagaContext;
 use async_trait::async_trait;
 use authn::external::session_cookie::HttpAuthnSessionCookie;
@@ -20,7 +21,7 @@ use authn::external::token::HttpAuthnToken;
 use authn::external::HttpAuthnScheme;
 use chrono::{DateTime, Duration, Utc};
 use internal_dns_client::names::{ServiceName, SRV};
-use omicron_common::address::{Ipv6Subnet, AZ_PREFIX, COCKROACH_PORT};
+use omicron_common::address::{Ipv6Subnet, AZ_PREFIX};
 use omicron_common::api::external::Error;
 use omicron_common::nexus_config;
 use omicron_common::postgres_config::PostgresConfigWithUrl;
@@ -156,13 +157,13 @@ impl ServerContext {
             nexus_config::Database::FromDns => {
                 info!(log, "Accessing DB url from DNS");
                 let address = resolver
-                    .lookup_ipv6(SRV::Service(ServiceName::Cockroach))
+                    .lookup_socket_v6(SRV::Service(ServiceName::Cockroach))
                     .await
-                    .map_err(|e| format!("Failed to lookup IP: {}", e))?;
+                    .map_err(|e| format!("Failed to lookup address: {}", e))?;
                 info!(log, "DB address: {}", address);
                 PostgresConfigWithUrl::from_str(&format!(
-                    "postgresql://root@[{}]:{}/omicron?sslmode=disable",
-                    address, COCKROACH_PORT
+                    "postgresql://root@{}/omicron?sslmode=disable",
+                    address
                 ))
                 .map_err(|e| format!("Cannot parse Postgres URL: {}", e))?
             }
@@ -549,10 +550,18 @@ impl authn::external::token::TokenContext for ServerContext {
     async fn token_actor(
         &self,
         token: String,
-    ) -> Result<authn::Actor, authn::Reason> {
+    ) -> Result<(authn::Actor, Option<shared::TokenScope>), authn::Reason> {
         let opctx = self.nexus.opctx_external_authn();
         self.nexus.device_access_token_actor(opctx, token).await
     }
+
+    async fn service_account_key_actor(
+        &self,
+        key: String,
+    ) -> Result<authn::Actor, authn::Reason> {
+        let opctx = self.nexus.opctx_external_authn();
+        self.nexus.service_account_key_actor(opctx, key).await
+    }
 }
 
 #[async_trait]
@@ -572,11 +581,6 @@ impl SessionStore for ServerContext {
         self.nexus.session_update_last_used(&opctx, &token).await.ok()
     }
 
-    async fn session_expire(&self, token: String) -> Option<()> {
-        let opctx = self.nexus.opctx_external_authn();
-        self.nexus.session_hard_delete(opctx, &token).await.ok()
-    }
-
     fn session_idle_timeout(&self) -> Duration {
         self.console_config.session_idle_timeout
     }
@@ -9,16 +9,25 @@ use crate::authn;
 use crate::authz;
 use crate::context::OpContext;
 use crate::db;
+use crate::db::error::public_error_from_diesel_pool;
+use crate::db::error::ErrorHandler;
 use crate::db::lookup::LookupPath;
 use crate::db::model::ConsoleSession;
+use crate::db::pagination::paginated;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::DateTime;
 use chrono::Utc;
 use diesel::prelude::*;
 use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::ResourceType;
 use omicron_common::api::external::UpdateResult;
+use uuid::Uuid;
 
 impl DataStore {
     // TODO-correctness: fix session method errors. the map_errs turn all errors
@@ -140,4 +149,103 @@ impl DataStore {
                 ))
             })
     }
+
+    /// List a user's unexpired console sessions.
+    ///
+    /// Sessions last used before `idle_cutoff` or created before
+    /// `absolute_cutoff` have expired.
+    pub async fn sessions_list(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+        idle_cutoff: DateTime<Utc>,
+        absolute_cutoff: DateTime<Utc>,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<ConsoleSession> {
+        opctx.authorize(authz::Action::ListChildren, authz_user).await?;
+
+        use db::schema::console_session::dsl;
+        paginated(dsl::console_session, dsl::id, pagparams)
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .filter(dsl::time_last_used.ge(idle_cutoff))
+            .filter(dsl::time_created.ge(absolute_cutoff))
+            .select(ConsoleSession::as_select())
+            .load_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Revoke one of a user's console sessions, logging it out.
+    pub async fn session_revoke(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+        session_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_user).await?;
+
+        use db::schema::console_session::dsl;
+        let deleted = diesel::delete(dsl::console_session)
+            .filter(dsl::id.eq(session_id))
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        if deleted == 0 {
+            return Err(Error::ObjectNotFound {
+                type_name: ResourceType::ConsoleSession,
+                lookup_type: LookupType::ById(session_id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Revoke all of a user's console sessions, except for the one with
+    /// token `except_token`, if given.
+    pub async fn sessions_revoke_all(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+        except_token: Option<&str>,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_user).await?;
+
+        use db::schema::console_session::dsl;
+        let mut query = diesel::delete(dsl::console_session)
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .into_boxed();
+        if let Some(token) = except_token {
+            query = query.filter(dsl::token.ne(token.to_string()));
+        }
+        query.execute_async(self.pool_authorized(opctx).await?).await.map_err(
+            |e| public_error_from_diesel_pool(e, ErrorHandler::Server),
+        )?;
+        Ok(())
+    }
+
+    /// Delete every session that was last used before `idle_cutoff` or
+    /// created before `absolute_cutoff`, returning how many were deleted.
+    pub async fn sessions_delete_expired(
+        &self,
+        opctx: &OpContext,
+        idle_cutoff: DateTime<Utc>,
+        absolute_cutoff: DateTime<Utc>,
+    ) -> Result<usize, Error> {
+        opctx
+            .authorize(authz::Action::Modify, &authz::CONSOLE_SESSION_LIST)
+            .await?;
+
+        use db::schema::console_session::dsl;
+        diesel::delete(dsl::console_session)
+            .filter(
+                dsl::time_last_used
+                    .lt(idle_cutoff)
+                    .or(dsl::time_created.lt(absolute_cutoff)),
+            )
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
 }
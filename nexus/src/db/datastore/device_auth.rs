@@ -13,11 +13,16 @@ use crate::db::error::ErrorHandler;
 use crate::db::error::TransactionError;
 use crate::db::model::DeviceAccessToken;
 use crate::db::model::DeviceAuthRequest;
+use crate::db::pagination::paginated;
 use async_bb8_diesel::AsyncConnection;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
 use diesel::prelude::*;
 use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
@@ -141,4 +146,80 @@ impl DataStore {
                 )
             })
     }
+
+    /// List the unexpired, unrevoked access tokens granted to a user.
+    pub async fn device_access_tokens_list(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<DeviceAccessToken> {
+        opctx.authorize(authz::Action::ListChildren, authz_user).await?;
+
+        use db::schema::device_access_token::dsl;
+        paginated(dsl::device_access_token, dsl::id, pagparams)
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .filter(dsl::time_revoked.is_null())
+            .filter(
+                dsl::time_expires
+                    .is_null()
+                    .or(dsl::time_expires.gt(Utc::now())),
+            )
+            .select(DeviceAccessToken::as_select())
+            .load_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Revoke one of the access tokens granted to a user.  Revoked tokens
+    /// are kept, so that using one fails differently than using a token that
+    /// never existed.
+    pub async fn device_access_token_revoke(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+        token_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_user).await?;
+
+        use db::schema::device_access_token::dsl;
+        let updated = diesel::update(dsl::device_access_token)
+            .filter(dsl::id.eq(token_id))
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .filter(dsl::time_revoked.is_null())
+            .set(dsl::time_revoked.eq(Utc::now()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        if updated == 0 {
+            return Err(Error::ObjectNotFound {
+                type_name: ResourceType::DeviceAccessToken,
+                lookup_type: LookupType::ById(token_id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Revoke all of the access tokens granted to a user.
+    pub async fn device_access_tokens_revoke_all(
+        &self,
+        opctx: &OpContext,
+        authz_user: &authz::SiloUser,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_user).await?;
+
+        use db::schema::device_access_token::dsl;
+        diesel::update(dsl::device_access_token)
+            .filter(dsl::silo_user_id.eq(authz_user.id()))
+            .filter(dsl::time_revoked.is_null())
+            .set(dsl::time_revoked.eq(Utc::now()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        Ok(())
+    }
 }
@@ -15,27 +15,37 @@ use crate::db::collection_detach::DatastoreDetachTarget;
 use crate::db::collection_detach::DetachError;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
+use crate::db::error::TransactionError;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Disk;
 use crate::db::model::DiskRuntimeState;
 use crate::db::model::Instance;
+use crate::db::model::Labels;
 use crate::db::model::Name;
+use crate::db::model::Volume;
 use crate::db::pagination::paginated;
 use crate::db::update_and_check::UpdateAndCheck;
 use crate::db::update_and_check::UpdateStatus;
+use async_bb8_diesel::AsyncConnection;
 use async_bb8_diesel::AsyncRunQueryDsl;
 use chrono::Utc;
 use diesel::prelude::*;
+use diesel::OptionalExtension as DieselOptionalExtension;
 use omicron_common::api;
+use omicron_common::api::external::ByteCount;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
 use omicron_common::bail_unless;
+use sled_agent_client::types::VolumeConstructionRequest;
 use uuid::Uuid;
 
 impl DataStore {
@@ -97,13 +107,18 @@ impl DataStore {
         opctx: &OpContext,
         authz_project: &authz::Project,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Disk> {
         opctx.authorize(authz::Action::ListChildren, authz_project).await?;
 
         use db::schema::disk::dsl;
-        paginated(dsl::disk, dsl::name, &pagparams)
+        let mut query = paginated(dsl::disk, dsl::name, &pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::project_id.eq(authz_project.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Disk::as_select())
             .load_async::<Disk>(self.pool_authorized(opctx).await?)
             .await
@@ -416,6 +431,293 @@ impl DataStore {
         Ok(updated)
     }
 
+    /// Replaces the labels on a Disk
+    pub async fn disk_update_labels(
+        &self,
+        opctx: &OpContext,
+        authz_disk: &authz::Disk,
+        labels: Labels,
+    ) -> UpdateResult<Disk> {
+        opctx.authorize(authz::Action::Modify, authz_disk).await?;
+
+        use db::schema::disk::dsl;
+        diesel::update(dsl::disk)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::id.eq(authz_disk.id()))
+            .set((dsl::labels.eq(labels), dsl::time_modified.eq(Utc::now())))
+            .returning(Disk::as_returning())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::NotFoundByResource(authz_disk),
+                )
+            })
+    }
+
+    /// Grows a disk from `old_size` to `new_size`
+    ///
+    /// If the disk's existing regions can't hold `new_size` bytes, the caller
+    /// allocates more regions under `extension_id` and passes the
+    /// corresponding sub-volume as `extension`.  In the same transaction as
+    /// the size change, that sub-volume is appended to the disk's volume and
+    /// the regions become part of that volume.
+    ///
+    /// As this is part of a saga, it's idempotent: if the disk has already
+    /// been grown this way, this does nothing.
+    pub async fn disk_resize(
+        &self,
+        opctx: &OpContext,
+        authz_disk: &authz::Disk,
+        old_size: ByteCount,
+        new_size: ByteCount,
+        extension_id: Uuid,
+        extension: Option<VolumeConstructionRequest>,
+    ) -> Result<(), Error> {
+        opctx.authorize(authz::Action::Modify, authz_disk).await?;
+
+        #[derive(Debug, thiserror::Error)]
+        enum DiskResizeError {
+            #[error("{0}")]
+            Public(Error),
+
+            #[error("Serde error resizing disk: {0}")]
+            SerdeError(#[from] serde_json::Error),
+        }
+        type TxnError = TransactionError<DiskResizeError>;
+
+        let authz_disk = authz_disk.clone();
+        self.pool_authorized(opctx)
+            .await?
+            .transaction(move |conn| {
+                use db::schema::disk::dsl as disk_dsl;
+                use db::schema::region::dsl as region_dsl;
+                use db::schema::volume::dsl as volume_dsl;
+
+                let disk = disk_dsl::disk
+                    .filter(disk_dsl::id.eq(authz_disk.id()))
+                    .filter(disk_dsl::time_deleted.is_null())
+                    .select(Disk::as_select())
+                    .get_result(conn)
+                    .optional()?
+                    .ok_or_else(|| {
+                        TxnError::CustomError(DiskResizeError::Public(
+                            authz_disk.not_found(),
+                        ))
+                    })?;
+                let volume = volume_dsl::volume
+                    .filter(volume_dsl::id.eq(disk.volume_id))
+                    .select(Volume::as_select())
+                    .get_result(conn)?;
+                let vcr: VolumeConstructionRequest =
+                    serde_json::from_str(volume.data()).map_err(|e| {
+                        TxnError::CustomError(DiskResizeError::SerdeError(e))
+                    })?;
+                let extended = sub_volume_ids(&vcr).contains(&extension_id);
+
+                if disk.size.to_bytes() == new_size.to_bytes()
+                    && (extension.is_none() || extended)
+                {
+                    // This resize has already been done.
+                    return Ok(());
+                }
+                if disk.size.to_bytes() != old_size.to_bytes() {
+                    return Err(TxnError::CustomError(
+                        DiskResizeError::Public(Error::invalid_request(
+                            "disk was resized concurrently with this request",
+                        )),
+                    ));
+                }
+                match disk.state().state() {
+                    api::external::DiskState::Detached
+                    | api::external::DiskState::Attached(_) => (),
+                    _ => {
+                        return Err(TxnError::CustomError(
+                            DiskResizeError::Public(Error::invalid_request(
+                                &format!(
+                                    "disk cannot be resized in state \"{}\"",
+                                    disk.runtime_state.disk_state
+                                ),
+                            )),
+                        ));
+                    }
+                }
+
+                diesel::update(disk_dsl::disk)
+                    .filter(disk_dsl::id.eq(disk.id()))
+                    .set((
+                        disk_dsl::size_bytes
+                            .eq(db::model::ByteCount::from(new_size)),
+                        disk_dsl::time_modified.eq(Utc::now()),
+                    ))
+                    .execute(conn)?;
+
+                if let Some(extension) = extension {
+                    diesel::update(region_dsl::region)
+                        .filter(region_dsl::volume_id.eq(extension_id))
+                        .set(region_dsl::volume_id.eq(disk.volume_id))
+                        .execute(conn)?;
+
+                    let new_vcr = match vcr {
+                        VolumeConstructionRequest::Volume {
+                            id,
+                            block_size,
+                            mut sub_volumes,
+                            read_only_parent,
+                        } => {
+                            sub_volumes.push(extension);
+                            VolumeConstructionRequest::Volume {
+                                id,
+                                block_size,
+                                sub_volumes,
+                                read_only_parent,
+                            }
+                        }
+                        _ => {
+                            return Err(TxnError::CustomError(
+                                DiskResizeError::Public(Error::internal_error(
+                                    "disk volume is not a Volume",
+                                )),
+                            ));
+                        }
+                    };
+                    let new_volume_data = serde_json::to_string(&new_vcr)
+                        .map_err(|e| {
+                            TxnError::CustomError(DiskResizeError::SerdeError(
+                                e,
+                            ))
+                        })?;
+                    diesel::update(volume_dsl::volume)
+                        .filter(volume_dsl::id.eq(disk.volume_id))
+                        .set(volume_dsl::data.eq(new_volume_data))
+                        .execute(conn)?;
+                }
+
+                Ok(())
+            })
+            .await
+            .map_err(|e| match e {
+                TxnError::CustomError(DiskResizeError::Public(e)) => e,
+                TxnError::CustomError(DiskResizeError::SerdeError(e)) => {
+                    Error::internal_error(&e.to_string())
+                }
+                TxnError::Pool(e) => {
+                    public_error_from_diesel_pool(e, ErrorHandler::Server)
+                }
+            })
+    }
+
+    /// Reverses [`DataStore::disk_resize`], returning the disk to `old_size`
+    ///
+    /// The sub-volume added under `extension_id` (if any) is removed from the
+    /// disk's volume, and the regions in `extension_region_ids` are returned to
+    /// `extension_id` so that the caller can clean them up.
+    pub async fn disk_resize_undo(
+        &self,
+        opctx: &OpContext,
+        authz_disk: &authz::Disk,
+        old_size: ByteCount,
+        new_size: ByteCount,
+        extension_id: Uuid,
+        extension_region_ids: Vec<Uuid>,
+    ) -> Result<(), Error> {
+        opctx.authorize(authz::Action::Modify, authz_disk).await?;
+
+        #[derive(Debug, thiserror::Error)]
+        enum DiskResizeUndoError {
+            #[error("Serde error undoing disk resize: {0}")]
+            SerdeError(#[from] serde_json::Error),
+        }
+        type TxnError = TransactionError<DiskResizeUndoError>;
+
+        let disk_id = authz_disk.id();
+        self.pool_authorized(opctx)
+            .await?
+            .transaction(move |conn| {
+                use db::schema::disk::dsl as disk_dsl;
+                use db::schema::region::dsl as region_dsl;
+                use db::schema::volume::dsl as volume_dsl;
+
+                let disk = disk_dsl::disk
+                    .filter(disk_dsl::id.eq(disk_id))
+                    .select(Disk::as_select())
+                    .get_result(conn)?;
+
+                diesel::update(disk_dsl::disk)
+                    .filter(disk_dsl::id.eq(disk_id))
+                    .filter(
+                        disk_dsl::size_bytes
+                            .eq(db::model::ByteCount::from(new_size)),
+                    )
+                    .set((
+                        disk_dsl::size_bytes
+                            .eq(db::model::ByteCount::from(old_size)),
+                        disk_dsl::time_modified.eq(Utc::now()),
+                    ))
+                    .execute(conn)?;
+
+                if extension_region_ids.is_empty() {
+                    return Ok(());
+                }
+
+                diesel::update(region_dsl::region)
+                    .filter(region_dsl::id.eq_any(extension_region_ids))
+                    .set(region_dsl::volume_id.eq(extension_id))
+                    .execute(conn)?;
+
+                let volume = volume_dsl::volume
+                    .filter(volume_dsl::id.eq(disk.volume_id))
+                    .select(Volume::as_select())
+                    .get_result(conn)?;
+                let vcr: VolumeConstructionRequest =
+                    serde_json::from_str(volume.data()).map_err(|e| {
+                        TxnError::CustomError(DiskResizeUndoError::SerdeError(
+                            e,
+                        ))
+                    })?;
+                if let VolumeConstructionRequest::Volume {
+                    id,
+                    block_size,
+                    sub_volumes,
+                    read_only_parent,
+                } = vcr
+                {
+                    let sub_volumes = sub_volumes
+                        .into_iter()
+                        .filter(|v| sub_volume_ids(v) != [extension_id])
+                        .collect();
+                    let new_vcr = VolumeConstructionRequest::Volume {
+                        id,
+                        block_size,
+                        sub_volumes,
+                        read_only_parent,
+                    };
+                    let new_volume_data = serde_json::to_string(&new_vcr)
+                        .map_err(|e| {
+                            TxnError::CustomError(
+                                DiskResizeUndoError::SerdeError(e),
+                            )
+                        })?;
+                    diesel::update(volume_dsl::volume)
+                        .filter(volume_dsl::id.eq(disk.volume_id))
+                        .set(volume_dsl::data.eq(new_volume_data))
+                        .execute(conn)?;
+                }
+
+                Ok(())
+            })
+            .await
+            .map_err(|e| match e {
+                TxnError::CustomError(DiskResizeUndoError::SerdeError(e)) => {
+                    Error::internal_error(&e.to_string())
+                }
+                TxnError::Pool(e) => {
+                    public_error_from_diesel_pool(e, ErrorHandler::Server)
+                }
+            })
+    }
+
     /// Fetches information about a Disk that the caller has previously fetched
     ///
     /// The only difference between this function and a new fetch by id is that
@@ -478,6 +780,7 @@ impl DataStore {
             api::external::DiskState::Detached,
             api::external::DiskState::Faulted,
             api::external::DiskState::Creating,
+            api::external::DiskState::Importing,
         ];
 
         let ok_to_delete_state_labels: Vec<_> =
@@ -539,3 +842,18 @@ impl DataStore {
         }
     }
 }
+
+/// Returns the ids of the Crucible region sets that make up `vcr`
+///
+/// For a Region, this is the id in its options.  For a Volume, this is the
+/// ids of all of its sub-volumes (but not its read-only parent).
+fn sub_volume_ids(vcr: &VolumeConstructionRequest) -> Vec<Uuid> {
+    match vcr {
+        VolumeConstructionRequest::Volume { sub_volumes, .. } => {
+            sub_volumes.iter().flat_map(sub_volume_ids).collect()
+        }
+        VolumeConstructionRequest::Region { opts, .. } => vec![opts.id],
+        VolumeConstructionRequest::Url { .. }
+        | VolumeConstructionRequest::File { .. } => vec![],
+    }
+}
@@ -0,0 +1,161 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods related to the external DNS zone.
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use crate::db;
+use crate::db::error::public_error_from_diesel_pool;
+use crate::db::error::ErrorHandler;
+use crate::db::model::IpKind;
+use crate::db::model::Name;
+use async_bb8_diesel::{AsyncConnection, AsyncRunQueryDsl};
+use chrono::Utc;
+use diesel::prelude::*;
+use ipnetwork::IpNetwork;
+use omicron_common::api::external::Error;
+use std::net::IpAddr;
+
+/// An instance with an external IP, as published in the external DNS zone.
+#[derive(Clone, Debug)]
+pub struct ExternalDnsInstance {
+    pub silo_name: Name,
+    pub organization_name: Name,
+    pub project_name: Name,
+    pub hostname: String,
+    pub ip: IpAddr,
+}
+
+/// The data the external DNS zone is built from, as of a single point in time.
+#[derive(Clone, Debug)]
+pub struct ExternalDnsZoneData {
+    /// The generation of the zone built from this data, which is newer than
+    /// that of any data read before
+    pub generation: u64,
+    /// The names of all silos
+    pub silo_names: Vec<Name>,
+    /// The instances with inbound external IPs, if they were requested
+    pub instances: Vec<ExternalDnsInstance>,
+}
+
+impl DataStore {
+    /// Read the data from which to build the contents of the external DNS
+    /// zone `zone`, bumping the zone's generation.
+    ///
+    /// The generation is bumped in the same transaction that reads the data,
+    /// so contents built from data with a newer generation never miss changes
+    /// that were seen by contents with an older one.
+    pub async fn external_dns_zone_data(
+        &self,
+        opctx: &OpContext,
+        zone: &str,
+        include_instances: bool,
+    ) -> Result<ExternalDnsZoneData, Error> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        let now = Utc::now();
+        let generation_query = {
+            use db::schema::dns_zone_generation::dsl;
+            diesel::insert_into(dsl::dns_zone_generation)
+                .values((
+                    dsl::zone.eq(zone.to_string()),
+                    dsl::generation.eq(1),
+                    dsl::time_modified.eq(now),
+                ))
+                .on_conflict(dsl::zone)
+                .do_update()
+                .set((
+                    dsl::generation.eq(dsl::generation + 1),
+                    dsl::time_modified.eq(now),
+                ))
+                .returning(dsl::generation)
+        };
+        let silos_query = {
+            use db::schema::silo::dsl;
+            dsl::silo
+                .filter(dsl::time_deleted.is_null())
+                .select(dsl::name)
+                .order(dsl::name)
+        };
+        // Source NAT addresses are shared between instances, and can't be
+        // used to reach any one of them, so only ephemeral and floating IPs
+        // get names.
+        let instances_query = {
+            use db::schema::external_ip;
+            use db::schema::instance;
+            use db::schema::organization;
+            use db::schema::project;
+            use db::schema::silo;
+            external_ip::table
+                .inner_join(
+                    instance::table.on(instance::id
+                        .nullable()
+                        .eq(external_ip::instance_id)),
+                )
+                .inner_join(
+                    project::table.on(project::id.eq(instance::project_id)),
+                )
+                .inner_join(
+                    organization::table
+                        .on(organization::id.eq(project::organization_id)),
+                )
+                .inner_join(silo::table.on(silo::id.eq(organization::silo_id)))
+                .filter(external_ip::time_deleted.is_null())
+                .filter(external_ip::kind.ne(IpKind::SNat))
+                .filter(instance::time_deleted.is_null())
+                .select((
+                    silo::name,
+                    organization::name,
+                    project::name,
+                    instance::hostname,
+                    external_ip::ip,
+                ))
+        };
+
+        let (generation, silo_names, instances) = self
+            .pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                let generation =
+                    generation_query.get_result_async::<i64>(&conn).await?;
+                let silo_names = silos_query.load_async::<Name>(&conn).await?;
+                let instances = if include_instances {
+                    instances_query
+                        .load_async::<(Name, Name, Name, String, IpNetwork)>(
+                            &conn,
+                        )
+                        .await?
+                } else {
+                    Vec::new()
+                };
+                Ok((generation, silo_names, instances))
+            })
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+
+        let instances = instances
+            .into_iter()
+            .map(
+                |(silo_name, organization_name, project_name, hostname, ip)| {
+                    ExternalDnsInstance {
+                        silo_name,
+                        organization_name,
+                        project_name,
+                        hostname,
+                        ip: ip.ip(),
+                    }
+                },
+            )
+            .collect();
+        Ok(ExternalDnsZoneData {
+            generation: u64::try_from(generation).unwrap(),
+            silo_names,
+            instances,
+        })
+    }
+}
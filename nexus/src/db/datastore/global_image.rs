@@ -11,6 +11,7 @@ use crate::db;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::model::GlobalImage;
 use crate::db::model::Name;
 use crate::db::pagination::paginated;
@@ -18,6 +19,7 @@ use async_bb8_diesel::AsyncRunQueryDsl;
 use diesel::prelude::*;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::ResourceType;
 
@@ -26,14 +28,19 @@ impl DataStore {
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<GlobalImage> {
         opctx
             .authorize(authz::Action::ListChildren, &authz::GLOBAL_IMAGE_LIST)
             .await?;
 
         use db::schema::global_image::dsl;
-        paginated(dsl::global_image, dsl::name, pagparams)
-            .filter(dsl::time_deleted.is_null())
+        let mut query = paginated(dsl::global_image, dsl::name, pagparams)
+            .filter(dsl::time_deleted.is_null());
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(GlobalImage::as_select())
             .load_async::<GlobalImage>(self.pool_authorized(opctx).await?)
             .await
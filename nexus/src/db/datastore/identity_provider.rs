@@ -16,11 +16,14 @@ use crate::db::model::Name;
 use crate::db::pagination::paginated;
 use async_bb8_diesel::AsyncConnection;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
 use diesel::prelude::*;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
+use uuid::Uuid;
 
 impl DataStore {
     pub async fn identity_provider_list(
@@ -93,4 +96,139 @@ impl DataStore {
                 )
             })
     }
+
+    /// List SAML identity providers (in all Silos) whose metadata was
+    /// supplied by URL
+    ///
+    /// This is used to find providers whose metadata should be refreshed.
+    /// Since this lists providers across the whole Fleet, it requires
+    /// [`authz::Action::ListChildren`] on the Fleet.  It does not check that
+    /// the caller may modify them: callers must look up each provider for
+    /// [`authz::Action::Modify`] before acting on it.
+    pub async fn saml_identity_provider_list_url_sourced(
+        &self,
+        opctx: &OpContext,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<db::model::SamlIdentityProvider> {
+        opctx.authorize(authz::Action::ListChildren, &authz::FLEET).await?;
+
+        use db::schema::saml_identity_provider::dsl;
+        paginated(dsl::saml_identity_provider, dsl::id, pagparams)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::idp_metadata_url.is_not_null())
+            .select(db::model::SamlIdentityProvider::as_select())
+            .load_async::<db::model::SamlIdentityProvider>(
+                self.pool_authorized(opctx).await?,
+            )
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Record the outcome of an attempt to refresh the metadata of a SAML
+    /// identity provider
+    ///
+    /// On success, the stored metadata document is replaced in the same update
+    /// that records the refresh time, so readers see either the old document
+    /// or the new one.  On failure, the stored document is left alone and only
+    /// the error is recorded.
+    pub async fn saml_identity_provider_metadata_refreshed(
+        &self,
+        opctx: &OpContext,
+        authz_idp: &authz::SamlIdentityProvider,
+        result: Result<String, String>,
+    ) -> UpdateResult<db::model::SamlIdentityProvider> {
+        opctx.authorize(authz::Action::Modify, authz_idp).await?;
+
+        use db::schema::saml_identity_provider::dsl;
+        let now = Utc::now();
+        let update = diesel::update(dsl::saml_identity_provider)
+            .filter(dsl::id.eq(authz_idp.id()))
+            .filter(dsl::time_deleted.is_null());
+        let pool = self.pool_authorized(opctx).await?;
+        let query_result = match result {
+            Ok(idp_metadata_document_string) => {
+                update
+                    .set((
+                        dsl::idp_metadata_document_string
+                            .eq(idp_metadata_document_string),
+                        dsl::time_metadata_refresh_attempted.eq(now),
+                        dsl::time_metadata_refreshed.eq(now),
+                        dsl::metadata_refresh_error.eq(None::<String>),
+                    ))
+                    .returning(db::model::SamlIdentityProvider::as_returning())
+                    .get_result_async(pool)
+                    .await
+            }
+            Err(message) => {
+                update
+                    .set((
+                        dsl::time_metadata_refresh_attempted.eq(now),
+                        dsl::metadata_refresh_error.eq(message),
+                    ))
+                    .returning(db::model::SamlIdentityProvider::as_returning())
+                    .get_result_async(pool)
+                    .await
+            }
+        };
+
+        query_result.map_err(|e| {
+            public_error_from_diesel_pool(
+                e,
+                ErrorHandler::NotFoundByResource(authz_idp),
+            )
+        })
+    }
+
+    pub async fn oidc_identity_provider_create(
+        &self,
+        opctx: &OpContext,
+        authz_idp_list: &authz::SiloIdentityProviderList,
+        provider: db::model::OidcIdentityProvider,
+    ) -> CreateResult<db::model::OidcIdentityProvider> {
+        opctx.authorize(authz::Action::CreateChild, authz_idp_list).await?;
+        assert_eq!(provider.silo_id, authz_idp_list.silo().id());
+
+        let name = provider.identity().name.to_string();
+        self.pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                // insert silo identity provider record with type Oidc
+                use db::schema::identity_provider::dsl as idp_dsl;
+                diesel::insert_into(idp_dsl::identity_provider)
+                    .values(db::model::IdentityProvider {
+                        identity: db::model::IdentityProviderIdentity {
+                            id: provider.identity.id,
+                            name: provider.identity.name.clone(),
+                            description: provider.identity.description.clone(),
+                            time_created: provider.identity.time_created,
+                            time_modified: provider.identity.time_modified,
+                            time_deleted: provider.identity.time_deleted,
+                        },
+                        silo_id: provider.silo_id,
+                        provider_type: db::model::IdentityProviderType::Oidc,
+                    })
+                    .execute_async(&conn)
+                    .await?;
+
+                // insert silo oidc identity provider record
+                use db::schema::oidc_identity_provider::dsl;
+                let result = diesel::insert_into(dsl::oidc_identity_provider)
+                    .values(provider)
+                    .returning(db::model::OidcIdentityProvider::as_returning())
+                    .get_result_async(&conn)
+                    .await?;
+
+                Ok(result)
+            })
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::Conflict(
+                        ResourceType::OidcIdentityProvider,
+                        &name,
+                    ),
+                )
+            })
+    }
 }
@@ -14,9 +14,11 @@ use crate::db::collection_detach_many::DetachManyError;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Instance;
 use crate::db::model::InstanceRuntimeState;
+use crate::db::model::Labels;
 use crate::db::model::Name;
 use crate::db::pagination::paginated;
 use crate::db::update_and_check::UpdateAndCheck;
@@ -29,10 +31,12 @@ use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
 use omicron_common::bail_unless;
 use uuid::Uuid;
 
@@ -102,13 +106,18 @@ impl DataStore {
         opctx: &OpContext,
         authz_project: &authz::Project,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Instance> {
         opctx.authorize(authz::Action::ListChildren, authz_project).await?;
 
         use db::schema::instance::dsl;
-        paginated(dsl::instance, dsl::name, &pagparams)
+        let mut query = paginated(dsl::instance, dsl::name, &pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::project_id.eq(authz_project.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Instance::as_select())
             .load_async::<Instance>(self.pool_authorized(opctx).await?)
             .await
@@ -182,6 +191,108 @@ impl DataStore {
         Ok(updated)
     }
 
+    /// Reconfigures a stopped Instance, and optionally replaces its labels
+    ///
+    /// `db_instance` is the caller's copy of the Instance.  The update fails
+    /// if the Instance isn't stopped or has changed since the caller fetched
+    /// it, in which case the labels aren't changed either.  Properties passed
+    /// as `None` are left unchanged.
+    pub async fn instance_update(
+        &self,
+        opctx: &OpContext,
+        authz_instance: &authz::Instance,
+        db_instance: &Instance,
+        ncpus: Option<api::external::InstanceCpuCount>,
+        memory: Option<api::external::ByteCount>,
+        hostname: Option<String>,
+        labels: Option<Labels>,
+    ) -> UpdateResult<Instance> {
+        opctx.authorize(authz::Action::Modify, authz_instance).await?;
+
+        let old_runtime = db_instance.runtime();
+        let mut new_runtime = old_runtime.clone();
+        if let Some(ncpus) = ncpus {
+            new_runtime.ncpus = ncpus.into();
+        }
+        if let Some(memory) = memory {
+            new_runtime.memory = memory.into();
+        }
+        if let Some(hostname) = hostname {
+            new_runtime.hostname = hostname;
+        }
+        // Bump the generation so that a stale runtime state reported by a sled
+        // agent can't clobber the new configuration.
+        new_runtime.gen = old_runtime.gen.next().into();
+        new_runtime.time_updated = Utc::now();
+
+        use db::schema::instance::dsl;
+        let stopped = db::model::InstanceState::new(
+            api::external::InstanceState::Stopped,
+        );
+        let result = diesel::update(dsl::instance)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::id.eq(authz_instance.id()))
+            .filter(dsl::state.eq(stopped))
+            .filter(dsl::state_generation.eq(old_runtime.gen))
+            .set((
+                new_runtime,
+                labels.map(|labels| dsl::labels.eq(labels)),
+                dsl::time_modified.eq(Utc::now()),
+            ))
+            .check_if_exists::<Instance>(authz_instance.id())
+            .execute_and_check(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::NotFoundByResource(authz_instance),
+                )
+            })?;
+
+        match result.status {
+            UpdateStatus::Updated => Ok(result.found),
+            UpdateStatus::NotUpdatedButExists => {
+                let instance_state = result.found.runtime().state.state();
+                if instance_state != &api::external::InstanceState::Stopped {
+                    Err(Error::invalid_request(&format!(
+                        "instance must be stopped to be reconfigured, but it \
+                        is \"{}\"",
+                        instance_state
+                    )))
+                } else {
+                    Err(Error::invalid_request(
+                        "instance was modified concurrently with this request",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Replaces the labels on an Instance
+    pub async fn instance_update_labels(
+        &self,
+        opctx: &OpContext,
+        authz_instance: &authz::Instance,
+        labels: Labels,
+    ) -> UpdateResult<Instance> {
+        opctx.authorize(authz::Action::Modify, authz_instance).await?;
+
+        use db::schema::instance::dsl;
+        diesel::update(dsl::instance)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::id.eq(authz_instance.id()))
+            .set((dsl::labels.eq(labels), dsl::time_modified.eq(Utc::now())))
+            .returning(Instance::as_returning())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::NotFoundByResource(authz_instance),
+                )
+            })
+    }
+
     pub async fn project_delete_instance(
         &self,
         opctx: &OpContext,
@@ -44,6 +44,7 @@ mod console_session;
 mod dataset;
 mod device_auth;
 mod disk;
+mod external_dns;
 mod external_ip;
 mod global_image;
 mod identity_provider;
@@ -59,6 +60,7 @@ mod region_snapshot;
 mod role;
 mod saga;
 mod service;
+mod service_account;
 mod silo;
 mod silo_group;
 mod silo_user;
@@ -70,6 +72,8 @@ mod volume;
 mod vpc;
 mod zpool;
 
+pub use external_dns::ExternalDnsInstance;
+pub use external_dns::ExternalDnsZoneData;
 pub use volume::CrucibleResources;
 
 // Number of unique datasets required to back a region.
@@ -273,6 +277,7 @@ mod test {
                 name: "org".parse().unwrap(),
                 description: "desc".to_string(),
             },
+            labels: Default::default(),
         };
 
         let organization =
@@ -285,6 +290,7 @@ mod test {
                     name: "project".parse().unwrap(),
                     description: "desc".to_string(),
                 },
+                labels: Default::default(),
             },
         );
         let (.., authz_org) = LookupPath::new(&opctx, &datastore)
@@ -325,6 +331,7 @@ mod test {
 
         let session = ConsoleSession {
             token: token.clone(),
+            id: Uuid::new_v4(),
             time_created: Utc::now() - Duration::minutes(5),
             time_last_used: Utc::now() - Duration::minutes(5),
             silo_user_id,
@@ -486,6 +493,7 @@ mod test {
                 block_size: params::BlockSize::try_from(4096).unwrap(),
             },
             size,
+            labels: Default::default(),
         }
     }
 
@@ -14,6 +14,7 @@ use crate::db::error::diesel_pool_result_optional;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::model::Name;
 use crate::db::model::Organization;
 use crate::db::model::OrganizationUpdate;
@@ -28,6 +29,7 @@ use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::ResourceType;
 use omicron_common::api::external::UpdateResult;
@@ -132,6 +134,7 @@ impl DataStore {
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Uuid>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Organization> {
         let authz_silo = opctx
             .authn
@@ -140,9 +143,13 @@ impl DataStore {
         opctx.authorize(authz::Action::ListChildren, &authz_silo).await?;
 
         use db::schema::organization::dsl;
-        paginated(dsl::organization, dsl::id, pagparams)
+        let mut query = paginated(dsl::organization, dsl::id, pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::silo_id.eq(authz_silo.id()))
+            .filter(dsl::silo_id.eq(authz_silo.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Organization::as_select())
             .load_async::<Organization>(self.pool_authorized(opctx).await?)
             .await
@@ -153,6 +160,7 @@ impl DataStore {
         &self,
         opctx: &OpContext,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Organization> {
         let authz_silo = opctx
             .authn
@@ -161,9 +169,13 @@ impl DataStore {
         opctx.authorize(authz::Action::ListChildren, &authz_silo).await?;
 
         use db::schema::organization::dsl;
-        paginated(dsl::organization, dsl::name, pagparams)
+        let mut query = paginated(dsl::organization, dsl::name, pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::silo_id.eq(authz_silo.id()))
+            .filter(dsl::silo_id.eq(authz_silo.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Organization::as_select())
             .load_async::<Organization>(self.pool_authorized(opctx).await?)
             .await
@@ -12,11 +12,14 @@ use crate::db::model::OximeterInfo;
 use crate::db::model::ProducerEndpoint;
 use crate::db::pagination::paginated;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use async_bb8_diesel::OptionalExtension;
 use chrono::Utc;
 use diesel::prelude::*;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
 use uuid::Uuid;
 
@@ -55,6 +58,24 @@ impl DataStore {
         Ok(())
     }
 
+    // Fetch the record for a single oximeter collector instance
+    pub async fn oximeter_fetch(&self, id: Uuid) -> LookupResult<OximeterInfo> {
+        use db::schema::oximeter::dsl;
+        dsl::oximeter
+            .filter(dsl::id.eq(id))
+            .first_async::<OximeterInfo>(self.pool())
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::NotFoundByLookup(
+                        ResourceType::Oximeter,
+                        LookupType::ById(id),
+                    ),
+                )
+            })
+    }
+
     // List the oximeter collector instances
     pub async fn oximeter_list(
         &self,
@@ -100,6 +121,40 @@ impl DataStore {
         Ok(())
     }
 
+    // Fetch the ID of the oximeter instance to which a producer endpoint is
+    // assigned, or `None` if there is no such producer.
+    pub async fn producer_endpoint_oximeter_id(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<Uuid>, Error> {
+        use db::schema::metric_producer::dsl;
+        dsl::metric_producer
+            .filter(dsl::id.eq(*id))
+            .select(dsl::oximeter_id)
+            .get_result_async(self.pool())
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    // Delete the record for a producer endpoint.
+    //
+    // Returns the ID of the oximeter instance to which the producer was assigned, or `None` if
+    // there was no such producer.
+    pub async fn producer_endpoint_delete(
+        &self,
+        id: &Uuid,
+    ) -> Result<Option<Uuid>, Error> {
+        use db::schema::metric_producer::dsl;
+        diesel::delete(dsl::metric_producer)
+            .filter(dsl::id.eq(*id))
+            .returning(dsl::oximeter_id)
+            .get_result_async(self.pool())
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
     // List the producer endpoint records by the oximeter instance to which they're assigned.
     pub async fn producers_list_by_oximeter_id(
         &self,
@@ -6,18 +6,22 @@
 
 use super::DataStore;
 use crate::authz;
+use crate::authz::ApiResource;
 use crate::context::OpContext;
 use crate::db;
 use crate::db::collection_insert::AsyncInsertError;
 use crate::db::collection_insert::DatastoreCollection;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
+use crate::db::error::TransactionError;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::model::Name;
 use crate::db::model::Organization;
 use crate::db::model::Project;
 use crate::db::model::ProjectUpdate;
 use crate::db::pagination::paginated;
+use async_bb8_diesel::AsyncConnection;
 use async_bb8_diesel::AsyncRunQueryDsl;
 use chrono::Utc;
 use diesel::prelude::*;
@@ -25,6 +29,7 @@ use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupType;
 use omicron_common::api::external::ResourceType;
@@ -100,14 +105,19 @@ impl DataStore {
         opctx: &OpContext,
         authz_org: &authz::Organization,
         pagparams: &DataPageParams<'_, Uuid>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Project> {
         use db::schema::project::dsl;
 
         opctx.authorize(authz::Action::ListChildren, authz_org).await?;
 
-        paginated(dsl::project, dsl::id, pagparams)
+        let mut query = paginated(dsl::project, dsl::id, pagparams)
             .filter(dsl::organization_id.eq(authz_org.id()))
-            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::time_deleted.is_null());
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Project::as_select())
             .load_async(self.pool_authorized(opctx).await?)
             .await
@@ -119,14 +129,19 @@ impl DataStore {
         opctx: &OpContext,
         authz_org: &authz::Organization,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Project> {
         use db::schema::project::dsl;
 
         opctx.authorize(authz::Action::ListChildren, authz_org).await?;
 
-        paginated(dsl::project, dsl::name, &pagparams)
+        let mut query = paginated(dsl::project, dsl::name, &pagparams)
             .filter(dsl::organization_id.eq(authz_org.id()))
-            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::time_deleted.is_null());
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Project::as_select())
             .load_async(self.pool_authorized(opctx).await?)
             .await
@@ -157,4 +172,135 @@ impl DataStore {
                 )
             })
     }
+
+    /// Moves a project out of the Organization `authz_src_org` and into the
+    /// Organization `authz_dst_org`
+    ///
+    /// The caller must be able to modify the project and its current
+    /// Organization (whose admins otherwise lose control of the project), and
+    /// to create projects in the destination Organization.
+    ///
+    /// Service accounts and access tokens limited to the project record the
+    /// Organization containing it, so those are updated along with the project
+    /// itself.
+    pub async fn project_move(
+        &self,
+        opctx: &OpContext,
+        authz_src_org: &authz::Organization,
+        authz_project: &authz::Project,
+        db_project: &Project,
+        authz_dst_org: &authz::Organization,
+    ) -> UpdateResult<Project> {
+        assert_eq!(authz_project.id(), db_project.id());
+        assert_eq!(authz_src_org.id(), db_project.organization_id);
+        opctx.authorize(authz::Action::Modify, authz_project).await?;
+        opctx.authorize(authz::Action::Modify, authz_src_org).await?;
+        opctx.authorize(authz::Action::CreateChild, authz_dst_org).await?;
+
+        #[derive(Debug)]
+        enum ProjectMoveError {
+            OrganizationNotFound,
+            ProjectNotFound,
+        }
+        type TxnError = TransactionError<ProjectMoveError>;
+
+        let project_id = authz_project.id();
+        let src_org_id = db_project.organization_id;
+        let dst_org_id = authz_dst_org.id();
+        self.pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                let now = Utc::now();
+
+                // Bumping the destination Organization's generation number
+                // takes a write lock on its row, ensuring that it's not
+                // deleted concurrently.
+                {
+                    use db::schema::organization::dsl;
+                    let updated_rows = diesel::update(dsl::organization)
+                        .filter(dsl::id.eq(dst_org_id))
+                        .filter(dsl::time_deleted.is_null())
+                        .set(dsl::rcgen.eq(dsl::rcgen + 1))
+                        .execute_async(&conn)
+                        .await?;
+                    if updated_rows == 0 {
+                        return Err(TxnError::CustomError(
+                            ProjectMoveError::OrganizationNotFound,
+                        ));
+                    }
+                }
+
+                // The project's name must be unique within its new
+                // Organization.  That's enforced by a unique index, so a
+                // conflict shows up as an error here.
+                {
+                    use db::schema::project::dsl;
+                    let updated_rows = diesel::update(dsl::project)
+                        .filter(dsl::id.eq(project_id))
+                        .filter(dsl::organization_id.eq(src_org_id))
+                        .filter(dsl::time_deleted.is_null())
+                        .set((
+                            dsl::organization_id.eq(dst_org_id),
+                            dsl::time_modified.eq(now),
+                        ))
+                        .execute_async(&conn)
+                        .await?;
+                    if updated_rows == 0 {
+                        return Err(TxnError::CustomError(
+                            ProjectMoveError::ProjectNotFound,
+                        ));
+                    }
+                }
+
+                {
+                    use db::schema::service_account::dsl;
+                    diesel::update(dsl::service_account)
+                        .filter(dsl::project_id.eq(project_id))
+                        .set(dsl::organization_id.eq(dst_org_id))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                {
+                    use db::schema::device_auth_request::dsl;
+                    diesel::update(dsl::device_auth_request)
+                        .filter(dsl::scope_project_id.eq(project_id))
+                        .set(dsl::scope_organization_id.eq(dst_org_id))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                {
+                    use db::schema::device_access_token::dsl;
+                    diesel::update(dsl::device_access_token)
+                        .filter(dsl::scope_project_id.eq(project_id))
+                        .set(dsl::scope_organization_id.eq(dst_org_id))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                use db::schema::project::dsl;
+                Ok(dsl::project
+                    .filter(dsl::id.eq(project_id))
+                    .select(Project::as_select())
+                    .get_result_async(&conn)
+                    .await?)
+            })
+            .await
+            .map_err(|e| match e {
+                TxnError::CustomError(
+                    ProjectMoveError::OrganizationNotFound,
+                ) => authz_dst_org.not_found(),
+                TxnError::CustomError(ProjectMoveError::ProjectNotFound) => {
+                    authz_project.not_found()
+                }
+                TxnError::Pool(e) => public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::Conflict(
+                        ResourceType::Project,
+                        db_project.name().as_str(),
+                    ),
+                ),
+            })
+    }
 }
@@ -59,7 +59,8 @@ impl DataStore {
         disk_source: &params::DiskSource,
     ) -> Result<db::model::BlockSize, Error> {
         match &disk_source {
-            params::DiskSource::Blank { block_size } => {
+            params::DiskSource::Blank { block_size }
+            | params::DiskSource::ImportingBlocks { block_size } => {
                 Ok(db::model::BlockSize::try_from(*block_size)
                     .map_err(|e| Error::invalid_request(&e.to_string()))?)
             }
@@ -147,6 +148,20 @@ impl DataStore {
 
         let block_size =
             self.get_block_size_from_disk_source(opctx, &disk_source).await?;
+        self.region_allocate_with_block_size(volume_id, block_size, size).await
+    }
+
+    /// Idempotently allocates enough regions of the given block size to back
+    /// `size` bytes of a volume.
+    ///
+    /// This is used when the block size is already known, as when growing an
+    /// existing disk.  See [`DataStore::region_allocate`] for details.
+    pub async fn region_allocate_with_block_size(
+        &self,
+        volume_id: Uuid,
+        block_size: db::model::BlockSize,
+        size: external::ByteCount,
+    ) -> Result<Vec<(Dataset, Region)>, Error> {
         let (blocks_per_extent, extent_count) =
             Self::get_crucible_allocation(&block_size, size);
 
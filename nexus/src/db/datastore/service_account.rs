@@ -0,0 +1,234 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods related to [`ServiceAccount`]s and their API keys.
+
+use super::DataStore;
+use crate::authz;
+use crate::authz::ApiResource;
+use crate::context::OpContext;
+use crate::db;
+use crate::db::error::public_error_from_diesel_pool;
+use crate::db::error::ErrorHandler;
+use crate::db::identity::Resource;
+use crate::db::model::IdentityType;
+use crate::db::model::Name;
+use crate::db::model::ServiceAccount;
+use crate::db::model::ServiceAccountKey;
+use crate::db::pagination::paginated;
+use async_bb8_diesel::AsyncConnection;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
+use diesel::prelude::*;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::ResourceType;
+use uuid::Uuid;
+
+impl DataStore {
+    /// Create a service account in a Silo.
+    pub async fn service_account_create(
+        &self,
+        opctx: &OpContext,
+        authz_silo: &authz::Silo,
+        service_account: ServiceAccount,
+    ) -> CreateResult<ServiceAccount> {
+        assert_eq!(authz_silo.id(), service_account.silo_id);
+        opctx.authorize(authz::Action::Modify, authz_silo).await?;
+        let name = service_account.name().to_string();
+
+        use db::schema::service_account::dsl;
+        diesel::insert_into(dsl::service_account)
+            .values(service_account)
+            .returning(ServiceAccount::as_returning())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::Conflict(ResourceType::ServiceAccount, &name),
+                )
+            })
+    }
+
+    pub async fn service_accounts_list(
+        &self,
+        opctx: &OpContext,
+        authz_silo: &authz::Silo,
+        pagparams: &DataPageParams<'_, Name>,
+    ) -> ListResultVec<ServiceAccount> {
+        opctx.authorize(authz::Action::ListChildren, authz_silo).await?;
+
+        use db::schema::service_account::dsl;
+        paginated(dsl::service_account, dsl::name, pagparams)
+            .filter(dsl::silo_id.eq(authz_silo.id()))
+            .filter(dsl::time_deleted.is_null())
+            .select(ServiceAccount::as_select())
+            .load_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Delete a service account, along with its API keys and any roles it's
+    /// been granted.
+    pub async fn service_account_delete(
+        &self,
+        opctx: &OpContext,
+        authz_service_account: &authz::ServiceAccount,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Delete, authz_service_account).await?;
+
+        let service_account_id = authz_service_account.id();
+        let updated_rows = self
+            .pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                let now = Utc::now();
+
+                use db::schema::service_account::dsl;
+                let updated_rows = diesel::update(dsl::service_account)
+                    .filter(dsl::id.eq(service_account_id))
+                    .filter(dsl::time_deleted.is_null())
+                    .set(dsl::time_deleted.eq(now))
+                    .execute_async(&conn)
+                    .await?;
+
+                {
+                    use db::schema::service_account_key::dsl;
+                    diesel::update(dsl::service_account_key)
+                        .filter(dsl::service_account_id.eq(service_account_id))
+                        .filter(dsl::time_deleted.is_null())
+                        .set(dsl::time_deleted.eq(now))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                {
+                    use db::schema::role_assignment::dsl;
+                    diesel::delete(dsl::role_assignment)
+                        .filter(
+                            dsl::identity_type.eq(IdentityType::ServiceAccount),
+                        )
+                        .filter(dsl::identity_id.eq(service_account_id))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                Ok(updated_rows)
+            })
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+
+        if updated_rows == 0 {
+            return Err(authz_service_account.not_found());
+        }
+        Ok(())
+    }
+
+    /// Create an API key for a service account.
+    pub async fn service_account_key_create(
+        &self,
+        opctx: &OpContext,
+        authz_service_account: &authz::ServiceAccount,
+        key: ServiceAccountKey,
+    ) -> CreateResult<ServiceAccountKey> {
+        assert_eq!(authz_service_account.id(), key.service_account_id);
+        opctx
+            .authorize(authz::Action::CreateChild, authz_service_account)
+            .await?;
+
+        use db::schema::service_account_key::dsl;
+        diesel::insert_into(dsl::service_account_key)
+            .values(key)
+            .returning(ServiceAccountKey::as_returning())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    pub async fn service_account_keys_list(
+        &self,
+        opctx: &OpContext,
+        authz_service_account: &authz::ServiceAccount,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<ServiceAccountKey> {
+        opctx
+            .authorize(authz::Action::ListChildren, authz_service_account)
+            .await?;
+
+        use db::schema::service_account_key::dsl;
+        paginated(dsl::service_account_key, dsl::id, pagparams)
+            .filter(dsl::service_account_id.eq(authz_service_account.id()))
+            .filter(dsl::time_deleted.is_null())
+            .select(ServiceAccountKey::as_select())
+            .load_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Delete one of a service account's API keys.  Requests authenticated
+    /// with it fail from then on.
+    pub async fn service_account_key_delete(
+        &self,
+        opctx: &OpContext,
+        authz_service_account: &authz::ServiceAccount,
+        key_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_service_account).await?;
+
+        use db::schema::service_account_key::dsl;
+        let updated_rows = diesel::update(dsl::service_account_key)
+            .filter(dsl::id.eq(key_id))
+            .filter(dsl::service_account_id.eq(authz_service_account.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+
+        if updated_rows == 0 {
+            return Err(Error::ObjectNotFound {
+                type_name: ResourceType::ServiceAccountKey,
+                lookup_type: LookupType::ById(key_id),
+            });
+        }
+        Ok(())
+    }
+
+    /// Look up an undeleted API key by its token.
+    ///
+    /// This is used to authenticate requests, before there's an actor whose
+    /// access could be checked.
+    pub async fn service_account_key_fetch(
+        &self,
+        opctx: &OpContext,
+        token: String,
+    ) -> LookupResult<ServiceAccountKey> {
+        use db::schema::service_account_key::dsl;
+        dsl::service_account_key
+            .filter(dsl::token.eq(token))
+            .filter(dsl::time_deleted.is_null())
+            .select(ServiceAccountKey::as_select())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::NotFoundByLookup(
+                        ResourceType::ServiceAccountKey,
+                        LookupType::ByCompositeId("token".to_string()),
+                    ),
+                )
+            })
+    }
+}
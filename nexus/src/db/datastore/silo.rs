@@ -340,6 +340,23 @@ impl DataStore {
             "deleted {} silo saml IdPs for silo {}", updated_rows, id
         );
 
+        use db::schema::oidc_identity_provider::dsl as oidc_idp_dsl;
+
+        let updated_rows = diesel::update(oidc_idp_dsl::oidc_identity_provider)
+            .filter(oidc_idp_dsl::silo_id.eq(id))
+            .filter(oidc_idp_dsl::time_deleted.is_null())
+            .set(oidc_idp_dsl::time_deleted.eq(Utc::now()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+
+        debug!(
+            opctx.log,
+            "deleted {} silo oidc IdPs for silo {}", updated_rows, id
+        );
+
         Ok(())
     }
 }
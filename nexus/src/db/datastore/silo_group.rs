@@ -6,12 +6,14 @@
 
 use super::DataStore;
 use crate::authz;
+use crate::authz::ApiResource;
 use crate::context::OpContext;
 use crate::db;
 use crate::db::datastore::RunnableQuery;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::error::TransactionError;
+use crate::db::model::IdentityType;
 use crate::db::model::SiloGroup;
 use crate::db::model::SiloGroupMembership;
 use crate::db::pagination::paginated;
@@ -26,6 +28,7 @@ use omicron_common::api::external::Error;
 use omicron_common::api::external::InternalContext;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::ResourceType;
 use omicron_common::api::external::UpdateResult;
 use uuid::Uuid;
 
@@ -68,6 +71,37 @@ impl DataStore {
             .unwrap())
     }
 
+    /// Create a group in a Silo, failing if it already has a group with the
+    /// same external id
+    pub async fn silo_group_create(
+        &self,
+        opctx: &OpContext,
+        authz_silo_group_list: &authz::SiloGroupList,
+        silo_group: SiloGroup,
+    ) -> CreateResult<SiloGroup> {
+        assert_eq!(authz_silo_group_list.silo().id(), silo_group.silo_id);
+        opctx
+            .authorize(authz::Action::CreateChild, authz_silo_group_list)
+            .await?;
+        let external_id = silo_group.external_id.clone();
+
+        use db::schema::silo_group::dsl;
+        diesel::insert_into(dsl::silo_group)
+            .values(silo_group)
+            .returning(SiloGroup::as_returning())
+            .get_result_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::Conflict(
+                        ResourceType::SiloGroup,
+                        &external_id,
+                    ),
+                )
+            })
+    }
+
     pub async fn silo_group_optional_lookup(
         &self,
         opctx: &OpContext,
@@ -181,6 +215,57 @@ impl DataStore {
             .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
     }
 
+    /// Add a user to a group.  Adding a user who's already a member does
+    /// nothing.
+    pub async fn silo_group_membership_add(
+        &self,
+        opctx: &OpContext,
+        authz_silo_group: &authz::SiloGroup,
+        authz_silo_user: &authz::SiloUser,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Modify, authz_silo_group).await?;
+
+        use db::schema::silo_group_membership::dsl;
+        diesel::insert_into(dsl::silo_group_membership)
+            .values(SiloGroupMembership::new(
+                authz_silo_group.id(),
+                authz_silo_user.id(),
+            ))
+            .on_conflict((dsl::silo_group_id, dsl::silo_user_id))
+            .do_nothing()
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        Ok(())
+    }
+
+    /// Remove a user from a group
+    pub async fn silo_group_membership_remove(
+        &self,
+        opctx: &OpContext,
+        authz_silo_group: &authz::SiloGroup,
+        authz_silo_user: &authz::SiloUser,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_silo_group).await?;
+
+        use db::schema::silo_group_membership::dsl;
+        let deleted = diesel::delete(dsl::silo_group_membership)
+            .filter(dsl::silo_group_id.eq(authz_silo_group.id()))
+            .filter(dsl::silo_user_id.eq(authz_silo_user.id()))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        if deleted == 0 {
+            // The user isn't a member of the group.
+            return Err(authz_silo_user.not_found());
+        }
+        Ok(())
+    }
+
     pub async fn silo_group_delete(
         &self,
         opctx: &OpContext,
@@ -221,13 +306,26 @@ impl DataStore {
                 }
 
                 // Delete silo group
-                use db::schema::silo_group::dsl;
-                diesel::update(dsl::silo_group)
-                    .filter(dsl::id.eq(group_id))
-                    .filter(dsl::time_deleted.is_null())
-                    .set(dsl::time_deleted.eq(Utc::now()))
-                    .execute_async(&conn)
-                    .await?;
+                {
+                    use db::schema::silo_group::dsl;
+                    diesel::update(dsl::silo_group)
+                        .filter(dsl::id.eq(group_id))
+                        .filter(dsl::time_deleted.is_null())
+                        .set(dsl::time_deleted.eq(Utc::now()))
+                        .execute_async(&conn)
+                        .await?;
+                }
+
+                // Delete the roles granted to the group, so that a group
+                // created later can't inherit them.
+                {
+                    use db::schema::role_assignment::dsl;
+                    diesel::delete(dsl::role_assignment)
+                        .filter(dsl::identity_type.eq(IdentityType::SiloGroup))
+                        .filter(dsl::identity_id.eq(group_id))
+                        .execute_async(&conn)
+                        .await?;
+                }
 
                 Ok(())
             })
@@ -15,21 +15,27 @@ use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::model::Name;
 use crate::db::model::SiloUser;
+use crate::db::model::SiloUserPasswordHash;
 use crate::db::model::UserBuiltin;
 use crate::db::pagination::paginated;
 use crate::db::update_and_check::UpdateAndCheck;
 use crate::external_api::params;
 use async_bb8_diesel::AsyncConnection;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use async_bb8_diesel::OptionalExtension;
+use chrono::DateTime;
 use chrono::Utc;
 use diesel::prelude::*;
+use diesel::upsert::excluded;
 use nexus_types::identity::Asset;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
 use uuid::Uuid;
 
 impl DataStore {
@@ -68,6 +74,62 @@ impl DataStore {
             })
     }
 
+    /// Create a user in a silo's local identity provider, along with their
+    /// password hash if they have one
+    ///
+    /// Both are written in one transaction, so that a user never exists
+    /// without the password they were created with.
+    pub async fn silo_user_create_with_password(
+        &self,
+        opctx: &OpContext,
+        authz_silo: &authz::Silo,
+        silo_user: SiloUser,
+        db_password_hash: Option<SiloUserPasswordHash>,
+    ) -> CreateResult<(authz::SiloUser, SiloUser)> {
+        let silo_user_id = silo_user.id();
+        let authz_silo_user = authz::SiloUser::new(
+            authz_silo.clone(),
+            silo_user_id,
+            LookupType::ById(silo_user_id),
+        );
+        opctx.authorize(authz::Action::Modify, &authz_silo_user).await?;
+
+        let silo_user_external_id = silo_user.external_id.clone();
+        let db_silo_user = self
+            .pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                let db_silo_user = {
+                    use db::schema::silo_user::dsl;
+                    diesel::insert_into(dsl::silo_user)
+                        .values(silo_user)
+                        .returning(SiloUser::as_returning())
+                        .get_result_async(&conn)
+                        .await?
+                };
+                if let Some(db_password_hash) = db_password_hash {
+                    assert_eq!(db_password_hash.silo_user_id, silo_user_id);
+                    use db::schema::silo_user_password_hash::dsl;
+                    diesel::insert_into(dsl::silo_user_password_hash)
+                        .values(db_password_hash)
+                        .execute_async(&conn)
+                        .await?;
+                }
+                Ok(db_silo_user)
+            })
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(
+                    e,
+                    ErrorHandler::Conflict(
+                        ResourceType::SiloUser,
+                        &silo_user_external_id,
+                    ),
+                )
+            })?;
+        Ok((authz_silo_user, db_silo_user))
+    }
+
     /// Delete a Silo User
     pub async fn silo_user_delete(
         &self,
@@ -96,6 +158,15 @@ impl DataStore {
                         .await?;
                 }
 
+                // Delete the user's password hash.
+                {
+                    use db::schema::silo_user_password_hash::dsl;
+                    diesel::delete(dsl::silo_user_password_hash)
+                        .filter(dsl::silo_user_id.eq(authz_silo_user_id))
+                        .execute_async(&mut conn)
+                        .await?;
+                }
+
                 // Delete console sessions.
                 {
                     use db::schema::console_session::dsl;
@@ -146,6 +217,138 @@ impl DataStore {
             })
     }
 
+    /// Fetch the password hash of a silo user, if they have one
+    pub async fn silo_user_password_hash_fetch(
+        &self,
+        opctx: &OpContext,
+        authz_silo_user: &authz::SiloUser,
+    ) -> LookupResult<Option<SiloUserPasswordHash>> {
+        opctx.authorize(authz::Action::Read, authz_silo_user).await?;
+
+        use db::schema::silo_user_password_hash::dsl;
+        dsl::silo_user_password_hash
+            .filter(dsl::silo_user_id.eq(authz_silo_user.id()))
+            .select(SiloUserPasswordHash::as_select())
+            .first_async::<SiloUserPasswordHash>(
+                self.pool_authorized(opctx).await?,
+            )
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Set the password hash of a silo user, or remove it if
+    /// `db_password_hash` is `None`
+    ///
+    /// Either way, this clears any record of failed login attempts, unlocking
+    /// the user.
+    pub async fn silo_user_password_hash_set(
+        &self,
+        opctx: &OpContext,
+        authz_silo_user: &authz::SiloUser,
+        db_password_hash: Option<SiloUserPasswordHash>,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Modify, authz_silo_user).await?;
+
+        use db::schema::silo_user_password_hash::dsl;
+        let pool = self.pool_authorized(opctx).await?;
+        match db_password_hash {
+            Some(db_password_hash) => {
+                assert_eq!(db_password_hash.silo_user_id, authz_silo_user.id());
+                diesel::insert_into(dsl::silo_user_password_hash)
+                    .values(db_password_hash)
+                    .on_conflict(dsl::silo_user_id)
+                    .do_update()
+                    .set((
+                        dsl::hash.eq(excluded(dsl::hash)),
+                        dsl::time_created.eq(excluded(dsl::time_created)),
+                        dsl::failed_attempts.eq(0),
+                        dsl::time_locked_until.eq(None::<DateTime<Utc>>),
+                    ))
+                    .execute_async(pool)
+                    .await
+            }
+            None => {
+                diesel::delete(dsl::silo_user_password_hash)
+                    .filter(dsl::silo_user_id.eq(authz_silo_user.id()))
+                    .execute_async(pool)
+                    .await
+            }
+        }
+        .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))?;
+        Ok(())
+    }
+
+    /// Record a failed attempt to log in as a silo user with a password
+    ///
+    /// Once there have been `max_failed_attempts` in a row, the user is locked
+    /// out until `locked_until`, and the count starts over.  Returns whether
+    /// this attempt locked the user out.
+    pub async fn silo_user_password_login_failed(
+        &self,
+        opctx: &OpContext,
+        authz_silo_user: &authz::SiloUser,
+        max_failed_attempts: i64,
+        locked_until: DateTime<Utc>,
+    ) -> UpdateResult<bool> {
+        opctx.authorize(authz::Action::Modify, authz_silo_user).await?;
+
+        use db::schema::silo_user_password_hash::dsl;
+        let silo_user_id = authz_silo_user.id();
+        self.pool_authorized(opctx)
+            .await?
+            .transaction_async(|conn| async move {
+                let failed_attempts =
+                    diesel::update(dsl::silo_user_password_hash)
+                        .filter(dsl::silo_user_id.eq(silo_user_id))
+                        .set(dsl::failed_attempts.eq(dsl::failed_attempts + 1))
+                        .returning(dsl::failed_attempts)
+                        .get_results_async::<i64>(&conn)
+                        .await?;
+                // The user may not have a password (any more), in which case
+                // there's nothing to lock.
+                let locked = matches!(
+                    failed_attempts.first(),
+                    Some(n) if *n >= max_failed_attempts
+                );
+                if locked {
+                    diesel::update(dsl::silo_user_password_hash)
+                        .filter(dsl::silo_user_id.eq(silo_user_id))
+                        .set((
+                            dsl::failed_attempts.eq(0),
+                            dsl::time_locked_until.eq(locked_until),
+                        ))
+                        .execute_async(&conn)
+                        .await?;
+                }
+                Ok(locked)
+            })
+            .await
+            .map_err(|e| public_error_from_diesel_pool(e, ErrorHandler::Server))
+    }
+
+    /// Record a successful login as a silo user with a password, clearing any
+    /// count of failed attempts
+    pub async fn silo_user_password_login_succeeded(
+        &self,
+        opctx: &OpContext,
+        authz_silo_user: &authz::SiloUser,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Modify, authz_silo_user).await?;
+
+        use db::schema::silo_user_password_hash::dsl;
+        diesel::update(dsl::silo_user_password_hash)
+            .filter(dsl::silo_user_id.eq(authz_silo_user.id()))
+            .filter(dsl::failed_attempts.ne(0))
+            .set(dsl::failed_attempts.eq(0))
+            .execute_async(self.pool_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel_pool(e, ErrorHandler::Server)
+            })?;
+        Ok(())
+    }
+
     /// Given an external ID, return
     /// - Ok(Some((authz::SiloUser, SiloUser))) if that external id refers to an
     ///   existing silo user
@@ -233,6 +436,7 @@ impl DataStore {
             &*authn::USER_INTERNAL_READ,
             &*authn::USER_EXTERNAL_AUTHN,
             &*authn::USER_SAGA_RECOVERY,
+            &*authn::USER_SAML_METADATA_REFRESH,
         ]
         .iter()
         .map(|u| {
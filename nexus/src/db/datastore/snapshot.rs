@@ -11,6 +11,7 @@ use crate::db;
 use crate::db::datastore::RunnableQuery;
 use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
+use crate::db::label_selector::labels_match;
 use crate::db::lookup::LookupPath;
 use crate::db::model::Generation;
 use crate::db::model::Name;
@@ -25,6 +26,7 @@ use nexus_types::identity::Resource;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::UpdateResult;
 use omicron_common::bail_unless;
@@ -117,13 +119,18 @@ impl DataStore {
         opctx: &OpContext,
         authz_project: &authz::Project,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Snapshot> {
         opctx.authorize(authz::Action::ListChildren, authz_project).await?;
 
         use db::schema::snapshot::dsl;
-        paginated(dsl::snapshot, dsl::name, &pagparams)
+        let mut query = paginated(dsl::snapshot, dsl::name, &pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::project_id.eq(authz_project.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Snapshot::as_select())
             .load_async::<Snapshot>(self.pool_authorized(opctx).await?)
             .await
@@ -15,6 +15,7 @@ use crate::db::error::public_error_from_diesel_pool;
 use crate::db::error::ErrorHandler;
 use crate::db::error::TransactionError;
 use crate::db::identity::Resource;
+use crate::db::label_selector::labels_match;
 use crate::db::model::IncompleteVpc;
 use crate::db::model::Name;
 use crate::db::model::NetworkInterface;
@@ -43,6 +44,7 @@ use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
 use omicron_common::api::external::DeleteResult;
 use omicron_common::api::external::Error;
+use omicron_common::api::external::LabelSelector;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
@@ -57,13 +59,18 @@ impl DataStore {
         opctx: &OpContext,
         authz_project: &authz::Project,
         pagparams: &DataPageParams<'_, Name>,
+        label_selector: Option<&LabelSelector>,
     ) -> ListResultVec<Vpc> {
         opctx.authorize(authz::Action::ListChildren, authz_project).await?;
 
         use db::schema::vpc::dsl;
-        paginated(dsl::vpc, dsl::name, &pagparams)
+        let mut query = paginated(dsl::vpc, dsl::name, &pagparams)
             .filter(dsl::time_deleted.is_null())
-            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::project_id.eq(authz_project.id()));
+        if let Some(label_selector) = label_selector {
+            query = query.filter(labels_match(dsl::labels, label_selector));
+        }
+        query
             .select(Vpc::as_select())
             .load_async(self.pool_authorized(opctx).await?)
             .await
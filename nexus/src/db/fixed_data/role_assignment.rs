@@ -47,5 +47,24 @@ lazy_static! {
                 *FLEET_ID,
                 role_builtin::FLEET_AUTHENTICATOR.role_name,
             ),
+
+            // The "saml-metadata-refresh" user gets the "viewer" role on the
+            // sole Fleet, so that it can find the SAML identity providers in
+            // every Silo, and the "saml-metadata-refresher" role, so that it
+            // can update their metadata.
+            RoleAssignment::new(
+                IdentityType::UserBuiltin,
+                user_builtin::USER_SAML_METADATA_REFRESH.id,
+                role_builtin::FLEET_VIEWER.resource_type,
+                *FLEET_ID,
+                role_builtin::FLEET_VIEWER.role_name,
+            ),
+            RoleAssignment::new(
+                IdentityType::UserBuiltin,
+                user_builtin::USER_SAML_METADATA_REFRESH.id,
+                role_builtin::FLEET_SAML_METADATA_REFRESHER.resource_type,
+                *FLEET_ID,
+                role_builtin::FLEET_SAML_METADATA_REFRESHER.role_name,
+            ),
         ];
 }
@@ -24,6 +24,12 @@ lazy_static! {
         role_name: "external-authenticator",
         description: "Fleet External Authenticator",
     };
+    pub static ref FLEET_SAML_METADATA_REFRESHER: RoleBuiltinConfig =
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Fleet,
+            role_name: "saml-metadata-refresher",
+            description: "Fleet SAML Metadata Refresher",
+        };
     pub static ref FLEET_VIEWER: RoleBuiltinConfig = RoleBuiltinConfig {
         resource_type: api::external::ResourceType::Fleet,
         role_name: "viewer",
@@ -49,6 +55,7 @@ lazy_static! {
     pub static ref BUILTIN_ROLES: Vec<RoleBuiltinConfig> = vec![
         FLEET_ADMIN.clone(),
         FLEET_AUTHENTICATOR.clone(),
+        FLEET_SAML_METADATA_REFRESHER.clone(),
         FLEET_VIEWER.clone(),
         RoleBuiltinConfig {
             resource_type: api::external::ResourceType::Fleet,
@@ -88,6 +95,36 @@ lazy_static! {
             role_name: "viewer",
             description: "Project Viewer",
         },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Instance,
+            role_name: "operator",
+            description: "Instance Operator",
+        },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Instance,
+            role_name: "viewer",
+            description: "Instance Viewer",
+        },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Vpc,
+            role_name: "admin",
+            description: "VPC Administrator",
+        },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Vpc,
+            role_name: "viewer",
+            description: "VPC Viewer",
+        },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Disk,
+            role_name: "admin",
+            description: "Disk Administrator",
+        },
+        RoleBuiltinConfig {
+            resource_type: api::external::ResourceType::Disk,
+            role_name: "viewer",
+            description: "Disk Viewer",
+        },
     ];
 }
 
@@ -111,6 +148,9 @@ mod test {
             ResourceType::Organization,
         );
         check_public_roles::<authz::ProjectRole>(ResourceType::Project);
+        check_public_roles::<authz::InstanceRole>(ResourceType::Instance);
+        check_public_roles::<authz::VpcRole>(ResourceType::Vpc);
+        check_public_roles::<authz::DiskRole>(ResourceType::Disk);
     }
 
     fn check_public_roles<T>(resource_type: ResourceType)
@@ -81,6 +81,16 @@ lazy_static! {
             "external-authn",
             "used by Nexus when authenticating external requests",
         );
+
+    /// Internal user used by Nexus to refresh the metadata of SAML identity
+    /// providers
+    pub static ref USER_SAML_METADATA_REFRESH: UserBuiltinConfig =
+        UserBuiltinConfig::new_static(
+            // "5a41" looks a bit like "saml".
+            "001de000-05e4-4000-8000-000000005a41",
+            "saml-metadata-refresh",
+            "used by Nexus to refresh SAML identity provider metadata",
+        );
 }
 
 #[cfg(test)]
@@ -91,6 +101,7 @@ mod test {
     use super::USER_INTERNAL_API;
     use super::USER_INTERNAL_READ;
     use super::USER_SAGA_RECOVERY;
+    use super::USER_SAML_METADATA_REFRESH;
     use super::USER_SERVICE_BALANCER;
 
     #[test]
@@ -101,5 +112,6 @@ mod test {
         assert_valid_uuid(&USER_EXTERNAL_AUTHN.id);
         assert_valid_uuid(&USER_INTERNAL_READ.id);
         assert_valid_uuid(&USER_SAGA_RECOVERY.id);
+        assert_valid_uuid(&USER_SAML_METADATA_REFRESH.id);
     }
 }
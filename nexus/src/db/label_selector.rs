@@ -0,0 +1,33 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Filtering queries with a [`LabelSelector`].
+
+use diesel::dsl::AsExprOf;
+use diesel::expression::AsExpression;
+use diesel::pg::Pg;
+use diesel::sql_types::Jsonb;
+use diesel::Expression;
+use nexus_db_model::Labels;
+use omicron_common::api::external::LabelSelector;
+
+diesel::infix_operator!(Contains, " @> ", backend: Pg);
+
+/// Returns an expression that's true for rows whose labels (stored in
+/// `column`) include every label in `selector`
+///
+/// This uses JSONB containment, so the database can answer it from an inverted
+/// index if one exists.
+pub fn labels_match<C>(
+    column: C,
+    selector: &LabelSelector,
+) -> Contains<C, AsExprOf<Labels, Jsonb>>
+where
+    C: Expression<SqlType = Jsonb>,
+{
+    Contains::new(
+        column,
+        AsExpression::<Jsonb>::as_expression(Labels(selector.labels().clone())),
+    )
+}
@@ -368,6 +368,47 @@ impl<'a> LookupPath<'a> {
         SiloGroup::PrimaryKey(Root { lookup_root: self }, id)
     }
 
+    /// Select a resource of type SamlIdentityProvider, identified by its id
+    pub fn saml_identity_provider_id(
+        self,
+        id: Uuid,
+    ) -> SamlIdentityProvider<'a> {
+        SamlIdentityProvider::PrimaryKey(Root { lookup_root: self }, id)
+    }
+
+    /// Select a resource of type ServiceAccount, identified by its name
+    /// within the current Silo
+    pub fn service_account_name<'b, 'c>(
+        self,
+        name: &'b Name,
+    ) -> ServiceAccount<'c>
+    where
+        'a: 'c,
+        'b: 'c,
+    {
+        match self
+            .opctx
+            .authn
+            .silo_required()
+            .internal_context("looking up ServiceAccount by name")
+        {
+            Ok(authz_silo) => {
+                let root = Root { lookup_root: self };
+                let silo_key = Silo::PrimaryKey(root, authz_silo.id());
+                ServiceAccount::Name(silo_key, name)
+            }
+            Err(error) => {
+                let root = Root { lookup_root: self };
+                ServiceAccount::Error(root, error)
+            }
+        }
+    }
+
+    /// Select a resource of type ServiceAccount, identified by its id
+    pub fn service_account_id(self, id: Uuid) -> ServiceAccount<'a> {
+        ServiceAccount::PrimaryKey(Root { lookup_root: self }, id)
+    }
+
     /// Select a resource of type Rack, identified by its id
     pub fn rack_id(self, id: Uuid) -> Rack<'a> {
         Rack::PrimaryKey(Root { lookup_root: self }, id)
@@ -442,7 +483,13 @@ impl<'a> Root<'a> {
 lookup_resource! {
     name = "Silo",
     ancestors = [],
-    children = [ "Organization", "IdentityProvider", "SamlIdentityProvider"],
+    children = [
+        "Organization",
+        "IdentityProvider",
+        "SamlIdentityProvider",
+        "OidcIdentityProvider",
+        "ServiceAccount"
+    ],
     lookup_by_name = true,
     soft_deletes = true,
     primary_key_columns = [ { column_name = "id", rust_type = Uuid } ]
@@ -464,7 +511,8 @@ lookup_resource! {
     children = [],
     lookup_by_name = false,
     soft_deletes = true,
-    primary_key_columns = [ { column_name = "id", rust_type = Uuid } ]
+    primary_key_columns = [ { column_name = "id", rust_type = Uuid } ],
+    visible_outside_silo = true
 }
 
 lookup_resource! {
@@ -500,6 +548,27 @@ lookup_resource! {
     visible_outside_silo = true
 }
 
+lookup_resource! {
+    name = "OidcIdentityProvider",
+    ancestors = [ "Silo" ],
+    children = [],
+    lookup_by_name = true,
+    soft_deletes = true,
+    primary_key_columns = [
+        { column_name = "id", rust_type = Uuid },
+    ],
+    visible_outside_silo = true
+}
+
+lookup_resource! {
+    name = "ServiceAccount",
+    ancestors = [ "Silo" ],
+    children = [],
+    lookup_by_name = true,
+    soft_deletes = true,
+    primary_key_columns = [ { column_name = "id", rust_type = Uuid } ]
+}
+
 lookup_resource! {
     name = "SshKey",
     ancestors = [ "Silo", "SiloUser" ],
@@ -18,6 +18,7 @@ pub mod datastore;
 mod error;
 mod explain;
 pub mod fixed_data;
+mod label_selector;
 pub mod lookup;
 mod pagination;
 mod pool;
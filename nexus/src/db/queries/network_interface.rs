@@ -1554,6 +1554,7 @@ mod tests {
             external_ips: vec![],
             disks: vec![],
             start: true,
+            labels: Default::default(),
         };
         let runtime = InstanceRuntimeState {
             run_state: InstanceState::Creating,
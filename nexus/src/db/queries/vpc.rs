@@ -6,6 +6,7 @@
 
 use crate::db::model::Generation;
 use crate::db::model::IncompleteVpc;
+use crate::db::model::Labels;
 use crate::db::model::Name;
 use crate::db::model::Vni;
 use crate::db::queries::next_item::DefaultShiftGenerator;
@@ -101,6 +102,11 @@ impl QueryFragment<Pg> for InsertVpcQuery {
         out.push_identifier(dsl::time_deleted::NAME)?;
         out.push_sql(", ");
 
+        out.push_bind_param::<sql_types::Jsonb, Labels>(&self.vpc.labels)?;
+        out.push_sql(" AS ");
+        out.push_identifier(dsl::labels::NAME)?;
+        out.push_sql(", ");
+
         out.push_bind_param::<sql_types::Uuid, Uuid>(&self.vpc.project_id)?;
         out.push_sql(" AS ");
         out.push_identifier(dsl::project_id::NAME)?;
@@ -184,6 +190,8 @@ impl QueryFragment<Pg> for InsertVpcQueryValues {
         out.push_sql(", ");
         out.push_identifier(dsl::time_deleted::NAME)?;
         out.push_sql(", ");
+        out.push_identifier(dsl::labels::NAME)?;
+        out.push_sql(", ");
         out.push_identifier(dsl::project_id::NAME)?;
         out.push_sql(", ");
         out.push_identifier(dsl::system_router_id::NAME)?;
@@ -7,9 +7,11 @@
 //! This was originally conceived as a separate dropshot server from the
 //! external API, but in order to avoid CORS issues for now, we are serving
 //! these routes directly from the external API.
+use super::params;
 use super::views;
 use crate::authn::{
-    silos::IdentityProviderType, USER_TEST_PRIVILEGED, USER_TEST_UNPRIVILEGED,
+    silos::{IdentityProviderType, OidcLoginState},
+    USER_TEST_PRIVILEGED, USER_TEST_UNPRIVILEGED,
 };
 use crate::context::OpContext;
 use crate::ServerContext;
@@ -317,6 +319,14 @@ pub async fn login_saml_begin(
 
                 http_response_found(sign_in_url)
             }
+
+            IdentityProviderType::Oidc(_) => Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "identity provider {} is not a SAML provider",
+                    path_params.provider_name
+                ),
+            )),
         }
     };
 
@@ -364,6 +374,16 @@ pub async fn login_saml(
                         nexus.samael_max_issue_delay(),
                     )?
                 }
+
+                IdentityProviderType::Oidc(_) => {
+                    return Err(HttpError::for_bad_request(
+                        None,
+                        format!(
+                            "identity provider {} is not a SAML provider",
+                            path_params.provider_name
+                        ),
+                    ));
+                }
             };
 
         let relay_state: Option<RelayState> =
@@ -440,6 +460,389 @@ pub async fn login_saml(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+// OpenID Connect login uses the authorization code flow with PKCE.  A user
+// starts at
+//
+//   GET /login/{silo_name}/oidc/{provider_name}
+//
+// and is redirected to the provider's authorization endpoint, with a cookie
+// holding the secrets of the login in progress (see `OidcLoginState`).  Once
+// they've logged in there, the provider redirects them back to
+//
+//   GET /login/{silo_name}/oidc/{provider_name}/callback?code=...&state=...
+//
+// where Nexus checks the state against the cookie, redeems the code for an ID
+// token, and creates their session.
+
+/// Name of the cookie that holds an OpenID Connect login in progress
+const OIDC_LOGIN_COOKIE_NAME: &str = "oidc_login";
+
+/// How long a user has to log in to their OpenID Connect provider
+const OIDC_LOGIN_MAX_AGE_SECS: i64 = 600;
+
+/// Generate the cookie holding an OpenID Connect login in progress
+///
+/// The cookie is only sent back to the provider's callback.  SameSite=Lax still
+/// lets it through there, since the provider sends the user back with a
+/// top-level GET.
+fn oidc_login_cookie_header_value(
+    path_params: &LoginToProviderPathParam,
+    value: &str,
+    max_age_secs: i64,
+) -> String {
+    format!(
+        "{}={}; Path=/login/{}/oidc/{}/callback; HttpOnly; SameSite=Lax; \
+        Max-Age={}",
+        OIDC_LOGIN_COOKIE_NAME,
+        value,
+        path_params.silo_name,
+        path_params.provider_name,
+        max_age_secs,
+    )
+}
+
+/// Prompt user login via OpenID Connect
+///
+/// Redirects the user to their identity provider to log in.
+#[endpoint {
+   method = GET,
+   path = "/login/{silo_name}/oidc/{provider_name}",
+   tags = ["login"],
+}]
+pub async fn login_oidc_begin(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<LoginToProviderPathParam>,
+) -> Result<HttpResponseFound, HttpError> {
+    let apictx = rqctx.context();
+    let handler = async {
+        let nexus = &apictx.nexus;
+        let path_params = path_params.into_inner();
+        let request = &rqctx.request.lock().await;
+
+        // Use opctx_external_authn because this request will be
+        // unauthenticated.
+        let opctx = nexus.opctx_external_authn();
+
+        let (.., identity_provider) = IdentityProviderType::lookup(
+            &nexus.datastore(),
+            &opctx,
+            &path_params.silo_name,
+            &path_params.provider_name,
+        )
+        .await?;
+
+        let oidc_identity_provider = match identity_provider {
+            IdentityProviderType::Oidc(oidc_identity_provider) => {
+                oidc_identity_provider
+            }
+            IdentityProviderType::Saml(_) => {
+                return Err(HttpError::for_bad_request(
+                    None,
+                    format!(
+                        "identity provider {} is not an OIDC provider",
+                        path_params.provider_name
+                    ),
+                ));
+            }
+        };
+
+        let referer = match request.headers().get(hyper::header::REFERER) {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .map_err(|e| {
+                        HttpError::for_bad_request(
+                            None,
+                            format!("referer header to_str failed! {}", e),
+                        )
+                    })?
+                    .to_string(),
+            ),
+            None => None,
+        };
+
+        let login_state = OidcLoginState::new(referer);
+        let authorization_url = oidc_identity_provider
+            .authorization_url(&login_state)
+            .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+        let encoded_login_state = login_state.to_encoded().map_err(|e| {
+            HttpError::for_internal_error(format!(
+                "encoding login state failed: {}",
+                e
+            ))
+        })?;
+
+        let mut response_with_headers = http_response_found(authorization_url)?;
+        response_with_headers.headers_mut().append(
+            header::SET_COOKIE,
+            http::HeaderValue::from_str(&oidc_login_cookie_header_value(
+                &path_params,
+                &encoded_login_state,
+                OIDC_LOGIN_MAX_AGE_SECS,
+            ))
+            .map_err(|error| {
+                HttpError::for_internal_error(format!(
+                    "unsupported cookie value: {:#}",
+                    error
+                ))
+            })?,
+        );
+        Ok(response_with_headers)
+    };
+
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Query parameters sent by an OpenID Connect provider to the callback
+#[derive(Deserialize, JsonSchema)]
+pub struct OidcCallbackQuery {
+    /// authorization code, on success
+    pub code: Option<String>,
+    /// state sent to the provider when the login began
+    pub state: Option<String>,
+    /// error code, on failure
+    pub error: Option<String>,
+    /// description of the error, on failure
+    pub error_description: Option<String>,
+}
+
+/// Authenticate a user via OpenID Connect
+///
+/// This is where the identity provider sends the user back after they log in.
+/// On success, the response sets a session cookie, as with SAML login.
+#[endpoint {
+   method = GET,
+   path = "/login/{silo_name}/oidc/{provider_name}/callback",
+   tags = ["login"],
+}]
+pub async fn login_oidc(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<LoginToProviderPathParam>,
+    query_params: Query<OidcCallbackQuery>,
+    cookies: Cookies,
+) -> Result<HttpResponseSeeOther, HttpError> {
+    let apictx = rqctx.context();
+    let handler = async {
+        let nexus = &apictx.nexus;
+        let path_params = path_params.into_inner();
+        let query = query_params.into_inner();
+
+        if let Some(error) = &query.error {
+            return Err(Error::Unauthenticated {
+                internal_message: format!(
+                    "identity provider returned error {}: {}",
+                    error,
+                    query.error_description.as_deref().unwrap_or("")
+                ),
+            }
+            .into());
+        }
+
+        let login_state = cookies
+            .get(OIDC_LOGIN_COOKIE_NAME)
+            .ok_or_else(|| {
+                HttpError::for_bad_request(
+                    None,
+                    String::from("no OIDC login in progress"),
+                )
+            })
+            .and_then(|cookie| {
+                OidcLoginState::from_encoded(cookie.value()).map_err(|e| {
+                    HttpError::for_bad_request(
+                        None,
+                        format!("bad OIDC login cookie: {:#}", e),
+                    )
+                })
+            })?;
+
+        // The state ties this response to the login begun by this browser,
+        // which protects against cross-site request forgery.
+        if query.state.as_deref() != Some(login_state.state.as_str()) {
+            return Err(HttpError::for_bad_request(
+                None,
+                String::from("state does not match OIDC login in progress"),
+            ));
+        }
+
+        let code = query.code.ok_or_else(|| {
+            HttpError::for_bad_request(
+                None,
+                String::from("missing authorization code"),
+            )
+        })?;
+
+        // Use opctx_external_authn because this request will be
+        // unauthenticated.
+        let opctx = nexus.opctx_external_authn();
+
+        let (authz_silo, db_silo, identity_provider) =
+            IdentityProviderType::lookup(
+                &nexus.datastore(),
+                &opctx,
+                &path_params.silo_name,
+                &path_params.provider_name,
+            )
+            .await?;
+
+        let authenticated_subject = match identity_provider {
+            IdentityProviderType::Oidc(oidc_identity_provider) => {
+                oidc_identity_provider
+                    .authenticated_subject(&code, &login_state)
+                    .await?
+            }
+            IdentityProviderType::Saml(_) => {
+                return Err(HttpError::for_bad_request(
+                    None,
+                    format!(
+                        "identity provider {} is not an OIDC provider",
+                        path_params.provider_name
+                    ),
+                ));
+            }
+        };
+
+        let user = nexus
+            .silo_user_from_authenticated_subject(
+                &opctx,
+                &authz_silo,
+                &db_silo,
+                &authenticated_subject,
+            )
+            .await?
+            .ok_or_else(|| Error::Unauthenticated {
+                internal_message: String::from(
+                    "no matching user found or credentials were not valid",
+                ),
+            })?;
+
+        let session = nexus.session_create(&opctx, user.id()).await?;
+
+        debug!(
+            &apictx.log,
+            "successful login to silo {} using provider {}: authenticated \
+            subject {} = user id {}",
+            path_params.silo_name,
+            path_params.provider_name,
+            authenticated_subject.external_id,
+            user.id(),
+        );
+
+        let next_url =
+            login_state.referer.clone().unwrap_or_else(|| "/".to_string());
+        let mut response_with_headers = http_response_see_other(next_url)?;
+
+        {
+            let headers = response_with_headers.headers_mut();
+            for cookie in [
+                session_cookie_header_value(
+                    &session.token,
+                    apictx.session_idle_timeout(),
+                ),
+                // The login is over, so its secrets are no longer needed.
+                oidc_login_cookie_header_value(&path_params, "", 0),
+            ] {
+                headers.append(
+                    header::SET_COOKIE,
+                    http::HeaderValue::from_str(&cookie).map_err(|error| {
+                        HttpError::for_internal_error(format!(
+                            "unsupported cookie value: {:#}",
+                            error
+                        ))
+                    })?,
+                );
+            }
+        }
+        Ok(response_with_headers)
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LoginPathParam {
+    pub silo_name: crate::db::model::Name,
+}
+
+/// Authenticate a user via username and password
+///
+/// This is only available in Silos with identity mode `LocalOnly`.  On
+/// success, the response sets a session cookie, as with SAML login.  Repeated
+/// attempts for the same user are rate limited, and a user who fails to log in
+/// too many times in a row is locked out for a while.
+#[endpoint {
+   method = POST,
+   path = "/login/{silo_name}/local",
+   tags = ["login"],
+}]
+pub async fn login_local(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<LoginPathParam>,
+    credentials: TypedBody<params::UsernamePasswordCredentials>,
+) -> Result<HttpResponseHeaders<HttpResponseUpdatedNoContent>, HttpError> {
+    let apictx = rqctx.context();
+    let handler = async {
+        let nexus = &apictx.nexus;
+        let path_params = path_params.into_inner();
+        let credentials = credentials.into_inner();
+
+        if !nexus.login_local_allowed(
+            &path_params.silo_name,
+            credentials.username.as_ref(),
+        ) {
+            return Err(HttpError::for_client_error(
+                Some(String::from("TooManyRequests")),
+                StatusCode::TOO_MANY_REQUESTS,
+                String::from("too many login attempts; try again later"),
+            ));
+        }
+
+        // Use opctx_external_authn because this request will be
+        // unauthenticated.
+        let opctx = nexus.opctx_external_authn();
+        let user = nexus
+            .login_local(&opctx, &path_params.silo_name, &credentials)
+            .await?;
+
+        let user = match user {
+            Some(user) => user,
+            None => Err(Error::Unauthenticated {
+                internal_message: String::from(
+                    "no matching user found or credentials were not valid",
+                ),
+            })?,
+        };
+
+        let session = nexus.session_create(&opctx, user.id()).await?;
+
+        debug!(
+            &apictx.log,
+            "successful login to silo {} using local password: user id {}",
+            path_params.silo_name,
+            user.id(),
+        );
+
+        let mut response =
+            HttpResponseHeaders::new_unnamed(HttpResponseUpdatedNoContent());
+        {
+            let headers = response.headers_mut();
+            headers.append(
+                header::SET_COOKIE,
+                http::HeaderValue::from_str(&session_cookie_header_value(
+                    &session.token,
+                    apictx.session_idle_timeout(),
+                ))
+                .map_err(|error| {
+                    HttpError::for_internal_error(format!(
+                        "unsupported cookie value: {:#}",
+                        error
+                    ))
+                })?,
+            );
+        };
+        Ok(response)
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // Log user out of web console by deleting session in both server and browser
 #[endpoint {
    // important for security that this be a POST despite the empty req body
@@ -466,9 +869,9 @@ pub async fn logout(
         }
 
         // If user's session was already expired, they failed auth and their
-        // session was automatically deleted by the auth scheme. If they have no
-        // session (e.g., they cleared their cookies while sitting on the page)
-        // they will also fail auth.
+        // session will be deleted by the periodic cleanup of expired sessions.
+        // If they have no session (e.g., they cleared their cookies while
+        // sitting on the page) they will also fail auth.
 
         // Even if the user failed auth, we don't want to send them back a 401
         // like we would for a normal request. They are in fact logged out like
@@ -10,6 +10,7 @@
 //! the client to make other API requests.
 
 use super::console_api::console_index_or_login_redirect;
+use super::shared::TokenScope;
 use super::views::DeviceAccessTokenGrant;
 use crate::context::OpContext;
 use crate::db::model::DeviceAccessToken;
@@ -50,6 +51,11 @@ where
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceAuthRequest {
     pub client_id: Uuid,
+    /// Optional limits on what the granted token may be used for: a
+    /// space-separated list of actions (`read`, `list_children`, `modify`,
+    /// `create_child`, `operate`), optionally with one `organization:<id>` or
+    /// `project:<id>` entry
+    pub scope: Option<String>,
 }
 
 /// Start an OAuth 2.0 Device Authorization Grant
@@ -108,8 +114,24 @@ pub async fn device_auth_request(
             }
         };
 
-        let model =
-            nexus.device_auth_request_create(&opctx, params.client_id).await?;
+        // RFC 6749 §3.3
+        let scope = params.scope.as_deref().map(str::parse::<TokenScope>);
+        let scope = match scope.transpose() {
+            Ok(scope) => scope,
+            Err(e) => {
+                return build_oauth_response(
+                    StatusCode::BAD_REQUEST,
+                    &serde_json::json!({
+                        "error": "invalid_scope",
+                        "error_description": format!("{}", e),
+                    }),
+                )
+            }
+        };
+
+        let model = nexus
+            .device_auth_request_create(&opctx, params.client_id, scope)
+            .await?;
         build_oauth_response(
             StatusCode::OK,
             &model.into_response(rqctx.server.tls, host),
@@ -14,6 +14,8 @@ use super::{
         VpcRouter, VpcSubnet,
     },
 };
+use crate::authn::external::cookies::Cookies;
+use crate::authn::external::session_cookie::SESSION_COOKIE_COOKIE_NAME;
 use crate::authz;
 use crate::context::OpContext;
 use crate::db;
@@ -36,7 +38,12 @@ use dropshot::Query;
 use dropshot::RequestContext;
 use dropshot::ResultsPage;
 use dropshot::TypedBody;
+use dropshot::UntypedBody;
 use dropshot::WhichPage;
+use http::header;
+use http::Response;
+use http::StatusCode;
+use hyper::Body;
 use ipnetwork::IpNetwork;
 use omicron_common::api::external::http_pagination::data_page_params_for;
 use omicron_common::api::external::http_pagination::data_page_params_nameid_id;
@@ -48,9 +55,13 @@ use omicron_common::api::external::http_pagination::PagField;
 use omicron_common::api::external::http_pagination::PaginatedById;
 use omicron_common::api::external::http_pagination::PaginatedByName;
 use omicron_common::api::external::http_pagination::PaginatedByNameOrId;
+use omicron_common::api::external::http_pagination::PaginatedByNameOrIdWithLabels;
+use omicron_common::api::external::http_pagination::PaginatedByNameWithLabels;
 use omicron_common::api::external::http_pagination::ScanById;
 use omicron_common::api::external::http_pagination::ScanByName;
 use omicron_common::api::external::http_pagination::ScanByNameOrId;
+use omicron_common::api::external::http_pagination::ScanByNameOrIdWithLabels;
+use omicron_common::api::external::http_pagination::ScanByNameWithLabels;
 use omicron_common::api::external::http_pagination::ScanParams;
 use omicron_common::api::external::to_list;
 use omicron_common::api::external::DataPageParams;
@@ -101,6 +112,7 @@ pub fn external_api() -> NexusApiDescription {
         api.register(project_view_by_id)?;
         api.register(project_delete)?;
         api.register(project_update)?;
+        api.register(project_move)?;
         api.register(project_policy_view)?;
         api.register(project_policy_update)?;
 
@@ -129,14 +141,25 @@ pub fn external_api() -> NexusApiDescription {
         api.register(disk_create)?;
         api.register(disk_view)?;
         api.register(disk_view_by_id)?;
+        api.register(disk_update)?;
+        api.register(disk_import_blocks_bulk_write)?;
+        api.register(disk_import_blocks_bulk_write_octets)?;
+        api.register(disk_finalize_import)?;
+        api.register(disk_export)?;
         api.register(disk_delete)?;
+        api.register(disk_policy_view)?;
+        api.register(disk_policy_update)?;
         api.register(disk_metrics_list)?;
+        api.register(instance_metrics_list)?;
 
         api.register(instance_list)?;
         api.register(instance_create)?;
         api.register(instance_view)?;
         api.register(instance_view_by_id)?;
+        api.register(instance_update)?;
         api.register(instance_delete)?;
+        api.register(instance_policy_view)?;
+        api.register(instance_policy_update)?;
         api.register(instance_migrate)?;
         api.register(instance_reboot)?;
         api.register(instance_start)?;
@@ -166,6 +189,8 @@ pub fn external_api() -> NexusApiDescription {
         api.register(vpc_view_by_id)?;
         api.register(vpc_update)?;
         api.register(vpc_delete)?;
+        api.register(vpc_policy_view)?;
+        api.register(vpc_policy_update)?;
 
         api.register(vpc_subnet_list)?;
         api.register(vpc_subnet_view)?;
@@ -213,6 +238,7 @@ pub fn external_api() -> NexusApiDescription {
         api.register(system_user_view)?;
 
         api.register(timeseries_schema_get)?;
+        api.register(timeseries_query)?;
 
         api.register(role_list)?;
         api.register(role_view)?;
@@ -221,6 +247,11 @@ pub fn external_api() -> NexusApiDescription {
         api.register(session_sshkey_view)?;
         api.register(session_sshkey_create)?;
         api.register(session_sshkey_delete)?;
+        api.register(session_token_list)?;
+        api.register(session_token_delete)?;
+        api.register(console_session_list)?;
+        api.register(console_session_delete_others)?;
+        api.register(console_session_delete)?;
 
         // Fleet-wide API operations
         api.register(silo_list)?;
@@ -234,9 +265,16 @@ pub fn external_api() -> NexusApiDescription {
 
         api.register(saml_identity_provider_create)?;
         api.register(saml_identity_provider_view)?;
+        api.register(oidc_identity_provider_create)?;
+        api.register(oidc_identity_provider_view)?;
 
         api.register(local_idp_user_create)?;
         api.register(local_idp_user_delete)?;
+        api.register(local_idp_user_set_password)?;
+        api.register(local_idp_group_create)?;
+        api.register(local_idp_group_delete)?;
+        api.register(local_idp_group_member_add)?;
+        api.register(local_idp_group_member_remove)?;
 
         api.register(system_image_list)?;
         api.register(system_image_create)?;
@@ -249,14 +287,27 @@ pub fn external_api() -> NexusApiDescription {
         api.register(user_list)?;
         api.register(silo_users_list)?;
         api.register(silo_user_view)?;
+        api.register(silo_user_tokens_delete)?;
+        api.register(silo_user_sessions_delete)?;
         api.register(group_list)?;
 
+        api.register(service_account_list)?;
+        api.register(service_account_create)?;
+        api.register(service_account_view)?;
+        api.register(service_account_delete)?;
+        api.register(service_account_key_list)?;
+        api.register(service_account_key_create)?;
+        api.register(service_account_key_delete)?;
+
         // Console API operations
         api.register(console_api::login_begin)?;
         api.register(console_api::login_spoof_begin)?;
         api.register(console_api::login_spoof)?;
         api.register(console_api::login_saml_begin)?;
         api.register(console_api::login_saml)?;
+        api.register(console_api::login_oidc_begin)?;
+        api.register(console_api::login_oidc)?;
+        api.register(console_api::login_local)?;
         api.register(console_api::logout)?;
 
         api.register(console_api::session_me)?;
@@ -701,6 +752,66 @@ async fn silo_user_view(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Revoke all of a user's access tokens
+///
+/// Revoke all of the access tokens granted to a user through device
+/// authorization flows, such as when they leave the organization.
+#[endpoint {
+    method = DELETE,
+    path = "/system/silos/{silo_name}/users/id/{user_id}/tokens",
+    tags = ["system"],
+}]
+async fn silo_user_tokens_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<UserPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .silo_user_tokens_revoke(
+                &opctx,
+                &path_params.silo_name,
+                path_params.user_id,
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Revoke all of a user's console sessions
+///
+/// Log a user out of the web console everywhere, such as when their account
+/// may have been compromised.
+#[endpoint {
+    method = DELETE,
+    path = "/system/silos/{silo_name}/users/id/{user_id}/sessions",
+    tags = ["system"],
+}]
+async fn silo_user_sessions_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<UserPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .silo_user_sessions_revoke(
+                &opctx,
+                &path_params.silo_name,
+                path_params.user_id,
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // Silo identity providers
 
 /// List a silo's IDPs
@@ -807,6 +918,75 @@ async fn saml_identity_provider_view(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+// Silo OIDC identity providers
+
+/// Create an OIDC IDP
+#[endpoint {
+    method = POST,
+    path = "/system/silos/{silo_name}/identity-providers/oidc",
+    tags = ["system"],
+}]
+async fn oidc_identity_provider_create(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<SiloPathParam>,
+    new_provider: TypedBody<params::OidcIdentityProviderCreate>,
+) -> Result<HttpResponseCreated<views::OidcIdentityProvider>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let provider = nexus
+            .oidc_identity_provider_create(
+                &opctx,
+                &path_params.into_inner().silo_name,
+                new_provider.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseCreated(provider.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for Silo OIDC identity provider requests
+#[derive(Deserialize, JsonSchema)]
+struct SiloOidcPathParam {
+    /// The silo's unique name.
+    silo_name: Name,
+    /// The OIDC identity provider's name
+    provider_name: Name,
+}
+
+/// Fetch an OIDC IDP
+#[endpoint {
+    method = GET,
+    path = "/system/silos/{silo_name}/identity-providers/oidc/{provider_name}",
+    tags = ["system"],
+}]
+async fn oidc_identity_provider_view(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<SiloOidcPathParam>,
+) -> Result<HttpResponseOk<views::OidcIdentityProvider>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+
+    let path_params = path_params.into_inner();
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let provider = nexus
+            .oidc_identity_provider_fetch(
+                &opctx,
+                &path_params.silo_name,
+                &path_params.provider_name,
+            )
+            .await?;
+
+        Ok(HttpResponseOk(provider.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // TODO: no DELETE for identity providers?
 
 // "Local" Identity Provider
@@ -869,6 +1049,175 @@ async fn local_idp_user_delete(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Set or invalidate a user's password
+///
+/// Passwords can only be updated for users in Silos with identity mode
+/// `LocalOnly`.  Setting a password also unlocks a user who was locked out
+/// after too many failed logins.
+#[endpoint {
+    method = POST,
+    path = "/system/silos/{silo_name}/identity-providers/local/users/{user_id}/set-password",
+    tags = ["system"],
+}]
+async fn local_idp_user_set_password(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<UserPathParam>,
+    update: TypedBody<params::UserPassword>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .local_idp_user_set_password(
+                &opctx,
+                &path_params.silo_name,
+                path_params.user_id,
+                &update.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseUpdatedNoContent())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Create a group
+///
+/// Groups can only be created in Silos with identity mode `LocalOnly`.
+/// Otherwise, groups come from the Silo's external Identity Provider.
+#[endpoint {
+    method = POST,
+    path = "/system/silos/{silo_name}/identity-providers/local/groups",
+    tags = ["system"],
+}]
+async fn local_idp_group_create(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<SiloPathParam>,
+    new_group_params: TypedBody<params::GroupCreate>,
+) -> Result<HttpResponseCreated<Group>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let silo_name = path_params.into_inner().silo_name;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let group = nexus
+            .local_idp_create_group(
+                &opctx,
+                &silo_name,
+                new_group_params.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseCreated(group.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for Silo Group requests
+#[derive(Deserialize, JsonSchema)]
+struct GroupPathParam {
+    /// The silo's unique name.
+    silo_name: Name,
+    /// The group's internal id
+    group_id: Uuid,
+}
+
+/// Delete a group
+///
+/// A group can only be deleted once it has no members.
+#[endpoint {
+    method = DELETE,
+    path = "/system/silos/{silo_name}/identity-providers/local/groups/{group_id}",
+    tags = ["system"],
+}]
+async fn local_idp_group_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<GroupPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .local_idp_delete_group(
+                &opctx,
+                &path_params.silo_name,
+                path_params.group_id,
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for Silo Group membership requests
+#[derive(Deserialize, JsonSchema)]
+struct GroupMemberPathParam {
+    /// The silo's unique name.
+    silo_name: Name,
+    /// The group's internal id
+    group_id: Uuid,
+    /// The member's internal user id
+    user_id: Uuid,
+}
+
+/// Add a user to a group
+#[endpoint {
+    method = PUT,
+    path = "/system/silos/{silo_name}/identity-providers/local/groups/{group_id}/members/{user_id}",
+    tags = ["system"],
+}]
+async fn local_idp_group_member_add(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<GroupMemberPathParam>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .local_idp_group_add_member(
+                &opctx,
+                &path_params.silo_name,
+                path_params.group_id,
+                path_params.user_id,
+            )
+            .await?;
+        Ok(HttpResponseUpdatedNoContent())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Remove a user from a group
+#[endpoint {
+    method = DELETE,
+    path = "/system/silos/{silo_name}/identity-providers/local/groups/{group_id}/members/{user_id}",
+    tags = ["system"],
+}]
+async fn local_idp_group_member_remove(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<GroupMemberPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path_params = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .local_idp_group_remove_member(
+                &opctx,
+                &path_params.silo_name,
+                path_params.group_id,
+                path_params.user_id,
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 /// List organizations
 #[endpoint {
     method = GET,
@@ -877,33 +1226,45 @@ async fn local_idp_user_delete(
 }]
 async fn organization_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByNameOrId>,
+    query_params: Query<PaginatedByNameOrIdWithLabels>,
 ) -> Result<HttpResponseOk<ResultsPage<Organization>>, HttpError> {
     let apictx = rqctx.context();
     let nexus = &apictx.nexus;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
         let query = query_params.into_inner();
-        let params = ScanByNameOrId::from_query(&query)?;
+        let params = ScanByNameOrIdWithLabels::from_query(&query)?;
         let field = pagination_field_for_scan_params(params);
 
         let organizations = match field {
             PagField::Id => {
                 let page_selector = data_page_params_nameid_id(&rqctx, &query)?;
-                nexus.organizations_list_by_id(&opctx, &page_selector).await?
+                nexus
+                    .organizations_list_by_id(
+                        &opctx,
+                        &page_selector,
+                        params.label_selector(),
+                    )
+                    .await?
             }
 
             PagField::Name => {
                 let page_selector =
                     data_page_params_nameid_name(&rqctx, &query)?
                         .map_name(|n| Name::ref_cast(n));
-                nexus.organizations_list_by_name(&opctx, &page_selector).await?
+                nexus
+                    .organizations_list_by_name(
+                        &opctx,
+                        &page_selector,
+                        params.label_selector(),
+                    )
+                    .await?
             }
         }
         .into_iter()
         .map(|p| p.into())
         .collect();
-        Ok(HttpResponseOk(ScanByNameOrId::results_page(
+        Ok(HttpResponseOk(ScanByNameOrIdWithLabels::results_page(
             &query,
             organizations,
             &marker_for_name_or_id,
@@ -987,6 +1348,14 @@ async fn organization_view_by_id(
 }
 
 /// Delete an organization
+///
+/// Without `cascade`, the organization must be empty, and it is deleted
+/// before the response (204 No Content) is sent.
+///
+/// With `cascade`, the response (202 Accepted) is sent as soon as the saga
+/// that deletes the organization and everything in it has started, and its
+/// body is that saga.  The organization is gone once the saga has finished,
+/// so clients can poll it until they get a 404.
 #[endpoint {
     method = DELETE,
     path = "/organizations/{organization_name}",
@@ -995,17 +1364,19 @@ async fn organization_view_by_id(
 async fn organization_delete(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
     path_params: Path<OrganizationPathParam>,
-) -> Result<HttpResponseDeleted, HttpError> {
+    query_params: Query<params::CascadeDelete>,
+) -> Result<Response<Body>, HttpError> {
+    // This isn't instrumented like other handlers, since the latency tracker
+    // needs to know the success status code of the response type.
     let apictx = rqctx.context();
     let nexus = &apictx.nexus;
     let params = path_params.into_inner();
+    let query = query_params.into_inner();
     let organization_name = &params.organization_name;
-    let handler = async {
-        let opctx = OpContext::for_external_api(&rqctx).await?;
-        nexus.organization_delete(&opctx, &organization_name).await?;
-        Ok(HttpResponseDeleted())
-    };
-    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+    let opctx = OpContext::for_external_api(&rqctx).await?;
+    let saga =
+        nexus.organization_delete(&opctx, &organization_name, &query).await?;
+    delete_response(saga)
 }
 
 /// Update an organization
@@ -1106,7 +1477,7 @@ async fn organization_policy_update(
 }]
 async fn project_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByNameOrId>,
+    query_params: Query<PaginatedByNameOrIdWithLabels>,
     path_params: Path<OrganizationPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Project>>, HttpError> {
     let apictx = rqctx.context();
@@ -1117,7 +1488,7 @@ async fn project_list(
 
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
-        let params = ScanByNameOrId::from_query(&query)?;
+        let params = ScanByNameOrIdWithLabels::from_query(&query)?;
         let field = pagination_field_for_scan_params(params);
         let projects = match field {
             PagField::Id => {
@@ -1127,6 +1498,7 @@ async fn project_list(
                         &opctx,
                         &organization_name,
                         &page_selector,
+                        params.label_selector(),
                     )
                     .await?
             }
@@ -1140,6 +1512,7 @@ async fn project_list(
                         &opctx,
                         &organization_name,
                         &page_selector,
+                        params.label_selector(),
                     )
                     .await?
             }
@@ -1147,7 +1520,7 @@ async fn project_list(
         .into_iter()
         .map(|p| p.into())
         .collect();
-        Ok(HttpResponseOk(ScanByNameOrId::results_page(
+        Ok(HttpResponseOk(ScanByNameOrIdWithLabels::results_page(
             &query,
             projects,
             &marker_for_name_or_id,
@@ -1242,6 +1615,14 @@ async fn project_view_by_id(
 }
 
 /// Delete a project
+///
+/// Without `cascade`, the project must be empty, and it is deleted before the
+/// response (204 No Content) is sent.
+///
+/// With `cascade`, the response (202 Accepted) is sent as soon as the saga
+/// that deletes the project and everything in it has started, and its body is
+/// that saga.  The project is gone once the saga has finished, so clients can
+/// poll it until they get a 404.
 #[endpoint {
     method = DELETE,
     path = "/organizations/{organization_name}/projects/{project_name}",
@@ -1250,18 +1631,45 @@ async fn project_view_by_id(
 async fn project_delete(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
     path_params: Path<ProjectPathParam>,
-) -> Result<HttpResponseDeleted, HttpError> {
+    query_params: Query<params::CascadeDelete>,
+) -> Result<Response<Body>, HttpError> {
+    // This isn't instrumented like other handlers, since the latency tracker
+    // needs to know the success status code of the response type.
     let apictx = rqctx.context();
     let nexus = &apictx.nexus;
     let params = path_params.into_inner();
+    let query = query_params.into_inner();
     let organization_name = &params.organization_name;
     let project_name = &params.project_name;
-    let handler = async {
-        let opctx = OpContext::for_external_api(&rqctx).await?;
-        nexus.project_delete(&opctx, &organization_name, &project_name).await?;
-        Ok(HttpResponseDeleted())
+    let opctx = OpContext::for_external_api(&rqctx).await?;
+    let saga = nexus
+        .project_delete(&opctx, &organization_name, &project_name, &query)
+        .await?;
+    delete_response(saga)
+}
+
+/// Builds the response to a delete that may have been done by a saga that is
+/// still running: 204 No Content if it's done, or 202 Accepted, with the saga
+/// as the body, if it isn't
+fn delete_response(saga: Option<Saga>) -> Result<Response<Body>, HttpError> {
+    let response = match saga {
+        None => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())?,
+        Some(saga) => {
+            let body = serde_json::to_string(&saga).map_err(|e| {
+                HttpError::for_internal_error(format!(
+                    "serializing saga: {}",
+                    e
+                ))
+            })?;
+            Response::builder()
+                .status(StatusCode::ACCEPTED)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(body.into())?
+        }
     };
-    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+    Ok(response)
 }
 
 /// Update a project
@@ -1300,6 +1708,37 @@ async fn project_update(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Move a project into another organization
+#[endpoint {
+    method = POST,
+    path = "/organizations/{organization_name}/projects/{project_name}/move",
+    tags = ["projects"],
+}]
+async fn project_move(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ProjectPathParam>,
+    move_params: TypedBody<params::ProjectMove>,
+) -> Result<HttpResponseOk<Project>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let project = nexus
+            .project_move(
+                &opctx,
+                &organization_name,
+                &project_name,
+                &move_params.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseOk(project.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 /// Fetch a project's IAM policy
 #[endpoint {
     method = GET,
@@ -1749,7 +2188,7 @@ async fn ip_pool_service_range_remove(
 }]
 async fn disk_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
     path_params: Path<ProjectPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Disk>>, HttpError> {
     let apictx = rqctx.context();
@@ -1760,6 +2199,7 @@ async fn disk_list(
     let project_name = &path.project_name;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let disks = nexus
             .project_list_disks(
                 &opctx,
@@ -1767,12 +2207,13 @@ async fn disk_list(
                 project_name,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|d| d.into())
             .collect();
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             disks,
             &marker_for_name,
@@ -1870,6 +2311,195 @@ async fn disk_view_by_id(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Update a disk
+///
+/// Disks can only grow.
+#[endpoint {
+    method = PUT,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}",
+    tags = ["disks"],
+}]
+async fn disk_update(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+    updated_disk: TypedBody<params::DiskUpdate>,
+) -> Result<HttpResponseOk<Disk>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let disk_name = &path.disk_name;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let disk = nexus
+            .disk_update(
+                &opctx,
+                &organization_name,
+                &project_name,
+                &disk_name,
+                &updated_disk.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseOk(disk.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Import blocks into a disk
+///
+/// The disk must have been created with the `importing_blocks` source, and
+/// must not have been finalized yet.
+///
+/// Only the simulated sled agent can do this I/O so far, so this endpoint is
+/// left out of the published API until real sleds can too.
+#[endpoint {
+    method = POST,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/bulk-write",
+    tags = ["disks"],
+    unpublished = true,
+}]
+async fn disk_import_blocks_bulk_write(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+    import_params: TypedBody<params::ImportBlocksBulkWrite>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let import_params = import_params.into_inner();
+    let handler = async {
+        let data = base64::decode(&import_params.base64_encoded_data).map_err(
+            |e| {
+                HttpError::for_bad_request(
+                    None,
+                    format!("failed to decode base64 data: {}", e),
+                )
+            },
+        )?;
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .disk_import_blocks_bulk_write(
+                &opctx,
+                &path.organization_name,
+                &path.project_name,
+                &path.disk_name,
+                import_params.offset,
+                data,
+            )
+            .await?;
+        Ok(HttpResponseUpdatedNoContent())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Import raw blocks into a disk
+///
+/// This is the same as the base64-encoded bulk write, but takes the blocks as
+/// the request body.
+///
+/// Only the simulated sled agent can do this I/O so far, so this endpoint is
+/// left out of the published API until real sleds can too.
+#[endpoint {
+    method = POST,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/bulk-write-octets",
+    tags = ["disks"],
+    unpublished = true,
+}]
+async fn disk_import_blocks_bulk_write_octets(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+    query_params: Query<params::ImportBlocksBulkWriteOctets>,
+    body: UntypedBody,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let query = query_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .disk_import_blocks_bulk_write(
+                &opctx,
+                &path.organization_name,
+                &path.project_name,
+                &path.disk_name,
+                query.offset,
+                body.as_bytes().to_vec(),
+            )
+            .await?;
+        Ok(HttpResponseUpdatedNoContent())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Finish importing blocks into a disk
+///
+/// The disk becomes detached, and can then be attached to instances.
+#[endpoint {
+    method = POST,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/finalize",
+    tags = ["disks"],
+}]
+async fn disk_finalize_import(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .disk_finalize_import(
+                &opctx,
+                &path.organization_name,
+                &path.project_name,
+                &path.disk_name,
+            )
+            .await?;
+        Ok(HttpResponseUpdatedNoContent())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Export the contents of a disk
+///
+/// The disk must be detached.  Its contents are streamed as the response body.
+///
+/// Only the simulated sled agent can do this I/O so far, so this endpoint is
+/// left out of the published API until real sleds can too.
+#[endpoint {
+    method = GET,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/export",
+    tags = ["disks"],
+    unpublished = true,
+}]
+async fn disk_export(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+) -> Result<Response<Body>, HttpError> {
+    // This isn't instrumented like other handlers, since the latency tracker
+    // needs to know the success status code of the response type.
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let opctx = OpContext::for_external_api(&rqctx).await?;
+    let (size, body) = nexus
+        .disk_export(
+            &opctx,
+            &path.organization_name,
+            &path.project_name,
+            &path.disk_name,
+        )
+        .await?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, size.to_bytes())
+        .body(body)?)
+}
+
 /// Delete a disk
 #[endpoint {
     method = DELETE,
@@ -1901,6 +2531,76 @@ async fn disk_delete(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Fetch a disk's IAM policy
+#[endpoint {
+    method = GET,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/policy",
+    tags = ["disks"],
+}]
+async fn disk_policy_view(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+) -> Result<HttpResponseOk<shared::Policy<authz::DiskRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let disk_name = &path.disk_name;
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .disk_fetch_policy(
+                &opctx,
+                organization_name,
+                project_name,
+                disk_name,
+            )
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Update a disk's IAM policy
+#[endpoint {
+    method = PUT,
+    path = "/organizations/{organization_name}/projects/{project_name}/disks/{disk_name}/policy",
+    tags = ["disks"],
+}]
+async fn disk_policy_update(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<DiskPathParam>,
+    new_policy: TypedBody<shared::Policy<authz::DiskRole>>,
+) -> Result<HttpResponseOk<shared::Policy<authz::DiskRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let new_policy = new_policy.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let disk_name = &path.disk_name;
+
+    let handler = async {
+        let nasgns = new_policy.role_assignments.len();
+        // This should have been validated during parsing.
+        bail_unless!(nasgns <= shared::MAX_ROLE_ASSIGNMENTS_PER_RESOURCE);
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .disk_update_policy(
+                &opctx,
+                organization_name,
+                project_name,
+                disk_name,
+                &new_policy,
+            )
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 #[derive(Display, Deserialize, JsonSchema)]
 #[display(style = "snake_case")]
 #[serde(rename_all = "snake_case")]
@@ -1960,6 +2660,70 @@ async fn disk_metrics_list(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+#[derive(Display, Deserialize, JsonSchema)]
+#[display(style = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceMetricName {
+    CpuBusy,
+    MemoryUsed,
+    NicPacketsReceived,
+    NicPacketsSent,
+    NicBytesReceived,
+    NicBytesSent,
+}
+
+/// Fetch instance metrics
+#[endpoint {
+    method = GET,
+    path = "/organizations/{organization_name}/projects/{project_name}/instances/{instance_name}/metrics/{metric_name}",
+    tags = ["instances"],
+}]
+async fn instance_metrics_list(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<MetricsPathParam<InstancePathParam, InstanceMetricName>>,
+    query_params: Query<
+        PaginationParams<params::ResourceMetrics, params::ResourceMetrics>,
+    >,
+) -> Result<HttpResponseOk<ResultsPage<oximeter_db::Measurement>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+
+    let path = path_params.into_inner();
+    let organization_name = &path.inner.organization_name;
+    let project_name = &path.inner.project_name;
+    let instance_name = &path.inner.instance_name;
+    let metric_name = path.metric_name;
+
+    let query = query_params.into_inner();
+    let limit = rqctx.page_limit(&query)?;
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+
+        // This ensures the user is authorized on Action::Read for this
+        // instance
+        let instance = nexus
+            .instance_fetch(
+                &opctx,
+                organization_name,
+                project_name,
+                instance_name,
+            )
+            .await?;
+        let result = nexus
+            .select_timeseries(
+                &format!("vm:{}", metric_name),
+                &[&format!("instance_id=={}", instance.id())],
+                query,
+                limit,
+            )
+            .await?;
+
+        Ok(HttpResponseOk(result))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // Instances
 
 /// List instances
@@ -1970,7 +2734,7 @@ async fn disk_metrics_list(
 }]
 async fn instance_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
     path_params: Path<ProjectPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Instance>>, HttpError> {
     let apictx = rqctx.context();
@@ -1981,6 +2745,7 @@ async fn instance_list(
     let project_name = &path.project_name;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let instances = nexus
             .project_list_instances(
                 &opctx,
@@ -1988,12 +2753,13 @@ async fn instance_list(
                 &project_name,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|i| i.into())
             .collect();
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             instances,
             &marker_for_name,
@@ -2102,9 +2868,44 @@ async fn instance_view_by_id(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
-/// Delete an instance
+/// Update an instance
+///
+/// The instance must be stopped.  Changes take effect the next time it starts.
 #[endpoint {
-    method = DELETE,
+    method = PUT,
+    path = "/organizations/{organization_name}/projects/{project_name}/instances/{instance_name}",
+    tags = ["instances"],
+}]
+async fn instance_update(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<InstancePathParam>,
+    updated_instance: TypedBody<params::InstanceUpdate>,
+) -> Result<HttpResponseOk<Instance>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let instance_name = &path.instance_name;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let instance = nexus
+            .instance_update(
+                &opctx,
+                &organization_name,
+                &project_name,
+                &instance_name,
+                &updated_instance.into_inner(),
+            )
+            .await?;
+        Ok(HttpResponseOk(instance.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Delete an instance
+#[endpoint {
+    method = DELETE,
     path = "/organizations/{organization_name}/projects/{project_name}/instances/{instance_name}",
     tags = ["instances"],
 }]
@@ -2133,6 +2934,76 @@ async fn instance_delete(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Fetch an instance's IAM policy
+#[endpoint {
+    method = GET,
+    path = "/organizations/{organization_name}/projects/{project_name}/instances/{instance_name}/policy",
+    tags = ["instances"],
+}]
+async fn instance_policy_view(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<InstancePathParam>,
+) -> Result<HttpResponseOk<shared::Policy<authz::InstanceRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let instance_name = &path.instance_name;
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .instance_fetch_policy(
+                &opctx,
+                organization_name,
+                project_name,
+                instance_name,
+            )
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Update an instance's IAM policy
+#[endpoint {
+    method = PUT,
+    path = "/organizations/{organization_name}/projects/{project_name}/instances/{instance_name}/policy",
+    tags = ["instances"],
+}]
+async fn instance_policy_update(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<InstancePathParam>,
+    new_policy: TypedBody<shared::Policy<authz::InstanceRole>>,
+) -> Result<HttpResponseOk<shared::Policy<authz::InstanceRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let new_policy = new_policy.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let instance_name = &path.instance_name;
+
+    let handler = async {
+        let nasgns = new_policy.role_assignments.len();
+        // This should have been validated during parsing.
+        bail_unless!(nasgns <= shared::MAX_ROLE_ASSIGNMENTS_PER_RESOURCE);
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .instance_update_policy(
+                &opctx,
+                organization_name,
+                project_name,
+                instance_name,
+                &new_policy,
+            )
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // TODO should this be in the public API?
 /// Migrate an instance
 #[endpoint {
@@ -2416,24 +3287,26 @@ async fn instance_disk_detach(
 }]
 async fn system_image_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
 ) -> Result<HttpResponseOk<ResultsPage<GlobalImage>>, HttpError> {
     let apictx = rqctx.context();
     let nexus = &apictx.nexus;
     let query = query_params.into_inner();
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let images = nexus
             .global_images_list(
                 &opctx,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|d| d.into())
             .collect();
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             images,
             &marker_for_name,
@@ -2555,7 +3428,7 @@ async fn system_image_delete(
 }]
 async fn image_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
     path_params: Path<ProjectPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Image>>, HttpError> {
     let apictx = rqctx.context();
@@ -2566,6 +3439,7 @@ async fn image_list(
     let project_name = &path.project_name;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let images = nexus
             .project_list_images(
                 &opctx,
@@ -2573,12 +3447,13 @@ async fn image_list(
                 project_name,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|d| d.into())
             .collect();
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             images,
             &marker_for_name,
@@ -2979,7 +3854,7 @@ async fn instance_external_ip_list(
 }]
 async fn snapshot_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
     path_params: Path<ProjectPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Snapshot>>, HttpError> {
     let apictx = rqctx.context();
@@ -2990,6 +3865,7 @@ async fn snapshot_list(
     let project_name = &path.project_name;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let snapshots = nexus
             .project_list_snapshots(
                 &opctx,
@@ -2997,12 +3873,13 @@ async fn snapshot_list(
                 project_name,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|d| d.into())
             .collect();
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             snapshots,
             &marker_for_name,
@@ -3147,7 +4024,7 @@ async fn snapshot_delete(
 }]
 async fn vpc_list(
     rqctx: Arc<RequestContext<Arc<ServerContext>>>,
-    query_params: Query<PaginatedByName>,
+    query_params: Query<PaginatedByNameWithLabels>,
     path_params: Path<ProjectPathParam>,
 ) -> Result<HttpResponseOk<ResultsPage<Vpc>>, HttpError> {
     let apictx = rqctx.context();
@@ -3158,6 +4035,7 @@ async fn vpc_list(
     let project_name = &path.project_name;
     let handler = async {
         let opctx = OpContext::for_external_api(&rqctx).await?;
+        let scan_params = ScanByNameWithLabels::from_query(&query)?;
         let vpcs = nexus
             .project_list_vpcs(
                 &opctx,
@@ -3165,13 +4043,14 @@ async fn vpc_list(
                 &project_name,
                 &data_page_params_for(&rqctx, &query)?
                     .map_name(|n| Name::ref_cast(n)),
+                scan_params.label_selector(),
             )
             .await?
             .into_iter()
             .map(|p| p.into())
             .collect();
 
-        Ok(HttpResponseOk(ScanByName::results_page(
+        Ok(HttpResponseOk(ScanByNameWithLabels::results_page(
             &query,
             vpcs,
             &marker_for_name,
@@ -3329,6 +4208,71 @@ async fn vpc_delete(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Fetch a VPC's IAM policy
+#[endpoint {
+    method = GET,
+    path = "/organizations/{organization_name}/projects/{project_name}/vpcs/{vpc_name}/policy",
+    tags = ["vpcs"],
+}]
+async fn vpc_policy_view(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<VpcPathParam>,
+) -> Result<HttpResponseOk<shared::Policy<authz::VpcRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let vpc_name = &path.vpc_name;
+
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .vpc_fetch_policy(&opctx, organization_name, project_name, vpc_name)
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Update a VPC's IAM policy
+#[endpoint {
+    method = PUT,
+    path = "/organizations/{organization_name}/projects/{project_name}/vpcs/{vpc_name}/policy",
+    tags = ["vpcs"],
+}]
+async fn vpc_policy_update(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<VpcPathParam>,
+    new_policy: TypedBody<shared::Policy<authz::VpcRole>>,
+) -> Result<HttpResponseOk<shared::Policy<authz::VpcRole>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let new_policy = new_policy.into_inner();
+    let organization_name = &path.organization_name;
+    let project_name = &path.project_name;
+    let vpc_name = &path.vpc_name;
+
+    let handler = async {
+        let nasgns = new_policy.role_assignments.len();
+        // This should have been validated during parsing.
+        bail_unless!(nasgns <= shared::MAX_ROLE_ASSIGNMENTS_PER_RESOURCE);
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let policy = nexus
+            .vpc_update_policy(
+                &opctx,
+                organization_name,
+                project_name,
+                vpc_name,
+                &new_policy,
+            )
+            .await?;
+        Ok(HttpResponseOk(policy))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 /// List subnets
 #[endpoint {
     method = GET,
@@ -4283,6 +5227,220 @@ async fn group_list(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+// Service accounts
+
+/// List service accounts
+///
+/// Lists the service accounts in the current silo.
+#[endpoint {
+    method = GET,
+    path = "/service-accounts",
+    tags = ["silos"],
+}]
+async fn service_account_list(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    query_params: Query<PaginatedByName>,
+) -> Result<HttpResponseOk<ResultsPage<views::ServiceAccount>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let query = query_params.into_inner();
+    let pagparams =
+        data_page_params_for(&rqctx, &query)?.map_name(|n| Name::ref_cast(n));
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let service_accounts = nexus
+            .service_accounts_list(&opctx, &pagparams)
+            .await?
+            .into_iter()
+            .map(|i| i.into())
+            .collect();
+        Ok(HttpResponseOk(ScanByName::results_page(
+            &query,
+            service_accounts,
+            &marker_for_name,
+        )?))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Create a service account
+///
+/// Creates a service account in the current silo.  If a project is given, the
+/// service account can only use that project and the resources within it.
+#[endpoint {
+    method = POST,
+    path = "/service-accounts",
+    tags = ["silos"],
+}]
+async fn service_account_create(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    new_service_account: TypedBody<params::ServiceAccountCreate>,
+) -> Result<HttpResponseCreated<views::ServiceAccount>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let service_account = nexus
+            .service_account_create(&opctx, new_service_account.into_inner())
+            .await?;
+        Ok(HttpResponseCreated(service_account.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for service account requests
+#[derive(Deserialize, JsonSchema)]
+struct ServiceAccountPathParam {
+    service_account_name: Name,
+}
+
+/// Fetch a service account
+#[endpoint {
+    method = GET,
+    path = "/service-accounts/{service_account_name}",
+    tags = ["silos"],
+}]
+async fn service_account_view(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ServiceAccountPathParam>,
+) -> Result<HttpResponseOk<views::ServiceAccount>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let service_account = nexus
+            .service_account_fetch(&opctx, &path.service_account_name)
+            .await?;
+        Ok(HttpResponseOk(service_account.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Delete a service account
+///
+/// Deletes a service account, along with its API keys and role assignments.
+#[endpoint {
+    method = DELETE,
+    path = "/service-accounts/{service_account_name}",
+    tags = ["silos"],
+}]
+async fn service_account_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ServiceAccountPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .service_account_delete(&opctx, &path.service_account_name)
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// List a service account's API keys
+#[endpoint {
+    method = GET,
+    path = "/service-accounts/{service_account_name}/keys",
+    tags = ["silos"],
+}]
+async fn service_account_key_list(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ServiceAccountPathParam>,
+    query_params: Query<PaginatedById>,
+) -> Result<HttpResponseOk<ResultsPage<views::ServiceAccountKey>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let query = query_params.into_inner();
+    let pagparams = data_page_params_for(&rqctx, &query)?;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let keys = nexus
+            .service_account_keys_list(
+                &opctx,
+                &path.service_account_name,
+                &pagparams,
+            )
+            .await?
+            .into_iter()
+            .map(views::ServiceAccountKey::from)
+            .collect();
+        Ok(HttpResponseOk(ScanById::results_page(
+            &query,
+            keys,
+            &|_, key: &views::ServiceAccountKey| key.id,
+        )?))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Create an API key for a service account
+///
+/// The key is only returned in this response.  Use it as a bearer token to
+/// authenticate as the service account.
+#[endpoint {
+    method = POST,
+    path = "/service-accounts/{service_account_name}/keys",
+    tags = ["silos"],
+}]
+async fn service_account_key_create(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ServiceAccountPathParam>,
+) -> Result<HttpResponseCreated<views::ServiceAccountKeyCreated>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let key = nexus
+            .service_account_key_create(&opctx, &path.service_account_name)
+            .await?;
+        Ok(HttpResponseCreated(key.into()))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for service account API key requests
+#[derive(Deserialize, JsonSchema)]
+struct ServiceAccountKeyPathParam {
+    service_account_name: Name,
+    key_id: Uuid,
+}
+
+/// Delete a service account's API key
+///
+/// Requests authenticated with the key fail from then on.
+#[endpoint {
+    method = DELETE,
+    path = "/service-accounts/{service_account_name}/keys/{key_id}",
+    tags = ["silos"],
+}]
+async fn service_account_key_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ServiceAccountKeyPathParam>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        nexus
+            .service_account_key_delete(
+                &opctx,
+                &path.service_account_name,
+                path.key_id,
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // Built-in (system) users
 
 /// List built-in users
@@ -4369,6 +5527,31 @@ async fn timeseries_schema_get(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+/// Query timeseries
+///
+/// Returns the measurements of every timeseries with the given name whose
+/// fields match all of the criteria, and which the caller is allowed to read.
+/// At most 10000 measurements are returned by a single query.
+#[endpoint {
+    method = POST,
+    path = "/timeseries/query",
+    tags = ["metrics"],
+}]
+async fn timeseries_query(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    body: TypedBody<params::TimeseriesQuery>,
+) -> Result<HttpResponseOk<Vec<oximeter_db::Timeseries>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let query = body.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let timeseries = nexus.timeseries_query(&opctx, &query).await?;
+        Ok(HttpResponseOk(timeseries))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 // Built-in roles
 
 // Roles have their own pagination scheme because they do not use the usual "id"
@@ -4585,6 +5768,188 @@ async fn session_sshkey_delete(
     apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
 }
 
+// Per-user access tokens
+
+/// List access tokens
+///
+/// Lists the unexpired access tokens granted to the currently authenticated
+/// user through device authorization flows.
+#[endpoint {
+    method = GET,
+    path = "/session/me/tokens",
+    tags = ["session"],
+}]
+async fn session_token_list(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    query_params: Query<PaginatedById>,
+) -> Result<HttpResponseOk<ResultsPage<views::DeviceAccessToken>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let query = query_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("listing current user's access tokens")?;
+        let pagparams = data_page_params_for(&rqctx, &query)?;
+        let tokens = nexus
+            .device_access_tokens_list(&opctx, actor.actor_id(), &pagparams)
+            .await?
+            .into_iter()
+            .map(views::DeviceAccessToken::from)
+            .collect();
+        Ok(HttpResponseOk(ScanById::results_page(
+            &query,
+            tokens,
+            &|_, token: &views::DeviceAccessToken| token.id,
+        )?))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for access token requests
+#[derive(Deserialize, JsonSchema)]
+struct TokenPathParams {
+    token_id: Uuid,
+}
+
+/// Revoke an access token
+///
+/// Revoke an access token granted to the currently authenticated user.
+#[endpoint {
+    method = DELETE,
+    path = "/session/me/tokens/{token_id}",
+    tags = ["session"],
+}]
+async fn session_token_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<TokenPathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("revoking one of current user's access tokens")?;
+        nexus
+            .device_access_token_revoke(&opctx, actor.actor_id(), path.token_id)
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+// Per-user console sessions
+
+/// List console sessions
+///
+/// Lists the unexpired web console sessions of the currently authenticated
+/// user.
+#[endpoint {
+    method = GET,
+    path = "/session/me/sessions",
+    tags = ["session"],
+}]
+async fn console_session_list(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    query_params: Query<PaginatedById>,
+) -> Result<HttpResponseOk<ResultsPage<views::ConsoleSession>>, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let query = query_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("listing current user's console sessions")?;
+        let pagparams = data_page_params_for(&rqctx, &query)?;
+        let sessions = nexus
+            .sessions_list(&opctx, actor.actor_id(), &pagparams)
+            .await?
+            .into_iter()
+            .map(views::ConsoleSession::from)
+            .collect();
+        Ok(HttpResponseOk(ScanById::results_page(
+            &query,
+            sessions,
+            &|_, session: &views::ConsoleSession| session.id,
+        )?))
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Revoke other console sessions
+///
+/// Revoke all of the currently authenticated user's web console sessions
+/// except the one making the request, logging them out everywhere else.
+#[endpoint {
+    method = DELETE,
+    path = "/session/me/sessions",
+    tags = ["session"],
+}]
+async fn console_session_delete_others(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    cookies: Cookies,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("revoking current user's other sessions")?;
+        let current_token = cookies.get(SESSION_COOKIE_COOKIE_NAME);
+        nexus
+            .sessions_revoke_others(
+                &opctx,
+                actor.actor_id(),
+                current_token.map(|cookie| cookie.value()),
+            )
+            .await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
+/// Path parameters for console session requests
+#[derive(Deserialize, JsonSchema)]
+struct ConsoleSessionPathParams {
+    session_id: Uuid,
+}
+
+/// Revoke a console session
+///
+/// Revoke one of the currently authenticated user's web console sessions.
+#[endpoint {
+    method = DELETE,
+    path = "/session/me/sessions/{session_id}",
+    tags = ["session"],
+}]
+async fn console_session_delete(
+    rqctx: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ConsoleSessionPathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let apictx = rqctx.context();
+    let nexus = &apictx.nexus;
+    let path = path_params.into_inner();
+    let handler = async {
+        let opctx = OpContext::for_external_api(&rqctx).await?;
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("revoking one of current user's sessions")?;
+        nexus.session_revoke(&opctx, actor.actor_id(), path.session_id).await?;
+        Ok(HttpResponseDeleted())
+    };
+    apictx.external_latencies.instrument_dropshot_handler(&rqctx, handler).await
+}
+
 /// Path parameters for metrics requests where `/metrics/{metric_name}` is
 /// appended to an existing path parameter type
 #[derive(Deserialize, JsonSchema)]
@@ -14,12 +14,19 @@ use dropshot::endpoint;
 use dropshot::ApiDescription;
 use dropshot::FreeformBody;
 use dropshot::HttpError;
+use dropshot::HttpResponseDeleted;
 use dropshot::HttpResponseOk;
 use dropshot::HttpResponseUpdatedNoContent;
 use dropshot::Path;
+use dropshot::Query;
 use dropshot::RequestContext;
+use dropshot::ResultsPage;
 use dropshot::TypedBody;
 use hyper::Body;
+use omicron_common::api::external::http_pagination::data_page_params_for;
+use omicron_common::api::external::http_pagination::PaginatedById;
+use omicron_common::api::external::http_pagination::ScanById;
+use omicron_common::api::external::http_pagination::ScanParams;
 use omicron_common::api::internal::nexus::DiskRuntimeState;
 use omicron_common::api::internal::nexus::InstanceRuntimeState;
 use omicron_common::api::internal::nexus::ProducerEndpoint;
@@ -44,7 +51,9 @@ pub fn internal_api() -> NexusApiDescription {
         api.register(cpapi_disks_put)?;
         api.register(cpapi_volume_remove_read_only_parent)?;
         api.register(cpapi_producers_post)?;
+        api.register(cpapi_producers_delete)?;
         api.register(cpapi_collectors_post)?;
+        api.register(cpapi_assigned_producers_list)?;
         api.register(cpapi_metrics_collect)?;
         api.register(cpapi_artifact_download)?;
         Ok(())
@@ -280,6 +289,28 @@ async fn cpapi_producers_post(
         .await
 }
 
+/// Unregister a metric producer, so that it is no longer collected from
+#[endpoint {
+     method = DELETE,
+     path = "/metrics/producers/{producer_id}",
+ }]
+async fn cpapi_producers_delete(
+    request_context: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<ProducerIdPathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let context = request_context.context();
+    let nexus = &context.nexus;
+    let producer_id = path_params.into_inner().producer_id;
+    let handler = async {
+        nexus.unassign_producer(producer_id).await?;
+        Ok(HttpResponseDeleted())
+    };
+    context
+        .internal_latencies
+        .instrument_dropshot_handler(&request_context, handler)
+        .await
+}
+
 /// Accept a notification of a new oximeter collection server.
 #[endpoint {
      method = POST,
@@ -302,6 +333,42 @@ async fn cpapi_collectors_post(
         .await
 }
 
+/// Path parameters for oximeter collector requests (internal API)
+#[derive(Deserialize, JsonSchema)]
+struct CollectorIdPathParams {
+    collector_id: Uuid,
+}
+
+/// List the metric producers assigned to an oximeter collection server.
+#[endpoint {
+     method = GET,
+     path = "/metrics/collectors/{collector_id}/producers",
+ }]
+async fn cpapi_assigned_producers_list(
+    request_context: Arc<RequestContext<Arc<ServerContext>>>,
+    path_params: Path<CollectorIdPathParams>,
+    query_params: Query<PaginatedById>,
+) -> Result<HttpResponseOk<ResultsPage<ProducerEndpoint>>, HttpError> {
+    let context = request_context.context();
+    let nexus = &context.nexus;
+    let collector_id = path_params.into_inner().collector_id;
+    let query = query_params.into_inner();
+    let handler = async {
+        let pagparams = data_page_params_for(&request_context, &query)?;
+        let producers =
+            nexus.oximeter_list_producers(collector_id, &pagparams).await?;
+        Ok(HttpResponseOk(ScanById::results_page(
+            &query,
+            producers,
+            &|_, producer: &ProducerEndpoint| producer.id,
+        )?))
+    };
+    context
+        .internal_latencies
+        .instrument_dropshot_handler(&request_context, handler)
+        .await
+}
+
 /// Endpoint for oximeter to collect nexus server metrics.
 #[endpoint {
     method = GET,
@@ -138,6 +138,7 @@ pub async fn create_organization(
                 name: organization_name.parse().unwrap(),
                 description: "an org".to_string(),
             },
+            labels: Default::default(),
         },
     )
     .await
@@ -157,6 +158,7 @@ pub async fn create_project(
                 name: project_name.parse().unwrap(),
                 description: "a pier".to_string(),
             },
+            labels: Default::default(),
         },
     )
     .await
@@ -184,6 +186,7 @@ pub async fn create_disk(
                 block_size: params::BlockSize::try_from(512).unwrap(),
             },
             size: ByteCount::from_gibibytes_u32(1),
+            labels: Default::default(),
         },
     )
     .await
@@ -241,6 +244,7 @@ pub async fn create_instance_with(
             external_ips: vec![],
             disks,
             start: true,
+            labels: Default::default(),
         },
     )
     .await
@@ -266,6 +270,7 @@ pub async fn create_vpc(
             },
             ipv6_prefix: None,
             dns_name: "abc".parse().unwrap(),
+            labels: Default::default(),
         },
     )
     .await
@@ -297,6 +302,7 @@ pub async fn create_vpc_with_error(
             },
             ipv6_prefix: None,
             dns_name: "abc".parse().unwrap(),
+            labels: Default::default(),
         }))
         .expect_status(Some(status)),
     )
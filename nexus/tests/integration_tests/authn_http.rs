@@ -365,12 +365,6 @@ impl session_cookie::SessionStore for WhoamiServerState {
         (*sessions).insert(token, new_session)
     }
 
-    async fn session_expire(&self, token: String) -> Option<()> {
-        let mut sessions = self.sessions.lock().unwrap();
-        (*sessions).remove(&token);
-        Some(())
-    }
-
     fn session_idle_timeout(&self) -> Duration {
         Duration::hours(1)
     }
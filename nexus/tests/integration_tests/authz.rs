@@ -176,6 +176,7 @@ async fn test_global_image_read_for_unpriv(
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     NexusRequest::objects_post(client, "/system/images", &image_create_params)
@@ -184,6 +184,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
                             "<auto-generated by test suite>",
                         ),
                     },
+                    labels: Default::default(),
                 },
             )
             .authn_as(AuthnMode::PrivilegedUser)
@@ -276,6 +277,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
                 name: None,
                 description: None,
             },
+            labels: None,
         }))
         .expect_status(Some(StatusCode::NOT_FOUND)),
     )
@@ -318,6 +320,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
             name: None,
             description: Some("Li'l lightnin'".to_string()),
         },
+        labels: None,
     };
     let project = NexusRequest::object_put(
         client,
@@ -350,6 +353,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
             name: Some("lil-lightnin".parse().unwrap()),
             description: Some("little lightning".to_string()),
         },
+        labels: None,
     };
     let project = NexusRequest::object_put(
         client,
@@ -383,6 +387,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
             name: "simproject1".parse().unwrap(),
             description: "a duplicate of simproject1".to_string(),
         },
+        labels: Default::default(),
     };
     let error = NexusRequest::new(
         RequestBuilder::new(client, Method::POST, &projects_url)
@@ -431,6 +436,7 @@ async fn test_projects_basic(cptestctx: &ControlPlaneTestContext) {
             name: "honor-roller".parse().unwrap(),
             description: "a soapbox racer".to_string(),
         },
+        labels: Default::default(),
     };
     let project: Project =
         NexusRequest::objects_post(client, projects_url, &project_create)
@@ -2,11 +2,13 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use chrono::{Duration, Utc};
 use dropshot::test_util::ClientTestContext;
 use dropshot::ResultsPage;
 use http::header::HeaderName;
 use http::{header, method::Method, StatusCode};
 use std::env::current_dir;
+use uuid::Uuid;
 
 use nexus_test_utils::http_testing::{
     AuthnMode, NexusRequest, RequestBuilder, TestResponse,
@@ -21,6 +23,7 @@ use omicron_nexus::authn::{USER_TEST_PRIVILEGED, USER_TEST_UNPRIVILEGED};
 use omicron_nexus::authz::SiloRole;
 use omicron_nexus::db::fixed_data::silo::DEFAULT_SILO;
 use omicron_nexus::db::identity::{Asset, Resource};
+use omicron_nexus::db::model::ConsoleSession;
 use omicron_nexus::external_api::console_api::SpoofLoginBody;
 use omicron_nexus::external_api::params::OrganizationCreate;
 use omicron_nexus::external_api::{shared, views};
@@ -48,6 +51,7 @@ async fn test_sessions(cptestctx: &ControlPlaneTestContext) {
             name: "my-org".parse().unwrap(),
             description: "an org".to_string(),
         },
+        labels: Default::default(),
     };
 
     // hitting auth-gated API endpoint without session cookie 401s
@@ -142,6 +146,177 @@ async fn test_sessions(cptestctx: &ControlPlaneTestContext) {
         .expect("failed to get 302 for unauthed console request");
 }
 
+/// List the sessions of the user logged in with `session_token`
+async fn sessions_list(
+    testctx: &ClientTestContext,
+    session_token: &str,
+) -> Vec<views::ConsoleSession> {
+    RequestBuilder::new(&testctx, Method::GET, "/session/me/sessions")
+        .header(header::COOKIE, session_token)
+        .expect_status(Some(StatusCode::OK))
+        .execute()
+        .await
+        .expect("failed to list sessions")
+        .parsed_body::<ResultsPage<views::ConsoleSession>>()
+        .unwrap()
+        .items
+}
+
+/// Log in, returning the new session's token and id
+async fn log_in_and_find_session(
+    testctx: &ClientTestContext,
+) -> (String, Uuid) {
+    let session_token = log_in_and_extract_token(&testctx).await;
+    let session = sessions_list(testctx, &session_token)
+        .await
+        .into_iter()
+        .max_by_key(|session| session.time_created)
+        .unwrap();
+    (session_token, session.id)
+}
+
+/// Make a request for the current user with a session cookie, expecting
+/// `status`
+async fn session_me(
+    testctx: &ClientTestContext,
+    session_token: &str,
+    status: StatusCode,
+) {
+    RequestBuilder::new(&testctx, Method::GET, "/session/me")
+        .header(header::COOKIE, session_token)
+        .expect_status(Some(status))
+        .execute()
+        .await
+        .expect("unexpected response for /session/me");
+}
+
+#[nexus_test]
+async fn test_session_list_and_revoke(cptestctx: &ControlPlaneTestContext) {
+    let testctx = &cptestctx.external_client;
+
+    let (session1, session1_id) = log_in_and_find_session(testctx).await;
+    let (session2, session2_id) = log_in_and_find_session(testctx).await;
+    assert_ne!(session1_id, session2_id);
+    let mut ids: Vec<_> = sessions_list(testctx, &session1)
+        .await
+        .into_iter()
+        .map(|session| session.id)
+        .collect();
+    ids.sort();
+    let mut expected_ids = vec![session1_id, session2_id];
+    expected_ids.sort();
+    assert_eq!(ids, expected_ids);
+
+    // Revoking a session logs it out, but not the others.
+    let session2_url = format!("/session/me/sessions/{}", session2_id);
+    RequestBuilder::new(&testctx, Method::DELETE, &session2_url)
+        .header(header::COOKIE, &session1)
+        .expect_status(Some(StatusCode::NO_CONTENT))
+        .execute()
+        .await
+        .expect("failed to revoke session");
+    session_me(testctx, &session2, StatusCode::UNAUTHORIZED).await;
+    session_me(testctx, &session1, StatusCode::OK).await;
+    RequestBuilder::new(&testctx, Method::DELETE, &session2_url)
+        .header(header::COOKIE, &session1)
+        .expect_status(Some(StatusCode::NOT_FOUND))
+        .execute()
+        .await
+        .expect("revoked session twice");
+
+    // Other users can't revoke the session.
+    NexusRequest::expect_failure(
+        testctx,
+        StatusCode::NOT_FOUND,
+        Method::DELETE,
+        &format!("/session/me/sessions/{}", session1_id),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("revoked another user's session");
+    session_me(testctx, &session1, StatusCode::OK).await;
+
+    // Revoking the other sessions leaves only the current one.
+    let (session3, _) = log_in_and_find_session(testctx).await;
+    let (session4, _) = log_in_and_find_session(testctx).await;
+    RequestBuilder::new(&testctx, Method::DELETE, "/session/me/sessions")
+        .header(header::COOKIE, &session1)
+        .expect_status(Some(StatusCode::NO_CONTENT))
+        .execute()
+        .await
+        .expect("failed to revoke other sessions");
+    session_me(testctx, &session1, StatusCode::OK).await;
+    session_me(testctx, &session3, StatusCode::UNAUTHORIZED).await;
+    session_me(testctx, &session4, StatusCode::UNAUTHORIZED).await;
+    let sessions = sessions_list(testctx, &session1).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session1_id);
+
+    // Silo administrators can log a user out everywhere.
+    let user_sessions_url = format!(
+        "/system/silos/{}/users/id/{}/sessions",
+        DEFAULT_SILO.identity().name,
+        USER_TEST_UNPRIVILEGED.id()
+    );
+    NexusRequest::object_delete(testctx, &user_sessions_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to revoke user's sessions");
+    session_me(testctx, &session1, StatusCode::UNAUTHORIZED).await;
+}
+
+#[nexus_test]
+async fn test_session_cleanup(cptestctx: &ControlPlaneTestContext) {
+    let testctx = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    let opctx = nexus.opctx_external_authn();
+
+    // Make sessions that have expired in each way, as though their users
+    // never came back.
+    let idle = ConsoleSession {
+        token: "a".repeat(40),
+        id: Uuid::new_v4(),
+        time_created: Utc::now() - Duration::hours(2),
+        time_last_used: Utc::now() - Duration::hours(2),
+        silo_user_id: USER_TEST_UNPRIVILEGED.id(),
+    };
+    let old = ConsoleSession {
+        token: "b".repeat(40),
+        id: Uuid::new_v4(),
+        time_created: Utc::now() - Duration::days(2),
+        time_last_used: Utc::now(),
+        silo_user_id: USER_TEST_UNPRIVILEGED.id(),
+    };
+    for session in [idle.clone(), old.clone()] {
+        nexus
+            .datastore()
+            .session_create(opctx, session)
+            .await
+            .expect("failed to create session");
+    }
+    let (session_token, session_id) = log_in_and_find_session(testctx).await;
+
+    // Expired sessions aren't listed or usable, but they aren't deleted until
+    // the periodic cleanup runs.
+    let sessions = sessions_list(testctx, &session_token).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, session_id);
+    let expired_token = format!("session={}", idle.token);
+    session_me(testctx, &expired_token, StatusCode::UNAUTHORIZED).await;
+    assert!(nexus.session_fetch(opctx, idle.token.clone()).await.is_ok());
+
+    let ndeleted = nexus
+        .sessions_delete_expired(opctx)
+        .await
+        .expect("failed to delete expired sessions");
+    assert_eq!(ndeleted, 2);
+    assert!(nexus.session_fetch(opctx, idle.token).await.is_err());
+    assert!(nexus.session_fetch(opctx, old.token).await.is_err());
+    session_me(testctx, &session_token, StatusCode::OK).await;
+}
+
 #[nexus_test]
 async fn test_console_pages(cptestctx: &ControlPlaneTestContext) {
     let testctx = &cptestctx.external_client;
@@ -2,17 +2,26 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use dropshot::test_util::ClientTestContext;
+use dropshot::ResultsPage;
 use nexus_test_utils::http_testing::{AuthnMode, NexusRequest, RequestBuilder};
-use nexus_test_utils::ControlPlaneTestContext;
+use nexus_test_utils::{
+    load_test_config, test_setup_with_config, ControlPlaneTestContext,
+};
 use nexus_test_utils_macros::nexus_test;
+use omicron_nexus::authn::{USER_TEST_PRIVILEGED, USER_TEST_UNPRIVILEGED};
+use omicron_nexus::db::fixed_data::silo::DEFAULT_SILO;
 use omicron_nexus::external_api::device_auth::{
     DeviceAccessTokenRequest, DeviceAuthRequest, DeviceAuthVerify,
 };
+use omicron_nexus::external_api::shared::{TokenScope, TokenScopeAction};
 use omicron_nexus::external_api::views::{
-    DeviceAccessTokenGrant, DeviceAccessTokenType, DeviceAuthResponse,
+    DeviceAccessToken, DeviceAccessTokenGrant, DeviceAccessTokenType,
+    DeviceAuthResponse,
 };
 
 use http::{header, method::Method, StatusCode};
+use omicron_common::api::external::ObjectIdentity;
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -35,7 +44,7 @@ async fn test_device_auth_flow(cptestctx: &ControlPlaneTestContext) {
         .expect("failed to reject device auth start without client_id");
 
     let client_id = Uuid::new_v4();
-    let authn_params = DeviceAuthRequest { client_id };
+    let authn_params = DeviceAuthRequest { client_id, scope: None };
 
     // Using a JSON encoded body fails.
     RequestBuilder::new(testctx, Method::POST, "/device/auth")
@@ -152,4 +161,271 @@ async fn test_device_auth_flow(cptestctx: &ControlPlaneTestContext) {
     assert_eq!(token.token_type, DeviceAccessTokenType::Bearer);
     assert_eq!(token.access_token.len(), 52);
     assert!(token.access_token.starts_with("oxide-token-"));
+
+    // The token authenticates its user.
+    session_me_with_token(testctx, &token.access_token, StatusCode::OK).await;
+}
+
+/// Run a device authorization flow to completion as the unprivileged user,
+/// requesting a token limited to `scope` (if given) and returning the granted
+/// token.
+async fn device_token_grant(
+    testctx: &ClientTestContext,
+    scope: Option<&str>,
+) -> DeviceAccessTokenGrant {
+    let client_id = Uuid::new_v4();
+    let auth_response: DeviceAuthResponse =
+        RequestBuilder::new(testctx, Method::POST, "/device/auth")
+            .allow_non_dropshot_errors()
+            .body_urlencoded(Some(&DeviceAuthRequest {
+                client_id,
+                scope: scope.map(str::to_string),
+            }))
+            .expect_status(Some(StatusCode::OK))
+            .execute()
+            .await
+            .expect("failed to start client authentication flow")
+            .parsed_body()
+            .expect("client authentication response");
+
+    NexusRequest::new(
+        RequestBuilder::new(testctx, Method::POST, "/device/confirm")
+            .body(Some(&DeviceAuthVerify {
+                user_code: auth_response.user_code,
+            }))
+            .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .expect("failed to confirm");
+
+    RequestBuilder::new(testctx, Method::POST, "/device/token")
+        .allow_non_dropshot_errors()
+        .body_urlencoded(Some(&DeviceAccessTokenRequest {
+            grant_type: "urn:ietf:params:oauth:grant-type:device_code"
+                .to_string(),
+            device_code: auth_response.device_code,
+            client_id,
+        }))
+        .expect_status(Some(StatusCode::OK))
+        .execute()
+        .await
+        .expect("failed to get token")
+        .parsed_body()
+        .expect("failed to deserialize token response")
+}
+
+/// Fetch the current user using `access_token`, expecting `status`.
+async fn session_me_with_token(
+    testctx: &ClientTestContext,
+    access_token: &str,
+    status: StatusCode,
+) {
+    RequestBuilder::new(testctx, Method::GET, "/session/me")
+        .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+        .expect_status(Some(status))
+        .execute()
+        .await
+        .expect("unexpected response using access token");
+}
+
+async fn tokens_list(
+    testctx: &ClientTestContext,
+    authn_mode: AuthnMode,
+) -> Vec<DeviceAccessToken> {
+    NexusRequest::object_get(testctx, "/session/me/tokens")
+        .authn_as(authn_mode)
+        .execute()
+        .await
+        .expect("failed to list tokens")
+        .parsed_body::<ResultsPage<DeviceAccessToken>>()
+        .expect("failed to parse list of tokens")
+        .items
+}
+
+#[nexus_test]
+async fn test_device_token_list_and_revoke(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let testctx = &cptestctx.external_client;
+    let token = device_token_grant(testctx, None).await;
+    session_me_with_token(testctx, &token.access_token, StatusCode::OK).await;
+
+    // The token is listed for its user, with the configured lifetime, but
+    // not for anyone else.
+    let tokens = tokens_list(testctx, AuthnMode::UnprivilegedUser).await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(
+        tokens[0].time_expires,
+        Some(tokens[0].time_created + chrono::Duration::minutes(60))
+    );
+    assert!(tokens_list(testctx, AuthnMode::PrivilegedUser).await.is_empty());
+
+    // Other users can't revoke it.
+    let token_url = format!("/session/me/tokens/{}", tokens[0].id);
+    NexusRequest::expect_failure(
+        testctx,
+        StatusCode::NOT_FOUND,
+        Method::DELETE,
+        &token_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to 404 on revoking another user's token");
+    session_me_with_token(testctx, &token.access_token, StatusCode::OK).await;
+
+    // Once its user revokes it, it's neither listed nor accepted, and it
+    // can't be revoked again.
+    NexusRequest::object_delete(testctx, &token_url)
+        .authn_as(AuthnMode::UnprivilegedUser)
+        .execute()
+        .await
+        .expect("failed to revoke token");
+    assert!(tokens_list(testctx, AuthnMode::UnprivilegedUser).await.is_empty());
+    session_me_with_token(
+        testctx,
+        &token.access_token,
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+    NexusRequest::expect_failure(
+        testctx,
+        StatusCode::NOT_FOUND,
+        Method::DELETE,
+        &token_url,
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .expect("failed to 404 on revoking a revoked token");
+}
+
+#[nexus_test]
+async fn test_device_token_revoke_all(cptestctx: &ControlPlaneTestContext) {
+    let testctx = &cptestctx.external_client;
+    let token1 = device_token_grant(testctx, None).await;
+    let token2 = device_token_grant(testctx, None).await;
+    assert_eq!(
+        tokens_list(testctx, AuthnMode::UnprivilegedUser).await.len(),
+        2
+    );
+
+    // Only silo administrators can revoke other users' tokens.
+    let revoke_url = |user_id| {
+        format!(
+            "/system/silos/{}/users/id/{}/tokens",
+            DEFAULT_SILO.identity().name,
+            user_id
+        )
+    };
+    NexusRequest::expect_failure(
+        testctx,
+        StatusCode::FORBIDDEN,
+        Method::DELETE,
+        &revoke_url(USER_TEST_PRIVILEGED.id()),
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .expect("failed to 403 on revoking tokens without privileges");
+    let revoke_url = revoke_url(USER_TEST_UNPRIVILEGED.id());
+    NexusRequest::object_delete(testctx, &revoke_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to revoke tokens");
+
+    assert!(tokens_list(testctx, AuthnMode::UnprivilegedUser).await.is_empty());
+    for token in [token1, token2] {
+        session_me_with_token(
+            testctx,
+            &token.access_token,
+            StatusCode::UNAUTHORIZED,
+        )
+        .await;
+    }
+}
+
+#[nexus_test]
+async fn test_device_token_scope(cptestctx: &ControlPlaneTestContext) {
+    let testctx = &cptestctx.external_client;
+
+    // A malformed scope is rejected with an OAuth error.
+    let error: OAuthError =
+        RequestBuilder::new(testctx, Method::POST, "/device/auth")
+            .allow_non_dropshot_errors()
+            .body_urlencoded(Some(&DeviceAuthRequest {
+                client_id: Uuid::new_v4(),
+                scope: Some(String::from("read destroy")),
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST))
+            .execute()
+            .await
+            .expect("failed to reject invalid scope")
+            .parsed_body()
+            .expect("failed to deserialize OAuth error");
+    assert_eq!(&error.error, "invalid_scope");
+
+    // A scoped token reports its scope and can be used within it.
+    let token = device_token_grant(testctx, Some("list_children read")).await;
+    let tokens = tokens_list(testctx, AuthnMode::UnprivilegedUser).await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(
+        tokens[0].scope,
+        Some(TokenScope {
+            actions: vec![
+                TokenScopeAction::Read,
+                TokenScopeAction::ListChildren
+            ],
+            organization_id: None,
+            project_id: None,
+        })
+    );
+    session_me_with_token(testctx, &token.access_token, StatusCode::OK).await;
+
+    // It can't be used to confirm another device authorization request, since
+    // that would grant a token without its limits.
+    let auth_response: DeviceAuthResponse =
+        RequestBuilder::new(testctx, Method::POST, "/device/auth")
+            .allow_non_dropshot_errors()
+            .body_urlencoded(Some(&DeviceAuthRequest {
+                client_id: Uuid::new_v4(),
+                scope: None,
+            }))
+            .expect_status(Some(StatusCode::OK))
+            .execute()
+            .await
+            .expect("failed to start client authentication flow")
+            .parsed_body()
+            .expect("client authentication response");
+    RequestBuilder::new(testctx, Method::POST, "/device/confirm")
+        .header(header::AUTHORIZATION, format!("Bearer {}", token.access_token))
+        .body(Some(&DeviceAuthVerify { user_code: auth_response.user_code }))
+        .expect_status(Some(StatusCode::FORBIDDEN))
+        .execute()
+        .await
+        .expect("failed to 403 on confirmation with a scoped token");
+}
+
+#[tokio::test]
+async fn test_device_token_expiry() {
+    let mut config = load_test_config();
+    config.pkg.authn.device_token_ttl_minutes = 0;
+    let cptestctx =
+        test_setup_with_config("test_device_token_expiry", &mut config).await;
+    let testctx = &cptestctx.external_client;
+
+    // A token that has expired is neither listed nor accepted.
+    let token = device_token_grant(testctx, None).await;
+    assert!(tokens_list(testctx, AuthnMode::UnprivilegedUser).await.is_empty());
+    session_me_with_token(
+        testctx,
+        &token.access_token,
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+
+    cptestctx.teardown().await;
 }
@@ -11,6 +11,7 @@ use dropshot::HttpErrorResponseBody;
 use dropshot::ResultsPage;
 use http::method::Method;
 use http::StatusCode;
+use hyper::body::HttpBody;
 use nexus_test_utils::http_testing::AuthnMode;
 use nexus_test_utils::http_testing::Collection;
 use nexus_test_utils::http_testing::NexusRequest;
@@ -33,11 +34,20 @@ use omicron_common::api::external::IdentityMetadataCreateParams;
 use omicron_common::api::external::Instance;
 use omicron_common::api::external::Name;
 use omicron_common::backoff;
+use omicron_nexus::authn;
+use omicron_nexus::authn::external::spoof;
+use omicron_nexus::authz;
+use omicron_nexus::context::OpContext;
+use omicron_nexus::db;
+use omicron_nexus::db::identity::Asset;
+use omicron_nexus::db::lookup::LookupPath;
 use omicron_nexus::TestInterfaces as _;
 use omicron_nexus::{external_api::params, Nexus};
 use oximeter::types::Datum;
 use oximeter::types::Measurement;
+use sled_agent_client::types::VolumeConstructionRequest;
 use sled_agent_client::TestInterfaces as _;
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -301,6 +311,7 @@ async fn test_disk_create_disk_that_already_exists_fails(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: ByteCount::from_gibibytes_u32(1),
+        labels: Default::default(),
     };
     let _ = create_disk(&client, ORG_NAME, PROJECT_NAME, DISK_NAME).await;
     let disk_url = format!("{}/{}", disks_url, DISK_NAME);
@@ -593,6 +604,7 @@ async fn test_disk_region_creation_failure(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     // Unfortunately, the error message is only posted internally to the
@@ -673,6 +685,7 @@ async fn test_disk_invalid_block_size_rejected(
             block_size: params::BlockSize(1024),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -716,6 +729,7 @@ async fn test_disk_reject_total_size_not_divisible_by_block_size(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -750,6 +764,7 @@ async fn test_disk_reject_total_size_less_than_one_gibibyte(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -794,6 +809,7 @@ async fn test_disk_reject_total_size_not_divisible_by_min_disk_size(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -848,6 +864,7 @@ async fn test_disk_backed_by_multiple_region_sets(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -884,6 +901,7 @@ async fn test_disk_too_big(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -938,6 +956,7 @@ async fn test_disk_size_accounting(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -976,6 +995,7 @@ async fn test_disk_size_accounting(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -1036,6 +1056,7 @@ async fn test_disk_size_accounting(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -1094,6 +1115,7 @@ async fn test_multiple_disks_multiple_zpools(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -1117,6 +1139,7 @@ async fn test_multiple_disks_multiple_zpools(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -1270,6 +1293,521 @@ async fn test_disk_metrics_paginated(cptestctx: &ControlPlaneTestContext) {
     }
 }
 
+#[nexus_test]
+async fn test_disk_resize(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_org_and_project(client).await;
+    let disk_url = format!("{}/{}", get_disks_url(), DISK_NAME);
+
+    let disk = create_disk(client, ORG_NAME, PROJECT_NAME, DISK_NAME).await;
+    assert_eq!(
+        disk.size.to_bytes(),
+        ByteCount::from_gibibytes_u32(1).to_bytes()
+    );
+
+    // Grow the (detached) disk.
+    let grown: Disk = NexusRequest::object_put(
+        client,
+        &disk_url,
+        Some(&params::DiskUpdate {
+            size: Some(ByteCount::from_gibibytes_u32(2)),
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(
+        grown.size.to_bytes(),
+        ByteCount::from_gibibytes_u32(2).to_bytes()
+    );
+    assert_eq!(grown.state, DiskState::Detached);
+    let fetched = disk_get(client, &disk_url).await;
+    disks_eq(&grown, &fetched);
+
+    // Asking for the current size again is a no-op.
+    let unchanged: Disk = NexusRequest::object_put(
+        client,
+        &disk_url,
+        Some(&params::DiskUpdate {
+            size: Some(ByteCount::from_gibibytes_u32(2)),
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    disks_eq(&grown, &unchanged);
+
+    // Disks can't shrink.
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &disk_url)
+            .body(Some(&params::DiskUpdate {
+                size: Some(ByteCount::from_gibibytes_u32(1)),
+                labels: None,
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<HttpErrorResponseBody>()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        format!(
+            "unsupported value for \"size\": disks can only grow: total size \
+            must be at least {}",
+            ByteCount::from_gibibytes_u32(2)
+        )
+    );
+
+    // The new size must still be a multiple of MIN_DISK_SIZE_BYTES.
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &disk_url)
+            .body(Some(&params::DiskUpdate {
+                size: Some(ByteCount::from(2 * 1024 * 1024 * 1024 + 512)),
+                labels: None,
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<HttpErrorResponseBody>()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        format!(
+            "unsupported value for \"size\": total size must be a multiple \
+            of {}",
+            ByteCount::from(params::MIN_DISK_SIZE_BYTES)
+        )
+    );
+
+    // Growing past what the zpools can hold fails, and leaves the disk as it
+    // was.
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &disk_url)
+            .body(Some(&params::DiskUpdate {
+                size: Some(ByteCount::from_gibibytes_u32(300)),
+                labels: None,
+            }))
+            .expect_status(Some(StatusCode::SERVICE_UNAVAILABLE)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    let fetched = disk_get(client, &disk_url).await;
+    disks_eq(&grown, &fetched);
+}
+
+#[nexus_test]
+async fn test_disk_resize_attached(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    let datastore = nexus.datastore();
+    DiskTest::new(&cptestctx).await;
+    create_org_and_project(client).await;
+    let disk_url = format!("{}/{}", get_disks_url(), DISK_NAME);
+    let instance_url = format!("{}/{}", get_instances_url(), INSTANCE_NAME);
+
+    let disk = create_disk(client, ORG_NAME, PROJECT_NAME, DISK_NAME).await;
+
+    // Attach the disk to a stopped instance, then start the instance.
+    let instance =
+        create_instance(&client, ORG_NAME, PROJECT_NAME, INSTANCE_NAME).await;
+    let instance_id = instance.identity.id;
+    set_instance_state(&client, &instance_url, "stop").await;
+    instance_simulate(nexus, &instance_id).await;
+    disk_post(client, &get_disk_attach_url(INSTANCE_NAME), disk.identity.name)
+        .await;
+    set_instance_state(&client, &instance_url, "start").await;
+    instance_simulate(nexus, &instance_id).await;
+
+    // The disk grows while the instance is running, and the instance's sled
+    // agent is given the grown volume.
+    let grown: Disk = NexusRequest::object_put(
+        client,
+        &disk_url,
+        Some(&params::DiskUpdate {
+            size: Some(ByteCount::from_gibibytes_u32(2)),
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(
+        grown.size.to_bytes(),
+        ByteCount::from_gibibytes_u32(2).to_bytes()
+    );
+    assert_eq!(grown.state, DiskState::Attached(instance_id));
+
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+    let (.., db_disk) = LookupPath::new(&opctx, &datastore)
+        .disk_id(disk.identity.id)
+        .fetch()
+        .await
+        .unwrap();
+    // Volumes are compared by the ids of their sub-volumes, since Nexus and
+    // the sled agent serialize them with different types.
+    let sub_volume_ids =
+        |volume: serde_json::Value| match volume["sub_volumes"].as_array() {
+            Some(sub_volumes) => sub_volumes
+                .iter()
+                .map(|sub_volume| sub_volume["opts"]["id"].to_string())
+                .collect::<Vec<_>>(),
+            None => panic!("volume has no sub-volumes: {:?}", volume),
+        };
+    let volume_ids = || async {
+        let volume = datastore.volume_get(db_disk.volume_id).await.unwrap();
+        sub_volume_ids(serde_json::from_str(volume.data()).unwrap())
+    };
+    let sled_agent = &cptestctx.sled_agent.sled_agent;
+    let sled_agent_volume_ids = || async {
+        let volume = sled_agent
+            .instance_disk_volume(instance_id, disk.identity.id)
+            .await
+            .expect("sled agent was not given the grown volume");
+        sub_volume_ids(serde_json::to_value(&volume).unwrap())
+    };
+    let grown_volume_ids = volume_ids().await;
+    assert_eq!(grown_volume_ids.len(), 2);
+    assert_eq!(sled_agent_volume_ids().await, grown_volume_ids);
+
+    // A stopped instance gets the grown volume when it next starts, so its
+    // sled agent isn't told about it.
+    set_instance_state(&client, &instance_url, "stop").await;
+    instance_simulate(nexus, &instance_id).await;
+    let grown: Disk = NexusRequest::object_put(
+        client,
+        &disk_url,
+        Some(&params::DiskUpdate {
+            size: Some(ByteCount::from_gibibytes_u32(3)),
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(
+        grown.size.to_bytes(),
+        ByteCount::from_gibibytes_u32(3).to_bytes()
+    );
+    assert_eq!(grown.state, DiskState::Attached(instance_id));
+    assert_eq!(volume_ids().await.len(), 3);
+    assert_eq!(sled_agent_volume_ids().await, grown_volume_ids);
+}
+
+// Tests that the disk record changes made by the resize saga are undone
+// cleanly, as they would be if a later node failed, and that both directions
+// are idempotent.
+#[nexus_test]
+async fn test_disk_resize_undo(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    let datastore = nexus.datastore();
+    DiskTest::new(&cptestctx).await;
+    create_org_and_project(client).await;
+    let disk_url = format!("{}/{}", get_disks_url(), DISK_NAME);
+
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+
+    let disk = create_disk(client, ORG_NAME, PROJECT_NAME, DISK_NAME).await;
+    let (.., authz_disk, db_disk) = LookupPath::new(&opctx, &datastore)
+        .disk_id(disk.identity.id)
+        .fetch_for(authz::Action::Modify)
+        .await
+        .unwrap();
+    let region_ids = |regions: Vec<(db::model::Dataset, db::model::Region)>| {
+        regions
+            .into_iter()
+            .map(|(_, region)| region.id())
+            .collect::<BTreeSet<_>>()
+    };
+    let sub_volumes = |volume: &db::model::Volume| {
+        let vcr: VolumeConstructionRequest =
+            serde_json::from_str(volume.data()).unwrap();
+        match vcr {
+            VolumeConstructionRequest::Volume { sub_volumes, .. } => {
+                sub_volumes
+            }
+            _ => panic!("disk volume is not a Volume"),
+        }
+    };
+    let original_regions = region_ids(
+        datastore.get_allocated_regions(db_disk.volume_id).await.unwrap(),
+    );
+    let original_volume =
+        datastore.volume_get(db_disk.volume_id).await.unwrap();
+    assert_eq!(sub_volumes(&original_volume).len(), 1);
+
+    // Grow the disk far enough that it needs new regions.
+    let old_size = ByteCount::from_gibibytes_u32(1);
+    let new_size = ByteCount::from_gibibytes_u32(2);
+    NexusRequest::object_put(
+        client,
+        &disk_url,
+        Some(&params::DiskUpdate { size: Some(new_size), labels: None }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    let grown_regions = region_ids(
+        datastore.get_allocated_regions(db_disk.volume_id).await.unwrap(),
+    );
+    let extension_region_ids: Vec<Uuid> =
+        grown_regions.difference(&original_regions).cloned().collect();
+    assert!(!extension_region_ids.is_empty());
+    let grown_volume = datastore.volume_get(db_disk.volume_id).await.unwrap();
+    let extension = sub_volumes(&grown_volume).pop().unwrap();
+    let extension_id = match &extension {
+        VolumeConstructionRequest::Region { opts, .. } => opts.id,
+        _ => panic!("disk extension is not a Region"),
+    };
+
+    // Undoing the disk record changes (twice, as a replayed undo action
+    // would) puts the disk back the way it was and hands the new regions back
+    // to the extension.
+    for _ in 0..2 {
+        datastore
+            .disk_resize_undo(
+                &opctx,
+                &authz_disk,
+                old_size,
+                new_size,
+                extension_id,
+                extension_region_ids.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            disk_get(client, &disk_url).await.size.to_bytes(),
+            old_size.to_bytes()
+        );
+        assert_eq!(
+            region_ids(
+                datastore
+                    .get_allocated_regions(db_disk.volume_id)
+                    .await
+                    .unwrap()
+            ),
+            original_regions
+        );
+        assert_eq!(
+            region_ids(
+                datastore.get_allocated_regions(extension_id).await.unwrap()
+            ),
+            extension_region_ids.iter().cloned().collect()
+        );
+        assert_eq!(
+            datastore.volume_get(db_disk.volume_id).await.unwrap().data(),
+            original_volume.data()
+        );
+    }
+
+    // Redoing them (again twice, as a replayed action would) grows the disk
+    // exactly once.
+    for _ in 0..2 {
+        datastore
+            .disk_resize(
+                &opctx,
+                &authz_disk,
+                old_size,
+                new_size,
+                extension_id,
+                Some(extension.clone()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            disk_get(client, &disk_url).await.size.to_bytes(),
+            new_size.to_bytes()
+        );
+        assert_eq!(
+            region_ids(
+                datastore
+                    .get_allocated_regions(db_disk.volume_id)
+                    .await
+                    .unwrap()
+            ),
+            grown_regions
+        );
+        assert_eq!(
+            datastore.volume_get(db_disk.volume_id).await.unwrap().data(),
+            grown_volume.data()
+        );
+    }
+}
+
+#[nexus_test]
+async fn test_disk_import_export(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_org_and_project(client).await;
+    let disk_url = format!("{}/{}", get_disks_url(), DISK_NAME);
+
+    // Create a disk that's ready to have blocks imported into it.
+    let new_disk = params::DiskCreate {
+        identity: IdentityMetadataCreateParams {
+            name: DISK_NAME.parse().unwrap(),
+            description: String::from("sells rainsticks"),
+        },
+        disk_source: params::DiskSource::ImportingBlocks {
+            block_size: params::BlockSize::try_from(512).unwrap(),
+        },
+        size: ByteCount::from_gibibytes_u32(1),
+        labels: Default::default(),
+    };
+    NexusRequest::objects_post(client, &get_disks_url(), &new_disk)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap();
+    let disk = disk_get(client, &disk_url).await;
+    assert_eq!(disk.state, DiskState::Importing);
+
+    // Disks can't be exported while they're being imported.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::BAD_REQUEST,
+        Method::GET,
+        &format!("{}/export", disk_url),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Write some blocks, both as base64 and as raw octets.
+    let bulk_write_url = format!("{}/bulk-write", disk_url);
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &bulk_write_url)
+            .body(Some(&params::ImportBlocksBulkWrite {
+                offset: 0,
+                base64_encoded_data: base64::encode([0x55; 1024]),
+            }))
+            .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("{}/bulk-write-octets?offset=1536", disk_url),
+        )
+        .raw_body(Some("a".repeat(512)))
+        .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Writes must be whole blocks, and must fit in the disk (even when the
+    // end of the write doesn't fit in a u64).
+    for (offset, len) in [
+        (100, 512),
+        (0, 100),
+        (ByteCount::from_gibibytes_u32(1).to_bytes(), 512),
+        (u64::MAX - 511, 512),
+    ] {
+        NexusRequest::new(
+            RequestBuilder::new(client, Method::POST, &bulk_write_url)
+                .body(Some(&params::ImportBlocksBulkWrite {
+                    offset,
+                    base64_encoded_data: base64::encode(vec![0; len]),
+                }))
+                .expect_status(Some(StatusCode::BAD_REQUEST)),
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap();
+    }
+
+    // Finalize the import.  After that, the disk is an ordinary detached disk
+    // that can't be written to this way.
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("{}/finalize", disk_url),
+        )
+        .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    let disk = disk_get(client, &disk_url).await;
+    assert_eq!(disk.state, DiskState::Detached);
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &bulk_write_url)
+            .body(Some(&params::ImportBlocksBulkWrite {
+                offset: 0,
+                base64_encoded_data: base64::encode([0; 512]),
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Export the disk.  Only read the start of it: the rest is zeros, and
+    // there's a gibibyte of them.
+    let request = hyper::Request::builder()
+        .method(Method::GET)
+        .uri(client.url(&format!("{}/export", disk_url)))
+        .header(
+            http::header::AUTHORIZATION,
+            spoof::make_header_value(authn::USER_TEST_PRIVILEGED.id())
+                .0
+                .encode(),
+        )
+        .body(hyper::Body::empty())
+        .unwrap();
+    let mut response = hyper::Client::new().request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(http::header::CONTENT_LENGTH).unwrap(),
+        &ByteCount::from_gibibytes_u32(1).to_bytes().to_string(),
+    );
+    let mut contents = Vec::new();
+    while contents.len() < 4096 {
+        let chunk = response.body_mut().data().await.unwrap().unwrap();
+        contents.extend_from_slice(&chunk);
+    }
+    assert_eq!(&contents[0..1024], &[0x55; 1024][..]);
+    assert_eq!(&contents[1024..1536], &[0; 512][..]);
+    assert_eq!(&contents[1536..2048], "a".repeat(512).as_bytes());
+    assert_eq!(&contents[2048..4096], &[0; 2048][..]);
+}
+
 async fn disk_get(client: &ClientTestContext, disk_url: &str) -> Disk {
     NexusRequest::object_get(client, disk_url)
         .authn_as(AuthnMode::PrivilegedUser)
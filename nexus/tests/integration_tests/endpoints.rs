@@ -62,6 +62,18 @@ lazy_static! {
             identity_mode: shared::SiloIdentityMode::SamlJit,
             admin_group_name: None,
         };
+    // Silo used for testing OIDC identity providers
+    pub static ref DEMO_OIDC_SILO_NAME: Name = "demo-oidc-silo".parse().unwrap();
+    pub static ref DEMO_OIDC_SILO_CREATE: params::SiloCreate =
+        params::SiloCreate {
+            identity: IdentityMetadataCreateParams {
+                name: DEMO_OIDC_SILO_NAME.clone(),
+                description: String::from(""),
+            },
+            discoverable: true,
+            identity_mode: shared::SiloIdentityMode::OidcJit,
+            admin_group_name: None,
+        };
     // Use the default Silo for testing the local IdP
     pub static ref DEMO_SILO_USERS_CREATE_URL: String = format!(
         "/system/silos/{}/identity-providers/local/users",
@@ -79,6 +91,29 @@ lazy_static! {
         "/system/silos/{}/identity-providers/local/users/{{id}}",
         DEFAULT_SILO.identity().name,
     );
+    pub static ref DEMO_SILO_USER_ID_TOKENS_URL: String = format!(
+        "/system/silos/{}/users/id/{{id}}/tokens",
+        DEFAULT_SILO.identity().name,
+    );
+    pub static ref DEMO_SILO_USER_ID_SESSIONS_URL: String = format!(
+        "/system/silos/{}/users/id/{{id}}/sessions",
+        DEFAULT_SILO.identity().name,
+    );
+    pub static ref DEMO_SILO_USER_ID_SET_PASSWORD_URL: String = format!(
+        "/system/silos/{}/identity-providers/local/users/{{id}}/set-password",
+        DEFAULT_SILO.identity().name,
+    );
+    pub static ref DEMO_SILO_GROUPS_CREATE_URL: String = format!(
+        "/system/silos/{}/identity-providers/local/groups",
+        DEFAULT_SILO.identity().name,
+    );
+    pub static ref DEMO_SILO_GROUP_ID_URL: String =
+        format!("{}/{{id}}", *DEMO_SILO_GROUPS_CREATE_URL);
+    pub static ref DEMO_SILO_GROUP_ID_MEMBER_URL: String = format!(
+        "{}/members/{}",
+        *DEMO_SILO_GROUP_ID_URL,
+        authn::USER_TEST_UNPRIVILEGED.id(),
+    );
 
     // Organization used for testing
     pub static ref DEMO_ORG_NAME: Name = "demo-org".parse().unwrap();
@@ -94,6 +129,7 @@ lazy_static! {
                 name: DEMO_ORG_NAME.clone(),
                 description: String::from(""),
             },
+            labels: Default::default(),
         };
 
     // Project used for testing
@@ -102,6 +138,8 @@ lazy_static! {
         format!("{}/{}", *DEMO_ORG_PROJECTS_URL, *DEMO_PROJECT_NAME);
     pub static ref DEMO_PROJECT_POLICY_URL: String =
         format!("{}/policy", *DEMO_PROJECT_URL);
+    pub static ref DEMO_PROJECT_MOVE_URL: String =
+        format!("{}/move", *DEMO_PROJECT_URL);
     pub static ref DEMO_PROJECT_URL_DISKS: String =
         format!("{}/disks", *DEMO_PROJECT_URL);
     pub static ref DEMO_PROJECT_URL_IMAGES: String =
@@ -118,12 +156,15 @@ lazy_static! {
                 name: DEMO_PROJECT_NAME.clone(),
                 description: String::from(""),
             },
+            labels: Default::default(),
         };
 
     // VPC used for testing
     pub static ref DEMO_VPC_NAME: Name = "demo-vpc".parse().unwrap();
     pub static ref DEMO_VPC_URL: String =
         format!("{}/{}", *DEMO_PROJECT_URL_VPCS, *DEMO_VPC_NAME);
+    pub static ref DEMO_VPC_POLICY_URL: String =
+        format!("{}/policy", *DEMO_VPC_URL);
     pub static ref DEMO_VPC_URL_FIREWALL_RULES: String =
         format!("{}/firewall/rules", *DEMO_VPC_URL);
     pub static ref DEMO_VPC_URL_ROUTERS: String =
@@ -138,6 +179,7 @@ lazy_static! {
             },
             ipv6_prefix: None,
             dns_name: DEMO_VPC_NAME.clone(),
+            labels: Default::default(),
         };
 
     // VPC Subnet used for testing
@@ -191,6 +233,8 @@ lazy_static! {
     pub static ref DEMO_DISK_NAME: Name = "demo-disk".parse().unwrap();
     pub static ref DEMO_DISK_URL: String =
         format!("{}/{}", *DEMO_PROJECT_URL_DISKS, *DEMO_DISK_NAME);
+    pub static ref DEMO_DISK_POLICY_URL: String =
+        format!("{}/policy", *DEMO_DISK_URL);
     pub static ref DEMO_DISK_CREATE: params::DiskCreate =
         params::DiskCreate {
             identity: IdentityMetadataCreateParams {
@@ -204,7 +248,28 @@ lazy_static! {
                 // divide by two to leave space for snapshot blocks
                 DiskTest::DEFAULT_ZPOOL_SIZE_GIB / 2
             ),
+            labels: Default::default(),
+        };
+    pub static ref DEMO_DISK_UPDATE: params::DiskUpdate =
+        params::DiskUpdate {
+            size: Some(ByteCount::from_gibibytes_u32(
+                DiskTest::DEFAULT_ZPOOL_SIZE_GIB / 2 + 1
+            )),
+            labels: None,
         };
+    pub static ref DEMO_DISK_BULK_WRITE_URL: String =
+        format!("{}/bulk-write", *DEMO_DISK_URL);
+    pub static ref DEMO_DISK_BULK_WRITE: params::ImportBlocksBulkWrite =
+        params::ImportBlocksBulkWrite {
+            offset: 0,
+            base64_encoded_data: base64::encode([0; 4096]),
+        };
+    pub static ref DEMO_DISK_BULK_WRITE_OCTETS_URL: String =
+        format!("{}/bulk-write-octets?offset=0", *DEMO_DISK_URL);
+    pub static ref DEMO_DISK_FINALIZE_URL: String =
+        format!("{}/finalize", *DEMO_DISK_URL);
+    pub static ref DEMO_DISK_EXPORT_URL: String =
+        format!("{}/export", *DEMO_DISK_URL);
     pub static ref DEMO_DISK_METRICS_URL: String =
         format!(
             "{}/metrics/activated?start_time={:?}&end_time={:?}",
@@ -217,6 +282,8 @@ lazy_static! {
     pub static ref DEMO_INSTANCE_NAME: Name = "demo-instance".parse().unwrap();
     pub static ref DEMO_INSTANCE_URL: String =
         format!("{}/{}", *DEMO_PROJECT_URL_INSTANCES, *DEMO_INSTANCE_NAME);
+    pub static ref DEMO_INSTANCE_POLICY_URL: String =
+        format!("{}/policy", *DEMO_INSTANCE_URL);
     pub static ref DEMO_INSTANCE_START_URL: String =
         format!("{}/start", *DEMO_INSTANCE_URL);
     pub static ref DEMO_INSTANCE_STOP_URL: String =
@@ -237,6 +304,13 @@ lazy_static! {
         format!("{}/external-ips", *DEMO_INSTANCE_URL);
     pub static ref DEMO_INSTANCE_SERIAL_URL: String =
         format!("{}/serial-console", *DEMO_INSTANCE_URL);
+    pub static ref DEMO_INSTANCE_METRICS_URL: String =
+        format!(
+            "{}/metrics/cpu_busy?start_time={:?}&end_time={:?}",
+            *DEMO_INSTANCE_URL,
+            Utc::now(),
+            Utc::now(),
+        );
     pub static ref DEMO_INSTANCE_CREATE: params::InstanceCreate =
         params::InstanceCreate {
             identity: IdentityMetadataCreateParams {
@@ -254,6 +328,14 @@ lazy_static! {
             ],
             disks: vec![],
             start: true,
+            labels: Default::default(),
+        };
+    pub static ref DEMO_INSTANCE_UPDATE: params::InstanceUpdate =
+        params::InstanceUpdate {
+            ncpus: Some(InstanceCpuCount(2)),
+            memory: None,
+            hostname: None,
+            labels: None,
         };
 
     // The instance needs a network interface, too.
@@ -297,6 +379,7 @@ lazy_static! {
             },
             source: params::ImageSource::Url { url: HTTP_SERVER.url("/image.raw").to_string() },
             block_size: params::BlockSize::try_from(4096).unwrap(),
+            labels: Default::default(),
         };
 
     // Global Images
@@ -315,6 +398,7 @@ lazy_static! {
                 version: String::from("edge"),
             },
             block_size: params::BlockSize::try_from(4096).unwrap(),
+            labels: Default::default(),
         };
 
     // IP Pools
@@ -362,6 +446,7 @@ lazy_static! {
                 description: String::from(""),
             },
             disk: DEMO_DISK_NAME.clone(),
+            labels: Default::default(),
         };
 
     // SSH keys
@@ -378,6 +463,45 @@ lazy_static! {
 
     pub static ref DEMO_SPECIFIC_SSHKEY_URL: String =
         format!("{}/{}", *DEMO_SSHKEYS_URL, *DEMO_SSHKEY_NAME);
+
+    // Access tokens
+    pub static ref DEMO_TOKENS_URL: &'static str = "/session/me/tokens";
+    pub static ref DEMO_SPECIFIC_TOKEN_URL: String =
+        format!("{}/{{id}}", *DEMO_TOKENS_URL);
+
+    // Console sessions
+    pub static ref DEMO_SESSIONS_URL: &'static str = "/session/me/sessions";
+    pub static ref DEMO_SPECIFIC_SESSION_URL: String =
+        format!("{}/{{id}}", *DEMO_SESSIONS_URL);
+
+    // Service accounts
+    pub static ref DEMO_SERVICE_ACCOUNTS_URL: &'static str = "/service-accounts";
+    pub static ref DEMO_SERVICE_ACCOUNT_NAME: Name =
+        "demo-service-account".parse().unwrap();
+    pub static ref DEMO_SERVICE_ACCOUNT_URL: String =
+        format!("{}/{}", *DEMO_SERVICE_ACCOUNTS_URL, *DEMO_SERVICE_ACCOUNT_NAME);
+    pub static ref DEMO_SERVICE_ACCOUNT_CREATE: params::ServiceAccountCreate =
+        params::ServiceAccountCreate {
+            identity: IdentityMetadataCreateParams {
+                name: DEMO_SERVICE_ACCOUNT_NAME.clone(),
+                description: "a demo service account".to_string(),
+            },
+            project_id: None,
+        };
+    pub static ref DEMO_SERVICE_ACCOUNT_KEYS_URL: String =
+        format!("{}/keys", *DEMO_SERVICE_ACCOUNT_URL);
+    pub static ref DEMO_SPECIFIC_SERVICE_ACCOUNT_KEY_URL: String =
+        format!("{}/{{id}}", *DEMO_SERVICE_ACCOUNT_KEYS_URL);
+
+    // Timeseries query
+    pub static ref DEMO_TIMESERIES_QUERY: params::TimeseriesQuery =
+        params::TimeseriesQuery {
+            timeseries_name: "integration_target:integration_metric".to_string(),
+            criteria: vec![],
+            start_time: Utc::now() - chrono::Duration::seconds(60),
+            end_time: Utc::now() + chrono::Duration::seconds(60),
+            aggregation: None,
+        };
 }
 
 lazy_static! {
@@ -408,10 +532,41 @@ lazy_static! {
             group_attribute_name: None,
         };
 
+    pub static ref OIDC_IDENTITY_PROVIDERS_URL: String = format!("/system/silos/demo-oidc-silo/identity-providers/oidc");
+
+    pub static ref DEMO_OIDC_IDENTITY_PROVIDER_NAME: Name = "demo-oidc-provider".parse().unwrap();
+    pub static ref SPECIFIC_OIDC_IDENTITY_PROVIDER_URL: String = format!("{}/{}", *OIDC_IDENTITY_PROVIDERS_URL, *DEMO_OIDC_IDENTITY_PROVIDER_NAME);
+
+    pub static ref OIDC_IDENTITY_PROVIDER: params::OidcIdentityProviderCreate =
+        params::OidcIdentityProviderCreate {
+            identity: IdentityMetadataCreateParams {
+                name: DEMO_OIDC_IDENTITY_PROVIDER_NAME.clone(),
+                description: "a demo provider".to_string(),
+            },
+
+            issuer: HTTP_SERVER.url("/oidc").to_string(),
+            client_id: "client_id".to_string(),
+            client_secret: None,
+            redirect_uri: "http://redirect".to_string(),
+
+            group_claim_name: None,
+        };
+
     // Users
     pub static ref DEMO_USER_CREATE: params::UserCreate = params::UserCreate {
         external_id: params::UserId::from_str("dummy-user").unwrap(),
+        password: params::UserPassword::InvalidPassword,
     };
+    pub static ref DEMO_USER_PASSWORD: params::UserPassword =
+        params::UserPassword::Password(
+            params::Password::from_str("dummy-password").unwrap(),
+        );
+
+    // Groups
+    pub static ref DEMO_GROUP_CREATE: params::GroupCreate =
+        params::GroupCreate {
+            display_name: "dummy-group".parse().unwrap(),
+        };
 }
 
 /// Describes an API endpoint to be verified by the "unauthorized" test
@@ -780,6 +935,65 @@ lazy_static! {
             ],
         },
 
+        VerifyEndpoint {
+            url: &*DEMO_SILO_USER_ID_TOKENS_URL,
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::ReadOnly,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SILO_USER_ID_SESSIONS_URL,
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::ReadOnly,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SILO_USER_ID_SET_PASSWORD_URL,
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::ReadOnly,
+            allowed_methods: vec![
+                AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_USER_PASSWORD).unwrap()
+                ),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SILO_GROUPS_CREATE_URL,
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::ReadOnly,
+            allowed_methods: vec![
+                AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_GROUP_CREATE).unwrap()
+                ),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SILO_GROUP_ID_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SILO_GROUP_ID_MEMBER_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Put(serde_json::Value::Null),
+                AllowedMethod::Delete,
+            ],
+        },
+
         VerifyEndpoint {
             url: "/groups",
             visibility: Visibility::Public,
@@ -789,6 +1003,49 @@ lazy_static! {
             ],
         },
 
+        /* Service accounts */
+
+        VerifyEndpoint {
+            url: &*DEMO_SERVICE_ACCOUNTS_URL,
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_SERVICE_ACCOUNT_CREATE).unwrap()
+                ),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SERVICE_ACCOUNT_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Delete,
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SERVICE_ACCOUNT_KEYS_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Post(serde_json::Value::Null),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_SPECIFIC_SERVICE_ACCOUNT_KEY_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
+
         /* Organizations */
 
         VerifyEndpoint {
@@ -824,7 +1081,8 @@ lazy_static! {
                         identity: IdentityMetadataUpdateParams {
                             name: None,
                             description: Some("different".to_string())
-                        }
+                        },
+                        labels: None,
                     }).unwrap()
                 ),
             ],
@@ -890,6 +1148,20 @@ lazy_static! {
                             name: None,
                             description: Some("different".to_string())
                         },
+                        labels: None,
+                    }).unwrap()
+                ),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_PROJECT_MOVE_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Post(
+                    serde_json::to_value(params::ProjectMove {
+                        organization: DEMO_ORG_NAME.clone(),
                     }).unwrap()
                 ),
             ],
@@ -946,12 +1218,29 @@ lazy_static! {
                             description: Some("different".to_string())
                         },
                         dns_name: None,
+                        labels: None,
                     }).unwrap()
                 ),
                 AllowedMethod::Delete,
             ],
         },
 
+        VerifyEndpoint {
+            url: &*DEMO_VPC_POLICY_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Put(
+                    serde_json::to_value(
+                        &shared::Policy::<authz::VpcRole> {
+                            role_assignments: vec![]
+                        }
+                    ).unwrap()
+                ),
+            ],
+        },
+
         /* Firewall rules */
         VerifyEndpoint {
             url: &*DEMO_VPC_URL_FIREWALL_RULES,
@@ -1131,10 +1420,69 @@ lazy_static! {
             unprivileged_access: UnprivilegedAccess::None,
             allowed_methods: vec![
                 AllowedMethod::Get,
+                AllowedMethod::Put(
+                    serde_json::to_value(&*DEMO_DISK_UPDATE).unwrap()
+                ),
                 AllowedMethod::Delete,
             ],
         },
 
+        VerifyEndpoint {
+            url: &*DEMO_DISK_BULK_WRITE_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_DISK_BULK_WRITE).unwrap()
+                ),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_DISK_BULK_WRITE_OCTETS_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Post(serde_json::Value::Null),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_DISK_FINALIZE_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Post(serde_json::Value::Null),
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_DISK_EXPORT_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                // A privileged GET would stream the whole demo disk, which
+                // isn't JSON.
+                AllowedMethod::GetNonexistent,
+            ],
+        },
+
+        VerifyEndpoint {
+            url: &*DEMO_DISK_POLICY_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Put(
+                    serde_json::to_value(
+                        &shared::Policy::<authz::DiskRole> {
+                            role_assignments: vec![]
+                        }
+                    ).unwrap()
+                ),
+            ],
+        },
+
         VerifyEndpoint {
             url: &*DEMO_DISK_METRICS_URL,
             visibility: Visibility::Protected,
@@ -1271,10 +1619,29 @@ lazy_static! {
             unprivileged_access: UnprivilegedAccess::None,
             allowed_methods: vec![
                 AllowedMethod::Get,
+                AllowedMethod::Put(
+                    serde_json::to_value(&*DEMO_INSTANCE_UPDATE).unwrap()
+                ),
                 AllowedMethod::Delete,
             ],
         },
 
+        VerifyEndpoint {
+            url: &*DEMO_INSTANCE_POLICY_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Put(
+                    serde_json::to_value(
+                        &shared::Policy::<authz::InstanceRole> {
+                            role_assignments: vec![]
+                        }
+                    ).unwrap()
+                ),
+            ],
+        },
+
         VerifyEndpoint {
             url: &*DEMO_INSTANCE_START_URL,
             visibility: Visibility::Protected,
@@ -1319,6 +1686,14 @@ lazy_static! {
                 AllowedMethod::GetNonexistent // has required query parameters
             ],
         },
+        VerifyEndpoint {
+            url: &*DEMO_INSTANCE_METRICS_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+            ],
+        },
 
         /* Instance NICs */
         VerifyEndpoint {
@@ -1437,7 +1812,7 @@ lazy_static! {
             allowed_methods: vec![AllowedMethod::GetNonexistent],
         },
 
-        /* Timeseries schema */
+        /* Timeseries */
 
         VerifyEndpoint {
             url: "/timeseries/schema",
@@ -1446,6 +1821,17 @@ lazy_static! {
             allowed_methods: vec![AllowedMethod::Get],
         },
 
+        // Any authenticated user may query timeseries, but only sees those
+        // describing resources they can read.
+        VerifyEndpoint {
+            url: "/timeseries/query",
+            visibility: Visibility::Public,
+            unprivileged_access: UnprivilegedAccess::Full,
+            allowed_methods: vec![AllowedMethod::Post(
+                serde_json::to_value(&*DEMO_TIMESERIES_QUERY).unwrap(),
+            )],
+        },
+
         /* Updates */
 
         VerifyEndpoint {
@@ -1531,6 +1917,22 @@ lazy_static! {
             unprivileged_access: UnprivilegedAccess::None,
             allowed_methods: vec![AllowedMethod::Get],
         },
+        VerifyEndpoint {
+            url: &*OIDC_IDENTITY_PROVIDERS_URL,
+            // As with SAML, this is in a non-default Silo that unprivileged
+            // users can't see.
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![AllowedMethod::Post(
+                serde_json::to_value(&*OIDC_IDENTITY_PROVIDER).unwrap(),
+            )],
+        },
+        VerifyEndpoint {
+            url: &*SPECIFIC_OIDC_IDENTITY_PROVIDER_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::None,
+            allowed_methods: vec![AllowedMethod::Get],
+        },
 
         /* Misc */
 
@@ -1573,5 +1975,38 @@ lazy_static! {
                 AllowedMethod::Delete,
             ],
         },
+        VerifyEndpoint {
+            url: &*DEMO_TOKENS_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::Full,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+            ],
+        },
+        VerifyEndpoint {
+            url: &*DEMO_SPECIFIC_TOKEN_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::Full,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
+        VerifyEndpoint {
+            url: &*DEMO_SESSIONS_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::Full,
+            allowed_methods: vec![
+                AllowedMethod::Get,
+                AllowedMethod::Delete,
+            ],
+        },
+        VerifyEndpoint {
+            url: &*DEMO_SPECIFIC_SESSION_URL,
+            visibility: Visibility::Protected,
+            unprivileged_access: UnprivilegedAccess::Full,
+            allowed_methods: vec![
+                AllowedMethod::Delete,
+            ],
+        },
     ];
 }
@@ -0,0 +1,277 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests that Nexus publishes the names of silos and instances in the external
+//! DNS zone
+
+use crate::integration_tests::instances::instance_post;
+use crate::integration_tests::instances::instance_simulate;
+use crate::integration_tests::instances::InstanceOp;
+use dropshot::test_util::LogContext;
+use internal_dns_client::types::DnsRecord;
+use nexus_test_utils::http_testing::{AuthnMode, NexusRequest};
+use nexus_test_utils::resource_helpers::{
+    create_instance, create_ip_pool, create_organization, create_project,
+    create_silo,
+};
+use nexus_test_utils::{load_test_config, test_setup_with_config};
+use omicron_common::api::external::{
+    ByteCount, IdentityMetadataCreateParams, IdentityMetadataUpdateParams,
+    Instance, InstanceCpuCount,
+};
+use omicron_nexus::config::ExternalDnsConfig;
+use omicron_nexus::external_api::{params, shared};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+const ZONE: &str = "oxide.test";
+const ORGANIZATION_NAME: &str = "test-org";
+const PROJECT_NAME: &str = "springfield-squidport";
+
+struct DnsServer {
+    _storage: TempDir,
+    _dns_server: internal_dns::dns_server::Server,
+    dropshot_server:
+        dropshot::HttpServer<Arc<internal_dns::dropshot_server::Context>>,
+}
+
+impl DnsServer {
+    async fn create(log: &slog::Logger) -> DnsServer {
+        let storage =
+            TempDir::new().expect("failed to create temporary directory");
+        let db = Arc::new(sled::open(&storage.path()).unwrap());
+        let dns_server = internal_dns::dns_server::run(
+            log.clone(),
+            db.clone(),
+            internal_dns::dns_server::Config {
+                bind_address: "127.0.0.1:0".to_string(),
+                zone: ZONE.into(),
+            },
+        )
+        .await
+        .unwrap();
+        let config = internal_dns::Config {
+            log: dropshot::ConfigLogging::StderrTerminal {
+                level: dropshot::ConfigLoggingLevel::Info,
+            },
+            dropshot: dropshot::ConfigDropshot {
+                bind_address: "127.0.0.1:0".parse().unwrap(),
+                request_body_max_bytes: 1024 * 1024,
+                ..Default::default()
+            },
+            data: internal_dns::dns_data::Config {
+                nmax_messages: 16,
+                storage_path: storage.path().to_string_lossy().into(),
+            },
+        };
+        let dropshot_server =
+            internal_dns::start_dropshot_server(config, log.clone(), db)
+                .await
+                .unwrap();
+        DnsServer {
+            _storage: storage,
+            _dns_server: dns_server,
+            dropshot_server,
+        }
+    }
+
+    /// Returns the addresses of each name in the zone.
+    async fn names(&self, log: &slog::Logger) -> BTreeMap<String, Vec<IpAddr>> {
+        let client = internal_dns_client::Client::new(
+            &format!("http://{}", self.dropshot_server.local_addr()),
+            log.clone(),
+        );
+        client
+            .dns_records_list()
+            .await
+            .expect("failed to list DNS records")
+            .into_iter()
+            .map(|kv| {
+                let addrs = kv
+                    .records
+                    .into_iter()
+                    .map(|record| match record {
+                        DnsRecord::A(addr) => IpAddr::V4(addr),
+                        DnsRecord::Aaaa(addr) => IpAddr::V6(addr),
+                        DnsRecord::Srv(srv) => {
+                            panic!("unexpected SRV record: {:?}", srv)
+                        }
+                    })
+                    .collect();
+                (kv.key.name, addrs)
+            })
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn test_external_dns() {
+    let mut config = load_test_config();
+    let logctx = LogContext::new("test_external_dns", &config.pkg.log);
+    let log = &logctx.log;
+    let dns_server = DnsServer::create(log).await;
+
+    let api_address: IpAddr = "192.0.2.1".parse().unwrap();
+    config.pkg.external_dns = Some(ExternalDnsConfig {
+        zone: ZONE.to_string(),
+        servers: vec![dns_server.dropshot_server.local_addr()],
+        api_addresses: vec![api_address],
+        instance_records: true,
+    });
+    let cptestctx =
+        test_setup_with_config("test_external_dns", &mut config).await;
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+
+    // Each silo gets a name that resolves to the external API.
+    create_silo(client, "nowhere", false, shared::SiloIdentityMode::SamlJit)
+        .await;
+    let names = dns_server.names(log).await;
+    assert_eq!(names["default-silo.sys.oxide.test"], vec![api_address]);
+    assert_eq!(names["nowhere.sys.oxide.test"], vec![api_address]);
+
+    // Instances with external IPs get a name under their silo, organization
+    // and project.
+    create_ip_pool(client, "p0", None, None).await;
+    create_organization(client, ORGANIZATION_NAME).await;
+    create_project(client, ORGANIZATION_NAME, PROJECT_NAME).await;
+    let instances_url = format!(
+        "/organizations/{}/projects/{}/instances",
+        ORGANIZATION_NAME, PROJECT_NAME
+    );
+    let instance: Instance = NexusRequest::objects_post(
+        client,
+        &instances_url,
+        &params::InstanceCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "web".parse().unwrap(),
+                description: String::from("a public instance"),
+            },
+            ncpus: InstanceCpuCount(1),
+            memory: ByteCount::from_gibibytes_u32(1),
+            hostname: String::from("web"),
+            user_data: vec![],
+            network_interfaces:
+                params::InstanceNetworkInterfaceAttachment::Default,
+            external_ips: vec![params::ExternalIpCreate::Ephemeral {
+                pool_name: None,
+            }],
+            disks: vec![],
+            start: true,
+            labels: Default::default(),
+        },
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to create instance")
+    .parsed_body()
+    .unwrap();
+    instance_simulate(nexus, &instance.identity.id).await;
+
+    // An instance without external IPs isn't published.  (This one's hostname
+    // isn't a valid DNS label anyway.)
+    create_instance(client, ORGANIZATION_NAME, PROJECT_NAME, "private").await;
+
+    let instance_name =
+        "web.springfield-squidport.test-org.default-silo.oxide.test";
+    let names = dns_server.names(log).await;
+    assert_eq!(names.len(), 3, "unexpected names: {:?}", names);
+    let addrs = &names[instance_name];
+    assert_eq!(addrs.len(), 1);
+    assert!(addrs[0].is_ipv4());
+
+    // Renaming the project or organization renames the instance, too.
+    NexusRequest::object_put(
+        client,
+        &format!(
+            "/organizations/{}/projects/{}",
+            ORGANIZATION_NAME, PROJECT_NAME
+        ),
+        Some(&params::ProjectUpdate {
+            identity: IdentityMetadataUpdateParams {
+                name: Some("shelbyville".parse().unwrap()),
+                description: None,
+            },
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to rename project");
+    NexusRequest::object_put(
+        client,
+        &format!("/organizations/{}", ORGANIZATION_NAME),
+        Some(&params::OrganizationUpdate {
+            identity: IdentityMetadataUpdateParams {
+                name: Some("renamed-org".parse().unwrap()),
+                description: None,
+            },
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to rename organization");
+    let names = dns_server.names(log).await;
+    assert_eq!(names.len(), 3, "unexpected names: {:?}", names);
+    assert!(!names.contains_key(instance_name));
+    assert_eq!(
+        &names["web.shelbyville.renamed-org.default-silo.oxide.test"],
+        addrs
+    );
+
+    // Changing the instance's hostname renames it, too.
+    let instances_url =
+        "/organizations/renamed-org/projects/shelbyville/instances";
+    let instance_url = format!("{}/web", instances_url);
+    instance_post(client, &instance_url, InstanceOp::Stop).await;
+    instance_simulate(nexus, &instance.identity.id).await;
+    NexusRequest::object_put(
+        client,
+        &instance_url,
+        Some(&params::InstanceUpdate {
+            ncpus: None,
+            memory: None,
+            hostname: Some(String::from("www")),
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to change hostname");
+    let names = dns_server.names(log).await;
+    assert_eq!(names.len(), 3, "unexpected names: {:?}", names);
+    assert!(!names
+        .contains_key("web.shelbyville.renamed-org.default-silo.oxide.test"));
+    assert_eq!(
+        &names["www.shelbyville.renamed-org.default-silo.oxide.test"],
+        addrs
+    );
+
+    // Names go away along with their instances and silos.
+    NexusRequest::object_delete(client, &instance_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to delete instance");
+    NexusRequest::object_delete(client, "/system/silos/nowhere")
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to delete silo");
+    let names = dns_server.names(log).await;
+    assert_eq!(
+        names.keys().collect::<Vec<_>>(),
+        vec!["default-silo.sys.oxide.test"]
+    );
+
+    cptestctx.teardown().await;
+    logctx.cleanup_successful();
+}
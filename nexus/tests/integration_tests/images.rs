@@ -63,6 +63,7 @@ async fn test_global_image_create(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     NexusRequest::objects_post(client, "/system/images", &image_create_params)
@@ -109,6 +110,7 @@ async fn test_global_image_create_url_404(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -146,6 +148,7 @@ async fn test_global_image_create_bad_url(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -196,6 +199,7 @@ async fn test_global_image_create_bad_content_length(
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -247,6 +251,7 @@ async fn test_global_image_create_bad_image_size(
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -298,6 +303,7 @@ async fn test_make_disk_from_global_image(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let alpine_image: GlobalImage = NexusRequest::objects_post(
@@ -324,6 +330,7 @@ async fn test_make_disk_from_global_image(cptestctx: &ControlPlaneTestContext) {
             image_id: alpine_image.identity.id,
         },
         size: ByteCount::from_gibibytes_u32(1),
+        labels: Default::default(),
     };
 
     NexusRequest::objects_post(
@@ -371,6 +378,7 @@ async fn test_make_disk_from_global_image_too_small(
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let alpine_image: GlobalImage = NexusRequest::objects_post(
@@ -397,6 +405,7 @@ async fn test_make_disk_from_global_image_too_small(
             image_id: alpine_image.identity.id,
         },
         size: ByteCount::from(4096 * 500),
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -4,6 +4,7 @@
 
 //! Tests basic instance support in the API
 
+use chrono::Utc;
 use http::method::Method;
 use http::StatusCode;
 use nexus_test_utils::http_testing::AuthnMode;
@@ -23,15 +24,24 @@ use omicron_common::api::external::Instance;
 use omicron_common::api::external::InstanceCpuCount;
 use omicron_common::api::external::InstanceState;
 use omicron_common::api::external::Ipv4Net;
+use omicron_common::api::external::Labels;
 use omicron_common::api::external::Name;
 use omicron_common::api::external::NetworkInterface;
+use omicron_common::backoff;
+use omicron_nexus::authn::USER_TEST_UNPRIVILEGED;
+use omicron_nexus::authz;
+use omicron_nexus::db::identity::Asset;
+use omicron_nexus::external_api::shared;
 use omicron_nexus::external_api::shared::IpKind;
 use omicron_nexus::external_api::shared::IpRange;
 use omicron_nexus::external_api::shared::Ipv4Range;
 use omicron_nexus::external_api::views;
 use omicron_nexus::TestInterfaces as _;
 use omicron_nexus::{external_api::params, Nexus};
+use oximeter::types::Datum;
+use oximeter::types::Measurement;
 use sled_agent_client::TestInterfaces as _;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -176,6 +186,7 @@ async fn test_instances_create_reboot_halt(
                 external_ips: vec![],
                 disks: vec![],
                 start: true,
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::BAD_REQUEST)),
     )
@@ -482,6 +493,7 @@ async fn test_instances_create_stopped_start(
             external_ips: vec![],
             disks: vec![],
             start: false,
+            labels: Default::default(),
         },
     )
     .await;
@@ -503,6 +515,153 @@ async fn test_instances_create_stopped_start(
     );
 }
 
+#[nexus_test]
+async fn test_instance_update(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let apictx = &cptestctx.server.apictx;
+    let nexus = &apictx.nexus;
+
+    create_ip_pool(&client, POOL_NAME, None, None).await;
+    create_org_and_project(client).await;
+    let instance_url = format!("{}/just-rainsticks", get_instances_url());
+
+    // Create a running instance.
+    let instance: Instance = object_create(
+        client,
+        &get_instances_url(),
+        &params::InstanceCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "just-rainsticks".parse().unwrap(),
+                description: "instance just-rainsticks".to_string(),
+            },
+            ncpus: InstanceCpuCount(4),
+            memory: ByteCount::from_gibibytes_u32(1),
+            hostname: String::from("the_host"),
+            user_data: vec![],
+            network_interfaces:
+                params::InstanceNetworkInterfaceAttachment::Default,
+            external_ips: vec![],
+            disks: vec![],
+            start: true,
+            labels: Default::default(),
+        },
+    )
+    .await;
+    instance_simulate(nexus, &instance.identity.id).await;
+    let instance = instance_get(&client, &instance_url).await;
+    assert_eq!(instance.runtime.run_state, InstanceState::Running);
+
+    // Running instances can't be reconfigured.  Labels in the same request
+    // aren't applied either.
+    let labels = Labels::try_from(BTreeMap::from([(
+        String::from("env"),
+        String::from("prod"),
+    )]))
+    .unwrap();
+    let update = params::InstanceUpdate {
+        ncpus: Some(InstanceCpuCount(2)),
+        memory: Some(ByteCount::from_gibibytes_u32(2)),
+        hostname: None,
+        labels: Some(labels.clone()),
+    };
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &instance_url)
+            .body(Some(&update))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<dropshot::HttpErrorResponseBody>()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        "instance must be stopped to be reconfigured, but it is \"running\""
+    );
+    assert_eq!(
+        instance_get(&client, &instance_url).await.labels,
+        Labels::default()
+    );
+
+    // Stop the instance and reconfigure it.  Properties that aren't specified
+    // are left alone.
+    instance_post(&client, &instance_url, InstanceOp::Stop).await;
+    instance_simulate(nexus, &instance.identity.id).await;
+    let updated: Instance =
+        NexusRequest::object_put(client, &instance_url, Some(&update))
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .unwrap()
+            .parsed_body()
+            .unwrap();
+    assert_eq!(updated.runtime.run_state, InstanceState::Stopped);
+    assert_eq!(updated.ncpus.0, 2);
+    assert_eq!(
+        updated.memory.to_bytes(),
+        ByteCount::from_gibibytes_u32(2).to_bytes()
+    );
+    assert_eq!(updated.hostname, "the_host");
+    assert_eq!(updated.labels, labels);
+    instances_eq(&updated, &instance_get(&client, &instance_url).await);
+
+    // The same rules apply to memory as when creating an instance.
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &instance_url)
+            .body(Some(&params::InstanceUpdate {
+                ncpus: None,
+                memory: Some(ByteCount::from(
+                    params::MIN_MEMORY_SIZE_BYTES / 2,
+                )),
+                hostname: None,
+                labels: None,
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // A new hostname must be usable as a DNS label.
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::PUT, &instance_url)
+            .body(Some(&params::InstanceUpdate {
+                ncpus: None,
+                memory: None,
+                hostname: Some(String::from("the_new_host")),
+                labels: None,
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<dropshot::HttpErrorResponseBody>()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        "unsupported value for \"hostname\": hostname must be a DNS label: \
+        at most 63 letters, digits and hyphens, not starting or ending with a \
+        hyphen"
+    );
+    assert_eq!(instance_get(&client, &instance_url).await.hostname, "the_host");
+
+    // The new configuration is used when the instance starts again, and the
+    // state reported by the sled agent agrees with it.
+    instance_post(&client, &instance_url, InstanceOp::Start).await;
+    instance_simulate(nexus, &instance.identity.id).await;
+    let instance = instance_get(&client, &instance_url).await;
+    assert_eq!(instance.runtime.run_state, InstanceState::Running);
+    assert_eq!(instance.ncpus.0, 2);
+    assert_eq!(
+        instance.memory.to_bytes(),
+        ByteCount::from_gibibytes_u32(2).to_bytes()
+    );
+}
+
 #[nexus_test]
 async fn test_instances_delete_fails_when_running_succeeds_when_stopped(
     cptestctx: &ControlPlaneTestContext,
@@ -569,6 +728,89 @@ async fn test_instances_delete_fails_when_running_succeeds_when_stopped(
         .unwrap();
 }
 
+#[nexus_test]
+async fn test_instance_operator_role(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let apictx = &cptestctx.server.apictx;
+    let nexus = &apictx.nexus;
+
+    create_org_and_project(client).await;
+    let instance_url = format!("{}/just-rainsticks", get_instances_url());
+    let reboot_url = format!("{}/reboot", instance_url);
+    let policy_url = format!("{}/policy", instance_url);
+    let instance = create_instance(
+        client,
+        ORGANIZATION_NAME,
+        PROJECT_NAME,
+        "just-rainsticks",
+    )
+    .await;
+    instance_simulate(nexus, &instance.identity.id).await;
+
+    // Without any role, the unprivileged user cannot even see the Instance.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::POST,
+        &reboot_url,
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Grant the unprivileged user "operator" on just this Instance.
+    let policy = shared::Policy {
+        role_assignments: vec![shared::RoleAssignment {
+            identity_type: shared::IdentityType::SiloUser,
+            identity_id: USER_TEST_UNPRIVILEGED.id(),
+            role_name: authz::InstanceRole::Operator,
+        }],
+    };
+    NexusRequest::object_put(client, &policy_url, Some(&policy))
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap();
+
+    // The operator can now reboot the Instance...
+    let instance: Instance = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &reboot_url)
+            .body(None as Option<&serde_json::Value>)
+            .expect_status(Some(StatusCode::ACCEPTED)),
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(instance.runtime.run_state, InstanceState::Rebooting);
+
+    // ... but cannot delete it or change who else has access to it.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::FORBIDDEN,
+        Method::DELETE,
+        &instance_url,
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::FORBIDDEN,
+        Method::PUT,
+        &policy_url,
+        &shared::Policy::<authz::InstanceRole> { role_assignments: vec![] },
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+}
+
 #[nexus_test]
 async fn test_instances_invalid_creation_returns_bad_request(
     cptestctx: &ControlPlaneTestContext,
@@ -676,6 +918,7 @@ async fn test_instance_create_saga_removes_instance_database_record(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -700,6 +943,7 @@ async fn test_instance_create_saga_removes_instance_database_record(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let _ =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -789,6 +1033,7 @@ async fn test_instance_with_single_explicit_ip_address(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -909,6 +1154,7 @@ async fn test_instance_with_new_custom_network_interfaces(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -1026,6 +1272,7 @@ async fn test_instance_create_delete_network_interface(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -1277,6 +1524,7 @@ async fn test_instance_update_network_interfaces(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -1683,6 +1931,7 @@ async fn test_instance_with_multiple_nics_unwinds_completely(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let builder =
         RequestBuilder::new(client, http::Method::POST, &url_instances)
@@ -1765,6 +2014,7 @@ async fn test_attach_one_disk_to_instance(cptestctx: &ControlPlaneTestContext) {
             },
         )],
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -1864,6 +2114,7 @@ async fn test_instance_fails_to_boot_with_disk(
             },
         )],
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -1970,6 +2221,7 @@ async fn test_attach_eight_disks_to_instance(
             })
             .collect(),
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -2078,6 +2330,7 @@ async fn test_cannot_attach_nine_disks_to_instance(
             })
             .collect(),
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -2210,6 +2463,7 @@ async fn test_cannot_attach_faulted_disks(cptestctx: &ControlPlaneTestContext) {
             })
             .collect(),
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -2326,6 +2580,7 @@ async fn test_disks_detached_when_instance_destroyed(
             })
             .collect(),
         start: true,
+        labels: Default::default(),
     };
 
     let url_instances = format!(
@@ -2432,6 +2687,7 @@ async fn test_instances_memory_rejected_less_than_min_memory_size(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -2482,6 +2738,7 @@ async fn test_instances_memory_not_divisible_by_min_memory_size(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
 
     let error = NexusRequest::new(
@@ -2657,6 +2914,7 @@ async fn test_instance_ephemeral_ip_from_correct_project(
         }],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
     let response =
         NexusRequest::objects_post(client, &url_instances, &instance_params)
@@ -2689,6 +2947,63 @@ async fn test_instance_ephemeral_ip_from_correct_project(
     );
 }
 
+#[nexus_test]
+async fn test_instance_metrics(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    create_org_and_project(client).await;
+
+    // Whenever we grab this URL, get the surrounding few seconds of metrics.
+    let instance_name = "just-rainsticks";
+    let metric_url = |metric_type: &str| {
+        let instance_url = format!("{}/{}", get_instances_url(), instance_name);
+        format!(
+            "{instance_url}/metrics/{metric_type}?start_time={:?}&end_time={:?}",
+            Utc::now() - chrono::Duration::seconds(2),
+            Utc::now() + chrono::Duration::seconds(2),
+        )
+    };
+
+    // Accessing metrics for an instance that doesn't exist should fail.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &metric_url("cpu_busy"),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("Expected a 404 for metrics of a nonexistent instance");
+
+    // Once the instance exists, the sled agent registers a producer for it
+    // and its measurements eventually show up.
+    create_instance(client, ORGANIZATION_NAME, PROJECT_NAME, instance_name)
+        .await;
+
+    let measurements = backoff::retry_notify(
+        backoff::internal_service_policy(),
+        || async {
+            let measurements: ResultsPage<Measurement> =
+                objects_list_page_authz(client, &metric_url("cpu_busy")).await;
+            if measurements.items.is_empty() {
+                return Err(backoff::BackoffError::transient("No metrics yet"));
+            }
+            Ok(measurements)
+        },
+        |_, _| {},
+    )
+    .await
+    .expect("Failed to query for measurements");
+
+    for item in &measurements.items {
+        let cumulative = match item.datum() {
+            Datum::CumulativeI64(c) => c,
+            _ => panic!("Unexpected datum type {:?}", item.datum()),
+        };
+        assert!(cumulative.start_time() <= item.timestamp());
+    }
+}
+
 async fn instance_get(
     client: &ClientTestContext,
     instance_url: &str,
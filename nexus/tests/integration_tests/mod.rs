@@ -11,9 +11,11 @@ mod console_api;
 mod datasets;
 mod device_auth;
 mod disks;
+mod external_dns;
 mod images;
 mod instances;
 mod ip_pools;
+mod oidc;
 mod organizations;
 mod oximeter;
 mod projects;
@@ -22,6 +24,7 @@ mod role_assignments;
 mod roles_builtin;
 mod router_routes;
 mod saml;
+mod service_accounts;
 mod silos;
 mod snapshots;
 mod ssh_keys;
@@ -0,0 +1,591 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests for logging in to silos with OpenID Connect, against an in-process
+//! mock provider
+
+use std::collections::BTreeMap;
+
+use nexus_test_utils::http_testing::{
+    AuthnMode, NexusRequest, RequestBuilder, TestResponse,
+};
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_nexus::authn::silos::{
+    pkce_code_challenge, IdentityProviderType, OidcLoginState,
+};
+use omicron_nexus::external_api::views;
+use omicron_nexus::external_api::{params, shared};
+
+use dropshot::test_util::ClientTestContext;
+use http::method::Method;
+use http::StatusCode;
+use nexus_test_utils::resource_helpers::{create_silo, object_create};
+
+use nexus_test_utils::ControlPlaneTestContext;
+use nexus_test_utils_macros::nexus_test;
+
+use dropshot::ResultsPage;
+use httptest::{matchers::*, responders::*, Expectation, Server};
+use openssl::pkey::Private;
+use openssl::rsa::Rsa;
+use serde_json::json;
+
+const SILO_NAME: &str = "oidc-silo";
+const PROVIDER_NAME: &str = "some-totally-real-oidc-provider";
+const CLIENT_ID: &str = "oxide-client";
+const CLIENT_SECRET: &str = "oxide-client-secret";
+const REDIRECT_URI: &str =
+    "https://customer.site/login/oidc-silo/oidc/some-totally-real-oidc-provider/callback";
+const KEY_ID: &str = "mock-key";
+
+/// An OpenID Provider that serves a discovery document, a JWK set and a token
+/// endpoint from an httptest server
+struct MockOidcProvider {
+    server: Server,
+    key: Rsa<Private>,
+}
+
+impl MockOidcProvider {
+    fn new() -> MockOidcProvider {
+        let server = Server::run();
+        let key = Rsa::generate(2048).unwrap();
+        let issuer = server.url("/realms/oxide").to_string();
+
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/realms/oxide/.well-known/openid-configuration",
+            ))
+            .times(..)
+            .respond_with(json_encoded(json!({
+                "issuer": issuer,
+                "authorization_endpoint":
+                    server.url("/realms/oxide/auth").to_string(),
+                "token_endpoint":
+                    server.url("/realms/oxide/token").to_string(),
+                "jwks_uri": server.url("/realms/oxide/certs").to_string(),
+                "code_challenge_methods_supported": ["plain", "S256"],
+            }))),
+        );
+
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/realms/oxide/certs",
+            ))
+            .times(..)
+            .respond_with(json_encoded(json!({
+                "keys": [{
+                    "kty": "RSA",
+                    "kid": KEY_ID,
+                    "use": "sig",
+                    "alg": "RS256",
+                    "n": base64::encode_config(
+                        key.n().to_vec(),
+                        base64::URL_SAFE_NO_PAD,
+                    ),
+                    "e": base64::encode_config(
+                        key.e().to_vec(),
+                        base64::URL_SAFE_NO_PAD,
+                    ),
+                }],
+            }))),
+        );
+
+        MockOidcProvider { server, key }
+    }
+
+    fn issuer(&self) -> String {
+        self.server.url("/realms/oxide").to_string()
+    }
+
+    /// Returns an ID token for `login`, signed by `key`, with `claims` on top
+    /// of the ones a provider would normally send
+    fn id_token(
+        &self,
+        key: &Rsa<Private>,
+        login: &BeganLogin,
+        claims: serde_json::Value,
+    ) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let mut all_claims = json!({
+            "iss": self.issuer(),
+            "aud": CLIENT_ID,
+            "sub": "some@customer.com",
+            "iat": now,
+            "exp": now + 300,
+            "nonce": login.nonce,
+        });
+        for (name, value) in claims.as_object().unwrap() {
+            all_claims[name] = value.clone();
+        }
+
+        let header = jsonwebtoken::Header {
+            kid: Some(KEY_ID.to_string()),
+            ..jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256)
+        };
+        jsonwebtoken::encode(
+            &header,
+            &all_claims,
+            &jsonwebtoken::EncodingKey::from_rsa_pem(
+                &key.private_key_to_pem().unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Expect `login`'s authorization code to be redeemed, returning
+    /// `id_token`
+    fn expect_token_request(&self, login: &BeganLogin, id_token: String) {
+        self.server.expect(
+            Expectation::matching(all_of![
+                request::method_path("POST", "/realms/oxide/token"),
+                request::body(url_decoded(contains((
+                    "grant_type",
+                    "authorization_code"
+                )))),
+                request::body(url_decoded(contains(("code", "some-code")))),
+                request::body(url_decoded(contains((
+                    "code_verifier",
+                    eq(login.login_state.code_verifier.clone())
+                )))),
+                request::body(url_decoded(contains(("client_id", CLIENT_ID)))),
+                request::body(url_decoded(contains((
+                    "client_secret",
+                    CLIENT_SECRET
+                )))),
+            ])
+            .respond_with(json_encoded(json!({
+                "access_token": "some-access-token",
+                "token_type": "Bearer",
+                "expires_in": 300,
+                "id_token": id_token,
+            }))),
+        );
+    }
+}
+
+async fn create_oidc_silo_and_idp(
+    client: &ClientTestContext,
+    provider: &MockOidcProvider,
+) -> views::OidcIdentityProvider {
+    create_silo(&client, SILO_NAME, true, shared::SiloIdentityMode::OidcJit)
+        .await;
+
+    object_create(
+        client,
+        &format!("/system/silos/{}/identity-providers/oidc", SILO_NAME),
+        &params::OidcIdentityProviderCreate {
+            identity: IdentityMetadataCreateParams {
+                name: PROVIDER_NAME.parse().unwrap(),
+                description: "a demo provider".to_string(),
+            },
+            issuer: provider.issuer(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some(CLIENT_SECRET.to_string()),
+            redirect_uri: REDIRECT_URI.to_string(),
+            group_claim_name: Some("groups".to_string()),
+        },
+    )
+    .await
+}
+
+/// What the test learns from starting a login
+struct BeganLogin {
+    /// value of the login cookie Nexus set
+    cookie: String,
+    /// secrets stored in the cookie
+    login_state: OidcLoginState,
+    /// state sent to the provider
+    state: String,
+    /// nonce sent to the provider
+    nonce: String,
+}
+
+/// Start logging in, checking the redirect to the provider's authorization
+/// endpoint
+async fn begin_login(
+    client: &ClientTestContext,
+    provider: &MockOidcProvider,
+) -> BeganLogin {
+    let result = NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::GET,
+            &format!("/login/{}/oidc/{}", SILO_NAME, PROVIDER_NAME),
+        )
+        .header(http::header::REFERER, "https://customer.site/projects")
+        .expect_status(Some(StatusCode::FOUND)),
+    )
+    .execute()
+    .await
+    .expect("expected success");
+
+    let location = result.headers["Location"].to_str().unwrap();
+    let location = reqwest::Url::parse(location).unwrap();
+    assert_eq!(
+        location.as_str().split('?').next().unwrap(),
+        provider.server.url("/realms/oxide/auth").to_string()
+    );
+    let query: BTreeMap<String, String> =
+        location.query_pairs().into_owned().collect();
+    assert_eq!(query["response_type"], "code");
+    assert_eq!(query["client_id"], CLIENT_ID);
+    assert_eq!(query["redirect_uri"], REDIRECT_URI);
+    assert!(query["scope"].split(' ').any(|scope| scope == "openid"));
+    assert_eq!(query["code_challenge_method"], "S256");
+
+    let set_cookie = result.headers["Set-Cookie"].to_str().unwrap();
+    assert!(set_cookie.contains(&format!(
+        "Path=/login/{}/oidc/{}/callback",
+        SILO_NAME, PROVIDER_NAME
+    )));
+    assert!(set_cookie.contains("HttpOnly"));
+    let (cookie, _) = set_cookie.split_once("; ").unwrap();
+    let (_, encoded) = cookie.split_once('=').unwrap();
+    let login_state = OidcLoginState::from_encoded(encoded).unwrap();
+
+    // The provider sees only the challenge for the verifier in the cookie.
+    assert_eq!(
+        query["code_challenge"],
+        pkce_code_challenge(&login_state.code_verifier)
+    );
+    assert_eq!(query["state"], login_state.state);
+    assert_eq!(query["nonce"], login_state.nonce);
+    assert_eq!(
+        login_state.referer.as_deref(),
+        Some("https://customer.site/projects")
+    );
+
+    BeganLogin {
+        cookie: cookie.to_string(),
+        state: query["state"].clone(),
+        nonce: query["nonce"].clone(),
+        login_state,
+    }
+}
+
+/// Return from the provider to the callback with the given `state`
+async fn finish_login(
+    client: &ClientTestContext,
+    login: &BeganLogin,
+    state: &str,
+    expected_status: StatusCode,
+) -> TestResponse {
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::GET,
+            &format!(
+                "/login/{}/oidc/{}/callback?code=some-code&state={}",
+                SILO_NAME, PROVIDER_NAME, state
+            ),
+        )
+        .header(http::header::COOKIE, login.cookie.clone())
+        .expect_status(Some(expected_status)),
+    )
+    .execute()
+    .await
+    .expect("expected success")
+}
+
+// Create an OIDC IdP
+#[nexus_test]
+async fn test_create_an_oidc_idp(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+
+    let silo_oidc_idp = create_oidc_silo_and_idp(client, &provider).await;
+    assert_eq!(silo_oidc_idp.issuer, provider.issuer());
+    assert_eq!(silo_oidc_idp.client_id, CLIENT_ID);
+
+    let fetched: views::OidcIdentityProvider = NexusRequest::object_get(
+        client,
+        &format!(
+            "/system/silos/{}/identity-providers/oidc/{}",
+            SILO_NAME, PROVIDER_NAME
+        ),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to make request")
+    .parsed_body()
+    .unwrap();
+    assert_eq!(fetched.identity.id, silo_oidc_idp.identity.id);
+
+    // Assert external authenticator opctx can read it
+    let nexus = &cptestctx.server.apictx.nexus;
+    let (.., retrieved_silo_idp_from_nexus) = IdentityProviderType::lookup(
+        &nexus.datastore(),
+        &nexus.opctx_external_authn(),
+        &SILO_NAME
+            .parse::<omicron_common::api::external::Name>()
+            .unwrap()
+            .into(),
+        &PROVIDER_NAME
+            .parse::<omicron_common::api::external::Name>()
+            .unwrap()
+            .into(),
+    )
+    .await
+    .unwrap();
+
+    match retrieved_silo_idp_from_nexus {
+        IdentityProviderType::Oidc(_) => {
+            // ok
+        }
+        IdentityProviderType::Saml(_) => {
+            panic!("expected an OIDC identity provider");
+        }
+    }
+
+    // The SAML login endpoints don't work with an OIDC provider.
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::GET,
+            &format!("/login/{}/saml/{}", SILO_NAME, PROVIDER_NAME),
+        )
+        .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .execute()
+    .await
+    .expect("expected success");
+}
+
+// OIDC IdPs can only be created in silos that use OIDC
+#[nexus_test]
+async fn test_create_oidc_idp_in_saml_silo(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+
+    create_silo(&client, "saml-silo", true, shared::SiloIdentityMode::SamlJit)
+        .await;
+
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            "/system/silos/saml-silo/identity-providers/oidc",
+        )
+        .body(Some(&params::OidcIdentityProviderCreate {
+            identity: IdentityMetadataCreateParams {
+                name: PROVIDER_NAME.parse().unwrap(),
+                description: "a demo provider".to_string(),
+            },
+            issuer: provider.issuer(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: REDIRECT_URI.to_string(),
+            group_claim_name: None,
+        }))
+        .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("unexpected success");
+}
+
+// The discovery document must be for the configured issuer
+#[nexus_test]
+async fn test_create_oidc_idp_issuer_mismatch(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path(
+            "GET",
+            "/.well-known/openid-configuration",
+        ))
+        .respond_with(json_encoded(json!({
+            "issuer": "https://some.other.idp.test",
+            "authorization_endpoint": "https://some.other.idp.test/auth",
+            "token_endpoint": "https://some.other.idp.test/token",
+            "jwks_uri": "https://some.other.idp.test/certs",
+        }))),
+    );
+
+    create_silo(&client, SILO_NAME, true, shared::SiloIdentityMode::OidcJit)
+        .await;
+
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("/system/silos/{}/identity-providers/oidc", SILO_NAME),
+        )
+        .body(Some(&params::OidcIdentityProviderCreate {
+            identity: IdentityMetadataCreateParams {
+                name: PROVIDER_NAME.parse().unwrap(),
+                description: "a demo provider".to_string(),
+            },
+            issuer: server.url("/").to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: REDIRECT_URI.to_string(),
+            group_claim_name: None,
+        }))
+        .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("unexpected success");
+}
+
+// Log in, provisioning the user and their groups
+#[nexus_test]
+async fn test_oidc_login(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+    create_oidc_silo_and_idp(client, &provider).await;
+
+    let login = begin_login(client, &provider).await;
+    let id_token = provider.id_token(
+        &provider.key,
+        &login,
+        json!({ "groups": ["SRE", " Admins ", ""] }),
+    );
+    provider.expect_token_request(&login, id_token);
+
+    let result =
+        finish_login(client, &login, &login.state, StatusCode::SEE_OTHER).await;
+    assert_eq!(
+        result.headers["Location"].to_str().unwrap(),
+        "https://customer.site/projects"
+    );
+
+    // The response sets the session cookie, and clears the login cookie.
+    let set_cookies: Vec<&str> = result
+        .headers
+        .get_all(http::header::SET_COOKIE)
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect();
+    assert_eq!(set_cookies.len(), 2);
+    let session_cookie = set_cookies
+        .iter()
+        .find(|cookie| cookie.starts_with("session="))
+        .expect("no session cookie")
+        .to_string();
+    assert!(set_cookies
+        .iter()
+        .any(|cookie| cookie.starts_with("oidc_login=;")
+            && cookie.contains("Max-Age=0")));
+
+    let session_me: views::User = NexusRequest::new(
+        RequestBuilder::new(client, Method::GET, "/session/me")
+            .header(http::header::COOKIE, session_cookie.clone())
+            .expect_status(Some(StatusCode::OK)),
+    )
+    .execute()
+    .await
+    .expect("expected success")
+    .parsed_body()
+    .unwrap();
+    assert_eq!(session_me.display_name, "some@customer.com");
+
+    let groups: ResultsPage<views::Group> = NexusRequest::new(
+        RequestBuilder::new(client, Method::GET, "/session/me/groups")
+            .header(http::header::COOKIE, session_cookie)
+            .expect_status(Some(StatusCode::OK)),
+    )
+    .execute()
+    .await
+    .expect("expected success")
+    .parsed_body()
+    .unwrap();
+    let mut group_names: Vec<&str> =
+        groups.items.iter().map(|g| g.display_name.as_str()).collect();
+    group_names.sort_unstable();
+    assert_eq!(group_names, vec!["Admins", "SRE"]);
+}
+
+// The state returned to the callback must match the login cookie
+#[nexus_test]
+async fn test_oidc_login_state_mismatch(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+    create_oidc_silo_and_idp(client, &provider).await;
+
+    // No token request is expected: the state is checked before the code is
+    // redeemed.
+    let login = begin_login(client, &provider).await;
+    finish_login(client, &login, "some-other-state", StatusCode::BAD_REQUEST)
+        .await;
+
+    // Without the login cookie, there's nothing to check the state against.
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::GET,
+            &format!(
+                "/login/{}/oidc/{}/callback?code=some-code&state={}",
+                SILO_NAME, PROVIDER_NAME, login.state
+            ),
+        )
+        .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .execute()
+    .await
+    .expect("expected success");
+}
+
+// The ID token's nonce must match the login cookie
+#[nexus_test]
+async fn test_oidc_login_nonce_mismatch(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+    create_oidc_silo_and_idp(client, &provider).await;
+
+    let login = begin_login(client, &provider).await;
+    let id_token = provider.id_token(
+        &provider.key,
+        &login,
+        json!({ "nonce": "some-other-nonce" }),
+    );
+    provider.expect_token_request(&login, id_token);
+
+    finish_login(client, &login, &login.state, StatusCode::BAD_REQUEST).await;
+}
+
+// The ID token must be signed by one of the provider's keys
+#[nexus_test]
+async fn test_oidc_login_bad_signature(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+    create_oidc_silo_and_idp(client, &provider).await;
+
+    let login = begin_login(client, &provider).await;
+    let other_key = Rsa::generate(2048).unwrap();
+    let id_token = provider.id_token(&other_key, &login, json!({}));
+    provider.expect_token_request(&login, id_token);
+
+    finish_login(client, &login, &login.state, StatusCode::BAD_REQUEST).await;
+}
+
+// The ID token must be meant for this client
+#[nexus_test]
+async fn test_oidc_login_wrong_audience(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let provider = MockOidcProvider::new();
+    create_oidc_silo_and_idp(client, &provider).await;
+
+    let login = begin_login(client, &provider).await;
+    let id_token = provider.id_token(
+        &provider.key,
+        &login,
+        json!({ "aud": "some-other-client" }),
+    );
+    provider.expect_token_request(&login, id_token);
+
+    finish_login(client, &login, &login.state, StatusCode::BAD_REQUEST).await;
+}
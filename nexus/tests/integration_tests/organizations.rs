@@ -2,13 +2,16 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::integration_tests::projects::delete_cascade_finish;
+use crate::integration_tests::projects::delete_cascade_start;
 use nexus_test_utils::http_testing::{AuthnMode, NexusRequest};
 use omicron_nexus::external_api::views::Organization;
 
 use http::method::Method;
 use http::StatusCode;
 use nexus_test_utils::resource_helpers::{
-    create_organization, create_project, objects_list_page_authz,
+    create_disk, create_organization, create_project, create_vpc,
+    objects_list_page_authz, DiskTest,
 };
 use nexus_test_utils::ControlPlaneTestContext;
 use nexus_test_utils_macros::nexus_test;
@@ -134,3 +137,48 @@ async fn test_organizations(cptestctx: &ControlPlaneTestContext) {
         .await
         .expect("failed to make request");
 }
+
+#[nexus_test]
+async fn test_organization_delete_cascade(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+
+    let org_name = "doomed-org";
+    let org_url = format!("/organizations/{}", org_name);
+    create_organization(&client, org_name).await;
+    create_project(&client, org_name, "p1").await;
+    create_project(&client, org_name, "p2").await;
+    let disk = create_disk(&client, org_name, "p1", "disk").await;
+    create_vpc(&client, org_name, "p2", "vpc").await;
+
+    // Without "cascade", a non-empty organization can't be deleted.
+    NexusRequest::expect_failure(
+        &client,
+        StatusCode::BAD_REQUEST,
+        Method::DELETE,
+        &org_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to make request");
+
+    // The request returns once the saga doing the work has started.
+    let saga_id = delete_cascade_start(&client, &org_url).await;
+    delete_cascade_finish(&client, &org_url, saga_id).await;
+
+    // The organization and everything in it are gone.
+    NexusRequest::expect_failure(
+        &client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &format!("/by-id/disks/{}", disk.identity.id),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to make request");
+
+    // The organization's name can be reused right away.
+    create_organization(&client, org_name).await;
+}
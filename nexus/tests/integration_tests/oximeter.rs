@@ -4,10 +4,16 @@
 
 //! Integration tests for oximeter collectors and producers.
 
+use dropshot::test_util::objects_list_page;
+use http::Method;
+use http::StatusCode;
 use nexus_test_utils::ControlPlaneTestContext;
 use nexus_test_utils_macros::nexus_test;
+use omicron_common::api::internal::nexus::ProducerEndpoint;
 use omicron_test_utils::dev::poll::{wait_for_condition, CondCheckError};
+use oximeter_client::types::ProducerDetails;
 use oximeter_db::DbWrite;
+use std::convert::Infallible;
 use std::net;
 use std::time::Duration;
 use uuid::Uuid;
@@ -317,3 +323,216 @@ async fn test_oximeter_reregistration() {
     );
     context.teardown().await;
 }
+
+// Helper to list the producers assigned to the test oximeter collector.
+async fn list_collector_producers(
+    context: &ControlPlaneTestContext,
+) -> Vec<ProducerDetails> {
+    let client = oximeter_client::Client::new(
+        &format!("http://{}", context.oximeter.server_address()),
+        context.logctx.log.new(o!("component" => "oximeter-client")),
+    );
+    client
+        .producers_list(None, None, None)
+        .await
+        .expect("Failed to list producers")
+        .into_inner()
+        .items
+}
+
+#[nexus_test]
+async fn test_oximeter_producer_unregister(context: &ControlPlaneTestContext) {
+    let producer_id: Uuid = nexus_test_utils::PRODUCER_UUID.parse().unwrap();
+
+    // The test producer should be collected from, and have a record in the DB.
+    let producers = list_collector_producers(context).await;
+    assert_eq!(producers.len(), 1);
+    assert_eq!(producers[0].endpoint.id, producer_id);
+
+    // Unregister the producer through Nexus.
+    let url = format!("/metrics/producers/{}", producer_id);
+    context
+        .internal_client
+        .make_request_no_body(Method::DELETE, &url, StatusCode::NO_CONTENT)
+        .await
+        .unwrap();
+
+    // Both the record in the DB and the collection task should be gone.
+    let conn = context.database.connect().await.unwrap();
+    let result = conn
+        .query("SELECT * FROM omicron.public.metric_producer;", &[])
+        .await
+        .unwrap();
+    assert!(result.is_empty(), "Expected the producer record to be deleted");
+    assert!(list_collector_producers(context).await.is_empty());
+
+    // Unregistering the producer again should fail.
+    context
+        .internal_client
+        .make_request_no_body(Method::DELETE, &url, StatusCode::NOT_FOUND)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_oximeter_reaps_unresponsive_producer() {
+    let mut context = nexus_test_utils::test_setup(
+        "test_oximeter_reaps_unresponsive_producer",
+    )
+    .await;
+    let producer_id: Uuid = nexus_test_utils::PRODUCER_UUID.parse().unwrap();
+
+    // Timeouts for checks. The collector gives up on a producer after a number
+    // of consecutive failures, each of which takes one collection interval.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const POLL_DURATION: Duration = Duration::from_secs(60);
+
+    // Helper to wait until the collector's record of the test producer
+    // satisfies a predicate.
+    async fn wait_for_producer<F>(context: &ControlPlaneTestContext, pred: F)
+    where
+        F: Fn(Option<&ProducerDetails>) -> bool,
+    {
+        let producer_id: Uuid =
+            nexus_test_utils::PRODUCER_UUID.parse().unwrap();
+        wait_for_condition(
+            || async {
+                let producers = list_collector_producers(context).await;
+                if pred(producers.iter().find(|p| p.endpoint.id == producer_id))
+                {
+                    Ok(())
+                } else {
+                    Err(CondCheckError::<Infallible>::NotYet)
+                }
+            },
+            &POLL_INTERVAL,
+            &POLL_DURATION,
+        )
+        .await
+        .expect("Collector's record of the producer did not reach the expected state")
+    }
+
+    // Wait for a successful collection from the producer.
+    wait_for_producer(&context, |p| {
+        p.map(|p| p.health.n_collections > 0).unwrap_or(false)
+    })
+    .await;
+
+    // Stop the producer, and wait for the collector to give up on it.
+    drop(context.producer);
+    wait_for_producer(&context, |p| p.is_none()).await;
+
+    // The collector also has Nexus remove the producer's assignment, so that
+    // it isn't collected from again after the collector restarts.
+    let conn = context.database.connect().await.unwrap();
+    wait_for_condition(
+        || async {
+            let rows = conn
+                .query("SELECT * FROM omicron.public.metric_producer;", &[])
+                .await
+                .unwrap();
+            if rows.is_empty() {
+                Ok(())
+            } else {
+                Err(CondCheckError::<Infallible>::NotYet)
+            }
+        },
+        &POLL_INTERVAL,
+        &POLL_DURATION,
+    )
+    .await
+    .expect("The reaped producer's assignment was not removed");
+
+    // Restarting the producer, which registers it with Nexus again, should
+    // have it collected from again.
+    context.producer = nexus_test_utils::start_producer_server(
+        context.server.http_server_internal.local_addr(),
+        producer_id,
+    )
+    .await
+    .expect("Failed to restart metric producer server");
+    nexus_test_utils::register_test_producer(&context.producer)
+        .expect("Failed to register producer");
+    wait_for_producer(&context, |p| {
+        p.map(|p| p.health.n_collections > 0).unwrap_or(false)
+    })
+    .await;
+
+    context.teardown().await;
+}
+
+#[nexus_test]
+async fn test_oximeter_assigned_producers_list(
+    context: &ControlPlaneTestContext,
+) {
+    let oximeter_id = nexus_test_utils::OXIMETER_UUID;
+    let producer_id: Uuid = nexus_test_utils::PRODUCER_UUID.parse().unwrap();
+
+    // The test producer should be assigned to the test collector.
+    let url = format!("/metrics/collectors/{}/producers", oximeter_id);
+    let page =
+        objects_list_page::<ProducerEndpoint>(&context.internal_client, &url)
+            .await;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, producer_id);
+    assert_eq!(page.items[0].address, context.producer.address());
+
+    // No producers are assigned to an unknown collector.
+    let url = format!("/metrics/collectors/{}/producers", Uuid::new_v4());
+    let page =
+        objects_list_page::<ProducerEndpoint>(&context.internal_client, &url)
+            .await;
+    assert!(page.items.is_empty());
+}
+
+#[tokio::test]
+async fn test_oximeter_collector_reloads_producers() {
+    let mut context = nexus_test_utils::test_setup(
+        "test_oximeter_collector_reloads_producers",
+    )
+    .await;
+    let oximeter_id = nexus_test_utils::OXIMETER_UUID.parse().unwrap();
+    let producer_id: Uuid = nexus_test_utils::PRODUCER_UUID.parse().unwrap();
+    let producers = list_collector_producers(&context).await;
+    assert_eq!(producers.len(), 1);
+    assert_eq!(producers[0].endpoint.id, producer_id);
+
+    // Restart the collector. The producer doesn't register itself again, so
+    // the collector must learn about it from Nexus.
+    context.oximeter.close().await.unwrap();
+    context.oximeter = nexus_test_utils::start_oximeter(
+        context.logctx.log.new(o!("component" => "oximeter")),
+        context.server.http_server_internal.local_addr(),
+        context.clickhouse.port(),
+        oximeter_id,
+    )
+    .await
+    .unwrap();
+    let producers = list_collector_producers(&context).await;
+    assert_eq!(producers.len(), 1);
+    assert_eq!(producers[0].endpoint.id, producer_id);
+    assert_eq!(
+        producers[0].endpoint.address,
+        context.producer.address().to_string()
+    );
+
+    // And it should actually collect from the producer.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const POLL_DURATION: Duration = Duration::from_secs(30);
+    wait_for_condition(
+        || async {
+            let producers = list_collector_producers(&context).await;
+            if producers[0].health.n_collections > 0 {
+                Ok(())
+            } else {
+                Err(CondCheckError::<Infallible>::NotYet)
+            }
+        },
+        &POLL_INTERVAL,
+        &POLL_DURATION,
+    )
+    .await
+    .expect("Expected a collection from the producer after restarting");
+
+    context.teardown().await;
+}
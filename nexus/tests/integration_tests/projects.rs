@@ -2,9 +2,46 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use crate::integration_tests::instances::instance_post;
+use crate::integration_tests::instances::instance_simulate;
+use crate::integration_tests::instances::InstanceOp;
+use dropshot::test_util::ClientTestContext;
+use dropshot::HttpErrorResponseBody;
+use http::Method;
+use http::StatusCode;
+use nexus_test_utils::http_testing::AuthnMode;
 use nexus_test_utils::http_testing::NexusRequest;
+use nexus_test_utils::http_testing::RequestBuilder;
+use nexus_test_utils::http_testing::TestResponse;
+use nexus_test_utils::resource_helpers::create_disk;
+use nexus_test_utils::resource_helpers::create_instance;
+use nexus_test_utils::resource_helpers::create_ip_pool;
+use nexus_test_utils::resource_helpers::create_router;
+use nexus_test_utils::resource_helpers::create_vpc;
+use nexus_test_utils::resource_helpers::grant_iam;
+use nexus_test_utils::resource_helpers::object_create;
 use nexus_test_utils::resource_helpers::project_get;
+use nexus_test_utils::resource_helpers::DiskTest;
+use omicron_common::api::external;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::IdentityMetadataUpdateParams;
+use omicron_common::api::external::Instance;
+use omicron_common::api::external::InstanceState;
+use omicron_common::api::external::Ipv4Net;
+use omicron_common::api::external::Labels;
+use omicron_nexus::authn::USER_TEST_UNPRIVILEGED;
+use omicron_nexus::authz;
+use omicron_nexus::authz::OrganizationRole;
+use omicron_nexus::authz::ProjectRole;
+use omicron_nexus::context::OpContext;
+use omicron_nexus::db::lookup::LookupPath;
+use omicron_nexus::external_api::params;
+use omicron_nexus::external_api::views;
 use omicron_nexus::external_api::views::Project;
+use omicron_test_utils::dev::poll::{wait_for_condition, CondCheckError};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use uuid::Uuid;
 
 use nexus_test_utils::resource_helpers::{create_organization, create_project};
 use nexus_test_utils::ControlPlaneTestContext;
@@ -70,3 +107,787 @@ async fn test_projects(cptestctx: &ControlPlaneTestContext) {
     assert_eq!(projects.len(), 1);
     assert_eq!(projects[0].identity.name, p1_name);
 }
+
+fn make_labels(pairs: &[(&str, &str)]) -> Labels {
+    Labels::try_from(
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+    )
+    .unwrap()
+}
+
+async fn list_project_names(
+    client: &ClientTestContext,
+    projects_url: &str,
+    params: &str,
+) -> Vec<String> {
+    // Use a tiny page size so that the label selector has to survive being
+    // carried in the page token.
+    NexusRequest::iter_collection_authn::<Project>(
+        client,
+        projects_url,
+        params,
+        Some(1),
+    )
+    .await
+    .expect("failed to list projects")
+    .all_items
+    .into_iter()
+    .map(|p| p.identity.name.to_string())
+    .collect()
+}
+
+#[nexus_test]
+async fn test_project_labels(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+
+    let org_name = "test-org";
+    create_organization(&client, &org_name).await;
+    let projects_url = format!("/organizations/{}/projects", org_name);
+
+    // Create some projects with labels.
+    for (name, labels) in [
+        ("prod-storage", make_labels(&[("env", "prod"), ("team", "storage")])),
+        ("prod-compute", make_labels(&[("env", "prod"), ("team", "compute")])),
+        ("dev-storage", make_labels(&[("env", "dev"), ("team", "storage")])),
+        ("unlabeled", Labels::default()),
+    ] {
+        let project: Project = NexusRequest::objects_post(
+            client,
+            &projects_url,
+            &params::ProjectCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: name.parse().unwrap(),
+                    description: String::from("a project"),
+                },
+                labels: labels.clone(),
+            },
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap()
+        .parsed_body()
+        .unwrap();
+        assert_eq!(project.labels, labels);
+    }
+
+    // List them with various selectors.
+    assert_eq!(
+        list_project_names(client, &projects_url, "").await,
+        ["dev-storage", "prod-compute", "prod-storage", "unlabeled"]
+    );
+    assert_eq!(
+        list_project_names(client, &projects_url, "label_selector=env%3Dprod")
+            .await,
+        ["prod-compute", "prod-storage"]
+    );
+    assert_eq!(
+        list_project_names(
+            client,
+            &projects_url,
+            "label_selector=env%3Dprod%2Cteam%3Dstorage"
+        )
+        .await,
+        ["prod-storage"]
+    );
+    assert_eq!(
+        list_project_names(
+            client,
+            &projects_url,
+            "label_selector=team%3Dstorage&sort_by=name_descending"
+        )
+        .await,
+        ["prod-storage", "dev-storage"]
+    );
+    assert!(list_project_names(
+        client,
+        &projects_url,
+        "label_selector=env%3Dstaging"
+    )
+    .await
+    .is_empty());
+
+    // Replace a project's labels and make sure the listing reflects that.
+    let project: Project = NexusRequest::object_put(
+        client,
+        &format!("{}/dev-storage", projects_url),
+        Some(&params::ProjectUpdate {
+            identity: IdentityMetadataUpdateParams {
+                name: None,
+                description: None,
+            },
+            labels: Some(make_labels(&[("env", "prod")])),
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(project.labels, make_labels(&[("env", "prod")]));
+    assert_eq!(
+        list_project_names(client, &projects_url, "label_selector=env%3Dprod")
+            .await,
+        ["dev-storage", "prod-compute", "prod-storage"]
+    );
+    assert_eq!(
+        list_project_names(
+            client,
+            &projects_url,
+            "label_selector=team%3Dstorage"
+        )
+        .await,
+        ["prod-storage"]
+    );
+
+    // An update that doesn't mention labels leaves them alone.
+    let project: Project = NexusRequest::object_put(
+        client,
+        &format!("{}/prod-storage", projects_url),
+        Some(&params::ProjectUpdate {
+            identity: IdentityMetadataUpdateParams {
+                name: None,
+                description: Some(String::from("still labeled")),
+            },
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(
+        project.labels,
+        make_labels(&[("env", "prod"), ("team", "storage")])
+    );
+
+    // Malformed selectors and labels are rejected.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::BAD_REQUEST,
+        Method::GET,
+        &format!("{}?label_selector=env", projects_url),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    let error: HttpErrorResponseBody = NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::BAD_REQUEST,
+        Method::POST,
+        &projects_url,
+        &serde_json::json!({
+            "name": "bad-labels",
+            "description": "a project",
+            "labels": { "env": "-prod" },
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert!(
+        error.message.contains("label value must begin and end"),
+        "unexpected error message: {}",
+        error.message
+    );
+}
+
+async fn project_move(
+    client: &ClientTestContext,
+    project_url: &str,
+    dst_org_name: &str,
+    authn_mode: AuthnMode,
+    expected_status: StatusCode,
+) -> TestResponse {
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("{}/move", project_url),
+        )
+        .body(Some(&params::ProjectMove {
+            organization: dst_org_name.parse().unwrap(),
+        }))
+        .expect_status(Some(expected_status)),
+    )
+    .authn_as(authn_mode)
+    .execute()
+    .await
+    .unwrap()
+}
+
+#[nexus_test]
+async fn test_project_move(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+
+    let src_org_name = "source-org";
+    let dst_org_name = "destination-org";
+    create_organization(&client, &src_org_name).await;
+    create_organization(&client, &dst_org_name).await;
+    let project =
+        create_project(&client, &src_org_name, "moving-project").await;
+    let src_project_url =
+        format!("/organizations/{}/projects/moving-project", src_org_name);
+    let dst_project_url =
+        format!("/organizations/{}/projects/moving-project", dst_org_name);
+
+    // The unprivileged user can view everything in the source Organization
+    // and create Projects in the destination Organization.  That's not enough
+    // to move a Project: that requires modifying the Project, too.
+    grant_iam(
+        client,
+        &format!("/organizations/{}", src_org_name),
+        OrganizationRole::Viewer,
+        USER_TEST_UNPRIVILEGED.id(),
+        AuthnMode::PrivilegedUser,
+    )
+    .await;
+    grant_iam(
+        client,
+        &format!("/organizations/{}", dst_org_name),
+        OrganizationRole::Collaborator,
+        USER_TEST_UNPRIVILEGED.id(),
+        AuthnMode::PrivilegedUser,
+    )
+    .await;
+    project_move(
+        client,
+        &src_project_url,
+        dst_org_name,
+        AuthnMode::UnprivilegedUser,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    // Being an admin of the Project isn't enough either: that would let the
+    // user take the Project away from the source Organization's admins.
+    grant_iam(
+        client,
+        &src_project_url,
+        ProjectRole::Admin,
+        USER_TEST_UNPRIVILEGED.id(),
+        AuthnMode::PrivilegedUser,
+    )
+    .await;
+    project_move(
+        client,
+        &src_project_url,
+        dst_org_name,
+        AuthnMode::UnprivilegedUser,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    project_get(&client, &src_project_url).await;
+
+    // Moving into an Organization that doesn't exist fails.
+    project_move(
+        client,
+        &src_project_url,
+        "no-such-org",
+        AuthnMode::PrivilegedUser,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+
+    // An admin of the source Organization may move the Project.
+    grant_iam(
+        client,
+        &format!("/organizations/{}", src_org_name),
+        OrganizationRole::Admin,
+        USER_TEST_UNPRIVILEGED.id(),
+        AuthnMode::PrivilegedUser,
+    )
+    .await;
+    let moved: Project = project_move(
+        client,
+        &src_project_url,
+        dst_org_name,
+        AuthnMode::UnprivilegedUser,
+        StatusCode::OK,
+    )
+    .await
+    .parsed_body()
+    .unwrap();
+    assert_eq!(moved.identity.id, project.identity.id);
+    assert_eq!(moved.identity.name, project.identity.name);
+    assert_ne!(moved.organization_id, project.organization_id);
+
+    // The Project, and what's inside it, are now only found in the destination
+    // Organization.
+    let fetched = project_get(&client, &dst_project_url).await;
+    assert_eq!(fetched.identity.id, project.identity.id);
+    assert_eq!(fetched.organization_id, moved.organization_id);
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &src_project_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    NexusRequest::object_get(
+        client,
+        &format!("{}/vpcs/default", dst_project_url),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    let src_projects = NexusRequest::iter_collection_authn::<Project>(
+        &client,
+        &format!("/organizations/{}/projects", src_org_name),
+        "",
+        None,
+    )
+    .await
+    .expect("failed to list projects")
+    .all_items;
+    assert!(src_projects.is_empty());
+
+    // The Project now inherits roles from the destination Organization, so
+    // the unprivileged user can modify it.  It can't move the Project back,
+    // though: it's only a collaborator in the destination Organization, so
+    // it can't modify that.
+    NexusRequest::object_put(
+        client,
+        &dst_project_url,
+        Some(&params::ProjectUpdate {
+            identity: IdentityMetadataUpdateParams {
+                name: None,
+                description: Some(String::from("moved")),
+            },
+            labels: None,
+        }),
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    project_move(
+        client,
+        &dst_project_url,
+        src_org_name,
+        AuthnMode::UnprivilegedUser,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    // Names must be unique within the destination Organization.
+    create_project(&client, &src_org_name, "moving-project").await;
+    let error: HttpErrorResponseBody = project_move(
+        client,
+        &src_project_url,
+        dst_org_name,
+        AuthnMode::PrivilegedUser,
+        StatusCode::BAD_REQUEST,
+    )
+    .await
+    .parsed_body()
+    .unwrap();
+    assert_eq!(error.message, "already exists: project \"moving-project\"");
+    let fetched = project_get(&client, &dst_project_url).await;
+    assert_eq!(fetched.identity.id, project.identity.id);
+}
+
+#[nexus_test]
+async fn test_project_delete_cascade(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    DiskTest::new(&cptestctx).await;
+    create_ip_pool(&client, "p0", None, None).await;
+
+    let org_name = "cascade-org";
+    let project_name = "doomed";
+    create_organization(&client, org_name).await;
+    create_project(&client, org_name, project_name).await;
+    let project_url =
+        format!("/organizations/{}/projects/{}", org_name, project_name);
+
+    // Fill the Project with one of everything: a running instance, a disk
+    // with a snapshot, and a VPC with a subnet and a router of its own (in
+    // addition to the default VPC).
+    let instance =
+        create_instance(&client, org_name, project_name, "running").await;
+    instance_simulate(nexus, &instance.identity.id).await;
+    let instance_url = format!("{}/instances/running", project_url);
+    let disk = create_disk(&client, org_name, project_name, "disk").await;
+    let snapshot: views::Snapshot = object_create(
+        client,
+        &format!("{}/snapshots", project_url),
+        &params::SnapshotCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "snapshot".parse().unwrap(),
+                description: String::from("a snapshot of the disk"),
+            },
+            disk: disk.identity.name.clone(),
+            labels: Default::default(),
+        },
+    )
+    .await;
+    let vpc = create_vpc(&client, org_name, project_name, "vpc").await;
+    let subnet: views::VpcSubnet = object_create(
+        client,
+        &format!("{}/vpcs/vpc/subnets", project_url),
+        &params::VpcSubnetCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "subnet".parse().unwrap(),
+                description: String::from("an extra subnet"),
+            },
+            ipv4_block: Ipv4Net("192.168.42.0/24".parse().unwrap()),
+            ipv6_block: None,
+        },
+    )
+    .await;
+    let router =
+        create_router(&client, org_name, project_name, "vpc", "router").await;
+
+    delete_cascade(cptestctx, &project_url, &instance_url).await;
+
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &project_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Everything that was in the Project is gone, too.  (That includes the
+    // instance's network interface, or its subnet couldn't have been deleted.)
+    let datastore = nexus.datastore();
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+    let lookup = || LookupPath::new(&opctx, &datastore);
+    assert!(matches!(
+        lookup().instance_id(instance.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().disk_id(disk.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().snapshot_id(snapshot.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().vpc_id(vpc.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().vpc_subnet_id(subnet.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().vpc_router_id(router.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+
+    // The Project's name can be reused right away.
+    create_project(&client, org_name, project_name).await;
+}
+
+// Saga recovery re-runs whichever action of the cascade was interrupted, so
+// every action has to cope with finding its work partly done.  Leave the work
+// of each one half finished, running the steps that it had got through twice
+// over, and check that the cascade still cleans everything up.
+#[nexus_test]
+async fn test_project_delete_cascade_idempotent(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    let disk_test = DiskTest::new(&cptestctx).await;
+    create_ip_pool(&client, "p0", None, None).await;
+
+    let org_name = "resumed-org";
+    let project_name = "doomed";
+    create_organization(&client, org_name).await;
+    create_project(&client, org_name, project_name).await;
+    let project_url =
+        format!("/organizations/{}/projects/{}", org_name, project_name);
+
+    let running =
+        create_instance(&client, org_name, project_name, "running").await;
+    instance_simulate(nexus, &running.identity.id).await;
+    let stopped =
+        create_instance(&client, org_name, project_name, "stopped").await;
+    instance_simulate(nexus, &stopped.identity.id).await;
+    let stopped_url = format!("{}/instances/stopped", project_url);
+    instance_post(&client, &stopped_url, InstanceOp::Stop).await;
+    instance_simulate(nexus, &stopped.identity.id).await;
+
+    let destroyed =
+        create_disk(&client, org_name, project_name, "destroyed").await;
+    let disk = create_disk(&client, org_name, project_name, "disk").await;
+    let mut snapshots = Vec::new();
+    for name in ["deleted", "snapshot"] {
+        let snapshot: views::Snapshot = object_create(
+            client,
+            &format!("{}/snapshots", project_url),
+            &params::SnapshotCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: name.parse().unwrap(),
+                    description: String::from("a snapshot of the disk"),
+                },
+                disk: disk.identity.name.clone(),
+                labels: Default::default(),
+            },
+        )
+        .await;
+        snapshots.push(snapshot);
+    }
+
+    let vpc = create_vpc(&client, org_name, project_name, "vpc").await;
+    let subnet: views::VpcSubnet = object_create(
+        client,
+        &format!("{}/vpcs/vpc/subnets", project_url),
+        &params::VpcSubnetCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "subnet".parse().unwrap(),
+                description: String::from("an extra subnet"),
+            },
+            ipv4_block: Ipv4Net("192.168.42.0/24".parse().unwrap()),
+            ipv6_block: None,
+        },
+    )
+    .await;
+    let router =
+        create_router(&client, org_name, project_name, "vpc", "router").await;
+
+    let datastore = nexus.datastore();
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+    let lookup = || LookupPath::new(&opctx, &datastore);
+
+    // DeleteInstances: the stopped instance's external IPs and network
+    // interfaces have been released, but its record is still there.
+    let (.., authz_instance) = lookup()
+        .instance_id(stopped.identity.id)
+        .lookup_for(authz::Action::Delete)
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        datastore
+            .deallocate_external_ip_by_instance_id(&opctx, stopped.identity.id)
+            .await
+            .unwrap();
+        datastore
+            .instance_delete_all_network_interfaces(&opctx, &authz_instance)
+            .await
+            .unwrap();
+    }
+
+    // DeleteSnapshots: one of the snapshots and its volumes are gone.
+    let (.., authz_snapshot, db_snapshot) =
+        lookup().snapshot_id(snapshots[0].identity.id).fetch().await.unwrap();
+    datastore
+        .project_delete_snapshot(&opctx, &authz_snapshot, &db_snapshot)
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        nexus.volume_delete(db_snapshot.volume_id).await.unwrap();
+        if let Some(volume_id) = db_snapshot.destination_volume_id {
+            nexus.volume_delete(volume_id).await.unwrap();
+        }
+    }
+
+    // The disk delete subsaga: one of the disks, and its volume, are gone.
+    let volume_id = datastore
+        .project_delete_disk_no_auth(&destroyed.identity.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        datastore
+            .project_delete_disk_no_auth(&destroyed.identity.id)
+            .await
+            .unwrap(),
+        volume_id
+    );
+    for _ in 0..2 {
+        nexus.volume_delete(volume_id).await.unwrap();
+    }
+
+    // DeleteVpcs: the extra subnet and the firewall rules are gone, but the
+    // routers and the VPC are still there.
+    let (.., authz_subnet, db_subnet) =
+        lookup().vpc_subnet_id(subnet.identity.id).fetch().await.unwrap();
+    datastore
+        .vpc_delete_subnet(&opctx, &db_subnet, &authz_subnet)
+        .await
+        .unwrap();
+    let (.., authz_vpc) = lookup()
+        .vpc_id(vpc.identity.id)
+        .lookup_for(authz::Action::Modify)
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        datastore
+            .vpc_delete_all_firewall_rules(&opctx, &authz_vpc)
+            .await
+            .unwrap();
+    }
+
+    // Now finish the job, by deleting the whole Organization.
+    let org_url = format!("/organizations/{}", org_name);
+    let running_url = format!("{}/instances/running", project_url);
+    delete_cascade(cptestctx, &org_url, &running_url).await;
+
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &org_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    for instance_id in [running.identity.id, stopped.identity.id] {
+        assert!(matches!(
+            lookup().instance_id(instance_id).fetch().await,
+            Err(external::Error::ObjectNotFound { .. })
+        ));
+    }
+    for disk_id in [destroyed.identity.id, disk.identity.id] {
+        assert!(matches!(
+            lookup().disk_id(disk_id).fetch().await,
+            Err(external::Error::ObjectNotFound { .. })
+        ));
+    }
+    for snapshot in &snapshots {
+        assert!(matches!(
+            lookup().snapshot_id(snapshot.identity.id).fetch().await,
+            Err(external::Error::ObjectNotFound { .. })
+        ));
+    }
+    assert!(matches!(
+        lookup().vpc_id(vpc.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+    assert!(matches!(
+        lookup().vpc_router_id(router.identity.id).fetch().await,
+        Err(external::Error::ObjectNotFound { .. })
+    ));
+
+    // Nothing was left behind in Crucible, either.
+    assert!(disk_test.crucible_resources_deleted().await);
+}
+
+/// Deletes the Organization or Project at `url` with `cascade`, finishing
+/// stopping the running instance at `instance_url` along the way
+async fn delete_cascade(
+    cptestctx: &ControlPlaneTestContext,
+    url: &str,
+    instance_url: &str,
+) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+
+    // The instance has to be stopped before it can be deleted.  The simulated
+    // sled agent only finishes stopping it when told to, so do that once the
+    // saga has asked for the instance to stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const POLL_DURATION: Duration = Duration::from_secs(30);
+    let finish_stopping = || async {
+        let instance: Instance = NexusRequest::object_get(client, instance_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .unwrap()
+            .parsed_body()
+            .unwrap();
+        if instance.runtime.run_state == InstanceState::Stopping {
+            instance_simulate(nexus, &instance.identity.id).await;
+            Ok(())
+        } else {
+            Err(CondCheckError::<external::Error>::NotYet)
+        }
+    };
+    let saga_id = delete_cascade_start(client, url).await;
+    let (stopped, ()) = futures::join!(
+        wait_for_condition(finish_stopping, &POLL_INTERVAL, &POLL_DURATION),
+        delete_cascade_finish(client, url, saga_id),
+    );
+    stopped.expect("instance was never asked to stop");
+}
+
+/// Starts deleting the Organization or Project at `url` with `cascade`, and
+/// returns the id of the saga doing it
+pub async fn delete_cascade_start(
+    client: &ClientTestContext,
+    url: &str,
+) -> Uuid {
+    let saga: serde_json::Value = NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::DELETE,
+            &format!("{}?cascade=true", url),
+        )
+        .expect_status(Some(StatusCode::ACCEPTED)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to start deleting with cascade")
+    .parsed_body()
+    .unwrap();
+    saga["id"].as_str().unwrap().parse().unwrap()
+}
+
+/// Waits for the saga `saga_id`, deleting the Organization or Project at
+/// `url`, to finish, and checks that it succeeded
+pub async fn delete_cascade_finish(
+    client: &ClientTestContext,
+    url: &str,
+    saga_id: Uuid,
+) {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const POLL_DURATION: Duration = Duration::from_secs(30);
+    let saga_url = format!("/system/sagas/{}", saga_id);
+    let saga_done = || async {
+        let saga: serde_json::Value =
+            NexusRequest::object_get(client, &saga_url)
+                .authn_as(AuthnMode::PrivilegedUser)
+                .execute()
+                .await
+                .unwrap()
+                .parsed_body()
+                .unwrap();
+        if saga["state"]["state"] == "running" {
+            Err(CondCheckError::<()>::NotYet)
+        } else {
+            Ok(saga)
+        }
+    };
+    let saga = wait_for_condition(saga_done, &POLL_INTERVAL, &POLL_DURATION)
+        .await
+        .expect("cascading delete never finished");
+    assert_eq!(saga["state"]["state"], "succeeded", "saga: {}", saga);
+
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+}
@@ -12,10 +12,14 @@ use http::Method;
 use http::StatusCode;
 use nexus_test_utils::http_testing::AuthnMode;
 use nexus_test_utils::http_testing::NexusRequest;
+use nexus_test_utils::resource_helpers::create_disk;
 use nexus_test_utils::resource_helpers::create_organization;
 use nexus_test_utils::resource_helpers::create_project;
+use nexus_test_utils::resource_helpers::create_vpc;
+use nexus_test_utils::resource_helpers::DiskTest;
 use nexus_test_utils::ControlPlaneTestContext;
 use nexus_test_utils_macros::nexus_test;
+use omicron_common::api::external::Disk;
 use omicron_common::api::external::ObjectIdentity;
 use omicron_nexus::authn::USER_TEST_UNPRIVILEGED;
 use omicron_nexus::authz;
@@ -398,12 +402,144 @@ async fn test_role_assignments_project(cptestctx: &ControlPlaneTestContext) {
     run_test(client, test_case).await;
 }
 
+#[nexus_test]
+async fn test_role_assignments_vpc(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let org_name = "test-org";
+    let project_name = "test-project";
+    let vpc_name = "test-vpc";
+    create_organization(client, org_name).await;
+    create_project(client, org_name, project_name).await;
+    create_vpc(client, org_name, project_name, vpc_name).await;
+    let vpc_url = format!(
+        "/organizations/{}/projects/{}/vpcs/{}",
+        org_name, project_name, vpc_name
+    );
+
+    struct VpcRoleAssignmentTest {
+        vpc_name: String,
+        vpc_url: String,
+        policy_url: String,
+    }
+    let test_case = VpcRoleAssignmentTest {
+        vpc_name: String::from(vpc_name),
+        vpc_url: vpc_url.clone(),
+        policy_url: format!("{}/policy", vpc_url),
+    };
+    impl RoleAssignmentTest for VpcRoleAssignmentTest {
+        type RoleType = authz::VpcRole;
+        const ROLE: Self::RoleType = authz::VpcRole::Admin;
+        const VISIBLE_TO_UNPRIVILEGED: bool = false;
+        fn policy_url(&self) -> String {
+            self.policy_url.clone()
+        }
+
+        fn verify_initial<'a, 'b, 'c, 'd>(
+            &'a self,
+            client: &'b ClientTestContext,
+            current_policy: &'c shared::Policy<Self::RoleType>,
+        ) -> BoxFuture<'d, ()>
+        where
+            'a: 'd,
+            'b: 'd,
+            'c: 'd,
+        {
+            resource_initial_conditions(client, &self.vpc_url, current_policy)
+                .boxed()
+        }
+
+        fn verify_privileged<'a, 'b, 'c>(
+            &'a self,
+            client: &'b ClientTestContext,
+        ) -> BoxFuture<'c, ()>
+        where
+            'a: 'c,
+            'b: 'c,
+        {
+            resource_privileged_conditions::<views::Vpc>(
+                client,
+                &self.vpc_url,
+                &self.vpc_name,
+            )
+            .boxed()
+        }
+    }
+
+    run_test(client, test_case).await;
+}
+
+#[nexus_test]
+async fn test_role_assignments_disk(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let org_name = "test-org";
+    let project_name = "test-project";
+    let disk_name = "test-disk";
+    DiskTest::new(&cptestctx).await;
+    create_organization(client, org_name).await;
+    create_project(client, org_name, project_name).await;
+    create_disk(client, org_name, project_name, disk_name).await;
+    let disk_url = format!(
+        "/organizations/{}/projects/{}/disks/{}",
+        org_name, project_name, disk_name
+    );
+
+    struct DiskRoleAssignmentTest {
+        disk_name: String,
+        disk_url: String,
+        policy_url: String,
+    }
+    let test_case = DiskRoleAssignmentTest {
+        disk_name: String::from(disk_name),
+        disk_url: disk_url.clone(),
+        policy_url: format!("{}/policy", disk_url),
+    };
+    impl RoleAssignmentTest for DiskRoleAssignmentTest {
+        type RoleType = authz::DiskRole;
+        const ROLE: Self::RoleType = authz::DiskRole::Admin;
+        const VISIBLE_TO_UNPRIVILEGED: bool = false;
+        fn policy_url(&self) -> String {
+            self.policy_url.clone()
+        }
+
+        fn verify_initial<'a, 'b, 'c, 'd>(
+            &'a self,
+            client: &'b ClientTestContext,
+            current_policy: &'c shared::Policy<Self::RoleType>,
+        ) -> BoxFuture<'d, ()>
+        where
+            'a: 'd,
+            'b: 'd,
+            'c: 'd,
+        {
+            resource_initial_conditions(client, &self.disk_url, current_policy)
+                .boxed()
+        }
+
+        fn verify_privileged<'a, 'b, 'c>(
+            &'a self,
+            client: &'b ClientTestContext,
+        ) -> BoxFuture<'c, ()>
+        where
+            'a: 'c,
+            'b: 'c,
+        {
+            resource_privileged_conditions::<Disk>(
+                client,
+                &self.disk_url,
+                &self.disk_name,
+            )
+            .boxed()
+        }
+    }
+
+    run_test(client, test_case).await;
+}
+
 /// Helper function for verifying the initial (unprivileged) conditions for most
 /// resources
 ///
-/// This is used for the Organization and Project tests today.  If we add
-/// support for assigning roles on other kinds of resources, we'd likely use
-/// this for those, too.  (It's Fleet and Silo that are special cases.)
+/// This is used for the Organization, Project, VPC, and Disk tests.  (It's
+/// Fleet and Silo that are special cases.)
 fn resource_initial_conditions<'a, 'b, 'c, 'd, T>(
     client: &'a ClientTestContext,
     resource_url: &'b str,
@@ -438,9 +574,8 @@ where
 
 /// Helper function for verifying the privileged conditions for most resources
 ///
-/// This is used for the Organization and Project tests today.  If we add
-/// support for assigning roles on other kinds of resources, we'd likely use
-/// this for those, too.  (It's Fleet and Silo that are special cases.)
+/// This is used for the Organization, Project, VPC, and Disk tests.  (It's
+/// Fleet and Silo that are special cases.)
 fn resource_privileged_conditions<'a, 'b, 'c, 'd, V>(
     client: &'a ClientTestContext,
     resource_url: &'b str,
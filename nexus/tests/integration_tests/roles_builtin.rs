@@ -28,10 +28,15 @@ async fn test_roles_builtin(cptestctx: &ControlPlaneTestContext) {
         .items;
 
     let expected = [
+        ("disk.admin", "Disk Administrator"),
+        ("disk.viewer", "Disk Viewer"),
         ("fleet.admin", "Fleet Administrator"),
         ("fleet.collaborator", "Fleet Collaborator"),
         ("fleet.external-authenticator", "Fleet External Authenticator"),
+        ("fleet.saml-metadata-refresher", "Fleet SAML Metadata Refresher"),
         ("fleet.viewer", "Fleet Viewer"),
+        ("instance.operator", "Instance Operator"),
+        ("instance.viewer", "Instance Viewer"),
         ("organization.admin", "Organization Administrator"),
         ("organization.collaborator", "Organization Collaborator"),
         ("organization.viewer", "Organization Viewer"),
@@ -41,6 +46,8 @@ async fn test_roles_builtin(cptestctx: &ControlPlaneTestContext) {
         ("silo.admin", "Silo Administrator"),
         ("silo.collaborator", "Silo Collaborator"),
         ("silo.viewer", "Silo Viewer"),
+        ("vpc.admin", "VPC Administrator"),
+        ("vpc.viewer", "VPC Viewer"),
     ];
     for (actual, expected) in roles.iter().zip(expected.iter()) {
         let (expected_name, expected_description) = expected;
@@ -0,0 +1,418 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests for service accounts and authenticating with their API keys
+
+use crate::integration_tests::instances::instance_simulate;
+use dropshot::test_util::ClientTestContext;
+use dropshot::ResultsPage;
+use http::{header, method::Method, StatusCode};
+use nexus_test_utils::http_testing::{AuthnMode, NexusRequest, RequestBuilder};
+use nexus_test_utils::resource_helpers::{
+    create_ip_pool, create_organization, create_project,
+    objects_list_page_authz,
+};
+use nexus_test_utils::ControlPlaneTestContext;
+use nexus_test_utils_macros::nexus_test;
+use omicron_common::api::external::{
+    ByteCount, IdentityMetadataCreateParams, Instance, InstanceCpuCount,
+    InstanceState,
+};
+use omicron_nexus::authz;
+use omicron_nexus::external_api::{params, shared, views};
+use uuid::Uuid;
+
+const ORGANIZATION_NAME: &str = "test-org";
+const ORGANIZATION_URL: &str = "/organizations/test-org";
+const PROJECT1_URL: &str = "/organizations/test-org/projects/springfield";
+const PROJECT2_URL: &str = "/organizations/test-org/projects/shelbyville";
+
+async fn service_account_create(
+    client: &ClientTestContext,
+    name: &str,
+    project_id: Option<Uuid>,
+) -> views::ServiceAccount {
+    NexusRequest::objects_post(
+        client,
+        "/service-accounts",
+        &params::ServiceAccountCreate {
+            identity: IdentityMetadataCreateParams {
+                name: name.parse().unwrap(),
+                description: String::from("a service account"),
+            },
+            project_id,
+        },
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to create service account")
+    .parsed_body()
+    .unwrap()
+}
+
+async fn key_create(
+    client: &ClientTestContext,
+    service_account_name: &str,
+) -> views::ServiceAccountKeyCreated {
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("/service-accounts/{}/keys", service_account_name),
+        )
+        .expect_status(Some(StatusCode::CREATED)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to create API key")
+    .parsed_body()
+    .unwrap()
+}
+
+/// Make a GET request for `url` using an API key, expecting `status`
+async fn get_with_key(
+    client: &ClientTestContext,
+    url: &str,
+    key: &str,
+    status: StatusCode,
+) {
+    RequestBuilder::new(client, Method::GET, url)
+        .header(header::AUTHORIZATION, format!("Bearer {}", key))
+        .expect_status(Some(status))
+        .execute()
+        .await
+        .unwrap_or_else(|_| panic!("unexpected response for GET {}", url));
+}
+
+/// Grant a service account `role` on the resource at `resource_url`
+async fn grant_role<T>(
+    client: &ClientTestContext,
+    resource_url: &str,
+    service_account_id: Uuid,
+    role: T,
+) where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let policy_url = format!("{}/policy", resource_url);
+    let mut policy: shared::Policy<T> =
+        NexusRequest::object_get(client, &policy_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .expect("failed to fetch policy")
+            .parsed_body()
+            .unwrap();
+    policy.role_assignments.push(shared::RoleAssignment {
+        identity_type: shared::IdentityType::ServiceAccount,
+        identity_id: service_account_id,
+        role_name: role,
+    });
+    NexusRequest::object_put(client, &policy_url, Some(&policy))
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to update policy");
+}
+
+/// Grant a service account the "viewer" role on the test Organization
+async fn grant_organization_viewer(
+    client: &ClientTestContext,
+    service_account_id: Uuid,
+) {
+    grant_role(
+        client,
+        ORGANIZATION_URL,
+        service_account_id,
+        authz::OrganizationRole::Viewer,
+    )
+    .await;
+}
+
+#[nexus_test]
+async fn test_service_accounts(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    create_organization(client, ORGANIZATION_NAME).await;
+    let project1 =
+        create_project(client, ORGANIZATION_NAME, "springfield").await;
+    create_project(client, ORGANIZATION_NAME, "shelbyville").await;
+
+    // Only silo administrators can create service accounts.
+    NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::FORBIDDEN,
+        Method::POST,
+        "/service-accounts",
+        &params::ServiceAccountCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "sneaky".parse().unwrap(),
+                description: String::new(),
+            },
+            project_id: None,
+        },
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .expect("unexpectedly created service account");
+
+    let scoped =
+        service_account_create(client, "deployer", Some(project1.identity.id))
+            .await;
+    assert_eq!(scoped.project_id, Some(project1.identity.id));
+    let unscoped = service_account_create(client, "auditor", None).await;
+    assert_eq!(unscoped.project_id, None);
+
+    let service_accounts = objects_list_page_authz::<views::ServiceAccount>(
+        client,
+        "/service-accounts",
+    )
+    .await
+    .items;
+    let names: Vec<_> = service_accounts
+        .iter()
+        .map(|sa| sa.identity.name.to_string())
+        .collect();
+    assert_eq!(names, vec!["auditor", "deployer"]);
+
+    let scoped_key = key_create(client, "deployer").await;
+    assert!(scoped_key.key.starts_with("oxide-key-"));
+    let unscoped_key = key_create(client, "auditor").await;
+
+    // A key authenticates its service account, which can do nothing until
+    // it's granted a role.
+    get_with_key(client, PROJECT1_URL, &scoped_key.key, StatusCode::NOT_FOUND)
+        .await;
+    get_with_key(
+        client,
+        PROJECT1_URL,
+        "oxide-key-bogus",
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+
+    // A role on the Organization gives the unscoped account access to all of
+    // its Projects, but the scoped account only to its own.
+    grant_organization_viewer(client, scoped.identity.id).await;
+    grant_organization_viewer(client, unscoped.identity.id).await;
+    get_with_key(client, PROJECT1_URL, &scoped_key.key, StatusCode::OK).await;
+    get_with_key(client, PROJECT2_URL, &scoped_key.key, StatusCode::NOT_FOUND)
+        .await;
+    get_with_key(
+        client,
+        ORGANIZATION_URL,
+        &scoped_key.key,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+    get_with_key(client, PROJECT1_URL, &unscoped_key.key, StatusCode::OK).await;
+    get_with_key(client, PROJECT2_URL, &unscoped_key.key, StatusCode::OK).await;
+    get_with_key(client, ORGANIZATION_URL, &unscoped_key.key, StatusCode::OK)
+        .await;
+
+    // Service accounts can't manage service accounts without being granted a
+    // role on the silo.
+    get_with_key(
+        client,
+        "/service-accounts/deployer",
+        &unscoped_key.key,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+
+    NexusRequest::object_delete(client, "/service-accounts/auditor")
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to delete service account");
+    get_with_key(
+        client,
+        PROJECT1_URL,
+        &unscoped_key.key,
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        "/service-accounts/auditor",
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("deleted service account still exists");
+}
+
+/// Make a POST request for `url` using an API key, expecting `status`
+async fn post_with_key<B, T>(
+    client: &ClientTestContext,
+    url: &str,
+    key: &str,
+    body: Option<&B>,
+    status: StatusCode,
+) -> T
+where
+    B: serde::Serialize,
+    T: serde::de::DeserializeOwned,
+{
+    RequestBuilder::new(client, Method::POST, url)
+        .header(header::AUTHORIZATION, format!("Bearer {}", key))
+        .body(body)
+        .expect_status(Some(status))
+        .execute()
+        .await
+        .unwrap_or_else(|_| panic!("unexpected response for POST {}", url))
+        .parsed_body()
+        .unwrap()
+}
+
+#[nexus_test]
+async fn test_scoped_service_account_instance(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    create_ip_pool(client, "p0", None, None).await;
+    create_organization(client, ORGANIZATION_NAME).await;
+    let project1 =
+        create_project(client, ORGANIZATION_NAME, "springfield").await;
+    let scoped =
+        service_account_create(client, "deployer", Some(project1.identity.id))
+            .await;
+    let key = key_create(client, "deployer").await.key;
+    grant_role(
+        client,
+        PROJECT1_URL,
+        scoped.identity.id,
+        authz::ProjectRole::Collaborator,
+    )
+    .await;
+
+    // Creating and starting an instance uses things outside the Project, like
+    // the IP pool its external address comes from and the sled it runs on.
+    // None of that is off-limits to an account scoped to the Project.
+    let instance: Instance = post_with_key(
+        client,
+        &format!("{}/instances", PROJECT1_URL),
+        &key,
+        Some(&params::InstanceCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "deployed".parse().unwrap(),
+                description: String::from("an instance"),
+            },
+            ncpus: InstanceCpuCount(1),
+            memory: ByteCount::from_gibibytes_u32(1),
+            hostname: String::from("deployed"),
+            user_data: vec![],
+            network_interfaces:
+                params::InstanceNetworkInterfaceAttachment::Default,
+            external_ips: vec![params::ExternalIpCreate::Ephemeral {
+                pool_name: None,
+            }],
+            disks: vec![],
+            start: true,
+            labels: Default::default(),
+        }),
+        StatusCode::CREATED,
+    )
+    .await;
+    instance_simulate(nexus, &instance.identity.id).await;
+
+    let instance_url = format!("{}/instances/deployed", PROJECT1_URL);
+    let no_body: Option<&serde_json::Value> = None;
+    for (action, state) in
+        [("stop", InstanceState::Stopped), ("start", InstanceState::Running)]
+    {
+        let _: Instance = post_with_key(
+            client,
+            &format!("{}/{}", instance_url, action),
+            &key,
+            no_body,
+            StatusCode::ACCEPTED,
+        )
+        .await;
+        instance_simulate(nexus, &instance.identity.id).await;
+        let instance: Instance =
+            NexusRequest::object_get(client, &instance_url)
+                .authn_as(AuthnMode::PrivilegedUser)
+                .execute()
+                .await
+                .expect("failed to fetch instance")
+                .parsed_body()
+                .unwrap();
+        assert_eq!(instance.runtime.run_state, state);
+    }
+}
+
+#[nexus_test]
+async fn test_service_account_key_rotation(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    create_organization(client, ORGANIZATION_NAME).await;
+    create_project(client, ORGANIZATION_NAME, "springfield").await;
+    let service_account =
+        service_account_create(client, "deployer", None).await;
+    grant_organization_viewer(client, service_account.identity.id).await;
+
+    // A service account may have several keys at once, so that a new key can
+    // be put into use before the old one is deleted.
+    let old_key = key_create(client, "deployer").await;
+    let new_key = key_create(client, "deployer").await;
+    assert_ne!(old_key.key, new_key.key);
+    get_with_key(client, PROJECT1_URL, &old_key.key, StatusCode::OK).await;
+    get_with_key(client, PROJECT1_URL, &new_key.key, StatusCode::OK).await;
+
+    let keys_url = "/service-accounts/deployer/keys";
+    let keys = NexusRequest::object_get(client, keys_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to list keys")
+        .parsed_body::<ResultsPage<views::ServiceAccountKey>>()
+        .unwrap()
+        .items;
+    let mut key_ids: Vec<_> = keys.iter().map(|key| key.id).collect();
+    key_ids.sort();
+    let mut expected_ids = vec![old_key.id, new_key.id];
+    expected_ids.sort();
+    assert_eq!(key_ids, expected_ids);
+
+    // Deleting a key stops it working, but not the others.
+    let old_key_url = format!("{}/{}", keys_url, old_key.id);
+    NexusRequest::object_delete(client, &old_key_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to delete key");
+    get_with_key(client, PROJECT1_URL, &old_key.key, StatusCode::UNAUTHORIZED)
+        .await;
+    get_with_key(client, PROJECT1_URL, &new_key.key, StatusCode::OK).await;
+
+    // Deleting it again fails, as does deleting a key as someone who can't
+    // see the service account.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::DELETE,
+        &old_key_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("deleted key twice");
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::DELETE,
+        &format!("{}/{}", keys_url, new_key.id),
+    )
+    .authn_as(AuthnMode::UnprivilegedUser)
+    .execute()
+    .await
+    .expect("unprivileged user deleted key");
+    get_with_key(client, PROJECT1_URL, &new_key.key, StatusCode::OK).await;
+}
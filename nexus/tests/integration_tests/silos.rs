@@ -134,6 +134,7 @@ async fn test_silos(cptestctx: &ControlPlaneTestContext) {
                 name: org_name.clone(),
                 description: String::new(),
             },
+            labels: Default::default(),
         },
     )
     .authn_as(AuthnMode::SiloUser(new_silo_user_id))
@@ -293,6 +294,7 @@ async fn test_silo_admin_group(cptestctx: &ControlPlaneTestContext) {
                 name: "myorg".parse().unwrap(),
                 description: "some org".into(),
             },
+            labels: Default::default(),
         },
     )
     .authn_as(AuthnMode::SiloUser(admin_group_user.id()))
@@ -552,6 +554,9 @@ async fn test_saml_idp_metadata_data_valid(
     )
     .await;
 
+    // Metadata supplied directly is never refreshed
+    assert!(silo_saml_idp.metadata_refresh.is_none());
+
     // Expect the SSO redirect when trying to log in
     let result = NexusRequest::new(
         RequestBuilder::new(
@@ -674,6 +679,174 @@ async fn test_saml_idp_metadata_data_invalid(
     .expect("unexpected success");
 }
 
+// Metadata supplied by URL is refreshed from there, and only replaced if the
+// new document is valid
+#[nexus_test]
+async fn test_saml_idp_metadata_refresh(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+    let opctx = &nexus.opctx_for_saml_metadata_refresh();
+
+    const SILO_NAME: &str = "saml-silo";
+    create_silo(&client, SILO_NAME, true, shared::SiloIdentityMode::SamlJit)
+        .await;
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/descriptor"))
+            .times(1)
+            .respond_with(status_code(200).body(SAML_IDP_DESCRIPTOR)),
+    );
+
+    let silo_saml_idp: SamlIdentityProvider = object_create(
+        client,
+        &format!("/system/silos/{}/identity-providers/saml", SILO_NAME),
+        &params::SamlIdentityProviderCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "some-totally-real-saml-provider"
+                    .to_string()
+                    .parse()
+                    .unwrap(),
+                description: "a demo provider".to_string(),
+            },
+
+            idp_metadata_source: params::IdpMetadataSource::Url {
+                url: server.url("/descriptor").to_string(),
+            },
+
+            idp_entity_id: "entity_id".to_string(),
+            sp_client_id: "client_id".to_string(),
+            acs_url: "http://acs".to_string(),
+            slo_url: "http://slo".to_string(),
+            technical_contact_email: "technical@fake".to_string(),
+
+            signing_keypair: None,
+
+            group_attribute_name: None,
+        },
+    )
+    .await;
+    server.verify_and_clear();
+
+    let status = silo_saml_idp.metadata_refresh.unwrap();
+    assert_eq!(status.url, server.url("/descriptor").to_string());
+    assert!(status.time_last_success.is_some());
+    assert_eq!(status.time_last_attempt, status.time_last_success);
+    assert!(status.last_error.is_none());
+
+    let idp_id = silo_saml_idp.identity.id;
+    let idp_url = &format!(
+        "/system/silos/{}/identity-providers/saml/{}",
+        SILO_NAME, silo_saml_idp.identity.name
+    );
+    let fetch_document = move || async move {
+        let (.., provider) = LookupPath::new(opctx, nexus.datastore())
+            .saml_identity_provider_id(idp_id)
+            .fetch()
+            .await
+            .unwrap();
+        provider.idp_metadata_document_string
+    };
+    let fetch_status = move || async move {
+        NexusRequest::object_get(client, idp_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .expect("failed to fetch SAML identity provider")
+            .parsed_body::<SamlIdentityProvider>()
+            .unwrap()
+            .metadata_refresh
+            .unwrap()
+    };
+
+    // Only the built-in user that does refreshes may do them.  (In particular,
+    // the user that authenticates external requests may not.)
+    assert!(nexus
+        .saml_identity_providers_refresh_metadata(nexus.opctx_external_authn())
+        .await
+        .is_err());
+
+    // The IdP publishes new metadata (e.g., after rotating its signing
+    // certificate).  A refresh stores it.
+    let rotated_descriptor = SAML_IDP_DESCRIPTOR
+        .replace("3017-08-30T19:10:29Z", "3018-08-30T19:10:29Z");
+    assert_ne!(rotated_descriptor, SAML_IDP_DESCRIPTOR);
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/descriptor"))
+            .times(1)
+            .respond_with(status_code(200).body(rotated_descriptor.clone())),
+    );
+    let nrefreshed =
+        nexus.saml_identity_providers_refresh_metadata(opctx).await.unwrap();
+    assert_eq!(nrefreshed, 1);
+    server.verify_and_clear();
+
+    assert_eq!(fetch_document().await, rotated_descriptor);
+    let refreshed_status = fetch_status().await;
+    assert!(refreshed_status.time_last_success > status.time_last_success);
+    assert_eq!(
+        refreshed_status.time_last_attempt,
+        refreshed_status.time_last_success
+    );
+    assert!(refreshed_status.last_error.is_none());
+
+    // If the IdP can't be reached, the stored metadata is kept and the error is
+    // reported.
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/descriptor"))
+            .times(1)
+            .respond_with(status_code(500)),
+    );
+    let nrefreshed =
+        nexus.saml_identity_providers_refresh_metadata(opctx).await.unwrap();
+    assert_eq!(nrefreshed, 0);
+    server.verify_and_clear();
+
+    assert_eq!(fetch_document().await, rotated_descriptor);
+    let failed_status = fetch_status().await;
+    assert_eq!(
+        failed_status.time_last_success,
+        refreshed_status.time_last_success
+    );
+    assert!(failed_status.time_last_attempt > failed_status.time_last_success);
+    let error = failed_status.last_error.unwrap();
+    assert!(error.contains("500"), "unexpected error: {}", error);
+
+    // Likewise if the IdP serves something that isn't valid metadata.
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/descriptor"))
+            .times(1)
+            .respond_with(status_code(200).body("not a descriptor")),
+    );
+    let nrefreshed =
+        nexus.saml_identity_providers_refresh_metadata(opctx).await.unwrap();
+    assert_eq!(nrefreshed, 0);
+    server.verify_and_clear();
+
+    assert_eq!(fetch_document().await, rotated_descriptor);
+    let invalid_status = fetch_status().await;
+    assert_eq!(
+        invalid_status.time_last_success,
+        refreshed_status.time_last_success
+    );
+    let error = invalid_status.last_error.unwrap();
+    assert!(error.contains("invalid metadata"), "unexpected error: {}", error);
+
+    // A later successful refresh clears the error.
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/descriptor"))
+            .times(1)
+            .respond_with(status_code(200).body(SAML_IDP_DESCRIPTOR)),
+    );
+    let nrefreshed =
+        nexus.saml_identity_providers_refresh_metadata(opctx).await.unwrap();
+    assert_eq!(nrefreshed, 1);
+    server.verify_and_clear();
+
+    assert_eq!(fetch_document().await, SAML_IDP_DESCRIPTOR);
+    assert!(fetch_status().await.last_error.is_none());
+}
+
 struct TestSiloUserProvisionTypes {
     identity_mode: shared::SiloIdentityMode,
     existing_silo_user: bool,
@@ -1713,6 +1886,7 @@ async fn test_jit_silo_constraints(cptestctx: &ControlPlaneTestContext) {
                 "/system/silos/jit/identity-providers/local/users",
                 &params::UserCreate {
                     external_id: params::UserId::from_str("dummy").unwrap(),
+                    password: params::UserPassword::InvalidPassword,
                 },
             )
             .authn_as(caller)
@@ -1911,6 +2085,248 @@ async fn test_local_silo_users(cptestctx: &ControlPlaneTestContext) {
     .await;
 }
 
+/// Fetch the groups that `user_id` is a member of
+async fn groups_of(
+    client: &dropshot::test_util::ClientTestContext,
+    user_id: Uuid,
+) -> Vec<views::Group> {
+    NexusRequest::object_get(client, "/session/me/groups")
+        .authn_as(AuthnMode::SiloUser(user_id))
+        .execute()
+        .await
+        .expect("failed to list user's groups")
+        .parsed_body::<dropshot::ResultsPage<views::Group>>()
+        .unwrap()
+        .items
+}
+
+/// Make a request with no body as `authn_mode`, expecting `status`
+async fn request_as(
+    client: &dropshot::test_util::ClientTestContext,
+    method: Method,
+    url: &str,
+    authn_mode: AuthnMode,
+    status: StatusCode,
+) {
+    NexusRequest::new(
+        RequestBuilder::new(client, method, url).expect_status(Some(status)),
+    )
+    .authn_as(authn_mode)
+    .execute()
+    .await
+    .unwrap_or_else(|_| panic!("unexpected response for {}", url));
+}
+
+#[nexus_test]
+async fn test_local_silo_groups(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.apictx.nexus;
+
+    let silo = create_silo(
+        &client,
+        "local",
+        true,
+        shared::SiloIdentityMode::LocalOnly,
+    )
+    .await;
+    let user_id = Uuid::new_v4();
+    nexus
+        .silo_user_create(silo.identity.id, user_id, "a-user".into())
+        .await
+        .unwrap();
+
+    // Create a group.
+    let url_groups = "/system/silos/local/identity-providers/local/groups";
+    let group_create =
+        params::GroupCreate { display_name: "devs".parse().unwrap() };
+    let group: views::Group =
+        NexusRequest::objects_post(client, url_groups, &group_create)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .expect("failed to create group")
+            .parsed_body()
+            .unwrap();
+    assert_eq!(group.display_name, "devs");
+    assert_eq!(group.silo_id, silo.identity.id);
+
+    // Group names are unique within a Silo, and only Silo admins can create
+    // groups.  Silo collaborators can't.
+    NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::BAD_REQUEST,
+        Method::POST,
+        url_groups,
+        &group_create,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("created group twice");
+    let policy_url = "/system/silos/local/policy";
+    let mut policy: shared::Policy<SiloRole> =
+        NexusRequest::object_get(client, policy_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .expect("failed to fetch policy")
+            .parsed_body()
+            .unwrap();
+    policy.role_assignments.push(shared::RoleAssignment {
+        identity_type: shared::IdentityType::SiloUser,
+        identity_id: user_id,
+        role_name: SiloRole::Collaborator,
+    });
+    NexusRequest::object_put(client, policy_url, Some(&policy))
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to update policy");
+    let other_group_create =
+        params::GroupCreate { display_name: "others".parse().unwrap() };
+    NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::FORBIDDEN,
+        Method::POST,
+        url_groups,
+        &other_group_create,
+    )
+    .authn_as(AuthnMode::SiloUser(user_id))
+    .execute()
+    .await
+    .expect("non-admin created group");
+
+    // Add the user to the group.  Adding them again does nothing.
+    let url_group = format!("{}/{}", url_groups, group.id);
+    let url_member = format!("{}/members/{}", url_group, user_id);
+    for _ in 0..2 {
+        request_as(
+            client,
+            Method::PUT,
+            &url_member,
+            AuthnMode::PrivilegedUser,
+            StatusCode::NO_CONTENT,
+        )
+        .await;
+    }
+    assert_eq!(groups_of(client, user_id).await, vec![group.clone()]);
+
+    // Roles granted to the group apply to its members.
+    policy.role_assignments.push(shared::RoleAssignment {
+        identity_type: shared::IdentityType::SiloGroup,
+        identity_id: group.id,
+        role_name: SiloRole::Admin,
+    });
+    NexusRequest::object_put(client, policy_url, Some(&policy))
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .expect("failed to update policy");
+    let other_group: views::Group =
+        NexusRequest::objects_post(client, url_groups, &other_group_create)
+            .authn_as(AuthnMode::SiloUser(user_id))
+            .execute()
+            .await
+            .expect("group member failed to create group")
+            .parsed_body()
+            .unwrap();
+
+    // Users can't be added to groups in other Silos.
+    let jit_silo =
+        create_silo(&client, "jit", true, shared::SiloIdentityMode::SamlJit)
+            .await;
+    let jit_user_id = Uuid::new_v4();
+    nexus
+        .silo_user_create(jit_silo.identity.id, jit_user_id, "jit-user".into())
+        .await
+        .unwrap();
+    request_as(
+        client,
+        Method::PUT,
+        &format!("{}/members/{}", url_group, jit_user_id),
+        AuthnMode::PrivilegedUser,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+
+    // Groups can't be managed through the "local" identity provider of a Silo
+    // that doesn't have one.
+    NexusRequest::expect_failure_with_body(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::POST,
+        "/system/silos/jit/identity-providers/local/groups",
+        &group_create,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("created group in SamlJit silo");
+
+    // A group can't be deleted while it has members.
+    request_as(
+        client,
+        Method::DELETE,
+        &url_group,
+        AuthnMode::PrivilegedUser,
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+
+    // Remove the user from the group.  Removing them again fails.
+    request_as(
+        client,
+        Method::DELETE,
+        &url_member,
+        AuthnMode::PrivilegedUser,
+        StatusCode::NO_CONTENT,
+    )
+    .await;
+    request_as(
+        client,
+        Method::DELETE,
+        &url_member,
+        AuthnMode::PrivilegedUser,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+    assert_eq!(groups_of(client, user_id).await, vec![]);
+
+    // Now the group can be deleted.
+    for status in [StatusCode::NO_CONTENT, StatusCode::NOT_FOUND] {
+        request_as(
+            client,
+            Method::DELETE,
+            &url_group,
+            AuthnMode::PrivilegedUser,
+            status,
+        )
+        .await;
+    }
+    request_as(
+        client,
+        Method::DELETE,
+        &format!("{}/{}", url_groups, other_group.id),
+        AuthnMode::PrivilegedUser,
+        StatusCode::NO_CONTENT,
+    )
+    .await;
+
+    // The roles granted to the group went away with it.
+    let policy: shared::Policy<SiloRole> =
+        NexusRequest::object_get(client, policy_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .expect("failed to fetch policy")
+            .parsed_body()
+            .unwrap();
+    assert!(!policy
+        .role_assignments
+        .iter()
+        .any(|assignment| assignment.identity_id == group.id));
+}
+
 /// Runs a sequence of tests for create, read, and delete of API-managed users
 async fn run_user_tests(
     client: &dropshot::test_util::ClientTestContext,
@@ -1945,6 +2361,7 @@ async fn run_user_tests(
         &url_user_create,
         &params::UserCreate {
             external_id: params::UserId::from_str("a-test-user").unwrap(),
+            password: params::UserPassword::InvalidPassword,
         },
     )
     .authn_as(authn_mode.clone())
@@ -2033,3 +2450,201 @@ async fn run_user_tests(
     println!("last_users: {:?}", last_users);
     assert_eq!(last_users, existing_users);
 }
+
+/// Attempts to log in to `silo_name` with a password, expecting `status`
+///
+/// On success, returns the session cookie to use for further requests.
+async fn local_login(
+    client: &dropshot::test_util::ClientTestContext,
+    silo_name: &str,
+    username: &str,
+    password: &str,
+    status: StatusCode,
+) -> Option<String> {
+    let response = RequestBuilder::new(
+        client,
+        Method::POST,
+        &format!("/login/{}/local", silo_name),
+    )
+    .body(Some(&params::UsernamePasswordCredentials {
+        username: params::UserId::from_str(username).unwrap(),
+        password: params::Password::from_str(password).unwrap(),
+    }))
+    .expect_status(Some(status))
+    .execute()
+    .await
+    .expect("unexpected response to login");
+    response.headers.get(http::header::SET_COOKIE).map(|cookie| {
+        let cookie = cookie.to_str().unwrap();
+        let (session_token, _) = cookie.split_once("; ").unwrap();
+        session_token.to_string()
+    })
+}
+
+#[nexus_test]
+async fn test_local_silo_password_login(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+
+    create_silo(&client, "local", true, shared::SiloIdentityMode::LocalOnly)
+        .await;
+    let url_local_idp_users =
+        "/system/silos/local/identity-providers/local/users";
+
+    // Create one user with a password and one without.
+    let user: views::User = NexusRequest::objects_post(
+        client,
+        url_local_idp_users,
+        &params::UserCreate {
+            external_id: params::UserId::from_str("alice").unwrap(),
+            password: params::UserPassword::Password(
+                params::Password::from_str("correct horse").unwrap(),
+            ),
+        },
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to create user")
+    .parsed_body()
+    .unwrap();
+    let other_user: views::User = NexusRequest::objects_post(
+        client,
+        url_local_idp_users,
+        &params::UserCreate {
+            external_id: params::UserId::from_str("bob").unwrap(),
+            password: params::UserPassword::InvalidPassword,
+        },
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to create user")
+    .parsed_body()
+    .unwrap();
+
+    // The right password gets a session for the user.
+    let session = local_login(
+        client,
+        "local",
+        "alice",
+        "correct horse",
+        StatusCode::NO_CONTENT,
+    )
+    .await
+    .expect("no session cookie after login");
+    let me: views::User =
+        RequestBuilder::new(client, Method::GET, "/session/me")
+            .header(http::header::COOKIE, &session)
+            .expect_status(Some(StatusCode::OK))
+            .execute()
+            .await
+            .expect("failed to fetch current user")
+            .parsed_body()
+            .unwrap();
+    assert_eq!(me.id, user.id);
+
+    // Wrong passwords, unknown users, users without passwords, and Silos
+    // without the local identity provider all fail.
+    for (silo_name, username, password, status) in [
+        ("local", "alice", "battery staple", StatusCode::UNAUTHORIZED),
+        ("local", "carol", "correct horse", StatusCode::UNAUTHORIZED),
+        ("local", "bob", "correct horse", StatusCode::UNAUTHORIZED),
+        ("default-silo", "alice", "correct horse", StatusCode::NOT_FOUND),
+    ] {
+        let session =
+            local_login(client, silo_name, username, password, status).await;
+        assert!(session.is_none());
+    }
+
+    // Once bob has a password, he can log in too.
+    let set_password_url = |user_id: Uuid| {
+        format!("{}/{}/set-password", url_local_idp_users, user_id)
+    };
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &set_password_url(other_user.id),
+        )
+        .body(Some(&params::UserPassword::Password(
+            params::Password::from_str("hunter2").unwrap(),
+        )))
+        .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to set password");
+    local_login(client, "local", "bob", "hunter2", StatusCode::NO_CONTENT)
+        .await
+        .expect("no session cookie after login");
+
+    // After too many failed attempts in a row, alice is locked out, even with
+    // the right password.  One attempt failed above.
+    for _ in 1..5 {
+        local_login(client, "local", "alice", "nope", StatusCode::UNAUTHORIZED)
+            .await;
+    }
+    local_login(
+        client,
+        "local",
+        "alice",
+        "correct horse",
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+
+    // Resetting her password unlocks her.
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &set_password_url(user.id))
+            .body(Some(&params::UserPassword::Password(
+                params::Password::from_str("new horse").unwrap(),
+            )))
+            .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to reset password");
+    local_login(
+        client,
+        "local",
+        "alice",
+        "correct horse",
+        StatusCode::UNAUTHORIZED,
+    )
+    .await;
+    local_login(client, "local", "alice", "new horse", StatusCode::NO_CONTENT)
+        .await
+        .expect("no session cookie after login");
+
+    // That's 9 attempts to log in as alice so far.  Only one more is allowed
+    // within the same minute, whatever the password.
+    local_login(client, "local", "alice", "new horse", StatusCode::NO_CONTENT)
+        .await;
+    local_login(
+        client,
+        "local",
+        "alice",
+        "new horse",
+        StatusCode::TOO_MANY_REQUESTS,
+    )
+    .await;
+
+    // Invalidating a password prevents logging in with it.
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &set_password_url(other_user.id),
+        )
+        .body(Some(&params::UserPassword::InvalidPassword))
+        .expect_status(Some(StatusCode::NO_CONTENT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to invalidate password");
+    local_login(client, "local", "bob", "hunter2", StatusCode::UNAUTHORIZED)
+        .await;
+}
@@ -89,6 +89,7 @@ async fn test_snapshot(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let global_image: views::GlobalImage = NexusRequest::objects_post(
@@ -115,6 +116,7 @@ async fn test_snapshot(cptestctx: &ControlPlaneTestContext) {
             image_id: global_image.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let base_disk: Disk = NexusRequest::new(
@@ -157,6 +159,7 @@ async fn test_snapshot(cptestctx: &ControlPlaneTestContext) {
             )],
             external_ips: vec![],
             start: true,
+            labels: Default::default(),
         },
     )
     .await;
@@ -176,6 +179,7 @@ async fn test_snapshot(cptestctx: &ControlPlaneTestContext) {
                 description: format!("instance {:?}", instance_name),
             },
             disk: base_disk_name,
+            labels: Default::default(),
         },
     )
     .await;
@@ -220,6 +224,7 @@ async fn test_snapshot_without_instance(cptestctx: &ControlPlaneTestContext) {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     let global_image: views::GlobalImage = NexusRequest::objects_post(
@@ -246,6 +251,7 @@ async fn test_snapshot_without_instance(cptestctx: &ControlPlaneTestContext) {
             image_id: global_image.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let base_disk: Disk = NexusRequest::new(
@@ -275,6 +281,7 @@ async fn test_snapshot_without_instance(cptestctx: &ControlPlaneTestContext) {
                 description: "not attached to instance".into(),
             },
             disk: base_disk_name,
+            labels: Default::default(),
         },
     )
     .await;
@@ -303,6 +310,7 @@ async fn test_delete_snapshot(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let base_disk: Disk = NexusRequest::new(
@@ -332,6 +340,7 @@ async fn test_delete_snapshot(cptestctx: &ControlPlaneTestContext) {
                 description: "not attached to instance".into(),
             },
             disk: base_disk_name,
+            labels: Default::default(),
         },
     )
     .await;
@@ -351,6 +360,7 @@ async fn test_delete_snapshot(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let _snap_disk: Disk = NexusRequest::new(
@@ -417,6 +427,7 @@ async fn test_reject_creating_disk_from_snapshot(
                     time_modified: Utc::now(),
                     time_deleted: None,
                 },
+                labels: Default::default(),
 
                 project_id,
                 disk_id: Uuid::new_v4(),
@@ -458,6 +469,7 @@ async fn test_reject_creating_disk_from_snapshot(
                         + db::model::BlockSize::Traditional.to_bytes(),
                 )
                 .unwrap(),
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::BAD_REQUEST)),
     )
@@ -487,6 +499,7 @@ async fn test_reject_creating_disk_from_snapshot(
 
                 size: ByteCount::try_from(1 * params::MIN_DISK_SIZE_BYTES)
                     .unwrap(),
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::BAD_REQUEST)),
     )
@@ -524,6 +537,7 @@ async fn test_reject_creating_disk_from_snapshot(
                         + db::model::BlockSize::AdvancedFormat.to_bytes(),
                 )
                 .unwrap(),
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::BAD_REQUEST)),
     )
@@ -574,6 +588,7 @@ async fn test_reject_creating_disk_from_illegal_snapshot(
                     time_modified: Utc::now(),
                     time_deleted: None,
                 },
+                labels: Default::default(),
 
                 project_id,
                 disk_id: Uuid::new_v4(),
@@ -621,6 +636,7 @@ async fn test_reject_creating_disk_from_illegal_snapshot(
                     db::model::BlockSize::AdvancedFormat.to_bytes() * 2,
                 )
                 .unwrap(),
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::BAD_REQUEST)),
     )
@@ -659,6 +675,7 @@ async fn test_cannot_snapshot_if_no_space(cptestctx: &ControlPlaneTestContext) {
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -685,6 +702,7 @@ async fn test_cannot_snapshot_if_no_space(cptestctx: &ControlPlaneTestContext) {
                     description: "not attached to instance".into(),
                 },
                 disk: base_disk_name,
+                labels: Default::default(),
             }))
             .expect_status(Some(StatusCode::SERVICE_UNAVAILABLE)),
     )
@@ -718,6 +736,7 @@ async fn test_create_snapshot_record_idempotent(
             time_modified: Utc::now(),
             time_deleted: None,
         },
+        labels: Default::default(),
 
         project_id,
         disk_id: Uuid::new_v4(),
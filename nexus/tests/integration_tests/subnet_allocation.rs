@@ -60,6 +60,7 @@ async fn create_instance_expect_failure(
         external_ips: vec![],
         disks: vec![],
         start: true,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -2,13 +2,28 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use nexus_test_utils::resource_helpers::objects_list_page_authz;
+use chrono::Utc;
+use dropshot::test_util::ClientTestContext;
+use http::{Method, StatusCode};
+use nexus_test_utils::http_testing::{AuthnMode, NexusRequest, RequestBuilder};
+use nexus_test_utils::resource_helpers::{
+    create_organization, create_project, grant_iam, objects_list_page_authz,
+};
 use nexus_test_utils::ControlPlaneTestContext;
 use nexus_test_utils_macros::nexus_test;
+use omicron_nexus::authn::USER_TEST_UNPRIVILEGED;
+use omicron_nexus::authz::ProjectRole;
+use omicron_nexus::external_api::params;
 use omicron_test_utils::dev::poll::{wait_for_condition, CondCheckError};
-use oximeter_db::TimeseriesSchema;
+use oximeter::types::{Datum, FieldValue};
+use oximeter_db::{Timeseries, TimeseriesSchema};
 use std::convert::Infallible;
+use std::num::NonZeroU32;
 use std::time::Duration;
+use uuid::Uuid;
+
+const TIMESERIES_QUERY_URL: &str = "/timeseries/query";
+const INTEGRATION_TIMESERIES: &str = "integration_target:integration_metric";
 
 #[nexus_test]
 async fn test_timeseries_schema(context: &ControlPlaneTestContext) {
@@ -50,3 +65,267 @@ async fn test_timeseries_schema(context: &ControlPlaneTestContext) {
         "Expected exactly one page of timeseries schema"
     );
 }
+
+fn integration_query() -> params::TimeseriesQuery {
+    params::TimeseriesQuery {
+        timeseries_name: INTEGRATION_TIMESERIES.to_string(),
+        criteria: vec![],
+        start_time: Utc::now() - chrono::Duration::minutes(5),
+        end_time: Utc::now() + chrono::Duration::minutes(5),
+        aggregation: None,
+    }
+}
+
+async fn timeseries_query(
+    client: &ClientTestContext,
+    query: &params::TimeseriesQuery,
+    authn_mode: AuthnMode,
+) -> Vec<Timeseries> {
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, TIMESERIES_QUERY_URL)
+            .body(Some(query))
+            .expect_status(Some(StatusCode::OK)),
+    )
+    .authn_as(authn_mode)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap()
+}
+
+// Wait until the integration producer's timeseries has been collected.
+async fn wait_for_integration_timeseries(
+    client: &ClientTestContext,
+) -> Vec<Timeseries> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+    const POLL_DURATION: Duration = Duration::from_secs(10);
+    wait_for_condition(
+        || async {
+            let timeseries = timeseries_query(
+                client,
+                &integration_query(),
+                AuthnMode::PrivilegedUser,
+            )
+            .await;
+            if timeseries.is_empty() {
+                Err(CondCheckError::<Infallible>::NotYet)
+            } else {
+                Ok(timeseries)
+            }
+        },
+        &POLL_INTERVAL,
+        &POLL_DURATION,
+    )
+    .await
+    .expect("Expected the integration timeseries to be collected")
+}
+
+#[nexus_test]
+async fn test_timeseries_query(context: &ControlPlaneTestContext) {
+    let client = &context.external_client;
+
+    let timeseries = wait_for_integration_timeseries(client).await;
+    assert_eq!(timeseries.len(), 1);
+    let timeseries = &timeseries[0];
+    assert_eq!(timeseries.timeseries_name, INTEGRATION_TIMESERIES);
+    assert!(timeseries.target.fields.iter().any(|field| field.name == "name"
+        && field.value
+            == FieldValue::String("integration-test-target".to_string())));
+    assert!(!timeseries.measurements.is_empty());
+
+    // Filtering on a field value that doesn't match selects nothing.
+    let mut query = integration_query();
+    query.criteria = vec!["name==not-the-integration-target".to_string()];
+    let timeseries =
+        timeseries_query(client, &query, AuthnMode::PrivilegedUser).await;
+    assert!(timeseries.is_empty());
+
+    // Filtering on a field the timeseries doesn't have is a client error.
+    let mut query = integration_query();
+    query.criteria = vec!["no_such_field==foo".to_string()];
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, TIMESERIES_QUERY_URL)
+            .body(Some(&query))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // So is an empty time range.
+    let mut query = integration_query();
+    query.end_time = query.start_time;
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, TIMESERIES_QUERY_URL)
+            .body(Some(&query))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+}
+
+#[nexus_test]
+async fn test_timeseries_query_authz(context: &ControlPlaneTestContext) {
+    let client = &context.external_client;
+
+    // The integration target doesn't describe any API resource, so it's only
+    // visible to users who can read the fleet.
+    wait_for_integration_timeseries(client).await;
+    let timeseries = timeseries_query(
+        client,
+        &integration_query(),
+        AuthnMode::UnprivilegedUser,
+    )
+    .await;
+    assert!(timeseries.is_empty());
+
+    // Unauthenticated users can't query anything at all.
+    RequestBuilder::new(client, Method::POST, TIMESERIES_QUERY_URL)
+        .body(Some(&integration_query()))
+        .expect_status(Some(StatusCode::UNAUTHORIZED))
+        .execute()
+        .await
+        .unwrap();
+}
+
+#[derive(Debug, Clone, oximeter::Target)]
+struct ProjectTarget {
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Clone, oximeter::Metric)]
+struct ProjectMetric {
+    pub datum: i64,
+}
+
+// A producer of a timeseries describing one Project
+#[derive(Debug, Clone)]
+struct ProjectProducer {
+    target: ProjectTarget,
+    metric: ProjectMetric,
+}
+
+impl oximeter::Producer for ProjectProducer {
+    fn produce(
+        &mut self,
+    ) -> Result<
+        Box<(dyn Iterator<Item = oximeter::types::Sample> + 'static)>,
+        oximeter::MetricsError,
+    > {
+        use oximeter::Metric;
+        let sample = oximeter::types::Sample::new(&self.target, &self.metric);
+        *self.metric.datum_mut() += 1;
+        Ok(Box::new(vec![sample].into_iter()))
+    }
+}
+
+#[nexus_test]
+async fn test_timeseries_query_project_scoped(
+    context: &ControlPlaneTestContext,
+) {
+    let client = &context.external_client;
+    create_organization(client, "test-org").await;
+    let project = create_project(client, "test-org", "test-project").await;
+    nexus_test_utils::register_producer(
+        &context.producer,
+        ProjectProducer {
+            target: ProjectTarget { project_id: project.identity.id },
+            metric: ProjectMetric { datum: 0 },
+        },
+    )
+    .unwrap();
+
+    let mut query = integration_query();
+    query.timeseries_name = "project_target:project_metric".to_string();
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+    const POLL_DURATION: Duration = Duration::from_secs(10);
+    wait_for_condition(
+        || async {
+            let timeseries =
+                timeseries_query(client, &query, AuthnMode::PrivilegedUser)
+                    .await;
+            if timeseries.is_empty() {
+                Err(CondCheckError::<Infallible>::NotYet)
+            } else {
+                Ok(())
+            }
+        },
+        &POLL_INTERVAL,
+        &POLL_DURATION,
+    )
+    .await
+    .expect("Expected the project timeseries to be collected");
+
+    // The unprivileged user can't see the Project yet, so they can't see its
+    // timeseries, either.
+    let timeseries =
+        timeseries_query(client, &query, AuthnMode::UnprivilegedUser).await;
+    assert!(timeseries.is_empty());
+
+    // Once they can read the Project, they get its data.
+    grant_iam(
+        client,
+        "/organizations/test-org/projects/test-project",
+        ProjectRole::Viewer,
+        USER_TEST_UNPRIVILEGED.id(),
+        AuthnMode::PrivilegedUser,
+    )
+    .await;
+    let timeseries =
+        timeseries_query(client, &query, AuthnMode::UnprivilegedUser).await;
+    assert_eq!(timeseries.len(), 1);
+    assert!(timeseries[0]
+        .target
+        .fields
+        .iter()
+        .any(|field| field.name == "project_id"
+            && field.value == FieldValue::Uuid(project.identity.id)));
+    assert!(!timeseries[0].measurements.is_empty());
+}
+
+#[nexus_test]
+async fn test_timeseries_query_aggregation(context: &ControlPlaneTestContext) {
+    let client = &context.external_client;
+    let raw = wait_for_integration_timeseries(client).await;
+    let n_measurements = raw[0].measurements.len() as i64;
+
+    // A single interval spanning the whole query counts every measurement.
+    // Query from the original start time to a fixed end time so that no new
+    // measurements can arrive.
+    let mut query = integration_query();
+    query.end_time = raw[0].measurements.last().unwrap().timestamp()
+        + chrono::Duration::milliseconds(1);
+    query.aggregation = Some(params::TimeseriesAggregation {
+        method: params::TimeseriesAggregationMethod::Count,
+        interval_seconds: NonZeroU32::new(3600).unwrap(),
+    });
+    let timeseries =
+        timeseries_query(client, &query, AuthnMode::PrivilegedUser).await;
+    assert_eq!(timeseries.len(), 1);
+    assert_eq!(timeseries[0].measurements.len(), 1);
+    let measurement = &timeseries[0].measurements[0];
+    assert_eq!(measurement.timestamp(), query.start_time);
+    match measurement.datum() {
+        Datum::I64(count) => assert_eq!(*count, n_measurements),
+        datum => panic!("Unexpected datum type {:?}", datum),
+    }
+
+    // The integration metric counts up from zero, so its largest value is
+    // the last one collected.
+    let last = match raw[0].measurements.last().unwrap().datum() {
+        Datum::I64(x) => *x as f64,
+        datum => panic!("Unexpected datum type {:?}", datum),
+    };
+    query.aggregation = Some(params::TimeseriesAggregation {
+        method: params::TimeseriesAggregationMethod::Max,
+        interval_seconds: NonZeroU32::new(3600).unwrap(),
+    });
+    let timeseries =
+        timeseries_query(client, &query, AuthnMode::PrivilegedUser).await;
+    assert_eq!(timeseries[0].measurements.len(), 1);
+    assert_eq!(timeseries[0].measurements[0].datum(), &Datum::F64(last));
+}
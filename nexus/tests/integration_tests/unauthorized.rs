@@ -177,6 +177,20 @@ lazy_static! {
                 .respond_with(status_code(200).body(SAML_IDP_DESCRIPTOR)),
         );
 
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/oidc/.well-known/openid-configuration",
+            ))
+            .times(1..)
+            .respond_with(json_encoded(serde_json::json!({
+                "issuer": server.url("/oidc").to_string(),
+                "authorization_endpoint": server.url("/oidc/auth").to_string(),
+                "token_endpoint": server.url("/oidc/token").to_string(),
+                "jwks_uri": server.url("/oidc/certs").to_string(),
+            }))),
+        );
+
         server
     };
 
@@ -188,6 +202,12 @@ lazy_static! {
             body: serde_json::to_value(&*DEMO_SILO_CREATE).unwrap(),
             id_routes: vec!["/system/by-id/silos/{id}"],
         },
+        // Create a Silo for OIDC identity providers
+        SetupReq::Post {
+            url: "/system/silos",
+            body: serde_json::to_value(&*DEMO_OIDC_SILO_CREATE).unwrap(),
+            id_routes: vec![],
+        },
         // Create a local User
         SetupReq::Post {
             url: &*DEMO_SILO_USERS_CREATE_URL,
@@ -195,6 +215,18 @@ lazy_static! {
             id_routes: vec![
                 &*DEMO_SILO_USER_ID_GET_URL,
                 &*DEMO_SILO_USER_ID_DELETE_URL,
+                &*DEMO_SILO_USER_ID_TOKENS_URL,
+                &*DEMO_SILO_USER_ID_SESSIONS_URL,
+                &*DEMO_SILO_USER_ID_SET_PASSWORD_URL,
+            ],
+        },
+        // Create a local Group
+        SetupReq::Post {
+            url: &*DEMO_SILO_GROUPS_CREATE_URL,
+            body: serde_json::to_value(&*DEMO_GROUP_CREATE).unwrap(),
+            id_routes: vec![
+                &*DEMO_SILO_GROUP_ID_URL,
+                &*DEMO_SILO_GROUP_ID_MEMBER_URL,
             ],
         },
         // Create an IP pool
@@ -280,12 +312,29 @@ lazy_static! {
             body: serde_json::to_value(&*SAML_IDENTITY_PROVIDER).unwrap(),
             id_routes: vec![],
         },
+        // Create an OIDC identity provider
+        SetupReq::Post {
+            url: &*OIDC_IDENTITY_PROVIDERS_URL,
+            body: serde_json::to_value(&*OIDC_IDENTITY_PROVIDER).unwrap(),
+            id_routes: vec![],
+        },
         // Create a SSH key
         SetupReq::Post {
             url: &*DEMO_SSHKEYS_URL,
             body: serde_json::to_value(&*DEMO_SSHKEY_CREATE).unwrap(),
             id_routes: vec![],
         },
+        // Create a service account and an API key for it
+        SetupReq::Post {
+            url: &*DEMO_SERVICE_ACCOUNTS_URL,
+            body: serde_json::to_value(&*DEMO_SERVICE_ACCOUNT_CREATE).unwrap(),
+            id_routes: vec![],
+        },
+        SetupReq::Post {
+            url: &*DEMO_SERVICE_ACCOUNT_KEYS_URL,
+            body: serde_json::Value::Null,
+            id_routes: vec![&*DEMO_SPECIFIC_SERVICE_ACCOUNT_KEY_URL],
+        },
     ];
 }
 
@@ -82,6 +82,7 @@ async fn create_global_image(client: &ClientTestContext) -> views::GlobalImage {
             version: "edge".into(),
         },
         block_size: params::BlockSize::try_from(512).unwrap(),
+        labels: Default::default(),
     };
 
     NexusRequest::objects_post(client, "/system/images", &image_create_params)
@@ -109,6 +110,7 @@ async fn create_base_disk(
             image_id: global_image.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -160,6 +162,7 @@ async fn test_snapshot_then_delete_disk(cptestctx: &ControlPlaneTestContext) {
                 description: "a snapshot!".to_string(),
             },
             disk: base_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -230,6 +233,7 @@ async fn test_delete_snapshot_then_disk(cptestctx: &ControlPlaneTestContext) {
                 description: "a snapshot!".to_string(),
             },
             disk: base_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -300,6 +304,7 @@ async fn test_multiple_snapshots(cptestctx: &ControlPlaneTestContext) {
                     description: "a snapshot!".to_string(),
                 },
                 disk: base_disk_name.clone(),
+                labels: Default::default(),
             },
         )
         .await;
@@ -370,6 +375,7 @@ async fn test_snapshot_prevents_other_disk(
                 description: "a snapshot!".to_string(),
             },
             disk: base_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -404,6 +410,7 @@ async fn test_snapshot_prevents_other_disk(
             image_id: global_image.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     NexusRequest::new(
@@ -475,6 +482,7 @@ async fn test_multiple_disks_multiple_snapshots_order_1(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let first_disk: Disk = NexusRequest::new(
@@ -504,6 +512,7 @@ async fn test_multiple_disks_multiple_snapshots_order_1(
                 description: "first snapshot!".to_string(),
             },
             disk: first_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -522,6 +531,7 @@ async fn test_multiple_disks_multiple_snapshots_order_1(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let second_disk: Disk = NexusRequest::new(
@@ -546,6 +556,7 @@ async fn test_multiple_disks_multiple_snapshots_order_1(
                 description: "second snapshot!".to_string(),
             },
             disk: second_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -622,6 +633,7 @@ async fn test_multiple_disks_multiple_snapshots_order_2(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let first_disk: Disk = NexusRequest::new(
@@ -651,6 +663,7 @@ async fn test_multiple_disks_multiple_snapshots_order_2(
                 description: "first snapshot!".to_string(),
             },
             disk: first_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -669,6 +682,7 @@ async fn test_multiple_disks_multiple_snapshots_order_2(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let second_disk: Disk = NexusRequest::new(
@@ -693,6 +707,7 @@ async fn test_multiple_disks_multiple_snapshots_order_2(
                 description: "second snapshot!".to_string(),
             },
             disk: second_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -763,6 +778,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
             block_size: params::BlockSize::try_from(512).unwrap(),
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let layer_1_disk: Disk = NexusRequest::new(
@@ -792,6 +808,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
                 description: "layer 1 snapshot!".to_string(),
             },
             disk: layer_1_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -810,6 +827,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
             snapshot_id: layer_1_snapshot.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let layer_2_disk: Disk = NexusRequest::new(
@@ -834,6 +852,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
                 description: "layer 2 snapshot!".to_string(),
             },
             disk: layer_2_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -852,6 +871,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
             snapshot_id: layer_2_snapshot.identity.id,
         },
         size: disk_size,
+        labels: Default::default(),
     };
 
     let layer_3_disk: Disk = NexusRequest::new(
@@ -876,6 +896,7 @@ async fn prepare_for_test_multiple_layers_of_snapshots(
                 description: "layer 3 snapshot!".to_string(),
             },
             disk: layer_3_disk_name.clone(),
+            labels: Default::default(),
         },
     )
     .await;
@@ -77,6 +77,7 @@ async fn test_vpcs(cptestctx: &ControlPlaneTestContext) {
                 },
                 ipv6_prefix: Some(bad_prefix),
                 dns_name: "abc".parse().unwrap(),
+                labels: Default::default(),
             })),
     )
     .authn_as(AuthnMode::PrivilegedUser)
@@ -133,6 +134,7 @@ async fn test_vpcs(cptestctx: &ControlPlaneTestContext) {
             description: Some("another description".to_string()),
         },
         dns_name: Some("def".parse().unwrap()),
+        labels: None,
     };
     let updated_vpc = vpc_put(&client, &vpc_url, update_params).await;
     assert_eq!(updated_vpc.identity.name, "new-name");
@@ -8,14 +8,14 @@ use crate::external_api::shared;
 use chrono::{DateTime, Utc};
 use omicron_common::api::external::{
     ByteCount, IdentityMetadataCreateParams, IdentityMetadataUpdateParams,
-    InstanceCpuCount, Ipv4Net, Ipv6Net, Name,
+    InstanceCpuCount, Ipv4Net, Ipv6Net, Labels, Name,
 };
 use schemars::JsonSchema;
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{net::IpAddr, str::FromStr};
+use std::{net::IpAddr, num::NonZeroU32, str::FromStr};
 use uuid::Uuid;
 
 // Silos
@@ -45,6 +45,8 @@ pub struct SiloCreate {
 pub struct UserCreate {
     /// username used to log in
     pub external_id: UserId,
+    /// password used to log in
+    pub password: UserPassword,
 }
 
 /// A username for a local-only user
@@ -89,6 +91,111 @@ impl JsonSchema for UserId {
     }
 }
 
+/// The longest password that may be set, in bytes
+///
+/// This caps the amount of work done hashing passwords.  It's far beyond any
+/// password a person would type.
+pub const MAX_PASSWORD_LENGTH: usize = 512;
+
+/// A password used to authenticate a user
+// The password is deliberately left out of the `Debug` output so that it
+// can't wind up in a log.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "String")]
+pub struct Password(String);
+
+impl AsRef<str> for Password {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for Password {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Password(<redacted>)")
+    }
+}
+
+impl FromStr for Password {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Password::try_from(String::from(value))
+    }
+}
+
+/// Used to impl `Deserialize`
+impl TryFrom<String> for Password {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(String::from("password may not be empty"));
+        }
+        if value.len() > MAX_PASSWORD_LENGTH {
+            return Err(format!(
+                "password may be at most {} bytes long",
+                MAX_PASSWORD_LENGTH
+            ));
+        }
+        Ok(Password(value))
+    }
+}
+
+impl JsonSchema for Password {
+    fn schema_name() -> String {
+        "Password".to_string()
+    }
+
+    fn json_schema(
+        _gen: &mut schemars::gen::SchemaGenerator,
+    ) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            metadata: Some(Box::new(schemars::schema::Metadata {
+                title: Some(
+                    "A password used to authenticate a user".to_string(),
+                ),
+                description: Some(format!(
+                    "Passwords may be up to {} bytes in length.",
+                    MAX_PASSWORD_LENGTH
+                )),
+                ..Default::default()
+            })),
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            string: Some(Box::new(schemars::schema::StringValidation {
+                max_length: Some(MAX_PASSWORD_LENGTH as u32),
+                min_length: Some(1),
+                pattern: None,
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Parameters for setting a user's password
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "user_password_value", content = "details")]
+#[serde(rename_all = "snake_case")]
+pub enum UserPassword {
+    /// Sets the user's password to the provided value
+    Password(Password),
+    /// Invalidates any current password (disabling password authentication)
+    InvalidPassword,
+}
+
+/// Credentials for local user login
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct UsernamePasswordCredentials {
+    pub username: UserId,
+    pub password: Password,
+}
+
+/// Create-time parameters for a [`Group`](crate::external_api::views::Group)
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct GroupCreate {
+    /// name of the group, unique within its Silo
+    pub display_name: Name,
+}
+
 // Silo identity providers
 
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
@@ -302,6 +409,32 @@ where
     Ok(v)
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct OidcIdentityProviderCreate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadataCreateParams,
+
+    /// the provider's issuer identifier, from which its discovery document is
+    /// fetched (at `<issuer>/.well-known/openid-configuration`)
+    pub issuer: String,
+
+    /// client id registered with the provider for this silo
+    pub client_id: String,
+
+    /// client secret registered with the provider, if the client is
+    /// confidential
+    pub client_secret: Option<String>,
+
+    /// endpoint where the provider should send the user back after login
+    /// (`/login/{silo_name}/oidc/{provider_name}/callback`)
+    pub redirect_uri: String,
+
+    /// If set, ID token claims with this name will be considered to denote a
+    /// user's group membership, where the claim value should be a group name
+    /// or an array of group names.
+    pub group_claim_name: Option<String>,
+}
+
 // ORGANIZATIONS
 
 /// Create-time parameters for an [`Organization`](crate::external_api::views::Organization)
@@ -309,6 +442,9 @@ where
 pub struct OrganizationCreate {
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the Organization
+    #[serde(default)]
+    pub labels: Labels,
 }
 
 /// Updateable properties of an [`Organization`](crate::external_api::views::Organization)
@@ -316,6 +452,8 @@ pub struct OrganizationCreate {
 pub struct OrganizationUpdate {
     #[serde(flatten)]
     pub identity: IdentityMetadataUpdateParams,
+    /// if present, replaces all of the Organization's labels
+    pub labels: Option<Labels>,
 }
 
 // PROJECTS
@@ -325,6 +463,9 @@ pub struct OrganizationUpdate {
 pub struct ProjectCreate {
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the Project
+    #[serde(default)]
+    pub labels: Labels,
 }
 
 /// Updateable properties of a [`Project`](crate::external_api::views::Project)
@@ -332,6 +473,25 @@ pub struct ProjectCreate {
 pub struct ProjectUpdate {
     #[serde(flatten)]
     pub identity: IdentityMetadataUpdateParams,
+    /// if present, replaces all of the Project's labels
+    pub labels: Option<Labels>,
+}
+
+/// Parameters for moving a [`Project`](crate::external_api::views::Project)
+/// into another Organization
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ProjectMove {
+    /// name of the Organization to move the Project into
+    pub organization: Name,
+}
+
+/// Query parameters for deleting an Organization or a Project
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct CascadeDelete {
+    /// if true, first delete everything the Organization or Project contains,
+    /// stopping any running instances
+    #[serde(default)]
+    pub cascade: bool,
 }
 
 // NETWORK INTERFACES
@@ -482,6 +642,9 @@ pub enum ExternalIpCreate {
 pub struct InstanceCreate {
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the Instance
+    #[serde(default)]
+    pub labels: Labels,
     pub ncpus: InstanceCpuCount,
     pub memory: ByteCount,
     pub hostname: String, // TODO-cleanup different type?
@@ -526,6 +689,22 @@ fn bool_true() -> bool {
     true
 }
 
+/// Updateable properties of an
+/// [`Instance`](omicron_common::api::external::Instance)
+///
+/// Properties that are omitted are left unchanged.  An instance's hardware
+/// (`ncpus`, `memory` and `hostname`) can only be reconfigured while it's
+/// stopped; the changes take effect the next time it starts.  Its labels can be
+/// changed at any time.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceUpdate {
+    pub ncpus: Option<InstanceCpuCount>,
+    pub memory: Option<ByteCount>,
+    pub hostname: Option<String>,
+    /// if present, replaces all of the Instance's labels
+    pub labels: Option<Labels>,
+}
+
 // If you change this, also update the error message in
 // `UserData::deserialize()` below.
 pub const MAX_USER_DATA_BYTES: usize = 32 * 1024; // 32 KiB
@@ -628,6 +807,9 @@ pub struct InstanceSerialConsoleData {
 pub struct VpcCreate {
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the VPC
+    #[serde(default)]
+    pub labels: Labels,
 
     /// The IPv6 prefix for this VPC.
     ///
@@ -644,6 +826,8 @@ pub struct VpcCreate {
 pub struct VpcUpdate {
     #[serde(flatten)]
     pub identity: IdentityMetadataUpdateParams,
+    /// if present, replaces all of the VPC's labels
+    pub labels: Option<Labels>,
     pub dns_name: Option<Name>,
 }
 
@@ -695,6 +879,9 @@ pub struct VpcRouterUpdate {
 
 pub const MIN_DISK_SIZE_BYTES: u32 = 1 << 30; // 1 GiB
 
+/// Largest number of bytes that can be imported into a disk in one bulk write
+pub const MAX_DISK_BULK_WRITE_BYTES: u32 = 512 * 1024; // 512 KiB
+
 #[derive(Copy, Clone, Debug, Deserialize, Serialize)]
 #[serde(try_from = "u32")] // invoke the try_from validation routine below
 pub struct BlockSize(pub u32);
@@ -762,6 +949,12 @@ pub enum DiskSource {
     Image { image_id: Uuid },
     /// Create a disk from a global image
     GlobalImage { image_id: Uuid },
+    /// Create a blank disk that will accept bulk writes of imported blocks
+    /// until the import is finalized
+    ImportingBlocks {
+        /// size of blocks for this Disk. valid values are: 512, 2048, or 4096
+        block_size: BlockSize,
+    },
 }
 
 /// Create-time parameters for a [`Disk`](omicron_common::api::external::Disk)
@@ -770,12 +963,45 @@ pub struct DiskCreate {
     /// common identifying metadata
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the Disk
+    #[serde(default)]
+    pub labels: Labels,
     /// initial source for this disk
     pub disk_source: DiskSource,
     /// total size of the Disk in bytes
     pub size: ByteCount,
 }
 
+/// Updateable properties of a [`Disk`](omicron_common::api::external::Disk)
+///
+/// Properties that are omitted are left unchanged.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DiskUpdate {
+    /// new total size of the Disk in bytes (disks can only grow)
+    pub size: Option<ByteCount>,
+    /// if present, replaces all of the Disk's labels
+    pub labels: Option<Labels>,
+}
+
+/// Parameters for importing blocks into a
+/// [`Disk`](omicron_common::api::external::Disk) with a bulk write
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImportBlocksBulkWrite {
+    /// byte offset of the first block to write
+    pub offset: u64,
+    /// the blocks to write, base64-encoded
+    pub base64_encoded_data: String,
+}
+
+/// Query parameters for importing blocks into a
+/// [`Disk`](omicron_common::api::external::Disk) with a bulk write of raw
+/// bytes
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImportBlocksBulkWriteOctets {
+    /// byte offset of the first block to write
+    pub offset: u64,
+}
+
 /// Parameters for the [`Disk`](omicron_common::api::external::Disk) to be
 /// attached or detached to an instance
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
@@ -825,6 +1051,9 @@ pub struct GlobalImageCreate {
     /// common identifying metadata
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the image
+    #[serde(default)]
+    pub labels: Labels,
 
     /// OS image distribution
     pub distribution: Distribution,
@@ -843,6 +1072,9 @@ pub struct ImageCreate {
     /// common identifying metadata
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the image
+    #[serde(default)]
+    pub labels: Labels,
 
     /// block size in bytes
     pub block_size: BlockSize,
@@ -859,6 +1091,9 @@ pub struct SnapshotCreate {
     /// common identifying metadata
     #[serde(flatten)]
     pub identity: IdentityMetadataCreateParams,
+    /// user-defined labels to attach to the snapshot
+    #[serde(default)]
+    pub labels: Labels,
 
     /// The name of the disk to be snapshotted
     pub disk: Name,
@@ -893,6 +1128,20 @@ pub struct SshKeyCreate {
     pub public_key: String,
 }
 
+// SERVICE ACCOUNTS
+
+/// Create-time parameters for a
+/// [`ServiceAccount`](crate::external_api::views::ServiceAccount)
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceAccountCreate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadataCreateParams,
+
+    /// If given, the service account can only use this Project and the
+    /// resources within it, whatever roles it's granted elsewhere
+    pub project_id: Option<Uuid>,
+}
+
 // METRICS
 
 /// Query parameters common to resource metrics endpoints.
@@ -903,3 +1152,52 @@ pub struct ResourceMetrics {
     /// An exclusive end time of metrics.
     pub end_time: DateTime<Utc>,
 }
+
+/// A query for measurements from any timeseries in the timeseries database.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TimeseriesQuery {
+    /// The name of the timeseries to query, of the form
+    /// `target_name:metric_name`.
+    pub timeseries_name: String,
+    /// Filters on the fields of the timeseries, each of the form
+    /// `field_name==value`. Other comparisons (`!=`, `>`, `>=`, `<`, `<=`,
+    /// `~=`) are supported where they make sense for the field's type.
+    #[serde(default)]
+    pub criteria: Vec<String>,
+    /// An inclusive start time of measurements.
+    pub start_time: DateTime<Utc>,
+    /// An exclusive end time of measurements.
+    pub end_time: DateTime<Utc>,
+    /// If provided, measurements from each timeseries are combined into one
+    /// value per interval, rather than being returned individually.
+    pub aggregation: Option<TimeseriesAggregation>,
+}
+
+/// The method used to combine measurements within an aggregation interval.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeseriesAggregationMethod {
+    /// The arithmetic mean of the measurements.
+    Mean,
+    /// The smallest measurement.
+    Min,
+    /// The largest measurement.
+    Max,
+    /// The sum of the measurements.
+    Sum,
+    /// The number of measurements.
+    Count,
+}
+
+/// How measurements of a timeseries query are aggregated.
+///
+/// Measurements are grouped into consecutive intervals of `interval_seconds`,
+/// starting at the query's start time. Only timeseries with scalar numeric
+/// data (integers, floats, and cumulative counters of either) can be
+/// aggregated.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TimeseriesAggregation {
+    pub method: TimeseriesAggregationMethod,
+    /// The width of each aggregation interval, in seconds.
+    pub interval_seconds: NonZeroU32,
+}
@@ -88,6 +88,7 @@ pub struct RoleAssignment<AllowedRoles> {
 pub enum IdentityType {
     SiloUser,
     SiloGroup,
+    ServiceAccount,
 }
 
 /// Describes how identities are managed and users are authenticated in this
@@ -101,10 +102,15 @@ pub enum SiloIdentityMode {
     /// groups).
     SamlJit,
 
+    /// Users are authenticated with OpenID Connect using an external
+    /// authentication provider.  As with `saml_jit`, the system updates
+    /// information about users and groups only during successful
+    /// authentication.
+    OidcJit,
+
     /// The system is the source of truth about users.  There is no linkage to
-    /// an external authentication provider or identity provider.
-    // NOTE: authentication for these users is not supported yet at all.  It
-    // will eventually be password-based.
+    /// an external authentication provider or identity provider.  Users log in
+    /// with passwords set through the API.
     LocalOnly,
 }
 
@@ -113,6 +119,7 @@ impl SiloIdentityMode {
         match self {
             SiloIdentityMode::LocalOnly => AuthenticationMode::Local,
             SiloIdentityMode::SamlJit => AuthenticationMode::Saml,
+            SiloIdentityMode::OidcJit => AuthenticationMode::Oidc,
         }
     }
 
@@ -120,6 +127,7 @@ impl SiloIdentityMode {
         match self {
             SiloIdentityMode::LocalOnly => UserProvisionType::ApiOnly,
             SiloIdentityMode::SamlJit => UserProvisionType::Jit,
+            SiloIdentityMode::OidcJit => UserProvisionType::Jit,
         }
     }
 }
@@ -131,6 +139,10 @@ pub enum AuthenticationMode {
     /// Authentication is via SAML using an external authentication provider
     Saml,
 
+    /// Authentication is via OpenID Connect using an external authentication
+    /// provider
+    Oidc,
+
     /// Authentication is local to the Oxide system
     Local,
 }
@@ -317,9 +329,138 @@ pub enum IpKind {
     Floating,
 }
 
+/// An action that a scoped access token may be used for
+///
+/// Each of these corresponds to a class of operations: for example, `modify`
+/// covers updating and deleting resources as well as changing their policies.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScopeAction {
+    Read,
+    ListChildren,
+    Modify,
+    CreateChild,
+    Operate,
+}
+
+impl TokenScopeAction {
+    fn label(&self) -> &'static str {
+        match self {
+            TokenScopeAction::Read => "read",
+            TokenScopeAction::ListChildren => "list_children",
+            TokenScopeAction::Modify => "modify",
+            TokenScopeAction::CreateChild => "create_child",
+            TokenScopeAction::Operate => "operate",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenScopeAction {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            TokenScopeAction::Read,
+            TokenScopeAction::ListChildren,
+            TokenScopeAction::Modify,
+            TokenScopeAction::CreateChild,
+            TokenScopeAction::Operate,
+        ]
+        .into_iter()
+        .find(|action| action.label() == s)
+        .ok_or_else(|| {
+            Error::invalid_request(&format!("unknown scope action: {:?}", s))
+        })
+    }
+}
+
+/// Limits on what an access token may be used for
+///
+/// A scoped token may only be used for the listed actions and, if an
+/// Organization or Project is given, only on that resource and the resources
+/// within it.  A token can never be used for more than its user's roles allow.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+pub struct TokenScope {
+    /// actions that the token may be used for
+    pub actions: Vec<TokenScopeAction>,
+    /// if given, the token may only be used within this Organization
+    pub organization_id: Option<Uuid>,
+    /// if given, the token may only be used within this Project
+    pub project_id: Option<Uuid>,
+}
+
+/// Parses an OAuth 2.0 "scope" parameter (RFC 6749 §3.3)
+///
+/// The scope is a space-separated list of action names (e.g., `read
+/// list_children`), optionally including one `organization:<id>` or
+/// `project:<id>` entry to limit the token to that resource.
+impl std::str::FromStr for TokenScope {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut scope = TokenScope {
+            actions: Vec::new(),
+            organization_id: None,
+            project_id: None,
+        };
+        for entry in s.split_whitespace() {
+            let (target, id) = match entry.split_once(':') {
+                None => {
+                    let action = entry.parse()?;
+                    if !scope.actions.contains(&action) {
+                        scope.actions.push(action);
+                    }
+                    continue;
+                }
+                Some((target, id)) => (target, id),
+            };
+            if scope.organization_id.is_some() || scope.project_id.is_some() {
+                return Err(Error::invalid_request(
+                    "scope may name at most one organization or project",
+                ));
+            }
+            let id = id.parse::<Uuid>().map_err(|e| {
+                Error::invalid_request(&format!(
+                    "bad id in scope entry {:?}: {}",
+                    entry, e
+                ))
+            })?;
+            match target {
+                "organization" => scope.organization_id = Some(id),
+                "project" => scope.project_id = Some(id),
+                _ => {
+                    return Err(Error::invalid_request(&format!(
+                        "unknown scope entry: {:?}",
+                        entry
+                    )))
+                }
+            }
+        }
+
+        if scope.actions.is_empty() {
+            return Err(Error::invalid_request(
+                "scope must include at least one action",
+            ));
+        }
+        scope.actions.sort();
+        Ok(scope)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Policy;
+    use super::TokenScope;
+    use super::TokenScopeAction;
     use super::MAX_ROLE_ASSIGNMENTS_PER_RESOURCE;
     use crate::external_api::shared::IpRange;
     use crate::external_api::shared::Ipv4Range;
@@ -423,4 +564,46 @@ mod test {
         assert!(IpRange::try_from((lo, hi)).is_ok());
         assert!(IpRange::try_from((hi, lo)).is_err());
     }
+
+    #[test]
+    fn test_token_scope_parsing() {
+        let project_id = uuid::Uuid::new_v4();
+        let scope: TokenScope =
+            format!("list_children project:{} read read", project_id)
+                .parse()
+                .unwrap();
+        assert_eq!(
+            scope,
+            TokenScope {
+                actions: vec![
+                    TokenScopeAction::Read,
+                    TokenScopeAction::ListChildren
+                ],
+                organization_id: None,
+                project_id: Some(project_id),
+            }
+        );
+
+        let org_id = uuid::Uuid::new_v4();
+        let scope: TokenScope =
+            format!("modify organization:{}", org_id).parse().unwrap();
+        assert_eq!(scope.actions, vec![TokenScopeAction::Modify]);
+        assert_eq!(scope.organization_id, Some(org_id));
+        assert_eq!(scope.project_id, None);
+
+        // There must be at least one action, every action must be known, and
+        // at most one resource may be named.
+        assert!("".parse::<TokenScope>().is_err());
+        assert!(format!("project:{}", project_id)
+            .parse::<TokenScope>()
+            .is_err());
+        assert!("read delete_everything".parse::<TokenScope>().is_err());
+        assert!("read project:not-a-uuid".parse::<TokenScope>().is_err());
+        assert!("read silo:3a2c9e1c-6d32-4bfb-8a1f-0e3dfe7d42f4"
+            .parse::<TokenScope>()
+            .is_err());
+        assert!(format!("read organization:{} project:{}", org_id, project_id)
+            .parse::<TokenScope>()
+            .is_err());
+    }
 }
@@ -10,7 +10,7 @@ use api_identity::ObjectIdentity;
 use chrono::DateTime;
 use chrono::Utc;
 use omicron_common::api::external::{
-    ByteCount, Digest, IdentityMetadata, Ipv4Net, Ipv6Net, Name,
+    ByteCount, Digest, IdentityMetadata, Ipv4Net, Ipv6Net, Labels, Name,
     ObjectIdentity, RoleName,
 };
 use schemars::JsonSchema;
@@ -69,6 +69,8 @@ pub struct Silo {
 pub enum IdentityProviderType {
     /// SAML identity provider
     Saml,
+    /// OpenID Connect identity provider
+    Oidc,
 }
 
 /// Client view of an [`IdentityProvider`]
@@ -103,6 +105,44 @@ pub struct SamlIdentityProvider {
 
     /// optional request signing public certificate (base64 encoded der file)
     pub public_cert: Option<String>,
+
+    /// status of periodic metadata refresh, present only if the idp metadata
+    /// was supplied by url
+    pub metadata_refresh: Option<SamlMetadataRefreshStatus>,
+}
+
+/// Status of the periodic re-fetch of a SAML identity provider's metadata
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SamlMetadataRefreshStatus {
+    /// url from which the idp metadata is fetched
+    pub url: String,
+
+    /// time of the most recent refresh attempt, if any
+    pub time_last_attempt: Option<DateTime<Utc>>,
+
+    /// time at which the stored metadata was last successfully fetched
+    pub time_last_success: Option<DateTime<Utc>>,
+
+    /// error from the most recent refresh attempt, if it failed
+    pub last_error: Option<String>,
+}
+
+#[derive(ObjectIdentity, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct OidcIdentityProvider {
+    #[serde(flatten)]
+    pub identity: IdentityMetadata,
+
+    /// the provider's issuer identifier
+    pub issuer: String,
+
+    /// client id registered with the provider for this silo
+    pub client_id: String,
+
+    /// endpoint where the provider sends the user back after login
+    pub redirect_uri: String,
+
+    /// name of the ID token claim that denotes a user's group membership
+    pub group_claim_name: Option<String>,
 }
 
 // ORGANIZATIONS
@@ -112,6 +152,8 @@ pub struct SamlIdentityProvider {
 pub struct Organization {
     #[serde(flatten)]
     pub identity: IdentityMetadata,
+    /// labels attached to this Organization
+    pub labels: Labels,
     // Important: Silo ID does not get presented to user
 }
 
@@ -124,6 +166,8 @@ pub struct Project {
     // intent in RFD 4?
     #[serde(flatten)]
     pub identity: IdentityMetadata,
+    /// labels attached to this Project
+    pub labels: Labels,
     pub organization_id: Uuid,
 }
 
@@ -135,6 +179,9 @@ pub struct GlobalImage {
     #[serde(flatten)]
     pub identity: IdentityMetadata,
 
+    /// labels attached to this image
+    pub labels: Labels,
+
     /// URL source of this image, if any
     pub url: Option<String>,
 
@@ -160,6 +207,9 @@ pub struct Image {
     #[serde(flatten)]
     pub identity: IdentityMetadata,
 
+    /// labels attached to this image
+    pub labels: Labels,
+
     /// The project the disk belongs to
     pub project_id: Uuid,
 
@@ -196,6 +246,9 @@ pub struct Snapshot {
     #[serde(flatten)]
     pub identity: IdentityMetadata,
 
+    /// labels attached to this snapshot
+    pub labels: Labels,
+
     pub project_id: Uuid,
     pub disk_id: Uuid,
 
@@ -212,6 +265,9 @@ pub struct Vpc {
     #[serde(flatten)]
     pub identity: IdentityMetadata,
 
+    /// labels attached to this VPC
+    pub labels: Labels,
+
     /// id for the project containing this VPC
     pub project_id: Uuid,
 
@@ -371,6 +427,55 @@ pub struct SshKey {
     pub public_key: String,
 }
 
+// SERVICE ACCOUNTS
+
+/// Client view of a [`ServiceAccount`]
+#[derive(ObjectIdentity, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceAccount {
+    #[serde(flatten)]
+    pub identity: IdentityMetadata,
+
+    /// The Project to which this service account's access is limited, if any
+    pub project_id: Option<Uuid>,
+}
+
+/// Client view of a [`ServiceAccount`]'s API key
+///
+/// The key itself is revealed only when it's created.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceAccountKey {
+    /// A unique, immutable, system-controlled identifier for the key.
+    pub id: Uuid,
+    /// timestamp when this key was created
+    pub time_created: DateTime<Utc>,
+}
+
+/// A newly created API key for a [`ServiceAccount`]
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceAccountKeyCreated {
+    /// A unique, immutable, system-controlled identifier for the key.
+    pub id: Uuid,
+    /// timestamp when this key was created
+    pub time_created: DateTime<Utc>,
+    /// The key, to be used as a bearer token.  It can't be retrieved again.
+    pub key: String,
+}
+
+// CONSOLE SESSIONS
+
+/// A web console session of the currently authenticated user
+///
+/// The session's token is never revealed.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ConsoleSession {
+    /// A unique, immutable, system-controlled identifier for the session.
+    pub id: Uuid,
+    /// timestamp when the user logged in
+    pub time_created: DateTime<Utc>,
+    /// timestamp when the session was last used
+    pub time_last_used: DateTime<Utc>,
+}
+
 // OAUTH 2.0 DEVICE AUTHORIZATION REQUESTS & TOKENS
 
 /// Response to an initial device authorization request.
@@ -396,6 +501,20 @@ pub struct DeviceAuthResponse {
     pub expires_in: u16,
 }
 
+/// An access token granted through a device authorization flow.
+/// The token itself is revealed only in the grant.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DeviceAccessToken {
+    /// A unique, immutable, system-controlled identifier for the token.
+    pub id: Uuid,
+    /// timestamp when this token was granted
+    pub time_created: DateTime<Utc>,
+    /// timestamp after which this token is no longer valid, if any
+    pub time_expires: Option<DateTime<Utc>>,
+    /// limits on what this token may be used for, if any
+    pub scope: Option<shared::TokenScope>,
+}
+
 /// Successful access token grant. See RFC 6749 §5.1.
 /// TODO-security: `expires_in`, `refresh_token`, etc.
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
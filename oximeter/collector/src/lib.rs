@@ -8,29 +8,37 @@
 
 use dropshot::{
     endpoint, ApiDescription, ConfigDropshot, ConfigLogging, HttpError,
-    HttpResponseUpdatedNoContent, HttpServer, HttpServerStarter,
-    RequestContext, TypedBody,
+    HttpResponseDeleted, HttpResponseOk, HttpResponseUpdatedNoContent,
+    HttpServer, HttpServerStarter, Path, Query, RequestContext, ResultsPage,
+    TypedBody,
 };
 use internal_dns_client::{
     multiclient::{ResolveError, Resolver},
     names::{ServiceName, SRV},
 };
-use omicron_common::address::{CLICKHOUSE_PORT, NEXUS_INTERNAL_PORT};
+use omicron_common::api::external::http_pagination::{
+    data_page_params_for, PaginatedById, ScanById, ScanParams,
+};
 use omicron_common::api::internal::nexus::ProducerEndpoint;
 use omicron_common::backoff;
 use oximeter::types::{ProducerResults, ProducerResultsItem};
 use oximeter_db::{Client, DbWrite};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use slog::{debug, error, info, o, trace, warn, Drain, Logger};
 use std::collections::{btree_map::Entry, BTreeMap};
 use std::net::{SocketAddr, SocketAddrV6};
-use std::path::Path;
+use std::ops::Bound;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::{sync::mpsc, sync::Mutex, task::JoinHandle, time::interval};
 use uuid::Uuid;
 
+mod self_stats;
+pub use self_stats::CollectionHealth;
+use self_stats::CollectionStats;
+
 /// Errors collecting metric data
 #[derive(Debug, Clone, Error)]
 pub enum Error {
@@ -45,6 +53,23 @@ pub enum Error {
 
     #[error(transparent)]
     ResolveError(#[from] ResolveError),
+
+    #[error("No producer is registered with ID {0}")]
+    NoSuchProducer(Uuid),
+
+    #[error("Error communicating with Nexus: {0}")]
+    Nexus(String),
+}
+
+impl From<Error> for HttpError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::NoSuchProducer(_) => {
+                HttpError::for_not_found(None, e.to_string())
+            }
+            _ => HttpError::for_internal_error(e.to_string()),
+        }
+    }
 }
 
 // Messages for controlling a collection task
@@ -57,21 +82,103 @@ enum CollectionMessage {
     // from its producer.
     Update(ProducerEndpoint),
     // Request that the task exit
-    #[allow(dead_code)]
     Shutdown,
 }
 
+// The result of a single attempt to collect from a producer.
+enum CollectionResult {
+    // The producer returned results
+    Ok(ProducerResults),
+    // The producer could not be reached, or did not return valid results
+    Failed,
+}
+
+// Request that the agent remove the collection task for a producer.
+//
+// This is sent by a collection task to the agent when it gives up on its
+// producer. The token identifies the exact task that gave up, so that the
+// agent does not remove a task that replaced it in the meantime.
+#[derive(Debug, Clone, Copy)]
+struct ReapRequest {
+    producer_id: Uuid,
+    token: Uuid,
+}
+
+// Make one request to a producer for its available metric data.
+async fn collect_once(
+    log: &Logger,
+    client: &reqwest::Client,
+    producer: &ProducerEndpoint,
+) -> CollectionResult {
+    let res = client
+        .get(format!(
+            "http://{}{}",
+            producer.address,
+            producer.collection_route()
+        ))
+        .send()
+        .await;
+    match res {
+        Ok(res) => {
+            if res.status().is_success() {
+                match res.json::<ProducerResults>().await {
+                    Ok(results) => {
+                        debug!(
+                            log,
+                            "collected {} total results",
+                            results.len();
+                        );
+                        CollectionResult::Ok(results)
+                    }
+                    Err(e) => {
+                        warn!(
+                            log,
+                            "failed to collect results from producer: {}",
+                            e.to_string();
+                        );
+                        CollectionResult::Failed
+                    }
+                }
+            } else {
+                warn!(
+                    log,
+                    "failed to receive metric results from producer";
+                    "status_code" => res.status().as_u16(),
+                );
+                CollectionResult::Failed
+            }
+        }
+        Err(e) => {
+            warn!(
+                log,
+                "failed to send collection request to producer: {}",
+                e.to_string();
+            );
+            CollectionResult::Failed
+        }
+    }
+}
+
 // Background task used to collect metrics from one producer on an interval.
 //
 // This function is started by the `OximeterAgent`, when a producer is registered. The task loops
-// endlessly, and collects metrics from the assigned producer on a timeout. The assigned agent can
-// also send a `CollectionMessage`, for example to update the collection interval. This is not
-// currently used, but will likely be exposed via control plane interfaces in the future.
+// and collects metrics from the assigned producer on a timeout, until it is asked to shut down. The
+// assigned agent can also send a `CollectionMessage`, for example to update the collection
+// interval.
+//
+// Each attempt to collect is recorded in `stats`, which are forwarded to the database along with
+// the producer's own data. If the producer fails `max_consecutive_failures` times in a row, the
+// task assumes the producer is gone, asks the agent to remove it with a `ReapRequest`, and exits.
+#[allow(clippy::too_many_arguments)]
 async fn collection_task(
     log: Logger,
     mut producer: ProducerEndpoint,
     mut inbox: mpsc::Receiver<CollectionMessage>,
     outbox: mpsc::Sender<ProducerResults>,
+    stats: Arc<Mutex<CollectionStats>>,
+    max_consecutive_failures: u32,
+    reaper: mpsc::Sender<ReapRequest>,
+    token: Uuid,
 ) {
     let client = reqwest::Client::new();
     let mut collection_timer = interval(producer.interval);
@@ -91,6 +198,7 @@ async fn collection_task(
                     }
                     Some(CollectionMessage::Shutdown) => {
                         debug!(log, "collection task received shutdown request");
+                        return;
                     },
                     Some(CollectionMessage::Collect) => {
                         debug!(log, "collection task received request to collect");
@@ -110,44 +218,36 @@ async fn collection_task(
             }
             _ = collection_timer.tick() => {
                 info!(log, "collecting from producer");
-                let res = client.get(format!("http://{}{}", producer.address, producer.collection_route()))
-                    .send()
-                    .await;
-                match res {
-                    Ok(res) => {
-                        if res.status().is_success() {
-                            match res.json::<ProducerResults>().await {
-                                Ok(results) => {
-                                    debug!(
-                                        log,
-                                        "collected {} total results",
-                                        results.len();
-                                    );
-                                    outbox.send(results).await.unwrap();
-                                },
-                                Err(e) => {
-                                    warn!(
-                                        log,
-                                        "failed to collect results from producer: {}",
-                                        e.to_string();
-                                    );
-                                }
-                            }
-                        } else {
-                            warn!(
-                                log,
-                                "failed to receive metric results from producer";
-                                "status_code" => res.status().as_u16(),
-                            );
+                let start = Instant::now();
+                let result = collect_once(&log, &client, &producer).await;
+                let (results, consecutive_failures) = {
+                    let mut stats = stats.lock().await;
+                    let mut results = match result {
+                        CollectionResult::Ok(results) => {
+                            stats.success(start.elapsed());
+                            results
                         }
-                    },
-                    Err(e) => {
-                        warn!(
-                            log,
-                            "failed to send collection request to producer: {}",
-                            e.to_string();
-                        );
+                        CollectionResult::Failed => {
+                            stats.failure();
+                            Vec::new()
+                        }
+                    };
+                    results.push(ProducerResultsItem::Ok(stats.samples()));
+                    (results, stats.health().consecutive_failures)
+                };
+                outbox.send(results).await.unwrap();
+
+                if consecutive_failures >= max_consecutive_failures {
+                    warn!(
+                        log,
+                        "producer failed too many consecutive collections, removing it";
+                        "consecutive_failures" => consecutive_failures,
+                    );
+                    let request = ReapRequest { producer_id: producer.id, token };
+                    if reaper.send(request).await.is_err() {
+                        error!(log, "failed to request removal of collection task");
                     }
+                    return;
                 }
             }
         }
@@ -157,12 +257,101 @@ async fn collection_task(
 // Struct representing a task for collecting metric data from a single producer
 #[derive(Debug)]
 struct CollectionTask {
+    // The producer this task collects from.
+    pub producer: ProducerEndpoint,
     // Channel used to send messages from the agent to the actual task. The task owns the other
     // side.
     pub inbox: mpsc::Sender<CollectionMessage>,
     // Handle to the actual tokio task running the collection loop.
     #[allow(dead_code)]
     pub task: JoinHandle<()>,
+    // Statistics about collections made by the task.
+    pub stats: Arc<Mutex<CollectionStats>>,
+    // Unique identifier for this task, used to match requests to reap it.
+    pub token: Uuid,
+}
+
+// Background task which removes the collection tasks that have given up on their producers.
+//
+// Each reaped producer is also unassigned from this collector in Nexus. Otherwise Nexus would still
+// list it among the collector's producers, and the collector would start collecting from it again
+// the next time it restarts.
+async fn reaper_task(
+    log: Logger,
+    collection_tasks: Arc<Mutex<BTreeMap<Uuid, CollectionTask>>>,
+    nexus_client: nexus_client::Client,
+    mut rx: mpsc::Receiver<ReapRequest>,
+) {
+    while let Some(ReapRequest { producer_id, token }) = rx.recv().await {
+        match collection_tasks.lock().await.entry(producer_id) {
+            Entry::Occupied(value) if value.get().token == token => {
+                value.remove();
+            }
+            // The producer was unregistered, or re-registered with a new task, since the request
+            // was sent.
+            _ => continue,
+        }
+        info!(
+            log,
+            "removed unresponsive metric producer";
+            "producer_id" => producer_id.to_string(),
+        );
+
+        // Nexus asks this collector to unregister the producer before removing its assignment,
+        // so this can't be done while holding the lock on the collection tasks.
+        let log = log.clone();
+        let client = nexus_client.clone();
+        tokio::spawn(async move {
+            unassign_producer(&log, &client, producer_id).await
+        });
+    }
+}
+
+// Ask Nexus to remove the assignment of a producer to this collector, retrying until it succeeds.
+async fn unassign_producer(
+    log: &Logger,
+    client: &nexus_client::Client,
+    producer_id: Uuid,
+) {
+    let unassign = || async {
+        match client.cpapi_producers_delete(&producer_id).await {
+            Ok(_) => Ok(()),
+            // The producer was unregistered in the meantime.
+            Err(nexus_client::Error::ErrorResponse(rv))
+                if rv.status() == reqwest::StatusCode::NOT_FOUND =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(backoff::BackoffError::transient(e.to_string())),
+        }
+    };
+    let log_failure = |error, delay| {
+        warn!(
+            log,
+            "failed to unassign removed producer, will retry in {:?}", delay;
+            "producer_id" => producer_id.to_string(),
+            "error" => ?error,
+        );
+    };
+    match backoff::retry_notify(
+        backoff::internal_service_policy(),
+        unassign,
+        log_failure,
+    )
+    .await
+    {
+        Ok(()) => info!(
+            log,
+            "unassigned removed producer";
+            "producer_id" => producer_id.to_string(),
+        ),
+        Err(e) => error!(
+            log,
+            "failed to unassign removed producer";
+            "producer_id" => producer_id.to_string(),
+            "error" => ?e,
+        ),
+    }
 }
 
 // Aggregation point for all results, from all collection tasks.
@@ -253,14 +442,50 @@ pub struct DbConfig {
     pub batch_interval: u64,
 }
 
+/// The default number of consecutive failed collections after which a producer is removed.
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 10;
+
+fn default_max_consecutive_failures() -> u32 {
+    DEFAULT_MAX_CONSECUTIVE_FAILURES
+}
+
+/// Configuration for collecting metric data from producers.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct CollectionConfig {
+    /// The number of consecutive failed collections from a producer, after which the producer is
+    /// assumed to be gone. It is no longer collected from, and its assignment to this collector is
+    /// removed from Nexus.
+    #[serde(default = "default_max_consecutive_failures")]
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for CollectionConfig {
+    fn default() -> Self {
+        Self { max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES }
+    }
+}
+
+/// Information about a producer assigned to a collector.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct ProducerDetails {
+    /// The endpoint from which data is collected.
+    pub endpoint: ProducerEndpoint,
+    /// The health of collections from the producer.
+    pub health: CollectionHealth,
+}
+
 /// The internal agent the oximeter server uses to collect metrics from producers.
 #[derive(Debug)]
 pub struct OximeterAgent {
     /// The collector ID for this agent
     pub id: Uuid,
     log: Logger,
+    // Configuration for the collection tasks
+    collection_config: CollectionConfig,
     // Handle to the TX-side of a channel for collecting results from the collection tasks
     result_sender: mpsc::Sender<ProducerResults>,
+    // Handle to the TX-side of a channel used by collection tasks to request their removal
+    reap_sender: mpsc::Sender<ReapRequest>,
     // The actual tokio tasks running the collection on a timer.
     collection_tasks: Arc<Mutex<BTreeMap<Uuid, CollectionTask>>>,
 }
@@ -270,23 +495,26 @@ impl OximeterAgent {
     pub async fn with_id(
         id: Uuid,
         db_config: DbConfig,
+        collection_config: CollectionConfig,
         resolver: &Resolver,
+        nexus_client: nexus_client::Client,
         log: &Logger,
     ) -> Result<Self, Error> {
         let (result_sender, result_receiver) = mpsc::channel(8);
+        let (reap_sender, reap_receiver) = mpsc::channel(8);
         let log = log.new(o!("component" => "oximeter-agent", "collector_id" => id.to_string()));
         let insertion_log = log.new(o!("component" => "results-sink"));
+        let reaper_log = log.new(o!("component" => "reaper"));
 
         // Construct the ClickHouse client first, propagate an error if we can't reach the
         // database.
         let db_address = if let Some(address) = db_config.address {
             address
         } else {
-            SocketAddr::new(
+            SocketAddr::V6(
                 resolver
-                    .lookup_ip(SRV::Service(ServiceName::Clickhouse))
+                    .lookup_socket_v6(SRV::Service(ServiceName::Clickhouse))
                     .await?,
-                CLICKHOUSE_PORT,
             )
         };
         let client = Client::new(db_address, &log);
@@ -303,14 +531,52 @@ impl OximeterAgent {
             )
             .await
         });
+
+        // Spawn the task for removing collection tasks whose producers have gone away
+        let collection_tasks = Arc::new(Mutex::new(BTreeMap::new()));
+        let tasks = Arc::clone(&collection_tasks);
+        tokio::spawn(async move {
+            reaper_task(reaper_log, tasks, nexus_client, reap_receiver).await
+        });
         Ok(Self {
             id,
             log,
+            collection_config,
             result_sender,
-            collection_tasks: Arc::new(Mutex::new(BTreeMap::new())),
+            reap_sender,
+            collection_tasks,
         })
     }
 
+    // Spawn a new task collecting from the given producer.
+    fn spawn_collection_task(&self, info: ProducerEndpoint) -> CollectionTask {
+        let id = info.id;
+        let (tx, rx) = mpsc::channel(4);
+        let q = self.result_sender.clone();
+        let reaper = self.reap_sender.clone();
+        let max_consecutive_failures =
+            self.collection_config.max_consecutive_failures;
+        let stats = Arc::new(Mutex::new(CollectionStats::new(self.id, id)));
+        let token = Uuid::new_v4();
+        let log = self.log.new(o!("component" => "collection-task", "producer_id" => id.to_string()));
+        let producer = info.clone();
+        let task_stats = Arc::clone(&stats);
+        let task = tokio::spawn(async move {
+            collection_task(
+                log,
+                info,
+                rx,
+                q,
+                task_stats,
+                max_consecutive_failures,
+                reaper,
+                token,
+            )
+            .await;
+        });
+        CollectionTask { producer, inbox: tx, task, stats, token }
+    }
+
     /// Register a new producer with this oximeter instance.
     pub async fn register_producer(
         &self,
@@ -324,16 +590,9 @@ impl OximeterAgent {
                       "address" => info.address,
                 );
 
-                // Build channel to control the task and receive results.
-                let (tx, rx) = mpsc::channel(4);
-                let q = self.result_sender.clone();
-                let log = self.log.new(o!("component" => "collection-task", "producer_id" => id.to_string()));
-                let task = tokio::spawn(async move {
-                    collection_task(log, info, rx, q).await;
-                });
-                value.insert(CollectionTask { inbox: tx, task });
+                value.insert(self.spawn_collection_task(info));
             }
-            Entry::Occupied(value) => {
+            Entry::Occupied(mut value) => {
                 info!(
                     self.log,
                     "received request to register existing metric producer, updating collection information";
@@ -341,16 +600,98 @@ impl OximeterAgent {
                    "interval" => ?info.interval,
                    "address" => info.address,
                 );
-                value
-                    .get()
+                let task = value.get_mut();
+                task.producer = info.clone();
+                if task
                     .inbox
-                    .send(CollectionMessage::Update(info))
+                    .send(CollectionMessage::Update(info.clone()))
                     .await
-                    .unwrap();
+                    .is_err()
+                {
+                    // The task has given up on the producer, and is waiting to be reaped. Replace
+                    // it with a fresh task.
+                    value.insert(self.spawn_collection_task(info));
+                }
             }
         }
         Ok(())
     }
+
+    /// Unregister a producer, so that this oximeter instance no longer collects from it.
+    pub async fn unregister_producer(&self, id: Uuid) -> Result<(), Error> {
+        let task = self
+            .collection_tasks
+            .lock()
+            .await
+            .remove(&id)
+            .ok_or(Error::NoSuchProducer(id))?;
+        info!(
+            self.log,
+            "unregistered metric producer";
+            "producer_id" => id.to_string(),
+            "address" => task.producer.address,
+        );
+        // The task may have already exited, if it gave up on the producer, in which case there is
+        // nothing to shut down.
+        let _ = task.inbox.send(CollectionMessage::Shutdown).await;
+        Ok(())
+    }
+
+    /// Register all producers that Nexus has assigned to this oximeter instance.
+    ///
+    /// Returns the number of producers registered.
+    pub async fn load_assigned_producers(
+        &self,
+        client: &nexus_client::Client,
+    ) -> Result<usize, Error> {
+        let mut n_producers = 0;
+        let mut page_token = None;
+        loop {
+            let page = client
+                .cpapi_assigned_producers_list(
+                    &self.id,
+                    None,
+                    page_token.as_deref(),
+                    None,
+                )
+                .await
+                .map_err(|e| Error::Nexus(e.to_string()))?
+                .into_inner();
+            for producer in page.items.iter() {
+                let info = ProducerEndpoint::try_from(producer)
+                    .map_err(Error::Nexus)?;
+                self.register_producer(info).await?;
+                n_producers += 1;
+            }
+            match page.next_page {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(n_producers)
+    }
+
+    /// List the producers this oximeter instance is collecting from, in order of their IDs.
+    ///
+    /// At most `limit` producers are returned, starting after the ID `start`, if it is provided.
+    pub async fn list_producers(
+        &self,
+        start: Option<Uuid>,
+        limit: usize,
+    ) -> Vec<ProducerDetails> {
+        let start = start.map(Bound::Excluded).unwrap_or(Bound::Unbounded);
+        let tasks = self.collection_tasks.lock().await;
+        let mut producers = Vec::with_capacity(limit.min(tasks.len()));
+        for task in
+            tasks.range((start, Bound::Unbounded)).take(limit).map(|(_, t)| t)
+        {
+            producers.push(ProducerDetails {
+                endpoint: task.producer.clone(),
+                health: task.stats.lock().await.health().clone(),
+            });
+        }
+        producers
+    }
 }
 
 /// Configuration used to initialize an oximeter server
@@ -365,13 +706,19 @@ pub struct Config {
     /// Configuration for working with ClickHouse
     pub db: DbConfig,
 
+    /// Configuration for collecting from metric producers
+    #[serde(default)]
+    pub collection: CollectionConfig,
+
     /// Logging configuration
     pub log: ConfigLogging,
 }
 
 impl Config {
     /// Load configuration for an Oximeter server from a file.
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+    pub fn from_file<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Config, Error> {
         let path = path.as_ref();
         let contents = std::fs::read_to_string(path)
             .map_err(|e| Error::Server(e.to_string()))?;
@@ -428,11 +775,52 @@ impl Oximeter {
 
         let resolver = Resolver::new_from_ip(*args.address.ip())?;
 
+        // Find Nexus, which assigns producers to this collector.
+        let nexus_address = if let Some(address) = config.nexus_address {
+            address
+        } else {
+            let find_nexus = || async {
+                debug!(log, "looking up nexus");
+                resolver
+                    .lookup_socket_v6(SRV::Service(ServiceName::Nexus))
+                    .await
+                    .map(SocketAddr::V6)
+                    .map_err(|e| {
+                        backoff::BackoffError::transient(e.to_string())
+                    })
+            };
+            let log_lookup_failure = |error, delay| {
+                warn!(
+                    log,
+                    "failed to look up nexus, will retry in {:?}", delay;
+                    "error" => ?error
+                );
+            };
+            backoff::retry_notify(
+                backoff::internal_service_policy(),
+                find_nexus,
+                log_lookup_failure,
+            )
+            .await
+            .expect("Expected an infinite retry loop looking up Nexus")
+        };
+        let nexus_client = nexus_client::Client::new(
+            &format!("http://{}", nexus_address),
+            log.new(o!("component" => "nexus-client")),
+        );
+
         let make_agent = || async {
             debug!(log, "creating ClickHouse client");
             Ok(Arc::new(
-                OximeterAgent::with_id(args.id, config.db, &resolver, &log)
-                    .await?,
+                OximeterAgent::with_id(
+                    args.id,
+                    config.db,
+                    config.collection,
+                    &resolver,
+                    nexus_client.clone(),
+                    &log,
+                )
+                .await?,
             ))
         };
         let log_client_failure = |error, delay| {
@@ -467,22 +855,6 @@ impl Oximeter {
         let client = reqwest::Client::new();
         let notify_nexus = || async {
             debug!(log, "contacting nexus");
-            let nexus_address = if let Some(address) = config.nexus_address {
-                address
-            } else {
-                SocketAddr::V6(SocketAddrV6::new(
-                    resolver
-                        .lookup_ipv6(SRV::Service(ServiceName::Nexus))
-                        .await
-                        .map_err(|e| {
-                            backoff::BackoffError::transient(e.to_string())
-                        })?,
-                    NEXUS_INTERNAL_PORT,
-                    0,
-                    0,
-                ))
-            };
-
             client
                 .post(format!("http://{}/metrics/collectors", nexus_address,))
                 .json(&nexus_client::types::OximeterInfo {
@@ -493,6 +865,7 @@ impl Oximeter {
                 .await
                 .map_err(|e| backoff::BackoffError::transient(e.to_string()))?
                 .error_for_status()
+                .map(|_| ())
                 .map_err(|e| backoff::BackoffError::transient(e.to_string()))
         };
         let log_notification_failure = |error, delay| {
@@ -509,11 +882,41 @@ impl Oximeter {
         )
         .await
         .expect("Expected an infinite retry loop contacting Nexus");
-
         info!(log, "oximeter registered with nexus"; "id" => ?agent.id);
+
+        // Start collecting from any producers Nexus has already assigned to this collector, for
+        // example before this collector was restarted. Those producers won't necessarily register
+        // themselves again.
+        let load_producers = || async {
+            agent
+                .load_assigned_producers(&nexus_client)
+                .await
+                .map_err(backoff::BackoffError::transient)
+        };
+        let log_load_failure = |error, delay| {
+            warn!(
+                log,
+                "failed to load assigned producers, will retry in {:?}", delay;
+                "error" => ?error
+            );
+        };
+        let n_producers = backoff::retry_notify(
+            backoff::internal_service_policy(),
+            load_producers,
+            log_load_failure,
+        )
+        .await
+        .expect("Expected an infinite retry loop loading assigned producers");
+        info!(log, "loaded assigned producers"; "n_producers" => n_producers);
+
         Ok(Self { _agent: agent, server })
     }
 
+    /// Return the address of the server's HTTP API.
+    pub fn server_address(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+
     /// Serve requests forever, consuming the server.
     pub async fn serve_forever(self) -> Result<(), Error> {
         self.server.await.map_err(Error::Server)
@@ -530,6 +933,10 @@ pub fn oximeter_api() -> ApiDescription<Arc<OximeterAgent>> {
     let mut api = ApiDescription::new();
     api.register(producers_post)
         .expect("Could not register producers_post API handler");
+    api.register(producers_list)
+        .expect("Could not register producers_list API handler");
+    api.register(producer_delete)
+        .expect("Could not register producer_delete API handler");
     api
 }
 
@@ -544,9 +951,51 @@ async fn producers_post(
 ) -> Result<HttpResponseUpdatedNoContent, HttpError> {
     let agent = request_context.context();
     let producer_info = body.into_inner();
-    agent
-        .register_producer(producer_info)
-        .await
-        .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+    agent.register_producer(producer_info).await?;
     Ok(HttpResponseUpdatedNoContent())
 }
+
+// List the producers this collector is assigned, and the health of collections from each.
+#[endpoint {
+    method = GET,
+    path = "/producers",
+}]
+async fn producers_list(
+    request_context: Arc<RequestContext<Arc<OximeterAgent>>>,
+    query: Query<PaginatedById>,
+) -> Result<HttpResponseOk<ResultsPage<ProducerDetails>>, HttpError> {
+    let agent = request_context.context();
+    let query = query.into_inner();
+    let pagparams = data_page_params_for(&request_context, &query)?;
+    let producers = agent
+        .list_producers(
+            pagparams.marker.copied(),
+            pagparams.limit.get() as usize,
+        )
+        .await;
+    Ok(HttpResponseOk(ScanById::results_page(
+        &query,
+        producers,
+        &|_, producer: &ProducerDetails| producer.endpoint.id,
+    )?))
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, Serialize)]
+struct ProducerIdPathParams {
+    producer_id: Uuid,
+}
+
+// Handle a request from Nexus to stop collecting from a producer.
+#[endpoint {
+    method = DELETE,
+    path = "/producers/{producer_id}",
+}]
+async fn producer_delete(
+    request_context: Arc<RequestContext<Arc<OximeterAgent>>>,
+    path: Path<ProducerIdPathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    let agent = request_context.context();
+    let producer_id = path.into_inner().producer_id;
+    agent.unregister_producer(producer_id).await?;
+    Ok(HttpResponseDeleted())
+}
@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Metrics the `oximeter` collector records about its own collections.
+
+// Copyright 2022 Oxide Computer Company
+
+use chrono::{DateTime, Utc};
+use oximeter::histogram::Histogram;
+use oximeter::types::{Cumulative, Sample};
+use oximeter::{Metric, Target};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The [`oximeter::Target`] for collections from a single producer.
+#[derive(Debug, Clone, Target)]
+pub struct OximeterCollector {
+    /// The ID of the collector doing the collection.
+    pub collector_id: Uuid,
+    /// The ID of the producer being collected from.
+    pub producer_id: Uuid,
+}
+
+/// The number of successful collections from a producer.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct Collections {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+/// The number of failed collections from a producer.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct FailedCollections {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+/// The number of failed collections since the last successful one.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct ConsecutiveFailures {
+    #[datum]
+    pub count: i64,
+}
+
+/// The Unix timestamp, in seconds, of the last successful collection.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct LastSuccess {
+    #[datum]
+    pub timestamp: f64,
+}
+
+/// A histogram of the latency of successful collections, in seconds.
+#[derive(Debug, Clone, Metric)]
+pub struct CollectionLatency {
+    #[datum]
+    pub latency: Histogram<f64>,
+}
+
+/// Summary of the health of collections from a single producer.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct CollectionHealth {
+    /// The time of the last successful collection, if any.
+    pub last_success: Option<DateTime<Utc>>,
+    /// The time of the last failed collection, if any.
+    pub last_failure: Option<DateTime<Utc>>,
+    /// The total number of successful collections.
+    pub n_collections: u64,
+    /// The total number of failed collections.
+    pub n_failures: u64,
+    /// The number of failed collections since the last successful one.
+    pub consecutive_failures: u32,
+    /// The duration of the last successful collection, if any.
+    pub last_latency: Option<Duration>,
+}
+
+/// Statistics about collections from a single producer.
+///
+/// These are updated by the collection task after each attempt to collect
+/// from its producer, and are reported both as samples inserted alongside the
+/// producer's own data, and as a [`CollectionHealth`] summary through the
+/// collector's API.
+#[derive(Debug, Clone)]
+pub struct CollectionStats {
+    target: OximeterCollector,
+    collections: Collections,
+    failed_collections: FailedCollections,
+    consecutive_failures: ConsecutiveFailures,
+    last_success: Option<LastSuccess>,
+    latency: CollectionLatency,
+    health: CollectionHealth,
+}
+
+impl CollectionStats {
+    /// Construct statistics for collections by `collector_id` from
+    /// `producer_id`.
+    pub fn new(collector_id: Uuid, producer_id: Uuid) -> Self {
+        Self {
+            target: OximeterCollector { collector_id, producer_id },
+            collections: Default::default(),
+            failed_collections: Default::default(),
+            consecutive_failures: Default::default(),
+            last_success: None,
+            latency: CollectionLatency {
+                latency: Histogram::span_decades(-3, 2)
+                    .expect("Statically-known histogram bins are valid"),
+            },
+            health: Default::default(),
+        }
+    }
+
+    /// Record a successful collection, which took `latency` to complete.
+    pub fn success(&mut self, latency: Duration) {
+        let now = Utc::now();
+        self.collections.datum_mut().increment();
+        *self.consecutive_failures.datum_mut() = 0;
+        self.last_success = Some(LastSuccess {
+            timestamp: now.timestamp_millis() as f64 / 1000.0,
+        });
+        // The histogram only fails on non-finite values, which a `Duration`
+        // can never produce.
+        let _ = self.latency.datum_mut().sample(latency.as_secs_f64());
+
+        self.health.last_success = Some(now);
+        self.health.n_collections += 1;
+        self.health.consecutive_failures = 0;
+        self.health.last_latency = Some(latency);
+    }
+
+    /// Record a failed collection.
+    pub fn failure(&mut self) {
+        self.failed_collections.datum_mut().increment();
+        *self.consecutive_failures.datum_mut() += 1;
+
+        self.health.last_failure = Some(Utc::now());
+        self.health.n_failures += 1;
+        self.health.consecutive_failures += 1;
+    }
+
+    /// Return a summary of the health of collections.
+    pub fn health(&self) -> &CollectionHealth {
+        &self.health
+    }
+
+    /// Return the current samples for all statistics.
+    pub fn samples(&self) -> Vec<Sample> {
+        let mut samples = vec![
+            Sample::new(&self.target, &self.collections),
+            Sample::new(&self.target, &self.failed_collections),
+            Sample::new(&self.target, &self.consecutive_failures),
+            Sample::new(&self.target, &self.latency),
+        ];
+        if let Some(last_success) = &self.last_success {
+            samples.push(Sample::new(&self.target, last_success));
+        }
+        samples
+    }
+}
@@ -7,13 +7,15 @@
 
 use anyhow::{bail, Context};
 use chrono::{DateTime, Utc};
-use clap::{Args, Parser};
+use clap::{Args, Parser, ValueEnum};
 use oximeter::{
-    types::{Cumulative, Sample},
+    types::{Cumulative, Datum, Sample},
     Metric, Target,
 };
-use oximeter_db::{query, Client, DbWrite};
+use oximeter_db::{query, Client, DbWrite, Timeseries, TimeseriesName};
 use slog::{debug, info, o, Drain, Level, Logger};
+use std::convert::TryFrom;
+use std::io::{BufRead, Write};
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use uuid::Uuid;
@@ -138,6 +140,28 @@ enum Subcommand {
         #[clap(long, conflicts_with("end"), action)]
         end_exclusive: Option<DateTime<Utc>>,
     },
+
+    /// Start an interactive shell for running queries in the oximeter query language.
+    ///
+    /// Queries are pipelines of operations, for example:
+    /// `get virtual_machine:cpu_busy | filter cpu_id == 0 | align mean(1m)`.
+    /// Enter `\help` in the shell for the available commands.
+    Repl {
+        /// The format in which query results are printed
+        #[clap(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+}
+
+/// The format in which the REPL prints query results.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// An aligned table, with one row per measurement
+    Table,
+    /// Comma-separated values, with one row per measurement
+    Csv,
+    /// The timeseries as pretty-printed JSON
+    Json,
 }
 
 async fn make_client(
@@ -294,6 +318,231 @@ async fn query(
     Ok(())
 }
 
+const REPL_HELP: &str = "\
+Enter a query, or one of the following commands:
+
+  \\d NAME            Describe the schema of the named timeseries
+  \\format FORMAT     Print results as a table, csv, or json
+  \\help              Print this help
+  \\q                 Quit
+
+Queries are a pipeline of operations, starting with `get`:
+
+  get NAME
+    | filter FIELD OP VALUE [&& FIELD OP VALUE ...]
+    | filter timestamp >= TIME [&& timestamp < TIME]
+    | align mean|min|max|sum|count(DURATION)
+    | group_by [FIELD, ...] [mean|min|max|sum|count]
+    | limit N
+
+Times are RFC 3339 timestamps, `now`, or `now-DURATION`, and durations are
+integers followed by one of `s`, `m`, `h`, or `d`.";
+
+// Return the value of a measurement's datum for display.
+fn datum_as_string(datum: &Datum) -> String {
+    match datum {
+        Datum::Bool(x) => x.to_string(),
+        Datum::I64(x) => x.to_string(),
+        Datum::F64(x) => x.to_string(),
+        Datum::String(x) => x.clone(),
+        Datum::Bytes(x) => format!("<{} bytes>", x.len()),
+        Datum::CumulativeI64(x) => x.value().to_string(),
+        Datum::CumulativeF64(x) => x.value().to_string(),
+        Datum::HistogramI64(x) => serde_json::to_string(x).unwrap(),
+        Datum::HistogramF64(x) => serde_json::to_string(x).unwrap(),
+    }
+}
+
+// Flatten timeseries into a header and rows, with one row per measurement.
+//
+// All timeseries returned by a query share a schema, so the columns are taken from the first.
+fn timeseries_rows(
+    timeseries: &[Timeseries],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut header = Vec::new();
+    if let Some(first) = timeseries.first() {
+        header.extend(
+            first
+                .target
+                .fields
+                .iter()
+                .chain(first.metric.fields.iter())
+                .map(|field| field.name.clone()),
+        );
+    }
+    header.push(String::from("timestamp"));
+    header.push(String::from("datum"));
+    let mut rows = Vec::new();
+    for ts in timeseries.iter() {
+        let fields = ts
+            .target
+            .fields
+            .iter()
+            .chain(ts.metric.fields.iter())
+            .map(|field| field.value.to_string())
+            .collect::<Vec<_>>();
+        for measurement in ts.measurements.iter() {
+            let mut row = fields.clone();
+            row.push(measurement.timestamp().to_rfc3339());
+            row.push(datum_as_string(measurement.datum()));
+            rows.push(row);
+        }
+    }
+    (header, rows)
+}
+
+fn print_table(header: &[String], rows: &[Vec<String>]) {
+    let mut widths = header.iter().map(|h| h.len()).collect::<Vec<_>>();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let print_row = |row: &[String]| {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    print_row(header);
+    print_row(
+        &widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>(),
+    );
+    for row in rows.iter() {
+        print_row(row);
+    }
+    println!("({} rows)", rows.len());
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains(|c: char| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn print_csv(header: &[String], rows: &[Vec<String>]) {
+    for row in std::iter::once(header).chain(rows.iter().map(|r| r.as_slice()))
+    {
+        let line = row.iter().map(|cell| csv_escape(cell)).collect::<Vec<_>>();
+        println!("{}", line.join(","));
+    }
+}
+
+fn print_timeseries(timeseries: &[Timeseries], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(timeseries).unwrap())
+        }
+        OutputFormat::Table => {
+            let (header, rows) = timeseries_rows(timeseries);
+            print_table(&header, &rows);
+        }
+        OutputFormat::Csv => {
+            let (header, rows) = timeseries_rows(timeseries);
+            print_csv(&header, &rows);
+        }
+    }
+}
+
+async fn describe_timeseries(
+    client: &Client,
+    name: &str,
+) -> Result<(), anyhow::Error> {
+    let name = TimeseriesName::try_from(name)?;
+    let schema = client
+        .schema_for_timeseries(&name)
+        .await?
+        .with_context(|| format!("No such timeseries: {}", name))?;
+    println!("Timeseries: {}", schema.timeseries_name);
+    println!("Datum type: {}", schema.datum_type);
+    let header = ["field", "type", "source"].map(String::from);
+    let rows = schema
+        .field_schema
+        .iter()
+        .map(|field| {
+            vec![
+                field.name.clone(),
+                field.ty.to_string(),
+                format!("{:?}", field.source).to_lowercase(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&header, &rows);
+    Ok(())
+}
+
+async fn repl(
+    address: IpAddr,
+    port: u16,
+    log: Logger,
+    mut format: OutputFormat,
+) -> Result<(), anyhow::Error> {
+    let client = make_client(address, port, &log).await?;
+    println!("Connected to {}. Enter \\help for help.", address);
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("oxdb> ");
+        std::io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => {
+                println!();
+                return Ok(());
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let result = if let Some(command) = line.strip_prefix('\\') {
+            let mut words = command.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("q" | "quit"), None) => return Ok(()),
+                (Some("help" | "?"), None) => {
+                    println!("{}", REPL_HELP);
+                    Ok(())
+                }
+                (Some("d"), Some(name)) => {
+                    describe_timeseries(&client, name).await
+                }
+                (Some("format"), Some(name)) => {
+                    match OutputFormat::from_str(name, true) {
+                        Ok(new_format) => {
+                            format = new_format;
+                            Ok(())
+                        }
+                        Err(_) => Err(anyhow::anyhow!(
+                            "Unknown format '{}', expected table, csv, or json",
+                            name
+                        )),
+                    }
+                }
+                _ => Err(anyhow::anyhow!(
+                    "Unknown command '{}', enter \\help for help",
+                    line
+                )),
+            }
+        } else {
+            match client.oxql_query(line).await {
+                Ok(timeseries) => {
+                    print_timeseries(&timeseries, format);
+                    Ok(())
+                }
+                Err(e) => Err(e.into()),
+            }
+        };
+        if let Err(e) = result {
+            println!("Error: {}", e);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let args = OxDb::parse();
@@ -344,5 +593,8 @@ async fn main() {
             .await
             .unwrap();
         }
+        Subcommand::Repl { format } => {
+            repl(args.address, args.port, log, format).await.unwrap();
+        }
     }
 }
@@ -6,8 +6,8 @@
 // Copyright 2021 Oxide Computer Company
 
 use crate::{
-    model, query, Error, Metric, Target, Timeseries, TimeseriesPageSelector,
-    TimeseriesScanParams, TimeseriesSchema,
+    model, oxql, query, Error, Metric, Target, Timeseries,
+    TimeseriesPageSelector, TimeseriesScanParams, TimeseriesSchema,
 };
 use crate::{TimeseriesKey, TimeseriesName};
 use async_trait::async_trait;
@@ -31,6 +31,31 @@ pub struct Client {
     schema: Mutex<BTreeMap<TimeseriesName, TimeseriesSchema>>,
 }
 
+/// Timeseries that matched a query on their fields, whose measurements have
+/// not been selected yet.
+///
+/// See [`Client::select_timeseries_matching`].
+#[derive(Debug, Clone)]
+pub struct TimeseriesMatches {
+    query: query::SelectQuery,
+    info: BTreeMap<TimeseriesKey, (Target, Metric)>,
+}
+
+impl TimeseriesMatches {
+    /// Return the targets of the matching timeseries.
+    pub fn targets(&self) -> impl Iterator<Item = &Target> {
+        self.info.values().map(|(target, _)| target)
+    }
+
+    /// Keep only the timeseries whose target satisfies `f`.
+    pub fn retain_targets<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Target) -> bool,
+    {
+        self.info.retain(|_, (target, _)| f(target));
+    }
+}
+
 impl Client {
     /// Construct a new ClickHouse client of the database at `address`.
     pub fn new(address: SocketAddr, log: &Logger) -> Self {
@@ -68,6 +93,32 @@ impl Client {
         end_time: Option<query::Timestamp>,
         limit: Option<NonZeroU32>,
     ) -> Result<Vec<Timeseries>, Error> {
+        let matches = self
+            .select_timeseries_matching(
+                timeseries_name,
+                criteria,
+                start_time,
+                end_time,
+                limit,
+            )
+            .await?;
+        self.select_measurements(&matches).await
+    }
+
+    /// Select the timeseries that match criteria on the fields, without their
+    /// measurements.
+    ///
+    /// The returned [`TimeseriesMatches`] can be narrowed down before passing
+    /// it to [`Client::select_measurements`], which applies the start/end
+    /// timestamps and `limit` to the remaining timeseries only.
+    pub async fn select_timeseries_matching(
+        &self,
+        timeseries_name: &str,
+        criteria: &[&str],
+        start_time: Option<query::Timestamp>,
+        end_time: Option<query::Timestamp>,
+        limit: Option<NonZeroU32>,
+    ) -> Result<TimeseriesMatches, Error> {
         // Querying uses up to three queries to the database:
         //  1. Retrieve the schema
         //  2. Retrieve the keys and field names/values for matching timeseries
@@ -79,6 +130,8 @@ impl Client {
         //  values from the measurement rows, we avoid transferring the data from those columns
         //  to/from the database, as well as the cost of parsing them for each measurement, only to
         //  promptly throw away almost all of them (except for the first).
+        //
+        // This method runs the first two; `select_measurements` runs the last.
         let timeseries_name = TimeseriesName::try_from(timeseries_name)?;
         let schema =
             self.schema_for_timeseries(&timeseries_name).await?.ok_or_else(
@@ -106,10 +159,64 @@ impl Client {
             }
             None => BTreeMap::new(),
         };
+        Ok(TimeseriesMatches { query, info })
+    }
+
+    /// Select the measurements of timeseries returned by
+    /// [`Client::select_timeseries_matching`].
+    pub async fn select_measurements(
+        &self,
+        matches: &TimeseriesMatches,
+    ) -> Result<Vec<Timeseries>, Error> {
+        if matches.info.is_empty() {
+            Ok(vec![])
+        } else {
+            self.select_timeseries_with_keys(
+                &matches.query,
+                &matches.info,
+                matches.query.schema(),
+            )
+            .await
+        }
+    }
+
+    /// Select timeseries described by a query in the oximeter query language.
+    ///
+    /// See the [`crate::oxql`] module for a description of the language.
+    pub async fn oxql_query(
+        &self,
+        query: &str,
+    ) -> Result<Vec<Timeseries>, Error> {
+        let query: oxql::Query = query.parse()?;
+        let schema = self
+            .schema_for_timeseries(query.timeseries_name())
+            .await?
+            .ok_or_else(|| {
+                Error::TimeseriesNotFound(query.timeseries_name().to_string())
+            })?;
+        let timeseries = self
+            .select_timeseries_with_query(&query.select_query(&schema)?)
+            .await?;
+        query.apply(timeseries)
+    }
+
+    // Select the timeseries, with their measurements, that match a compiled query.
+    async fn select_timeseries_with_query(
+        &self,
+        query: &query::SelectQuery,
+    ) -> Result<Vec<Timeseries>, Error> {
+        let schema = query.schema();
+        let info = match query.field_query() {
+            Some(field_query) => {
+                self.select_matching_timeseries_info(&field_query, schema)
+                    .await?
+            }
+            None => BTreeMap::new(),
+        };
         if info.is_empty() {
             Ok(vec![])
         } else {
-            self.select_timeseries_with_keys(&query, &info, &schema).await
+            self.select_timeseries_with_keys(query, &info, schema).await
         }
     }
 
@@ -876,6 +983,54 @@ mod tests {
         db.cleanup().await.expect("Failed to cleanup ClickHouse server");
     }
 
+    #[tokio::test]
+    async fn test_client_oxql_query() {
+        let (mut db, client, _) = setup_filter_testcase().await;
+        let results = client
+            .oxql_query("get virtual_machine:cpu_busy | filter cpu_id == 0")
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 4, "Expected one timeseries per instance");
+        for timeseries in results.iter() {
+            assert_eq!(timeseries.measurements.len(), 2);
+        }
+
+        // Group the instances of each project, counting their samples.
+        let results = client
+            .oxql_query(
+                "get virtual_machine:cpu_busy \
+                | filter cpu_id == 0 \
+                | align count(1d) \
+                | group_by [project_id] sum",
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2, "Expected one timeseries per project");
+        for timeseries in results.iter() {
+            assert_eq!(timeseries.target.fields.len(), 1);
+            assert_eq!(timeseries.target.fields[0].name, "project_id");
+            let count: f64 = timeseries
+                .measurements
+                .iter()
+                .map(|m| match m.datum() {
+                    oximeter::Datum::F64(x) => *x,
+                    datum => panic!("Unexpected datum {datum:?}"),
+                })
+                .sum();
+            assert_eq!(count, 4.0);
+        }
+
+        assert!(matches!(
+            client.oxql_query("get virtual_machine:cpu_busy | filter").await,
+            Err(Error::QuerySyntax { .. })
+        ));
+        assert!(matches!(
+            client.oxql_query("get virtual_machine:nope").await,
+            Err(Error::TimeseriesNotFound(_))
+        ));
+        db.cleanup().await.expect("Failed to cleanup ClickHouse server");
+    }
+
     #[tokio::test]
     async fn test_field_record_count() {
         // This test verifies that the number of records in the field tables is as expected.
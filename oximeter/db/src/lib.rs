@@ -19,8 +19,9 @@ use thiserror::Error;
 
 mod client;
 pub mod model;
+pub mod oxql;
 pub mod query;
-pub use client::{Client, DbWrite};
+pub use client::{Client, DbWrite, TimeseriesMatches};
 
 #[derive(Clone, Debug, Error)]
 pub enum Error {
@@ -79,6 +80,12 @@ pub enum Error {
 
     #[error("Invalid timeseries name")]
     InvalidTimeseriesName,
+
+    #[error("Query syntax error at offset {offset}: {message}")]
+    QuerySyntax { offset: usize, message: String },
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
 }
 
 /// A timeseries name.
@@ -219,7 +226,7 @@ impl From<model::DbTimeseriesSchema> for TimeseriesSchema {
 }
 
 /// The target identifies the resource or component about which metric data is produced.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Target {
     pub name: String,
     pub fields: Vec<Field>,
@@ -234,7 +241,7 @@ pub struct Metric {
 }
 
 /// A list of timestamped measurements from a single timeseries.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Timeseries {
     pub timeseries_name: String,
     pub target: Target,
@@ -0,0 +1,1113 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A small textual language for querying timeseries.
+//!
+//! Queries are a pipeline of operations, separated by `|`. Every query starts by naming the
+//! timeseries it selects with `get`, and may then filter, align, group, and limit the results:
+//!
+//! ```text
+//! get vm:nic_bytes_received
+//!     | filter instance_id != 2b5f13a4-2a1c-4d8f-9c56-2b05f3c86e41
+//!     | filter timestamp >= now-1h
+//!     | align max(1m)
+//!     | group_by [instance_id] sum
+//!     | limit 1000
+//! ```
+//!
+//! (The `vm` timeseries are the per-instance metrics reported by each sled agent, whose only field
+//! is `instance_id`.)
+//!
+//! The supported operations are:
+//!
+//! - `filter NAME OP VALUE [&& NAME OP VALUE ...]`: Select timeseries whose field `NAME` compares
+//!   to `VALUE` with `OP`, one of the comparisons supported by [`FieldCmp`]. Values may be
+//!   double-quoted, which is required if they contain whitespace. The special name `timestamp`
+//!   constrains the time range instead, and accepts an RFC 3339 timestamp, `now`, or `now-DURATION`.
+//! - `align METHOD(DURATION)`: Bucket the measurements of each timeseries into intervals of the
+//!   given duration, and reduce each bucket with `mean`, `min`, `max`, `sum`, or `count`.
+//! - `group_by [NAME, ...] [METHOD]`: Combine aligned timeseries that share the same values of the
+//!   listed fields, reducing coincident measurements with `METHOD` (`sum` by default).
+//! - `limit N`: Return at most `N` measurements from the database.
+//!
+//! Durations are an integer followed by one of the units `s`, `m`, `h`, or `d`.
+//!
+//! Filters and time bounds are compiled into a [`SelectQuery`] and run by the database, while
+//! alignment and grouping are applied to the returned timeseries by [`Query::apply`].
+// Copyright 2022 Oxide Computer Company
+
+use crate::query::{
+    FieldCmp, SelectQuery, SelectQueryBuilder, StringFieldSelector, Timestamp,
+};
+use crate::{Error, Field, Timeseries, TimeseriesName, TimeseriesSchema};
+use chrono::{DateTime, TimeZone, Utc};
+use oximeter::types::{Datum, DatumType, Measurement};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The name used in a filter to constrain the time range of a query.
+const TIMESTAMP_FILTER_NAME: &str = "timestamp";
+
+/// A method used to reduce a set of measurements into a single value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reducer {
+    Mean,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+impl FromStr for Reducer {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mean" => Ok(Reducer::Mean),
+            "min" => Ok(Reducer::Min),
+            "max" => Ok(Reducer::Max),
+            "sum" => Ok(Reducer::Sum),
+            "count" => Ok(Reducer::Count),
+            _ => Err(Error::InvalidQuery(format!(
+                "unknown reduction method '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Reducer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Reducer::Mean => "mean",
+            Reducer::Min => "min",
+            Reducer::Max => "max",
+            Reducer::Sum => "sum",
+            Reducer::Count => "count",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl Reducer {
+    // Reduce the given values, which must not be empty.
+    fn reduce(&self, values: &[f64]) -> Datum {
+        match self {
+            Reducer::Count => Datum::I64(values.len() as i64),
+            Reducer::Sum => Datum::F64(values.iter().sum()),
+            Reducer::Mean => {
+                Datum::F64(values.iter().sum::<f64>() / values.len() as f64)
+            }
+            Reducer::Min => {
+                Datum::F64(values.iter().copied().fold(f64::INFINITY, f64::min))
+            }
+            Reducer::Max => Datum::F64(
+                values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ),
+        }
+    }
+
+    // The type of the datum produced by this reduction.
+    fn datum_type(&self) -> DatumType {
+        match self {
+            Reducer::Count => DatumType::I64,
+            _ => DatumType::F64,
+        }
+    }
+}
+
+/// Alignment of the measurements in a timeseries into regular intervals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub method: Reducer,
+    pub interval: Duration,
+}
+
+/// Grouping of timeseries by the values of some of their fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupBy {
+    pub fields: Vec<String>,
+    pub method: Reducer,
+}
+
+/// A parsed query, describing the timeseries to select and how to transform them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    timeseries_name: TimeseriesName,
+    filters: Vec<StringFieldSelector>,
+    start_time: Option<Timestamp>,
+    end_time: Option<Timestamp>,
+    alignment: Option<Alignment>,
+    group_by: Option<GroupBy>,
+    limit: Option<NonZeroU32>,
+}
+
+impl FromStr for Query {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s)?.parse_query()
+    }
+}
+
+impl Query {
+    /// Return the name of the timeseries selected by this query.
+    pub fn timeseries_name(&self) -> &TimeseriesName {
+        &self.timeseries_name
+    }
+
+    /// Return the field filters in this query.
+    pub fn filters(&self) -> &[StringFieldSelector] {
+        &self.filters
+    }
+
+    /// Return the start of the time range of this query, if any.
+    pub fn start_time(&self) -> Option<Timestamp> {
+        self.start_time
+    }
+
+    /// Return the end of the time range of this query, if any.
+    pub fn end_time(&self) -> Option<Timestamp> {
+        self.end_time
+    }
+
+    /// Return the alignment applied to the selected timeseries, if any.
+    pub fn alignment(&self) -> Option<&Alignment> {
+        self.alignment.as_ref()
+    }
+
+    /// Return the grouping applied to the selected timeseries, if any.
+    pub fn group_by(&self) -> Option<&GroupBy> {
+        self.group_by.as_ref()
+    }
+
+    /// Compile this query into a [`SelectQuery`], using the schema of the selected timeseries.
+    ///
+    /// An error is returned if the schema is for a different timeseries, or if any filter or
+    /// grouping refers to a field that the timeseries doesn't have.
+    pub fn select_query(
+        &self,
+        schema: &TimeseriesSchema,
+    ) -> Result<SelectQuery, Error> {
+        if schema.timeseries_name != self.timeseries_name {
+            return Err(Error::InvalidQuery(format!(
+                "query selects timeseries '{}', but the schema is for '{}'",
+                self.timeseries_name, schema.timeseries_name
+            )));
+        }
+        if let Some(group_by) = &self.group_by {
+            for field_name in group_by.fields.iter() {
+                if schema.field_schema(field_name).is_none() {
+                    return Err(Error::NoSuchField {
+                        timeseries_name: self.timeseries_name.to_string(),
+                        field_name: field_name.clone(),
+                    });
+                }
+            }
+        }
+        let mut builder = SelectQueryBuilder::new(schema)
+            .start_time(self.start_time)
+            .end_time(self.end_time);
+        if let Some(limit) = self.limit {
+            builder = builder.limit(limit);
+        }
+        for filter in self.filters.iter() {
+            builder = builder.filter_str(filter)?;
+        }
+        Ok(builder.build())
+    }
+
+    /// Apply the alignment and grouping of this query to the timeseries it selected.
+    pub fn apply(
+        &self,
+        timeseries: Vec<Timeseries>,
+    ) -> Result<Vec<Timeseries>, Error> {
+        let timeseries = match &self.alignment {
+            Some(alignment) => timeseries
+                .into_iter()
+                .map(|ts| align_timeseries(ts, alignment))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => timeseries,
+        };
+        match &self.group_by {
+            Some(group_by) => Ok(group_timeseries(timeseries, group_by)),
+            None => Ok(timeseries),
+        }
+    }
+}
+
+// Return the value of a scalar datum as a float, for reduction.
+fn datum_as_f64(timeseries_name: &str, datum: &Datum) -> Result<f64, Error> {
+    match datum {
+        Datum::I64(x) => Ok(*x as f64),
+        Datum::F64(x) => Ok(*x),
+        Datum::CumulativeI64(x) => Ok(x.value() as f64),
+        Datum::CumulativeF64(x) => Ok(x.value()),
+        datum => Err(Error::InvalidQuery(format!(
+            "cannot align timeseries '{}' with datum type {}",
+            timeseries_name,
+            datum.datum_type()
+        ))),
+    }
+}
+
+// Bucket the measurements of a timeseries into intervals aligned to the Unix epoch, and reduce
+// each bucket to a single measurement timestamped with the start of its interval.
+fn align_timeseries(
+    mut timeseries: Timeseries,
+    alignment: &Alignment,
+) -> Result<Timeseries, Error> {
+    let interval_ms = interval_millis(&alignment.interval)?;
+    let mut buckets: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+    for measurement in timeseries.measurements.iter() {
+        let value =
+            datum_as_f64(&timeseries.timeseries_name, measurement.datum())?;
+        let bucket =
+            measurement.timestamp().timestamp_millis().div_euclid(interval_ms)
+                * interval_ms;
+        buckets.entry(bucket).or_default().push(value);
+    }
+    timeseries.measurements = reduce_buckets(buckets, alignment.method);
+    timeseries.metric.datum_type = alignment.method.datum_type();
+    Ok(timeseries)
+}
+
+// Return the length of an alignment interval in milliseconds, which must be a positive `i64` to
+// bucket timestamps by.
+fn interval_millis(interval: &Duration) -> Result<i64, Error> {
+    match i64::try_from(interval.as_millis()) {
+        Ok(ms) if ms > 0 => Ok(ms),
+        _ => Err(Error::InvalidQuery(format!(
+            "invalid alignment interval {:?}",
+            interval
+        ))),
+    }
+}
+
+fn reduce_buckets(
+    buckets: BTreeMap<i64, Vec<f64>>,
+    method: Reducer,
+) -> Vec<Measurement> {
+    buckets
+        .into_iter()
+        .map(|(bucket, values)| {
+            Measurement::with_timestamp(
+                Utc.timestamp_millis(bucket),
+                method.reduce(&values),
+            )
+        })
+        .collect()
+}
+
+// Combine aligned timeseries which share the values of the grouped fields.
+//
+// The resulting timeseries retain only the grouped fields of their target and metric.
+fn group_timeseries(
+    timeseries: Vec<Timeseries>,
+    group_by: &GroupBy,
+) -> Vec<Timeseries> {
+    let select_fields = |fields: &[Field]| {
+        fields
+            .iter()
+            .filter(|field| group_by.fields.contains(&field.name))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let mut groups: BTreeMap<
+        Vec<String>,
+        (Timeseries, BTreeMap<i64, Vec<f64>>),
+    > = BTreeMap::new();
+    for ts in timeseries.into_iter() {
+        let key = group_by
+            .fields
+            .iter()
+            .map(|name| {
+                ts.target
+                    .fields
+                    .iter()
+                    .chain(ts.metric.fields.iter())
+                    .find(|field| &field.name == name)
+                    .map(|field| field.value.to_string())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        let (_, buckets) = groups.entry(key).or_insert_with(|| {
+            let mut group = ts.clone();
+            group.target.fields = select_fields(&ts.target.fields);
+            group.metric.fields = select_fields(&ts.metric.fields);
+            group.metric.datum_type = group_by.method.datum_type();
+            group.measurements = vec![];
+            (group, BTreeMap::new())
+        });
+        for measurement in ts.measurements.iter() {
+            // Aligned timeseries only contain scalar data.
+            let value = match measurement.datum() {
+                Datum::I64(x) => *x as f64,
+                Datum::F64(x) => *x,
+                _ => continue,
+            };
+            buckets
+                .entry(measurement.timestamp().timestamp_millis())
+                .or_default()
+                .push(value);
+        }
+    }
+    groups
+        .into_values()
+        .map(|(mut group, buckets)| {
+            group.measurements = reduce_buckets(buckets, group_by.method);
+            group
+        })
+        .collect()
+}
+
+/// Parse a duration such as `30s`, `5m`, `1h`, or `7d`.
+pub fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let err = || Error::InvalidQuery(format!("invalid duration '{}'", s));
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+    let (count, unit) = s.split_at(split);
+    let count: u64 = count.parse().map_err(|_| err())?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return Err(err()),
+    };
+    if count == 0 {
+        return Err(err());
+    }
+    count.checked_mul(scale).map(Duration::from_secs).ok_or_else(err)
+}
+
+// Parse a point in time, either an RFC 3339 timestamp, `now`, or `now-DURATION`.
+fn parse_time(s: &str) -> Result<DateTime<Utc>, Error> {
+    if s == "now" {
+        return Ok(Utc::now());
+    }
+    if let Some(ago) = s.strip_prefix("now-") {
+        let ago = chrono::Duration::from_std(parse_duration(ago)?)
+            .map_err(|e| Error::InvalidQuery(e.to_string()))?;
+        return Utc::now().checked_sub_signed(ago).ok_or_else(|| {
+            Error::InvalidQuery(format!("invalid timestamp '{}'", s))
+        });
+    }
+    s.parse()
+        .map_err(|_| Error::InvalidQuery(format!("invalid timestamp '{}'", s)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Cmp(FieldCmp),
+    And,
+    Pipe,
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(s) => write!(f, "'{}'", s),
+            Token::Quoted(s) => write!(f, "\"{}\"", s),
+            Token::Cmp(op) => write!(f, "'{}'", op),
+            Token::And => write!(f, "'&&'"),
+            Token::Pipe => write!(f, "'|'"),
+            Token::Comma => write!(f, "','"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+        }
+    }
+}
+
+// Characters which end a bare word.
+fn is_word_delimiter(c: char) -> bool {
+    c.is_whitespace() || "|,()[]\"=!<>~&".contains(c)
+}
+
+// Split a query into tokens, each with the byte offset at which it starts.
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let syntax_error = |offset, message: &str| Error::QuerySyntax {
+        offset,
+        message: message.to_string(),
+    };
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let rest = &s[offset..];
+        let two = rest.get(..2);
+        let (token, len) = match (c, two) {
+            (_, Some("==")) => (Token::Cmp(FieldCmp::Eq), 2),
+            (_, Some("!=")) => (Token::Cmp(FieldCmp::Neq), 2),
+            (_, Some(">=")) => (Token::Cmp(FieldCmp::Ge), 2),
+            (_, Some("<=")) => (Token::Cmp(FieldCmp::Le), 2),
+            (_, Some("~=")) => (Token::Cmp(FieldCmp::Like), 2),
+            (_, Some("&&")) => (Token::And, 2),
+            ('>', _) => (Token::Cmp(FieldCmp::Gt), 1),
+            ('<', _) => (Token::Cmp(FieldCmp::Lt), 1),
+            ('|', _) => (Token::Pipe, 1),
+            (',', _) => (Token::Comma, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('[', _) => (Token::LBracket, 1),
+            (']', _) => (Token::RBracket, 1),
+            ('"', _) => {
+                let end = rest[1..].find('"').ok_or_else(|| {
+                    syntax_error(offset, "unterminated quoted string")
+                })?;
+                (Token::Quoted(rest[1..end + 1].to_string()), end + 2)
+            }
+            ('=' | '!' | '~' | '&', _) => {
+                return Err(syntax_error(
+                    offset,
+                    &format!("unexpected character '{}'", c),
+                ));
+            }
+            _ => {
+                let len = rest.find(is_word_delimiter).unwrap_or(rest.len());
+                (Token::Word(rest[..len].to_string()), len)
+            }
+        };
+        tokens.push((offset, token));
+        while chars.peek().map(|(i, _)| *i < offset + len).unwrap_or(false) {
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end_offset: usize,
+}
+
+impl Parser {
+    fn new(s: &str) -> Result<Self, Error> {
+        Ok(Self { tokens: tokenize(s)?, position: 0, end_offset: s.len() })
+    }
+
+    // The byte offset of the next token, or of the end of the input.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.end_offset)
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::QuerySyntax { offset: self.offset(), message: message.into() }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(_, t)| t.clone());
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        match self.peek() {
+            Some(token) if *token == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(token) => {
+                Err(self
+                    .error(format!("expected {}, found {}", expected, token)))
+            }
+            None => Err(self.error(format!("expected {}", expected))),
+        }
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.position += 1;
+                Ok(word)
+            }
+            Some(token) => {
+                Err(self.error(format!("expected {}, found {}", what, token)))
+            }
+            None => Err(self.error(format!("expected {}", what))),
+        }
+    }
+
+    fn expect_value(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => {
+                let value = value.clone();
+                self.position += 1;
+                Ok(value)
+            }
+            Some(token) => {
+                Err(self.error(format!("expected a value, found {}", token)))
+            }
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    // Map errors from parsing a word to a syntax error at the offset of that word.
+    fn at<T>(
+        &self,
+        offset: usize,
+        result: Result<T, Error>,
+    ) -> Result<T, Error> {
+        result.map_err(|e| match e {
+            Error::InvalidQuery(message) => {
+                Error::QuerySyntax { offset, message }
+            }
+            e => e,
+        })
+    }
+
+    fn parse_query(mut self) -> Result<Query, Error> {
+        let keyword = self.expect_word("'get'")?;
+        if keyword != "get" {
+            self.position -= 1;
+            return Err(self.error("queries must start with 'get'"));
+        }
+        let offset = self.offset();
+        let name = self.expect_word("a timeseries name")?;
+        let timeseries_name =
+            TimeseriesName::try_from(name.as_str()).map_err(|_| {
+                Error::QuerySyntax {
+                    offset,
+                    message: format!("invalid timeseries name '{}'", name),
+                }
+            })?;
+        let mut query = Query {
+            timeseries_name,
+            filters: vec![],
+            start_time: None,
+            end_time: None,
+            alignment: None,
+            group_by: None,
+            limit: None,
+        };
+        while let Some(token) = self.next() {
+            if token != Token::Pipe {
+                self.position -= 1;
+                return Err(
+                    self.error(format!("expected '|', found {}", token))
+                );
+            }
+            let offset = self.offset();
+            let operation = self.expect_word("an operation")?;
+            match operation.as_str() {
+                "filter" => {
+                    if query.alignment.is_some() || query.group_by.is_some() {
+                        return Err(Error::QuerySyntax {
+                            offset,
+                            message: String::from(
+                                "filters must precede 'align' and 'group_by'",
+                            ),
+                        });
+                    }
+                    self.parse_filter(&mut query)?;
+                }
+                "align" => {
+                    if query.alignment.is_some() || query.group_by.is_some() {
+                        return Err(Error::QuerySyntax {
+                            offset,
+                            message: String::from(
+                                "'align' may appear once, before 'group_by'",
+                            ),
+                        });
+                    }
+                    query.alignment = Some(self.parse_align()?);
+                }
+                "group_by" => {
+                    if query.alignment.is_none() || query.group_by.is_some() {
+                        return Err(Error::QuerySyntax {
+                            offset,
+                            message: String::from(
+                                "'group_by' may appear once, after 'align'",
+                            ),
+                        });
+                    }
+                    query.group_by = Some(self.parse_group_by()?);
+                }
+                "limit" => {
+                    if query.limit.is_some() {
+                        return Err(Error::QuerySyntax {
+                            offset,
+                            message: String::from("'limit' may appear once"),
+                        });
+                    }
+                    let offset = self.offset();
+                    let limit = self.expect_word("a limit")?;
+                    query.limit = Some(limit.parse().map_err(|_| {
+                        Error::QuerySyntax {
+                            offset,
+                            message: format!("invalid limit '{}'", limit),
+                        }
+                    })?);
+                }
+                _ => {
+                    return Err(Error::QuerySyntax {
+                        offset,
+                        message: format!("unknown operation '{}'", operation),
+                    });
+                }
+            }
+        }
+        Ok(query)
+    }
+
+    fn parse_filter(&mut self, query: &mut Query) -> Result<(), Error> {
+        loop {
+            let offset = self.offset();
+            let name = self.expect_word("a field name")?;
+            let op = match self.peek() {
+                Some(Token::Cmp(op)) => *op,
+                _ => return Err(self.error("expected a comparison operator")),
+            };
+            self.position += 1;
+            let value_offset = self.offset();
+            let value = self.expect_value()?;
+            if name == TIMESTAMP_FILTER_NAME {
+                let time = self.at(value_offset, parse_time(&value))?;
+                let (bound, timestamp) = match op {
+                    FieldCmp::Gt => {
+                        (&mut query.start_time, Timestamp::Exclusive(time))
+                    }
+                    FieldCmp::Ge => {
+                        (&mut query.start_time, Timestamp::Inclusive(time))
+                    }
+                    FieldCmp::Lt => {
+                        (&mut query.end_time, Timestamp::Exclusive(time))
+                    }
+                    FieldCmp::Le => {
+                        (&mut query.end_time, Timestamp::Inclusive(time))
+                    }
+                    _ => {
+                        return Err(Error::QuerySyntax {
+                            offset,
+                            message: format!(
+                                "timestamps may only be compared with '>', \
+                                '>=', '<', or '<=', found '{}'",
+                                op
+                            ),
+                        });
+                    }
+                };
+                if bound.replace(timestamp).is_some() {
+                    return Err(Error::QuerySyntax {
+                        offset,
+                        message: String::from(
+                            "each bound of the time range may appear once",
+                        ),
+                    });
+                }
+            } else {
+                query.filters.push(StringFieldSelector::new(name, op, value));
+            }
+            if self.peek() == Some(&Token::And) {
+                self.position += 1;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_align(&mut self) -> Result<Alignment, Error> {
+        let offset = self.offset();
+        let method = self.expect_word("an alignment method")?;
+        let method = self.at(offset, method.parse())?;
+        self.expect(Token::LParen)?;
+        let offset = self.offset();
+        let interval = self.expect_word("an alignment interval")?;
+        let interval = self.at(offset, parse_duration(&interval))?;
+        self.at(offset, interval_millis(&interval))?;
+        self.expect(Token::RParen)?;
+        Ok(Alignment { method, interval })
+    }
+
+    fn parse_group_by(&mut self) -> Result<GroupBy, Error> {
+        self.expect(Token::LBracket)?;
+        let mut fields = vec![self.expect_word("a field name")?];
+        while self.peek() == Some(&Token::Comma) {
+            self.position += 1;
+            fields.push(self.expect_word("a field name")?);
+        }
+        self.expect(Token::RBracket)?;
+        let method = match self.peek() {
+            Some(Token::Word(_)) => {
+                let offset = self.offset();
+                let method = self.expect_word("a grouping method")?;
+                self.at(offset, method.parse())?
+            }
+            _ => Reducer::Sum,
+        };
+        Ok(GroupBy { fields, method })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldSchema, FieldSource, Metric, Target};
+    use oximeter::types::FieldType;
+    use oximeter::FieldValue;
+
+    fn schema() -> TimeseriesSchema {
+        TimeseriesSchema {
+            timeseries_name: TimeseriesName::try_from("vm:cpu_busy").unwrap(),
+            field_schema: vec![
+                FieldSchema {
+                    name: String::from("project_id"),
+                    ty: FieldType::Uuid,
+                    source: FieldSource::Target,
+                },
+                FieldSchema {
+                    name: String::from("instance_id"),
+                    ty: FieldType::Uuid,
+                    source: FieldSource::Target,
+                },
+                FieldSchema {
+                    name: String::from("cpu_id"),
+                    ty: FieldType::I64,
+                    source: FieldSource::Metric,
+                },
+            ],
+            datum_type: DatumType::CumulativeF64,
+            created: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_get() {
+        let query: Query = "get vm:cpu_busy".parse().unwrap();
+        assert_eq!(*query.timeseries_name(), "vm:cpu_busy");
+        assert!(query.filters().is_empty());
+        assert!(query.alignment().is_none());
+        assert!(query.group_by().is_none());
+
+        assert!("vm:cpu_busy".parse::<Query>().is_err());
+        assert!("get".parse::<Query>().is_err());
+        assert!("get vm".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy |".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | frobnicate".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_parse_doc_example() {
+        let query: Query = "get vm:nic_bytes_received
+            | filter instance_id != 2b5f13a4-2a1c-4d8f-9c56-2b05f3c86e41
+            | filter timestamp >= now-1h
+            | align max(1m)
+            | group_by [instance_id] sum
+            | limit 1000"
+            .parse()
+            .unwrap();
+        assert_eq!(*query.timeseries_name(), "vm:nic_bytes_received");
+        assert_eq!(query.filters().len(), 1);
+        assert!(query.start_time().is_some());
+        assert!(query.alignment().is_some());
+        assert!(query.group_by().is_some());
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let id = "2b5f13a4-2a1c-4d8f-9c56-2b05f3c86e41";
+        let query: Query = format!(
+            "get vm:cpu_busy | filter project_id == {id} && cpu_id>0 \
+            | filter name ~= \"some thing\""
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(
+            query.filters(),
+            &[
+                StringFieldSelector::new("project_id", FieldCmp::Eq, id),
+                StringFieldSelector::new("cpu_id", FieldCmp::Gt, "0"),
+                StringFieldSelector::new("name", FieldCmp::Like, "some thing"),
+            ]
+        );
+
+        assert!("get vm:cpu_busy | filter cpu_id".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | filter cpu_id ==".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | filter cpu_id = 0"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | filter cpu_id == 0 &&"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | filter a == \"b".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_parse_time_range() {
+        let query: Query = "get vm:cpu_busy \
+            | filter timestamp >= 2022-10-01T00:00:00Z \
+            && timestamp < 2022-10-02T00:00:00Z"
+            .parse()
+            .unwrap();
+        let start: DateTime<Utc> = "2022-10-01T00:00:00Z".parse().unwrap();
+        let end: DateTime<Utc> = "2022-10-02T00:00:00Z".parse().unwrap();
+        assert_eq!(query.start_time(), Some(Timestamp::Inclusive(start)));
+        assert_eq!(query.end_time(), Some(Timestamp::Exclusive(end)));
+
+        let before = Utc::now();
+        let query: Query =
+            "get vm:cpu_busy | filter timestamp > now-1h".parse().unwrap();
+        match query.start_time() {
+            Some(Timestamp::Exclusive(t)) => {
+                assert!(t >= before - chrono::Duration::hours(1));
+                assert!(t <= Utc::now() - chrono::Duration::hours(1));
+            }
+            other => {
+                panic!("Expected an exclusive start time, found {other:?}")
+            }
+        }
+
+        assert!("get vm:cpu_busy | filter timestamp == now"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | filter timestamp > yesterday"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | filter timestamp > now-999999999999999d"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | filter timestamp > now && timestamp > now"
+            .parse::<Query>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_align_group_by_limit() {
+        let query: Query = "get vm:cpu_busy \
+            | align mean(1m) \
+            | group_by [instance_id, cpu_id] \
+            | limit 10"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            query.alignment(),
+            Some(&Alignment {
+                method: Reducer::Mean,
+                interval: Duration::from_secs(60)
+            })
+        );
+        assert_eq!(
+            query.group_by(),
+            Some(&GroupBy {
+                fields: vec![
+                    String::from("instance_id"),
+                    String::from("cpu_id")
+                ],
+                method: Reducer::Sum,
+            })
+        );
+        assert_eq!(query.limit, NonZeroU32::new(10));
+
+        let query: Query =
+            "get vm:cpu_busy | align max(30s) | group_by [cpu_id] count"
+                .parse()
+                .unwrap();
+        assert_eq!(query.group_by().unwrap().method, Reducer::Count);
+
+        // Grouping requires aligned timeseries, and filters must come first.
+        assert!("get vm:cpu_busy | group_by [cpu_id]"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | align mean(1m) | filter cpu_id == 0"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | align median(1m)".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | align mean(0s)".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | align mean(1y)".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | align mean 1m".parse::<Query>().is_err());
+        assert!("get vm:cpu_busy | align mean(999999999999999999d)"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | align mean(9223372036854776s)"
+            .parse::<Query>()
+            .is_err());
+        assert!("get vm:cpu_busy | limit 0".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_syntax_error_offset() {
+        let err = "get vm:cpu_busy | align mean(1m) | bogus"
+            .parse::<Query>()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::QuerySyntax { offset: 35, .. }),
+            "Unexpected error: {err:?}"
+        );
+        let err =
+            "get vm:cpu_busy | align mean(1y)".parse::<Query>().unwrap_err();
+        assert!(
+            matches!(err, Error::QuerySyntax { offset: 29, .. }),
+            "Unexpected error: {err:?}"
+        );
+    }
+
+    #[test]
+    fn test_select_query() {
+        let schema = schema();
+        let query: Query =
+            "get vm:cpu_busy | filter cpu_id > 0 | limit 5".parse().unwrap();
+        let select = query.select_query(&schema).unwrap();
+        let expected = SelectQueryBuilder::new(&schema)
+            .filter("cpu_id", FieldCmp::Gt, 0i64)
+            .unwrap()
+            .limit(NonZeroU32::new(5).unwrap())
+            .build();
+        assert_eq!(select.field_query(), expected.field_query());
+        assert_eq!(
+            select.measurement_query(&[0]),
+            expected.measurement_query(&[0])
+        );
+
+        let query: Query =
+            "get vm:cpu_busy | filter nonexistent == 0".parse().unwrap();
+        assert!(matches!(
+            query.select_query(&schema),
+            Err(Error::NoSuchField { .. })
+        ));
+        let query: Query =
+            "get vm:cpu_busy | filter cpu_id == foo".parse().unwrap();
+        assert!(matches!(
+            query.select_query(&schema),
+            Err(Error::InvalidFieldValue { .. })
+        ));
+        let query: Query = "get vm:cpu_busy | align sum(1m) | group_by [nope]"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            query.select_query(&schema),
+            Err(Error::NoSuchField { .. })
+        ));
+        let query: Query = "get vm:other".parse().unwrap();
+        assert!(matches!(
+            query.select_query(&schema),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    fn make_timeseries(
+        instance_id: &str,
+        cpu_id: i64,
+        values: &[(i64, f64)],
+    ) -> Timeseries {
+        Timeseries {
+            timeseries_name: String::from("vm:cpu_busy"),
+            target: Target {
+                name: String::from("vm"),
+                fields: vec![Field {
+                    name: String::from("instance_id"),
+                    value: FieldValue::from(instance_id),
+                }],
+            },
+            metric: Metric {
+                name: String::from("cpu_busy"),
+                fields: vec![Field {
+                    name: String::from("cpu_id"),
+                    value: FieldValue::from(cpu_id),
+                }],
+                datum_type: DatumType::F64,
+            },
+            measurements: values
+                .iter()
+                .map(|(seconds, value)| {
+                    Measurement::with_timestamp(
+                        Utc.timestamp(*seconds, 0),
+                        Datum::F64(*value),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn values(timeseries: &Timeseries) -> Vec<(i64, Datum)> {
+        timeseries
+            .measurements
+            .iter()
+            .map(|m| (m.timestamp().timestamp(), m.datum().clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_align() {
+        let query: Query = "get vm:cpu_busy | align mean(1m)".parse().unwrap();
+        let ts = make_timeseries("a", 0, &[(0, 1.0), (30, 3.0), (60, 5.0)]);
+        let out = query.apply(vec![ts]).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            values(&out[0]),
+            vec![(0, Datum::F64(2.0)), (60, Datum::F64(5.0))]
+        );
+
+        let query: Query = "get vm:cpu_busy | align count(1m)".parse().unwrap();
+        let ts = make_timeseries("a", 0, &[(0, 1.0), (30, 3.0), (60, 5.0)]);
+        let out = query.apply(vec![ts]).unwrap();
+        assert_eq!(out[0].metric.datum_type, DatumType::I64);
+        assert_eq!(
+            values(&out[0]),
+            vec![(0, Datum::I64(2)), (60, Datum::I64(1))]
+        );
+
+        // Intervals that can't be expressed as a positive number of
+        // milliseconds are rejected, rather than dividing by zero.
+        for interval in
+            [Duration::from_micros(1), Duration::from_secs(u64::MAX / 1000)]
+        {
+            let mut query: Query =
+                "get vm:cpu_busy | align mean(1m)".parse().unwrap();
+            query.alignment.as_mut().unwrap().interval = interval;
+            let ts = make_timeseries("a", 0, &[(0, 1.0)]);
+            assert!(matches!(
+                query.apply(vec![ts]),
+                Err(Error::InvalidQuery(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_apply_group_by() {
+        let query: Query =
+            "get vm:cpu_busy | align max(1m) | group_by [instance_id]"
+                .parse()
+                .unwrap();
+        let timeseries = vec![
+            make_timeseries("a", 0, &[(0, 1.0), (30, 3.0)]),
+            make_timeseries("a", 1, &[(10, 10.0), (70, 20.0)]),
+            make_timeseries("b", 0, &[(0, 100.0)]),
+        ];
+        let out = query.apply(timeseries).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            values(&out[0]),
+            vec![(0, Datum::F64(13.0)), (60, Datum::F64(20.0))]
+        );
+        assert_eq!(values(&out[1]), vec![(0, Datum::F64(100.0))]);
+        for ts in out.iter() {
+            assert_eq!(ts.target.fields.len(), 1);
+            assert!(ts.metric.fields.is_empty());
+        }
+    }
+}
@@ -341,6 +341,18 @@ pub struct StringFieldSelector {
     value: String,
 }
 
+impl StringFieldSelector {
+    /// Construct a selector comparing the named field with a value, which is parsed into the
+    /// field's type when the selector is added to a query.
+    pub fn new<N, V>(name: N, op: FieldCmp, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Self { name: name.into(), op, value: value.into() }
+    }
+}
+
 impl FromStr for StringFieldSelector {
     type Err = Error;
 
@@ -466,7 +478,7 @@ impl TimeRange {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Timestamp {
     Inclusive(DateTime<Utc>),
     Exclusive(DateTime<Utc>),
@@ -184,6 +184,23 @@ pub async fn register(
         .map_err(|msg| Error::RegistrationError(msg.to_string()))
 }
 
+/// Unregister a metric server, so that it is no longer polled for metric data.
+///
+/// This is the counterpart to [`register`], and can be used by producers that are shutting down.
+pub async fn unregister(
+    address: SocketAddr,
+    log: &slog::Logger,
+    producer_id: Uuid,
+) -> Result<(), Error> {
+    let client =
+        nexus_client::Client::new(&format!("http://{}", address), log.clone());
+    client
+        .cpapi_producers_delete(&producer_id)
+        .await
+        .map(|_| ())
+        .map_err(|msg| Error::RegistrationError(msg.to_string()))
+}
+
 /// Handle a request to pull available metric data from a [`ProducerRegistry`].
 pub async fn collect(
     registry: &ProducerRegistry,
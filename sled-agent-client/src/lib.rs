@@ -156,6 +156,7 @@ impl From<omicron_common::api::external::DiskState> for types::DiskState {
         use omicron_common::api::external::DiskState::*;
         match s {
             Creating => Self::Creating,
+            Importing => Self::Importing,
             Detached => Self::Detached,
             Attaching(u) => Self::Attaching(u),
             Attached(u) => Self::Attached(u),
@@ -183,6 +184,7 @@ impl From<types::DiskState> for omicron_common::api::external::DiskState {
         use types::DiskState::*;
         match s {
             Creating => Self::Creating,
+            Importing => Self::Importing,
             Detached => Self::Detached,
             Attaching(u) => Self::Attaching(u),
             Attached(u) => Self::Attached(u),
@@ -32,7 +32,9 @@ use omicron_common::api::external::InstanceState;
 use omicron_common::api::internal::nexus::InstanceRuntimeState;
 use omicron_common::backoff;
 use propolis_client::api::DiskRequest;
+use propolis_client::api::VolumeConstructionRequest;
 use propolis_client::Client as PropolisClient;
+use serde::Serialize;
 use slog::Logger;
 use std::net::IpAddr;
 use std::net::SocketAddr;
@@ -94,6 +96,12 @@ pub enum Error {
 
     #[error("Instance {0} not running!")]
     InstanceNotRunning(Uuid),
+
+    #[error("Instance {0} has no disk {1}")]
+    NoSuchDisk(Uuid, Uuid),
+
+    #[error("Failed to replace the volume of disk {0} in Propolis: {1}")]
+    VolumeReplace(Uuid, reqwest::Error),
 }
 
 // Issues read-only, idempotent HTTP requests at propolis until it responds with
@@ -149,7 +157,7 @@ fn fmri_name(id: &Uuid) -> String {
     format!("{}:{}", service_name(), instance_name(id))
 }
 
-fn propolis_zone_name(id: &Uuid) -> String {
+pub(crate) fn propolis_zone_name(id: &Uuid) -> String {
     format!("{}{}", PROPOLIS_ZONE_PREFIX, id)
 }
 
@@ -321,6 +329,13 @@ impl InstanceInner {
                 slot: propolis_client::api::Slot(port.slot()),
             })
             .collect();
+        instance_ticket.set_links(
+            running_zone
+                .opte_ports()
+                .iter()
+                .map(|port| port.vnic_name().to_string())
+                .collect(),
+        );
 
         let migrate = match migrate {
             Some(params) => {
@@ -442,6 +457,11 @@ mockall::mock! {
             disk_id: Uuid,
             snapshot_name: Uuid,
         ) -> Result<(), Error>;
+        pub async fn disk_volume_put(
+            &self,
+            disk_id: Uuid,
+            volume_construction_request: VolumeConstructionRequest,
+        ) -> Result<(), Error>;
     }
     impl Clone for Instance {
         fn clone(&self) -> Self;
@@ -847,6 +867,93 @@ impl Instance {
             Err(Error::InstanceNotRunning(inner.properties.id))
         }
     }
+
+    /// Replaces the volume backing the disk whose volume is identified by
+    /// `disk_id`, e.g. because the disk has grown.
+    ///
+    /// If the instance is running, Propolis swaps the disk's backend for the
+    /// new volume, and the guest sees the disk's new size.  Either way, the
+    /// new volume is used whenever the instance starts from now on.
+    pub async fn disk_volume_put(
+        &self,
+        disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+    ) -> Result<(), Error> {
+        let mut inner = self.inner.lock().await;
+        let instance_id = inner.properties.id;
+
+        let name = inner
+            .requested_disks
+            .iter()
+            .find(|disk| disk_has_volume(disk, disk_id))
+            .ok_or(Error::NoSuchDisk(instance_id, disk_id))?
+            .name
+            .clone();
+
+        // Only remember the new volume once Propolis has it, so that a failed
+        // replacement leaves the instance as it was.
+        if inner.running_state.is_some() {
+            let propolis_addr = inner
+                .state
+                .current()
+                .propolis_addr
+                .ok_or(Error::InstanceNotRunning(instance_id))?;
+            propolis_volume_replace(
+                propolis_addr,
+                disk_id,
+                name,
+                &volume_construction_request,
+            )
+            .await?;
+        }
+
+        let disk = inner
+            .requested_disks
+            .iter_mut()
+            .find(|disk| disk_has_volume(disk, disk_id))
+            .ok_or(Error::NoSuchDisk(instance_id, disk_id))?;
+        disk.volume_construction_request = volume_construction_request;
+
+        Ok(())
+    }
+}
+
+fn disk_has_volume(disk: &DiskRequest, disk_id: Uuid) -> bool {
+    match &disk.volume_construction_request {
+        VolumeConstructionRequest::Volume { id, .. } => *id == disk_id,
+        _ => false,
+    }
+}
+
+/// Body of Propolis's request to replace the volume backing a disk
+#[derive(Serialize)]
+struct PropolisVolumeReplace {
+    name: String,
+    vcr_json: String,
+}
+
+/// Asks the Propolis server at `propolis_addr` to replace the volume backing
+/// its disk `disk_id` with the one described by `volume_construction_request`
+// TODO-cleanup The propolis client doesn't wrap this endpoint yet.
+async fn propolis_volume_replace(
+    propolis_addr: SocketAddr,
+    disk_id: Uuid,
+    name: String,
+    volume_construction_request: &VolumeConstructionRequest,
+) -> Result<(), Error> {
+    let body = serde_json::to_vec(&PropolisVolumeReplace {
+        name,
+        vcr_json: serde_json::to_string(volume_construction_request)?,
+    })?;
+    reqwest::Client::new()
+        .put(format!("http://{}/instance/disk/{}/vcr", propolis_addr, disk_id))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| Error::VolumeReplace(disk_id, e))?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -6,6 +6,7 @@
 
 use crate::illumos::dladm::Etherstub;
 use crate::illumos::vnic::VnicAllocator;
+use crate::instance_metrics::{self, InstanceMetrics, InstanceStats};
 use crate::nexus::LazyNexusClient;
 use crate::opte::PortManager;
 use crate::params::{
@@ -14,10 +15,14 @@ use crate::params::{
 };
 use crate::serial::ByteOffset;
 use macaddr::MacAddr6;
+use omicron_common::address::NEXUS_INTERNAL_PORT;
 use omicron_common::api::internal::nexus::InstanceRuntimeState;
+use omicron_common::backoff;
+use oximeter_producer::Server as ProducerServer;
+use propolis_client::api::VolumeConstructionRequest;
 use slog::Logger;
 use std::collections::BTreeMap;
-use std::net::Ipv6Addr;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
@@ -50,6 +55,12 @@ struct InstanceManagerInternal {
 
     vnic_allocator: VnicAllocator<Etherstub>,
     port_manager: PortManager,
+
+    /// Statistics about all instances, reported to Oximeter.
+    metrics: InstanceMetrics,
+    /// The server through which `metrics` are collected, once it has been
+    /// registered with Nexus.
+    metrics_server: Mutex<Option<ProducerServer>>,
 }
 
 /// All instances currently running on the sled.
@@ -77,10 +88,66 @@ impl InstanceManager {
                     underlay_ip,
                     gateway_mac,
                 ),
+                metrics: InstanceMetrics::new(),
+                metrics_server: Mutex::new(None),
             }),
         }
     }
 
+    /// Starts reporting metrics about all instances on this sled to
+    /// Oximeter, from a producer server listening on `address`.
+    ///
+    /// Nexus may not be running yet when the sled starts, so the producer
+    /// server is started and registered in the background, retrying until it
+    /// succeeds.
+    pub fn start_metrics_producer(&self, sled_id: Uuid, address: Ipv6Addr) {
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            let start = || async {
+                let nexus_ip =
+                    inner.lazy_nexus_client.get_ip().await.map_err(|e| {
+                        backoff::BackoffError::transient(e.to_string())
+                    })?;
+                inner
+                    .metrics
+                    .start_server(
+                        sled_id,
+                        SocketAddr::V6(SocketAddrV6::new(address, 0, 0, 0)),
+                        SocketAddr::V6(SocketAddrV6::new(
+                            nexus_ip,
+                            NEXUS_INTERNAL_PORT,
+                            0,
+                            0,
+                        )),
+                        instance_metrics::COLLECTION_INTERVAL,
+                    )
+                    .await
+                    .map_err(|e| {
+                        backoff::BackoffError::transient(e.to_string())
+                    })
+            };
+            let log_failure = |error: String, delay: std::time::Duration| {
+                warn!(
+                    inner.log,
+                    "failed to start instance metrics producer";
+                    "error" => error,
+                    "retry_after" => ?delay,
+                );
+            };
+            let server = backoff::retry_notify(
+                backoff::internal_service_policy(),
+                start,
+                log_failure,
+            )
+            .await
+            .expect(
+                "Expected an infinite retry loop starting metrics producer",
+            );
+            info!(inner.log, "started instance metrics producer");
+            inner.metrics_server.lock().unwrap().replace(server);
+        });
+    }
+
     /// Idempotently ensures that the given Instance (described by
     /// `initial_hardware`) exists on this server in the given runtime state
     /// (described by `target`).
@@ -124,6 +191,10 @@ impl InstanceManager {
                     // a intra-sled migration. Either way - create an instance
                     info!(&self.inner.log, "new instance");
                     let instance_log = self.inner.log.new(o!());
+                    let stats = InstanceStats::new(
+                        instance_id,
+                        &initial_hardware.runtime,
+                    );
                     let instance = Instance::new(
                         instance_log,
                         instance_id,
@@ -152,6 +223,7 @@ impl InstanceManager {
                         std::mem::forget(old_instance);
                     }
 
+                    self.inner.metrics.insert(instance_id, stats);
                     let ticket = Some(InstanceTicket::new(
                         instance_id,
                         self.inner.clone(),
@@ -213,6 +285,26 @@ impl InstanceManager {
             .map_err(Error::from)
     }
 
+    pub async fn instance_disk_volume_put(
+        &self,
+        instance_id: Uuid,
+        disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+    ) -> Result<(), Error> {
+        let instance = {
+            let instances = self.inner.instances.lock().unwrap();
+            let (_, instance) = instances
+                .get(&instance_id)
+                .ok_or(Error::NoSuchInstance(instance_id))?;
+            instance.clone()
+        };
+
+        instance
+            .disk_volume_put(disk_id, volume_construction_request)
+            .await
+            .map_err(Error::from)
+    }
+
     pub async fn firewall_rules_ensure(
         &self,
         rules: &[VpcFirewallRule],
@@ -246,6 +338,15 @@ impl InstanceTicket {
     pub fn terminate(&mut self) {
         if let Some(inner) = self.inner.take() {
             inner.instances.lock().unwrap().remove(&self.id);
+            inner.metrics.remove(&self.id);
+        }
+    }
+
+    /// Records the names of the data links backing this instance's network
+    /// interfaces, so that their statistics can be reported.
+    pub fn set_links(&self, links: Vec<String>) {
+        if let Some(inner) = &self.inner {
+            inner.metrics.set_links(&self.id, links);
         }
     }
 }
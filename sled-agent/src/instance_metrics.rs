@@ -0,0 +1,465 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Metrics describing the instances running on a sled.
+//!
+//! Each instance is an [`oximeter::Target`] with a handful of metrics
+//! describing its use of CPU, memory and networking. All instances on a sled
+//! are reported by a single [`InstanceMetrics`] producer.
+//!
+//! On illumos, the values are read from the kstats of the Propolis zone
+//! running the instance. Elsewhere, there are no real instances to measure,
+//! so synthetic values are produced instead, which is enough to exercise the
+//! rest of the metrics pipeline in tests.
+//!
+//! There are no disk metrics here. An instance's disks are Crucible volumes,
+//! whose I/O goes through the Crucible upstairs in Propolis rather than
+//! through the zone's filesystem, so none of the zone's kstats count it. Each
+//! upstairs reports metrics for its own disk instead.
+
+use dropshot::{ConfigDropshot, ConfigLogging, ConfigLoggingLevel};
+use omicron_common::api::internal::nexus::{
+    InstanceRuntimeState, ProducerEndpoint,
+};
+use oximeter::types::{Cumulative, Sample};
+use oximeter::{Metric, MetricsError, Target};
+use oximeter_producer::Server as ProducerServer;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// The interval on which Oximeter collects instance metrics.
+pub const COLLECTION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A virtual machine instance.
+#[derive(Debug, Clone, Target)]
+pub struct Vm {
+    pub instance_id: Uuid,
+}
+
+/// Total time the instance's vCPUs have spent running, in nanoseconds.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct CpuBusy {
+    #[datum]
+    pub nanoseconds: Cumulative<i64>,
+}
+
+/// Memory currently used by the instance, in bytes.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct MemoryUsed {
+    #[datum]
+    pub bytes: i64,
+}
+
+/// Packets received on all of the instance's network interfaces.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct NicPacketsReceived {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+/// Packets sent on all of the instance's network interfaces.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct NicPacketsSent {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+/// Bytes received on all of the instance's network interfaces.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct NicBytesReceived {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+/// Bytes sent on all of the instance's network interfaces.
+#[derive(Debug, Default, Clone, Copy, Metric)]
+pub struct NicBytesSent {
+    #[datum]
+    pub count: Cumulative<i64>,
+}
+
+// Identifies where the statistics describing an instance come from.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(target_os = "illumos"), allow(dead_code))]
+struct StatsSource {
+    // The ID of the Propolis server (and zone) running the instance.
+    propolis_id: Uuid,
+    // The numeric ID of that zone, once it has been looked up.
+    zone_id: Option<String>,
+    // Names of the data links of the instance's network interfaces, once
+    // they have been created.
+    links: Vec<String>,
+}
+
+/// The statistics tracked for a single instance.
+#[derive(Debug, Clone)]
+pub struct InstanceStats {
+    target: Vm,
+    #[cfg_attr(not(target_os = "illumos"), allow(dead_code))]
+    source: StatsSource,
+    // The provisioned size of the instance, used for synthetic values.
+    #[cfg_attr(target_os = "illumos", allow(dead_code))]
+    ncpus: i64,
+    #[cfg_attr(target_os = "illumos", allow(dead_code))]
+    memory: i64,
+    // Whether the statistics describe the running instance, i.e., the last
+    // attempt to read them succeeded.
+    valid: bool,
+    cpu_busy: CpuBusy,
+    memory_used: MemoryUsed,
+    nic_packets_received: NicPacketsReceived,
+    nic_packets_sent: NicPacketsSent,
+    nic_bytes_received: NicBytesReceived,
+    nic_bytes_sent: NicBytesSent,
+}
+
+impl InstanceStats {
+    /// Construct statistics for the instance `instance_id`, described by
+    /// `runtime`.
+    pub fn new(instance_id: Uuid, runtime: &InstanceRuntimeState) -> Self {
+        Self {
+            target: Vm { instance_id },
+            source: StatsSource {
+                propolis_id: runtime.propolis_id,
+                zone_id: None,
+                links: Vec::new(),
+            },
+            ncpus: runtime.ncpus.0.into(),
+            memory: runtime.memory.to_bytes() as i64,
+            valid: false,
+            cpu_busy: Default::default(),
+            memory_used: Default::default(),
+            nic_packets_received: Default::default(),
+            nic_packets_sent: Default::default(),
+            nic_bytes_received: Default::default(),
+            nic_bytes_sent: Default::default(),
+        }
+    }
+
+    /// Return the current samples for all metrics.
+    pub fn samples(&self) -> Vec<Sample> {
+        vec![
+            Sample::new(&self.target, &self.cpu_busy),
+            Sample::new(&self.target, &self.memory_used),
+            Sample::new(&self.target, &self.nic_packets_received),
+            Sample::new(&self.target, &self.nic_packets_sent),
+            Sample::new(&self.target, &self.nic_bytes_received),
+            Sample::new(&self.target, &self.nic_bytes_sent),
+        ]
+    }
+
+    /// Record statistics read from the instance's Propolis zone by
+    /// [`StatsSource::read`].
+    #[cfg(target_os = "illumos")]
+    fn record(
+        &mut self,
+        source: StatsSource,
+        readings: Result<kstat::Readings, MetricsError>,
+    ) {
+        self.source.zone_id = source.zone_id;
+        let readings = match readings {
+            Ok(readings) => readings,
+            Err(_) => {
+                self.valid = false;
+                return;
+            }
+        };
+        set_cumulative(self.cpu_busy.datum_mut(), readings.cpu);
+        *self.memory_used.datum_mut() = readings.rss;
+        set_cumulative(
+            self.nic_packets_received.datum_mut(),
+            readings.ipackets,
+        );
+        set_cumulative(self.nic_packets_sent.datum_mut(), readings.opackets);
+        set_cumulative(self.nic_bytes_received.datum_mut(), readings.rbytes);
+        set_cumulative(self.nic_bytes_sent.datum_mut(), readings.obytes);
+        self.valid = true;
+    }
+
+    /// Advance the statistics by a fixed amount, as if the instance were
+    /// half-busy and doing a bit of networking.
+    #[cfg(not(target_os = "illumos"))]
+    fn advance(&mut self) {
+        const NANOS_PER_UPDATE: i64 = 50_000_000;
+        const PACKETS_PER_UPDATE: i64 = 10;
+        const BYTES_PER_PACKET: i64 = 1500;
+
+        *self.cpu_busy.datum_mut() += self.ncpus * NANOS_PER_UPDATE;
+        *self.memory_used.datum_mut() = self.memory / 2;
+        *self.nic_packets_received.datum_mut() += PACKETS_PER_UPDATE;
+        *self.nic_packets_sent.datum_mut() += PACKETS_PER_UPDATE;
+        *self.nic_bytes_received.datum_mut() +=
+            PACKETS_PER_UPDATE * BYTES_PER_PACKET;
+        *self.nic_bytes_sent.datum_mut() +=
+            PACKETS_PER_UPDATE * BYTES_PER_PACKET;
+        self.valid = true;
+    }
+}
+
+#[cfg(target_os = "illumos")]
+impl StatsSource {
+    /// Read the instance's statistics from the kstats of its Propolis zone.
+    ///
+    /// This runs several commands, so it must not be called on the async
+    /// runtime.
+    fn read(&mut self) -> Result<kstat::Readings, MetricsError> {
+        let zone_id = match &self.zone_id {
+            Some(zone_id) => zone_id.clone(),
+            None => {
+                let zone_name =
+                    crate::instance::propolis_zone_name(&self.propolis_id);
+                let zone_id = kstat::zone_id(&zone_name)?;
+                self.zone_id = Some(zone_id.clone());
+                zone_id
+            }
+        };
+        let readings = kstat::read_zone(&zone_id, &self.links);
+        if readings.is_err() {
+            // The zone may have stopped, so look up its ID again next time.
+            self.zone_id = None;
+        }
+        readings
+    }
+}
+
+// Update a counter to a value read from the system, keeping its start time.
+#[cfg(target_os = "illumos")]
+fn set_cumulative(counter: &mut Cumulative<i64>, value: i64) {
+    *counter = Cumulative::with_start_time(counter.start_time(), value);
+}
+
+/// An [`oximeter::Producer`] reporting statistics for a set of instances.
+///
+/// Cloning this object clones the reference to the set of instances.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceMetrics {
+    instances: Arc<Mutex<BTreeMap<Uuid, InstanceStats>>>,
+}
+
+impl InstanceMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start reporting statistics for an instance, replacing any previous
+    /// statistics for it.
+    pub fn insert(&self, instance_id: Uuid, stats: InstanceStats) {
+        self.instances.lock().unwrap().insert(instance_id, stats);
+    }
+
+    /// Stop reporting statistics for an instance.
+    pub fn remove(&self, instance_id: &Uuid) {
+        self.instances.lock().unwrap().remove(instance_id);
+    }
+
+    /// Record the names of the data links backing an instance's network
+    /// interfaces.
+    pub fn set_links(&self, instance_id: &Uuid, links: Vec<String>) {
+        if let Some(stats) = self.instances.lock().unwrap().get_mut(instance_id)
+        {
+            stats.source.links = links;
+        }
+    }
+
+    /// Start a producer server exposing these metrics, and register it with
+    /// Nexus.
+    pub async fn start_server(
+        &self,
+        producer_id: Uuid,
+        address: SocketAddr,
+        nexus_address: SocketAddr,
+        interval: Duration,
+    ) -> Result<ProducerServer, oximeter_producer::Error> {
+        let server_info = ProducerEndpoint {
+            id: producer_id,
+            address,
+            base_route: "/collect".to_string(),
+            interval,
+        };
+        let config = oximeter_producer::Config {
+            server_info,
+            registration_address: nexus_address,
+            dropshot_config: ConfigDropshot {
+                bind_address: address,
+                ..Default::default()
+            },
+            logging_config: ConfigLogging::StderrTerminal {
+                level: ConfigLoggingLevel::Error,
+            },
+        };
+        let server = ProducerServer::start(&config).await?;
+        server
+            .registry()
+            .register_producer(self.clone())
+            .map_err(|e| oximeter_producer::Error::Server(e.to_string()))?;
+        #[cfg(target_os = "illumos")]
+        self.spawn_refresh_task(interval);
+        Ok(server)
+    }
+
+    /// Spawn a task reading the statistics of every instance from the
+    /// system, once per `interval`.
+    ///
+    /// Reading kstats means running commands, so the reads are made on a
+    /// blocking thread, without holding the lock on the instances, rather
+    /// than when the statistics are collected. The task exits once the
+    /// instances are dropped.
+    #[cfg(target_os = "illumos")]
+    fn spawn_refresh_task(&self, interval: Duration) {
+        let instances = Arc::downgrade(&self.instances);
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(interval);
+            loop {
+                timer.tick().await;
+                match instances.upgrade() {
+                    Some(instances) => refresh(&instances).await,
+                    None => return,
+                }
+            }
+        });
+    }
+}
+
+// Read the statistics of a set of instances from the system.
+#[cfg(target_os = "illumos")]
+async fn refresh(instances: &Mutex<BTreeMap<Uuid, InstanceStats>>) {
+    let sources: Vec<(Uuid, StatsSource)> = instances
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(id, stats)| (*id, stats.source.clone()))
+        .collect();
+    let readings = tokio::task::spawn_blocking(move || {
+        sources
+            .into_iter()
+            .map(|(id, mut source)| {
+                let readings = source.read();
+                (id, source, readings)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .expect("reading instance kstats panicked");
+
+    let mut instances = instances.lock().unwrap();
+    for (id, source, readings) in readings {
+        match instances.get_mut(&id) {
+            // The instance may have been removed, or restarted in a new
+            // zone, while its statistics were being read.
+            Some(stats) if stats.source.propolis_id == source.propolis_id => {
+                stats.record(source, readings)
+            }
+            _ => (),
+        }
+    }
+}
+
+impl oximeter::Producer for InstanceMetrics {
+    fn produce(
+        &mut self,
+    ) -> Result<Box<(dyn Iterator<Item = Sample> + 'static)>, MetricsError>
+    {
+        let mut instances = self.instances.lock().unwrap();
+        let mut samples = Vec::with_capacity(instances.len() * 6);
+        for stats in instances.values_mut() {
+            #[cfg(not(target_os = "illumos"))]
+            stats.advance();
+            // An instance may not be running yet (or any more), in which case
+            // there's nothing to report for it.
+            if stats.valid {
+                samples.extend(stats.samples());
+            }
+        }
+        Ok(Box::new(samples.into_iter()))
+    }
+}
+
+#[cfg(target_os = "illumos")]
+mod kstat {
+    use crate::illumos::{execute, PFEXEC};
+    use oximeter::MetricsError;
+
+    const KSTAT: &str = "/usr/bin/kstat";
+    const ZONEADM: &str = "/usr/sbin/zoneadm";
+
+    fn run(args: &[&str]) -> Result<String, MetricsError> {
+        let mut command = std::process::Command::new(PFEXEC);
+        let output = execute(command.args(args))
+            .map_err(|e| MetricsError::DatumError(e.to_string()))?;
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Return the numeric ID of a running zone, which is the kstat instance
+    /// of all of its per-zone statistics.
+    pub fn zone_id(zone_name: &str) -> Result<String, MetricsError> {
+        // Parseable output is "id:name:status:path:uuid:brand:ip-type".
+        let output = run(&[ZONEADM, "-z", zone_name, "list", "-p"])?;
+        output
+            .split(':')
+            .next()
+            .filter(|id| *id != "-" && !id.is_empty())
+            .map(String::from)
+            .ok_or_else(|| {
+                MetricsError::DatumError(format!(
+                    "zone {zone_name} is not running"
+                ))
+            })
+    }
+
+    /// The statistics of an instance's Propolis zone.
+    pub struct Readings {
+        pub cpu: i64,
+        pub rss: i64,
+        pub ipackets: i64,
+        pub opackets: i64,
+        pub rbytes: i64,
+        pub obytes: i64,
+    }
+
+    /// Read the statistics of the zone `zone_id`, including those of the
+    /// data links `links`.
+    pub fn read_zone(
+        zone_id: &str,
+        links: &[String],
+    ) -> Result<Readings, MetricsError> {
+        // The zone's CPU time is almost entirely Propolis running the vCPUs.
+        let cpu = read(&format!("zones:{zone_id}::nsec_user"))?
+            + read(&format!("zones:{zone_id}::nsec_sys"))?;
+        let rss = read(&format!("memory_cap:{zone_id}::rss"))?;
+
+        let mut totals = [0; 4];
+        for link in links {
+            for (total, stat) in totals.iter_mut().zip([
+                "ipackets64",
+                "opackets64",
+                "rbytes64",
+                "obytes64",
+            ]) {
+                *total += read(&format!("link:0:{link}:{stat}"))?;
+            }
+        }
+        let [ipackets, opackets, rbytes, obytes] = totals;
+        Ok(Readings { cpu, rss, ipackets, opackets, rbytes, obytes })
+    }
+
+    /// Read a single numeric statistic, identified by a
+    /// `module:instance:name:statistic` specifier.
+    fn read(specifier: &str) -> Result<i64, MetricsError> {
+        // Parseable output is "module:instance:name:statistic\tvalue".
+        let output = run(&[KSTAT, "-p", specifier])?;
+        output
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| {
+                MetricsError::DatumError(format!(
+                    "no numeric kstat matching {specifier}"
+                ))
+            })
+    }
+}
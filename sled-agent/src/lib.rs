@@ -17,6 +17,7 @@ pub mod sim;
 
 // Modules shared by both simulated and non-simulated sled agents.
 pub mod common;
+mod instance_metrics;
 
 // Modules for the non-simulated sled agent.
 pub mod bootstrap;
@@ -11,7 +11,6 @@ use internal_dns_client::{
     multiclient::{ResolveError, Resolver},
     names::{ServiceName, SRV},
 };
-use omicron_common::address::NEXUS_INTERNAL_PORT;
 use slog::Logger;
 use std::net::Ipv6Addr;
 use std::sync::Arc;
@@ -51,10 +50,14 @@ impl LazyNexusClient {
     }
 
     pub async fn get(&self) -> Result<NexusClient, ResolveError> {
-        let address = self.get_ip().await?;
+        let address = self
+            .inner
+            .resolver
+            .lookup_socket_v6(SRV::Service(ServiceName::Nexus))
+            .await?;
 
         Ok(NexusClient::new(
-            &format!("http://[{}]:{}", address, NEXUS_INTERNAL_PORT),
+            &format!("http://{}", address),
             self.inner.log.clone(),
         ))
     }
@@ -319,6 +319,32 @@ impl<S: Simulatable + 'static> SimCollection<S> {
         rv
     }
 
+    /// Forgets the object identified by `id` if it exists, has no
+    /// asynchronous state transition in progress, and its current state
+    /// satisfies `predicate`.  Returns whether the object was forgotten.
+    pub async fn sim_forget_if<F>(
+        self: &Arc<Self>,
+        id: &Uuid,
+        predicate: F,
+    ) -> bool
+    where
+        F: FnOnce(&S::CurrentState) -> bool,
+    {
+        let mut objects = self.objects.lock().await;
+        match objects.get(id) {
+            Some(object)
+                if object.object.desired().is_none()
+                    && predicate(object.object.current()) => {}
+            _ => return false,
+        }
+
+        let object = objects.remove(id).unwrap();
+        if let Some(mut tx) = object.channel_tx {
+            tx.close_channel();
+        }
+        true
+    }
+
     pub async fn sim_contains(self: &Arc<Self>, id: &Uuid) -> bool {
         let objects = self.objects.lock().await;
         objects.contains_key(id)
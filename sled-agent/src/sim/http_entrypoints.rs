@@ -42,7 +42,10 @@ pub fn api() -> SledApiDescription {
         api.register(update_artifact)?;
         api.register(instance_serial_get)?;
         api.register(instance_issue_disk_snapshot_request)?;
+        api.register(instance_disk_volume_put)?;
         api.register(issue_disk_snapshot_request)?;
+        api.register(disk_bulk_write)?;
+        api.register(disk_bulk_read)?;
         api.register(vpc_firewall_rules_put)?;
 
         Ok(())
@@ -241,6 +244,45 @@ async fn instance_issue_disk_snapshot_request(
     }))
 }
 
+#[derive(Deserialize, JsonSchema)]
+pub struct InstanceDiskVolumePathParam {
+    instance_id: Uuid,
+    disk_id: Uuid,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct InstanceDiskVolumeBody {
+    volume_construction_request: VolumeConstructionRequest,
+}
+
+/// Replace the volume backing a disk that is attached to an instance
+///
+/// This is used when a disk grows.  A running instance switches to the new
+/// volume right away; otherwise, it uses it the next time it starts.
+#[endpoint {
+    method = PUT,
+    path = "/instances/{instance_id}/disks/{disk_id}/volume",
+}]
+async fn instance_disk_volume_put(
+    rqctx: Arc<RequestContext<Arc<SledAgent>>>,
+    path_params: Path<InstanceDiskVolumePathParam>,
+    body: TypedBody<InstanceDiskVolumeBody>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let sa = rqctx.context();
+    let path_params = path_params.into_inner();
+    let body = body.into_inner();
+
+    sa.instance_disk_volume_put(
+        path_params.instance_id,
+        path_params.disk_id,
+        body.volume_construction_request,
+    )
+    .await
+    .map_err(|e| HttpError::for_internal_error(e.to_string()))?;
+
+    Ok(HttpResponseUpdatedNoContent())
+}
+
 #[derive(Deserialize, JsonSchema)]
 pub struct IssueDiskSnapshotRequestPathParam {
     disk_id: Uuid,
@@ -286,6 +328,83 @@ async fn issue_disk_snapshot_request(
     }))
 }
 
+#[derive(Deserialize, JsonSchema)]
+pub struct DiskBulkWriteBody {
+    volume_construction_request: VolumeConstructionRequest,
+    /// byte offset of the first block to write
+    offset: u64,
+    base64_encoded_data: String,
+}
+
+/// Write blocks to a disk that is not attached to an instance
+#[endpoint {
+    method = POST,
+    path = "/disks/{disk_id}/bulk-write",
+}]
+async fn disk_bulk_write(
+    rqctx: Arc<RequestContext<Arc<SledAgent>>>,
+    path_params: Path<DiskPathParam>,
+    body: TypedBody<DiskBulkWriteBody>,
+) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+    let sa = rqctx.context();
+    let disk_id = path_params.into_inner().disk_id;
+    let body = body.into_inner();
+    let data = base64::decode(&body.base64_encoded_data)
+        .map_err(|e| HttpError::for_bad_request(None, e.to_string()))?;
+
+    sa.disk_bulk_write(
+        disk_id,
+        body.volume_construction_request,
+        body.offset,
+        data,
+    )
+    .await?;
+
+    Ok(HttpResponseUpdatedNoContent())
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DiskBulkReadBody {
+    volume_construction_request: VolumeConstructionRequest,
+    /// byte offset of the first block to read
+    offset: u64,
+    /// number of bytes to read
+    size: u64,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct DiskBulkReadResponse {
+    base64_encoded_data: String,
+}
+
+/// Read blocks from a disk that is not attached to an instance
+#[endpoint {
+    method = POST,
+    path = "/disks/{disk_id}/bulk-read",
+}]
+async fn disk_bulk_read(
+    rqctx: Arc<RequestContext<Arc<SledAgent>>>,
+    path_params: Path<DiskPathParam>,
+    body: TypedBody<DiskBulkReadBody>,
+) -> Result<HttpResponseOk<DiskBulkReadResponse>, HttpError> {
+    let sa = rqctx.context();
+    let disk_id = path_params.into_inner().disk_id;
+    let body = body.into_inner();
+
+    let data = sa
+        .disk_bulk_read(
+            disk_id,
+            body.volume_construction_request,
+            body.offset,
+            body.size,
+        )
+        .await?;
+
+    Ok(HttpResponseOk(DiskBulkReadResponse {
+        base64_encoded_data: base64::encode(data),
+    }))
+}
+
 /// Path parameters for VPC requests (sled agent API)
 #[derive(Deserialize, JsonSchema)]
 struct VpcPathParam {
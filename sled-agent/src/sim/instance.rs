@@ -6,6 +6,7 @@
 
 use super::simulatable::Simulatable;
 
+use crate::instance_metrics::{InstanceMetrics, InstanceStats};
 use crate::nexus::NexusClient;
 use crate::params::{InstanceRuntimeStateRequested, InstanceStateRequested};
 use async_trait::async_trait;
@@ -14,34 +15,66 @@ use omicron_common::api::external::Error;
 use omicron_common::api::external::Generation;
 use omicron_common::api::external::InstanceState;
 use omicron_common::api::internal::nexus::InstanceRuntimeState;
+use oximeter_producer::Server as ProducerServer;
 use propolis_client::api::InstanceState as PropolisInstanceState;
+use std::net::{Ipv6Addr, SocketAddr};
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::common::instance::{Action as InstanceAction, InstanceStates};
 
 /// Simulated Instance (virtual machine), as created by the external Oxide API
-#[derive(Debug)]
 pub struct SimInstance {
     state: InstanceStates,
+    producer: Option<ProducerServer>,
+}
+
+// "producer" doesn't implement Debug, so we can't derive it on SimInstance.
+impl std::fmt::Debug for SimInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimInstance").field("state", &self.state).finish()
+    }
 }
 
 #[async_trait]
 impl Simulatable for SimInstance {
     type CurrentState = InstanceRuntimeState;
     type RequestedState = InstanceRuntimeStateRequested;
-    type ProducerArgs = ();
+    type ProducerArgs = (SocketAddr, Uuid, InstanceStats);
     type Action = InstanceAction;
 
     fn new(current: InstanceRuntimeState) -> Self {
-        SimInstance { state: InstanceStates::new(current) }
+        SimInstance { state: InstanceStates::new(current), producer: None }
     }
 
     async fn set_producer(
         &mut self,
-        _args: Self::ProducerArgs,
+        args: Self::ProducerArgs,
     ) -> Result<(), Error> {
-        // NOTE: Not implemented, yet.
+        // Instances are ensured on every state change, but only need one
+        // producer for their lifetime.
+        if self.producer.is_some() {
+            return Ok(());
+        }
+        let (nexus_address, id, stats) = args;
+        let metrics = InstanceMetrics::new();
+        metrics.insert(id, stats);
+        // This listens on any available port, and the server internally
+        // updates this to the actual bound port of the Dropshot HTTP server.
+        let producer_address = SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 0);
+        let server = metrics
+            .start_server(
+                id,
+                producer_address,
+                nexus_address,
+                Duration::from_millis(200),
+            )
+            .await
+            .map_err(|e| {
+                Error::internal_error(&format!("Setting producer server: {e}"))
+            })?;
+        self.producer.replace(server);
         Ok(())
     }
 
@@ -4,6 +4,7 @@
 
 //! Simulated sled agent implementation
 
+use crate::instance_metrics::InstanceStats;
 use crate::nexus::NexusClient;
 use crate::params::{
     DiskStateRequested, InstanceHardware, InstanceRuntimeStateRequested,
@@ -46,6 +47,10 @@ pub struct SledAgent {
     nexus_address: SocketAddr,
     pub nexus_client: Arc<NexusClient>,
     disk_id_to_region_ids: Mutex<HashMap<String, Vec<Uuid>>>,
+    /// the volume most recently given for each disk of each instance, indexed
+    /// by (instance uuid, disk uuid)
+    instance_disk_volumes:
+        Mutex<HashMap<(Uuid, Uuid), VolumeConstructionRequest>>,
 }
 
 fn extract_targets_from_volume_construction_request(
@@ -86,6 +91,40 @@ fn extract_targets_from_volume_construction_request(
     }
 }
 
+/// The simulated regions that back one sub-volume of a volume, along with the
+/// size of that sub-volume in bytes
+struct SimSubVolume {
+    size: u64,
+    regions: Vec<(Arc<CrucibleData>, Uuid)>,
+}
+
+/// Splits the `len` bytes at `offset` in a volume made up of `sub_volumes` into
+/// pieces that each fall within one sub-volume, returning the index of that
+/// sub-volume, the offset within it, and the length of each piece.
+fn split_across_sub_volumes(
+    sub_volumes: &[SimSubVolume],
+    mut offset: u64,
+    mut len: u64,
+) -> Result<Vec<(usize, u64, u64)>, Error> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, sub_volume) in sub_volumes.iter().enumerate() {
+        let end = start + sub_volume.size;
+        if len > 0 && offset < end {
+            let piece_len = std::cmp::min(len, end - offset);
+            pieces.push((i, offset - start, piece_len));
+            offset += piece_len;
+            len -= piece_len;
+        }
+        start = end;
+    }
+
+    if len > 0 {
+        return Err(Error::invalid_request("I/O past the end of the volume"));
+    }
+    Ok(pieces)
+}
+
 impl SledAgent {
     // TODO-cleanup should this instantiate the NexusClient it needs?
     // Should it take a Config object instead of separate id, sim_mode, etc?
@@ -124,6 +163,7 @@ impl SledAgent {
             nexus_address,
             nexus_client,
             disk_id_to_region_ids: Mutex::new(HashMap::new()),
+            instance_disk_volumes: Mutex::new(HashMap::new()),
         }
     }
 
@@ -223,10 +263,29 @@ impl SledAgent {
                 .await?;
         }
 
+        // A real sled agent forgets about instances once they've stopped, so
+        // that starting one again picks up any changes made to its hardware in
+        // the meantime.  Do the same here.
+        self.instances
+            .sim_forget_if(&instance_id, |current| {
+                current.run_state == InstanceState::Stopped
+            })
+            .await;
+
+        let stats = InstanceStats::new(instance_id, &initial_hardware.runtime);
         let instance_run_time_state = self
             .instances
             .sim_ensure(&instance_id, initial_hardware.runtime, target)
             .await?;
+        // The instance may already be gone if it was destroyed.
+        if self.instances.sim_contains(&instance_id).await {
+            self.instances
+                .sim_ensure_producer(
+                    &instance_id,
+                    (self.nexus_address, instance_id, stats),
+                )
+                .await?;
+        }
 
         for disk_request in &initial_hardware.disks {
             self.map_disk_ids_to_region_ids(
@@ -421,6 +480,43 @@ impl SledAgent {
         Ok(())
     }
 
+    /// Replace the volume backing a disk attached to an instance
+    pub async fn instance_disk_volume_put(
+        &self,
+        instance_id: Uuid,
+        disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+    ) -> Result<(), Error> {
+        if !self.instances.sim_contains(&instance_id).await {
+            return Err(Error::not_found_by_id(
+                ResourceType::Instance,
+                &instance_id,
+            ));
+        }
+
+        // Re-map the disk so that later snapshots cover the new regions too.
+        self.map_disk_ids_to_region_ids(&volume_construction_request).await?;
+        self.instance_disk_volumes
+            .lock()
+            .await
+            .insert((instance_id, disk_id), volume_construction_request);
+        Ok(())
+    }
+
+    /// Returns the volume most recently given for a disk attached to an
+    /// instance, if any
+    pub async fn instance_disk_volume(
+        &self,
+        instance_id: Uuid,
+        disk_id: Uuid,
+    ) -> Option<VolumeConstructionRequest> {
+        self.instance_disk_volumes
+            .lock()
+            .await
+            .get(&(instance_id, disk_id))
+            .cloned()
+    }
+
     /// Issue a snapshot request for a Crucible disk not attached to an
     /// instance.
     pub async fn issue_disk_snapshot_request(
@@ -442,4 +538,126 @@ impl SledAgent {
         )
         .await
     }
+
+    /// Find the simulated regions backing each sub-volume of a volume, in
+    /// order.
+    async fn sub_volume_regions(
+        &self,
+        volume_construction_request: &VolumeConstructionRequest,
+    ) -> Result<Vec<SimSubVolume>, Error> {
+        let sub_volumes = match volume_construction_request {
+            VolumeConstructionRequest::Volume { sub_volumes, .. } => {
+                sub_volumes
+            }
+            _ => {
+                return Err(Error::invalid_request(
+                    "root of volume construction request not a volume",
+                ));
+            }
+        };
+
+        let storage = self.storage.lock().await;
+        let mut result = Vec::with_capacity(sub_volumes.len());
+        for sub_volume in sub_volumes {
+            let mut targets = Vec::new();
+            extract_targets_from_volume_construction_request(
+                &mut targets,
+                sub_volume,
+            );
+
+            let mut size = 0;
+            let mut regions = Vec::with_capacity(targets.len());
+            for target in targets {
+                let crucible_data = storage
+                    .get_dataset_for_port(target.port())
+                    .await
+                    .ok_or_else(|| {
+                        Error::internal_error(&format!(
+                            "no dataset for port {}",
+                            target.port()
+                        ))
+                    })?;
+                let region = crucible_data
+                    .list()
+                    .await
+                    .into_iter()
+                    .find(|region| region.port_number == target.port())
+                    .ok_or_else(|| {
+                        Error::internal_error(&format!(
+                            "no region for port {}",
+                            target.port()
+                        ))
+                    })?;
+                size = region.block_size
+                    * region.extent_size
+                    * region.extent_count;
+                regions.push((
+                    crucible_data,
+                    Uuid::from_str(&region.id.0).unwrap(),
+                ));
+            }
+            result.push(SimSubVolume { size, regions });
+        }
+
+        Ok(result)
+    }
+
+    /// Write `data` at byte `offset` of a disk not attached to an instance,
+    /// storing it in every region of the affected sub-volumes.
+    pub async fn disk_bulk_write(
+        &self,
+        _disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        let sub_volumes =
+            self.sub_volume_regions(&volume_construction_request).await?;
+        let pieces =
+            split_across_sub_volumes(&sub_volumes, offset, data.len() as u64)?;
+
+        let mut data = &data[..];
+        for (i, piece_offset, piece_len) in pieces {
+            let (piece, rest) = data.split_at(piece_len as usize);
+            for (crucible_data, region_id) in &sub_volumes[i].regions {
+                crucible_data
+                    .write_blocks(*region_id, piece_offset, piece)
+                    .await
+                    .map_err(|e| Error::invalid_request(&e.to_string()))?;
+            }
+            data = rest;
+        }
+
+        Ok(())
+    }
+
+    /// Read `size` bytes at byte `offset` of a disk not attached to an
+    /// instance.
+    pub async fn disk_bulk_read(
+        &self,
+        _disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+        offset: u64,
+        size: u64,
+    ) -> Result<Vec<u8>, Error> {
+        let sub_volumes =
+            self.sub_volume_regions(&volume_construction_request).await?;
+        let pieces = split_across_sub_volumes(&sub_volumes, offset, size)?;
+
+        let mut data = Vec::with_capacity(size as usize);
+        for (i, piece_offset, piece_len) in pieces {
+            // Every region in a sub-volume holds the same data.
+            let (crucible_data, region_id) =
+                sub_volumes[i].regions.first().ok_or_else(|| {
+                    Error::internal_error("sub-volume has no regions")
+                })?;
+            let piece = crucible_data
+                .read_blocks(*region_id, piece_offset, piece_len)
+                .await
+                .map_err(|e| Error::invalid_request(&e.to_string()))?;
+            data.extend_from_slice(&piece);
+        }
+
+        Ok(data)
+    }
 }
@@ -19,6 +19,7 @@ use nexus_client::types::{
     ByteCount, DatasetKind, DatasetPutRequest, ZpoolPutRequest,
 };
 use slog::Logger;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
@@ -29,6 +30,9 @@ type CreateCallback = Box<dyn Fn(&CreateRegion) -> State + Send + 'static>;
 
 struct CrucibleDataInner {
     regions: HashMap<Uuid, Region>,
+    // Contents of each region, by block number.  Blocks that were never
+    // written read back as zeroes.
+    blocks: HashMap<Uuid, BTreeMap<u64, Vec<u8>>>,
     snapshots: HashMap<Uuid, Vec<Snapshot>>,
     running_snapshots: HashMap<Uuid, HashMap<String, RunningSnapshot>>,
     on_create: Option<CreateCallback>,
@@ -39,6 +43,7 @@ impl CrucibleDataInner {
     fn new(crucible_port: u16) -> Self {
         Self {
             regions: HashMap::new(),
+            blocks: HashMap::new(),
             snapshots: HashMap::new(),
             running_snapshots: HashMap::new(),
             on_create: None,
@@ -112,12 +117,64 @@ impl CrucibleDataInner {
         let id = Uuid::from_str(&id.0).unwrap();
         if let Some(mut region) = self.regions.get_mut(&id) {
             region.state = State::Destroyed;
+            self.blocks.remove(&id);
             Ok(Some(region.clone()))
         } else {
             Ok(None)
         }
     }
 
+    /// Returns the block size of region `id`, checking that `offset` and
+    /// `len` describe whole blocks within it.
+    fn check_block_range(
+        &self,
+        id: Uuid,
+        offset: u64,
+        len: u64,
+    ) -> Result<u64> {
+        let region = match self.regions.get(&id) {
+            Some(region) if region.state == State::Created => region,
+            _ => bail!("region {} does not exist", id),
+        };
+        let block_size = region.block_size;
+        let region_size = block_size * region.extent_size * region.extent_count;
+        if offset % block_size != 0 || len % block_size != 0 {
+            bail!("I/O must be aligned to the block size ({})", block_size);
+        }
+        if offset + len > region_size {
+            bail!("I/O past the end of region {}", id);
+        }
+        Ok(block_size)
+    }
+
+    fn write_blocks(
+        &mut self,
+        id: Uuid,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        let block_size =
+            self.check_block_range(id, offset, data.len() as u64)?;
+        let blocks = self.blocks.entry(id).or_insert_with(BTreeMap::new);
+        for (i, block) in data.chunks(block_size as usize).enumerate() {
+            blocks.insert(offset / block_size + i as u64, block.to_vec());
+        }
+        Ok(())
+    }
+
+    fn read_blocks(&self, id: Uuid, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let block_size = self.check_block_range(id, offset, len)?;
+        let mut data = vec![0; len as usize];
+        if let Some(blocks) = self.blocks.get(&id) {
+            let first = offset / block_size;
+            for (n, block) in blocks.range(first..first + len / block_size) {
+                let start = ((n - first) * block_size) as usize;
+                data[start..start + block.len()].copy_from_slice(block);
+            }
+        }
+        Ok(data)
+    }
+
     fn create_snapshot(
         &mut self,
         id: Uuid,
@@ -276,6 +333,24 @@ impl CrucibleData {
             .state = state;
     }
 
+    pub async fn write_blocks(
+        &self,
+        id: Uuid,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        self.inner.lock().await.write_blocks(id, offset, data)
+    }
+
+    pub async fn read_blocks(
+        &self,
+        id: Uuid,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        self.inner.lock().await.read_blocks(id, offset, len)
+    }
+
     pub async fn create_snapshot(
         &self,
         id: Uuid,
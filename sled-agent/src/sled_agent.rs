@@ -94,6 +94,9 @@ pub enum Error {
 
     #[error("Error resolving DNS name: {0}")]
     ResolveError(#[from] internal_dns_client::multiclient::ResolveError),
+
+    #[error("Bulk I/O to disk {0} is not supported on this sled")]
+    DiskBulkIoUnsupported(Uuid),
 }
 
 impl From<Error> for omicron_common::api::external::Error {
@@ -139,6 +142,10 @@ impl From<Error> for dropshot::HttpError {
                 }
             }
 
+            e @ crate::sled_agent::Error::DiskBulkIoUnsupported(_) => {
+                HttpError::for_unavail(None, e.to_string())
+            }
+
             e => HttpError::for_internal_error(e.to_string()),
         }
     }
@@ -300,6 +307,7 @@ impl SledAgent {
             *sled_address.ip(),
             request.gateway.mac,
         );
+        instances.start_metrics_producer(id, *sled_address.ip());
 
         let svc_config = services::Config {
             gateway_address: request.gateway.address,
@@ -418,6 +426,23 @@ impl SledAgent {
             .map_err(Error::from)
     }
 
+    /// Replace the volume backing a disk attached to an instance
+    pub async fn instance_disk_volume_put(
+        &self,
+        instance_id: Uuid,
+        disk_id: Uuid,
+        volume_construction_request: VolumeConstructionRequest,
+    ) -> Result<(), Error> {
+        self.instances
+            .instance_disk_volume_put(
+                instance_id,
+                disk_id,
+                volume_construction_request,
+            )
+            .await
+            .map_err(Error::from)
+    }
+
     /// Issue a snapshot request for a Crucible disk not attached to an
     /// instance.
     pub async fn issue_disk_snapshot_request(
@@ -432,6 +457,32 @@ impl SledAgent {
         todo!();
     }
 
+    /// Write blocks to a Crucible disk not attached to an instance.
+    pub async fn disk_bulk_write(
+        &self,
+        disk_id: Uuid,
+        _volume_construction_request: VolumeConstructionRequest,
+        _offset: u64,
+        _data: Vec<u8>,
+    ) -> Result<(), Error> {
+        // As with snapshots of disks not attached to an instance, this
+        // requires constructing the volume in the sled agent, which isn't
+        // implemented yet.
+        Err(Error::DiskBulkIoUnsupported(disk_id))
+    }
+
+    /// Read blocks from a Crucible disk not attached to an instance.
+    pub async fn disk_bulk_read(
+        &self,
+        disk_id: Uuid,
+        _volume_construction_request: VolumeConstructionRequest,
+        _offset: u64,
+        _size: u64,
+    ) -> Result<Vec<u8>, Error> {
+        // See `disk_bulk_write`.
+        Err(Error::DiskBulkIoUnsupported(disk_id))
+    }
+
     pub async fn firewall_rules_ensure(
         &self,
         _vpc_id: Uuid,